@@ -6,14 +6,14 @@ use std::time::Duration;
 
 fn setup_handler() -> (DocxHandler, TempDir) {
     let temp_dir = TempDir::new().unwrap();
-    let handler = DocxHandler::new_with_temp_dir(temp_dir.path()).unwrap();
+    let handler = DocxHandler::new_with_base_dir(temp_dir.path()).unwrap();
     (handler, temp_dir)
 }
 
 fn bench_document_creation(c: &mut Criterion) {
     c.bench_function("create_document", |b| {
         b.iter_batched(
-            || setup_handler(),
+            setup_handler,
             |(mut handler, _temp_dir)| {
                 black_box(handler.create_document().unwrap())
             },
@@ -66,11 +66,12 @@ fn bench_styled_paragraph_addition(c: &mut Criterion) {
                     color: Some("#000000".to_string()),
                     alignment: Some("left".to_string()),
                     line_spacing: Some(1.0),
+                    ..Default::default()
                 };
                 (handler, doc_id, temp_dir, style)
             },
             |(mut handler, doc_id, _temp_dir, style)| {
-                black_box(handler.add_paragraph(&doc_id, "Styled paragraph", Some(style)).unwrap())
+                handler.add_paragraph(&doc_id, "Styled paragraph", Some(style)).unwrap()
             },
             criterion::BatchSize::LargeInput,
         )
@@ -92,7 +93,7 @@ fn bench_heading_addition(c: &mut Criterion) {
                         (handler, doc_id, temp_dir)
                     },
                     |(mut handler, doc_id, _temp_dir)| {
-                        black_box(handler.add_heading(&doc_id, &format!("Heading Level {}", level), level).unwrap())
+                        handler.add_heading(&doc_id, &format!("Heading Level {}", level), level).unwrap()
                     },
                     criterion::BatchSize::LargeInput,
                 )
@@ -128,12 +129,16 @@ fn bench_table_addition(c: &mut Criterion) {
                             rows: table_rows,
                             headers: None,
                             border_style: Some("single".to_string()),
+                            col_widths: None,
+                            merges: None,
+                            cell_shading: None,
+                            ..Default::default()
                         };
                         
                         (handler, doc_id, temp_dir, table_data)
                     },
                     |(mut handler, doc_id, _temp_dir, table_data)| {
-                        black_box(handler.add_table(&doc_id, table_data).unwrap())
+                        handler.add_table(&doc_id, table_data).unwrap()
                     },
                     criterion::BatchSize::LargeInput,
                 )
@@ -163,7 +168,7 @@ fn bench_list_addition(c: &mut Criterion) {
                         (handler, doc_id, temp_dir, items)
                     },
                     |(mut handler, doc_id, _temp_dir, items)| {
-                        black_box(handler.add_list(&doc_id, items, false).unwrap())
+                        handler.add_list(&doc_id, items, false).unwrap()
                     },
                     criterion::BatchSize::LargeInput,
                 )
@@ -238,7 +243,7 @@ fn bench_pdf_conversion(c: &mut Criterion) {
                         (metadata, converter, output_path, temp_dir)
                     },
                     |(metadata, converter, output_path, _temp_dir)| {
-                        black_box(converter.convert_docx_to_pdf(&metadata.path, &output_path).unwrap())
+                        converter.convert_docx_to_pdf(&metadata.path, &output_path).unwrap()
                     },
                     criterion::BatchSize::LargeInput,
                 )
@@ -309,7 +314,7 @@ fn bench_concurrent_operations(c: &mut Criterion) {
                         let handles: Vec<_> = (0..thread_count).map(|i| {
                             let temp_path = Arc::clone(&temp_path);
                             thread::spawn(move || {
-                                let mut handler = DocxHandler::new_with_temp_dir(&temp_path).unwrap();
+                                let mut handler = DocxHandler::new_with_base_dir(temp_path.as_path()).unwrap();
                                 let doc_id = handler.create_document().unwrap();
                                 
                                 for j in 0..10 {
@@ -344,7 +349,7 @@ fn bench_memory_usage(c: &mut Criterion) {
             doc_count,
             |b, &count| {
                 b.iter_batched(
-                    || setup_handler(),
+                    setup_handler,
                     |(mut handler, _temp_dir)| {
                         let mut doc_ids = Vec::new();
                         
@@ -380,7 +385,7 @@ fn bench_memory_usage(c: &mut Criterion) {
 fn bench_complex_document_operations(c: &mut Criterion) {
     c.bench_function("complex_document", |b| {
         b.iter_batched(
-            || setup_handler(),
+            setup_handler,
             |(mut handler, _temp_dir)| {
                 let doc_id = handler.create_document().unwrap();
                 
@@ -407,6 +412,10 @@ fn bench_complex_document_operations(c: &mut Criterion) {
                     ],
                     headers: Some(vec!["Header 1".to_string(), "Header 2".to_string(), "Header 3".to_string()]),
                     border_style: Some("single".to_string()),
+                    col_widths: None,
+                    merges: None,
+                    cell_shading: None,
+                    ..Default::default()
                 };
                 handler.add_table(&doc_id, table_data).unwrap();
                 
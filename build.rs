@@ -29,7 +29,7 @@ fn main() -> Result<()> {
             
             // Create a minimal placeholder TTF file (this won't work for actual rendering)
             // You should download the actual fonts
-            fs::write(&font_path, &[0u8; 100])?;
+            fs::write(&font_path, [0u8; 100])?;
         }
     }
     
@@ -0,0 +1,111 @@
+//! Lightweight text analytics: keyword frequency, regex/heuristic entity extraction and
+//! first-sentence summaries. Everything here is deterministic and dependency-free so it can
+//! run on large documents before an agent decides what to read in full.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any",
+    "are", "as", "at", "be", "because", "been", "before", "being", "below", "between", "both",
+    "but", "by", "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few",
+    "for", "from", "further", "had", "has", "have", "having", "he", "her", "here", "hers",
+    "him", "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself", "just", "may",
+    "me", "more", "most", "must", "my", "no", "nor", "not", "now", "of", "off", "on", "once",
+    "only", "or", "other", "our", "ours", "out", "over", "own", "same", "shall", "she",
+    "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them", "then",
+    "there", "these", "they", "this", "those", "through", "to", "too", "under", "until", "up",
+    "upon", "us", "very", "was", "we", "were", "what", "when", "where", "which", "while", "who",
+    "whom", "why", "will", "with", "within", "without", "would", "you", "your", "yours",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keyword {
+    pub term: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    /// date, money, percentage, email, url, phone or organization
+    pub kind: String,
+    pub text: String,
+    pub count: usize,
+}
+
+static WORD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\p{L}][\p{L}\p{N}'’-]*").unwrap());
+
+static ENTITY_PATTERNS: Lazy<Vec<(&'static str, Regex)>> = Lazy::new(|| {
+    vec![
+        ("date", Regex::new(r"(?x)
+            \b\d{4}-\d{2}-\d{2}\b
+          | \b\d{1,2}/\d{1,2}/\d{2,4}\b
+          | \b(?:Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|Aug(?:ust)?|Sep(?:t(?:ember)?)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\.?\s+\d{1,2}(?:st|nd|rd|th)?,?\s+\d{4}\b
+          | \b\d{1,2}(?:st|nd|rd|th)?\s+(?:January|February|March|April|May|June|July|August|September|October|November|December)\s+\d{4}\b
+        ").unwrap()),
+        ("money", Regex::new(r"(?:[$€£¥]\s?\d[\d,]*(?:\.\d+)?(?:\s?(?:million|billion|thousand|[mMbBkK]))?|\b\d[\d,]*(?:\.\d+)?\s?(?:USD|EUR|GBP|JPY|CNY|HKD|AUD|NZD|dollars|euros|pounds)\b)").unwrap()),
+        ("percentage", Regex::new(r"\b\d+(?:\.\d+)?\s?%").unwrap()),
+        ("email", Regex::new(r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)+\b").unwrap()),
+        ("url", Regex::new(r"\bhttps?://[^\s<>()]+").unwrap()),
+        ("phone", Regex::new(r"\+?\d{1,3}[\s.-]?\(?\d{2,4}\)?[\s.-]\d{3,4}[\s.-]\d{3,4}\b").unwrap()),
+        ("organization", Regex::new(r"\b(?:[A-Z][\w&'-]*[ \t]+){0,4}[A-Z][\w&'-]*,?[ \t]+(?:Inc\.?|Ltd\.?|LLC|LLP|PLC|Corp\.?|Corporation|Company|Co\.|GmbH|AG|S\.A\.|Limited|Group|Holdings|Bank|University|Foundation|Institute|Agency|Association)").unwrap()),
+    ]
+});
+
+/// Most frequent non-stopword terms (case-insensitive), ties broken alphabetically
+pub fn top_keywords(text: &str, limit: usize) -> Vec<Keyword> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for m in WORD_RE.find_iter(text) {
+        let term = m.as_str().trim_end_matches(['\'', '’', '-']).to_lowercase();
+        if term.chars().count() < 3 || STOPWORDS.contains(&term.as_str()) {
+            continue;
+        }
+        *counts.entry(term).or_default() += 1;
+    }
+    let mut keywords: Vec<Keyword> = counts.into_iter().map(|(term, count)| Keyword { term, count }).collect();
+    keywords.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    keywords.truncate(limit);
+    keywords
+}
+
+/// Entities found by pattern, deduplicated per kind with occurrence counts
+pub fn extract_entities(text: &str) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = Vec::new();
+    for (kind, re) in ENTITY_PATTERNS.iter() {
+        for m in re.find_iter(text) {
+            let value = m.as_str().trim().trim_end_matches([',', '.', ';', ')']).to_string();
+            match entities.iter_mut().find(|e| e.kind == *kind && e.text == value) {
+                Some(e) => e.count += 1,
+                None => entities.push(Entity { kind: kind.to_string(), text: value, count: 1 }),
+            }
+        }
+    }
+    entities
+}
+
+/// First sentence of a text block, capped at `max_chars` characters
+pub fn first_sentence(text: &str, max_chars: usize) -> String {
+    let trimmed = text.trim();
+    let end = trimmed
+        .char_indices()
+        .find(|(i, c)| {
+            matches!(c, '.' | '!' | '?' | '。')
+                && trimmed[i + c.len_utf8()..].chars().next().map(|n| n.is_whitespace()).unwrap_or(true)
+        })
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(trimmed.len());
+    let sentence = &trimmed[..end];
+    if sentence.chars().count() > max_chars {
+        let cut: String = sentence.chars().take(max_chars).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        sentence.to_string()
+    }
+}
+
+/// Whitespace word count, matching how get_word_count counts words
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
@@ -0,0 +1,288 @@
+//! Scenario benchmarks for the hidden `bench` subcommand and `run_benchmark` tool, so the
+//! cost of the write path can be measured on the machine that will run the server.
+//!
+//! Each run builds a document from scratch in a throwaway directory (the handler rewrites
+//! the package after every edit, so build time covers the per-edit write path), saves it
+//! once more and reports wall-clock times. Memory figures come from `/proc/self/status`
+//! and are only available on Linux; they describe the whole process, not just the run.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::docx_handler::{DocxHandler, ImageData, PackageOptions, TableData};
+
+/// Upper bounds so a tool call cannot tie the server up for minutes
+const MAX_ELEMENTS: usize = 20_000;
+const MAX_IMAGES: usize = 500;
+const MAX_IMAGE_SIZE: u32 = 4096;
+const MAX_ITERATIONS: usize = 50;
+
+fn default_table_rows() -> usize { 10 }
+fn default_table_cols() -> usize { 4 }
+fn default_image_size() -> u32 { 256 }
+fn default_iterations() -> usize { 3 }
+
+/// What to put in the benchmark document. All counts default to zero; an empty scenario
+/// stands for the standard suite (see [`standard_suite`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Body paragraphs
+    #[serde(default)]
+    pub paragraphs: usize,
+    /// Tables of table_rows x table_cols cells
+    #[serde(default)]
+    pub tables: usize,
+    #[serde(default = "default_table_rows")]
+    pub table_rows: usize,
+    #[serde(default = "default_table_cols")]
+    pub table_cols: usize,
+    /// Square PNG images of image_size pixels
+    #[serde(default)]
+    pub images: usize,
+    #[serde(default = "default_image_size")]
+    pub image_size: u32,
+    /// Times to repeat the run
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            paragraphs: 0,
+            tables: 0,
+            table_rows: default_table_rows(),
+            table_cols: default_table_cols(),
+            images: 0,
+            image_size: default_image_size(),
+            iterations: default_iterations(),
+        }
+    }
+}
+
+impl Scenario {
+    /// N paragraphs of body text
+    pub fn paragraphs(count: usize) -> Self {
+        Self { paragraphs: count, ..Self::default() }
+    }
+
+    /// M tables of `rows` x `cols` cells
+    pub fn tables(count: usize, rows: usize, cols: usize) -> Self {
+        Self { tables: count, table_rows: rows, table_cols: cols, ..Self::default() }
+    }
+
+    /// `count` images of `size` x `size` pixels, one short paragraph before each
+    pub fn image_heavy(count: usize, size: u32) -> Self {
+        Self { images: count, image_size: size, paragraphs: count, ..Self::default() }
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paragraphs == 0 && self.tables == 0 && self.images == 0
+    }
+
+    /// Elements added to the document (each image also counts its paragraph)
+    pub fn elements(&self) -> usize {
+        self.paragraphs + self.tables + self.images
+    }
+
+    /// Short label such as "200p+10t(10x4)+5i(256px)"
+    pub fn name(&self) -> String {
+        let mut parts = Vec::new();
+        if self.paragraphs > 0 { parts.push(format!("{}p", self.paragraphs)); }
+        if self.tables > 0 { parts.push(format!("{}t({}x{})", self.tables, self.table_rows, self.table_cols)); }
+        if self.images > 0 { parts.push(format!("{}i({}px)", self.images, self.image_size)); }
+        if parts.is_empty() { "empty".to_string() } else { parts.join("+") }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.is_empty() {
+            anyhow::bail!("nothing to benchmark: set paragraphs, tables or images");
+        }
+        if self.elements() > MAX_ELEMENTS {
+            anyhow::bail!("at most {} elements per scenario (got {})", MAX_ELEMENTS, self.elements());
+        }
+        if self.tables > 0 && (self.table_rows == 0 || self.table_cols == 0) {
+            anyhow::bail!("table_rows and table_cols must be at least 1");
+        }
+        if self.images > MAX_IMAGES {
+            anyhow::bail!("at most {} images per scenario (got {})", MAX_IMAGES, self.images);
+        }
+        if self.images > 0 && !(1..=MAX_IMAGE_SIZE).contains(&self.image_size) {
+            anyhow::bail!("image_size must be between 1 and {} (got {})", MAX_IMAGE_SIZE, self.image_size);
+        }
+        if !(1..=MAX_ITERATIONS).contains(&self.iterations) {
+            anyhow::bail!("iterations must be between 1 and {} (got {})", MAX_ITERATIONS, self.iterations);
+        }
+        Ok(())
+    }
+}
+
+/// Text-heavy, table-heavy and image-heavy scenarios, run when none is given
+pub fn standard_suite(iterations: usize) -> Vec<Scenario> {
+    vec![
+        Scenario::paragraphs(500).with_iterations(iterations),
+        Scenario::tables(25, 20, 5).with_iterations(iterations),
+        Scenario::image_heavy(20, 512).with_iterations(iterations),
+    ]
+}
+
+/// Timings of one run in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct RunTiming {
+    /// Creating the document and adding every element
+    pub build_ms: f64,
+    /// The final save to an output file
+    pub save_ms: f64,
+    pub total_ms: f64,
+}
+
+/// Resident set size in kilobytes
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryUsage {
+    pub rss_before_kb: u64,
+    pub rss_after_kb: u64,
+    /// High-water mark of the process so far, not only of this scenario
+    pub peak_rss_kb: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub name: String,
+    pub scenario: Scenario,
+    pub elements: usize,
+    pub runs: Vec<RunTiming>,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Mean build time divided by the element count
+    pub ms_per_element: f64,
+    /// Size of the saved document
+    pub output_bytes: u64,
+    pub memory: Option<MemoryUsage>,
+}
+
+/// Run a scenario `iterations` times, each in a fresh scratch directory, zipping the
+/// output with `package`
+pub fn run(scenario: &Scenario, package: PackageOptions) -> Result<BenchReport> {
+    scenario.validate()?;
+    let image = if scenario.images > 0 { Some(test_image(scenario.image_size)?) } else { None };
+    let rss_before = memory_kb("VmRSS");
+    let mut runs = Vec::with_capacity(scenario.iterations);
+    let mut output_bytes = 0;
+    for _ in 0..scenario.iterations {
+        let (timing, bytes) = run_once(scenario, package, image.as_deref())?;
+        runs.push(timing);
+        output_bytes = bytes;
+    }
+    let totals: Vec<f64> = runs.iter().map(|r| r.total_ms).collect();
+    let mean_build = runs.iter().map(|r| r.build_ms).sum::<f64>() / runs.len() as f64;
+    let memory = match (rss_before, memory_kb("VmRSS"), memory_kb("VmHWM")) {
+        (Some(before), Some(after), Some(peak)) => Some(MemoryUsage { rss_before_kb: before, rss_after_kb: after, peak_rss_kb: peak }),
+        _ => None,
+    };
+    Ok(BenchReport {
+        name: scenario.name(),
+        scenario: scenario.clone(),
+        elements: scenario.elements(),
+        min_ms: totals.iter().copied().fold(f64::INFINITY, f64::min),
+        mean_ms: totals.iter().sum::<f64>() / totals.len() as f64,
+        max_ms: totals.iter().copied().fold(0.0, f64::max),
+        ms_per_element: mean_build / scenario.elements() as f64,
+        runs,
+        output_bytes,
+        memory,
+    })
+}
+
+/// Run each scenario in turn, stopping at the first failure
+pub fn run_all(scenarios: &[Scenario], package: PackageOptions) -> Result<Vec<BenchReport>> {
+    scenarios.iter()
+        .map(|s| run(s, package).with_context(|| format!("benchmark {}", s.name())))
+        .collect()
+}
+
+fn run_once(scenario: &Scenario, package: PackageOptions, image: Option<&[u8]>) -> Result<(RunTiming, u64)> {
+    let scratch = tempfile::TempDir::new().context("create scratch directory")?;
+    let mut handler = DocxHandler::new_with_base_dir(scratch.path())?;
+    handler.set_package_options(package)?;
+
+    let start = Instant::now();
+    let doc_id = handler.create_document()?;
+    let plain = scenario.paragraphs.saturating_sub(scenario.images);
+    for i in 0..plain {
+        handler.add_paragraph(&doc_id, &sample_paragraph(i), None)?;
+    }
+    for t in 0..scenario.tables {
+        handler.add_table(&doc_id, sample_table(t, scenario.table_rows, scenario.table_cols))?;
+    }
+    if let Some(image) = image {
+        for i in 0..scenario.images {
+            if plain + i < scenario.paragraphs {
+                handler.add_paragraph(&doc_id, &format!("Figure {}", i + 1), None)?;
+            }
+            handler.add_image(&doc_id, ImageData {
+                data: image.to_vec(),
+                width: Some(scenario.image_size.min(600)),
+                height: Some(scenario.image_size.min(600)),
+                alt_text: Some(format!("Figure {}", i + 1)),
+            })?;
+        }
+    }
+    let build = start.elapsed();
+
+    let output = scratch.path().join("bench.docx");
+    let save_start = Instant::now();
+    handler.save_document(&doc_id, &output)?;
+    let save = save_start.elapsed();
+    let bytes = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    Ok((RunTiming { build_ms: ms(build), save_ms: ms(save), total_ms: ms(build + save) }, bytes))
+}
+
+fn sample_paragraph(i: usize) -> String {
+    format!(
+        "Paragraph {}. The quick brown fox jumps over the lazy dog while the benchmark measures \
+         how long each edit takes to reach the saved package on disk.",
+        i + 1
+    )
+}
+
+fn sample_table(index: usize, rows: usize, cols: usize) -> TableData {
+    TableData {
+        rows: (0..rows).map(|r| (0..cols).map(|c| format!("T{} R{} C{}", index + 1, r + 1, c + 1)).collect()).collect(),
+        headers: Some((0..cols).map(|c| format!("Column {}", c + 1)).collect()),
+        border_style: Some("single".into()),
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }
+}
+
+/// A patterned PNG that does not compress to nothing, so image size shows in the timings
+fn test_image(size: u32) -> Result<Vec<u8>> {
+    let img = ::image::RgbImage::from_fn(size, size, |x, y| {
+        ::image::Rgb([((x * 7) ^ (y * 13)) as u8, (x.wrapping_mul(y) >> 3) as u8, (x + y) as u8])
+    });
+    let mut buf = Vec::new();
+    ::image::DynamicImage::ImageRgb8(img)
+        .write_to(&mut std::io::Cursor::new(&mut buf), ::image::ImageFormat::Png)
+        .context("encode benchmark image")?;
+    Ok(buf)
+}
+
+/// A `kB` field of /proc/self/status (VmRSS, VmHWM); None off Linux
+fn memory_kb(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status.lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse().ok())
+}
@@ -0,0 +1,174 @@
+//! Right-to-left text support: direction detection, the `w:bidi`/`w:rtl` markup pass for
+//! generated documents and a simplified visual reordering for the PDF renderer.
+//!
+//! Reordering follows the shape of the Unicode bidi algorithm without its full rules:
+//! runs of left-to-right letters and digits keep their order, everything else in an RTL
+//! line is reversed and brackets are mirrored. That covers Hebrew and Arabic prose
+//! with embedded numbers and Latin words; nested embeddings are not modelled.
+
+/// Right-to-left mark: a leading RLM forces a paragraph right-to-left
+pub const RLM: char = '\u{200F}';
+/// Left-to-right mark: a leading LRM keeps a paragraph left-to-right
+pub const LRM: char = '\u{200E}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+impl std::str::FromStr for Direction {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ltr" => Ok(Direction::Ltr),
+            "rtl" => Ok(Direction::Rtl),
+            other => anyhow::bail!("unknown direction '{}' (use ltr or rtl)", other),
+        }
+    }
+}
+
+/// Hebrew, Arabic, Syriac, Thaana, NKo and their presentation forms. Arabic-Indic
+/// digits are excluded: numbers keep their left-to-right order.
+pub fn is_rtl_char(c: char) -> bool {
+    let code = c as u32;
+    let rtl_block = matches!(code,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF);
+    (rtl_block && !matches!(code, 0x0660..=0x0669 | 0x06F0..=0x06F9)) || c == RLM
+}
+
+fn is_strong_ltr(c: char) -> bool {
+    c == LRM || (c.is_alphanumeric() && !is_rtl_char(c))
+}
+
+/// Direction of the first strong character, if any
+pub fn base_direction(text: &str) -> Option<Direction> {
+    text.chars().find_map(|c| {
+        if is_rtl_char(c) {
+            Some(Direction::Rtl)
+        } else if c.is_alphabetic() || c == LRM {
+            Some(Direction::Ltr)
+        } else {
+            None
+        }
+    })
+}
+
+/// Every document is checked on each repack, so ASCII text skips the per-character scan
+pub fn contains_rtl(text: &str) -> bool {
+    !text.is_ascii() && text.chars().any(is_rtl_char)
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        other => other,
+    }
+}
+
+/// Reorder a line from logical to visual (left-to-right drawing) order. Direction marks
+/// are dropped since fonts have no glyphs for them.
+pub fn visual_order(line: &str, base: Direction) -> String {
+    let chars: Vec<char> = line.chars().filter(|c| *c != RLM && *c != LRM).collect();
+    // Segments of (left-to-right, text). Neutrals between two LTR characters join the
+    // LTR run; other neutrals take the base direction.
+    let mut segments: Vec<(bool, Vec<char>)> = Vec::new();
+    for (i, &c) in chars.iter().enumerate() {
+        let ltr = if is_strong_ltr(c) {
+            true
+        } else if is_rtl_char(c) {
+            false
+        } else {
+            let prev_ltr = segments.last().map(|(l, _)| *l).unwrap_or(base == Direction::Ltr);
+            let next_ltr = chars[i + 1..].iter().find(|c| is_strong_ltr(**c) || is_rtl_char(**c))
+                .map(|c| is_strong_ltr(*c))
+                .unwrap_or(base == Direction::Ltr);
+            if prev_ltr == next_ltr { prev_ltr } else { base == Direction::Ltr }
+        };
+        match segments.last_mut() {
+            Some((l, run)) if *l == ltr => run.push(c),
+            _ => segments.push((ltr, vec![c])),
+        }
+    }
+    let render = |(ltr, run): &(bool, Vec<char>)| -> String {
+        if *ltr { run.iter().collect() } else { run.iter().rev().map(|c| mirror(*c)).collect() }
+    };
+    match base {
+        Direction::Ltr => segments.iter().map(render).collect(),
+        Direction::Rtl => segments.iter().rev().map(render).collect(),
+    }
+}
+
+/// Add `w:bidi` to paragraphs whose direction is right-to-left (a leading RLM, or an RTL
+/// first strong character without a leading LRM) and `w:rtl` to their runs containing
+/// RTL text. Runs with RTL text in left-to-right paragraphs also get `w:rtl`.
+pub fn mark_rtl_paragraphs(xml: &str) -> String {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    static PARAGRAPH: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>").unwrap());
+    static RUN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)(<w:r(?:\s[^>]*)?>)(.*?)</w:r>").unwrap());
+    static TEXT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<w:t(?:\s[^>]*)?>(.*?)</w:t>").unwrap());
+    let text_of = |fragment: &str| -> String {
+        TEXT.captures_iter(fragment).map(|c| c[1].to_string()).collect()
+    };
+
+    PARAGRAPH.replace_all(xml, |caps: &regex::Captures| {
+        let para = &caps[0];
+        let text = text_of(para);
+        if !contains_rtl(&text) {
+            return para.to_string();
+        }
+        let rtl = base_direction(&text) == Some(Direction::Rtl);
+        let mut out = RUN.replace_all(para, |run: &regex::Captures| {
+            let (open, inner) = (&run[1], &run[2]);
+            if !contains_rtl(&text_of(inner)) || inner.contains("<w:rtl/>") {
+                return run[0].to_string();
+            }
+            format!("{}{}</w:r>", open, insert_property(inner, "rPr", "<w:rtl/>", RTL_FOLLOWERS))
+        }).into_owned();
+        if rtl && !out.contains("<w:bidi/>") {
+            let open_end = out.find('>').map(|i| i + 1).unwrap_or(0);
+            let (head, body) = out.split_at(open_end);
+            out = format!("{}{}", head, insert_property(body, "pPr", "<w:bidi/>", BIDI_FOLLOWERS));
+        }
+        out
+    }).into_owned()
+}
+
+/// pPr children that come after `w:bidi` in the schema order
+const BIDI_FOLLOWERS: &[&str] = &[
+    "<w:adjustRightInd", "<w:snapToGrid", "<w:spacing", "<w:ind", "<w:contextualSpacing",
+    "<w:mirrorIndents", "<w:suppressOverlap", "<w:jc", "<w:textDirection", "<w:textAlignment",
+    "<w:textboxTightWrap", "<w:outlineLvl", "<w:divId", "<w:cnfStyle", "<w:rPr", "<w:sectPr", "<w:pPrChange",
+];
+/// rPr children that come after `w:rtl` in the schema order
+const RTL_FOLLOWERS: &[&str] = &["<w:cs", "<w:em ", "<w:lang", "<w:eastAsianLayout", "<w:specVanish", "<w:oMath", "<w:rPrChange"];
+
+/// Insert `element` into the leading `<w:{props}>` of `content` before the first of
+/// `followers`, creating the properties element when there is none
+pub(crate) fn insert_property(content: &str, props: &str, element: &str, followers: &[&str]) -> String {
+    let open = format!("<w:{}>", props);
+    let close = format!("</w:{}>", props);
+    let (empty, spaced_empty) = (format!("<w:{}/>", props), format!("<w:{} />", props));
+    let trimmed = content.trim_start();
+    let lead = &content[..content.len() - trimmed.len()];
+    if let Some(rest) = trimmed.strip_prefix(&empty).or_else(|| trimmed.strip_prefix(&spaced_empty)) {
+        return format!("{}{}{}{}{}", lead, open, element, close, rest);
+    }
+    let Some(rest) = trimmed.strip_prefix(&open) else {
+        return format!("{}{}{}{}{}", lead, open, element, close, trimmed);
+    };
+    let end = rest.find(&close).unwrap_or(rest.len());
+    // Only look for followers at the top level of the properties element
+    let at = followers.iter().filter_map(|f| rest[..end].find(f)).min().unwrap_or(end);
+    format!("{}{}{}{}{}", lead, open, &rest[..at], element, &rest[at..])
+}
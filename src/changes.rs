@@ -0,0 +1,237 @@
+//! Change logs for `summarize_changes`: a document compared with a snapshot of itself
+//! (`snapshot_document`) section by section, in terms a reader would use in an email or
+//! PR description rather than as an element diff.
+//!
+//! Sections are matched by heading text (repeated headings in order of appearance);
+//! content before the first heading is the preamble. A renamed heading therefore shows
+//! up as one section removed and one added. Tables are compared by position within their
+//! section.
+
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+
+use crate::docx_handler::DocxOp;
+
+/// Words and tables under one heading
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Section {
+    /// None for the preamble
+    pub heading: Option<String>,
+    pub words: usize,
+    /// Hash of the section's text outside tables, to catch edits that keep the word count
+    text_hash: u64,
+    pub tables: Vec<TableShape>,
+}
+
+impl Section {
+    fn new(heading: Option<String>) -> Self {
+        Self { heading, words: 0, text_hash: 0, tables: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TableShape {
+    pub rows: usize,
+    pub columns: usize,
+    cells: Vec<Vec<String>>,
+}
+
+/// Split ops into sections at every heading
+pub(crate) fn sections(ops: &[DocxOp]) -> Vec<Section> {
+    let mut sections = vec![Section::new(None)];
+    let mut hasher = DefaultHasher::new();
+    for op in ops {
+        let section = sections.last_mut().unwrap();
+        let mut add_text = |text: &str| {
+            section.words += text.split_whitespace().count();
+            text.hash(&mut hasher);
+        };
+        match op {
+            DocxOp::Heading { text, .. } => {
+                section.text_hash = std::mem::replace(&mut hasher, DefaultHasher::new()).finish();
+                sections.push(Section::new(Some(text.trim().to_string())));
+            }
+            DocxOp::Paragraph { text, .. } | DocxOp::ListItem { text, .. } | DocxOp::Hyperlink { text, .. } => add_text(text),
+            DocxOp::List { items, .. } => items.iter().for_each(|item| add_text(item)),
+            DocxOp::Table { data } => {
+                section.words += data.rows.iter().flatten().map(|c| c.split_whitespace().count()).sum::<usize>();
+                section.tables.push(TableShape {
+                    rows: data.rows.len(),
+                    columns: data.rows.iter().map(|r| r.len()).max().unwrap_or(0),
+                    cells: data.rows.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+    sections.last_mut().unwrap().text_hash = hasher.finish();
+    // An empty preamble is not a section anyone wrote
+    if sections[0].words == 0 && sections[0].tables.is_empty() {
+        sections.remove(0);
+    }
+    sections
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionSummary {
+    pub heading: Option<String>,
+    pub words: usize,
+    pub tables: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableChange {
+    /// 1-based position of the table in its section
+    pub table: usize,
+    pub rows_before: usize,
+    pub columns_before: usize,
+    pub rows_after: usize,
+    pub columns_after: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionChange {
+    pub heading: Option<String>,
+    pub words_before: usize,
+    pub words_after: usize,
+    pub word_delta: i64,
+    /// Text outside tables changed, even if the word count did not
+    pub text_edited: bool,
+    pub tables_added: usize,
+    pub tables_removed: usize,
+    pub tables_modified: Vec<TableChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeLog {
+    pub words_before: usize,
+    pub words_after: usize,
+    pub sections_added: Vec<SectionSummary>,
+    pub sections_removed: Vec<SectionSummary>,
+    pub sections_changed: Vec<SectionChange>,
+}
+
+impl ChangeLog {
+    pub fn is_empty(&self) -> bool {
+        self.sections_added.is_empty() && self.sections_removed.is_empty() && self.sections_changed.is_empty()
+    }
+
+    /// Bulleted plain-text log; `since` names the baseline in the first line
+    pub fn to_text(&self, since: &str) -> String {
+        let mut out = format!("Changes since {}:\n", since);
+        if self.is_empty() {
+            out.push_str("- No changes\n");
+            return out;
+        }
+        for section in &self.sections_added {
+            let _ = writeln!(out, "- Added section {} ({})", name(&section.heading), size(section.words, section.tables));
+        }
+        for section in &self.sections_removed {
+            let _ = writeln!(out, "- Removed section {} ({})", name(&section.heading), size(section.words, section.tables));
+        }
+        for change in &self.sections_changed {
+            let mut parts = Vec::new();
+            if change.word_delta != 0 {
+                parts.push(format!("{:+} words ({} → {})", change.word_delta, change.words_before, change.words_after));
+            } else if change.text_edited {
+                parts.push("text edited".to_string());
+            }
+            if change.tables_added > 0 {
+                parts.push(format!("{} added", plural(change.tables_added, "table")));
+            }
+            if change.tables_removed > 0 {
+                parts.push(format!("{} removed", plural(change.tables_removed, "table")));
+            }
+            for table in &change.tables_modified {
+                let shape = if (table.rows_before, table.columns_before) == (table.rows_after, table.columns_after) {
+                    "cells edited".to_string()
+                } else {
+                    format!("{}×{} → {}×{}", table.rows_before, table.columns_before, table.rows_after, table.columns_after)
+                };
+                parts.push(format!("table {} modified ({})", table.table, shape));
+            }
+            let _ = writeln!(out, "- {}: {}", name(&change.heading), parts.join(", "));
+        }
+        let delta = self.words_after as i64 - self.words_before as i64;
+        let _ = writeln!(out, "- Words: {} → {} ({:+})", self.words_before, self.words_after, delta);
+        out
+    }
+}
+
+fn name(heading: &Option<String>) -> String {
+    match heading {
+        Some(heading) => format!("\"{}\"", heading),
+        None => "Preamble".to_string(),
+    }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    format!("{} {}{}", n, word, if n == 1 { "" } else { "s" })
+}
+
+fn size(words: usize, tables: usize) -> String {
+    let mut size = plural(words, "word");
+    if tables > 0 {
+        size.push_str(&format!(", {}", plural(tables, "table")));
+    }
+    size
+}
+
+/// Section-level differences from `before` to `after`
+pub(crate) fn compare(before: &[Section], after: &[Section]) -> ChangeLog {
+    // Key each section by heading and how many times that heading came before it
+    fn keyed(sections: &[Section]) -> Vec<((Option<&str>, usize), &Section)> {
+        let mut seen: HashMap<Option<&str>, usize> = HashMap::new();
+        sections.iter().map(|s| {
+            let n = seen.entry(s.heading.as_deref()).or_default();
+            *n += 1;
+            ((s.heading.as_deref(), *n), s)
+        }).collect()
+    }
+    let summary = |s: &Section| SectionSummary { heading: s.heading.clone(), words: s.words, tables: s.tables.len() };
+    let (before, after) = (keyed(before), keyed(after));
+    let old: HashMap<_, _> = before.iter().cloned().collect();
+    let new: HashMap<_, _> = after.iter().cloned().collect();
+
+    let mut log = ChangeLog {
+        words_before: before.iter().map(|(_, s)| s.words).sum(),
+        words_after: after.iter().map(|(_, s)| s.words).sum(),
+        sections_added: Vec::new(),
+        sections_removed: before.iter().filter(|(k, _)| !new.contains_key(k)).map(|(_, s)| summary(s)).collect(),
+        sections_changed: Vec::new(),
+    };
+    for (key, section) in &after {
+        let Some(previous) = old.get(key) else {
+            log.sections_added.push(summary(section));
+            continue;
+        };
+        let shared = previous.tables.len().min(section.tables.len());
+        let tables_modified: Vec<TableChange> = (0..shared)
+            .filter(|&i| previous.tables[i] != section.tables[i])
+            .map(|i| TableChange {
+                table: i + 1,
+                rows_before: previous.tables[i].rows,
+                columns_before: previous.tables[i].columns,
+                rows_after: section.tables[i].rows,
+                columns_after: section.tables[i].columns,
+            })
+            .collect();
+        let change = SectionChange {
+            heading: section.heading.clone(),
+            words_before: previous.words,
+            words_after: section.words,
+            word_delta: section.words as i64 - previous.words as i64,
+            text_edited: section.text_hash != previous.text_hash,
+            tables_added: section.tables.len() - shared,
+            tables_removed: previous.tables.len() - shared,
+            tables_modified,
+        };
+        if change.text_edited || change.tables_added + change.tables_removed > 0 || !change.tables_modified.is_empty() {
+            log.sections_changed.push(change);
+        }
+    }
+    log
+}
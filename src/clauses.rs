@@ -0,0 +1,81 @@
+//! Clause library for `insert_clause`: reusable .docx and .md fragments in the directory
+//! given with `--clause-dir`. A clause is named by its file stem, so
+//! `limitation-of-liability.docx` is inserted as `limitation-of-liability`; when both
+//! formats exist under one name the .docx wins.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClauseInfo {
+    pub name: String,
+    /// "docx" or "md"
+    pub format: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClauseLibrary {
+    dir: PathBuf,
+}
+
+impl ClauseLibrary {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Clauses in the library, sorted by name. Files are read on every call, so edits to
+    /// the directory show up without a restart.
+    pub fn list(&self) -> Result<Vec<ClauseInfo>> {
+        Ok(self.scan()?.into_iter().map(|(info, _)| info).collect())
+    }
+
+    /// Path of the fragment for a clause name, taken from the same scan as `list` so any
+    /// clause it shows (e.g. from `Limits.DOCX`) can be inserted
+    pub fn resolve(&self, name: &str) -> Result<PathBuf> {
+        let name = name.trim();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            anyhow::bail!("Invalid clause name '{}'", name);
+        }
+        self.scan()?.into_iter()
+            .find(|(info, _)| info.name == name)
+            .map(|(_, path)| path)
+            .ok_or_else(|| anyhow::anyhow!("No clause named '{}' in {:?} (see list_clauses)", name, self.dir))
+    }
+
+    fn scan(&self) -> Result<Vec<(ClauseInfo, PathBuf)>> {
+        let entries = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read clause directory {:?}", self.dir))?;
+        let mut clauses: Vec<(ClauseInfo, PathBuf)> = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let (Some(name), Some(format)) = (stem(&path), clause_format(&path)) else { continue };
+            let Ok(metadata) = std::fs::metadata(&path) else { continue };
+            if !metadata.is_file() {
+                continue;
+            }
+            let info = ClauseInfo { name, format, size_bytes: metadata.len() };
+            match clauses.iter_mut().find(|(c, _)| c.name == info.name) {
+                Some(existing) if info.format == "docx" => *existing = (info, path),
+                Some(_) => {}
+                None => clauses.push((info, path)),
+            }
+        }
+        clauses.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+        Ok(clauses)
+    }
+}
+
+fn stem(path: &Path) -> Option<String> {
+    path.file_stem().and_then(|s| s.to_str()).filter(|s| !s.starts_with('.')).map(String::from)
+}
+
+fn clause_format(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    matches!(ext.as_str(), "docx" | "md").then_some(ext)
+}
@@ -0,0 +1,291 @@
+//! Client integration helpers behind `docx-mcp install` and `docx-mcp doctor`.
+//!
+//! `install` writes an `mcpServers` entry into an MCP client's JSON config.
+//! `doctor` spawns the server over stdio and runs the MCP handshake, reporting what
+//! went wrong if the client would see "Failed to connect".
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// MCP clients with a known config location
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientKind {
+    ClaudeCode,
+    ClaudeDesktop,
+    Cursor,
+}
+
+impl ClientKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClientKind::ClaudeCode => "claude-code",
+            ClientKind::ClaudeDesktop => "claude-desktop",
+            ClientKind::Cursor => "cursor",
+        }
+    }
+}
+
+fn home_dir() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine home directory; pass --config"))
+}
+
+/// Default config file for a client on this platform
+pub fn default_config_path(client: ClientKind) -> Result<PathBuf> {
+    let home = home_dir()?;
+    let path = match client {
+        ClientKind::ClaudeCode => home.join(".claude.json"),
+        ClientKind::ClaudeDesktop => {
+            if cfg!(target_os = "macos") {
+                home.join("Library/Application Support/Claude/claude_desktop_config.json")
+            } else if cfg!(windows) {
+                let appdata = std::env::var_os("APPDATA")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| home.join("AppData").join("Roaming"));
+                appdata.join("Claude").join("claude_desktop_config.json")
+            } else {
+                home.join(".config/Claude/claude_desktop_config.json")
+            }
+        }
+        ClientKind::Cursor => home.join(".cursor/mcp.json"),
+    };
+    Ok(path)
+}
+
+/// Parse `KEY=VALUE` pairs given with `--env`
+pub fn parse_env_pairs(pairs: &[String]) -> Result<serde_json::Map<String, Value>> {
+    let mut env = serde_json::Map::new();
+    for pair in pairs {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid --env '{}', expected KEY=VALUE", pair))?;
+        env.insert(key.trim().to_string(), Value::String(value.to_string()));
+    }
+    Ok(env)
+}
+
+/// The `mcpServers` entry for this binary. Clients start servers without a shell,
+/// so the command must be an absolute path.
+pub fn server_entry(client: ClientKind, command: &Path, args: &[String], env: serde_json::Map<String, Value>) -> Value {
+    let mut entry = json!({
+        "command": command.to_string_lossy(),
+        "args": args,
+        "env": env,
+    });
+    if client == ClientKind::ClaudeCode {
+        entry["type"] = json!("stdio");
+    }
+    entry
+}
+
+/// Insert or replace `name` under `mcpServers` in the config at `path`, keeping every
+/// other key. The previous file is kept as `<file>.bak`. Returns the resulting config.
+pub fn install(path: &Path, name: &str, entry: Value, dry_run: bool) -> Result<Value> {
+    let mut config = if path.exists() {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        if raw.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(&raw).with_context(|| format!("{} is not valid JSON", path.display()))?
+        }
+    } else {
+        json!({})
+    };
+    let root = config
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{} must contain a JSON object", path.display()))?;
+    let servers = root.entry("mcpServers").or_insert_with(|| json!({}));
+    servers
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("mcpServers in {} is not an object", path.display()))?
+        .insert(name.to_string(), entry);
+
+    if !dry_run {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        }
+        if path.exists() {
+            let backup = path.with_extension(format!(
+                "{}.bak",
+                path.extension().and_then(|e| e.to_str()).unwrap_or("json")
+            ));
+            std::fs::copy(path, &backup).with_context(|| format!("backup to {}", backup.display()))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&config)? + "\n")
+            .with_context(|| format!("write {}", path.display()))?;
+    }
+    Ok(config)
+}
+
+/// One doctor check result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &str, ok: bool, detail: impl Into<String>) -> Self {
+        Self { name: name.to_string(), ok, detail: detail.into() }
+    }
+}
+
+/// Spawn `command` as an MCP stdio server and run `initialize` + `tools/list`.
+/// Any non-JSON output on stdout is reported, since clients treat it as a broken stream.
+pub fn run_handshake(command: &Path, args: &[String], timeout: Duration) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if !command.exists() {
+        checks.push(Check::new("binary", false, format!("{} does not exist", command.display())));
+        return checks;
+    }
+    checks.push(Check::new("binary", true, command.display().to_string()));
+
+    let mut child = match Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            checks.push(Check::new("spawn", false, e.to_string()));
+            return checks;
+        }
+    };
+    checks.push(Check::new("spawn", true, format!("pid {}", child.id())));
+
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let stdout = child.stdout.take().expect("piped stdout");
+    let mut stderr = child.stderr.take().expect("piped stderr");
+    let (tx, rx) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let requests = [
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "docx-mcp-doctor", "version": env!("CARGO_PKG_VERSION")}
+        }}),
+        json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+        json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list", "params": {}}),
+    ];
+    let mut write_failed = None;
+    for request in &requests {
+        if let Err(e) = writeln!(stdin, "{}", request).and_then(|_| stdin.flush()) {
+            write_failed = Some(e.to_string());
+            break;
+        }
+    }
+
+    let mut stray_lines = Vec::new();
+    let mut initialize = None;
+    let mut tools = None;
+    let deadline = std::time::Instant::now() + timeout;
+    while initialize.is_none() || tools.is_none() {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        let Ok(line) = rx.recv_timeout(remaining) else { break };
+        match serde_json::from_str::<Value>(&line) {
+            Ok(msg) if msg["id"] == 1 => initialize = Some(msg),
+            Ok(msg) if msg["id"] == 2 => tools = Some(msg),
+            Ok(_) => {}
+            Err(_) => stray_lines.push(line),
+        }
+    }
+
+    drop(stdin);
+    let _ = child.kill();
+    let _ = child.wait();
+    let mut stderr_text = String::new();
+    let _ = stderr.read_to_string(&mut stderr_text);
+    let stderr_tail: String = stderr_text.lines().rev().take(5).collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
+
+    if let Some(e) = write_failed {
+        checks.push(Check::new("stdin", false, format!("server closed stdin early: {}", e)));
+    }
+    checks.push(Check::new(
+        "stdout_clean",
+        stray_lines.is_empty(),
+        if stray_lines.is_empty() {
+            "only JSON-RPC on stdout".to_string()
+        } else {
+            format!("non-JSON output on stdout (clients reject this): {}", stray_lines[0])
+        },
+    ));
+    match initialize {
+        Some(msg) if msg.get("result").is_some() => {
+            let server = msg["result"]["serverInfo"]["name"].as_str().unwrap_or("unknown").to_string();
+            checks.push(Check::new("initialize", true, format!("server {}", server)));
+        }
+        Some(msg) => checks.push(Check::new("initialize", false, format!("error response: {}", msg["error"]))),
+        None => {
+            let mut detail = format!("no initialize response within {}s", timeout.as_secs());
+            if stderr_text.contains("Runtime server disabled") {
+                detail = "binary was built without the runtime-server feature".to_string();
+            } else if !stderr_tail.is_empty() {
+                detail.push_str(&format!("; stderr: {}", stderr_tail));
+            }
+            checks.push(Check::new("initialize", false, detail));
+        }
+    }
+    if let Some(msg) = tools {
+        let count = msg["result"]["tools"].as_array().map(|t| t.len()).unwrap_or(0);
+        checks.push(Check::new("tools_list", count > 0, format!("{} tools advertised", count)));
+    } else if checks.iter().all(|c| c.ok) {
+        checks.push(Check::new("tools_list", false, "no tools/list response"));
+    }
+    checks
+}
+
+/// Report on each client's config: whether a server entry exists and whether its
+/// command is an absolute path that exists
+pub fn inspect_client_configs(name: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for client in [ClientKind::ClaudeCode, ClientKind::ClaudeDesktop, ClientKind::Cursor] {
+        let Ok(path) = default_config_path(client) else { continue };
+        let label = format!("config:{}", client.label());
+        let Ok(raw) = std::fs::read_to_string(&path) else {
+            checks.push(Check::new(&label, true, format!("{} not present", path.display())));
+            continue;
+        };
+        let config: Value = match serde_json::from_str(&raw) {
+            Ok(v) => v,
+            Err(e) => {
+                checks.push(Check::new(&label, false, format!("{} is not valid JSON: {}", path.display(), e)));
+                continue;
+            }
+        };
+        match config["mcpServers"][name]["command"].as_str() {
+            None => checks.push(Check::new(&label, true, format!("no '{}' entry in {}", name, path.display()))),
+            Some(cmd) => {
+                let cmd_path = Path::new(cmd);
+                let ok = cmd_path.is_absolute() && cmd_path.exists();
+                let detail = if !cmd_path.is_absolute() {
+                    format!("command '{}' is not an absolute path; clients do not search PATH reliably", cmd)
+                } else if !cmd_path.exists() {
+                    format!("command '{}' does not exist", cmd)
+                } else {
+                    format!("'{}' -> {}", name, cmd)
+                };
+                checks.push(Check::new(&label, ok, detail));
+            }
+        }
+    }
+    checks
+}
@@ -0,0 +1,290 @@
+//! Server settings from a TOML file given with `--config` (or `DOCX_MCP_CONFIG`).
+//!
+//! Every key mirrors a command line flag. The command line wins over the environment,
+//! which wins over the file, which wins over the built-in defaults; a setting from the
+//! file is only used when neither of the others gave it. Relative paths are resolved
+//! against the directory of the config file.
+//!
+//! ```toml
+//! [server]
+//! http = "127.0.0.1:8080"              # serve over HTTP instead of stdio
+//! admin_key = "change-me"              # bearer token for operator access over HTTP
+//! session_idle_timeout = 1800          # seconds before an idle HTTP session ends
+//! max_sessions_per_token = 16
+//! metrics_public = false               # serve /metrics without the admin key
+//!
+//! [[server.tokens]]                    # HTTP clients must present one of these
+//! name = "analyst"
+//! token = "a-long-random-analyst-token"
+//! profile = "readonly"
+//!
+//! [[server.tokens]]
+//! name = "author"
+//! token = "a-long-random-author-token"
+//! blacklist = ["merge_documents"]
+//!
+//! [security]
+//! profile = "sandbox"                  # default, readonly, sandbox or readonly-sandbox
+//! whitelist = ["open_document", "extract_text", "convert_to_pdf"]
+//! blacklist = ["merge_documents"]
+//! allowed_paths = ["/srv/documents"]
+//! external_tools = false
+//! network = false
+//!
+//! [limits]
+//! max_document_size = 52428800         # bytes
+//! max_open_documents = 20
+//! max_document_memory = 16777216       # bytes of edit history per document
+//! storage_threshold_mb = 500
+//!
+//! [storage]
+//! temp_dir = "/var/lib/docx-mcp"
+//! compression_level = 9
+//! deterministic = true
+//!
+//! [conversion]
+//! prefer_external_tools = true         # try LibreOffice/unoconv before the built-in renderer
+//! fallback_fonts = ["han=/fonts/SourceHanSans.otf"]
+//!
+//! [content]
+//! clause_dir = "clauses"
+//! letterheads = "letterheads.toml"
+//!
+//! [webhooks]
+//! urls = ["https://hooks.example.com/docx"]
+//! events = ["document_saved"]
+//! ```
+
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::security::{Args, SecurityConfig};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub server: ServerSection,
+    #[serde(default)]
+    pub security: SecuritySection,
+    #[serde(default)]
+    pub limits: LimitsSection,
+    #[serde(default)]
+    pub storage: StorageSection,
+    #[serde(default)]
+    pub conversion: ConversionSection,
+    #[serde(default)]
+    pub content: ContentSection,
+    #[serde(default)]
+    pub webhooks: WebhooksSection,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerSection {
+    /// Address to serve MCP over HTTP on
+    pub http: Option<String>,
+    /// Bearer token for operator access over HTTP
+    pub admin_key: Option<String>,
+    /// Seconds an HTTP session may stay idle before it ends; 0 for never
+    pub session_idle_timeout: Option<u64>,
+    /// Most HTTP sessions one token may have open at once; 0 for no limit
+    pub max_sessions_per_token: Option<usize>,
+    /// Serve /metrics without the admin key
+    pub metrics_public: Option<bool>,
+    /// Bearer tokens HTTP clients authenticate with, each with its own profile
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+}
+
+/// A bearer token for the HTTP transport. Its profile and command lists narrow what
+/// the server's own settings allow; they never widen them.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiToken {
+    /// Shown in logs instead of the token
+    pub name: String,
+    pub token: String,
+    /// default, readonly, sandbox or readonly-sandbox
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub whitelist: Option<Vec<String>>,
+    pub blacklist: Option<Vec<String>>,
+    /// Operator access: every session's documents and the operator tools
+    #[serde(default)]
+    pub admin: bool,
+}
+
+impl std::fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiToken")
+            .field("name", &self.name)
+            .field("profile", &self.profile)
+            .field("whitelist", &self.whitelist)
+            .field("blacklist", &self.blacklist)
+            .field("admin", &self.admin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ApiToken {
+    /// The extra checks calls made with this token go through
+    pub fn restrictions(&self) -> SecurityConfig {
+        let (readonly, sandbox) = self.profile.as_deref()
+            .and_then(|p| profile_flags(p).ok())
+            .unwrap_or((false, false));
+        SecurityConfig {
+            readonly_mode: readonly,
+            sandbox_mode: sandbox,
+            command_whitelist: self.whitelist.as_ref().map(|w| w.iter().cloned().collect()),
+            command_blacklist: self.blacklist.as_ref().map(|b| b.iter().cloned().collect()),
+            // Limits and the rest stay with the server settings
+            max_document_size: usize::MAX,
+            max_open_documents: usize::MAX,
+            allow_external_tools: !sandbox,
+            allow_network: !sandbox,
+            allowed_paths: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecuritySection {
+    /// default, readonly, sandbox or readonly-sandbox
+    pub profile: Option<String>,
+    pub whitelist: Option<Vec<String>>,
+    pub blacklist: Option<Vec<String>>,
+    /// Directories that file paths in tool arguments must stay inside
+    #[serde(default)]
+    pub allowed_paths: Vec<PathBuf>,
+    /// false disables LibreOffice and other external converters
+    pub external_tools: Option<bool>,
+    /// false disables object storage and downloads
+    pub network: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsSection {
+    pub max_document_size: Option<usize>,
+    pub max_open_documents: Option<usize>,
+    pub max_document_memory: Option<usize>,
+    pub storage_threshold_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageSection {
+    pub temp_dir: Option<PathBuf>,
+    pub compression_level: Option<u32>,
+    pub deterministic: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConversionSection {
+    pub prefer_external_tools: Option<bool>,
+    #[serde(default)]
+    pub fallback_fonts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContentSection {
+    pub clause_dir: Option<PathBuf>,
+    pub letterheads: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhooksSection {
+    #[serde(default)]
+    pub urls: Vec<String>,
+    pub events: Option<Vec<String>>,
+}
+
+impl ConfigFile {
+    /// Read and validate a config file, resolving its relative paths
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid config file {:?}", path))?;
+        if let Some(profile) = &config.security.profile {
+            profile_flags(profile).with_context(|| format!("Invalid config file {:?}", path))?;
+        }
+        for token in &config.server.tokens {
+            if let Some(profile) = &token.profile {
+                profile_flags(profile).with_context(|| format!("Invalid config file {:?}: token '{}'", path, token.name))?;
+            }
+            if token.token.len() < 16 {
+                anyhow::bail!("Invalid config file {:?}: token '{}' is shorter than 16 characters", path, token.name);
+            }
+        }
+        let base = path.parent().unwrap_or(Path::new("."));
+        let paths = config.security.allowed_paths.iter_mut()
+            .chain(config.storage.temp_dir.iter_mut())
+            .chain(config.content.clause_dir.iter_mut())
+            .chain(config.content.letterheads.iter_mut());
+        for path in paths.filter(|p| p.is_relative()) {
+            *path = base.join(&*path);
+        }
+        Ok(config)
+    }
+
+    /// Fill in every setting of `args` that neither the command line nor the environment
+    /// set, per `matches` (the matches `args` was built from)
+    pub fn apply(&self, args: &mut Args, matches: &ArgMatches) {
+        let unset = |id: &str| !matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable));
+
+        if unset("http") && self.server.http.is_some() { args.http = self.server.http.clone(); }
+        if unset("admin_key") && self.server.admin_key.is_some() { args.admin_key = self.server.admin_key.clone(); }
+        if let (true, Some(secs)) = (unset("session_idle_timeout"), self.server.session_idle_timeout) { args.session_idle_timeout = secs; }
+        if let (true, Some(max)) = (unset("max_sessions_per_token"), self.server.max_sessions_per_token) { args.max_sessions_per_token = max; }
+        if let (true, Some(public)) = (unset("metrics_public"), self.server.metrics_public) { args.metrics_public = public; }
+        args.tokens = self.server.tokens.clone();
+
+        if let Some((readonly, sandbox)) = self.security.profile.as_deref().and_then(|p| profile_flags(p).ok()) {
+            if unset("readonly") { args.readonly = readonly; }
+            if unset("sandbox") { args.sandbox = sandbox; }
+        }
+        if unset("whitelist") && self.security.whitelist.is_some() { args.whitelist = self.security.whitelist.clone(); }
+        if unset("blacklist") && self.security.blacklist.is_some() { args.blacklist = self.security.blacklist.clone(); }
+        if unset("allowed_paths") && !self.security.allowed_paths.is_empty() { args.allowed_paths = self.security.allowed_paths.clone(); }
+        if let (true, Some(enabled)) = (unset("no_external_tools"), self.security.external_tools) { args.no_external_tools = !enabled; }
+        if let (true, Some(enabled)) = (unset("no_network"), self.security.network) { args.no_network = !enabled; }
+
+        if unset("max_size") && self.limits.max_document_size.is_some() { args.max_size = self.limits.max_document_size; }
+        if unset("max_docs") && self.limits.max_open_documents.is_some() { args.max_docs = self.limits.max_open_documents; }
+        if unset("max_document_memory") && self.limits.max_document_memory.is_some() { args.max_document_memory = self.limits.max_document_memory; }
+        if unset("storage_threshold_mb") && self.limits.storage_threshold_mb.is_some() { args.storage_threshold_mb = self.limits.storage_threshold_mb; }
+
+        if unset("temp_dir") && self.storage.temp_dir.is_some() { args.temp_dir = self.storage.temp_dir.clone(); }
+        if let (true, Some(level)) = (unset("compression_level"), self.storage.compression_level) { args.compression_level = level; }
+        if let (true, Some(deterministic)) = (unset("deterministic"), self.storage.deterministic) { args.deterministic = deterministic; }
+
+        if let (true, Some(prefer)) = (unset("prefer_external_tools"), self.conversion.prefer_external_tools) { args.prefer_external_tools = prefer; }
+        if unset("fallback_fonts") && !self.conversion.fallback_fonts.is_empty() { args.fallback_fonts = self.conversion.fallback_fonts.clone(); }
+
+        if unset("clause_dir") && self.content.clause_dir.is_some() { args.clause_dir = self.content.clause_dir.clone(); }
+        if unset("letterheads") && self.content.letterheads.is_some() { args.letterheads = self.content.letterheads.clone(); }
+
+        if unset("webhook_urls") && !self.webhooks.urls.is_empty() { args.webhook_urls = self.webhooks.urls.clone(); }
+        if unset("webhook_events") && self.webhooks.events.is_some() { args.webhook_events = self.webhooks.events.clone(); }
+    }
+}
+
+/// (readonly, sandbox) for a security profile name
+fn profile_flags(profile: &str) -> Result<(bool, bool)> {
+    Ok(match profile {
+        "default" => (false, false),
+        "readonly" => (true, false),
+        "sandbox" => (false, true),
+        "readonly-sandbox" => (true, true),
+        other => anyhow::bail!("unknown security profile '{}' (use default, readonly, sandbox or readonly-sandbox)", other),
+    })
+}
@@ -1,22 +1,25 @@
 use anyhow::{Context, Result};
 use ::image::{ImageFormat};
-use printpdf::*;
-use dotext::MsDoc;
-use ::lopdf::{dictionary, Object, ObjectId, Document as LoDocument};
-use std::fs::{self, File};
-use std::io::{BufWriter, Read};
+use ::lopdf::Document as LoDocument;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::NamedTempFile;
 use tracing::{debug, info};
 
-use crate::pure_converter::PureRustConverter;
+use crate::pure_converter::{PdfOptions, PureRustConverter};
 
 pub struct DocumentConverter {
     pure_converter: PureRustConverter,
     prefer_external_tools: bool,
 }
 
+impl Default for DocumentConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DocumentConverter {
     pub fn new() -> Self {
         Self {
@@ -47,8 +50,25 @@ impl DocumentConverter {
         Ok(())
     }
 
+    /// Try external converters first (`--prefer-external-tools`); hi-fidelity builds
+    /// always do
+    pub fn with_external_preference(mut self, prefer: bool) -> Self {
+        self.prefer_external_tools = prefer || cfg!(feature = "hi-fidelity");
+        self
+    }
+
+    /// Whether external converters are tried first by default (hi-fidelity builds)
+    pub fn prefers_external_tools(&self) -> bool {
+        self.prefer_external_tools
+    }
+
     /// Convert with explicit preference overriding internal default
     pub fn docx_to_pdf_with_preference(&self, docx_path: &Path, pdf_path: &Path, prefer_external: bool) -> Result<()> {
+        self.docx_to_pdf_with_options(docx_path, pdf_path, prefer_external, &PdfOptions::default())
+    }
+
+    /// Convert with explicit preference; `options` apply when the pure Rust renderer is used
+    pub fn docx_to_pdf_with_options(&self, docx_path: &Path, pdf_path: &Path, prefer_external: bool, options: &PdfOptions) -> Result<()> {
         if prefer_external {
             if self.try_libreoffice_conversion(docx_path, pdf_path).is_ok() {
                 info!("Successfully converted DOCX to PDF using LibreOffice (explicit preference)");
@@ -60,14 +80,14 @@ impl DocumentConverter {
             }
         }
         // Fallback to pure implementation
-        self.pure_converter.docx_to_pdf_pure(docx_path, pdf_path)?;
+        self.pure_converter.docx_to_pdf_pure_with_options(docx_path, pdf_path, options)?;
         info!("Successfully converted DOCX to PDF using pure Rust implementation (explicit preference)");
         Ok(())
     }
 
     fn try_libreoffice_conversion(&self, docx_path: &Path, pdf_path: &Path) -> Result<()> {
         let output = Command::new("libreoffice")
-            .args(&[
+            .args([
                 "--headless",
                 "--invisible",
                 "--nodefault",
@@ -107,7 +127,7 @@ impl DocumentConverter {
 
     fn try_unoconv_conversion(&self, docx_path: &Path, pdf_path: &Path) -> Result<()> {
         let output = Command::new("unoconv")
-            .args(&[
+            .args([
                 "-f", "pdf",
                 "-o", pdf_path.to_str().unwrap(),
                 docx_path.to_str().unwrap(),
@@ -127,42 +147,6 @@ impl DocumentConverter {
         }
     }
 
-    fn basic_docx_to_pdf(&self, docx_path: &Path, pdf_path: &Path) -> Result<()> {
-        // Extract text from DOCX (fallback using dotext)
-        let mut reader = dotext::Docx::open(docx_path)
-            .with_context(|| format!("Failed to open DOCX {:?}", docx_path))?;
-        let mut data = String::new();
-        use std::io::Read as _;
-        reader.read_to_string(&mut data)?;
-        let text = data;
-        
-        // Create a basic PDF with the extracted text
-        let (doc, page1, layer1) = PdfDocument::new("Document", Mm(210.0), Mm(297.0), "Layer 1");
-        let _current_layer = doc.get_page(page1).get_layer(layer1);
-        
-        // Load a basic font
-        let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
-        
-        // Split text into lines and add to PDF
-        let lines: Vec<&str> = text.lines().collect();
-        let mut y_position = Mm(280.0);
-        let line_height = Mm(5.0);
-        
-        let mut current_layer = doc.get_page(page1).get_layer(layer1);
-        for line in lines {
-            if y_position < Mm(20.0) {
-                let (page, layer) = doc.add_page(Mm(210.0), Mm(297.0), "Page layer");
-                current_layer = doc.get_page(page).get_layer(layer);
-                y_position = Mm(280.0);
-            }
-            current_layer.use_text(line, 12.0, Mm(10.0), y_position, &font);
-            y_position -= line_height;
-        }
-        
-        doc.save(&mut BufWriter::new(File::create(pdf_path)?))?;
-        Ok(())
-    }
-
     pub fn pdf_to_images(
         &self,
         pdf_path: &Path,
@@ -210,7 +194,7 @@ impl DocumentConverter {
         };
         
         let output = Command::new("pdftoppm")
-            .args(&[
+            .args([
                 format_arg,
                 "-r", &dpi.to_string(),
                 pdf_path.to_str().unwrap(),
@@ -261,7 +245,7 @@ impl DocumentConverter {
         let output_pattern = output_dir.join(format!("page-%03d.{}", extension));
         
         let output = Command::new("convert")
-            .args(&[
+            .args([
                 "-density", &dpi.to_string(),
                 pdf_path.to_str().unwrap(),
                 "-quality", "100",
@@ -312,7 +296,7 @@ impl DocumentConverter {
         let output_pattern = output_dir.join(format!("page-%03d.{}", extension));
         
         let output = Command::new("gs")
-            .args(&[
+            .args([
                 "-dNOPAUSE",
                 "-dBATCH",
                 "-sDEVICE", device,
@@ -431,7 +415,7 @@ impl DocumentConverter {
         merged.version = "1.5".to_string();
         
         for pdf_path in pdf_paths {
-            let mut doc = LoDocument::load(pdf_path)?;
+            let doc = LoDocument::load(pdf_path)?;
             
             // Merge pages
             for page_id in doc.get_pages().values() {
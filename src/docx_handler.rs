@@ -21,9 +21,95 @@ pub struct DocxMetadata {
     pub author: Option<String>,
     pub title: Option<String>,
     pub subject: Option<String>,
+    /// Last time the working copy was saved to an output path
+    #[serde(default)]
+    pub saved_at: Option<DateTime<Utc>>,
+    /// Workspace the document belongs to, if any
+    #[serde(default)]
+    pub workspace: Option<String>,
+    /// Client (HTTP session) that created or opened the document; only that client
+    /// and operators can see it. None for documents of the local, unscoped client.
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Edits and saves since the document was created or opened
+    #[serde(default)]
+    pub activity: DocActivity,
+    /// For opened documents made editable: what reading them into ops had to drop
+    #[serde(default)]
+    pub fidelity: Option<crate::docx_reader::Fidelity>,
+    /// SHA-256 (hex) of the packed working copy, filled in by `get_metadata`
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
+/// Per-document session activity, reported by `get_metadata`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocActivity {
+    /// Operations that changed the document's content
+    pub ops_applied: u64,
+    /// The most recent of those operations, oldest first
+    pub recent_ops: Vec<RecordedOp>,
+    /// Total bytes written to output paths by saves
+    pub bytes_written: u64,
+    /// Whether the content changed since the last save (or since creation, if never saved)
+    pub dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedOp {
+    /// Tool that applied the operation
+    pub op: String,
+    /// First element the operation changed
+    pub element_index: usize,
+    pub at: DateTime<Utc>,
+}
+
+/// How many operations `DocActivity::recent_ops` keeps
+const RECENT_OPS: usize = 5;
+
+/// Snapshots kept per document; taking another drops the oldest
+const MAX_SNAPSHOTS: usize = 20;
+
+/// Identifies ops journals written by `export_ops`
+const OPS_JOURNAL_FORMAT: &str = "docx-mcp-ops";
+/// Bumped when a change to `DocxOp` would stop older journals from replaying
+const OPS_JOURNAL_VERSION: u32 = 1;
+
+/// What `redact_text` puts in place of each match
+pub const REDACTION_MARK: &str = "█";
+
+/// A document's construction recipe, exported by `export_ops` and read by `replay_ops`
+#[derive(Debug, Serialize, Deserialize)]
+struct OpsJournal {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    title: Option<String>,
+    ops: Vec<DocxOp>,
+}
+
+/// A named group of related documents sharing an output directory
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    /// Relative output paths of member documents are resolved against this directory
+    pub output_dir: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters for `document_summaries`; unset fields match every document
+#[derive(Debug, Clone, Default)]
+pub struct DocumentFilter {
+    pub title_contains: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub workspace: Option<String>,
+    /// Only documents owned by this client
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DocxStyle {
     pub font_family: Option<String>,
     pub font_size: Option<usize>,
@@ -33,9 +119,147 @@ pub struct DocxStyle {
     pub color: Option<String>,
     pub alignment: Option<String>,
     pub line_spacing: Option<f32>,
+    /// "rtl" or "ltr"; unset paragraphs take the direction of their first letter
+    pub direction: Option<String>,
+    /// Word highlight name such as "yellow" or "lightGray"
+    pub highlight: Option<String>,
+    pub strike: Option<bool>,
+    pub double_strike: Option<bool>,
+    /// "superscript", "subscript" or "baseline"
+    pub vert_align: Option<String>,
+    pub caps: Option<bool>,
+    pub small_caps: Option<bool>,
+    /// Extra space between characters in points; negative values condense
+    pub character_spacing: Option<f32>,
+    /// Underline pattern such as "double", "dotted" or "wave"; implies `underline`
+    pub underline_style: Option<String>,
+    pub borders: Option<ParagraphBorders>,
+    /// Paragraph background as hex RGB like "EEEEEE"
+    pub shading: Option<String>,
+    /// Hidden text: kept in the file but not shown or printed unless Word's
+    /// "show hidden text" option is on
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParagraphBorders {
+    pub top: Option<BorderSpec>,
+    pub bottom: Option<BorderSpec>,
+    pub left: Option<BorderSpec>,
+    pub right: Option<BorderSpec>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BorderSpec {
+    /// "single", "double", "dotted", "dashed", "thick", "wave", ...
+    #[serde(default = "default_border_style")]
+    pub style: String,
+    /// Hex RGB; defaults to automatic (black)
+    pub color: Option<String>,
+    /// Line width in eighths of a point
+    pub size: Option<u32>,
+    /// Gap between border and text in points
+    pub space: Option<u32>,
+}
+
+fn default_border_style() -> String {
+    "single".to_string()
+}
+
+impl DocxStyle {
+    /// Reject values Word would not understand before they reach the document
+    fn validate(&self) -> Result<()> {
+        if let Some(direction) = self.direction.as_deref() {
+            direction.parse::<crate::bidi::Direction>()?;
+        }
+        if let Some(color) = self.highlight.as_deref() {
+            if highlight_name(color).is_none() {
+                anyhow::bail!("unknown highlight color '{}' (use one of {})", color, HIGHLIGHT_NAMES.join(", "));
+            }
+        }
+        if let Some(align) = self.vert_align.as_deref() {
+            vert_align_type(align)?;
+        }
+        if self.character_spacing.map(|s| !s.is_finite() || s.abs() > 100.0).unwrap_or(false) {
+            anyhow::bail!("character_spacing must be between -100 and 100 points");
+        }
+        if let Some(pattern) = self.underline_style.as_deref() {
+            canonical_name(UNDERLINE_STYLES, pattern)
+                .ok_or_else(|| anyhow::anyhow!("unknown underline_style '{}' (use one of {})", pattern, UNDERLINE_STYLES.join(", ")))?;
+        }
+        if let Some(borders) = &self.borders {
+            for spec in [&borders.top, &borders.bottom, &borders.left, &borders.right].into_iter().flatten() {
+                canonical_name(BORDER_STYLES, &spec.style)
+                    .ok_or_else(|| anyhow::anyhow!("unknown border style '{}' (use one of {})", spec.style, BORDER_STYLES.join(", ")))?;
+                if let Some(color) = &spec.color { check_hex_color(color)?; }
+            }
+        }
+        if let Some(fill) = &self.shading { check_hex_color(fill)?; }
+        Ok(())
+    }
+
+    /// Whether the paragraph needs `w:pBdr`/`w:shd` written into its properties
+    fn has_box(&self) -> bool {
+        self.shading.is_some()
+            || self.borders.as_ref().map(|b| b.top.is_some() || b.bottom.is_some() || b.left.is_some() || b.right.is_some()).unwrap_or(false)
+    }
+
+    /// `w:pBdr` and `w:shd` elements for the paragraph's borders and shading
+    fn box_xml(&self) -> String {
+        let mut xml = String::new();
+        if let Some(borders) = &self.borders {
+            let sides = [("top", &borders.top), ("left", &borders.left), ("bottom", &borders.bottom), ("right", &borders.right)];
+            let edges: String = sides.iter().filter_map(|(side, spec)| spec.as_ref().map(|spec| format!(
+                r#"<w:{} w:val="{}" w:sz="{}" w:space="{}" w:color="{}"/>"#,
+                side,
+                canonical_name(BORDER_STYLES, &spec.style).unwrap_or("single"),
+                spec.size.unwrap_or(4),
+                spec.space.unwrap_or(1),
+                spec.color.as_deref().map(|c| c.trim_start_matches('#').to_uppercase()).unwrap_or_else(|| "auto".into()),
+            ))).collect();
+            if !edges.is_empty() { xml.push_str(&format!("<w:pBdr>{}</w:pBdr>", edges)); }
+        }
+        if let Some(fill) = &self.shading {
+            xml.push_str(&format!(r#"<w:shd w:val="clear" w:color="auto" w:fill="{}"/>"#, fill.trim_start_matches('#').to_uppercase()));
+        }
+        xml
+    }
+}
+
+const UNDERLINE_STYLES: &[&str] = &[
+    "single", "double", "thick", "dotted", "dottedHeavy", "dash", "dashedHeavy", "dashLong",
+    "dotDash", "dotDotDash", "wave", "wavyHeavy", "wavyDouble", "words", "none",
+];
+const BORDER_STYLES: &[&str] = &[
+    "single", "double", "dotted", "dashed", "thick", "wave", "dotDash", "dotDotDash", "triple", "none",
+];
+
+fn canonical_name(names: &[&'static str], value: &str) -> Option<&'static str> {
+    names.iter().copied().find(|n| n.eq_ignore_ascii_case(value))
+}
+
+fn check_hex_color(color: &str) -> Result<()> {
+    let hex = color.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("'{}' is not a hex RGB color like \"FFF2CC\"", color);
+    }
+    Ok(())
+}
+
+fn highlight_name(color: &str) -> Option<&'static str> {
+    canonical_name(HIGHLIGHT_NAMES, color)
+}
+
+fn vert_align_type(value: &str) -> Result<VertAlignType> {
+    match value.to_ascii_lowercase().as_str() {
+        "superscript" | "super" => Ok(VertAlignType::SuperScript),
+        "subscript" | "sub" => Ok(VertAlignType::SubScript),
+        "baseline" => Ok(VertAlignType::Baseline),
+        other => anyhow::bail!("unknown vert_align '{}' (use superscript, subscript or baseline)", other),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TableData {
     pub rows: Vec<Vec<String>>,
     pub headers: Option<Vec<String>>,
@@ -43,6 +267,133 @@ pub struct TableData {
     pub col_widths: Option<Vec<u32>>, // approximate column widths (px)
     pub merges: Option<Vec<TableMerge>>, // best-effort merge specs
     pub cell_shading: Option<String>, // hex RGB like "EEEEEE"
+    /// Vertical alignment, padding and text direction of cells
+    #[serde(default)]
+    pub cell_styles: Option<Vec<CellStyle>>,
+    /// Repeat the first row at the top of every page the table runs onto
+    #[serde(default)]
+    pub repeat_header_row: Option<bool>,
+    /// Keep each row on one page instead of letting it break across pages
+    #[serde(default)]
+    pub cant_split_rows: Option<bool>,
+    /// "fixed" keeps the column widths as given; "autofit" lets Word size columns to
+    /// their content
+    #[serde(default)]
+    pub table_layout: Option<String>,
+    /// Table width as a percentage of the text width
+    #[serde(default)]
+    pub width_percent: Option<f32>,
+    /// Numbered caption above the table ("Table 1: ..."), kept on the table's page and
+    /// picked up by add_table_of_figures
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+impl TableData {
+    pub fn validate(&self) -> Result<()> {
+        for style in self.cell_styles.iter().flatten() {
+            style.validate()?;
+        }
+        if let Some(layout) = &self.table_layout {
+            table_layout_type(layout)?;
+        }
+        if let Some(percent) = self.width_percent {
+            if !(1.0..=100.0).contains(&percent) {
+                anyhow::bail!("width_percent must be between 1 and 100 (got {})", percent);
+            }
+        }
+        if self.caption.as_ref().is_some_and(|c| c.trim().is_empty()) {
+            anyhow::bail!("caption cannot be empty");
+        }
+        Ok(())
+    }
+
+    /// Whether the table sets anything that is written after packing
+    fn has_row_or_cell_layout(&self) -> bool {
+        self.cell_styles.as_ref().is_some_and(|s| !s.is_empty())
+            || self.repeat_header_row == Some(true)
+            || self.cant_split_rows == Some(true)
+            || self.table_layout.is_some()
+            || self.width_percent.is_some()
+            || self.caption.is_some()
+    }
+
+    /// Caption paragraph text with its sequence field
+    fn caption_text(&self) -> Option<String> {
+        self.caption.as_ref().map(|caption| format!("Table {}SEQ Table \\* ARABIC}}: {}", FIELD_TOKEN_OPEN, caption.trim()))
+    }
+}
+
+fn table_layout_type(name: &str) -> Result<&'static str> {
+    Ok(match name.trim().to_ascii_lowercase().as_str() {
+        "fixed" => "fixed",
+        "autofit" | "auto" => "autofit",
+        other => anyhow::bail!("unknown table_layout '{}' (use fixed or autofit)", other),
+    })
+}
+
+/// Layout of the cells at `row` and `col`. Leave either out to cover every row or
+/// column (`row: 0` alone styles the first row); later entries win where they overlap.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CellStyle {
+    pub row: Option<usize>,
+    pub col: Option<usize>,
+    /// top, center or bottom
+    pub vertical_align: Option<String>,
+    /// Space between the cell border and its text
+    pub padding: Option<CellPadding>,
+    /// horizontal, rotate_up (reads bottom to top, for narrow header columns) or rotate_down
+    pub text_direction: Option<String>,
+}
+
+/// Cell padding in points; unset sides keep the table default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CellPadding {
+    pub top: Option<f32>,
+    pub bottom: Option<f32>,
+    pub left: Option<f32>,
+    pub right: Option<f32>,
+}
+
+impl CellStyle {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(align) = &self.vertical_align {
+            cell_v_align(align)?;
+        }
+        if let Some(direction) = &self.text_direction {
+            cell_text_direction(direction)?;
+        }
+        if let Some(padding) = &self.padding {
+            for side in [padding.top, padding.bottom, padding.left, padding.right].into_iter().flatten() {
+                if !(0.0..=72.0).contains(&side) {
+                    anyhow::bail!("Cell padding must be between 0 and 72 points (got {})", side);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn covers(&self, row: usize, col: usize) -> bool {
+        self.row.is_none_or(|r| r == row) && self.col.is_none_or(|c| c == col)
+    }
+}
+
+fn cell_v_align(name: &str) -> Result<&'static str> {
+    Ok(match name.trim().to_ascii_lowercase().as_str() {
+        "top" => "top",
+        "center" | "centre" | "middle" => "center",
+        "bottom" => "bottom",
+        other => anyhow::bail!("unknown vertical_align '{}' (use top, center or bottom)", other),
+    })
+}
+
+fn cell_text_direction(name: &str) -> Result<&'static str> {
+    Ok(match name.trim() {
+        "horizontal" | "lrTb" => "lrTb",
+        "rotate_up" | "btLr" => "btLr",
+        "rotate_down" | "tbRl" => "tbRl",
+        other => anyhow::bail!("unknown text_direction '{}' (use horizontal, rotate_up or rotate_down)", other),
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,11 +412,343 @@ pub struct ImageData {
     pub alt_text: Option<String>,
 }
 
+/// Rich header/footer content besides the text: paragraph alignment and a logo placed in
+/// the left, center or right zone of a tab-separated line
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeaderFooterLayout {
+    pub alignment: Option<String>,
+    pub logo: Option<ImageData>,
+    /// left (default), center or right
+    pub logo_position: Option<String>,
+    /// One-row table above the text, for layouts such as logo | title | date
+    pub table: Option<HeaderFooterTable>,
+}
+
+/// A one-row table spanning the header/footer text width
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeaderFooterTable {
+    pub cells: Vec<HeaderFooterCell>,
+    /// Relative column widths (e.g. [1, 2, 1]); equal columns when unset
+    pub col_widths: Option<Vec<u32>>,
+    /// Draw cell borders (default off)
+    pub borders: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeaderFooterCell {
+    /// Cell text, one paragraph per line; may hold field tokens such as {FIELD:DATE}
+    pub text: String,
+    /// left (default), center, right or justify
+    pub alignment: Option<String>,
+    /// Image shown before the text
+    pub image: Option<ImageData>,
+}
+
+/// Text or picture behind the text of every page, drawn from the section's header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watermark {
+    pub content: WatermarkContent,
+    /// Section it belongs to (0 is the part before the first section break); None for
+    /// every section without a watermark of its own
+    pub section: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WatermarkContent {
+    /// WordArt text stretched across the text width
+    Text {
+        text: String,
+        font: Option<String>,
+        /// Hex RGB fill (default silver)
+        color: Option<String>,
+        /// 0 (invisible) to 1 (solid), default 0.5
+        opacity: Option<f32>,
+        /// On a 45° slant (default) or level
+        diagonal: bool,
+    },
+    /// PNG, JPEG or SVG picture centred on the page
+    Image {
+        #[serde(with = "crate::spill::base64_bytes")]
+        data: Vec<u8>,
+        /// Width in inches; the picture's own size, at most the text width, when unset
+        width: Option<f32>,
+        /// Faded like Word's "washout" so the text stays readable
+        washout: bool,
+    },
+}
+
+/// One signatory of `add_signature_block`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SignatureParty {
+    /// Person who signs
+    pub name: String,
+    /// Organisation they sign for
+    pub company: Option<String>,
+    pub title: Option<String>,
+    /// Caption above the signature line, instead of "Signed for and on behalf of ..."
+    pub label: Option<String>,
+}
+
+/// Layout of `add_signature_block`
+#[derive(Debug, Clone, Default)]
+pub struct SignatureBlockOptions {
+    /// "line" (a blank line to date, the default), "field" (a DATE field showing the
+    /// current date) or "none"
+    pub date: Option<String>,
+    /// An initials line in each party's block
+    pub initials: bool,
+    /// A footer line with an initials slot per party, so every page gets initialled
+    pub initial_each_page: bool,
+    /// Tag each line with an e-signature anchor in invisible text
+    pub anchors: bool,
+    /// Anchor text with `{kind}` (s signature, d date, i initials) and `{n}` (party
+    /// number from 1); DocuSign's `\{kind}{n}\` by default
+    pub anchor_format: Option<String>,
+}
+
+/// Where `insert_at` places new content, written in JSON as a single-key object such
+/// as `{"heading": "Scope"}` or `{"table_index": 0}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InsertAnchor {
+    /// Heading text, matched exactly and then case-insensitively
+    Heading(String),
+    /// Name of a bookmark added with `insert_bookmark_after_heading`
+    Bookmark(String),
+    /// Element index as in `get_outline` and `extract_text`
+    #[serde(alias = "element_index")]
+    ElementId(usize),
+    /// Zero-based table number
+    TableIndex(usize),
+}
+
+/// Matching rules and reach of `find_and_replace_with`
+#[derive(Debug, Clone, Default)]
+pub struct ReplaceOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Treat the pattern as a regular expression rather than literal text; the
+    /// replacement may then refer to groups as `$1` or `${name}` (`$$` for a dollar sign)
+    pub use_regex: bool,
+    /// Give each replacement the case of the text it replaces (see `text_transform::match_case`)
+    pub preserve_case: bool,
+    pub scope: ReplaceScope,
+    /// Text outside the body to change as well
+    pub include: Vec<ReplaceTarget>,
+}
+
+/// Text besides the body, headers and footers that `find_and_replace_with` can change,
+/// so a rebranding pass (domain change, product rename) catches everything
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaceTarget {
+    HyperlinkUrls,
+    AltText,
+    /// Renames that would leave an invalid name (see `is_valid_bookmark_name`) are
+    /// skipped. Nothing in a created document refers to a bookmark by name (links are
+    /// external and no field is a REF), so there are no references to update; links to
+    /// the old name from other documents break.
+    BookmarkNames,
+    /// Title, subject and author, whatever the scope
+    Properties,
+}
+
+/// The part of a document `find_and_replace_advanced` may change, written in JSON as
+/// `"tables"`, `"headers"`, `{"heading": "Terms"}` or `{"element_range": {"start": 3, "end": 9}}`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaceScope {
+    #[default]
+    All,
+    /// A heading's section with its subsections, the heading included (matched as in `insert_at`)
+    Heading(String),
+    /// Elements `start` to `end` inclusive, indexed as in `get_outline`
+    ElementRange { start: usize, end: usize },
+    /// Table cells only
+    Tables,
+    /// Header and footer text only
+    Headers,
+}
+
+/// One match replaced by `find_and_replace_with`, or that would be in a dry run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Replacement {
+    /// None for document properties
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element_index: Option<usize>,
+    /// paragraph, heading, list_item, table_cell, hyperlink, header, footer, or with
+    /// `ReplaceOptions::include` hyperlink_url, image_alt_text, bookmark_name or property
+    pub kind: String,
+    /// title, subject or author, for a property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    /// Position in a list element's items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<usize>,
+    /// [row, column] of a table cell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell: Option<[usize; 2]>,
+    pub matched: String,
+    pub replacement: String,
+    /// The text around the match as it reads now and after the change
+    pub before: String,
+    pub after: String,
+}
+
+/// One element for `insert_at`, tagged by `type`:
+/// `{"type": "heading", "text": "Scope", "level": 2}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)] // DocxStyle is large; boxing it would touch every match
+pub enum NewElement {
+    Paragraph {
+        text: String,
+        #[serde(default)]
+        style: Option<DocxStyle>,
+    },
+    Heading { text: String, level: usize },
+    List {
+        items: Vec<String>,
+        #[serde(default)]
+        ordered: bool,
+    },
+    Table(TableData),
+    PageBreak,
+}
+
+/// One heading of a `create_outline` skeleton, with its subsections in `children`:
+/// `{"title": "Background", "placeholder": "Market and competitors", "children": [...]}`.
+/// The `tree` from `get_outline` reads the same way, `text` standing in for `title`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlineNode {
+    #[serde(alias = "text")]
+    pub title: String,
+    /// Paragraph under the heading, "[title]" when not given and left out when empty
+    #[serde(default)]
+    pub placeholder: Option<String>,
+    #[serde(default)]
+    pub children: Vec<OutlineNode>,
+}
+
+/// Style and font renaming for content brought in from another document
+/// (`insert_document`, `copy_elements`, `assemble_document`), so the combined document
+/// looks like one piece
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleMap {
+    /// Source heading style → target style, by name or id ("Heading 1" or "Heading1").
+    /// A heading moves to the target level, or becomes body text with "Normal".
+    #[serde(default)]
+    pub styles: std::collections::BTreeMap<String, String>,
+    /// Source font family → target font family, for direct formatting and the default
+    /// font; "*" maps every font not listed
+    #[serde(default)]
+    pub fonts: std::collections::BTreeMap<String, String>,
+}
+
+/// Target of a style mapping: a heading level or body text
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MappedStyle {
+    Heading(usize),
+    Normal,
+}
+
+fn mapped_style(name: &str) -> Option<MappedStyle> {
+    let key: String = name.chars().filter(|c| !c.is_whitespace() && *c != '_' && *c != '-').collect::<String>().to_ascii_lowercase();
+    match key.as_str() {
+        "normal" | "bodytext" | "body" => Some(MappedStyle::Normal),
+        _ => key.strip_prefix("heading")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| (1..=6).contains(n))
+            .map(MappedStyle::Heading),
+    }
+}
+
+impl StyleMap {
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty() && self.fonts.is_empty()
+    }
+
+    /// Check that every mapped style is a heading level or Normal, the styles the
+    /// element model represents
+    pub fn validate(&self) -> Result<()> {
+        for (from, to) in &self.styles {
+            if !matches!(mapped_style(from), Some(MappedStyle::Heading(_))) {
+                anyhow::bail!("Cannot map style '{}': only Heading 1-6 can be mapped", from);
+            }
+            if mapped_style(to).is_none() {
+                anyhow::bail!("Cannot map '{}' to '{}': use Heading 1-6 or Normal", from, to);
+            }
+        }
+        for (from, to) in &self.fonts {
+            if from.trim().is_empty() || to.trim().is_empty() {
+                anyhow::bail!("Font mappings need a source and a target font");
+            }
+        }
+        Ok(())
+    }
+
+    fn font(&self, family: &str) -> Option<&String> {
+        self.fonts.iter()
+            .find(|(from, _)| from.eq_ignore_ascii_case(family))
+            .map(|(_, to)| to)
+            .or_else(|| self.fonts.get("*"))
+    }
+
+    /// Rename heading styles and fonts in imported ops
+    fn apply(&self, ops: &mut [DocxOp]) {
+        let levels: Vec<(usize, MappedStyle)> = self.styles.iter()
+            .filter_map(|(from, to)| match (mapped_style(from), mapped_style(to)) {
+                (Some(MappedStyle::Heading(level)), Some(target)) => Some((level, target)),
+                _ => None,
+            })
+            .collect();
+        for op in ops.iter_mut() {
+            match op {
+                DocxOp::Heading { text, style } => {
+                    let level = heading_level(style);
+                    match levels.iter().find(|(from, _)| *from == level).map(|(_, to)| *to) {
+                        Some(MappedStyle::Heading(to)) => *style = format!("Heading{}", to),
+                        Some(MappedStyle::Normal) => *op = DocxOp::Paragraph { text: std::mem::take(text), style: None },
+                        None => {}
+                    }
+                }
+                DocxOp::Paragraph { style: Some(style), .. } => {
+                    if let Some(to) = style.font_family.as_deref().and_then(|f| self.font(f)) {
+                        style.font_family = Some(to.clone());
+                    }
+                }
+                DocxOp::DefaultFont { family: Some(family), .. } => {
+                    if let Some(to) = self.font(family) {
+                        *family = to.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
 pub struct DocxHandler {
     temp_dir: PathBuf,
     pub documents: std::collections::HashMap<String, DocxMetadata>,
     // In-memory operations for documents created via this handler
-    in_memory_ops: std::collections::HashMap<String, Vec<DocxOp>>,
+    in_memory_ops: crate::spill::OpStore,
+    workspaces: std::collections::HashMap<String, Workspace>,
+    /// Baselines for `summarize_changes`, by snapshot id
+    snapshots: std::collections::HashMap<String, Snapshot>,
+    /// How packages this handler writes are zipped
+    package_options: PackageOptions,
+    /// Layout page counts for `get_metadata`, with the sha256 of the file they describe
+    page_counts: std::sync::Mutex<std::collections::HashMap<String, (String, usize)>>,
+}
+
+/// A copy of a document's ops at one point, kept in the op store under the snapshot id
+/// until the document is closed or has `MAX_SNAPSHOTS` newer ones
+struct Snapshot {
+    doc_id: String,
+    label: Option<String>,
+    taken_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,16 +759,53 @@ pub enum RangeId {
     TableCell { table_index: usize, row: usize, col: usize },
 }
 
+/// Handler failures callers branch on (mapped to tool error codes); everything else
+/// is a plain anyhow error
+#[derive(Debug, thiserror::Error)]
+pub enum DocxError {
+    #[error("Document not found: {0}")]
+    NotFound(String),
+    #[error("Operation is supported only for documents created by this server; {0} was opened from a file")]
+    UnsupportedOnOpenedDoc(String),
+}
+
+/// Error for a missing op list: unknown id, or an opened document (which has no ops)
+fn missing_ops(documents: &std::collections::HashMap<String, DocxMetadata>, doc_id: &str) -> DocxError {
+    if documents.contains_key(doc_id) {
+        DocxError::UnsupportedOnOpenedDoc(doc_id.to_string())
+    } else {
+        DocxError::NotFound(doc_id.to_string())
+    }
+}
+
+/// A piece of editable text with a reference back to the element that holds it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementText {
+    /// Index into the document's element list (w:p order for opened documents)
+    pub element_index: usize,
+    /// paragraph, heading, list_item, table_cell, header, footer or hyperlink
+    pub kind: String,
+    pub range_id: Option<RangeId>,
+    /// Position within a multi-item element (list items)
+    pub item: Option<usize>,
+    pub text: String,
+}
+
 impl DocxHandler {
     pub fn new() -> Result<Self> {
-        let base = std::env::var_os("DOCX_MCP_TEMP").map(PathBuf::from).unwrap_or_else(|| std::env::temp_dir());
+        let base = std::env::var_os("DOCX_MCP_TEMP").map(PathBuf::from).unwrap_or_else(std::env::temp_dir);
         let temp_dir = base.join("docx-mcp");
         fs::create_dir_all(&temp_dir)?;
+        let spill_dir = temp_dir.join("spill");
         
         Ok(Self {
             temp_dir,
             documents: std::collections::HashMap::new(),
-            in_memory_ops: std::collections::HashMap::new(),
+            in_memory_ops: crate::spill::OpStore::new(spill_dir),
+            workspaces: std::collections::HashMap::new(),
+            snapshots: std::collections::HashMap::new(),
+            package_options: PackageOptions::default(),
+            page_counts: std::sync::Mutex::new(std::collections::HashMap::new()),
         })
     }
 
@@ -93,10 +813,15 @@ impl DocxHandler {
     pub fn new_with_base_dir<P: AsRef<Path>>(base_dir: P) -> Result<Self> {
         let temp_dir = base_dir.as_ref().join("docx-mcp");
         fs::create_dir_all(&temp_dir)?;
+        let spill_dir = temp_dir.join("spill");
         Ok(Self {
             temp_dir,
             documents: std::collections::HashMap::new(),
-            in_memory_ops: std::collections::HashMap::new(),
+            in_memory_ops: crate::spill::OpStore::new(spill_dir),
+            workspaces: std::collections::HashMap::new(),
+            snapshots: std::collections::HashMap::new(),
+            package_options: PackageOptions::default(),
+            page_counts: std::sync::Mutex::new(std::collections::HashMap::new()),
         })
     }
 
@@ -104,11 +829,16 @@ impl DocxHandler {
     pub fn new_with_temp_dir(temp_dir: &Path) -> Result<Self> {
         let temp_dir = temp_dir.to_path_buf();
         fs::create_dir_all(&temp_dir)?;
+        let spill_dir = temp_dir.join("spill");
         
         Ok(Self {
             temp_dir,
             documents: std::collections::HashMap::new(),
-            in_memory_ops: std::collections::HashMap::new(),
+            in_memory_ops: crate::spill::OpStore::new(spill_dir),
+            workspaces: std::collections::HashMap::new(),
+            snapshots: std::collections::HashMap::new(),
+            package_options: PackageOptions::default(),
+            page_counts: std::sync::Mutex::new(std::collections::HashMap::new()),
         })
     }
 
@@ -126,6 +856,7 @@ impl DocxHandler {
             .with_context(|| format!("Failed to create DOCX file at {:?}", doc_path))?;
         docx.build().pack(file)
             .with_context(|| format!("Failed to write DOCX package at {:?}", doc_path))?;
+        finish_package(&doc_path, self.package_options)?;
         
         let metadata = DocxMetadata {
             id: doc_id.clone(),
@@ -138,6 +869,12 @@ impl DocxHandler {
             author: None,
             title: None,
             subject: None,
+            saved_at: None,
+            workspace: None,
+            owner: None,
+            activity: DocActivity::default(),
+            fidelity: None,
+            sha256: None,
         };
         
         self.documents.insert(doc_id.clone(), metadata);
@@ -147,6 +884,90 @@ impl DocxHandler {
         Ok(doc_id)
     }
 
+    /// Create an invoice in the layout of the built-in Invoice template: company name and
+    /// "INVOICE" right-aligned, an invoice details table, the billed party, a line items
+    /// table with computed amounts and subtotal/tax/total rows. Amounts are formatted
+    /// for the invoice's locale and currency.
+    pub fn generate_invoice(&mut self, invoice: &crate::invoice::Invoice) -> Result<(String, crate::invoice::InvoiceTotals)> {
+        let totals = invoice.totals()?;
+        let locale = invoice.locale()?;
+        let money = |v: f64| locale.format_currency(v, &invoice.currency);
+        let number = |v: f64| locale.format_number(v, if v.fract() == 0.0 { 0 } else { 2 });
+        let style = |size: usize, bold: bool, alignment: Option<&str>| Some(DocxStyle {
+            font_family: None,
+            font_size: Some(size),
+            bold: Some(bold),
+            italic: None,
+            underline: None,
+            color: None,
+            alignment: alignment.map(|a| a.to_string()),
+            ..Default::default()
+        });
+        let table = |rows: Vec<Vec<String>>, col_widths: Vec<u32>, merges: Option<Vec<TableMerge>>| DocxOp::Table {
+            data: TableData {
+                rows,
+                headers: None,
+                border_style: Some("single".into()),
+                col_widths: Some(col_widths),
+                merges,
+                cell_shading: None,
+                ..Default::default()
+            },
+        };
+
+        let mut ops = vec![
+            DocxOp::Paragraph { text: invoice.company_name.clone(), style: style(32, true, Some("right")) },
+            DocxOp::Paragraph { text: "INVOICE".into(), style: style(28, true, Some("right")) },
+        ];
+        let details: Vec<Vec<String>> = [
+            ("Invoice #:", &invoice.invoice_number),
+            ("Date:", &invoice.date),
+            ("Due Date:", &invoice.due_date),
+        ].into_iter()
+            .filter_map(|(label, value)| value.as_ref().map(|v| vec![label.to_string(), v.clone()]))
+            .collect();
+        if !details.is_empty() {
+            ops.push(table(details, vec![120, 240], None));
+        }
+        if !invoice.bill_to.is_empty() {
+            ops.push(DocxOp::Paragraph { text: "Bill To:".into(), style: style(22, true, None) });
+            ops.extend(invoice.bill_to.iter().map(|line| DocxOp::Paragraph { text: line.clone(), style: None }));
+        }
+
+        let mut rows = vec![vec!["Description".into(), "Qty".into(), "Unit Price".into(), "Tax".into(), "Amount".into()]];
+        for line in &totals.lines {
+            rows.push(vec![
+                line.description.clone(),
+                number(line.quantity),
+                money(line.unit_price),
+                format!("{}%", number(line.tax_rate)),
+                money(line.net),
+            ]);
+        }
+        let first_total = rows.len();
+        for (label, amount) in [("Subtotal", totals.subtotal), ("Tax", totals.tax), ("Total", totals.total)] {
+            rows.push(vec![label.into(), String::new(), String::new(), String::new(), money(amount)]);
+        }
+        // Total labels span the description to tax columns
+        let merges = (first_total..rows.len()).map(|row| TableMerge { row, col: 0, row_span: 1, col_span: 4 }).collect();
+        ops.push(table(rows, vec![240, 60, 100, 60, 100], Some(merges)));
+        if let Some(notes) = invoice.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+            ops.push(DocxOp::Paragraph { text: notes.to_string(), style: None });
+        }
+
+        let doc_id = self.create_document()?;
+        self.in_memory_ops.insert(doc_id.clone(), ops);
+        if let Some(metadata) = self.documents.get_mut(&doc_id) {
+            metadata.title = Some(match &invoice.invoice_number {
+                Some(n) => format!("Invoice {}", n),
+                None => "Invoice".to_string(),
+            });
+        }
+        self.write_docx(&doc_id)?;
+        info!("Generated invoice document {}", doc_id);
+        Ok((doc_id, totals))
+    }
+
     pub fn open_document(&mut self, path: &Path) -> Result<String> {
         let doc_id = Uuid::new_v4().to_string();
         let doc_path = self.temp_dir.join(format!("{}.docx", doc_id));
@@ -171,6 +992,12 @@ impl DocxHandler {
             author: None,
             title: None,
             subject: None,
+            saved_at: None,
+            workspace: None,
+            owner: None,
+            activity: DocActivity::default(),
+            fidelity: None,
+            sha256: None,
         };
         
         self.documents.insert(doc_id.clone(), metadata);
@@ -179,10 +1006,29 @@ impl DocxHandler {
         Ok(doc_id)
     }
 
+    /// Read an opened document into ops so the editing tools work on it. The working copy
+    /// is rewritten from the ops, so anything the reader cannot represent (see the returned
+    /// report, also kept in the metadata) is gone from it; the source file is untouched.
+    pub fn make_editable(&mut self, doc_id: &str) -> Result<crate::docx_reader::Fidelity> {
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        if self.in_memory_ops.contains_key(doc_id) {
+            return Ok(metadata.fidelity.clone().unwrap_or_default());
+        }
+        let read = crate::docx_reader::read_docx(&metadata.path)?;
+        metadata.fidelity = Some(read.fidelity.clone());
+        self.in_memory_ops.insert(doc_id.to_string(), read.ops);
+        self.write_docx(doc_id)?;
+        info!("Read document {} into {} elements (lossless: {})", doc_id, read.fidelity.elements, read.fidelity.is_lossless());
+        Ok(read.fidelity)
+    }
+
     pub fn add_paragraph(&mut self, doc_id: &str, text: &str, style: Option<DocxStyle>) -> Result<()> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Paragraph { text: text.to_string(), style });
+        if let Some(style) = &style {
+            style.validate()?;
+        }
+        self.in_memory_ops.push(doc_id, DocxOp::Paragraph { text: text.to_string(), style })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added paragraph to document {}", doc_id);
         Ok(())
@@ -190,7 +1036,7 @@ impl DocxHandler {
 
     pub fn add_heading(&mut self, doc_id: &str, text: &str, level: usize) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         
         let heading_style = match level {
             1 => "Heading1",
@@ -202,8 +1048,7 @@ impl DocxHandler {
             _ => "Heading1",
         };
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Heading { text: text.to_string(), style: heading_style.to_string() });
+        self.in_memory_ops.push(doc_id, DocxOp::Heading { text: text.to_string(), style: heading_style.to_string() })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added heading level {} to document {}", level, doc_id);
         Ok(())
@@ -211,23 +1056,100 @@ impl DocxHandler {
 
     pub fn add_table(&mut self, doc_id: &str, table_data: TableData) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Table { data: table_data });
+        table_data.validate()?;
+        self.in_memory_ops.push(doc_id, DocxOp::Table { data: table_data })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added table to document {}", doc_id);
         Ok(())
     }
 
+    /// Import a worksheet range from an XLSX workbook as a table.
+    /// `sheet` defaults to the first sheet and `range` (A1 notation, e.g. "B2:E10")
+    /// defaults to the used range. When `header_row` is set the first row becomes the header.
+    pub fn add_table_from_xlsx(
+        &mut self,
+        doc_id: &str,
+        xlsx_path: &Path,
+        sheet: Option<&str>,
+        range: Option<&str>,
+        header_row: bool,
+    ) -> Result<usize> {
+        use calamine::{open_workbook_auto, Reader};
+
+        let _metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        self.ensure_modifiable(doc_id)?;
+
+        let mut workbook = open_workbook_auto(xlsx_path)
+            .with_context(|| format!("Failed to open workbook {:?}", xlsx_path))?;
+        let sheet_name = match sheet {
+            Some(s) => s.to_string(),
+            None => workbook.sheet_names().first().cloned()
+                .ok_or_else(|| anyhow::anyhow!("Workbook has no sheets: {:?}", xlsx_path))?,
+        };
+        let used = workbook.worksheet_range(&sheet_name)
+            .map_err(|e| anyhow::anyhow!("Failed to read sheet '{}': {}", sheet_name, e))?;
+        let cells = match range {
+            Some(r) => {
+                let (start, end) = parse_a1_range(r)?;
+                // Only the part of the range that has cells: `A1:XFD1048576` must not
+                // allocate the whole sheet grid
+                match (used.start(), used.end()) {
+                    (Some(first), Some(last)) => {
+                        let start = (start.0.max(first.0), start.1.max(first.1));
+                        let end = (end.0.min(last.0), end.1.min(last.1));
+                        if start.0 > end.0 || start.1 > end.1 {
+                            calamine::Range::empty()
+                        } else {
+                            used.range(start, end)
+                        }
+                    }
+                    _ => calamine::Range::empty(),
+                }
+            }
+            None => used,
+        };
+
+        let mut rows: Vec<Vec<String>> = cells.rows()
+            .map(|row| row.iter().map(|c| c.to_string()).collect())
+            .collect();
+        // Drop trailing rows that are entirely empty (common with formatted-but-blank ranges)
+        while rows.last().map(|r| r.iter().all(|c| c.trim().is_empty())).unwrap_or(false) {
+            rows.pop();
+        }
+        if rows.is_empty() {
+            anyhow::bail!("No cells found in sheet '{}'{}", sheet_name, range.map(|r| format!(" range {}", r)).unwrap_or_default());
+        }
+        // Header cells stay in `rows`; the first row is rendered with the TableHeader style
+        let headers = if header_row { rows.first().cloned() } else { None };
+        let row_count = rows.len() - usize::from(header_row);
+
+        let table = DocxOp::Table {
+            data: TableData {
+                rows,
+                headers,
+                border_style: Some("single".to_string()),
+                col_widths: None,
+                merges: None,
+                cell_shading: None,
+                ..Default::default()
+            },
+        };
+        self.in_memory_ops.push(doc_id, table)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        self.write_docx(doc_id)?;
+        info!("Imported {} rows from {:?} [{}] into document {}", row_count, xlsx_path, sheet_name, doc_id);
+        Ok(row_count)
+    }
+
     pub fn add_list(&mut self, doc_id: &str, items: Vec<String>, ordered: bool) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::List { items, ordered });
+        self.in_memory_ops.push(doc_id, DocxOp::List { items, ordered })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added {} list to document {}", if ordered { "ordered" } else { "unordered" }, doc_id);
         Ok(())
@@ -236,11 +1158,10 @@ impl DocxHandler {
     /// Add a single list item with an explicit indent level (0-based)
     pub fn add_list_item(&mut self, doc_id: &str, text: &str, level: usize, ordered: bool) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
 
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::ListItem { text: text.to_string(), level, ordered });
+        self.in_memory_ops.push(doc_id, DocxOp::ListItem { text: text.to_string(), level, ordered })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added list item (level {}) to document {}", level, doc_id);
         Ok(())
@@ -249,13 +1170,13 @@ impl DocxHandler {
     /// Add an image to the document
     pub fn add_image(&mut self, doc_id: &str, image: ImageData) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
 
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
         let width = image.width.unwrap_or(100);
         let height = image.height.unwrap_or(100);
-        ops.push(DocxOp::Image { data: image.data, width, height, alt_text: image.alt_text });
+        let image = DocxOp::Image { data: image.data.into(), width, height, alt_text: image.alt_text };
+        self.in_memory_ops.push(doc_id, image)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added image to document {}", doc_id);
         Ok(())
@@ -264,11 +1185,10 @@ impl DocxHandler {
     /// Add a hyperlink to the document
     pub fn add_hyperlink(&mut self, doc_id: &str, text: &str, url: &str) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
 
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Hyperlink { text: text.to_string(), url: url.to_string() });
+        self.in_memory_ops.push(doc_id, DocxOp::Hyperlink { text: text.to_string(), url: url.to_string() })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added hyperlink to document {}", doc_id);
         Ok(())
@@ -283,27 +1203,76 @@ impl DocxHandler {
         margins: Option<MarginsSpec>,
     ) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
 
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::SectionBreak {
+        if let Some(size) = page_size {
+            page_size_twips(size)?;
+        }
+        self.in_memory_ops.push(doc_id, DocxOp::SectionBreak {
             page_size: page_size.map(|s| s.to_string()),
             orientation: orientation.map(|s| s.to_string()),
             margins,
-        });
+        })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added section break to document {}", doc_id);
         Ok(())
     }
 
+    /// Page setup of the body section, no section break needed. Arguments left out keep
+    /// their current value (A4 portrait with 1in margins to begin with); `gutter` is in
+    /// inches and `mirror` switches to inside/outside margins for facing pages.
+    pub fn set_page_setup(
+        &mut self,
+        doc_id: &str,
+        size: Option<&str>,
+        orientation: Option<&str>,
+        margins: Option<MarginsSpec>,
+        gutter: Option<f32>,
+        mirror: Option<bool>,
+    ) -> Result<()> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut setup = ops.iter().rev().find_map(|op| match op {
+            DocxOp::PageSetup(setup) => Some(setup.clone()),
+            _ => None,
+        }).unwrap_or_default();
+        if let Some(size) = size {
+            (setup.width, setup.height) = page_size_twips(size)?;
+        }
+        if let Some(orientation) = orientation {
+            setup.landscape = match orientation.trim().to_ascii_lowercase().as_str() {
+                "portrait" => false,
+                "landscape" => true,
+                other => anyhow::bail!("Unknown orientation '{}' (use portrait or landscape)", other),
+            };
+        }
+        if let Some(m) = margins {
+            let current = &mut setup.margins;
+            for (value, new) in [(&mut current.top, m.top), (&mut current.bottom, m.bottom), (&mut current.left, m.left), (&mut current.right, m.right)] {
+                if new.is_some() { *value = new; }
+            }
+        }
+        for value in [setup.margins.top, setup.margins.bottom, setup.margins.left, setup.margins.right, gutter].into_iter().flatten() {
+            if !(0.0..=22.0).contains(&value) {
+                anyhow::bail!("Margins and gutter must be between 0 and 22 inches (got {})", value);
+            }
+        }
+        if gutter.is_some() { setup.gutter = gutter; }
+        if let Some(mirror) = mirror { setup.mirror = mirror; }
+        ops.retain(|op| !matches!(op, DocxOp::PageSetup(_)));
+        ops.push(DocxOp::PageSetup(setup));
+        self.write_docx(doc_id)?;
+        info!("Set page setup of document {}", doc_id);
+        Ok(())
+    }
+
     /// Insert a Table of Contents placeholder (post-processed into a TOC field when enabled)
     pub fn insert_toc(&mut self, doc_id: &str, from_level: usize, to_level: usize, right_align_dots: bool) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Toc { from_level, to_level, right_align_dots });
+        self.in_memory_ops.push(doc_id, DocxOp::Toc { from_level, to_level, right_align_dots })?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         Ok(())
     }
@@ -311,7 +1280,7 @@ impl DocxHandler {
     /// Insert a bookmark immediately after the first heading matching text (best-effort)
     pub fn insert_bookmark_after_heading(&mut self, doc_id: &str, heading_text: &str, name: &str) -> Result<bool> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         if let Some(pos) = ops.iter().position(|op| matches!(op, DocxOp::Heading { text: t, .. } if t == heading_text)) {
             ops.insert(pos + 1, DocxOp::BookmarkAfterHeading { heading_text: heading_text.to_string(), name: name.to_string() });
             self.write_docx(doc_id)?;
@@ -322,57 +1291,543 @@ impl DocxHandler {
 
     pub fn add_page_break(&mut self, doc_id: &str) -> Result<()> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::PageBreak);
+        self.in_memory_ops.push(doc_id, DocxOp::PageBreak)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         self.write_docx(doc_id)?;
         info!("Added page break to document {}", doc_id);
         Ok(())
     }
 
-    pub fn set_header(&mut self, doc_id: &str, text: &str) -> Result<()> {
-        let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
-        
+    /// Keep elements `start..=end` (element indices as in `get_outline`) on one page, such
+    /// as a heading with its first paragraph and a table: every paragraph of the group
+    /// gets keepLines and all but the last keepNext. Groups cannot overlap. Returns the
+    /// number of elements in the group.
+    pub fn group_keep_together(&mut self, doc_id: &str, start: usize, end: usize) -> Result<usize> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Header(text.to_string()));
+        let count = insert_keep_group(self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?, start, end)?;
         self.write_docx(doc_id)?;
-        info!("Set header for document {}", doc_id);
-        Ok(())
+        info!("Grouped elements {}..={} of document {} to keep together", start, end, doc_id);
+        Ok(count)
     }
 
-    pub fn set_footer(&mut self, doc_id: &str, text: &str) -> Result<()> {
-        let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
-        
-        self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        ops.push(DocxOp::Footer(text.to_string()));
-        self.write_docx(doc_id)?;
-        info!("Set footer for document {}", doc_id);
-        Ok(())
+    /// Add an empty paragraph with a bottom border, drawn across the text width
+    pub fn add_horizontal_rule(&mut self, doc_id: &str, style: Option<&str>, color: Option<&str>, size: Option<u32>) -> Result<()> {
+        let rule = DocxStyle {
+            borders: Some(ParagraphBorders {
+                bottom: Some(BorderSpec {
+                    style: style.unwrap_or("single").to_string(),
+                    color: color.map(|c| c.to_string()),
+                    size: Some(size.unwrap_or(6)),
+                    space: Some(1),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        self.add_paragraph(doc_id, "", Some(rule))
     }
 
-    /// Convenience: set simple page numbering text in header or footer
-    pub fn set_page_numbering(&mut self, doc_id: &str, location: &str, template: Option<&str>) -> Result<()> {
-        let text = template.unwrap_or("Page {PAGE} of {PAGES}");
-        match location {
-            "header" => self.set_header(doc_id, text),
-            "footer" => self.set_footer(doc_id, text),
-            _ => anyhow::bail!("invalid location: {}", location),
+    /// Add signature blocks for `parties`, one after the other: a caption, room to sign
+    /// above the signature line, then name, title and date lines and optionally an
+    /// initials line. With `anchors`, the signature, date and initials lines carry
+    /// e-signature anchor tags (`\s1\`, `\d1\`, `\i1\` for the first party by default)
+    /// written as white 1pt text, so the document can go straight to a signing service
+    /// that places its fields on anchor text. Returns the number of paragraphs added.
+    pub fn add_signature_block(&mut self, doc_id: &str, parties: &[SignatureParty], options: &SignatureBlockOptions) -> Result<usize> {
+        if parties.is_empty() {
+            anyhow::bail!("add_signature_block needs at least one party");
         }
-    }
-
-    /// Attempt to replace placeholder page numbering text in header with Word field codes (PAGE/NUMPAGES)
-    /// This is a best-effort, post-processing step that edits the zipped DOCX XML in-place by rebuilding the archive.
-    pub fn embed_page_number_fields(&self, doc_id: &str) -> Result<()> {
-        let metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
-        if !metadata.path.exists() {
-            anyhow::bail!("Document file missing: {:?}", metadata.path);
+        if let Some(i) = parties.iter().position(|p| p.name.trim().is_empty()) {
+            anyhow::bail!("Party {} has no name", i + 1);
+        }
+        let date = options.date.as_deref().unwrap_or("line");
+        if !matches!(date, "line" | "field" | "none") {
+            anyhow::bail!("Unknown date '{}' (use line, field or none)", date);
+        }
+        let format = options.anchor_format.as_deref().unwrap_or(r"\{kind}{n}\");
+        if options.anchors && !(format.contains("{kind}") && format.contains("{n}")) {
+            anyhow::bail!(r#"anchor_format needs the {{kind}} and {{n}} placeholders, as in "\{{kind}}{{n}}\""#);
+        }
+        self.ensure_modifiable(doc_id)?;
+
+        let mut anchors: Vec<String> = Vec::new();
+        let mut anchor = |kind: &str, n: usize| -> String {
+            if !options.anchors {
+                return String::new();
+            }
+            let tag = format.replace("{kind}", kind).replace("{n}", &n.to_string());
+            anchors.push(tag.clone());
+            tag
+        };
+        let line = |len: usize| "_".repeat(len);
+        let bold = DocxStyle { bold: Some(true), ..Default::default() };
+        let mut paragraphs: Vec<(String, Option<DocxStyle>)> = Vec::new();
+        for (i, party) in parties.iter().enumerate() {
+            let n = i + 1;
+            if i > 0 {
+                paragraphs.push((String::new(), None));
+            }
+            let caption = party.label.clone().unwrap_or_else(|| match &party.company {
+                Some(company) => format!("Signed for and on behalf of {}", company),
+                None => format!("Signed by {}", party.name),
+            });
+            paragraphs.push((caption, Some(bold.clone())));
+            paragraphs.push((String::new(), None));
+            paragraphs.push((format!("Signature: {}{}", anchor("s", n), line(30)), None));
+            paragraphs.push((format!("Name: {}", party.name), None));
+            if let Some(title) = &party.title {
+                paragraphs.push((format!("Title: {}", title), None));
+            }
+            match date {
+                "line" => paragraphs.push((format!("Date: {}{}", anchor("d", n), line(20)), None)),
+                "field" => paragraphs.push((format!(r#"Date: {}DATE \@ "d MMMM yyyy"}}"#, FIELD_TOKEN_OPEN), None)),
+                _ => {}
+            }
+            if options.initials {
+                paragraphs.push((format!("Initials: {}{}", anchor("i", n), line(12)), None));
+            }
+        }
+        let footer_slots = options.initial_each_page.then(|| {
+            (1..=parties.len()).map(|n| format!("{}{}", anchor("i", n), line(12))).collect::<Vec<_>>().join("   ")
+        });
+
+        let added = paragraphs.len();
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        ops.extend(paragraphs.into_iter().map(|(text, style)| DocxOp::Paragraph { text, style }));
+        if let Some(slots) = footer_slots {
+            let existing = ops.iter().rev().find_map(|op| match op { DocxOp::Footer(text) => Some(text.clone()), _ => None });
+            ops.push(DocxOp::Footer(match existing.filter(|t| !t.is_empty()) {
+                Some(text) => format!("{}\nInitials: {}", text, slots),
+                None => format!("Initials: {}", slots),
+            }));
+        }
+        if !anchors.is_empty() {
+            anchors.sort();
+            anchors.dedup();
+            let pattern = anchors.iter().map(|a| regex::escape(a)).collect::<Vec<_>>().join("|");
+            ops.push(DocxOp::InvisibleText { pattern });
+        }
+        self.write_docx(doc_id)?;
+        info!("Added signature block for {} parties to document {}", parties.len(), doc_id);
+        Ok(added)
+    }
+
+    pub fn set_header(&mut self, doc_id: &str, text: &str) -> Result<()> {
+        let _metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        
+        self.ensure_modifiable(doc_id)?;
+        self.in_memory_ops.push(doc_id, DocxOp::Header(text.to_string()))?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        self.write_docx(doc_id)?;
+        info!("Set header for document {}", doc_id);
+        Ok(())
+    }
+
+    pub fn set_footer(&mut self, doc_id: &str, text: &str) -> Result<()> {
+        let _metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        
+        self.ensure_modifiable(doc_id)?;
+        self.in_memory_ops.push(doc_id, DocxOp::Footer(text.to_string()))?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        self.write_docx(doc_id)?;
+        info!("Set footer for document {}", doc_id);
+        Ok(())
+    }
+
+    /// Set the header or footer text together with its layout, replacing both. Tabs in
+    /// `text` split it into left, center and right zones (`Company\tConfidential\tPage
+    /// {PAGE}`), aligned with tab stops at the middle and right edge of the text area, and
+    /// each line becomes a paragraph. The plain `set_header`/`set_footer` keep the layout
+    /// and only change the text.
+    pub fn set_header_footer(&mut self, doc_id: &str, location: &str, text: &str, layout: HeaderFooterLayout) -> Result<()> {
+        let footer = match location {
+            "header" => false,
+            "footer" => true,
+            _ => anyhow::bail!("invalid location: {} (use header or footer)", location),
+        };
+        if let Some(alignment) = layout.alignment.as_deref() {
+            if alignment_type(alignment).is_none() {
+                anyhow::bail!("Unknown alignment '{}' (use left, center, right or justify)", alignment);
+            }
+        }
+        logo_zone(layout.logo_position.as_deref())?;
+        if let Some(table) = &layout.table {
+            if table.cells.is_empty() || table.cells.len() > 6 {
+                anyhow::bail!("A header/footer table needs 1 to 6 cells (got {})", table.cells.len());
+            }
+            if let Some(widths) = &table.col_widths {
+                if widths.len() != table.cells.len() || widths.contains(&0) {
+                    anyhow::bail!("col_widths needs one non-zero width per cell");
+                }
+            }
+            for alignment in table.cells.iter().filter_map(|c| c.alignment.as_deref()) {
+                if alignment_type(alignment).is_none() {
+                    anyhow::bail!("Unknown cell alignment '{}' (use left, center, right or justify)", alignment);
+                }
+            }
+        }
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        ops.retain(|op| !matches!(op, DocxOp::HeaderLayout { footer: f, .. } if *f == footer));
+        if layout.alignment.is_some() || layout.logo.is_some() || layout.table.is_some() {
+            ops.push(DocxOp::HeaderLayout { footer, layout });
+        }
+        if footer { self.set_footer(doc_id, text) } else { self.set_header(doc_id, text) }
+    }
+
+    /// Put a watermark behind the text of every section, or of one section only
+    /// (`section` counts from 0, each section break starting the next one). A section's
+    /// own watermark takes the place of the document-wide one there, so "DRAFT" can mark
+    /// just the appendices. Text is drawn as WordArt; pictures may be PNG, JPEG or SVG.
+    pub fn add_watermark(&mut self, doc_id: &str, watermark: Watermark) -> Result<()> {
+        match &watermark.content {
+            WatermarkContent::Text { text, color, opacity, .. } => {
+                if text.trim().is_empty() {
+                    anyhow::bail!("Watermark text is empty");
+                }
+                if let Some(color) = color {
+                    check_hex_color(color)?;
+                }
+                if let Some(opacity) = opacity {
+                    if !(0.0..=1.0).contains(opacity) {
+                        anyhow::bail!("opacity must be between 0 and 1 (got {})", opacity);
+                    }
+                }
+            }
+            WatermarkContent::Image { data, width, .. } => {
+                watermark_image_info(data)?;
+                if let Some(width) = width {
+                    if !(*width > 0.0 && *width <= 22.0) {
+                        anyhow::bail!("Watermark width must be between 0 and 22 inches (got {})", width);
+                    }
+                }
+            }
+        }
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        if let Some(section) = watermark.section {
+            let sections = ops.iter().filter(|op| matches!(op, DocxOp::SectionBreak { .. })).count() + 1;
+            if section >= sections {
+                anyhow::bail!(
+                    "Section {} does not exist: the document has {} section(s), numbered from 0 (add_section_break starts a new one)",
+                    section, sections
+                );
+            }
+        }
+        let section = watermark.section;
+        ops.retain(|op| !matches!(op, DocxOp::Watermark(w) if w.section == section));
+        ops.push(DocxOp::Watermark(watermark));
+        self.write_docx(doc_id)?;
+        match section {
+            Some(section) => info!("Added watermark to section {} of document {}", section, doc_id),
+            None => info!("Added watermark to document {}", doc_id),
+        }
+        Ok(())
+    }
+
+    /// Remove the watermark of one section, or the document-wide one when `section` is
+    /// None. Returns false when there was none.
+    pub fn remove_watermark(&mut self, doc_id: &str, section: Option<usize>) -> Result<bool> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let before = ops.len();
+        ops.retain(|op| !matches!(op, DocxOp::Watermark(w) if w.section == section));
+        if ops.len() == before {
+            return Ok(false);
+        }
+        self.write_docx(doc_id)?;
+        info!("Removed watermark from document {}", doc_id);
+        Ok(true)
+    }
+
+    /// Set the document default font (used by every run without its own font). `size` is
+    /// in points.
+    pub fn set_default_font(&mut self, doc_id: &str, family: Option<&str>, size: Option<f32>) -> Result<()> {
+        if let Some(size) = size {
+            if !(1.0..=1638.0).contains(&size) {
+                anyhow::bail!("Font size must be between 1 and 1638 points (got {})", size);
+            }
+        }
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        ops.retain(|op| !matches!(op, DocxOp::DefaultFont { .. }));
+        ops.push(DocxOp::DefaultFont {
+            family: family.map(String::from),
+            size: size.map(|pt| (pt * 2.0).round() as usize),
+        });
+        self.write_docx(doc_id)?;
+        info!("Set default font of document {}", doc_id);
+        Ok(())
+    }
+
+    /// Set the language Word proofs text in and/or exclude text from spelling and
+    /// grammar checks. Document scope sets the style defaults (and replaces earlier
+    /// document settings); element and tag scopes mark their runs, overriding the
+    /// defaults. Returns the number of regions marked (1 for the document).
+    pub fn set_proofing_language(&mut self, doc_id: &str, scope: ProofingScope, proofing: Proofing) -> Result<usize> {
+        if let Some(language) = &proofing.language {
+            static LANGUAGE_TAG: once_cell::sync::Lazy<regex::Regex> =
+                once_cell::sync::Lazy::new(|| regex::Regex::new(r"^[A-Za-z]{2,3}(?:-[A-Za-z0-9]{2,8})*$").unwrap());
+            if !LANGUAGE_TAG.is_match(language) {
+                anyhow::bail!("'{}' is not a language tag like en-GB or de-DE", language);
+            }
+        }
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let ranges: Vec<(usize, usize)> = match &scope {
+            ProofingScope::Document => {
+                ops.retain(|op| !matches!(op, DocxOp::DefaultProofing(_)));
+                if proofing != Proofing::default() {
+                    ops.push(DocxOp::DefaultProofing(proofing));
+                }
+                self.write_docx(doc_id)?;
+                info!("Set document proofing of {}", doc_id);
+                return Ok(1);
+            }
+            _ if proofing == Proofing::default() => anyhow::bail!("Give a language, no_proof or both"),
+            ProofingScope::Elements { start, end } => {
+                if start > end || *end >= ops.len() {
+                    anyhow::bail!("Invalid element range {}..={} (document has {} elements)", start, end, ops.len());
+                }
+                vec![(*start, *end)]
+            }
+            ProofingScope::Tag(tag) => {
+                let mut open = Vec::new();
+                let mut ranges = Vec::new();
+                for (i, op) in ops.iter().enumerate() {
+                    match op {
+                        DocxOp::TagStart(t) if t == tag => open.push(i),
+                        DocxOp::TagEnd(t) if t == tag => if let Some(start) = open.pop() { ranges.push((start, i)) },
+                        _ => {}
+                    }
+                }
+                if ranges.is_empty() {
+                    anyhow::bail!("No content is tagged '{}' (see list_tagged_content)", tag);
+                }
+                ranges
+            }
+        };
+        // From the back so earlier indices stay valid
+        let mut sorted = ranges.clone();
+        sorted.sort_by_key(|r| std::cmp::Reverse(r.0));
+        for (start, end) in sorted {
+            ops.insert(end + 1, DocxOp::ProofingEnd);
+            ops.insert(start, DocxOp::ProofingStart(proofing.clone()));
+        }
+        self.write_docx(doc_id)?;
+        info!("Set proofing on {} region(s) of document {}", ranges.len(), doc_id);
+        Ok(ranges.len())
+    }
+
+    /// Stamp a letterhead profile onto a document: page size and margins, default font,
+    /// a header with the logo and the address block (right-aligned, one line each) and
+    /// the footer text. Parts the profile leaves out are not touched.
+    pub fn apply_letterhead(&mut self, doc_id: &str, letterhead: &crate::letterhead::Letterhead) -> Result<()> {
+        let profile = &letterhead.profile;
+        self.ensure_modifiable(doc_id)?;
+        // Validate everything first so a bad profile leaves the document unchanged
+        if let Some(size) = profile.page_size.as_deref() {
+            page_size_twips(size)?;
+        }
+        logo_zone(profile.logo_position.as_deref())?;
+        if let Some(alignment) = profile.footer_alignment.as_deref() {
+            alignment_type(alignment).ok_or_else(|| anyhow::anyhow!("Unknown footer_alignment '{}'", alignment))?;
+        }
+
+        if profile.page_size.is_some() || profile.margins.is_some() {
+            self.set_page_setup(doc_id, profile.page_size.as_deref(), None, profile.margins.clone(), None, None)?;
+        }
+        if profile.font.is_some() || profile.font_size.is_some() {
+            self.set_default_font(doc_id, profile.font.as_deref(), profile.font_size)?;
+        }
+        if letterhead.logo.is_some() || !profile.address.is_empty() {
+            let text = profile.address.iter().map(|line| format!("\t\t{}", line)).collect::<Vec<_>>().join("\n");
+            let layout = HeaderFooterLayout {
+                alignment: None,
+                logo: letterhead.logo.clone().map(|data| ImageData {
+                    data,
+                    width: profile.logo_width,
+                    height: profile.logo_height,
+                    alt_text: Some(format!("{} logo", letterhead.name)),
+                }),
+                logo_position: profile.logo_position.clone(),
+                table: None,
+            };
+            self.set_header_footer(doc_id, "header", &text, layout)?;
+        }
+        if let Some(footer) = profile.footer.as_deref() {
+            let layout = HeaderFooterLayout {
+                alignment: Some(profile.footer_alignment.clone().unwrap_or_else(|| "center".into())),
+                ..Default::default()
+            };
+            self.set_header_footer(doc_id, "footer", footer, layout)?;
+        }
+        info!("Applied letterhead {} to document {}", letterhead.name, doc_id);
+        Ok(())
+    }
+
+    /// Apply a brand profile in one pass (see `rebrand`): fonts, colors, logo, term
+    /// renames and the header and footer text. The mapping is checked before anything
+    /// changes. Returns how much of each kind was changed.
+    pub fn rebrand(&mut self, doc_id: &str, mapping: &crate::rebrand::BrandMapping) -> Result<serde_json::Value> {
+        use crate::rebrand::{swap_colors, swap_fonts, swap_logos};
+        self.ensure_modifiable(doc_id)?;
+        let colors = mapping.color_pairs()?;
+        let logo = mapping.logo()?;
+        let term_options = ReplaceOptions {
+            whole_word: true,
+            preserve_case: true,
+            include: vec![ReplaceTarget::HyperlinkUrls, ReplaceTarget::AltText, ReplaceTarget::BookmarkNames, ReplaceTarget::Properties],
+            ..Default::default()
+        };
+
+        let ops = self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let mut fonts_changed = swap_fonts(ops, &mapping.fonts);
+        if let Some(font) = mapping.default_font.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+            let size = ops.iter().rev().find_map(|op| match op {
+                DocxOp::DefaultFont { size, .. } => Some(*size),
+                _ => None,
+            }).flatten();
+            ops.retain(|op| !matches!(op, DocxOp::DefaultFont { .. }));
+            ops.push(DocxOp::DefaultFont { family: Some(font.to_string()), size });
+            fonts_changed += 1;
+        }
+        let colors_changed = swap_colors(ops, &colors);
+        let logos_replaced = logo.map(|logo| swap_logos(ops, &logo)).unwrap_or(0);
+        let mut terms_replaced = 0;
+        for (old, new) in mapping.ordered_terms() {
+            terms_replaced += replace_in_ops(ops, metadata, old, new, &term_options)?.len();
+        }
+        // Set after the renames so the new text is written as given
+        if let Some(header) = &mapping.header {
+            ops.push(DocxOp::Header(header.clone()));
+        }
+        if let Some(footer) = &mapping.footer {
+            ops.push(DocxOp::Footer(footer.clone()));
+        }
+        self.write_docx(doc_id)?;
+        info!("Rebranded document {}", doc_id);
+        Ok(serde_json::json!({
+            "fonts_changed": fonts_changed,
+            "colors_changed": colors_changed,
+            "logos_replaced": logos_replaced,
+            "terms_replaced": terms_replaced,
+            "header_set": mapping.header.is_some(),
+            "footer_set": mapping.footer.is_some(),
+        }))
+    }
+
+    /// Convenience: set simple page numbering text in header or footer
+    pub fn set_page_numbering(&mut self, doc_id: &str, location: &str, template: Option<&str>) -> Result<()> {
+        let text = template.unwrap_or("Page {PAGE} of {PAGES}");
+        match location {
+            "header" => self.set_header(doc_id, text),
+            "footer" => self.set_footer(doc_id, text),
+            _ => anyhow::bail!("invalid location: {}", location),
+        }
+    }
+
+    /// Insert a Word field (DATE, TIME, FILENAME, AUTHOR, TITLE, STYLEREF, PAGE, NUMPAGES) as its
+    /// own body paragraph or appended to the header/footer text. Fields are stored as
+    /// `{FIELD:<instruction>}` tokens and written as field codes that Word refreshes on open.
+    /// `argument` is the date/time picture for DATE/TIME and the style name for STYLEREF.
+    pub fn insert_field(
+        &mut self,
+        doc_id: &str,
+        field: &str,
+        argument: Option<&str>,
+        location: &str,
+        text_before: Option<&str>,
+    ) -> Result<String> {
+        let name = field.trim().to_ascii_uppercase();
+        let instr = match (name.as_str(), argument.filter(|a| !a.trim().is_empty())) {
+            ("DATE" | "TIME", Some(picture)) => format!(r#"{} \@ "{}""#, name, picture.replace('"', "")),
+            ("STYLEREF", Some(style)) => format!(r#"STYLEREF "{}""#, style.replace('"', "")),
+            ("STYLEREF", None) => anyhow::bail!("STYLEREF needs the style name as its argument (e.g. \"Heading 1\")"),
+            (n, _) if FIELD_NAMES.contains(&n) => name.clone(),
+            _ => anyhow::bail!("unsupported field '{}' (use one of {})", field, FIELD_NAMES.join(", ")),
+        };
+        if instr.contains(['{', '}']) {
+            anyhow::bail!("field arguments cannot contain braces");
+        }
+        let token = format!("{}{}{}", text_before.unwrap_or(""), FIELD_TOKEN_OPEN, instr) + "}";
+        match location {
+            "body" => self.add_paragraph(doc_id, &token, None)?,
+            "header" | "footer" => {
+                self.ensure_modifiable(doc_id)?;
+                let existing = self.in_memory_ops.get(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?.iter().rev().find_map(|op| match (op, location) {
+                    (DocxOp::Header(text), "header") | (DocxOp::Footer(text), "footer") => Some(text.clone()),
+                    _ => None,
+                }).unwrap_or_default();
+                if location == "header" {
+                    self.set_header(doc_id, &(existing + &token))?;
+                } else {
+                    self.set_footer(doc_id, &(existing + &token))?;
+                }
+            }
+            other => anyhow::bail!("invalid location: {} (use body, header or footer)", other),
+        }
+        Ok(instr)
+    }
+
+    /// Add a table of figures (`kind` "figures") or of tables ("tables") under a "List of
+    /// Figures"/"List of Tables" title. By default it is a TOC field over the "Figure" or
+    /// "Table" caption sequence, which Word fills in with page numbers on open; with
+    /// `static_list` it is one paragraph per caption found now ("Figure 2: ..."
+    /// paragraphs; image alt texts when a document has no figure captions). Goes after the
+    /// table of contents when there is one, else at the end. Returns the number of entries
+    /// (0 for the field, whose entries Word builds).
+    pub fn add_table_of_figures(&mut self, doc_id: &str, kind: &str, static_list: bool) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let (label, title) = match kind.trim().to_ascii_lowercase().as_str() {
+            "figures" | "figure" => ("Figure", "List of Figures"),
+            "tables" | "table" => ("Table", "List of Tables"),
+            other => anyhow::bail!("Unknown kind '{}' (use figures or tables)", other),
+        };
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut entries: Vec<DocxOp> = Vec::new();
+        if static_list {
+            let captions = caption_entries(ops, label);
+            let captions = if captions.is_empty() && label == "Figure" {
+                ops.iter()
+                    .filter_map(|op| match op { DocxOp::Image { alt_text, .. } => Some(alt_text.clone().unwrap_or_default()), _ => None })
+                    .enumerate()
+                    .map(|(n, alt)| if alt.trim().is_empty() { format!("Figure {}", n + 1) } else { format!("Figure {}: {}", n + 1, alt.trim()) })
+                    .collect()
+            } else {
+                captions
+            };
+            if captions.is_empty() {
+                anyhow::bail!("No {} captions found (captions are paragraphs starting \"{} 1\")", label.to_lowercase(), label);
+            }
+            entries.extend(captions.into_iter().map(|text| DocxOp::Paragraph { text, style: None }));
+        } else {
+            entries.push(DocxOp::Paragraph {
+                text: format!("{}TOC \\h \\z \\c \"{}\"}}", FIELD_TOKEN_OPEN, label),
+                style: None,
+            });
+        }
+        let count = if static_list { entries.len() } else { 0 };
+        entries.insert(0, DocxOp::Paragraph { text: title.to_string(), style: Some(DocxStyle { bold: Some(true), ..Default::default() }) });
+        let position = ops.iter().rposition(|op| matches!(op, DocxOp::Toc { .. })).map(|i| i + 1).unwrap_or(ops.len());
+        ops.splice(position..position, entries);
+        self.write_docx(doc_id)?;
+        info!("Added {} to document {}", title, doc_id);
+        Ok(count)
+    }
+
+    /// Attempt to replace placeholder page numbering text in header with Word field codes (PAGE/NUMPAGES)
+    /// This is a best-effort, post-processing step that edits the zipped DOCX XML in-place by rebuilding the archive.
+    pub fn embed_page_number_fields(&self, doc_id: &str) -> Result<()> {
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        if !metadata.path.exists() {
+            anyhow::bail!("Document file missing: {:?}", metadata.path);
         }
 
         let src_file = std::fs::File::open(&metadata.path)?;
@@ -382,7 +1837,7 @@ impl DocxHandler {
         let temp_path = metadata.path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
 
         let mut did_replace = false;
         for i in 0..archive.len() {
@@ -440,7 +1895,7 @@ impl DocxHandler {
 
     pub fn find_and_replace(&mut self, doc_id: &str, _find_text: &str, _replace_text: &str) -> Result<usize> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         
         // Note: This is a simplified implementation
         // Real implementation would need to parse the DOCX XML structure
@@ -461,135 +1916,1173 @@ impl DocxHandler {
         whole_word: bool,
         use_regex: bool,
     ) -> Result<usize> {
-        use regex::RegexBuilder;
+        let options = ReplaceOptions { case_sensitive, whole_word, use_regex, ..Default::default() };
+        self.find_and_replace_with(doc_id, pattern, replacement, &options)
+    }
 
+    /// `find_and_replace_advanced` limited to `options.scope`
+    pub fn find_and_replace_with(
+        &mut self,
+        doc_id: &str,
+        pattern: &str,
+        replacement: &str,
+        options: &ReplaceOptions,
+    ) -> Result<usize> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let replacements = replace_in_ops(ops, metadata, pattern, replacement, options)?;
 
-        // Build regex
-        let pattern = if use_regex { pattern.to_string() } else { regex::escape(pattern) };
-        let pattern = if whole_word { format!("\\b{}\\b", pattern) } else { pattern };
-        let re = RegexBuilder::new(&pattern)
-            .case_insensitive(!case_sensitive)
-            .build()
-            .with_context(|| "Invalid regex pattern")?;
+        // Persist changes
+        self.write_docx(doc_id)?;
+        Ok(replacements.len())
+    }
+
+    /// The replacements `find_and_replace_with` would make, each with the text around
+    /// it; the document is left as it is
+    pub fn preview_replacements(
+        &self,
+        doc_id: &str,
+        pattern: &str,
+        replacement: &str,
+        options: &ReplaceOptions,
+    ) -> Result<Vec<Replacement>> {
+        self.ensure_modifiable(doc_id)?;
+        let mut ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?
+            .into_owned();
+        let mut metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?
+            .clone();
+        replace_in_ops(&mut ops, &mut metadata, pattern, replacement, options)
+    }
+
+    /// Apply a bulk text transform to the elements in `scope`: "paragraphs", "headings",
+    /// "tables" (cell text), "lists", "code" (paragraphs in a monospace font) or "all".
+    /// Returns the number of elements whose text changed.
+    pub fn transform_text(&mut self, doc_id: &str, scope: &str, transform: crate::text_transform::Transform) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let (paragraphs, code_only, headings, tables, lists) = match scope {
+            "paragraphs" => (true, false, false, false, false),
+            "code" => (true, true, false, false, false),
+            "headings" => (false, false, true, false, false),
+            "tables" | "table_cells" => (false, false, false, true, false),
+            "lists" => (false, false, false, false, true),
+            "all" => (true, false, true, true, true),
+            other => anyhow::bail!("invalid scope: {} (use paragraphs, headings, tables, lists, code or all)", other),
+        };
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut changed = 0usize;
+        let mut apply = |text: &mut String| {
+            let new_text = transform.apply(text);
+            if new_text != *text {
+                *text = new_text;
+                changed += 1;
+            }
+        };
+        for op in ops.iter_mut() {
+            match op {
+                DocxOp::Paragraph { text, style } if paragraphs => {
+                    let monospace = style.as_ref().and_then(|s| s.font_family.as_deref()).map(is_monospace_font).unwrap_or(false);
+                    if !code_only || monospace { apply(text); }
+                }
+                DocxOp::Heading { text, .. } if headings => apply(text),
+                DocxOp::Table { data } if tables => data.rows.iter_mut().flatten().for_each(&mut apply),
+                DocxOp::List { items, .. } if lists => items.iter_mut().for_each(&mut apply),
+                DocxOp::ListItem { text, .. } if lists => apply(text),
+                _ => {}
+            }
+        }
+        if changed > 0 { self.write_docx(doc_id)?; }
+        info!("Transformed {} elements of document {}", changed, doc_id);
+        Ok(changed)
+    }
+
+    /// XLIFF 1.2 with one translation unit per text-bearing element (see `xliff`)
+    pub fn export_xliff(&self, doc_id: &str, source_language: &str, target_language: Option<&str>) -> Result<String> {
+        let elements = self.text_elements(doc_id)?;
+        let original = self.documents.get(doc_id)
+            .and_then(|m| m.title.clone())
+            .unwrap_or_else(|| doc_id.to_string());
+        Ok(crate::xliff::export(&original, source_language, target_language, &elements))
+    }
 
-        let mut total_replacements = 0usize;
+    /// Apply the translated units of an XLIFF file exported by `export_xliff`. Units whose
+    /// source no longer matches the element text are skipped as stale so a translation
+    /// never overwrites content edited after the export.
+    pub fn import_xliff(&mut self, doc_id: &str, xml: &str) -> Result<serde_json::Value> {
+        use once_cell::sync::Lazy;
+        static ID: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"^e(\d+)(?:\.i(\d+)|\.r(\d+)c(\d+))?$").unwrap());
 
-        let mut replace_text = |text: &str| -> (String, usize) {
-            let mut count = 0usize;
-            let result = re.replace_all(text, |_: &regex::Captures| {
-                count += 1;
-                replacement.to_string()
+        self.ensure_modifiable(doc_id)?;
+        let units = crate::xliff::parse(xml)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (mut applied, mut untranslated) = (0usize, 0usize);
+        let (mut stale, mut unknown) = (Vec::new(), Vec::new());
+        for unit in units {
+            let Some(target) = unit.target else { untranslated += 1; continue };
+            let reference = ID.captures(&unit.id).and_then(|caps| {
+                let num = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<usize>().ok());
+                Some((num(1)?, num(2), num(3).zip(num(4))))
             });
-            (result.into_owned(), count)
+            let slot = match reference {
+                Some((index, item, cell)) => element_text_mut(ops, index, item, cell),
+                None => None,
+            };
+            match slot {
+                None => unknown.push(unit.id),
+                Some(text) if *text != unit.source => stale.push(unit.id),
+                Some(text) => {
+                    *text = target;
+                    applied += 1;
+                }
+            }
+        }
+        if applied > 0 { self.write_docx(doc_id)?; }
+        info!("Imported {} translated units into document {}", applied, doc_id);
+        Ok(serde_json::json!({
+            "applied": applied,
+            "untranslated": untranslated,
+            "stale": stale,
+            "unknown": unknown,
+        }))
+    }
+
+    /// Fill `{{…}}` template tags in paragraphs, headings, lists, table cells, headers and
+    /// footers from `data` (see `templating`). A paragraph holding only `{{#each}}`,
+    /// `{{#if}}` or `{{#unless}}` opens a block that repeats or keeps the elements up to
+    /// the matching closing paragraph; a table row whose first cell starts with
+    /// `{{#each list}}` and whose last cell ends with `{{/each}}` is repeated per item.
+    /// Format helpers (`{{currency total}}`, `{{date due "long"}}`) use `formatting`.
+    pub fn render_template(
+        &mut self,
+        doc_id: &str,
+        data: &serde_json::Value,
+        formatting: &crate::locale::Formatting,
+    ) -> Result<serde_json::Value> {
+        use crate::templating::Context as TemplateContext;
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut context = TemplateContext::new(data).with_formatting(formatting.clone());
+        let rendered = expand_template_ops(&ops, &mut context)?;
+        let (before, after) = (ops.len(), rendered.len());
+        self.in_memory_ops.insert(doc_id.to_string(), rendered);
+        self.write_docx(doc_id)?;
+        info!("Rendered template for document {}", doc_id);
+        Ok(serde_json::json!({
+            "elements_before": before,
+            "elements_after": after,
+            "missing": context.missing(),
+        }))
+    }
+
+    /// Mark elements `start..=end` (element indices as in `text_elements`) as belonging
+    /// to `tag`. Regions may nest; the markers are written as bookmarks named `tag_<tag>_<n>`.
+    pub fn tag_content(&mut self, doc_id: &str, tag: &str, start: usize, end: usize) -> Result<()> {
+        self.ensure_modifiable(doc_id)?;
+        if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            anyhow::bail!("Tag '{}' must be letters, digits, '_' or '-'", tag);
+        }
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        if start > end || end >= ops.len() {
+            anyhow::bail!("Invalid element range {}..={} (document has {} elements)", start, end, ops.len());
+        }
+        ops.insert(end + 1, DocxOp::TagEnd(tag.to_string()));
+        ops.insert(start, DocxOp::TagStart(tag.to_string()));
+        self.write_docx(doc_id)?;
+        info!("Tagged elements {}..={} of document {} as {}", start, end, doc_id, tag);
+        Ok(())
+    }
+
+    /// Tagged regions in document order: tag and the element range they cover
+    pub fn list_tagged_content(&self, doc_id: &str) -> Result<Vec<serde_json::Value>> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut open: Vec<(&str, usize)> = Vec::new();
+        let mut regions = Vec::new();
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                DocxOp::TagStart(tag) => open.push((tag.as_str(), i)),
+                DocxOp::TagEnd(tag) => if let Some(pos) = open.iter().rposition(|(t, _)| *t == tag.as_str()) {
+                    let (_, start) = open.remove(pos);
+                    regions.push((start, serde_json::json!({"tag": tag, "start": start + 1, "end": i.saturating_sub(1)})));
+                },
+                _ => {}
+            }
+        }
+        regions.sort_by_key(|(start, _)| *start);
+        Ok(regions.into_iter().map(|(_, region)| region).collect())
+    }
+
+    /// Remove every region tagged with one of `tags` (e.g. "internal" for a customer copy)
+    pub fn strip_tagged_content(&mut self, doc_id: &str, tags: &[String]) -> Result<usize> {
+        self.filter_tagged(doc_id, |tag| tags.iter().any(|t| t == tag))
+    }
+
+    /// Remove every tagged region whose tag is not in `tags`; untagged content is kept
+    pub fn keep_only_tagged(&mut self, doc_id: &str, tags: &[String]) -> Result<usize> {
+        self.filter_tagged(doc_id, |tag| !tags.iter().any(|t| t == tag))
+    }
+
+    fn filter_tagged(&mut self, doc_id: &str, drop: impl Fn(&str) -> bool) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (kept, removed) = drop_tagged_ops(&ops, drop);
+        self.in_memory_ops.insert(doc_id.to_string(), kept);
+        self.write_docx(doc_id)?;
+        info!("Removed {} tagged elements from document {}", removed, doc_id);
+        Ok(removed)
+    }
+
+    /// Flatten the document into text-bearing elements with stable references.
+    /// Opened documents (no ops) fall back to one entry per XML paragraph.
+    pub fn text_elements(&self, doc_id: &str) -> Result<Vec<ElementText>> {
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let ops = match self.in_memory_ops.get(doc_id)? {
+            Some(ops) => ops,
+            None => {
+                use crate::pure_converter::PureRustConverter;
+                let paragraphs = PureRustConverter::new().extract_paragraphs_from_docx(&metadata.path)
+                    .with_context(|| format!("Failed to read paragraphs from document {}", doc_id))?;
+                return Ok(paragraphs.into_iter().enumerate().map(|(i, text)| ElementText {
+                    element_index: i,
+                    kind: "paragraph".into(),
+                    range_id: Some(RangeId::Paragraph { index: i }),
+                    item: None,
+                    text,
+                }).collect());
+            }
         };
 
-        for op in ops.iter_mut() {
+        let mut elements = Vec::new();
+        let (mut para_idx, mut heading_idx, mut table_idx) = (0usize, 0usize, 0usize);
+        let mut push = |element_index: usize, kind: &str, range_id: Option<RangeId>, item: Option<usize>, text: &str| {
+            elements.push(ElementText { element_index, kind: kind.to_string(), range_id, item, text: text.to_string() });
+        };
+        for (i, op) in ops.iter().enumerate() {
             match op {
                 DocxOp::Paragraph { text, .. } => {
-                    let (new_text, cnt) = replace_text(text);
-                    if cnt > 0 { *text = new_text; total_replacements += cnt; }
+                    push(i, "paragraph", Some(RangeId::Paragraph { index: para_idx }), None, text);
+                    para_idx += 1;
                 }
                 DocxOp::Heading { text, .. } => {
-                    let (new_text, cnt) = replace_text(text);
-                    if cnt > 0 { *text = new_text; total_replacements += cnt; }
+                    push(i, "heading", Some(RangeId::Heading { index: heading_idx }), None, text);
+                    heading_idx += 1;
                 }
                 DocxOp::List { items, .. } => {
-                    for item in items.iter_mut() {
-                        let (new_text, cnt) = replace_text(item);
-                        if cnt > 0 { *item = new_text; total_replacements += cnt; }
+                    for (n, item) in items.iter().enumerate() {
+                        push(i, "list_item", None, Some(n), item);
                     }
                 }
-                DocxOp::ListItem { text, .. } => {
-                    let (new_text, cnt) = replace_text(text);
-                    if cnt > 0 { *text = new_text; total_replacements += cnt; }
-                }
+                DocxOp::ListItem { text, .. } => push(i, "list_item", None, None, text),
                 DocxOp::Table { data } => {
-                    for row in data.rows.iter_mut() {
-                        for cell in row.iter_mut() {
-                            let (new_text, cnt) = replace_text(cell);
-                            if cnt > 0 { *cell = new_text; total_replacements += cnt; }
+                    // Header cells are the first entry of `rows`, so they are covered below
+                    for (r, row) in data.rows.iter().enumerate() {
+                        for (c, cell) in row.iter().enumerate() {
+                            push(i, "table_cell", Some(RangeId::TableCell { table_index: table_idx, row: r, col: c }), None, cell);
                         }
                     }
+                    table_idx += 1;
                 }
-                DocxOp::Header(text) | DocxOp::Footer(text) => {
-                    let (new_text, cnt) = replace_text(text);
-                    if cnt > 0 { *text = new_text; total_replacements += cnt; }
-                }
-                DocxOp::Image { .. } | DocxOp::Hyperlink { .. } => {}
-                DocxOp::PageBreak => {}
-                DocxOp::SectionBreak { .. } => {}
-                DocxOp::Toc { .. } => {}
-                DocxOp::BookmarkAfterHeading { .. } => {}
+                DocxOp::Header(text) => push(i, "header", None, None, text),
+                DocxOp::Footer(text) => push(i, "footer", None, None, text),
+                DocxOp::Hyperlink { text, .. } => push(i, "hyperlink", None, None, text),
+                DocxOp::Image { .. } | DocxOp::PageBreak | DocxOp::SectionBreak { .. } => {}
+                DocxOp::Toc { .. } | DocxOp::BookmarkAfterHeading { .. } | DocxOp::Highlight { .. } | DocxOp::InvisibleText { .. } | DocxOp::EmbedFont { .. }
+                | DocxOp::HeadingNumbering { .. } | DocxOp::PageSetup(_)
+                | DocxOp::HeaderLayout { .. } | DocxOp::DefaultFont { .. } => {}
+                DocxOp::TagStart(_) | DocxOp::TagEnd(_) => {}
+                DocxOp::DefaultProofing(_) | DocxOp::ProofingStart(_) | DocxOp::ProofingEnd => {}
+                DocxOp::KeepTogetherStart | DocxOp::KeepTogetherEnd => {}
+                DocxOp::Watermark(_) => {}
             }
         }
-
-        // Persist changes
-        self.write_docx(doc_id)?;
-        Ok(total_replacements)
+        Ok(elements)
     }
 
-    /// Analyze document structure using in-memory ops (if available)
-    pub fn analyze_structure(&self, doc_id: &str) -> Result<serde_json::Value> {
-        let ops = match self.in_memory_ops.get(doc_id) {
-            Some(ops) => ops,
-            None => {
-                // Fallback to text-based outline if ops not available
-                let text = self.extract_text(doc_id).unwrap_or_default();
-                let mut outline = Vec::new();
-                for line in text.lines() {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() { continue; }
-                    if trimmed.len() < 100 && trimmed.chars().any(|c| c.is_uppercase()) {
-                        let level = if trimmed.chars().all(|c| c.is_uppercase() || c.is_whitespace()) { 1 } else { 2 };
-                        outline.push(serde_json::json!({"type":"heading","text":trimmed,"level":level}));
-                    }
-                }
-                return Ok(serde_json::json!({
-                    "has_ops": false,
-                    "outline": outline,
-                    "lists": [],
-                    "tables": [],
-                    "images": [],
-                    "links": [],
-                    "styles": {}
-                }));
-            }
-        };
-
+    /// Search every text-bearing element and report matches with element references.
+    /// Offsets are character positions within the element text.
+    pub fn search_elements(
+        &self,
+        doc_id: &str,
+        pattern: &str,
+        case_sensitive: bool,
+        whole_word: bool,
+        use_regex: bool,
+    ) -> Result<Vec<serde_json::Value>> {
+        use regex::RegexBuilder;
+
+        let pattern = if use_regex { pattern.to_string() } else { regex::escape(pattern) };
+        let pattern = if whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+        let re = RegexBuilder::new(&pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .with_context(|| "Invalid regex pattern")?;
+
+        let mut matches = Vec::new();
+        for element in self.text_elements(doc_id)? {
+            for caps in re.captures_iter(&element.text) {
+                let m = caps.get(0).unwrap();
+                if m.as_str().is_empty() { continue; }
+                let start = element.text[..m.start()].chars().count();
+                let end = start + m.as_str().chars().count();
+                let mut context_start = m.start().saturating_sub(40);
+                while !element.text.is_char_boundary(context_start) { context_start -= 1; }
+                let mut context_end = (m.end() + 40).min(element.text.len());
+                while !element.text.is_char_boundary(context_end) { context_end += 1; }
+                let groups: Vec<Option<&str>> = caps.iter().skip(1).map(|g| g.map(|g| g.as_str())).collect();
+                matches.push(serde_json::json!({
+                    "element_index": element.element_index,
+                    "kind": element.kind,
+                    "range_id": element.range_id,
+                    "item": element.item,
+                    "start": start,
+                    "end": end,
+                    "text": m.as_str(),
+                    "groups": groups,
+                    "context": &element.text[context_start..context_end],
+                }));
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Every match of `pattern` as a structured record, for requirement and traceability
+    /// reviews: the match, its capture groups (by name, or "1", "2", ... for unnamed
+    /// groups), the element text and the headings above it. Matches in a table cell also
+    /// carry the cells of their row. Heading context needs the document's element model,
+    /// so opened documents that were not made editable report no section.
+    pub fn extract_patterned_items(&self, doc_id: &str, pattern: &str, case_sensitive: bool) -> Result<Vec<serde_json::Value>> {
+        let re = regex::RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .with_context(|| "Invalid regex pattern")?;
+        let names: Vec<String> = re.capture_names().enumerate().skip(1)
+            .map(|(i, name)| name.map(String::from).unwrap_or_else(|| i.to_string()))
+            .collect();
+        let ops = self.in_memory_ops.get(doc_id)?;
+        // Headings above the current element, outermost first
+        let mut path: Vec<(usize, String)> = Vec::new();
+        let mut items = Vec::new();
+        for element in self.text_elements(doc_id)? {
+            let op = ops.as_deref().and_then(|ops| ops.get(element.element_index));
+            if let Some(DocxOp::Heading { text, style }) = op {
+                let level = heading_level(style);
+                path.retain(|(l, _)| *l < level);
+                path.push((level, text.clone()));
+            }
+            let row = match (op, &element.range_id) {
+                (Some(DocxOp::Table { data }), Some(RangeId::TableCell { row, .. })) => data.rows.get(*row),
+                _ => None,
+            };
+            for caps in re.captures_iter(&element.text) {
+                let m = caps.get(0).unwrap();
+                if m.as_str().is_empty() { continue; }
+                let fields: serde_json::Map<String, serde_json::Value> = names.iter().zip(caps.iter().skip(1))
+                    .filter_map(|(name, group)| group.map(|g| (name.clone(), serde_json::Value::from(g.as_str()))))
+                    .collect();
+                items.push(serde_json::json!({
+                    "match": m.as_str(),
+                    "fields": fields,
+                    "text": element.text,
+                    "element_index": element.element_index,
+                    "kind": element.kind,
+                    "range_id": element.range_id,
+                    "item": element.item,
+                    "row": row,
+                    "section": path.last().map(|(_, heading)| heading),
+                    "section_path": path.iter().map(|(_, heading)| heading).collect::<Vec<_>>(),
+                }));
+            }
+        }
+        Ok(items)
+    }
+
+    /// Highlight every match of `pattern` without changing the text (review passes).
+    /// `color` is a Word highlight name (yellow, green, cyan, ...) or a hex fill like "FFF2CC".
+    /// Created documents keep the highlight across later edits; opened documents are
+    /// annotated in place. Matches that span several runs are not highlighted.
+    pub fn highlight_matches(
+        &mut self,
+        doc_id: &str,
+        pattern: &str,
+        color: &str,
+        case_sensitive: bool,
+        whole_word: bool,
+        use_regex: bool,
+    ) -> Result<usize> {
+        let pattern = if use_regex { pattern.to_string() } else { regex::escape(pattern) };
+        let pattern = if whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+        let pattern = if case_sensitive { pattern } else { format!("(?i){}", pattern) };
+        regex::Regex::new(&pattern).with_context(|| "Invalid regex pattern")?;
+
+        let count = self.search_elements(doc_id, &pattern, true, false, true)?.len();
+        if self.in_memory_ops.contains_key(doc_id) {
+            self.in_memory_ops.push(doc_id, DocxOp::Highlight { pattern, color: color.to_string() })?;
+            self.write_docx(doc_id)?;
+        } else {
+            let path = self.documents.get(doc_id).unwrap().path.clone();
+            self.apply_highlight_xml(&path, &[(pattern, highlight_mark(color))])?;
+            if let Some(metadata) = self.documents.get_mut(doc_id) {
+                metadata.activity.dirty = true;
+            }
+        }
+        info!("Highlighted {} matches in document {}", count, doc_id);
+        Ok(count)
+    }
+
+    /// Identify the language of every text element and report each language's share of
+    /// the document (weighted by letter count).
+    pub fn detect_languages(&self, doc_id: &str) -> Result<serde_json::Value> {
+        use crate::language::detect_language;
+
+        let mut elements = Vec::new();
+        let mut weights: std::collections::BTreeMap<String, (String, Option<String>, usize)> = std::collections::BTreeMap::new();
+        let mut total_letters = 0usize;
+        for el in self.text_elements(doc_id)? {
+            let letters = el.text.chars().filter(|c| c.is_alphabetic()).count();
+            let detected = detect_language(&el.text);
+            if let Some(d) = &detected {
+                let entry = weights.entry(d.code.clone()).or_insert_with(|| (d.name.clone(), d.tag.clone(), 0));
+                entry.2 += letters;
+                total_letters += letters;
+            }
+            if letters > 0 {
+                elements.push(serde_json::json!({
+                    "element_index": el.element_index,
+                    "kind": el.kind,
+                    "range_id": el.range_id,
+                    "item": el.item,
+                    "language": detected,
+                }));
+            }
+        }
+
+        let mut languages: Vec<serde_json::Value> = weights.into_iter().map(|(code, (name, tag, letters))| {
+            let proportion = if total_letters > 0 { letters as f64 / total_letters as f64 } else { 0.0 };
+            serde_json::json!({"code": code, "name": name, "tag": tag, "letters": letters, "proportion": (proportion * 1000.0).round() / 1000.0})
+        }).collect();
+        languages.sort_by(|a, b| b["letters"].as_u64().cmp(&a["letters"].as_u64()));
+        Ok(serde_json::json!({
+            "primary": languages.first().map(|l| l["code"].clone()),
+            "languages": languages,
+            "elements": elements,
+        }))
+    }
+
+    /// Report words missing from the dictionary, with element references and suggestions
+    #[cfg(feature = "spellcheck")]
+    pub fn check_spelling(
+        &self,
+        doc_id: &str,
+        checker: &crate::spelling::SpellChecker,
+        max_suggestions: usize,
+    ) -> Result<Vec<crate::spelling::Misspelling>> {
+        let mut out = Vec::new();
+        for el in self.text_elements(doc_id)? {
+            for (word, start, end) in checker.check_text(&el.text) {
+                out.push(crate::spelling::Misspelling {
+                    suggestions: checker.suggest(&word, max_suggestions),
+                    word,
+                    element_index: el.element_index,
+                    kind: el.kind.clone(),
+                    range_id: el.range_id.clone(),
+                    item: el.item,
+                    start,
+                    end,
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// Replace one occurrence of `word` inside a single element (as reported by
+    /// check_spelling/search_text). With `start` the word must sit at that character
+    /// offset; otherwise the first whole-word occurrence is replaced.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_correction(
+        &mut self,
+        doc_id: &str,
+        element_index: usize,
+        item: Option<usize>,
+        cell: Option<(usize, usize)>,
+        word: &str,
+        replacement: &str,
+        start: Option<usize>,
+    ) -> Result<()> {
+        if word.is_empty() {
+            anyhow::bail!("No word to correct: 'word' is empty");
+        }
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let op = ops.get_mut(element_index)
+            .ok_or_else(|| anyhow::anyhow!("Element {} not found", element_index))?;
+        let text: &mut String = match (op, item, cell) {
+            (DocxOp::Paragraph { text, .. }, _, _)
+            | (DocxOp::Heading { text, .. }, _, _)
+            | (DocxOp::ListItem { text, .. }, _, _)
+            | (DocxOp::Header(text), _, _)
+            | (DocxOp::Footer(text), _, _)
+            | (DocxOp::Hyperlink { text, .. }, _, _) => text,
+            (DocxOp::List { items, .. }, Some(i), _) => items.get_mut(i)
+                .ok_or_else(|| anyhow::anyhow!("List item {} not found", i))?,
+            (DocxOp::Table { data }, _, Some((r, c))) => data.rows.get_mut(r).and_then(|row| row.get_mut(c))
+                .ok_or_else(|| anyhow::anyhow!("Cell ({}, {}) not found", r, c))?,
+            _ => anyhow::bail!("Element {} has no editable text at that position", element_index),
+        };
+
+        let byte_start = match start {
+            Some(char_start) => {
+                let byte = text.char_indices().nth(char_start).map(|(b, _)| b)
+                    .ok_or_else(|| anyhow::anyhow!("Offset {} is past the end of the element", char_start))?;
+                if !text[byte..].starts_with(word) {
+                    anyhow::bail!("'{}' not found at offset {} (element text changed?)", word, char_start);
+                }
+                byte
+            }
+            None => {
+                let re = regex::Regex::new(&format!(r"\b{}\b", regex::escape(word)))?;
+                re.find(text).map(|m| m.start())
+                    .ok_or_else(|| anyhow::anyhow!("'{}' not found in element {}", word, element_index))?
+            }
+        };
+        text.replace_range(byte_start..byte_start + word.len(), replacement);
+        self.write_docx(doc_id)?;
+        info!("Applied correction '{}' -> '{}' in document {}", word, replacement, doc_id);
+        Ok(())
+    }
+
+    /// Cheap structured overview: top keywords, pattern-based entities and a
+    /// section-by-section skeleton (heading, word count, lead sentence, keywords)
+    pub fn analyze_content(&self, doc_id: &str, keyword_limit: usize) -> Result<serde_json::Value> {
+        use crate::analysis::{extract_entities, first_sentence, top_keywords, word_count};
+
+        struct Section { heading: Option<String>, element_index: Option<usize>, body: Vec<String> }
+        let mut sections = vec![Section { heading: None, element_index: None, body: Vec::new() }];
+        let mut full_text = String::new();
+        for el in self.text_elements(doc_id)? {
+            full_text.push_str(&el.text);
+            full_text.push('\n');
+            if el.kind == "heading" {
+                sections.push(Section { heading: Some(el.text), element_index: Some(el.element_index), body: Vec::new() });
+            } else if el.kind != "header" && el.kind != "footer" {
+                sections.last_mut().unwrap().body.push(el.text);
+            }
+        }
+        // Drop the untitled preamble when the document starts with a heading
+        if sections.len() > 1 && sections[0].body.iter().all(|t| t.trim().is_empty()) {
+            sections.remove(0);
+        }
+
+        let skeleton: Vec<serde_json::Value> = sections.iter().map(|sec| {
+            let body = sec.body.join("\n");
+            let lead = sec.body.iter().find(|t| !t.trim().is_empty()).map(|t| first_sentence(t, 200));
+            serde_json::json!({
+                "heading": sec.heading,
+                "element_index": sec.element_index,
+                "word_count": word_count(&body),
+                "lead": lead,
+                "keywords": top_keywords(&body, 5).into_iter().map(|k| k.term).collect::<Vec<_>>(),
+            })
+        }).collect();
+
+        Ok(serde_json::json!({
+            "word_count": word_count(&full_text),
+            "keywords": top_keywords(&full_text, keyword_limit),
+            "entities": extract_entities(&full_text),
+            "sections": skeleton,
+        }))
+    }
+
+    /// Word counts split the way Word's dialog reports them: body text, tables,
+    /// headers/footers and footnotes/endnotes, per heading section, plus the page count
+    /// from the PDF text layout instead of a words-per-page guess.
+    pub fn word_count_report(&self, doc_id: &str) -> Result<serde_json::Value> {
+        use crate::analysis::word_count;
+        use crate::pure_converter::PureRustConverter;
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let document_xml = read_part(&metadata.path, "word/document.xml")?
+            .ok_or_else(|| anyhow::anyhow!("No document.xml found in DOCX file"))?;
+        fn paragraph_text(p: roxmltree::Node) -> String {
+            let mut text = String::new();
+            for node in p.descendants() {
+                match node.tag_name().name() {
+                    "t" => text.push_str(node.text().unwrap_or("")),
+                    "tab" | "br" => text.push(' '),
+                    _ => {}
+                }
+            }
+            text
+        }
+        fn part_words(xml: &str) -> Result<usize> {
+            let doc = roxmltree::Document::parse(xml)?;
+            Ok(doc.descendants()
+                .filter(|n| n.tag_name().name() == "p")
+                // Skip the separator pseudo-notes Word stores alongside real footnotes
+                .filter(|p| !p.ancestors().any(|a| {
+                    matches!(a.tag_name().name(), "footnote" | "endnote")
+                        && a.attributes().any(|at| at.name() == "type" && at.value() != "normal")
+                }))
+                .map(|p| word_count(&paragraph_text(p)))
+                .sum())
+        }
+
+        let doc = roxmltree::Document::parse(&document_xml)?;
+        let (mut body, mut tables) = (0usize, 0usize);
+        let mut sections: Vec<serde_json::Value> = Vec::new();
+        let mut current: Option<(String, usize, usize)> = None; // (heading, level, words)
+        let mut preamble = 0usize;
+        for p in doc.descendants().filter(|n| n.tag_name().name() == "p") {
+            let words = word_count(&paragraph_text(p));
+            let in_table = p.ancestors().any(|a| a.tag_name().name() == "tbl");
+            let style = p.children()
+                .find(|n| n.tag_name().name() == "pPr")
+                .and_then(|ppr| ppr.children().find(|n| n.tag_name().name() == "pStyle"))
+                .and_then(|ps| ps.attributes().find(|a| a.name() == "val").map(|a| a.value().to_string()));
+            if in_table {
+                tables += words;
+            } else {
+                body += words;
+            }
+            match style.as_deref() {
+                Some(st) if !in_table && (st.starts_with("Heading") || st == "Title") => {
+                    if let Some((heading, level, count)) = current.take() {
+                        sections.push(serde_json::json!({"heading": heading, "level": level, "words": count}));
+                    }
+                    let level = if st == "Title" { 1 } else { heading_level(st) };
+                    current = Some((paragraph_text(p).trim().to_string(), level, words));
+                }
+                _ => match current.as_mut() {
+                    Some((_, _, count)) => *count += words,
+                    None => preamble += words,
+                },
+            }
+        }
+        if let Some((heading, level, count)) = current {
+            sections.push(serde_json::json!({"heading": heading, "level": level, "words": count}));
+        }
+        if preamble > 0 {
+            sections.insert(0, serde_json::json!({"heading": null, "level": 0, "words": preamble}));
+        }
+
+        let archive = ZipArchive::new(File::open(&metadata.path)?)?;
+        let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+        let (mut headers_footers, mut footnotes, mut endnotes) = (0usize, 0usize, 0usize);
+        for name in &names {
+            let Some(part) = name.strip_prefix("word/") else { continue };
+            let slot = if part.starts_with("header") || part.starts_with("footer") {
+                &mut headers_footers
+            } else if part == "footnotes.xml" {
+                &mut footnotes
+            } else if part == "endnotes.xml" {
+                &mut endnotes
+            } else {
+                continue;
+            };
+            if let Some(xml) = read_part(&metadata.path, name)? {
+                *slot += part_words(&xml)?;
+            }
+        }
+
+        let converter = PureRustConverter::new();
+        let text = converter.extract_text_from_docx(&metadata.path)?;
+        let layout = converter.layout_text(&text);
+        Ok(serde_json::json!({
+            "breakdown": {
+                "body": body,
+                "tables": tables,
+                "headers_footers": headers_footers,
+                "footnotes": footnotes,
+                "endnotes": endnotes,
+            },
+            // Word's status bar total: main text including tables, notes excluded
+            "main_text_words": body + tables,
+            "words_including_notes": body + tables + footnotes + endnotes,
+            "sections": sections,
+            "lines": layout.len(),
+            "pages": layout.last().map(|l| l.page + 1).unwrap_or(1),
+        }))
+    }
+
+    /// Body elements (headers and footers left out) laid out by the PDF renderer, with
+    /// the index into the elements of each layout line's source line
+    fn body_layout(&self, doc_id: &str) -> Result<(Vec<ElementText>, Vec<usize>, Vec<crate::pure_converter::LayoutLine>)> {
+        let elements: Vec<ElementText> = self.text_elements(doc_id)?
+            .into_iter()
+            .filter(|e| e.kind != "header" && e.kind != "footer")
+            .collect();
+        // Each element contributes one source line per embedded line break
+        let mut owners = Vec::new();
+        let mut lines = Vec::new();
+        for (i, el) in elements.iter().enumerate() {
+            for line in el.text.split('\n') {
+                owners.push(i);
+                lines.push(line);
+            }
+        }
+        let layout = crate::pure_converter::PureRustConverter::new().layout_text(&lines.join("\n"));
+        Ok((elements, owners, layout))
+    }
+
+    /// Page layout as produced by the PDF renderer: the page count and, for every page,
+    /// the element whose text starts it. Headers/footers are not part of the body flow.
+    pub fn get_pagination(&self, doc_id: &str) -> Result<serde_json::Value> {
+        let (elements, owners, layout) = self.body_layout(doc_id)?;
+
+        let mut element_pages: Vec<Option<usize>> = vec![None; elements.len()];
+        let mut pages: Vec<serde_json::Value> = Vec::new();
+        let mut previous_owner: Option<usize> = None;
+        for line in &layout {
+            let owner = owners[line.source_line];
+            if element_pages[owner].is_none() {
+                element_pages[owner] = Some(line.page + 1);
+            }
+            if line.slot == 0 {
+                let el = &elements[owner];
+                let preview: String = el.text.chars().take(80).collect();
+                pages.push(serde_json::json!({
+                    "page": line.page + 1,
+                    "first_element": {
+                        "element_index": el.element_index,
+                        "kind": el.kind,
+                        "range_id": el.range_id,
+                        "item": el.item,
+                        "text": preview,
+                    },
+                    "continues_previous": previous_owner == Some(owner),
+                }));
+            }
+            previous_owner = Some(owner);
+        }
+
+        // Empty elements take no space; they sit on the page of the next text
+        let mut next_page = layout.last().map(|l| l.page + 1).unwrap_or(1);
+        for page in element_pages.iter_mut().rev() {
+            match page {
+                Some(p) => next_page = *p,
+                None => *page = Some(next_page),
+            }
+        }
+        let element_list: Vec<serde_json::Value> = elements.iter().zip(&element_pages).map(|(el, page)| {
+            serde_json::json!({
+                "element_index": el.element_index,
+                "kind": el.kind,
+                "item": el.item,
+                "page": page,
+            })
+        }).collect();
+
+        Ok(serde_json::json!({
+            "page_count": pages.len().max(1),
+            "lines_per_page": crate::pure_converter::LAYOUT_LINES_PER_PAGE,
+            "pages": pages,
+            "elements": element_list,
+        }))
+    }
+
+    /// Find pagination problems with the PDF renderer's layout (see `get_pagination`)
+    /// and fix them with keep-together groups:
+    /// - a heading left at the foot of a page is kept with the element after it, or
+    ///   moved to the next page with a page break when that element is longer than a page
+    /// - a paragraph that leaves a single line on either page is kept on one page
+    /// - a table that fits on a page but is split across two is kept together
+    ///
+    /// Elements already in a keep-together group are left alone. Returns the fixes made,
+    /// with element indices as they were before the fixes.
+    pub fn fix_pagination(&mut self, doc_id: &str) -> Result<serde_json::Value> {
+        use crate::pure_converter::LAYOUT_LINES_PER_PAGE;
+        self.ensure_modifiable(doc_id)?;
+        let (elements, owners, layout) = self.body_layout(doc_id)?;
+
+        /// Where an element's lines fall: first/last page and lines on each of them
+        #[derive(Clone, Copy)]
+        struct Extent { first_page: usize, last_page: usize, first_lines: usize, last_lines: usize, lines: usize }
+        let mut extents: std::collections::BTreeMap<usize, Extent> = std::collections::BTreeMap::new();
+        for line in &layout {
+            let index = elements[owners[line.source_line]].element_index;
+            let e = extents.entry(index).or_insert(Extent { first_page: line.page, last_page: line.page, first_lines: 0, last_lines: 0, lines: 0 });
+            if line.page != e.last_page {
+                e.last_page = line.page;
+                e.last_lines = 0;
+            }
+            if line.page == e.first_page {
+                e.first_lines += 1;
+            }
+            e.last_lines += 1;
+            e.lines += 1;
+        }
+
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut grouped = vec![false; ops.len()];
+        let mut depth = 0usize;
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                DocxOp::KeepTogetherStart => depth += 1,
+                DocxOp::KeepTogetherEnd => depth = depth.saturating_sub(1),
+                _ => grouped[i] = depth > 0,
+            }
+        }
+
+        // (start, end, page break instead of a group, problem)
+        let mut fixes: Vec<(usize, usize, bool, &str)> = Vec::new();
+        let entries: Vec<(usize, Extent)> = extents.into_iter().filter(|(i, _)| !grouped[*i]).collect();
+        let mut claimed = std::collections::HashSet::new();
+        for (k, (index, extent)) in entries.iter().enumerate() {
+            if claimed.contains(index) {
+                continue;
+            }
+            match &ops[*index] {
+                DocxOp::Heading { .. } => {
+                    let Some((next, next_extent)) = entries.get(k + 1) else { continue };
+                    if next_extent.first_page <= extent.last_page || ops[*index + 1..*next].iter().any(|op| matches!(op, DocxOp::PageBreak | DocxOp::SectionBreak { .. })) {
+                        continue;
+                    }
+                    if extent.lines + next_extent.lines <= LAYOUT_LINES_PER_PAGE {
+                        fixes.push((*index, *next, false, "orphan_heading"));
+                        claimed.insert(*next);
+                    } else {
+                        fixes.push((*index, *index, true, "orphan_heading"));
+                    }
+                }
+                DocxOp::Table { .. } if extent.first_page != extent.last_page && extent.lines <= LAYOUT_LINES_PER_PAGE => {
+                    fixes.push((*index, *index, false, "split_table"));
+                }
+                DocxOp::Paragraph { .. } | DocxOp::List { .. } | DocxOp::ListItem { .. }
+                    if extent.first_page != extent.last_page && (extent.first_lines == 1 || extent.last_lines == 1)
+                        && extent.lines <= LAYOUT_LINES_PER_PAGE =>
+                {
+                    fixes.push((*index, *index, false, if extent.last_lines == 1 { "widow" } else { "orphan_line" }));
+                }
+                _ => {}
+            }
+        }
+
+        // From the back so earlier indices stay valid
+        let mut report = Vec::with_capacity(fixes.len());
+        for (start, end, page_break, problem) in fixes.iter().rev() {
+            if *page_break {
+                ops.insert(*start, DocxOp::PageBreak);
+            } else {
+                insert_keep_group(ops, *start, *end)?;
+            }
+            let preview: String = elements.iter().find(|e| e.element_index == *start)
+                .map(|e| e.text.chars().take(80).collect())
+                .unwrap_or_default();
+            report.push(serde_json::json!({
+                "problem": problem,
+                "element_index": start,
+                "text": preview,
+                "fix": if *page_break { "page_break_before" } else if start == end { "keep_together" } else { "keep_with_next" },
+            }));
+        }
+        report.reverse();
+        if !report.is_empty() {
+            self.write_docx(doc_id)?;
+        }
+        info!("Fixed {} pagination problems in document {}", report.len(), doc_id);
+        Ok(serde_json::json!({"fixed": report.len(), "fixes": report}))
+    }
+
+    /// Formatting inventory read from the package XML (works for opened documents too):
+    /// paragraph/character styles with counts, fonts, sizes, colors, images, tables,
+    /// numbering definitions and body-text inconsistencies.
+    pub fn analyze_formatting(&self, doc_id: &str) -> Result<serde_json::Value> {
+        use std::collections::BTreeMap;
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let document_xml = read_part(&metadata.path, "word/document.xml")?
+            .ok_or_else(|| anyhow::anyhow!("No document.xml found in DOCX file"))?;
+        let doc = roxmltree::Document::parse(&document_xml)?;
+        fn w(node: roxmltree::Node, attr: &str) -> Option<String> {
+            node.attributes().find(|a| a.name() == attr).map(|a| a.value().to_string())
+        }
+
+        let mut paragraph_styles: BTreeMap<String, usize> = BTreeMap::new();
+        let mut character_styles: BTreeMap<String, usize> = BTreeMap::new();
+        let mut fonts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut sizes: BTreeMap<String, usize> = BTreeMap::new();
+        let mut colors: BTreeMap<String, usize> = BTreeMap::new();
+        let mut highlights: BTreeMap<String, usize> = BTreeMap::new();
+        let mut body_fonts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut body_sizes: BTreeMap<String, usize> = BTreeMap::new();
+        let mut numbered_paragraphs = 0usize;
+
+        for p in doc.descendants().filter(|n| n.tag_name().name() == "p") {
+            let style = p.descendants()
+                .find(|n| n.tag_name().name() == "pStyle")
+                .and_then(|n| w(n, "val"))
+                .unwrap_or_else(|| "Normal".to_string());
+            let is_heading = style.to_ascii_lowercase().starts_with("heading") || style == "Title";
+            *paragraph_styles.entry(style).or_default() += 1;
+            if p.descendants().any(|n| n.tag_name().name() == "numPr") { numbered_paragraphs += 1; }
+
+            for r in p.children().filter(|n| n.tag_name().name() == "r") {
+                let has_text = r.children().any(|n| n.tag_name().name() == "t" && n.text().map(|t| !t.trim().is_empty()).unwrap_or(false));
+                let Some(rpr) = r.children().find(|n| n.tag_name().name() == "rPr") else { continue };
+                for prop in rpr.children() {
+                    match prop.tag_name().name() {
+                        "rStyle" => if let Some(v) = w(prop, "val") { *character_styles.entry(v).or_default() += 1; },
+                        "rFonts" => if let Some(v) = w(prop, "ascii").or_else(|| w(prop, "hAnsi")) {
+                            if has_text && !is_heading { *body_fonts.entry(v.clone()).or_default() += 1; }
+                            *fonts.entry(v).or_default() += 1;
+                        },
+                        "sz" => if let Some(v) = w(prop, "val").and_then(|v| v.parse::<f32>().ok()) {
+                            let pt = format!("{}", v / 2.0);
+                            if has_text && !is_heading { *body_sizes.entry(pt.clone()).or_default() += 1; }
+                            *sizes.entry(pt).or_default() += 1;
+                        },
+                        "color" => if let Some(v) = w(prop, "val") { *colors.entry(v.to_uppercase()).or_default() += 1; },
+                        "highlight" => if let Some(v) = w(prop, "val") { *highlights.entry(v).or_default() += 1; },
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Default body font from styles.xml docDefaults
+        let default_font = read_part(&metadata.path, "word/styles.xml")?.and_then(|xml| {
+            let styles = roxmltree::Document::parse(&xml).ok()?;
+            let defaults = styles.descendants().find(|n| n.tag_name().name() == "rPrDefault")?;
+            let rfonts = defaults.descendants().find(|n| n.tag_name().name() == "rFonts")?;
+            rfonts.attributes().find(|a| a.name() == "ascii" || a.name() == "asciiTheme").map(|a| a.value().to_string())
+        });
+        let numbering = read_part(&metadata.path, "word/numbering.xml")?.and_then(|xml| {
+            let numbering = roxmltree::Document::parse(&xml).ok()?;
+            let abstract_nums = numbering.descendants().filter(|n| n.tag_name().name() == "abstractNum").count();
+            let nums = numbering.descendants().filter(|n| n.tag_name().name() == "num").count();
+            Some(serde_json::json!({"abstract_definitions": abstract_nums, "instances": nums}))
+        });
+
+        let count = |name: &str| doc.descendants().filter(|n| n.tag_name().name() == name).count();
+        let mut inconsistencies = Vec::new();
+        if body_fonts.len() > 1 {
+            inconsistencies.push(serde_json::json!({
+                "kind": "mixed_body_fonts",
+                "message": format!("Body text uses {} different fonts", body_fonts.len()),
+                "values": body_fonts,
+            }));
+        }
+        if body_sizes.len() > 2 {
+            inconsistencies.push(serde_json::json!({
+                "kind": "mixed_body_sizes",
+                "message": format!("Body text uses {} different font sizes", body_sizes.len()),
+                "values": body_sizes,
+            }));
+        }
+        if colors.len() > 3 {
+            inconsistencies.push(serde_json::json!({
+                "kind": "many_text_colors",
+                "message": format!("{} distinct text colors are used", colors.len()),
+            }));
+        }
+
+        Ok(serde_json::json!({
+            "styles_used": paragraph_styles.keys().collect::<Vec<_>>(),
+            "paragraph_styles": paragraph_styles,
+            "character_styles": character_styles,
+            "default_font": default_font,
+            "fonts_detected": fonts.keys().collect::<Vec<_>>(),
+            "fonts": fonts,
+            "font_sizes": sizes,
+            "color_palette": colors,
+            "highlights": highlights,
+            "image_count": count("drawing") + count("pict"),
+            "table_count": count("tbl"),
+            "hyperlink_count": count("hyperlink"),
+            "has_tables": count("tbl") > 0,
+            "has_images": count("drawing") + count("pict") > 0,
+            "has_hyperlinks": count("hyperlink") > 0,
+            "numbering": numbering,
+            "numbered_paragraphs": numbered_paragraphs,
+            "section_count": count("sectPr").max(1),
+            "inconsistencies": inconsistencies,
+        }))
+    }
+
+    /// Style consistency linter over the document ops. Rules: `mixed_body_fonts`,
+    /// `heading_level_jump`, `list_indent_jump`, `bold_paragraph_as_heading` and
+    /// `repeated_blank_paragraphs`. With `auto_fix` every reported issue is normalized.
+    pub fn lint_formatting(&mut self, doc_id: &str, auto_fix: bool) -> Result<serde_json::Value> {
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut issues = Vec::new();
+        let issue = |rule: &str, element_index: usize, message: String| {
+            serde_json::json!({"rule": rule, "element_index": element_index, "message": message})
+        };
+
+        // Dominant body font (ties resolved by first use)
+        let mut font_counts: Vec<(String, usize)> = Vec::new();
+        for op in ops.iter() {
+            if let DocxOp::Paragraph { style: Some(DocxStyle { font_family: Some(f), .. }), .. } = op {
+                match font_counts.iter_mut().find(|(name, _)| name == f) {
+                    Some((_, n)) => *n += 1,
+                    None => font_counts.push((f.clone(), 1)),
+                }
+            }
+        }
+        let dominant_font = font_counts.iter().fold(None::<&(String, usize)>, |best, cur| match best {
+            Some(b) if b.1 >= cur.1 => Some(b),
+            _ => Some(cur),
+        }).map(|(f, _)| f.clone());
+
+        let looks_like_heading = |text: &str, style: &Option<DocxStyle>| {
+            let t = text.trim();
+            style.as_ref().and_then(|s| s.bold) == Some(true)
+                && !t.is_empty() && t.chars().count() <= 80
+                && !t.ends_with(['.', ',', ';', ':', '!', '?'])
+        };
+
+        let mut heading_fixes: Vec<(usize, usize)> = Vec::new(); // (op index, new level)
+        let mut promote: Vec<(usize, usize)> = Vec::new();       // bold paragraph -> heading level
+        let mut font_fixes: Vec<usize> = Vec::new();
+        let mut list_fixes: Vec<(usize, usize)> = Vec::new();
+        let mut blank_removals: Vec<usize> = Vec::new();
+        let mut last_heading_level = 0usize;
+        let mut last_list_level: Option<usize> = None;
+        let mut previous_blank = false;
+
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                DocxOp::Heading { text, style } => {
+                    let level = heading_level(style);
+                    if level > last_heading_level + 1 {
+                        let fixed = last_heading_level + 1;
+                        issues.push(issue("heading_level_jump", i, format!("Heading '{}' jumps from level {} to {}", text, last_heading_level, level)));
+                        heading_fixes.push((i, fixed));
+                        last_heading_level = fixed;
+                    } else {
+                        last_heading_level = level;
+                    }
+                }
+                DocxOp::Paragraph { text, style } => {
+                    if let (Some(dominant), Some(DocxStyle { font_family: Some(f), .. })) = (&dominant_font, style) {
+                        if f != dominant {
+                            issues.push(issue("mixed_body_fonts", i, format!("Body paragraph uses '{}' instead of '{}'", f, dominant)));
+                            font_fixes.push(i);
+                        }
+                    }
+                    if looks_like_heading(text, style) {
+                        let level = (last_heading_level + 1).min(6);
+                        issues.push(issue("bold_paragraph_as_heading", i, format!("Bold line '{}' looks like a heading", text.trim())));
+                        promote.push((i, level));
+                    }
+                    let blank = text.trim().is_empty();
+                    if blank && previous_blank {
+                        issues.push(issue("repeated_blank_paragraphs", i, "Consecutive blank paragraphs used for spacing".to_string()));
+                        blank_removals.push(i);
+                    }
+                    previous_blank = blank;
+                    last_list_level = None;
+                    continue;
+                }
+                DocxOp::ListItem { level, .. } => {
+                    let allowed = last_list_level.map(|l| l + 1).unwrap_or(0);
+                    if *level > allowed {
+                        issues.push(issue("list_indent_jump", i, format!("List item indented to level {} (expected at most {})", level, allowed)));
+                        list_fixes.push((i, allowed));
+                        last_list_level = Some(allowed);
+                    } else {
+                        last_list_level = Some(*level);
+                    }
+                    previous_blank = false;
+                    continue;
+                }
+                DocxOp::List { .. } => { last_list_level = Some(0); previous_blank = false; continue; }
+                _ => {}
+            }
+            previous_blank = false;
+            last_list_level = None;
+        }
+
+        let issue_count = issues.len();
+        if auto_fix && issue_count > 0 {
+            self.ensure_modifiable(doc_id)?;
+            let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+            for (i, level) in heading_fixes {
+                if let DocxOp::Heading { style, .. } = &mut ops[i] { *style = format!("Heading{}", level); }
+            }
+            for i in font_fixes {
+                if let DocxOp::Paragraph { style: Some(st), .. } = &mut ops[i] { st.font_family = dominant_font.clone(); }
+            }
+            for (i, fixed) in list_fixes {
+                if let DocxOp::ListItem { level, .. } = &mut ops[i] { *level = fixed; }
+            }
+            for (i, level) in promote {
+                if let DocxOp::Paragraph { text, .. } = &ops[i] {
+                    ops[i] = DocxOp::Heading { text: text.trim().to_string(), style: format!("Heading{}", level) };
+                }
+            }
+            // Remove from the back so earlier indices stay valid
+            for i in blank_removals.into_iter().rev() {
+                ops.remove(i);
+            }
+            self.write_docx(doc_id)?;
+            info!("Fixed {} formatting issues in document {}", issue_count, doc_id);
+        }
+
+        Ok(serde_json::json!({
+            "issues": issues,
+            "issue_count": issue_count,
+            "fixed": if auto_fix { issue_count } else { 0 },
+        }))
+    }
+
+    /// Structural overview: headings with levels, per-section content counts, lists,
+    /// tables, images and links, each with its element index. Built from the ops for
+    /// documents created here and from the package XML for opened documents, where the
+    /// index counts top-level body blocks (paragraphs and tables).
+    pub fn analyze_structure(&self, doc_id: &str) -> Result<serde_json::Value> {
+        let ops = match self.in_memory_ops.get(doc_id)? {
+            Some(ops) => ops,
+            None => return self.analyze_structure_xml(doc_id),
+        };
+
         let mut outline = Vec::new();
+        let mut sections = vec![new_section(None, 0, None)];
         let mut lists = Vec::new();
         let mut tables = Vec::new();
         let mut images = Vec::new();
         let mut links = Vec::new();
         let mut styles_used: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
 
-        for op in ops.iter() {
+        for (i, op) in ops.iter().enumerate() {
             match op {
                 DocxOp::Heading { text, style } => {
-                    let level = style.chars().last().and_then(|c| c.to_digit(10)).map(|d| d as usize).unwrap_or(1);
-                    outline.push(serde_json::json!({"text": text, "level": level}));
+                    let level = heading_level(style);
+                    outline.push(serde_json::json!({"text": text, "level": level, "element_index": i}));
+                    sections.push(new_section(Some(text), level, Some(i)));
                 }
                 DocxOp::List { items, .. } => {
-                    lists.push(serde_json::json!({"level": 0, "items": items}));
+                    lists.push(serde_json::json!({"level": 0, "items": items, "element_index": i}));
+                    bump_section(&mut sections, "lists");
                 }
                 DocxOp::ListItem { text, level, .. } => {
-                    lists.push(serde_json::json!({"level": level, "items": [text]}));
+                    lists.push(serde_json::json!({"level": level, "items": [text], "element_index": i}));
+                    if !matches!(i.checked_sub(1).map(|p| &ops[p]), Some(DocxOp::ListItem { .. })) {
+                        bump_section(&mut sections, "lists");
+                    }
                 }
                 DocxOp::Table { data } => {
                     let rows = data.rows.len();
                     let cols = data.rows.first().map(|r| r.len()).unwrap_or(0);
-                    tables.push(serde_json::json!({"rows": rows, "cols": cols}));
+                    tables.push(serde_json::json!({"rows": rows, "cols": cols, "element_index": i}));
+                    bump_section(&mut sections, "tables");
                 }
                 DocxOp::Image { width, height, .. } => {
-                    images.push(serde_json::json!({"width": width, "height": height}));
+                    images.push(serde_json::json!({"width": width, "height": height, "element_index": i}));
+                    bump_section(&mut sections, "images");
                 }
                 DocxOp::Hyperlink { text, url } => {
-                    links.push(serde_json::json!({"text": text, "url": url}));
+                    links.push(serde_json::json!({"text": text, "url": url, "element_index": i}));
+                    bump_section(&mut sections, "paragraphs");
                 }
                 DocxOp::Paragraph { style, .. } => {
+                    bump_section(&mut sections, "paragraphs");
                     if let Some(s) = style {
                         if s.bold == Some(true) { *styles_used.entry("bold".into()).or_default() += 1; }
                         if s.italic == Some(true) { *styles_used.entry("italic".into()).or_default() += 1; }
@@ -603,12 +3096,19 @@ impl DocxHandler {
                 DocxOp::Header(_) | DocxOp::Footer(_) | DocxOp::PageBreak | DocxOp::SectionBreak { .. } => {}
                 DocxOp::Toc { .. } => {}
                 DocxOp::BookmarkAfterHeading { .. } => {}
+                DocxOp::Highlight { .. } | DocxOp::InvisibleText { .. } | DocxOp::EmbedFont { .. } | DocxOp::HeadingNumbering { .. } | DocxOp::PageSetup(_)
+                | DocxOp::HeaderLayout { .. } | DocxOp::DefaultFont { .. } => {}
+                DocxOp::TagStart(_) | DocxOp::TagEnd(_) => {}
+                DocxOp::DefaultProofing(_) | DocxOp::ProofingStart(_) | DocxOp::ProofingEnd => {}
+                DocxOp::KeepTogetherStart | DocxOp::KeepTogetherEnd => {}
+                DocxOp::Watermark(_) => {}
             }
         }
 
         Ok(serde_json::json!({
             "has_ops": true,
             "outline": outline,
+            "sections": drop_empty_preamble(sections),
             "lists": lists,
             "tables": tables,
             "images": images,
@@ -617,50 +3117,390 @@ impl DocxHandler {
         }))
     }
 
-    /// Outline with stable indices for headings (range_ids)
-    pub fn get_outline(&self, doc_id: &str) -> Result<serde_json::Value> {
-        let ops = self.in_memory_ops.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
-        let mut outline = Vec::new();
-        let mut heading_idx = 0usize;
-        for op in ops.iter() {
-            if let DocxOp::Heading { text, style } = op {
-                let level = style.chars().last().and_then(|c| c.to_digit(10)).map(|d| d as usize).unwrap_or(1);
-                outline.push(serde_json::json!({
-                    "text": text,
-                    "level": level,
-                    "range_id": RangeId::Heading { index: heading_idx }
-                }));
-                heading_idx += 1;
-            }
+    /// `analyze_structure` for opened documents. Heading levels come from the paragraph
+    /// style (resolved through styles.xml, so renamed or localized heading styles count)
+    /// or a direct outline level.
+    fn analyze_structure_xml(&self, doc_id: &str) -> Result<serde_json::Value> {
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let document_xml = read_part(&metadata.path, "word/document.xml")?
+            .ok_or_else(|| anyhow::anyhow!("No document.xml found in DOCX file"))?;
+        let heading_styles = match read_part(&metadata.path, "word/styles.xml")? {
+            Some(xml) => crate::docx_reader::style_outline_levels(&xml)?,
+            None => std::collections::HashMap::new(),
+        };
+        let link_targets = match read_part(&metadata.path, "word/_rels/document.xml.rels")? {
+            Some(xml) => crate::docx_reader::relationship_targets(&xml)?,
+            None => std::collections::HashMap::new(),
+        };
+        let doc = roxmltree::Document::parse(&document_xml)?;
+        fn w(node: roxmltree::Node, attr: &str) -> Option<String> {
+            node.attributes().find(|a| a.name() == attr).map(|a| a.value().to_string())
         }
-        Ok(serde_json::json!({"outline": outline}))
-    }
-
-    /// Simple selector to ranges. Supported selectors:
-    /// - heading:'Text'
-    /// - paragraph[INDEX]
-    /// - table[T].cell[R,C]
-    pub fn get_ranges(&self, doc_id: &str, selector: &str) -> Result<Vec<RangeId>> {
-        let ops = self.in_memory_ops.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
-        let mut results = Vec::new();
-        if let Some(rest) = selector.strip_prefix("heading:") {
-            let needle = rest.trim().trim_matches('\'').trim_matches('"');
-            let mut idx = 0usize;
-            for op in ops.iter() {
-                if let DocxOp::Heading { text, .. } = op {
-                    if text == needle { results.push(RangeId::Heading { index: idx }); }
-                    idx += 1;
+        fn child<'a, 'i>(node: roxmltree::Node<'a, 'i>, name: &str) -> Option<roxmltree::Node<'a, 'i>> {
+            node.children().find(|n| n.tag_name().name() == name)
+        }
+        fn text_of(node: roxmltree::Node) -> String {
+            let mut text = String::new();
+            for n in node.descendants() {
+                match n.tag_name().name() {
+                    "t" => text.push_str(n.text().unwrap_or("")),
+                    "tab" | "br" => text.push(' '),
+                    _ => {}
                 }
             }
-            return Ok(results);
+            text
         }
-        if let Some(start) = selector.strip_prefix("paragraph[") {
-            if let Some(endpos) = start.find(']') {
-                if let Ok(pi) = start[..endpos].parse::<usize>() {
-                    results.push(RangeId::Paragraph { index: pi });
-                    return Ok(results);
+        // Top-level blocks, looking through block-level content controls
+        fn blocks<'a, 'i>(parent: roxmltree::Node<'a, 'i>, out: &mut Vec<roxmltree::Node<'a, 'i>>) {
+            for n in parent.children().filter(|n| n.is_element()) {
+                match n.tag_name().name() {
+                    "p" | "tbl" => out.push(n),
+                    "sdt" => if let Some(content) = child(n, "sdtContent") { blocks(content, out) },
+                    _ => {}
+                }
+            }
+        }
+        let body = doc.descendants().find(|n| n.tag_name().name() == "body")
+            .ok_or_else(|| anyhow::anyhow!("No body found in document.xml"))?;
+        let mut body_blocks = Vec::new();
+        blocks(body, &mut body_blocks);
+
+        let mut outline = Vec::new();
+        let mut sections = vec![new_section(None, 0, None)];
+        let mut lists: Vec<serde_json::Value> = Vec::new();
+        let mut tables = Vec::new();
+        let mut images = Vec::new();
+        let mut links = Vec::new();
+        let mut styles_used: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        let mut in_list = false;
+
+        for (i, block) in body_blocks.iter().enumerate() {
+            if block.tag_name().name() == "tbl" {
+                in_list = false;
+                let rows = block.children().filter(|n| n.tag_name().name() == "tr").count();
+                let cols = child(*block, "tblGrid")
+                    .map(|g| g.children().filter(|n| n.tag_name().name() == "gridCol").count())
+                    .unwrap_or(0);
+                tables.push(serde_json::json!({"rows": rows, "cols": cols, "element_index": i}));
+                bump_section(&mut sections, "tables");
+                continue;
+            }
+            let ppr = child(*block, "pPr");
+            let style = ppr.and_then(|p| child(p, "pStyle")).and_then(|n| w(n, "val"));
+            let direct_level = ppr.and_then(|p| child(p, "outlineLvl"))
+                .and_then(|n| w(n, "val")).and_then(|v| v.parse::<usize>().ok())
+                .filter(|&l| l < 9)
+                .map(|l| l + 1);
+            let level = direct_level.or_else(|| style.as_ref().and_then(|s| heading_styles.get(s).copied()));
+            let text = text_of(*block).trim().to_string();
+
+            for link in block.descendants().filter(|n| n.tag_name().name() == "hyperlink") {
+                let url = link.attributes().find(|a| a.name() == "id")
+                    .and_then(|a| link_targets.get(a.value()).cloned())
+                    .or_else(|| w(link, "anchor").map(|a| format!("#{}", a)));
+                links.push(serde_json::json!({"text": text_of(link), "url": url, "element_index": i}));
+            }
+            for drawing in block.descendants().filter(|n| n.tag_name().name() == "drawing") {
+                let extent = drawing.descendants().find(|n| n.tag_name().name() == "extent");
+                let px = |attr: &str| extent.and_then(|e| w(e, attr)).and_then(|v| v.parse::<u64>().ok()).map(|emu| emu / 9525);
+                let alt_text = drawing.descendants().find(|n| n.tag_name().name() == "docPr").and_then(|n| w(n, "descr"));
+                images.push(serde_json::json!({"width": px("cx"), "height": px("cy"), "alt_text": alt_text, "element_index": i}));
+                bump_section(&mut sections, "images");
+            }
+
+            if let Some(level) = level.filter(|_| !text.is_empty()) {
+                in_list = false;
+                outline.push(serde_json::json!({"text": text, "level": level, "style": style, "element_index": i}));
+                sections.push(new_section(Some(&text), level, Some(i)));
+                continue;
+            }
+            if let Some(num) = ppr.and_then(|p| child(p, "numPr")) {
+                let ilvl = child(num, "ilvl").and_then(|n| w(n, "val")).and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+                match lists.last_mut() {
+                    Some(list) if in_list => {
+                        if let Some(items) = list["items"].as_array_mut() { items.push(serde_json::json!(text)); }
+                    }
+                    _ => {
+                        lists.push(serde_json::json!({"level": ilvl, "items": [text], "element_index": i}));
+                        bump_section(&mut sections, "lists");
+                    }
+                }
+                in_list = true;
+                continue;
+            }
+            in_list = false;
+            if text.is_empty() { continue; }
+            bump_section(&mut sections, "paragraphs");
+            let runs: Vec<_> = block.descendants().filter(|n| n.tag_name().name() == "rPr").collect();
+            for (key, element) in [("bold", "b"), ("italic", "i"), ("underline", "u"), ("font_family", "rFonts"), ("font_size", "sz"), ("color", "color")] {
+                if runs.iter().any(|r| child(*r, element).is_some()) {
+                    *styles_used.entry(key.into()).or_default() += 1;
+                }
+            }
+            if ppr.and_then(|p| child(p, "jc")).is_some() {
+                *styles_used.entry("alignment".into()).or_default() += 1;
+            }
+        }
+
+        Ok(serde_json::json!({
+            "has_ops": false,
+            "outline": outline,
+            "sections": drop_empty_preamble(sections),
+            "lists": lists,
+            "tables": tables,
+            "images": images,
+            "links": links,
+            "styles": styles_used,
+        }))
+    }
+
+    /// Outline with stable indices for headings (range_ids). `outline` is the flat list in
+    /// document order; `tree` nests each heading under the nearest shallower heading.
+    pub fn get_outline(&self, doc_id: &str) -> Result<serde_json::Value> {
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut outline = Vec::new();
+        let mut heading_idx = 0usize;
+        let numbers = active_heading_scheme(&ops).map(|levels| heading_numbers(&ops, levels));
+        for (i, op) in ops.iter().enumerate() {
+            if let DocxOp::Heading { text, style } = op {
+                let mut entry = serde_json::json!({
+                    "text": text,
+                    "level": heading_level(style),
+                    "element_index": i,
+                    "range_id": RangeId::Heading { index: heading_idx }
+                });
+                if let Some(number) = numbers.as_ref().and_then(|n| n[i].as_ref()) {
+                    entry["number"] = serde_json::json!(number);
+                }
+                outline.push(entry);
+                heading_idx += 1;
+            }
+        }
+
+        // Build the tree with a stack of (level, node) pairs, folding deeper nodes into their parent
+        fn fold_into_parent(stack: &mut Vec<(usize, serde_json::Value)>, roots: &mut Vec<serde_json::Value>) {
+            let (_, node) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some((_, parent)) => parent["children"].as_array_mut().unwrap().push(node),
+                None => roots.push(node),
+            }
+        }
+        let mut roots = Vec::new();
+        let mut stack: Vec<(usize, serde_json::Value)> = Vec::new();
+        for entry in &outline {
+            let level = entry["level"].as_u64().unwrap_or(1) as usize;
+            while stack.last().map(|(l, _)| *l >= level).unwrap_or(false) {
+                fold_into_parent(&mut stack, &mut roots);
+            }
+            let mut node = entry.clone();
+            node["children"] = serde_json::json!([]);
+            stack.push((level, node));
+        }
+        while !stack.is_empty() {
+            fold_into_parent(&mut stack, &mut roots);
+        }
+        Ok(serde_json::json!({"outline": outline, "tree": roots}))
+    }
+
+    /// Append a document skeleton: a heading for each node of `outline` (top-level nodes
+    /// as Heading 1, each child one level deeper, down to Heading 6) with an italic
+    /// placeholder paragraph under it. With `toc`, a table of contents and a page break
+    /// come first. Returns the number of headings.
+    pub fn create_outline(&mut self, doc_id: &str, outline: &[OutlineNode], toc: bool) -> Result<usize> {
+        fn push_nodes(nodes: &[OutlineNode], level: usize, ops: &mut Vec<DocxOp>) -> Result<usize> {
+            let mut headings = 0;
+            for node in nodes {
+                let title = node.title.trim();
+                if title.is_empty() {
+                    anyhow::bail!("Every outline heading needs a title");
+                }
+                if level > 6 {
+                    anyhow::bail!("Outline is nested too deep: '{}' would be a level {} heading (at most 6)", title, level);
+                }
+                ops.push(DocxOp::Heading { text: title.to_string(), style: format!("Heading{}", level) });
+                let placeholder = node.placeholder.clone().unwrap_or_else(|| format!("[{}]", title));
+                if !placeholder.trim().is_empty() {
+                    let style = DocxStyle { italic: Some(true), ..Default::default() };
+                    ops.push(DocxOp::Paragraph { text: placeholder, style: Some(style) });
+                }
+                headings += 1 + push_nodes(&node.children, level + 1, ops)?;
+            }
+            Ok(headings)
+        }
+
+        self.ensure_modifiable(doc_id)?;
+        if outline.is_empty() {
+            anyhow::bail!("The outline has no headings");
+        }
+        let mut new_ops = Vec::new();
+        if toc {
+            new_ops.push(DocxOp::Toc { from_level: 1, to_level: 3, right_align_dots: true });
+            new_ops.push(DocxOp::PageBreak);
+        }
+        let headings = push_nodes(outline, 1, &mut new_ops)?;
+        self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?
+            .extend(new_ops);
+        self.write_docx(doc_id)?;
+        info!("Created an outline of {} headings in document {}", headings, doc_id);
+        Ok(headings)
+    }
+
+    /// Count a content-changing operation in the document's activity and mark it dirty
+    pub fn record_op(&mut self, doc_id: &str, op: &str, element_index: usize) {
+        if let Some(metadata) = self.documents.get_mut(doc_id) {
+            let now = Utc::now();
+            let activity = &mut metadata.activity;
+            activity.ops_applied += 1;
+            activity.recent_ops.push(RecordedOp { op: op.to_string(), element_index, at: now });
+            if activity.recent_ops.len() > RECENT_OPS {
+                activity.recent_ops.remove(0);
+            }
+            activity.dirty = true;
+            metadata.modified_at = now;
+        }
+    }
+
+    /// Content hash per element, so callers can tell which elements an edit touched.
+    /// None for opened documents, which have no element list, and when the ops cannot
+    /// be read (the edit itself reports that).
+    pub fn element_fingerprints(&self, doc_id: &str) -> Option<Vec<u64>> {
+        self.in_memory_ops.fingerprints(doc_id)
+    }
+
+    /// Cheap overview for the `docx://{id}/summary` resource: outline with the first
+    /// sentence of each section, plus table and figure inventories. Built from the ops on
+    /// every read, so it always reflects the latest edit.
+    pub fn document_summary(&self, doc_id: &str) -> Result<serde_json::Value> {
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut intro: Option<String> = None;
+        let mut sections: Vec<serde_json::Value> = Vec::new();
+        let (mut tables, mut figures) = (Vec::new(), Vec::new());
+        let mut words = 0usize;
+        for (i, op) in ops.iter().enumerate() {
+            let body_text = match op {
+                DocxOp::Heading { text, style } => {
+                    words += text.split_whitespace().count();
+                    sections.push(serde_json::json!({
+                        "heading": text,
+                        "level": heading_level(style),
+                        "element_index": i,
+                        "first_sentence": null,
+                    }));
+                    None
+                }
+                DocxOp::Paragraph { text, .. } | DocxOp::ListItem { text, .. } => Some(text.clone()),
+                DocxOp::List { items, .. } => Some(items.join(" ")),
+                DocxOp::Table { data } => {
+                    words += data.rows.iter().flatten().map(|c| c.split_whitespace().count()).sum::<usize>();
+                    tables.push(serde_json::json!({
+                        "element_index": i,
+                        "rows": data.rows.len(),
+                        "columns": data.rows.iter().map(|r| r.len()).max().unwrap_or(0),
+                        "header": data.rows.first(),
+                    }));
+                    None
+                }
+                DocxOp::Image { width, height, alt_text, .. } => {
+                    figures.push(serde_json::json!({"element_index": i, "width": width, "height": height, "alt_text": alt_text}));
+                    None
+                }
+                _ => None,
+            };
+            let Some(text) = body_text else { continue };
+            words += text.split_whitespace().count();
+            // Blank text and internal marker paragraphs have no lead sentence
+            let text = text.trim();
+            if text.is_empty() || text.starts_with("__") {
+                continue;
+            }
+            let sentence = crate::analysis::first_sentence(text, 200);
+            match sections.last_mut() {
+                Some(section) if section["first_sentence"].is_null() => section["first_sentence"] = sentence.into(),
+                None if intro.is_none() => intro = Some(sentence),
+                _ => {}
+            }
+        }
+        Ok(serde_json::json!({
+            "document_id": doc_id,
+            "title": metadata.title,
+            "modified_at": metadata.modified_at,
+            "elements": ops.len(),
+            "words": words,
+            "intro": intro,
+            "sections": sections,
+            "tables": tables,
+            "figures": figures,
+        }))
+    }
+
+    /// Text of the section under a heading, selected by heading text (exact, then
+    /// case-insensitive) or by the heading's element_index from `get_outline`.
+    /// The section ends at the next heading of the same or a higher level; with
+    /// `include_subsections` false it ends at the next heading of any level.
+    pub fn get_section_text(
+        &self,
+        doc_id: &str,
+        heading: Option<&str>,
+        element_index: Option<usize>,
+        include_subsections: bool,
+    ) -> Result<serde_json::Value> {
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (start, end) = section_bounds(&ops, heading, element_index, include_subsections)?;
+        let (title, level) = match &ops[start] {
+            DocxOp::Heading { text, style } => (text.clone(), heading_level(style)),
+            _ => unreachable!(),
+        };
+
+        let mut lines = Vec::new();
+        let mut elements = Vec::new();
+        for el in self.text_elements(doc_id)? {
+            // Headers and footers belong to the page, not to the section they were set in
+            if el.element_index > start && el.element_index < end && el.kind != "header" && el.kind != "footer" {
+                lines.push(el.text.clone());
+                elements.push(el);
+            }
+        }
+        Ok(serde_json::json!({
+            "heading": title,
+            "level": level,
+            "element_index": start,
+            "end_element_index": end,
+            "text": lines.join("\n"),
+            "elements": elements,
+        }))
+    }
+
+    /// Simple selector to ranges. Supported selectors:
+    /// - heading:'Text'
+    /// - paragraph[INDEX]
+    /// - table[T].cell[R,C]
+    pub fn get_ranges(&self, doc_id: &str, selector: &str) -> Result<Vec<RangeId>> {
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let mut results = Vec::new();
+        if let Some(rest) = selector.strip_prefix("heading:") {
+            let needle = rest.trim().trim_matches('\'').trim_matches('"');
+            let mut idx = 0usize;
+            for op in ops.iter() {
+                if let DocxOp::Heading { text, .. } = op {
+                    if text == needle { results.push(RangeId::Heading { index: idx }); }
+                    idx += 1;
+                }
+            }
+            return Ok(results);
+        }
+        if let Some(start) = selector.strip_prefix("paragraph[") {
+            if let Some(endpos) = start.find(']') {
+                if let Ok(pi) = start[..endpos].parse::<usize>() {
+                    results.push(RangeId::Paragraph { index: pi });
+                    return Ok(results);
                 }
             }
         }
@@ -690,8 +3530,8 @@ impl DocxHandler {
     /// Replace text in a given range id (paragraph or heading). For TableCell use set_table_cell_text
     pub fn replace_range_text(&mut self, doc_id: &str, range: &RangeId, new_text: &str) -> Result<()> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         match range {
             RangeId::Paragraph { index } => {
                 let mut para_idx = 0usize;
@@ -720,8 +3560,8 @@ impl DocxHandler {
     /// Set table cell text by table index and coordinates
     pub fn set_table_cell_text(&mut self, doc_id: &str, table_index: usize, row: usize, col: usize, text: &str) -> Result<()> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         let mut ti = 0usize;
         for op in ops.iter_mut() {
             if let DocxOp::Table { data } = op {
@@ -740,9 +3580,178 @@ impl DocxHandler {
         anyhow::bail!("Table not found")
     }
 
+    /// Replace a template row of a table with one row per record, keeping the row's
+    /// horizontal merges and the table's widths, borders and shading. Object records fill
+    /// `{{field}}` placeholders in the template cells, or else the column whose header
+    /// names the field; array records fill the cells in order. Values are formatted with
+    /// the spec `formatting` has for their field or column header. Returns the rows added.
+    pub fn populate_table(
+        &mut self,
+        doc_id: &str,
+        table_index: usize,
+        template_row: Option<usize>,
+        records: &[serde_json::Value],
+        formatting: &crate::locale::Formatting,
+    ) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let data = ops.iter_mut()
+            .filter_map(|op| match op { DocxOp::Table { data } => Some(data), _ => None })
+            .nth(table_index)
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", table_index))?;
+        if data.rows.is_empty() {
+            anyhow::bail!("Table {} has no rows to use as a template", table_index);
+        }
+        let template_index = template_row.unwrap_or(data.rows.len() - 1);
+        let template = data.rows.get(template_index)
+            .ok_or_else(|| anyhow::anyhow!("Template row {} out of bounds", template_index))?
+            .clone();
+        let header = (template_index > 0).then(|| data.rows[0].clone());
+        let column_name = |c: usize| header.as_ref().and_then(|h| h.get(c)).map(|h| h.trim()).unwrap_or("");
+        // Format with the spec for the record field, else for the column header
+        let cell_text = |value: Option<&serde_json::Value>, field: &str, column: &str| -> Result<String> {
+            match (value, formatting.spec_for(field).or_else(|| formatting.spec_for(column))) {
+                (None | Some(serde_json::Value::Null), _) => Ok(String::new()),
+                (Some(v), Some(spec)) => formatting.format(v, spec)
+                    .with_context(|| format!("Cannot format '{}' value {}", column, v)),
+                (Some(v), None) => Ok(crate::templating::display(v)),
+            }
+        };
+
+        let mut filled = Vec::with_capacity(records.len());
+        for (index, record) in records.iter().enumerate() {
+            let row = match record {
+                serde_json::Value::Array(values) => template.iter().enumerate()
+                    .map(|(c, _)| cell_text(values.get(c), column_name(c), column_name(c)))
+                    .collect::<Result<Vec<String>>>()?,
+                serde_json::Value::Object(fields) => {
+                    let mut context = crate::templating::Context::new(record).with_formatting(formatting.clone());
+                    context.push(record.clone(), index);
+                    template.iter().enumerate().map(|(c, cell)| {
+                        if cell.contains("{{") {
+                            return context.render(cell);
+                        }
+                        let name = column_name(c);
+                        let key = name.to_lowercase().replace(' ', "_");
+                        let field = fields.iter().find(|(k, _)| k.as_str() == name)
+                            .or_else(|| fields.iter().find(|(k, _)| k.eq_ignore_ascii_case(name) || k.to_lowercase() == key));
+                        match field {
+                            Some((k, v)) => cell_text(Some(v), k, name),
+                            None => Ok(String::new()),
+                        }
+                    }).collect::<Result<Vec<String>>>()?
+                }
+                other => anyhow::bail!("Record {} must be an object or an array, got {}", index, other),
+            };
+            filled.push(row);
+        }
+
+        let added = filled.len();
+        data.rows.splice(template_index..=template_index, filled);
+        if let Some(merges) = data.merges.as_mut() {
+            let mut remapped = Vec::new();
+            for merge in merges.drain(..) {
+                if merge.row == template_index && merge.row_span <= 1 {
+                    remapped.extend((0..added).map(|r| TableMerge { row: template_index + r, ..merge.clone() }));
+                } else if merge.row > template_index {
+                    remapped.push(TableMerge { row: merge.row + added - 1, ..merge });
+                } else if merge.row != template_index {
+                    remapped.push(merge);
+                }
+            }
+            *merges = remapped;
+        }
+        self.write_docx(doc_id)?;
+        info!("Populated table {} of document {} with {} rows", table_index, doc_id, added);
+        Ok(added)
+    }
+
+    /// Replace table `table_index` with a list of one item per row, the row's non-empty
+    /// cells joined by `separator` (a two-column table becomes "Term: definition"
+    /// items). The header row is left out when `skip_header` is set, which defaults to
+    /// whether the table has headers. Returns the number of items.
+    pub fn table_to_list(&mut self, doc_id: &str, table_index: usize, ordered: bool, separator: &str, skip_header: Option<bool>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let position = ops.iter().enumerate()
+            .filter(|(_, op)| matches!(op, DocxOp::Table { .. }))
+            .nth(table_index)
+            .map(|(i, _)| i)
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", table_index))?;
+        let DocxOp::Table { data } = &ops[position] else { unreachable!() };
+        let skip = skip_header.unwrap_or_else(|| data.headers.as_ref().is_some_and(|h| !h.is_empty()));
+        let items: Vec<String> = data.rows.iter().skip(usize::from(skip))
+            .map(|row| row.iter().map(|c| c.trim()).filter(|c| !c.is_empty()).collect::<Vec<_>>().join(separator))
+            .filter(|item| !item.is_empty())
+            .collect();
+        if items.is_empty() {
+            anyhow::bail!("Table {} has no rows with text to list", table_index);
+        }
+        let count = items.len();
+        ops[position] = DocxOp::List { items, ordered };
+        self.write_docx(doc_id)?;
+        info!("Converted table {} of document {} to a list of {} items", table_index, doc_id, count);
+        Ok(count)
+    }
+
+    /// Replace the list at `element_index` (an `add_list` list, or the run of list items
+    /// starting there) with a table. Each item is split at `separator` into cells, up to
+    /// `columns` cells when given ("Term: definition" items become a two-column table);
+    /// short rows are padded. `headers` adds a header row. Returns the number of rows.
+    pub fn list_to_table(&mut self, doc_id: &str, element_index: usize, separator: &str, columns: Option<usize>, headers: Option<Vec<String>>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        if separator.is_empty() {
+            anyhow::bail!("separator cannot be empty");
+        }
+        if columns == Some(0) {
+            anyhow::bail!("columns must be at least 1");
+        }
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (items, end): (Vec<String>, usize) = match ops.get(element_index) {
+            Some(DocxOp::List { items, .. }) => (items.clone(), element_index + 1),
+            Some(DocxOp::ListItem { .. }) => {
+                let run: Vec<String> = ops[element_index..].iter()
+                    .map_while(|op| match op { DocxOp::ListItem { text, .. } => Some(text.clone()), _ => None })
+                    .collect();
+                let end = element_index + run.len();
+                (run, end)
+            }
+            Some(_) => anyhow::bail!("Element {} is not a list", element_index),
+            None => anyhow::bail!("Element {} not found (document has {} elements)", element_index, ops.len()),
+        };
+        let mut rows: Vec<Vec<String>> = items.iter()
+            .map(|item| match columns {
+                Some(n) => item.splitn(n, separator).map(|c| c.trim().to_string()).collect(),
+                None => item.split(separator).map(|c| c.trim().to_string()).collect(),
+            })
+            .collect();
+        let headers = headers.filter(|h| !h.is_empty());
+        if let Some(headers) = &headers {
+            rows.insert(0, headers.clone());
+        }
+        let width = rows.iter().map(Vec::len).max().unwrap_or(1).max(columns.unwrap_or(1));
+        for row in &mut rows {
+            row.resize(width, String::new());
+        }
+        let count = rows.len();
+        let table = DocxOp::Table { data: TableData {
+            rows,
+            headers,
+            border_style: Some("single".into()),
+            col_widths: None,
+            merges: None,
+            cell_shading: None,
+            ..Default::default()
+        } };
+        ops.splice(element_index..end, [table]);
+        self.write_docx(doc_id)?;
+        info!("Converted the list at element {} of document {} to a table of {} rows", element_index, doc_id, count);
+        Ok(count)
+    }
+
     pub fn extract_text(&self, doc_id: &str) -> Result<String> {
         let _metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         
         // Use pure Rust text extraction
         use crate::pure_converter::PureRustConverter;
@@ -753,10 +3762,49 @@ impl DocxHandler {
         Ok(text)
     }
 
+    /// Text of the body plus the headers, footers, notes, comments or hidden text that
+    /// `scope` asks for
+    pub fn extract_text_with_scope(&self, doc_id: &str, scope: &crate::pure_converter::TextScope) -> Result<String> {
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        crate::pure_converter::PureRustConverter::new().extract_text_with_scope(&metadata.path, scope)
+            .with_context(|| format!("Failed to extract text from document {}", doc_id))
+    }
+
+    /// Body text as paragraphs, headings, list items and tables of formatted spans
+    pub fn extract_formatted_text(&self, doc_id: &str) -> Result<Vec<crate::docx_reader::FormattedBlock>> {
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        crate::docx_reader::read_formatted(&metadata.path)
+            .with_context(|| format!("Failed to extract formatted text from document {}", doc_id))
+    }
+
     pub fn get_metadata(&self, doc_id: &str) -> Result<DocxMetadata> {
-        self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))
-            .map(|m| m.clone())
+        let mut metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?
+            .clone();
+        metadata.sha256 = file_sha256(&metadata.path).ok();
+        // Page count from the PDF text layout, redone only when the file changed; left as
+        // recorded if the file can't be read
+        let Some(sha256) = metadata.sha256.clone() else { return Ok(metadata) };
+        let cached = self.page_counts.lock().unwrap().get(doc_id)
+            .filter(|(hash, _)| *hash == sha256)
+            .map(|(_, pages)| *pages);
+        metadata.page_count = match cached {
+            Some(pages) => Some(pages),
+            None => {
+                let converter = crate::pure_converter::PureRustConverter::new();
+                match converter.extract_text_from_docx(&metadata.path) {
+                    Ok(text) => {
+                        let pages = converter.page_count_for_text(&text);
+                        self.page_counts.lock().unwrap().insert(doc_id.to_string(), (sha256, pages));
+                        Some(pages)
+                    }
+                    Err(_) => metadata.page_count,
+                }
+            }
+        };
+        Ok(metadata)
     }
 
     /// Update paragraph formatting for paragraphs matching the selector (currently supports substring match)
@@ -767,17 +3815,15 @@ impl DocxHandler {
         new_format: DocxStyle,
     ) -> Result<usize> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        new_format.validate()?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         let mut updated = 0usize;
         for op in ops.iter_mut() {
             if let DocxOp::Paragraph { text, style } = op {
                 if contains.map(|needle| text.contains(needle)).unwrap_or(true) {
                     // Merge properties; prefer provided values over existing
-                    let mut merged = style.clone().unwrap_or(DocxStyle {
-                        font_family: None, font_size: None, bold: None, italic: None, underline: None,
-                        color: None, alignment: None, line_spacing: None,
-                    });
+                    let mut merged = style.clone().unwrap_or_default();
                     if new_format.font_family.is_some() { merged.font_family = new_format.font_family.clone(); }
                     if new_format.font_size.is_some() { merged.font_size = new_format.font_size; }
                     if new_format.bold.is_some() { merged.bold = new_format.bold; }
@@ -786,6 +3832,18 @@ impl DocxHandler {
                     if new_format.color.is_some() { merged.color = new_format.color.clone(); }
                     if new_format.alignment.is_some() { merged.alignment = new_format.alignment.clone(); }
                     if new_format.line_spacing.is_some() { merged.line_spacing = new_format.line_spacing; }
+                    if new_format.direction.is_some() { merged.direction = new_format.direction.clone(); }
+                    if new_format.highlight.is_some() { merged.highlight = new_format.highlight.clone(); }
+                    if new_format.strike.is_some() { merged.strike = new_format.strike; }
+                    if new_format.double_strike.is_some() { merged.double_strike = new_format.double_strike; }
+                    if new_format.vert_align.is_some() { merged.vert_align = new_format.vert_align.clone(); }
+                    if new_format.caps.is_some() { merged.caps = new_format.caps; }
+                    if new_format.small_caps.is_some() { merged.small_caps = new_format.small_caps; }
+                    if new_format.character_spacing.is_some() { merged.character_spacing = new_format.character_spacing; }
+                    if new_format.underline_style.is_some() { merged.underline_style = new_format.underline_style.clone(); }
+                    if new_format.borders.is_some() { merged.borders = new_format.borders.clone(); }
+                    if new_format.shading.is_some() { merged.shading = new_format.shading.clone(); }
+                    if new_format.hidden.is_some() { merged.hidden = new_format.hidden; }
                     *style = Some(merged);
                     updated += 1;
                 }
@@ -797,8 +3855,8 @@ impl DocxHandler {
 
     /// List tables with resolved merges and sizes
     pub fn get_tables_json(&self, doc_id: &str) -> Result<serde_json::Value> {
-        let ops = self.in_memory_ops.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         let mut tables = Vec::new();
         for (ti, op) in ops.iter().enumerate() {
             if let DocxOp::Table { data } = op {
@@ -819,8 +3877,8 @@ impl DocxHandler {
 
     /// List images with basic metadata
     pub fn list_images(&self, doc_id: &str) -> Result<serde_json::Value> {
-        let ops = self.in_memory_ops.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         let mut images = Vec::new();
         for (i, op) in ops.iter().enumerate() {
             if let DocxOp::Image { width, height, alt_text, .. } = op {
@@ -832,8 +3890,8 @@ impl DocxHandler {
 
     /// List hyperlinks present in the in-memory ops
     pub fn list_hyperlinks(&self, doc_id: &str) -> Result<serde_json::Value> {
-        let ops = self.in_memory_ops.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
         let mut links = Vec::new();
         for (i, op) in ops.iter().enumerate() {
             if let DocxOp::Hyperlink { text, url } = op {
@@ -846,7 +3904,7 @@ impl DocxHandler {
     /// Summarize fields from document and header/footer XML (best-effort)
     pub fn get_fields_summary(&self, doc_id: &str) -> Result<serde_json::Value> {
         let metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         let src_file = std::fs::File::open(&metadata.path)?;
         let mut archive = ZipArchive::new(src_file)?;
         let mut parts = vec!["word/document.xml".to_string()];
@@ -857,12 +3915,13 @@ impl DocxHandler {
             }
         }
         let mut fields: Vec<serde_json::Value> = Vec::new();
+        let instr_text = regex::Regex::new(r"<w:instrText[\s\S]*?>([\s\S]*?)</w:instrText>")?;
         for part in parts {
             if let Ok(mut f) = archive.by_name(&part) {
                 let mut xml = String::new();
                 use std::io::Read as _;
                 f.read_to_string(&mut xml)?;
-                for cap in regex::Regex::new(r"<w:instrText[\s\S]*?>([\s\S]*?)</w:instrText>")?.captures_iter(&xml) {
+                for cap in instr_text.captures_iter(&xml) {
                     let instr = cap.get(1).map(|m| m.as_str().trim()).unwrap_or("").to_string();
                     let kind = if instr.contains("TOC") { "TOC" } else if instr.contains("PAGE") { "PAGE" } else if instr.contains("NUMPAGES") { "NUMPAGES" } else { "OTHER" };
                     fields.push(serde_json::json!({"part": part, "instruction": instr, "kind": kind}));
@@ -875,7 +3934,7 @@ impl DocxHandler {
     /// Remove personal info (best-effort): clear in-memory metadata and scrub core.xml if present
     pub fn strip_personal_info(&mut self, doc_id: &str) -> Result<()> {
         let meta = self.documents.get_mut(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         meta.author = None; meta.title = None; meta.subject = None;
         // Try to scrub docProps/core.xml
         let src_file = std::fs::File::open(&meta.path)?;
@@ -899,7 +3958,7 @@ impl DocxHandler {
         let temp_path = meta.path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
@@ -927,7 +3986,7 @@ impl DocxHandler {
         author: Option<String>,
     ) -> Result<()> {
         let meta = self.documents.get_mut(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         if let Some(t) = title { meta.title = Some(t); }
         if let Some(s) = subject { meta.subject = Some(s); }
         if let Some(a) = author { meta.author = Some(a); }
@@ -936,7 +3995,7 @@ impl DocxHandler {
 
     pub fn get_document_properties_json(&self, doc_id: &str) -> Result<serde_json::Value> {
         let meta = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
         Ok(serde_json::json!({
             "title": meta.title,
             "subject": meta.subject,
@@ -946,23 +4005,158 @@ impl DocxHandler {
         }))
     }
 
+    /// Embed a TrueType/OpenType font in the package so the document renders with it on
+    /// machines without the font installed. The family name comes from the font unless
+    /// `family` overrides it; the style (regular, bold, ...) from its subfamily. Embedding
+    /// the same family and style again replaces the earlier file. Returns (family, slot).
+    pub fn embed_font(&mut self, doc_id: &str, font_path: &Path, family: Option<&str>) -> Result<(String, String)> {
+        self.ensure_modifiable(doc_id)?;
+        let info = crate::fonts_cli::font_info(font_path)?;
+        if !info.valid {
+            anyhow::bail!("{} is not a TrueType/OpenType font", font_path.display());
+        }
+        if info.restricted_license {
+            anyhow::bail!("{} has a restricted license and may not be embedded", font_path.display());
+        }
+        let family = family.map(str::trim).filter(|f| !f.is_empty()).map(String::from)
+            .or(info.family.clone())
+            .ok_or_else(|| anyhow::anyhow!("{} has no family name; pass font_name", font_path.display()))?;
+        let subfamily = info.subfamily.as_deref().unwrap_or("").to_ascii_lowercase();
+        let slot = match (subfamily.contains("bold"), subfamily.contains("italic") || subfamily.contains("oblique")) {
+            (true, true) => "embedBoldItalic",
+            (true, false) => "embedBold",
+            (false, true) => "embedItalic",
+            (false, false) => "embedRegular",
+        };
+        let data = std::fs::read(font_path)?;
+
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        ops.retain(|op| !matches!(op, DocxOp::EmbedFont { family: f, slot: s, .. } if *f == family && s == slot));
+        ops.push(DocxOp::EmbedFont { family: family.clone(), slot: slot.to_string(), data: data.into() });
+        self.write_docx(doc_id)?;
+        Ok((family, slot.to_string()))
+    }
+
     /// Insert a paragraph after the first heading that matches `heading_text`
     pub fn insert_after_heading(&mut self, doc_id: &str, heading_text: &str, text: &str) -> Result<bool> {
         self.ensure_modifiable(doc_id)?;
-        let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
-        if let Some(pos) = ops.iter().position(|op| matches!(op, DocxOp::Heading { text: t, .. } if t == heading_text)) {
-            ops.insert(pos + 1, DocxOp::Paragraph { text: text.to_string(), style: None });
-            self.write_docx(doc_id)?;
-            return Ok(true);
+        if !self.in_memory_ops.get(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?.iter().any(|op| matches!(op, DocxOp::Heading { text: t, .. } if t == heading_text)) {
+            return Ok(false);
         }
-        Ok(false)
+        let paragraph = NewElement::Paragraph { text: text.to_string(), style: None };
+        self.insert_at(doc_id, &InsertAnchor::Heading(heading_text.to_string()), "after", vec![paragraph])?;
+        Ok(true)
+    }
+
+    /// Insert elements before or after an anchor: a heading (after means directly
+    /// below the heading line), a bookmark, an element index or a table. Returns the
+    /// element index of the first inserted element.
+    pub fn insert_at(&mut self, doc_id: &str, anchor: &InsertAnchor, position: &str, elements: Vec<NewElement>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let after = match position.trim().to_ascii_lowercase().as_str() {
+            "before" => false,
+            "after" => true,
+            other => anyhow::bail!("position must be before or after (got '{}')", other),
+        };
+        if elements.is_empty() {
+            anyhow::bail!("No elements to insert");
+        }
+        let new_ops = new_element_ops(elements)?;
+
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let at = anchor_position(ops, anchor, after)?;
+        let count = new_ops.len();
+        ops.splice(at..at, new_ops);
+        self.write_docx(doc_id)?;
+        info!("Inserted {} elements at {} in document {}", count, at, doc_id);
+        Ok(at)
+    }
+
+    /// Replace the content directly under a heading (up to the next heading of any level)
+    /// with `blocks`, such as the placeholder left by `create_outline`. The heading and
+    /// every section after it, its own subsections included, stay as they are, and so do
+    /// bookmarks, tags and other settings in the old content. Headings in `blocks` must
+    /// be deeper than the section's own. Returns the number of elements removed.
+    pub fn set_section_content(&mut self, doc_id: &str, heading: Option<&str>, element_index: Option<usize>, blocks: Vec<NewElement>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (start, end) = section_bounds(ops, heading, element_index, false)?;
+        let level = match &ops[start] {
+            DocxOp::Heading { style, .. } => heading_level(style),
+            _ => 1,
+        };
+        if let Some(NewElement::Heading { text, level: sub }) = blocks.iter().find(|b| matches!(b, NewElement::Heading { level: sub, .. } if *sub <= level)) {
+            anyhow::bail!("Heading '{}' (level {}) would end the section; headings in its content must be deeper than level {}", text, sub, level);
+        }
+        let new_ops = new_element_ops(blocks)?;
+        let (kept, removed): (Vec<DocxOp>, Vec<DocxOp>) = ops.drain(start + 1..end)
+            .partition(|op| is_document_setting(op) || matches!(op, DocxOp::Highlight { .. } | DocxOp::InvisibleText { .. }));
+        let count = removed.len();
+        ops.splice(start + 1..start + 1, kept.into_iter().chain(new_ops));
+        self.write_docx(doc_id)?;
+        info!("Replaced {} elements under the heading at element {} of document {}", count, start, doc_id);
+        Ok(count)
+    }
+
+    /// Move a heading and everything under it (up to the next heading of the same or a
+    /// higher level) before or after another heading's section. With "after", the
+    /// section lands after the target's subsections too. Returns the number of
+    /// elements moved.
+    pub fn move_section(&mut self, doc_id: &str, heading: &str, position: &str, target: &str) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let after = match position.trim().to_ascii_lowercase().as_str() {
+            "before" => false,
+            "after" => true,
+            other => anyhow::bail!("position must be before or after (got '{}')", other),
+        };
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (start, end) = section_bounds(ops, Some(heading), None, true)?;
+        let (target_start, target_end) = section_bounds(ops, Some(target), None, true)?;
+        if target_start >= start && target_start < end {
+            anyhow::bail!("Cannot move section '{}' relative to '{}', which is inside it", heading, target);
+        }
+        let insert_at = if after { target_end } else { target_start };
+        let moved: Vec<DocxOp> = ops.drain(start..end).collect();
+        let count = moved.len();
+        let insert_at = if insert_at > start { insert_at - count } else { insert_at };
+        ops.splice(insert_at..insert_at, moved);
+        self.write_docx(doc_id)?;
+        info!("Moved section '{}' ({} elements) {} '{}' in {}", heading, count, position.trim(), target, doc_id);
+        Ok(count)
+    }
+
+    /// Delete a heading and everything under it, up to the next heading of the same or
+    /// a higher level. Returns the number of elements removed.
+    pub fn delete_section(&mut self, doc_id: &str, heading: Option<&str>, element_index: Option<usize>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let (start, end) = section_bounds(&self.in_memory_ops.get(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?, heading, element_index, true)?;
+        self.delete_range(doc_id, start, end - 1)
+    }
+
+    /// Delete elements `start..=end` (element indices as in `get_outline` and
+    /// `extract_text`). Headers, footers, page setup, highlights and content-tag markers
+    /// in the range are kept. Returns the number of elements removed.
+    pub fn delete_range(&mut self, doc_id: &str, start: usize, end: usize) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        if start > end || end >= ops.len() {
+            anyhow::bail!("Invalid element range {}..={} (document has {} elements)", start, end, ops.len());
+        }
+        let (kept, removed): (Vec<DocxOp>, Vec<DocxOp>) = ops.drain(start..=end)
+            .partition(|op| is_document_setting(op) || matches!(op, DocxOp::Highlight { .. } | DocxOp::InvisibleText { .. }));
+        let count = removed.len();
+        ops.splice(start..start, kept);
+        self.write_docx(doc_id)?;
+        info!("Deleted {} elements in {}..={} of document {}", count, start, end, doc_id);
+        Ok(count)
     }
 
     /// Remove external hyperlinks (basic sanitizer)
     pub fn sanitize_external_links(&mut self, doc_id: &str) -> Result<usize> {
         self.ensure_modifiable(doc_id)?;
         let removed = {
-            let ops = self.in_memory_ops.get_mut(doc_id).unwrap();
+            let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
             let before = ops.len();
             ops.retain(|op| match op {
                 DocxOp::Hyperlink { url, .. } => {
@@ -977,142 +4171,2932 @@ impl DocxHandler {
         Ok(removed)
     }
 
-    /// Redact text using advanced find/replace with a block character
-    pub fn redact_text(&mut self, doc_id: &str, pattern: &str, use_regex: bool, whole_word: bool, case_sensitive: bool) -> Result<usize> {
-        self.find_and_replace_advanced(doc_id, pattern, "█", case_sensitive, whole_word, use_regex)
+    /// Redact text using advanced find/replace with a block character
+    pub fn redact_text(&mut self, doc_id: &str, pattern: &str, use_regex: bool, whole_word: bool, case_sensitive: bool) -> Result<usize> {
+        self.find_and_replace_advanced(doc_id, pattern, REDACTION_MARK, case_sensitive, whole_word, use_regex)
+    }
+
+    /// Turn review comments of the form "change X to Y" into tracked changes by `author`
+    /// (`apply`), or just list them with the text they point at. Comments that ask for
+    /// something else come back as `other_comments` for the caller to handle.
+    pub fn apply_comment_suggestions(&mut self, doc_id: &str, apply: bool, author: &str) -> Result<serde_json::Value> {
+        use crate::review::{Revision, Status};
+        let metadata = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        if apply && self.in_memory_ops.contains_key(doc_id) {
+            anyhow::bail!("Document {} is edited through the op model, which keeps no comments or tracked changes; apply suggestions to an opened document", doc_id);
+        }
+        let path = metadata.path.clone();
+        let comments = match read_part(&path, "word/comments.xml")? {
+            Some(xml) => crate::review::read_comments(&xml)?,
+            None => Vec::new(),
+        };
+        let mut document = read_part(&path, "word/document.xml")?.unwrap_or_default();
+        let revision = Revision {
+            author: author.to_string(),
+            date: (!self.package_options.deterministic).then(|| Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+        };
+
+        let mut suggestions = Vec::new();
+        let mut other_comments = Vec::new();
+        let mut applied = 0usize;
+        for comment in &comments {
+            let anchor = crate::review::anchor_text(&document, &comment.id);
+            let Some((find, replace)) = crate::review::parse_suggestion(&comment.text) else {
+                other_comments.push(serde_json::json!({
+                    "comment_id": comment.id, "author": comment.author, "comment": comment.text, "anchor_text": anchor,
+                }));
+                continue;
+            };
+            let status = if !apply {
+                Status::Pending
+            } else {
+                match crate::review::track_replacement(&document, &comment.id, &find, &replace, &revision) {
+                    Ok(updated) => {
+                        document = updated;
+                        applied += 1;
+                        Status::Applied
+                    }
+                    Err(status) => status,
+                }
+            };
+            suggestions.push(serde_json::json!({
+                "comment_id": comment.id, "author": comment.author, "comment": comment.text, "anchor_text": anchor,
+                "find": find, "replace": replace, "status": status,
+            }));
+        }
+
+        if applied > 0 {
+            rewrite_xml_parts(&path, self.package_options, |name, _| (name == "word/document.xml").then(|| document.clone()))?;
+            let metadata = self.documents.get_mut(doc_id).unwrap();
+            metadata.modified_at = Utc::now();
+            metadata.activity.dirty = true;
+            info!("Applied {} comment suggestions as tracked changes in document {}", applied, doc_id);
+        }
+        Ok(serde_json::json!({
+            "applied": applied,
+            "suggestions": suggestions,
+            "other_comments": other_comments,
+        }))
+    }
+
+    pub fn save_document(&mut self, doc_id: &str, output_path: &Path) -> Result<()> {
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        
+        let bytes = fs::copy(&metadata.path, output_path)
+            .with_context(|| format!("Failed to save document to {:?}", output_path))?;
+        metadata.saved_at = Some(Utc::now());
+        metadata.activity.bytes_written += bytes;
+        metadata.activity.dirty = false;
+        
+        info!("Saved document {} to {:?}", doc_id, output_path);
+        Ok(())
+    }
+
+    /// Open a document stored in S3, GCS or Azure Blob (see `object_store`)
+    pub fn open_remote_document(&mut self, uri: &crate::object_store::ObjectUri, max_bytes: usize) -> Result<String> {
+        let download = self.temp_dir.join(format!(".download-{}.docx", Uuid::new_v4()));
+        let opened = crate::object_store::download(uri, &download, max_bytes)
+            .and_then(|_| self.open_document(&download));
+        let _ = fs::remove_file(&download);
+        opened
+    }
+
+    /// Upload a document's working copy to object storage
+    pub fn save_document_remote(&mut self, doc_id: &str, uri: &crate::object_store::ObjectUri) -> Result<()> {
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        crate::object_store::upload(&metadata.path, uri)?;
+        metadata.saved_at = Some(Utc::now());
+        metadata.activity.bytes_written += fs::metadata(&metadata.path).map(|m| m.len()).unwrap_or(0);
+        metadata.activity.dirty = false;
+        info!("Saved document {} to {:?}", doc_id, uri);
+        Ok(())
+    }
+
+    /// Copy a document's working file and op list to a new id so it can be edited
+    /// independently. The copy keeps the workspace; `title` defaults to "<title> (copy)".
+    pub fn clone_document(&mut self, doc_id: &str, title: Option<&str>) -> Result<String> {
+        let source = self.documents.get(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?
+            .clone();
+        let new_id = Uuid::new_v4().to_string();
+        let new_path = self.temp_dir.join(format!("{}.docx", new_id));
+        fs::copy(&source.path, &new_path)
+            .with_context(|| format!("Failed to copy document {} to {:?}", doc_id, new_path))?;
+
+        let now = Utc::now();
+        let title = match title {
+            Some(t) => Some(t.trim().to_string()).filter(|t| !t.is_empty()),
+            None => source.title.as_ref().map(|t| format!("{} (copy)", t)),
+        };
+        self.documents.insert(new_id.clone(), DocxMetadata {
+            id: new_id.clone(),
+            path: new_path,
+            created_at: now,
+            modified_at: now,
+            saved_at: None,
+            title,
+            activity: DocActivity::default(),
+            fidelity: None,
+            ..source
+        });
+        if let Some(ops) = self.in_memory_ops.get(doc_id)?.map(|ops| ops.into_owned()) {
+            self.in_memory_ops.insert(new_id.clone(), ops);
+        }
+        info!("Cloned document {} as {}", doc_id, new_id);
+        Ok(new_id)
+    }
+
+    /// A document's op list as a JSON journal: the recipe `replay_ops` rebuilds it from.
+    /// Images and fonts are embedded as base64, so the journal is self-contained.
+    pub fn export_ops(&self, doc_id: &str) -> Result<String> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| anyhow::anyhow!("Failed to read the ops of document {}", doc_id))?;
+        let journal = OpsJournal {
+            format: OPS_JOURNAL_FORMAT.to_string(),
+            version: OPS_JOURNAL_VERSION,
+            title: self.documents.get(doc_id).and_then(|m| m.title.clone()),
+            ops: ops.into_owned(),
+        };
+        serde_json::to_string_pretty(&journal).context("Failed to serialize ops journal")
+    }
+
+    /// Keep a copy of the document as it is now, as a baseline for `summarize_changes`
+    pub fn snapshot_document(&mut self, doc_id: &str, label: Option<&str>) -> Result<serde_json::Value> {
+        let snapshot_id = Uuid::new_v4().to_string();
+        let elements = self.in_memory_ops.snapshot(doc_id, &snapshot_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let label = label.map(str::trim).filter(|l| !l.is_empty()).map(str::to_string);
+        let taken_at = Utc::now();
+        self.snapshots.insert(snapshot_id.clone(), Snapshot { doc_id: doc_id.to_string(), label: label.clone(), taken_at });
+        let mut taken: Vec<(&String, DateTime<Utc>)> = self.snapshots.iter()
+            .filter(|(_, s)| s.doc_id == doc_id)
+            .map(|(id, s)| (id, s.taken_at))
+            .collect();
+        let mut dropped = None;
+        if taken.len() > MAX_SNAPSHOTS {
+            taken.sort_by_key(|(_, taken_at)| *taken_at);
+            let oldest = taken[0].0.clone();
+            self.snapshots.remove(&oldest);
+            self.in_memory_ops.remove_snapshot(&oldest);
+            info!("Dropped snapshot {} of document {}: over {} snapshots", oldest, doc_id, MAX_SNAPSHOTS);
+            dropped = Some(oldest);
+        }
+        info!("Took snapshot {} of document {}", snapshot_id, doc_id);
+        let mut result = serde_json::json!({
+            "snapshot_id": snapshot_id,
+            "label": label,
+            "taken_at": taken_at,
+            "elements": elements,
+        });
+        if let Some(dropped) = dropped {
+            result["dropped_snapshot_id"] = dropped.into();
+        }
+        Ok(result)
+    }
+
+    /// What changed since a snapshot of this document, given by id or label (the latest
+    /// snapshot with that label): sections added and removed, word deltas per section and
+    /// modified tables, plus the same as a bulleted `text` log
+    pub fn summarize_changes(&self, doc_id: &str, since: &str) -> Result<serde_json::Value> {
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let (snapshot_id, snapshot) = self.snapshots.get_key_value(since)
+            .filter(|(_, s)| s.doc_id == doc_id)
+            .or_else(|| self.snapshots.iter()
+                .filter(|(_, s)| s.doc_id == doc_id && s.label.as_deref() == Some(since))
+                .max_by_key(|(_, s)| s.taken_at))
+            .ok_or_else(|| anyhow::anyhow!("No snapshot '{}' of document {}; take one with snapshot_document", since, doc_id))?;
+        let before = self.in_memory_ops.snapshot_ops(snapshot_id)?
+            .ok_or_else(|| anyhow::anyhow!("Snapshot {} has no ops", snapshot_id))?;
+        let log = crate::changes::compare(&crate::changes::sections(&before), &crate::changes::sections(&ops));
+        let name = match &snapshot.label {
+            Some(label) => format!("snapshot \"{}\" ({})", label, snapshot.taken_at.format("%Y-%m-%d %H:%M UTC")),
+            None => format!("snapshot of {}", snapshot.taken_at.format("%Y-%m-%d %H:%M UTC")),
+        };
+        let mut report = serde_json::to_value(&log)?;
+        report["text"] = log.to_text(&name).into();
+        report["changed"] = (!log.is_empty()).into();
+        Ok(report)
+    }
+
+    /// Build a new document from a journal written by `export_ops`. `title` overrides
+    /// the title recorded in the journal.
+    pub fn replay_ops(&mut self, journal: &str, title: Option<&str>) -> Result<String> {
+        let journal: OpsJournal = serde_json::from_str(journal).context("Invalid ops journal")?;
+        if journal.format != OPS_JOURNAL_FORMAT {
+            anyhow::bail!("Invalid ops journal: format is '{}', expected '{}'", journal.format, OPS_JOURNAL_FORMAT);
+        }
+        if journal.version > OPS_JOURNAL_VERSION {
+            anyhow::bail!("Ops journal version {} is newer than this server supports ({})", journal.version, OPS_JOURNAL_VERSION);
+        }
+        let count = journal.ops.len();
+        let doc_id = self.create_document()?;
+        self.in_memory_ops.insert(doc_id.clone(), journal.ops);
+        if let Err(e) = self.write_docx(&doc_id) {
+            let _ = self.close_document(&doc_id);
+            return Err(e.context("Failed to render replayed ops"));
+        }
+        let title = title.map(str::to_string).or(journal.title);
+        self.rename_document(&doc_id, title.as_deref().unwrap_or(""))?;
+        info!("Replayed {} ops into document {}", count, doc_id);
+        Ok(doc_id)
+    }
+
+    /// Stitch documents into a new one, in order, with a page break between parts. The
+    /// first part that has a header, footer, page setup or default font supplies it; fonts and bookmarks are taken
+    /// once. The same goes for a document-wide watermark, while section watermarks stay
+    /// behind, as section numbers change once the parts are joined. With `toc`, a table
+    /// of contents (levels 1-3) opens the document. With `continuous_numbering`, heading
+    /// numbers typed into the parts ("2.", "3.1") are replaced by one sequence over the
+    /// whole document. `style_map` renames heading styles and fonts in every part.
+    pub fn assemble_document(&mut self, parts: &[String], title: Option<&str>, toc: bool, continuous_numbering: bool, style_map: Option<&StyleMap>) -> Result<String> {
+        if parts.is_empty() {
+            anyhow::bail!("assemble_document needs at least one part");
+        }
+        if let Some(map) = style_map {
+            map.validate()?;
+        }
+        for part in parts {
+            self.ensure_modifiable(part)?;
+        }
+        let mut ops = Vec::new();
+        if toc {
+            ops.push(DocxOp::Toc { from_level: 1, to_level: 3, right_align_dots: true });
+            ops.push(DocxOp::PageBreak);
+        }
+        let (mut header, mut footer, mut page_setup) = (None, None, None);
+        let (mut header_layout, mut footer_layout, mut default_font) = (None, None, None);
+        let (mut default_proofing, mut watermark) = (None, None);
+        let mut fonts: Vec<DocxOp> = Vec::new();
+        let mut bookmarks = std::collections::HashSet::new();
+        for (n, part) in parts.iter().enumerate() {
+            if n > 0 {
+                ops.push(DocxOp::PageBreak);
+            }
+            for op in self.in_memory_ops.get(part)?.ok_or_else(|| missing_ops(&self.documents, part))?.iter() {
+                match op {
+                    DocxOp::Header(_) => { header.get_or_insert_with(|| op.clone()); }
+                    DocxOp::Footer(_) => { footer.get_or_insert_with(|| op.clone()); }
+                    DocxOp::PageSetup(_) => { page_setup.get_or_insert_with(|| op.clone()); }
+                    DocxOp::HeaderLayout { footer: false, .. } => { header_layout.get_or_insert_with(|| op.clone()); }
+                    DocxOp::HeaderLayout { footer: true, .. } => { footer_layout.get_or_insert_with(|| op.clone()); }
+                    DocxOp::DefaultFont { .. } => { default_font.get_or_insert_with(|| op.clone()); }
+                    DocxOp::DefaultProofing(_) => { default_proofing.get_or_insert_with(|| op.clone()); }
+                    DocxOp::Watermark(w) if w.section.is_none() => { watermark.get_or_insert_with(|| op.clone()); }
+                    DocxOp::Watermark(_) => {}
+                    DocxOp::EmbedFont { family, slot, .. } => {
+                        if !fonts.iter().any(|f| matches!(f, DocxOp::EmbedFont { family: fa, slot: sl, .. } if fa == family && sl == slot)) {
+                            fonts.push(op.clone());
+                        }
+                    }
+                    DocxOp::BookmarkAfterHeading { name, .. } => {
+                        if bookmarks.insert(name.clone()) { ops.push(op.clone()); }
+                    }
+                    _ => ops.push(op.clone()),
+                }
+            }
+        }
+        if let Some(map) = style_map {
+            map.apply(&mut ops);
+            map.apply(default_font.as_mut_slice());
+        }
+        if continuous_numbering {
+            renumber_heading_text(&mut ops);
+        }
+        ops.extend(header);
+        ops.extend(footer);
+        ops.extend(header_layout);
+        ops.extend(footer_layout);
+        ops.extend(default_font);
+        ops.extend(default_proofing);
+        ops.extend(watermark);
+        ops.extend(page_setup);
+        ops.extend(fonts);
+
+        let doc_id = self.create_document()?;
+        if let Some(title) = title {
+            self.rename_document(&doc_id, title)?;
+        }
+        self.in_memory_ops.insert(doc_id.clone(), ops);
+        self.write_docx(&doc_id)?;
+        info!("Assembled {} parts into document {}", parts.len(), doc_id);
+        Ok(doc_id)
+    }
+
+    /// Insert a .docx or .md fragment (a clause) at `position` (default: the end), keeping
+    /// its headings, lists, tables, images and paragraph formatting. Returns the number of
+    /// elements inserted.
+    pub fn insert_fragment(&mut self, doc_id: &str, path: &Path, position: Option<usize>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let fragment = fragment_ops(path)?;
+        if fragment.is_empty() {
+            anyhow::bail!("Fragment {:?} has no content", path);
+        }
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let position = position.unwrap_or(ops.len());
+        if position > ops.len() {
+            anyhow::bail!("Position {} is past the end of the document ({} elements)", position, ops.len());
+        }
+        let count = fragment.len();
+        ops.splice(position..position, fragment);
+        self.write_docx(doc_id)?;
+        info!("Inserted fragment {:?} into document {} at {}", path, doc_id, position);
+        Ok(count)
+    }
+
+    /// Insert the content of another document at an anchor (the end by default).
+    /// `source` is a document id from this session or the path of a .docx or .md file.
+    /// Headings come across as headings of the same level and paragraphs keep their
+    /// formatting; the source's header, footer, page setup and default font are left
+    /// behind so the target's apply. `style_map` renames heading styles and fonts on the
+    /// way in. Fonts the content needs are embedded once. Returns the number of elements
+    /// inserted.
+    pub fn insert_document(&mut self, doc_id: &str, source: &str, anchor: Option<&InsertAnchor>, position: &str, style_map: Option<&StyleMap>) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        if let Some(map) = style_map {
+            map.validate()?;
+        }
+        if source == doc_id {
+            anyhow::bail!("A document cannot be inserted into itself");
+        }
+        let source_ops = match (self.in_memory_ops.get(source)?, self.documents.get(source)) {
+            (Some(ops), _) => ops.into_owned(),
+            (None, Some(metadata)) => crate::docx_reader::read_docx(&metadata.path)?.ops,
+            (None, None) if Path::new(source).is_file() => fragment_ops(Path::new(source))?,
+            (None, None) => anyhow::bail!("No document or file named {}", source),
+        };
+        let after = match position.trim().to_ascii_lowercase().as_str() {
+            "before" => false,
+            "after" => true,
+            other => anyhow::bail!("position must be before or after (got '{}')", other),
+        };
+        let mut content: Vec<DocxOp> = source_ops.iter()
+            .filter(|op| !is_document_setting(op))
+            .cloned()
+            .collect();
+        if content.is_empty() {
+            anyhow::bail!("Document {} has no content to insert", source);
+        }
+        if let Some(map) = style_map {
+            map.apply(&mut content);
+        }
+        let families: std::collections::HashSet<&str> = content.iter()
+            .filter_map(|op| match op {
+                DocxOp::Paragraph { style: Some(style), .. } => style.font_family.as_deref(),
+                _ => None,
+            })
+            .collect();
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let fonts: Vec<DocxOp> = source_ops.iter()
+            .filter(|op| matches!(op, DocxOp::EmbedFont { family, slot, .. }
+                if families.contains(family.as_str())
+                    && !ops.iter().any(|t| matches!(t, DocxOp::EmbedFont { family: f, slot: s, .. } if f == family && s == slot))))
+            .cloned()
+            .collect();
+        let at = match anchor {
+            Some(anchor) => anchor_position(ops, anchor, after)?,
+            None => ops.len(),
+        };
+        let count = content.len();
+        ops.splice(at..at, content);
+        ops.extend(fonts);
+        self.write_docx(doc_id)?;
+        info!("Inserted {} elements of {} into document {} at {}", count, source, doc_id, at);
+        Ok(count)
+    }
+
+    /// Number Heading1-6 paragraphs with Word multilevel numbering: `decimal` (1. / 1.1. /
+    /// 1.1.1.), `legal` (1 / 1.1 / 1.1.1) or `outline` (I. / A. / 1. / a) / i) / (1)); `none`
+    /// removes it. Numbers typed into heading text are stripped so they don't double up.
+    /// Returns the number of headings.
+    pub fn enable_heading_numbering(&mut self, doc_id: &str, scheme: &str) -> Result<usize> {
+        self.ensure_modifiable(doc_id)?;
+        let scheme = scheme.trim().to_ascii_lowercase();
+        if scheme != "none" && heading_scheme(&scheme).is_none() {
+            anyhow::bail!("Unknown numbering scheme '{}' (use decimal, legal, outline or none)", scheme);
+        }
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        ops.retain(|op| !matches!(op, DocxOp::HeadingNumbering { .. }));
+        if scheme != "none" {
+            ops.push(DocxOp::HeadingNumbering { scheme: scheme.clone() });
+            strip_heading_numbers(ops);
+        }
+        let headings = ops.iter().filter(|op| matches!(op, DocxOp::Heading { .. })).count();
+        self.write_docx(doc_id)?;
+        info!("Set heading numbering of document {} to {}", doc_id, scheme);
+        Ok(headings)
+    }
+
+    /// Renumbering pass after structural edits. With heading numbering on, Word keeps the
+    /// numbers current, so this only strips numbers typed back into heading text; otherwise
+    /// typed numbers, if the headings use any, are rewritten as one sequence. Returns each
+    /// heading with its number.
+    pub fn renumber_headings(&mut self, doc_id: &str) -> Result<Vec<serde_json::Value>> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let numbers = match active_heading_scheme(ops) {
+            Some(levels) => {
+                strip_heading_numbers(ops);
+                heading_numbers(ops, levels)
+            }
+            None => {
+                let typed = ops.iter().any(|op| matches!(op, DocxOp::Heading { text, .. } if strip_typed_number(text).is_some()));
+                if typed {
+                    renumber_heading_text(ops);
+                }
+                vec![None; ops.len()]
+            }
+        };
+        let headings = ops.iter().zip(numbers).enumerate()
+            .filter_map(|(i, (op, number))| match op {
+                DocxOp::Heading { text, style } => Some(serde_json::json!({
+                    "element_index": i,
+                    "level": heading_level(style),
+                    "number": number,
+                    "text": text,
+                })),
+                _ => None,
+            })
+            .collect();
+        self.write_docx(doc_id)?;
+        Ok(headings)
+    }
+
+    /// Clean up headings after heavy editing: apply `case` (title or sentence case) to
+    /// the heading text, and with `renumber` replace the assorted number prefixes people
+    /// type ("2)", "IV.", "Section 3:", "1.2.") with one sequence. With heading numbering
+    /// on, Word numbers the headings, so typed prefixes are removed instead; headings in a
+    /// document that types no numbers are left unnumbered. Returns the headings that changed.
+    pub fn normalize_headings(&mut self, doc_id: &str, case: Option<crate::text_transform::Transform>, renumber: bool) -> Result<Vec<serde_json::Value>> {
+        self.ensure_modifiable(doc_id)?;
+        let ops = self.in_memory_ops.get_mut(doc_id)?.ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let before: Vec<Option<String>> = ops.iter()
+            .map(|op| match op { DocxOp::Heading { text, .. } => Some(text.clone()), _ => None })
+            .collect();
+        let mut numbered = false;
+        for op in ops.iter_mut() {
+            let DocxOp::Heading { text, .. } = op else { continue };
+            let (prefix, bare) = split_heading_prefix(text);
+            numbered |= !prefix.is_empty();
+            let bare = case.map(|c| c.apply(bare)).unwrap_or_else(|| bare.to_string());
+            *text = if renumber { bare } else { format!("{}{}", prefix, bare) };
+        }
+        if renumber && numbered && active_heading_scheme(ops).is_none() {
+            let numbers = heading_numbers(ops, &TYPED_HEADING_NUMBERS);
+            for (op, number) in ops.iter_mut().zip(numbers) {
+                if let (DocxOp::Heading { text, .. }, Some(number)) = (op, number) {
+                    *text = format!("{} {}", number, text);
+                }
+            }
+        }
+        let changed: Vec<serde_json::Value> = ops.iter().zip(before).enumerate()
+            .filter_map(|(i, (op, before))| match (op, before) {
+                (DocxOp::Heading { text, style }, Some(before)) if *text != before => Some(serde_json::json!({
+                    "element_index": i,
+                    "level": heading_level(style),
+                    "before": before,
+                    "after": text,
+                })),
+                _ => None,
+            })
+            .collect();
+        if !changed.is_empty() {
+            self.write_docx(doc_id)?;
+        }
+        info!("Normalized {} headings of document {}", changed.len(), doc_id);
+        Ok(changed)
+    }
+
+    /// Copy elements `start..=end` of one document into another at `position` (default:
+    /// the end). Styles, list numbering and image data travel with the elements; fonts the
+    /// copied text uses are embedded in the target too when the source embeds them.
+    /// Headers, footers, watermarks, bookmarks, tag markers and heading numbering stay
+    /// behind, since they belong to the source document's layout. `style_map` renames
+    /// heading styles and fonts in the copy. Returns the number of elements copied.
+    pub fn copy_elements(&mut self, source_id: &str, start: usize, end: usize, target_id: &str, position: Option<usize>, style_map: Option<&StyleMap>) -> Result<usize> {
+        self.ensure_modifiable(source_id)?;
+        self.ensure_modifiable(target_id)?;
+        if let Some(map) = style_map {
+            map.validate()?;
+        }
+        let source = self.in_memory_ops.get(source_id)?.ok_or_else(|| missing_ops(&self.documents, source_id))?;
+        if start > end || end >= source.len() {
+            anyhow::bail!("Invalid element range {}..={} (source document has {} elements)", start, end, source.len());
+        }
+        let mut copied: Vec<DocxOp> = source[start..=end].iter()
+            .filter(|op| !is_document_setting(op))
+            .cloned()
+            .collect();
+        if copied.is_empty() {
+            anyhow::bail!("Elements {}..={} hold no copyable content", start, end);
+        }
+        if let Some(map) = style_map {
+            map.apply(&mut copied);
+        }
+        let families: std::collections::HashSet<&str> = copied.iter()
+            .filter_map(|op| match op {
+                DocxOp::Paragraph { style: Some(style), .. } => style.font_family.as_deref(),
+                _ => None,
+            })
+            .collect();
+        let target = self.in_memory_ops.get(target_id)?.ok_or_else(|| missing_ops(&self.documents, target_id))?;
+        let fonts: Vec<DocxOp> = source.iter()
+            .filter(|op| matches!(op, DocxOp::EmbedFont { family, slot, .. }
+                if families.contains(family.as_str())
+                    && !target.iter().any(|t| matches!(t, DocxOp::EmbedFont { family: f, slot: s, .. } if f == family && s == slot))))
+            .cloned()
+            .collect();
+        let position = position.unwrap_or(target.len());
+        if position > target.len() {
+            anyhow::bail!("Position {} is past the end of the target document ({} elements)", position, target.len());
+        }
+
+        let count = copied.len();
+        let target = self.in_memory_ops.get_mut(target_id)?.ok_or_else(|| missing_ops(&self.documents, target_id))?;
+        target.splice(position..position, copied);
+        target.extend(fonts);
+        self.write_docx(target_id)?;
+        info!("Copied elements {}..={} of document {} into {} at {}", start, end, source_id, target_id, position);
+        Ok(count)
+    }
+
+    pub fn close_document(&mut self, doc_id: &str) -> Result<()> {
+        let metadata = self.documents.remove(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        
+        if metadata.path.exists() {
+            fs::remove_file(&metadata.path)?;
+        }
+        self.in_memory_ops.remove(doc_id);
+        let in_memory_ops = &mut self.in_memory_ops;
+        self.snapshots.retain(|id, snapshot| {
+            let keep = snapshot.doc_id != doc_id;
+            if !keep {
+                in_memory_ops.remove_snapshot(id);
+            }
+            keep
+        });
+        self.page_counts.lock().unwrap().remove(doc_id);
+        
+        info!("Closed document {}", doc_id);
+        Ok(())
+    }
+
+    /// Set the session title shown by `list_documents`; an empty title clears it
+    pub fn rename_document(&mut self, doc_id: &str, title: &str) -> Result<()> {
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let title = title.trim();
+        metadata.title = (!title.is_empty()).then(|| title.to_string());
+        Ok(())
+    }
+
+    pub fn list_documents(&self) -> Vec<DocxMetadata> {
+        self.documents.values().cloned().collect()
+    }
+
+    /// One page of open documents, oldest first, with a summary per document for pickers:
+    /// word and heading counts, working-copy size, and whether it changed since the last
+    /// save (or since it was created/opened). Returns the filtered total and the page.
+    pub fn document_summaries(&self, filter: &DocumentFilter, offset: usize, limit: Option<usize>) -> Result<(usize, Vec<serde_json::Value>)> {
+        let needle = filter.title_contains.as_ref().map(|t| t.to_lowercase());
+        let mut docs: Vec<&DocxMetadata> = self.documents.values()
+            .filter(|m| match &needle {
+                Some(n) => m.title.as_ref().map(|t| t.to_lowercase().contains(n)).unwrap_or(false),
+                None => true,
+            })
+            .filter(|m| filter.created_after.map(|t| m.created_at >= t).unwrap_or(true))
+            .filter(|m| filter.created_before.map(|t| m.created_at < t).unwrap_or(true))
+            .filter(|m| filter.workspace.is_none() || m.workspace == filter.workspace)
+            .filter(|m| filter.owner.is_none() || m.owner == filter.owner)
+            .collect();
+        docs.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        let total = docs.len();
+
+        let mut page = Vec::new();
+        for m in docs.into_iter().skip(offset).take(limit.unwrap_or(usize::MAX)) {
+            let file = fs::metadata(&m.path)
+                .with_context(|| format!("Failed to stat working copy of document {}", m.id))?;
+            let modified_at: DateTime<Utc> = file.modified().map(DateTime::from).unwrap_or(m.modified_at);
+            let report = self.word_count_report(&m.id)?;
+            let headings = report["sections"].as_array()
+                .map(|s| s.iter().filter(|sec| !sec["heading"].is_null()).count())
+                .unwrap_or(0);
+            page.push(serde_json::json!({
+                "id": m.id,
+                "title": m.title,
+                "created_at": m.created_at,
+                "modified_at": modified_at,
+                "saved_at": m.saved_at,
+                "workspace": m.workspace,
+                "owner": m.owner,
+                "size_bytes": file.len(),
+                "word_count": report["main_text_words"],
+                "headings": headings,
+                "dirty": m.activity.dirty,
+            }));
+        }
+        Ok((total, page))
+    }
+
+    /// Create a workspace; its output directory defaults to `workspaces/<id>` under the temp dir
+    pub fn create_workspace(&mut self, name: &str, output_dir: Option<&Path>) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let output_dir = match output_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => self.temp_dir.join("workspaces").join(&id),
+        };
+        fs::create_dir_all(&output_dir)
+            .with_context(|| format!("Failed to create workspace output directory {:?}", output_dir))?;
+        self.workspaces.insert(id.clone(), Workspace {
+            id: id.clone(),
+            name: name.trim().to_string(),
+            output_dir,
+            created_at: Utc::now(),
+        });
+        info!("Created workspace {} ({})", id, name);
+        Ok(id)
+    }
+
+    pub fn get_workspace(&self, workspace_id: &str) -> Result<Workspace> {
+        self.workspaces.get(workspace_id).cloned()
+            .ok_or_else(|| anyhow::anyhow!("Workspace not found: {}", workspace_id))
+    }
+
+    pub fn list_workspaces(&self) -> Vec<Workspace> {
+        let mut workspaces: Vec<Workspace> = self.workspaces.values().cloned().collect();
+        workspaces.sort_by_key(|a| a.created_at);
+        workspaces
+    }
+
+    /// Move a document into a workspace, or out of any workspace with `None`
+    pub fn assign_document_to_workspace(&mut self, doc_id: &str, workspace_id: Option<&str>) -> Result<()> {
+        if let Some(ws) = workspace_id {
+            self.get_workspace(ws)?;
+        }
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        metadata.workspace = workspace_id.map(String::from);
+        Ok(())
+    }
+
+    /// The client that owns a document (None when unowned), or None if no such document
+    pub fn document_owner(&self, doc_id: &str) -> Option<Option<&str>> {
+        self.documents.get(doc_id).map(|m| m.owner.as_deref())
+    }
+
+    /// Hand a document to a client, or back to the local client with `None`
+    pub fn set_document_owner(&mut self, doc_id: &str, owner: Option<&str>) -> Result<()> {
+        let metadata = self.documents.get_mut(doc_id)
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        metadata.owner = owner.map(String::from);
+        Ok(())
+    }
+
+    /// Ids of the documents a client owns
+    pub fn documents_owned_by(&self, owner: &str) -> Vec<String> {
+        self.documents.values()
+            .filter(|m| m.owner.as_deref() == Some(owner))
+            .map(|m| m.id.clone())
+            .collect()
+    }
+
+    /// Where a document's output should go: relative paths of workspace members are
+    /// placed in the workspace output directory. Absolute paths, paths containing `..`,
+    /// object storage URIs and documents outside a workspace are returned unchanged.
+    pub fn resolve_output_path(&self, doc_id: &str, output_path: &Path) -> PathBuf {
+        use std::path::Component;
+        let escapes = output_path.components().any(|c| matches!(c, Component::ParentDir));
+        let remote = output_path.to_str().map(crate::object_store::is_object_uri).unwrap_or(false);
+        if output_path.is_absolute() || escapes || remote {
+            return output_path.to_path_buf();
+        }
+        self.documents.get(doc_id)
+            .and_then(|m| m.workspace.as_ref())
+            .and_then(|ws| self.workspaces.get(ws))
+            .map(|ws| ws.output_dir.join(output_path))
+            .unwrap_or_else(|| output_path.to_path_buf())
+    }
+
+    pub fn temp_dir_path(&self) -> PathBuf {
+        self.temp_dir.clone()
+    }
+
+    /// Temp directory status for health checks: existence, a write probe and open documents
+    pub fn temp_dir_health(&self) -> serde_json::Value {
+        let probe = self.temp_dir.join(format!(".probe-{}", Uuid::new_v4()));
+        let writable = fs::write(&probe, b"ok").is_ok();
+        let _ = fs::remove_file(&probe);
+        serde_json::json!({
+            "path": self.temp_dir,
+            "exists": self.temp_dir.is_dir(),
+            "writable": writable,
+            "open_documents": self.documents.len(),
+        })
+    }
+
+    pub fn get_storage_info(&self) -> Result<serde_json::Value> {
+        use std::time::UNIX_EPOCH;
+        let mut total_bytes: u64 = 0;
+        let mut file_count: u64 = 0;
+        let mut oldest: Option<u64> = None;
+        let mut newest: Option<u64> = None;
+        if self.temp_dir.exists() {
+            for entry in walkdir::WalkDir::new(&self.temp_dir).into_iter().filter_map(|e| e.ok()) {
+                if entry.file_type().is_file() {
+                    file_count += 1;
+                    if let Ok(meta) = entry.metadata() {
+                        total_bytes = total_bytes.saturating_add(meta.len());
+                        if let Ok(modified) = meta.modified() {
+                            if let Ok(secs) = modified.duration_since(UNIX_EPOCH) {
+                                let ts = secs.as_secs();
+                                oldest = Some(oldest.map_or(ts, |o| o.min(ts)));
+                                newest = Some(newest.map_or(ts, |n| n.max(ts)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(serde_json::json!({
+            "success": true,
+            "storage": {
+                "base_dir": self.temp_dir,
+                "file_count": file_count,
+                "total_bytes": total_bytes,
+                "oldest_modified": oldest,
+                "newest_modified": newest,
+                "max_document_memory": self.in_memory_ops.max_bytes(),
+                "spilled_documents": self.in_memory_ops.spilled_count(),
+            }
+        }))
+    }
+
+    /// Keep at most this many bytes of ops in memory per document; larger documents are
+    /// spilled to disk after each edit (see `spill`). None keeps everything in memory.
+    pub fn set_max_document_memory(&mut self, max_bytes: Option<usize>) {
+        self.in_memory_ops.set_max_bytes(max_bytes);
+    }
+
+    /// Zip settings for every package written from now on
+    pub fn set_package_options(&mut self, options: PackageOptions) -> Result<()> {
+        if options.compression_level > 9 {
+            anyhow::bail!("compression level must be 0 to 9 (got {})", options.compression_level);
+        }
+        self.package_options = options;
+        Ok(())
+    }
+
+    pub fn package_options(&self) -> PackageOptions {
+        self.package_options
+    }
+
+    /// Whether any of a document's ops or image and font data are held on disk
+    pub fn is_spilled(&self, doc_id: &str) -> bool {
+        self.in_memory_ops.is_spilled(doc_id)
+    }
+}
+
+/// Serializable so op lists can be spilled to disk (see `spill`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)] // DocxStyle is large; boxing it would touch every match
+pub(crate) enum DocxOp {
+    Paragraph { text: String, style: Option<DocxStyle> },
+    Heading { text: String, style: String },
+    Table { data: TableData },
+    List { items: Vec<String>, ordered: bool },
+    ListItem { text: String, level: usize, ordered: bool },
+    PageBreak,
+    Header(String),
+    Footer(String),
+    Image {
+        data: crate::spill::Payload,
+        width: u32,
+        height: u32,
+        alt_text: Option<String>,
+    },
+    Hyperlink { text: String, url: String },
+    SectionBreak { page_size: Option<String>, orientation: Option<String>, margins: Option<MarginsSpec> },
+    Toc { from_level: usize, to_level: usize, right_align_dots: bool },
+    BookmarkAfterHeading { heading_text: String, name: String },
+    /// Highlight every match of `pattern` (regex source with inline flags) in the rendered XML
+    Highlight { pattern: String, color: String },
+    /// Write every match of `pattern` as invisible text (e-signature anchor tags)
+    InvisibleText { pattern: String },
+    /// Font file embedded as word/fonts/*.odttf; `slot` is the fontTable embed element
+    /// (embedRegular, embedBold, embedItalic or embedBoldItalic)
+    EmbedFont {
+        family: String,
+        slot: String,
+        data: crate::spill::Payload,
+    },
+    /// Start/end of a tagged region, written as a body-level bookmark named `tag_<tag>_<n>`
+    TagStart(String),
+    TagEnd(String),
+    /// Multilevel numbering for Heading1-6 paragraphs, one of `HEADING_NUMBER_SCHEMES`
+    HeadingNumbering { scheme: String },
+    /// Page setup of the body (final) section
+    PageSetup(PageSetup),
+    /// Alignment and logo of the header (or footer), rendered with its text
+    HeaderLayout { footer: bool, layout: HeaderFooterLayout },
+    /// Document default font; `size` in half-points like `DocxStyle::font_size`
+    DefaultFont { family: Option<String>, size: Option<usize> },
+    /// Proofing language and spell-check exclusion of the whole document, written to the
+    /// style defaults
+    DefaultProofing(Proofing),
+    /// Start/end of a region whose runs get their own proofing settings
+    ProofingStart(Proofing),
+    ProofingEnd,
+    /// Watermark of one section or of the whole document, drawn from the headers
+    Watermark(Watermark),
+    /// Start/end of a run of elements kept on one page with keepNext/keepLines
+    KeepTogetherStart,
+    KeepTogetherEnd,
+}
+
+/// Language that Word proofs text in, and whether spelling and grammar checks are
+/// skipped (`w:noProof`, for code, part numbers and the like)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Proofing {
+    /// BCP 47 tag such as "en-GB" or "de-DE"
+    pub language: Option<String>,
+    pub no_proof: bool,
+}
+
+/// Which content `set_proofing_language` applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofingScope {
+    /// Style defaults: every run without its own language, including later additions
+    Document,
+    /// Elements `start..=end`, indexed as in `text_elements`
+    Elements { start: usize, end: usize },
+    /// Every region tagged with `tag_content`
+    Tag(String),
+}
+
+/// Body section page setup. Sizes are in twips, margins and gutter in inches like
+/// `MarginsSpec`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PageSetup {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) landscape: bool,
+    pub(crate) margins: MarginsSpec,
+    pub(crate) gutter: Option<f32>,
+    pub(crate) mirror: bool,
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        let (width, height) = A4_TWIPS;
+        PageSetup {
+            width,
+            height,
+            landscape: false,
+            margins: MarginsSpec { top: Some(1.0), bottom: Some(1.0), left: Some(1.0), right: Some(1.0) },
+            gutter: None,
+            mirror: false,
+        }
+    }
+}
+
+const A4_TWIPS: (u32, u32) = (11906, 16838);
+
+/// Named page sizes, portrait (width, height) in twips
+pub(crate) const PAGE_SIZE_PRESETS: &[(&str, (u32, u32))] = &[
+    ("A3", (16838, 23811)),     // 297 x 420 mm
+    ("A4", A4_TWIPS),           // 210 x 297 mm
+    ("A5", (8391, 11906)),      // 148 x 210 mm
+    ("B5", (9979, 14173)),      // 176 x 250 mm (ISO)
+    ("Letter", (12240, 15840)), // 8.5 x 11 in
+    ("Legal", (12240, 20160)),  // 8.5 x 14 in
+    ("Tabloid", (15840, 24480)), // 11 x 17 in
+];
+/// Word accepts pages from 0.1 to 22 inches a side
+const PAGE_SIDE_TWIPS: std::ops::RangeInclusive<u32> = 144..=31680;
+
+/// Portrait (width, height) in twips of a page size: a preset name from
+/// `PAGE_SIZE_PRESETS` or custom dimensions such as `210x297mm`, `8.5 x 11 in` or
+/// `21x29.7cm`
+fn page_size_twips(size: &str) -> Result<(u32, u32)> {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    let size = size.trim();
+    if let Some((_, twips)) = PAGE_SIZE_PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(size)) {
+        return Ok(*twips);
+    }
+    static CUSTOM: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)^(\d+(?:\.\d+)?)\s*[x×]\s*(\d+(?:\.\d+)?)\s*(mm|cm|in|inch|inches|\x22)$").unwrap()
+    });
+    let caps = CUSTOM.captures(size).ok_or_else(|| anyhow::anyhow!(
+        "Unknown page size '{}' (use {} or custom dimensions such as 210x297mm or 8.5x11in)",
+        size,
+        PAGE_SIZE_PRESETS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", "),
+    ))?;
+    let per_unit = match caps[3].to_ascii_lowercase().as_str() {
+        "mm" => 1440.0 / 25.4,
+        "cm" => 1440.0 / 2.54,
+        _ => 1440.0,
+    };
+    let to_twips = |value: &str| -> Result<u32> {
+        let twips = (value.parse::<f64>()? * per_unit).round() as u32;
+        if !PAGE_SIDE_TWIPS.contains(&twips) {
+            anyhow::bail!("Page size '{}' is out of range (each side must be 0.1 to 22 inches)", size);
+        }
+        Ok(twips)
+    };
+    Ok((to_twips(&caps[1])?, to_twips(&caps[2])?))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginsSpec {
+    pub top: Option<f32>,
+    pub bottom: Option<f32>,
+    pub left: Option<f32>,
+    pub right: Option<f32>,
+}
+
+fn op_block_tag(op: &DocxOp) -> Option<crate::templating::BlockTag<'_>> {
+    match op {
+        DocxOp::Paragraph { text, .. } => crate::templating::block_tag(text),
+        _ => None,
+    }
+}
+
+/// Expand block paragraphs and render the text of every other op
+fn expand_template_ops(ops: &[DocxOp], context: &mut crate::templating::Context) -> Result<Vec<DocxOp>> {
+    use crate::templating::BlockTag;
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < ops.len() {
+        match op_block_tag(&ops[i]) {
+            Some(BlockTag::Open { kind, path }) => {
+                // Find the matching close (and a top-level else) by nesting depth
+                let (mut depth, mut else_at, mut end) = (0usize, None, None);
+                for (j, op) in ops.iter().enumerate().skip(i + 1) {
+                    match op_block_tag(op) {
+                        Some(BlockTag::Open { .. }) => depth += 1,
+                        Some(BlockTag::Else) if depth == 0 => else_at = Some(j),
+                        Some(BlockTag::Close(_)) if depth == 0 => {
+                            end = Some(j);
+                            break;
+                        }
+                        Some(BlockTag::Close(_)) => depth -= 1,
+                        _ => {}
+                    }
+                }
+                let end = end.ok_or_else(|| anyhow::anyhow!("{{{{#{} {}}}}} has no closing paragraph", kind, path))?;
+                let body = &ops[i + 1..else_at.unwrap_or(end)];
+                let otherwise = else_at.map(|e| &ops[e + 1..end]).unwrap_or(&[]);
+                if kind == "each" {
+                    let items = context.items(path);
+                    if items.is_empty() {
+                        out.extend(expand_template_ops(otherwise, context)?);
+                    }
+                    for (index, item) in items.into_iter().enumerate() {
+                        context.push(item, index);
+                        let expanded = expand_template_ops(body, context);
+                        context.pop();
+                        out.extend(expanded?);
+                    }
+                } else {
+                    let show = context.is_truthy(path) == (kind == "if");
+                    out.extend(expand_template_ops(if show { body } else { otherwise }, context)?);
+                }
+                i = end + 1;
+            }
+            Some(tag) => anyhow::bail!("unexpected {:?} paragraph without an opening block", tag),
+            None => {
+                out.push(render_template_op(&ops[i], context)?);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn render_template_op(op: &DocxOp, context: &mut crate::templating::Context) -> Result<DocxOp> {
+    let mut op = op.clone();
+    match &mut op {
+        DocxOp::Paragraph { text, .. }
+        | DocxOp::Heading { text, .. }
+        | DocxOp::ListItem { text, .. }
+        | DocxOp::Header(text)
+        | DocxOp::Footer(text)
+        | DocxOp::BookmarkAfterHeading { heading_text: text, .. } => *text = context.render(text)?,
+        DocxOp::Hyperlink { text, url } => {
+            *text = context.render(text)?;
+            *url = context.render(url)?;
+        }
+        DocxOp::List { items, .. } => {
+            for item in items.iter_mut() {
+                *item = context.render(item)?;
+            }
+        }
+        DocxOp::Table { data } => {
+            if let Some(headers) = data.headers.as_mut() {
+                for cell in headers.iter_mut() {
+                    *cell = context.render(cell)?;
+                }
+            }
+            let mut rows = Vec::new();
+            // First output row of each template row, for remapping merges
+            let mut row_starts = Vec::new();
+            for row in &data.rows {
+                row_starts.push(rows.len());
+                match template_row_each(row) {
+                    Some((path, cells)) => {
+                        for (index, item) in context.items(&path).into_iter().enumerate() {
+                            context.push(item, index);
+                            let rendered: Result<Vec<String>> = cells.iter().map(|c| context.render(c)).collect();
+                            context.pop();
+                            rows.push(rendered?);
+                        }
+                    }
+                    None => rows.push(row.iter().map(|c| context.render(c)).collect::<Result<Vec<String>>>()?),
+                }
+            }
+            if let Some(merges) = data.merges.as_mut() {
+                for merge in merges.iter_mut() {
+                    if let Some(start) = row_starts.get(merge.row) {
+                        merge.row = *start;
+                    }
+                }
+            }
+            data.rows = rows;
+        }
+        _ => {}
+    }
+    Ok(op)
+}
+
+/// `(list path, cells without the wrapping tags)` for a row wrapped in an each block
+fn template_row_each(row: &[String]) -> Option<(String, Vec<String>)> {
+    static OPEN: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| regex::Regex::new(r"^\s*\{\{\s*#each\s+([^{}]+?)\s*\}\}").unwrap());
+    static CLOSE: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| regex::Regex::new(r"\{\{\s*/each\s*\}\}\s*$").unwrap());
+    let first = row.first()?;
+    let open = OPEN.captures(first)?;
+    // An each opened and closed inside the first cell is inline, not a row block
+    if first.matches("{{#each").count() <= first.matches("{{/each").count() {
+        return None;
+    }
+    let last = row.last()?;
+    let close = CLOSE.find(last)?;
+    let mut cells = row.to_vec();
+    let n = cells.len();
+    cells[n - 1] = last[..close.start()].to_string();
+    cells[0] = cells[0][open.get(0)?.end()..].to_string();
+    Some((open[1].to_string(), cells))
+}
+
+/// Whether an op carries right-to-left text or an explicit RTL direction
+fn op_has_rtl(op: &DocxOp) -> bool {
+    use crate::bidi::contains_rtl;
+    match op {
+        DocxOp::Paragraph { text, style } => contains_rtl(text)
+            || style.as_ref().and_then(|s| s.direction.as_deref()) == Some("rtl"),
+        DocxOp::Heading { text, .. }
+        | DocxOp::ListItem { text, .. }
+        | DocxOp::Header(text)
+        | DocxOp::Footer(text)
+        | DocxOp::Hyperlink { text, .. } => contains_rtl(text),
+        DocxOp::List { items, .. } => items.iter().any(|i| contains_rtl(i)),
+        DocxOp::Table { data } => data.rows.iter().flatten().any(|c| contains_rtl(c)),
+        _ => false,
+    }
+}
+
+const SMALL_CAPS_MARKER: &str = "__SMALL_CAPS__";
+/// rPr children that come after `w:smallCaps` in the schema order
+const SMALL_CAPS_FOLLOWERS: &[&str] = &[
+    "<w:strike", "<w:dstrike", "<w:outline", "<w:shadow", "<w:emboss", "<w:imprint", "<w:noProof",
+    "<w:snapToGrid", "<w:vanish", "<w:webHidden", "<w:color", "<w:spacing", "<w:w ", "<w:kern",
+    "<w:position", "<w:sz", "<w:highlight", "<w:u ", "<w:effect", "<w:bdr", "<w:shd", "<w:fitText",
+    "<w:vertAlign", "<w:rtl", "<w:cs", "<w:em ", "<w:lang", "<w:eastAsianLayout", "<w:specVanish", "<w:oMath",
+];
+
+/// sectPr children that come after `w:pgSz` in the schema order
+const PG_SZ_FOLLOWERS: &[&str] = &[
+    "<w:pgMar", "<w:paperSrc", "<w:pgBorders", "<w:lnNumType", "<w:pgNumType", "<w:cols", "<w:formProt",
+    "<w:vAlign", "<w:noEndnote", "<w:titlePg", "<w:textDirection", "<w:bidi", "<w:rtlGutter", "<w:docGrid",
+    "<w:printerSettings", "<w:sectPrChange",
+];
+/// settings children that come after `w:mirrorMargins` in the schema order
+const MIRROR_MARGINS_FOLLOWERS: &[&str] = &[
+    "<w:alignBordersAndEdges", "<w:bordersDoNotSurroundHeader", "<w:bordersDoNotSurroundFooter",
+    "<w:gutterAtTop", "<w:hideSpellingErrors", "<w:hideGrammaticalErrors", "<w:activeWritingStyle",
+    "<w:proofState", "<w:formsDesign", "<w:attachedTemplate", "<w:linkStyles", "<w:stylePaneFormatFilter",
+    "<w:stylePaneSortMethod", "<w:documentType", "<w:mailMerge", "<w:revisionView", "<w:trackRevisions",
+    "<w:doNotTrackMoves", "<w:doNotTrackFormatting", "<w:documentProtection", "<w:autoFormatOverride",
+    "<w:styleLockTheme", "<w:styleLockQFSet", "<w:defaultTabStop",
+];
+
+/// Write the page size and margins into the body's final `w:sectPr` and set
+/// `w:mirrorMargins` in the settings. Header and footer distances already in `w:pgMar`
+/// are kept.
+fn write_page_setup(docx_path: &Path, package: PackageOptions, setup: &PageSetup) -> Result<()> {
+    let (w, h) = if setup.landscape { (setup.height, setup.width) } else { (setup.width, setup.height) };
+    let orient = if setup.landscape { r#" w:orient="landscape""# } else { "" };
+    let pg_sz = format!(r#"<w:pgSz w:w="{}" w:h="{}"{}/>"#, w, h, orient);
+    let to_twips = |inches: Option<f32>| -> u32 { (inches.unwrap_or(1.0) * 1440.0).round().max(0.0) as u32 };
+    let pg_sz_re = regex::Regex::new(r"<w:pgSz\b[^>]*/>")?;
+    let pg_mar_re = regex::Regex::new(r"<w:pgMar\b[^>]*/>")?;
+    let distance = |pg_mar: &str, attr: &str, default: u32| -> u32 {
+        regex::Regex::new(&format!(r#"w:{}="(\d+)""#, attr)).ok()
+            .and_then(|re| re.captures(pg_mar).and_then(|c| c[1].parse().ok()))
+            .unwrap_or(default)
+    };
+    rewrite_xml_parts(docx_path, package, |name, xml| match name {
+        "word/document.xml" => {
+            let body_end = xml.rfind("</w:body>")?;
+            let start = xml[..body_end].rfind("<w:sectPr")?;
+            let end = start + xml[start..body_end].find("</w:sectPr>")?;
+            let open_end = start + xml[start..end].find('>')? + 1;
+            let inner = &xml[open_end..end];
+            let old_mar = pg_mar_re.find(inner).map(|m| m.as_str()).unwrap_or("");
+            let pg_mar = format!(
+                r#"<w:pgMar w:top="{}" w:right="{}" w:bottom="{}" w:left="{}" w:header="{}" w:footer="{}" w:gutter="{}"/>"#,
+                to_twips(setup.margins.top), to_twips(setup.margins.right), to_twips(setup.margins.bottom),
+                to_twips(setup.margins.left), distance(old_mar, "header", 720), distance(old_mar, "footer", 720),
+                to_twips(Some(setup.gutter.unwrap_or(0.0))),
+            );
+            let inner = pg_mar_re.replace_all(&pg_sz_re.replace_all(inner, ""), "").into_owned();
+            let at = PG_SZ_FOLLOWERS[1..].iter().filter_map(|f| inner.find(f)).min().unwrap_or(inner.len());
+            Some(format!("{}{}{}{}{}{}", &xml[..open_end], &inner[..at], pg_sz, pg_mar, &inner[at..], &xml[end..]))
+        }
+        "word/settings.xml" => {
+            let has = xml.contains("<w:mirrorMargins");
+            if setup.mirror == has { return None; }
+            if !setup.mirror {
+                return Some(regex::Regex::new(r"<w:mirrorMargins\b[^>]*/>").ok()?.replace_all(xml, "").into_owned());
+            }
+            let open_end = xml.find("<w:settings").and_then(|s| xml[s..].find('>').map(|e| s + e + 1))?;
+            let close = xml.rfind("</w:settings>")?;
+            let at = MIRROR_MARGINS_FOLLOWERS.iter().filter_map(|f| xml[open_end..close].find(f)).min()
+                .map(|p| open_end + p).unwrap_or(close);
+            Some(format!("{}<w:mirrorMargins/>{}", &xml[..at], &xml[at..]))
+        }
+        _ => None,
+    })
+}
+
+/// tcPr children from `w:tcMar` on, in the schema order
+const TC_PR_TAIL: &[&str] = &[
+    "<w:tcMar", "<w:textDirection", "<w:tcFitText", "<w:vAlign", "<w:hideMark", "<w:headers",
+    "<w:cellIns", "<w:cellDel", "<w:cellMerge", "<w:tcPrChange",
+];
+
+/// Cell properties at the start of a cell's content with `style` applied
+fn apply_cell_style(content: &str, style: &CellStyle) -> String {
+    use crate::bidi::insert_property;
+    let mut out = content.to_string();
+    if let Some(padding) = style.padding {
+        let sides: String = [("top", padding.top), ("left", padding.left), ("bottom", padding.bottom), ("right", padding.right)]
+            .iter()
+            .filter_map(|(side, pt)| pt.map(|pt| format!(r#"<w:{} w:w="{}" w:type="dxa"/>"#, side, (pt * 20.0).round() as u32)))
+            .collect();
+        if !sides.is_empty() {
+            out = regex::Regex::new(r"(?s)<w:tcMar>.*?</w:tcMar>").unwrap().replacen(&out, 1, "").into_owned();
+            out = insert_property(&out, "tcPr", &format!("<w:tcMar>{}</w:tcMar>", sides), &TC_PR_TAIL[1..]);
+        }
+    }
+    if let Some(direction) = style.text_direction.as_deref().and_then(|d| cell_text_direction(d).ok()) {
+        out = regex::Regex::new(r"<w:textDirection\b[^>]*/>").unwrap().replacen(&out, 1, "").into_owned();
+        out = insert_property(&out, "tcPr", &format!(r#"<w:textDirection w:val="{}"/>"#, direction), &TC_PR_TAIL[2..]);
+    }
+    if let Some(align) = style.vertical_align.as_deref().and_then(|a| cell_v_align(a).ok()) {
+        out = regex::Regex::new(r"<w:vAlign\b[^>]*/>").unwrap().replacen(&out, 1, "").into_owned();
+        out = insert_property(&out, "tcPr", &format!(r#"<w:vAlign w:val="{}"/>"#, align), &TC_PR_TAIL[4..]);
+    }
+    out
+}
+
+/// trPr children that follow the row properties set here
+const TR_PR_FOLLOWERS: &[&str] = &["<w:ins", "<w:del", "<w:trPrChange"];
+
+/// Row properties at the start of a row's content with `element` added once
+fn apply_row_property(content: &str, element: &str) -> String {
+    let tag = element.trim_end_matches("/>");
+    if content.contains(&format!("{}/>", tag)) || content.contains(&format!("{} />", tag)) {
+        return content.to_string();
+    }
+    crate::bidi::insert_property(content, "trPr", element, TR_PR_FOLLOWERS)
+}
+
+/// tblPr children from `w:tblW` on, in the schema order
+const TBL_PR_TAIL: &[&str] = &[
+    "<w:tblW", "<w:jc", "<w:tblCellSpacing", "<w:tblInd", "<w:tblBorders", "<w:shd", "<w:tblLayout",
+    "<w:tblCellMar", "<w:tblLook", "<w:tblCaption", "<w:tblDescription", "<w:tblPrChange",
+];
+
+/// Table properties at the start of a table's content with its width, layout and
+/// accessible caption applied
+fn apply_table_properties(content: &str, data: &TableData) -> String {
+    use crate::bidi::insert_property;
+    let mut out = content.to_string();
+    if let Some(percent) = data.width_percent {
+        out = regex::Regex::new(r"<w:tblW\b[^>]*/>").unwrap().replacen(&out, 1, "").into_owned();
+        let width = format!(r#"<w:tblW w:w="{}" w:type="pct"/>"#, (percent * 50.0).round() as u32);
+        out = insert_property(&out, "tblPr", &width, &TBL_PR_TAIL[1..]);
+    }
+    if let Some(layout) = data.table_layout.as_deref().and_then(|l| table_layout_type(l).ok()) {
+        out = regex::Regex::new(r"<w:tblLayout\b[^>]*/>").unwrap().replacen(&out, 1, "").into_owned();
+        out = insert_property(&out, "tblPr", &format!(r#"<w:tblLayout w:type="{}"/>"#, layout), &TBL_PR_TAIL[7..]);
+    }
+    if let Some(caption) = &data.caption {
+        out = regex::Regex::new(r"<w:tblCaption\b[^>]*/>").unwrap().replacen(&out, 1, "").into_owned();
+        let element = format!(r#"<w:tblCaption w:val="{}"/>"#, xml_escape(caption.trim()));
+        out = insert_property(&out, "tblPr", &element, &TBL_PR_TAIL[10..]);
+    }
+    out
+}
+
+const CAPTION_STYLE_ID: &str = "Caption";
+/// Word's built-in caption style, kept with the next paragraph so a caption never ends
+/// a page without its table
+const CAPTION_STYLE: &str = concat!(
+    r#"<w:style w:type="paragraph" w:styleId="Caption"><w:name w:val="caption"/>"#,
+    r#"<w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:uiPriority w:val="35"/><w:qFormat/>"#,
+    r#"<w:pPr><w:keepNext/><w:spacing w:after="120"/></w:pPr><w:rPr><w:i/><w:sz w:val="18"/></w:rPr></w:style>"#,
+);
+
+/// Write each table's width, layout, header-row repeat, row splitting and cell styles
+/// into its `w:tblPr`, `w:trPr` and `w:tcPr`. Tables in document.xml line up with the
+/// table ops in order.
+fn write_table_layout(docx_path: &Path, package: PackageOptions, ops: &[DocxOp]) -> Result<()> {
+    let tables: Vec<&TableData> = ops.iter().filter_map(|op| match op {
+        DocxOp::Table { data } => Some(data),
+        _ => None,
+    }).collect();
+    let has_caption = tables.iter().any(|t| t.caption.is_some());
+    let tr_open_re = regex::Regex::new(r"^<w:tr(?:\s[^>]*)?>")?;
+    let tbl_re = regex::Regex::new(r"(?s)<w:tbl>.*?</w:tbl>")?;
+    let tr_re = regex::Regex::new(r"(?s)<w:tr(?:\s[^>]*)?>.*?</w:tr>")?;
+    let tc_re = regex::Regex::new(r"(?s)(<w:tc(?:\s[^>]*)?>)(.*?)</w:tc>")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        if name == "word/styles.xml" {
+            if !has_caption || xml.contains(r#"w:styleId="Caption""#) { return None; }
+            return xml.rfind("</w:styles>").map(|at| format!("{}{}{}", &xml[..at], CAPTION_STYLE, &xml[at..]));
+        }
+        if name != "word/document.xml" { return None; }
+        let mut index = 0;
+        Some(tbl_re.replace_all(xml, |table: &regex::Captures| {
+            let Some(data) = tables.get(index).copied() else { return table[0].to_string() };
+            index += 1;
+            if !data.has_row_or_cell_layout() { return table[0].to_string(); }
+            let styles = data.cell_styles.as_deref().unwrap_or(&[]);
+            let table_xml = format!("<w:tbl>{}", apply_table_properties(&table[0]["<w:tbl>".len()..], data));
+            let mut row = 0;
+            tr_re.replace_all(&table_xml, |tr: &regex::Captures| {
+                let open = tr_open_re.find(&tr[0]).map(|m| m.end()).unwrap_or(0);
+                let mut row_xml = tr[0][open..].to_string();
+                if data.cant_split_rows == Some(true) {
+                    row_xml = apply_row_property(&row_xml, "<w:cantSplit/>");
+                }
+                if row == 0 && data.repeat_header_row == Some(true) {
+                    row_xml = apply_row_property(&row_xml, "<w:tblHeader/>");
+                }
+                let row_xml = format!("{}{}", &tr[0][..open], row_xml);
+                let mut col = 0;
+                let out = tc_re.replace_all(&row_xml, |tc: &regex::Captures| {
+                    let mut content = tc[2].to_string();
+                    for style in styles.iter().filter(|s| s.covers(row, col)) {
+                        content = apply_cell_style(&content, style);
+                    }
+                    col += 1;
+                    format!("{}{}</w:tc>", &tc[1], content)
+                }).into_owned();
+                row += 1;
+                out
+            }).into_owned()
+        }).into_owned())
+    })
+}
+
+const PROOFING_START_MARKER: &str = "__PROOFING_START__";
+const PROOFING_END_MARKER: &str = "__PROOFING_END__";
+/// rPr children that come after `w:noProof` and `w:lang` in the schema order
+const NO_PROOF_FOLLOWERS: &[&str] = &[
+    "<w:snapToGrid", "<w:vanish", "<w:webHidden", "<w:color", "<w:spacing", "<w:w ", "<w:kern",
+    "<w:position", "<w:sz", "<w:highlight", "<w:u ", "<w:effect", "<w:bdr", "<w:shd", "<w:fitText",
+    "<w:vertAlign", "<w:rtl", "<w:cs", "<w:em ", "<w:lang", "<w:eastAsianLayout", "<w:specVanish", "<w:oMath",
+    "<w:rPrChange",
+];
+const LANG_FOLLOWERS: &[&str] = &["<w:eastAsianLayout", "<w:specVanish", "<w:oMath", "<w:rPrChange"];
+
+/// `w:lang` for a language tag. East Asian and right-to-left languages go in the
+/// attribute Word reads for those scripts.
+fn lang_element(language: &str) -> String {
+    let primary = language.split('-').next().unwrap_or("").to_ascii_lowercase();
+    let attr = match primary.as_str() {
+        "zh" | "ja" | "ko" => "eastAsia",
+        "ar" | "he" | "fa" | "ur" | "yi" | "ps" | "sd" | "ug" => "bidi",
+        _ => "val",
+    };
+    format!(r#"<w:lang w:{}="{}"/>"#, attr, language)
+}
+
+/// Set the proofing properties in the run properties at the start of `content`,
+/// replacing any language or noProof already there
+fn apply_proofing(content: &str, proofing: &Proofing) -> String {
+    static EXISTING: once_cell::sync::Lazy<regex::Regex> =
+        once_cell::sync::Lazy::new(|| regex::Regex::new(r"<w:(?:lang|noProof)\b[^>]*/>").unwrap());
+    let (props, rest) = match content.find("</w:rPr>") {
+        Some(end) if content.trim_start().starts_with("<w:rPr>") => content.split_at(end),
+        _ => ("", content),
+    };
+    let mut out = format!("{}{}", EXISTING.replace_all(props, ""), rest);
+    if proofing.no_proof {
+        out = crate::bidi::insert_property(&out, "rPr", "<w:noProof/>", NO_PROOF_FOLLOWERS);
+    }
+    if let Some(language) = &proofing.language {
+        out = crate::bidi::insert_property(&out, "rPr", &lang_element(language), LANG_FOLLOWERS);
+    }
+    out
+}
+
+/// Apply proofing regions to the runs between their marker paragraphs (innermost
+/// language wins, noProof if any region asks for it) and drop the markers. `default`
+/// goes into the style defaults.
+fn write_proofing(docx_path: &Path, package: PackageOptions, default: Option<&Proofing>) -> Result<()> {
+    let p_re = regex::Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>")?;
+    let marker_re = regex::Regex::new(r"__PROOFING_START__ (\S+) ([01])|__PROOFING_END__")?;
+    let run_re = regex::Regex::new(r"(?s)(<w:r(?:\s[^>]*)?>)(.*?)</w:r>")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| match name {
+        "word/document.xml" if xml.contains(PROOFING_START_MARKER) => {
+            let mut open: Vec<Proofing> = Vec::new();
+            let mut out = String::with_capacity(xml.len());
+            let mut pos = 0;
+            let flush = |out: &mut String, chunk: &str, open: &[Proofing]| {
+                if open.is_empty() {
+                    out.push_str(chunk);
+                    return;
+                }
+                let effective = Proofing {
+                    language: open.iter().rev().find_map(|p| p.language.clone()),
+                    no_proof: open.iter().any(|p| p.no_proof),
+                };
+                out.push_str(&run_re.replace_all(chunk, |caps: &regex::Captures| {
+                    format!("{}{}</w:r>", &caps[1], apply_proofing(&caps[2], &effective))
+                }));
+            };
+            for para in p_re.find_iter(xml) {
+                let Some(m) = marker_re.captures(para.as_str()) else { continue };
+                flush(&mut out, &xml[pos..para.start()], &open);
+                pos = para.end();
+                match (m.get(1), m.get(2)) {
+                    (Some(language), Some(no_proof)) => open.push(Proofing {
+                        language: (language.as_str() != "-").then(|| language.as_str().to_string()),
+                        no_proof: no_proof.as_str() == "1",
+                    }),
+                    _ => { open.pop(); }
+                }
+            }
+            flush(&mut out, &xml[pos..], &open);
+            Some(out)
+        }
+        "word/styles.xml" => {
+            let default = default?;
+            let at = xml.find("<w:rPrDefault>")? + "<w:rPrDefault>".len();
+            Some(format!("{}{}", &xml[..at], apply_proofing(&xml[at..], default)))
+        }
+        _ => None,
+    })
+}
+
+const KEEP_START_MARKER: &str = "__KEEP_TOGETHER_START__";
+const KEEP_END_MARKER: &str = "__KEEP_TOGETHER_END__";
+/// pPr children that come after `w:keepNext`/`w:keepLines` in the schema order
+const KEEP_FOLLOWERS: &[&str] = &[
+    "<w:pageBreakBefore", "<w:framePr", "<w:widowControl", "<w:numPr", "<w:suppressLineNumbers", "<w:pBdr",
+    "<w:shd", "<w:tabs", "<w:suppressAutoHyphens", "<w:kinsoku", "<w:wordWrap", "<w:overflowPunct",
+    "<w:topLinePunct", "<w:autoSpaceDE", "<w:autoSpaceDN", "<w:bidi", "<w:adjustRightInd", "<w:snapToGrid",
+    "<w:spacing", "<w:ind", "<w:contextualSpacing", "<w:mirrorIndents", "<w:suppressOverlap", "<w:jc",
+    "<w:textDirection", "<w:textAlignment", "<w:textboxTightWrap", "<w:outlineLvl", "<w:divId", "<w:cnfStyle",
+    "<w:rPr", "<w:sectPr", "<w:pPrChange",
+];
+
+/// Set `keepLines` on a paragraph (`<w:p>...</w:p>`), and `keepNext` too when `next`
+fn keep_paragraph(para: &str, next: bool) -> String {
+    static EXISTING: once_cell::sync::Lazy<regex::Regex> =
+        once_cell::sync::Lazy::new(|| regex::Regex::new(r"<w:keep(?:Next|Lines)\b[^>]*/>").unwrap());
+    let Some(open_end) = para.find('>') else { return para.to_string() };
+    let (head, body) = para.split_at(open_end + 1);
+    let body = match body.find("</w:pPr>") {
+        Some(end) if body.trim_start().starts_with("<w:pPr>") => format!("{}{}", EXISTING.replace_all(&body[..end], ""), &body[end..]),
+        _ => body.to_string(),
+    };
+    let props = if next { "<w:keepNext/><w:keepLines/>" } else { "<w:keepLines/>" };
+    format!("{}{}", head, crate::bidi::insert_property(&body, "pPr", props, KEEP_FOLLOWERS))
+}
+
+/// Keep the paragraphs between keep-together markers on one page (keepNext on all but
+/// the last, keepLines on all, table cell paragraphs included) and drop the markers
+fn write_keep_together(docx_path: &Path, package: PackageOptions) -> Result<()> {
+    let p_re = regex::Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        if name != "word/document.xml" || !xml.contains(KEEP_START_MARKER) { return None; }
+        let mut out = String::with_capacity(xml.len());
+        let mut pos = 0;
+        for para in p_re.find_iter(xml) {
+            let (start, end) = (para.as_str().contains(KEEP_START_MARKER), para.as_str().contains(KEEP_END_MARKER));
+            if !start && !end { continue; }
+            let chunk = &xml[pos..para.start()];
+            if end {
+                let paras: Vec<regex::Match> = p_re.find_iter(chunk).collect();
+                let mut last = 0;
+                for (i, p) in paras.iter().enumerate() {
+                    out.push_str(&chunk[last..p.start()]);
+                    out.push_str(&keep_paragraph(p.as_str(), i + 1 < paras.len()));
+                    last = p.end();
+                }
+                out.push_str(&chunk[last..]);
+            } else {
+                out.push_str(chunk);
+            }
+            pos = para.end();
+        }
+        out.push_str(&xml[pos..]);
+        Some(out)
+    })
+}
+
+/// Replace the small caps marker run style with `w:smallCaps`
+fn write_small_caps(docx_path: &Path, package: PackageOptions) -> Result<()> {
+    let marker = regex::Regex::new(&format!(r#"<w:rStyle w:val="{}"\s*/>"#, SMALL_CAPS_MARKER))?;
+    let run_re = regex::Regex::new(r"(?s)(<w:r(?:\s[^>]*)?>)(.*?)</w:r>")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        if name != "word/document.xml" || !marker.is_match(xml) { return None; }
+        Some(run_re.replace_all(xml, |caps: &regex::Captures| {
+            if !marker.is_match(&caps[2]) { return caps[0].to_string(); }
+            let inner = marker.replace(&caps[2], "");
+            format!("{}{}</w:r>", &caps[1],
+                crate::bidi::insert_property(&inner, "rPr", "<w:smallCaps/>", SMALL_CAPS_FOLLOWERS))
+        }).into_owned())
+    })
+}
+
+/// Text of element `index` of `ops`: a list item, a table cell or the element's own text
+fn element_text_mut(ops: &mut [DocxOp], index: usize, item: Option<usize>, cell: Option<(usize, usize)>) -> Option<&mut String> {
+    match (ops.get_mut(index)?, item, cell) {
+        (DocxOp::List { items, .. }, Some(item), None) => items.get_mut(item),
+        (DocxOp::Table { data }, None, Some((row, col))) => data.rows.get_mut(row)?.get_mut(col),
+        (DocxOp::Paragraph { text, .. }, None, None)
+        | (DocxOp::Heading { text, .. }, None, None)
+        | (DocxOp::ListItem { text, .. }, None, None)
+        | (DocxOp::Hyperlink { text, .. }, None, None)
+        | (DocxOp::Header(text), None, None)
+        | (DocxOp::Footer(text), None, None) => Some(text),
+        _ => None,
+    }
+}
+
+/// Content hash of an op. Binary payloads are hashed directly rather than through their
+/// (very long) Debug form.
+pub(crate) fn op_fingerprint(op: &DocxOp) -> u64 {
+    use std::fmt::Write as _;
+    use std::hash::{Hash, Hasher};
+    struct HashWriter<'h>(&'h mut std::collections::hash_map::DefaultHasher);
+    impl std::fmt::Write for HashWriter<'_> {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    match op {
+        DocxOp::Image { data, width, height, alt_text } => ("image", data, width, height, alt_text).hash(&mut hasher),
+        DocxOp::EmbedFont { family, slot, data } => ("font", family, slot, data).hash(&mut hasher),
+        other => { let _ = write!(HashWriter(&mut hasher), "{:?}", other); }
+    }
+    hasher.finish()
+}
+
+fn is_monospace_font(family: &str) -> bool {
+    let family = family.to_ascii_lowercase();
+    ["mono", "courier", "consolas", "menlo", "monaco", "code", "lucida console", "inconsolata"]
+        .iter().any(|m| family.contains(m))
+}
+
+const FIELD_NAMES: &[&str] = &["DATE", "TIME", "FILENAME", "AUTHOR", "TITLE", "STYLEREF", "PAGE", "NUMPAGES"];
+const FIELD_TOKEN_OPEN: &str = "{FIELD:";
+
+fn has_field_token(op: &DocxOp) -> bool {
+    match op {
+        DocxOp::HeaderLayout { layout, .. } => layout.table.as_ref()
+            .is_some_and(|t| t.cells.iter().any(|c| c.text.contains(FIELD_TOKEN_OPEN))),
+        DocxOp::Paragraph { text, .. } | DocxOp::Header(text) | DocxOp::Footer(text) => text.contains(FIELD_TOKEN_OPEN),
+        DocxOp::Table { data } => data.caption.is_some(),
+        _ => false,
+    }
+}
+
+/// Caption paragraphs for `label` ("Figure", "Table") in document order, with sequence
+/// fields replaced by their running number
+fn caption_entries(ops: &[DocxOp], label: &str) -> Vec<String> {
+    let caption = regex::Regex::new(&format!(r"^{}\s+(\d+|\{{FIELD:SEQ [^{{}}]*\}})", regex::escape(label))).unwrap();
+    let mut n = 0;
+    ops.iter()
+        .filter_map(|op| match op {
+            DocxOp::Paragraph { text, .. } => Some(text.clone()),
+            DocxOp::Table { data } => data.caption_text(),
+            _ => None,
+        })
+        .filter_map(|text| {
+            let m = caption.captures(&text)?;
+            n += 1;
+            let number = m.get(1).unwrap();
+            if number.as_str().starts_with(FIELD_TOKEN_OPEN) {
+                Some(format!("{}{}{}", &text[..number.start()], n, &text[number.end()..]))
+            } else {
+                Some(text)
+            }
+        })
+        .collect()
+}
+
+/// Text shown for a field until Word recalculates it
+fn field_placeholder(instr: &str, metadata: &DocxMetadata) -> String {
+    let picture = instr.split_once(r#"\@ ""#).map(|(_, p)| p.trim_end_matches('"'));
+    let now = chrono::Local::now();
+    match instr.split_whitespace().next().unwrap_or("") {
+        "DATE" if picture.is_some() => now.format(&word_picture_to_strftime(picture.unwrap_or(""))).to_string(),
+        "DATE" => now.format("%Y-%m-%d").to_string(),
+        "TIME" if picture.is_some() => now.format(&word_picture_to_strftime(picture.unwrap_or(""))).to_string(),
+        "TIME" => now.format("%H:%M").to_string(),
+        "FILENAME" => metadata.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        "AUTHOR" => metadata.author.clone().unwrap_or_default(),
+        "TITLE" => metadata.title.clone().unwrap_or_default(),
+        "PAGE" | "NUMPAGES" => "1".to_string(),
+        "TOC" => "Update this field to build the list".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Translate the common Word date/time picture items to strftime
+fn word_picture_to_strftime(picture: &str) -> String {
+    let tokens = [
+        ("yyyy", "%Y"), ("yy", "%y"), ("MMMM", "%B"), ("MMM", "%b"), ("MM", "%m"), ("M", "%-m"),
+        ("dddd", "%A"), ("ddd", "%a"), ("dd", "%d"), ("d", "%-d"), ("HH", "%H"), ("H", "%-H"),
+        ("hh", "%I"), ("h", "%-I"), ("mm", "%M"), ("m", "%-M"), ("ss", "%S"), ("s", "%-S"),
+        ("AM/PM", "%p"), ("am/pm", "%P"),
+    ];
+    let mut out = String::new();
+    let mut rest = picture;
+    'outer: while let Some(c) = rest.chars().next() {
+        for (word, strf) in tokens {
+            if let Some(after) = rest.strip_prefix(word) {
+                out.push_str(strf);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        if c == '%' { out.push('%'); }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Split runs around `{FIELD:...}` tokens and write each token as a complex field marked
+/// dirty so Word recalculates it when the document opens
+fn write_field_codes(docx_path: &Path, package: PackageOptions, metadata: &DocxMetadata) -> Result<()> {
+    let token_re = regex::Regex::new(r"\{FIELD:([^{}]+)\}")?;
+    let run_re = regex::Regex::new(r"(?s)<w:r(?:\s[^>]*)?>(.*?)</w:r>")?;
+    let rpr_re = regex::Regex::new(r"(?s)^\s*(<w:rPr\s*/>|<w:rPr>.*?</w:rPr>)")?;
+    let t_re = regex::Regex::new(r"(?s)^\s*<w:t(?:\s[^>]*)?>(.*?)</w:t>\s*$")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        let is_story = name == "word/document.xml"
+            || ((name.starts_with("word/header") || name.starts_with("word/footer")) && name.ends_with(".xml"));
+        if !is_story || !xml.contains(FIELD_TOKEN_OPEN) { return None; }
+        // Running number of each SEQ field identifier, shown until Word updates them
+        let mut sequences: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        Some(run_re.replace_all(xml, |caps: &regex::Captures| {
+            let whole = &caps[0];
+            let inner = &caps[1];
+            let (rpr, rest) = match rpr_re.captures(inner) {
+                Some(c) => (c.get(1).unwrap().as_str(), &inner[c.get(0).unwrap().end()..]),
+                None => ("", inner),
+            };
+            let Some(t) = t_re.captures(rest) else { return whole.to_string() };
+            let text = xml_unescape(&t[1]);
+            if !token_re.is_match(&text) { return whole.to_string(); }
+            let text_run = |piece: &str| format!(r#"<w:r>{}<w:t xml:space="preserve">{}</w:t></w:r>"#, rpr, xml_escape(piece));
+            let mut out = String::new();
+            let mut pos = 0usize;
+            for m in token_re.captures_iter(&text) {
+                let span = m.get(0).unwrap();
+                if span.start() > pos { out.push_str(&text_run(&text[pos..span.start()])); }
+                let instr = m[1].trim();
+                let placeholder = match instr.strip_prefix("SEQ ").and_then(|rest| rest.split_whitespace().next()) {
+                    Some(id) => {
+                        let n = sequences.entry(id.to_string()).or_insert(0);
+                        *n += 1;
+                        n.to_string()
+                    }
+                    None => field_placeholder(instr, metadata),
+                };
+                out.push_str(&format!(
+                    concat!(
+                        r#"<w:r>{rpr}<w:fldChar w:fldCharType="begin" w:dirty="true"/></w:r>"#,
+                        r#"<w:r>{rpr}<w:instrText xml:space="preserve"> {instr} </w:instrText></w:r>"#,
+                        r#"<w:r>{rpr}<w:fldChar w:fldCharType="separate"/></w:r>"#,
+                        "{result}",
+                        r#"<w:r>{rpr}<w:fldChar w:fldCharType="end"/></w:r>"#,
+                    ),
+                    rpr = rpr,
+                    instr = xml_escape(instr),
+                    result = text_run(&placeholder),
+                ));
+                pos = span.end();
+            }
+            if pos < text.len() { out.push_str(&text_run(&text[pos..])); }
+            out
+        }).into_owned())
+    })
+}
+
+const PARA_BOX_MARKER: &str = "__PARA_BOX__";
+/// pPr children that come after `w:pBdr`/`w:shd` in the schema order
+const PARA_BOX_FOLLOWERS: &[&str] = &[
+    "<w:tabs", "<w:suppressAutoHyphens", "<w:kinsoku", "<w:wordWrap", "<w:overflowPunct", "<w:topLinePunct",
+    "<w:autoSpaceDE", "<w:autoSpaceDN", "<w:bidi", "<w:adjustRightInd", "<w:snapToGrid", "<w:spacing", "<w:ind",
+    "<w:contextualSpacing", "<w:mirrorIndents", "<w:suppressOverlap", "<w:jc", "<w:textDirection",
+    "<w:textAlignment", "<w:textboxTightWrap", "<w:outlineLvl", "<w:divId", "<w:cnfStyle", "<w:rPr",
+    "<w:sectPr", "<w:pPrChange",
+];
+
+/// Replace the numbered paragraph box marker styles with their `w:pBdr`/`w:shd` properties
+fn write_paragraph_boxes(docx_path: &Path, package: PackageOptions, boxes: &[String]) -> Result<()> {
+    let marker = regex::Regex::new(&format!(r#"<w:pStyle w:val="{}(\d+)"\s*/>"#, PARA_BOX_MARKER))?;
+    let ppr_re = regex::Regex::new(r"(?s)<w:pPr>.*?</w:pPr>")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        if name != "word/document.xml" || !marker.is_match(xml) { return None; }
+        Some(ppr_re.replace_all(xml, |caps: &regex::Captures| {
+            let ppr = &caps[0];
+            let Some(found) = marker.captures(ppr) else { return ppr.to_string() };
+            let props = found[1].parse::<usize>().ok().and_then(|i| boxes.get(i)).cloned().unwrap_or_default();
+            let stripped = marker.replace(ppr, "");
+            crate::bidi::insert_property(&stripped, "pPr", &props, PARA_BOX_FOLLOWERS)
+        }).into_owned())
+    })
+}
+
+const TAG_START_MARKER: &str = "__TAG_START__";
+const TAG_END_MARKER: &str = "__TAG_END__";
+
+/// Ops with the regions `drop` selects removed (markers included), and the number of
+/// content elements removed. Content inside a dropped region goes even when a nested
+/// region would be kept.
+fn drop_tagged_ops(ops: &[DocxOp], drop: impl Fn(&str) -> bool) -> (Vec<DocxOp>, usize) {
+    let mut open: Vec<(&str, bool)> = Vec::new();
+    let mut kept = Vec::with_capacity(ops.len());
+    let mut removed = 0;
+    for op in ops {
+        let dropping = open.iter().any(|(_, d)| *d);
+        match op {
+            DocxOp::TagStart(tag) => {
+                let d = drop(tag);
+                if !dropping && !d {
+                    kept.push(op.clone());
+                }
+                open.push((tag.as_str(), d));
+            }
+            DocxOp::TagEnd(tag) => {
+                let d = match open.iter().rposition(|(t, _)| *t == tag.as_str()) {
+                    Some(pos) => open.remove(pos).1,
+                    None => false,
+                };
+                if !dropping && !d {
+                    kept.push(op.clone());
+                }
+            }
+            _ if dropping => removed += 1,
+            _ => kept.push(op.clone()),
+        }
+    }
+    (kept, removed)
+}
+
+/// Replace tag marker paragraphs with body-level bookmarkStart/bookmarkEnd elements
+fn write_tag_bookmarks(docx_path: &Path, package: PackageOptions) -> Result<()> {
+    let p_re = regex::Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>")?;
+    let marker_re = regex::Regex::new(r"(__TAG_START__|__TAG_END__) ([A-Za-z0-9_-]+)")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        if name != "word/document.xml" || !xml.contains(TAG_START_MARKER) {
+            return None;
+        }
+        // Bookmark ids well above the ones docx-rs and the heading bookmarks use
+        let mut next_id = 10_000usize;
+        let mut open: Vec<(String, usize)> = Vec::new();
+        let mut count: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        let out = p_re.replace_all(xml, |caps: &regex::Captures| {
+            let para = &caps[0];
+            let Some(m) = marker_re.captures(para) else { return para.to_string() };
+            let tag = m[2].to_string();
+            if &m[1] == TAG_START_MARKER {
+                let n = count.entry(tag.clone()).or_insert(0);
+                *n += 1;
+                let id = next_id;
+                next_id += 1;
+                let element = format!(r#"<w:bookmarkStart w:id="{}" w:name="tag_{}_{}"/>"#, id, tag, n);
+                open.push((tag, id));
+                element
+            } else {
+                match open.iter().rposition(|(t, _)| *t == tag) {
+                    Some(pos) => format!(r#"<w:bookmarkEnd w:id="{}"/>"#, open.remove(pos).1),
+                    None => String::new(),
+                }
+            }
+        });
+        Some(out.into_owned())
+    })
+}
+
+/// Tab zone (0 left, 1 center, 2 right) of a header/footer logo position
+fn logo_zone(position: Option<&str>) -> Result<usize> {
+    match position.map(|p| p.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("left") => Ok(0),
+        Some("center") | Some("centre") => Ok(1),
+        Some("right") => Ok(2),
+        Some(other) => anyhow::bail!("Unknown logo_position '{}' (use left, center or right)", other),
+    }
+}
+
+/// Width in twips between the body margins, from the page setup (A4 with 1in margins
+/// when there is none)
+fn text_area_width(ops: &[DocxOp]) -> u32 {
+    let setup = ops.iter().rev().find_map(|op| match op {
+        DocxOp::PageSetup(setup) => Some(setup.clone()),
+        _ => None,
+    }).unwrap_or_default();
+    let page = if setup.landscape { setup.height } else { setup.width };
+    let twips = |inches: f32| (inches * 1440.0).round() as u32;
+    let margins = twips(setup.margins.left.unwrap_or(1.0)) + twips(setup.margins.right.unwrap_or(1.0));
+    page.saturating_sub(margins + twips(setup.gutter.unwrap_or(0.0)))
+}
+
+/// Header/footer paragraphs, one per line of `text`. Tab-separated text becomes
+/// left/center/right zones with center and right tab stops; the logo goes at the start
+/// of its zone on the first line.
+fn header_footer_paragraphs(text: &str, layout: Option<&HeaderFooterLayout>, text_width: u32) -> Vec<Paragraph> {
+    text.split('\n').enumerate()
+        .map(|(n, line)| header_footer_paragraph(line, layout, n == 0, text_width))
+        .collect()
+}
+
+fn header_footer_paragraph(text: &str, layout: Option<&HeaderFooterLayout>, with_logo: bool, text_width: u32) -> Paragraph {
+    let mut zones: Vec<&str> = text.split('\t').collect();
+    let logo = layout.filter(|_| with_logo)
+        .and_then(|l| l.logo.as_ref().map(|logo| (logo, logo_zone(l.logo_position.as_deref()).unwrap_or(0))));
+    if let Some((_, zone)) = logo {
+        while zones.len() <= zone { zones.push(""); }
+    }
+    let mut para = Paragraph::new();
+    if zones.len() > 1 {
+        para = para
+            .add_tab(Tab::new().val(TabValueType::Center).pos((text_width / 2) as usize))
+            .add_tab(Tab::new().val(TabValueType::Right).pos(text_width as usize));
+    } else if let Some(alignment) = layout.and_then(|l| l.alignment.as_deref()).and_then(alignment_type) {
+        para = para.align(alignment);
+    }
+    for (i, zone) in zones.iter().enumerate() {
+        if i > 0 {
+            para = para.add_run(Run::new().add_tab());
+        }
+        if let Some((image, _)) = logo.filter(|(_, z)| *z == i) {
+            let pic = Pic::new_with_dimensions(image.data.clone(), image.width.unwrap_or(120), image.height.unwrap_or(40));
+            para = para.add_run(Run::new().add_image(pic));
+        }
+        if !zone.is_empty() {
+            para = para.add_run(Run::new().add_text(*zone));
+        }
+    }
+    para
+}
+
+/// The header/footer table with its cell content; widths and borders are written after
+/// packing by `write_header_footer_tables`
+fn header_footer_table(table: &HeaderFooterTable) -> Table {
+    let cells = table.cells.iter().map(|cell| {
+        let alignment = cell.alignment.as_deref().and_then(alignment_type);
+        cell.text.split('\n').enumerate().fold(TableCell::new(), |tc, (n, line)| {
+            let mut para = Paragraph::new();
+            if let Some(alignment) = alignment {
+                para = para.align(alignment);
+            }
+            if let Some(image) = cell.image.as_ref().filter(|_| n == 0) {
+                let pic = Pic::new_with_dimensions(image.data.clone(), image.width.unwrap_or(120), image.height.unwrap_or(40));
+                para = para.add_run(Run::new().add_image(pic));
+            }
+            if !line.is_empty() {
+                para = para.add_run(Run::new().add_text(line));
+            }
+            tc.add_paragraph(para)
+        })
+    }).collect();
+    Table::new(vec![]).add_row(TableRow::new(cells))
+}
+
+/// Give the header/footer tables the full text width, split by their relative column
+/// widths, in a fixed layout without borders unless asked for
+fn write_header_footer_tables(docx_path: &Path, package: PackageOptions, header: Option<&HeaderFooterTable>, footer: Option<&HeaderFooterTable>, text_width: u32) -> Result<()> {
+    use crate::bidi::insert_property;
+    let tbl_re = regex::Regex::new(r"(?s)<w:tbl>(.*?)</w:tbl>")?;
+    let grid_re = regex::Regex::new(r"(?s)<w:tblGrid\s*/>|<w:tblGrid>.*?</w:tblGrid>")?;
+    let tc_re = regex::Regex::new(r"(?s)(<w:tc(?:\s[^>]*)?>)(.*?)</w:tc>")?;
+    let tcw_re = regex::Regex::new(r"<w:tcW\b[^>]*/>")?;
+    rewrite_xml_parts(docx_path, package, |name, xml| {
+        let table = if name.starts_with("word/header") { header } else if name.starts_with("word/footer") { footer } else { None }?;
+        let weights = table.col_widths.clone().unwrap_or_else(|| vec![1; table.cells.len()]);
+        let total: u32 = weights.iter().sum::<u32>().max(1);
+        let widths: Vec<u32> = weights.iter().map(|w| text_width * w / total).collect();
+        let grid: String = widths.iter().map(|w| format!(r#"<w:gridCol w:w="{}"/>"#, w)).collect();
+        Some(tbl_re.replacen(xml, 1, |caps: &regex::Captures| {
+            let mut content = regex::Regex::new(r"<w:tblW\b[^>]*/>|<w:tblLayout\b[^>]*/>").unwrap().replace_all(&caps[1], "").into_owned();
+            content = insert_property(&content, "tblPr", r#"<w:tblW w:w="5000" w:type="pct"/>"#, &TBL_PR_TAIL[1..]);
+            content = insert_property(&content, "tblPr", r#"<w:tblLayout w:type="fixed"/>"#, &TBL_PR_TAIL[7..]);
+            if !table.borders {
+                let none: String = ["top", "left", "bottom", "right", "insideH", "insideV"].iter()
+                    .map(|side| format!(r#"<w:{} w:val="nil"/>"#, side)).collect();
+                content = regex::Regex::new(r"(?s)<w:tblBorders\s*/>|<w:tblBorders>.*?</w:tblBorders>").unwrap().replace_all(&content, "").into_owned();
+                content = insert_property(&content, "tblPr", &format!("<w:tblBorders>{}</w:tblBorders>", none), &TBL_PR_TAIL[5..]);
+            }
+            content = if grid_re.is_match(&content) {
+                grid_re.replace(&content, format!("<w:tblGrid>{}</w:tblGrid>", grid).as_str()).into_owned()
+            } else {
+                content.replacen("</w:tblPr>", &format!("</w:tblPr><w:tblGrid>{}</w:tblGrid>", grid), 1)
+            };
+            let mut col = 0;
+            content = tc_re.replace_all(&content, |tc: &regex::Captures| {
+                let width = widths.get(col).copied().unwrap_or(0);
+                col += 1;
+                let cell = tcw_re.replace(&tc[2], "").into_owned();
+                let cell = insert_property(&cell, "tcPr", &format!(r#"<w:tcW w:w="{}" w:type="dxa"/>"#, width), TC_W_FOLLOWERS);
+                format!("{}{}</w:tc>", &tc[1], cell)
+            }).into_owned();
+            format!("<w:tbl>{}</w:tbl>", content)
+        }).into_owned())
+    })
+}
+
+const SECTION_BREAK_MARKER: &str = "__SECTION_BREAK__";
+
+const EMPTY_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"></Relationships>"#;
+
+const EMPTY_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><w:hdr xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"></w:hdr>"#;
+
+fn has_section_watermarks(ops: &[DocxOp]) -> bool {
+    ops.iter().any(|op| matches!(op, DocxOp::Watermark(w) if w.section.is_some()))
+}
+
+/// The watermark of each section: its own, else the document-wide one
+fn section_watermarks(ops: &[DocxOp]) -> Vec<Option<&Watermark>> {
+    let sections = ops.iter().filter(|op| matches!(op, DocxOp::SectionBreak { .. })).count() + 1;
+    let find = |section: Option<usize>| ops.iter().rev().find_map(|op| match op {
+        DocxOp::Watermark(w) if w.section == section => Some(w),
+        _ => None,
+    });
+    (0..sections).map(|s| find(Some(s)).or_else(|| find(None))).collect()
+}
+
+/// File extension and pixel size of a watermark picture
+fn watermark_image_info(data: &[u8]) -> Result<(&'static str, f64, f64)> {
+    if let Ok(format) = ::image::guess_format(data) {
+        let ext = match format {
+            ::image::ImageFormat::Png => "png",
+            ::image::ImageFormat::Jpeg => "jpeg",
+            other => anyhow::bail!("{:?} pictures can't be watermarks (use PNG, JPEG or SVG)", other),
+        };
+        let image = ::image::load_from_memory_with_format(data, format).context("Unreadable watermark picture")?;
+        return Ok((ext, image.width() as f64, image.height() as f64));
+    }
+    let not_svg = || anyhow::anyhow!("Watermark picture is not PNG, JPEG or SVG");
+    let text = std::str::from_utf8(data).map_err(|_| not_svg())?;
+    let doc = roxmltree::Document::parse(text).map_err(|_| not_svg())?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(not_svg());
+    }
+    let view_box: Vec<f64> = root.attribute("viewBox")
+        .map(|v| v.split(|c: char| c == ',' || c.is_whitespace()).filter_map(|n| n.parse().ok()).collect())
+        .unwrap_or_default();
+    let width = root.attribute("width").and_then(svg_length).or_else(|| view_box.get(2).copied()).unwrap_or(300.0);
+    let height = root.attribute("height").and_then(svg_length).or_else(|| view_box.get(3).copied()).unwrap_or(150.0);
+    Ok(("svg", width, height))
+}
+
+/// An SVG length in pixels; percentages and unknown units have no fixed size
+fn svg_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    let number: f64 = value[..split].parse().ok()?;
+    let per_unit = match value[split..].trim() {
+        "" | "px" => 1.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        _ => return None,
+    };
+    Some(number * per_unit).filter(|px| *px > 0.0)
+}
+
+/// Transparent PNG shown instead of an SVG watermark by Word versions without SVG support
+fn blank_png() -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    ::image::DynamicImage::ImageRgba8(::image::RgbaImage::new(1, 1))
+        .write_to(&mut std::io::Cursor::new(&mut png), ::image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// VML WordArt run for a text watermark, the way Word writes its own. The shape spans
+/// the text width with a height that keeps the letters in proportion.
+fn text_watermark_run(text: &str, font: Option<&str>, color: Option<&str>, opacity: Option<f32>, diagonal: bool, n: usize, text_width: u32) -> String {
+    let width = text_width as f64 / 20.0;
+    let height = (width / (text.chars().count().max(2) as f64 * 0.6)).min(width / 3.0);
+    format!(
+        concat!(
+            r##"<w:r><w:rPr><w:noProof/></w:rPr><w:pict>"##,
+            r##"<v:shapetype id="_x0000_t136" coordsize="21600,21600" o:spt="136" adj="10800" path="m@7,l@8,m@5,21600l@6,21600e">"##,
+            r##"<v:formulas><v:f eqn="sum #0 0 10800"/><v:f eqn="prod #0 2 1"/><v:f eqn="sum 21600 0 @1"/><v:f eqn="sum 0 0 @2"/>"##,
+            r##"<v:f eqn="sum 21600 0 @3"/><v:f eqn="if @0 @3 0"/><v:f eqn="if @0 21600 @1"/><v:f eqn="if @0 0 @2"/>"##,
+            r##"<v:f eqn="if @0 @4 21600"/><v:f eqn="mid @5 @6"/><v:f eqn="mid @8 @5"/><v:f eqn="mid @7 @8"/>"##,
+            r##"<v:f eqn="mid @6 @7"/><v:f eqn="sum @6 0 @5"/></v:formulas>"##,
+            r##"<v:path textpathok="t" o:connecttype="custom" o:connectlocs="@9,0;@10,10800;@11,21600;@12,10800" o:connectangles="270,180,90,0"/>"##,
+            r##"<v:textpath on="t" fitshape="t"/><v:handles><v:h position="#0,bottomRight" xrange="6629,14971"/></v:handles>"##,
+            r##"<o:lock v:ext="edit" text="t" shapetype="t"/></v:shapetype>"##,
+            r##"<v:shape id="PowerPlusWaterMarkObject{n}" o:spid="_x0000_s{spid}" type="#_x0000_t136" "##,
+            r##"style="position:absolute;margin-left:0;margin-top:0;width:{width:.1}pt;height:{height:.1}pt;{rotation}z-index:-251654144;"##,
+            r##"mso-position-horizontal:center;mso-position-horizontal-relative:margin;mso-position-vertical:center;mso-position-vertical-relative:margin" "##,
+            r##"o:allowincell="f" fillcolor="{fill}" stroked="f"><v:fill opacity="{opacity}"/>"##,
+            r##"<v:textpath style="font-family:&quot;{font}&quot;;font-size:1pt" string="{text}"/>"##,
+            r##"<w10:wrap anchorx="margin" anchory="margin"/></v:shape></w:pict></w:r>"##,
+        ),
+        n = n,
+        spid = 2048 + n,
+        width = width,
+        height = height,
+        rotation = if diagonal { "rotation:315;" } else { "" },
+        fill = color.map(|c| format!("#{}", c.trim_start_matches('#'))).unwrap_or_else(|| "silver".to_string()),
+        opacity = opacity.unwrap_or(0.5),
+        font = xml_escape(font.unwrap_or("Calibri")),
+        text = xml_escape(text),
+    )
+}
+
+/// Anchored picture run for an image watermark, centred on the page behind the text.
+/// `svg_rel` is the SVG part when `rel` is its PNG fallback.
+fn image_watermark_run(n: usize, rel: &str, svg_rel: Option<&str>, cx: u64, cy: u64, washout: bool) -> String {
+    let washout = if washout { r#"<a:lum bright="70000" contrast="-70000"/>"# } else { "" };
+    let svg = svg_rel.map(|id| format!(
+        r#"<a:extLst><a:ext uri="{{96DAC541-7B7A-43D3-8B79-37D633B846F1}}"><asvg:svgBlip xmlns:asvg="http://schemas.microsoft.com/office/drawing/2016/SVG/main" r:embed="{}"/></a:ext></a:extLst>"#,
+        id
+    )).unwrap_or_default();
+    format!(
+        concat!(
+            r#"<w:r><w:rPr><w:noProof/></w:rPr><w:drawing>"#,
+            r#"<wp:anchor distT="0" distB="0" distL="0" distR="0" simplePos="0" relativeHeight="251658240" behindDoc="1" locked="0" layoutInCell="1" allowOverlap="1">"#,
+            r#"<wp:simplePos x="0" y="0"/><wp:positionH relativeFrom="margin"><wp:align>center</wp:align></wp:positionH>"#,
+            r#"<wp:positionV relativeFrom="margin"><wp:align>center</wp:align></wp:positionV>"#,
+            r#"<wp:extent cx="{cx}" cy="{cy}"/><wp:effectExtent l="0" t="0" r="0" b="0"/><wp:wrapNone/>"#,
+            r#"<wp:docPr id="{doc_pr}" name="Watermark {n}"/>"#,
+            r#"<wp:cNvGraphicFramePr><a:graphicFrameLocks xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" noChangeAspect="1"/></wp:cNvGraphicFramePr>"#,
+            r#"<a:graphic xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"><a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture">"#,
+            r#"<pic:pic xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture"><pic:nvPicPr><pic:cNvPr id="0" name="Watermark {n}"/><pic:cNvPicPr/></pic:nvPicPr>"#,
+            r#"<pic:blipFill><a:blip r:embed="{rel}">{washout}{svg}</a:blip><a:stretch><a:fillRect/></a:stretch></pic:blipFill>"#,
+            r#"<pic:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></pic:spPr>"#,
+            r#"</pic:pic></a:graphicData></a:graphic></wp:anchor></w:drawing></w:r>"#,
+        ),
+        cx = cx,
+        cy = cy,
+        doc_pr = 20_000 + n,
+        n = n,
+        rel = rel,
+        washout = washout,
+        svg = svg,
+    )
+}
+
+/// Declare the namespaces of the watermark markup on the header's root element
+fn with_watermark_namespaces(xml: &str) -> String {
+    const NAMESPACES: &[(&str, &str)] = &[
+        ("r", "http://schemas.openxmlformats.org/officeDocument/2006/relationships"),
+        ("v", "urn:schemas-microsoft-com:vml"),
+        ("o", "urn:schemas-microsoft-com:office:office"),
+        ("w10", "urn:schemas-microsoft-com:office:word"),
+        ("wp", "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing"),
+    ];
+    let Some(start) = xml.find("<w:hdr") else { return xml.to_string() };
+    let Some(end) = xml[start..].find('>').map(|e| start + e) else { return xml.to_string() };
+    let end = if xml[..end].ends_with('/') { end - 1 } else { end };
+    let missing: String = NAMESPACES.iter()
+        .filter(|(prefix, _)| !xml[start..end].contains(&format!("xmlns:{}=", prefix)))
+        .map(|(prefix, uri)| format!(r#" xmlns:{}="{}""#, prefix, uri))
+        .collect();
+    format!("{}{}{}", &xml[..end], missing, &xml[end..])
+}
+
+/// Add `run` to the end of the header's first paragraph outside a table, or to a new
+/// paragraph when it has none
+fn append_header_run(xml: &str, run: &str) -> Result<String> {
+    let p_re = regex::Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>")?;
+    let tbl_re = regex::Regex::new(r"(?s)<w:tbl>.*?</w:tbl>")?;
+    let tables: Vec<_> = tbl_re.find_iter(xml).map(|m| m.range()).collect();
+    if let Some(p) = p_re.find_iter(xml).find(|p| !tables.iter().any(|t| t.contains(&p.start()))) {
+        let at = p.end() - "</w:p>".len();
+        return Ok(format!("{}{}{}", &xml[..at], run, &xml[at..]));
+    }
+    let at = xml.rfind("</w:hdr>").context("Header part has no closing </w:hdr>")?;
+    Ok(format!("{}<w:p>{}</w:p>{}", &xml[..at], run, &xml[at..]))
+}
+
+/// Target of relationship `id`, relative to the part's folder
+fn relationship_target(rels: &str, id: &str) -> Option<String> {
+    let doc = roxmltree::Document::parse(rels).ok()?;
+    let target = doc.descendants()
+        .find(|n| n.tag_name().name() == "Relationship" && n.attribute("Id") == Some(id))?
+        .attribute("Target")?;
+    Some(target.to_string())
+}
+
+fn add_relationship(rels: &str, id: &str, kind: &str, target: &str) -> String {
+    rels.replacen("</Relationships>", &format!(
+        r#"<Relationship Id="{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/{}" Target="{}"/></Relationships>"#,
+        id, kind, target
+    ), 1)
+}
+
+/// Draw the watermarks from the headers. A document-wide watermark goes into the default
+/// header, which is created when the document has none. With section watermarks, the
+/// section break markers become real section breaks and each section points at a copy
+/// of the default header holding its own watermark, so header text and layout carry on
+/// unchanged across the sections.
+fn write_watermarks(docx_path: &Path, package: PackageOptions, ops: &[DocxOp], text_width: u32) -> Result<()> {
+    const HEADER_TYPE: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.header+xml";
+    let assigned = section_watermarks(ops);
+    if assigned.iter().all(Option::is_none) {
+        return Ok(());
+    }
+    let sectioned = has_section_watermarks(ops);
+    let mut parts = read_package_parts(docx_path)?;
+    let mut document = part_text(&parts, "word/document.xml").context("word/document.xml is missing")?;
+    let mut doc_rels = part_text(&parts, "word/_rels/document.xml.rels").unwrap_or_else(|| EMPTY_RELS.to_string());
+    let mut types = part_text(&parts, "[Content_Types].xml").context("[Content_Types].xml is missing")?;
+
+    // Body section properties and the default header they point at
+    let sect_start = document.rfind("<w:sectPr").context("document.xml has no section properties")?;
+    let sect_end = document[sect_start..].find("</w:sectPr>")
+        .map(|e| sect_start + e + "</w:sectPr>".len())
+        .context("document.xml has unterminated section properties")?;
+    let body_sect = document[sect_start..sect_end].to_string();
+    let header_ref_re = regex::Regex::new(r#"<w:headerReference\b[^>]*w:type="default"[^>]*/>"#)?;
+    let id_re = regex::Regex::new(r#"r:id="([^"]+)""#)?;
+    let base_id = header_ref_re.find(&body_sect).and_then(|m| id_re.captures(m.as_str())).map(|c| c[1].to_string());
+    let base_part = base_id.as_deref()
+        .and_then(|id| relationship_target(&doc_rels, id))
+        .map(|target| format!("word/{}", target.trim_start_matches('/').trim_start_matches("word/")));
+    let rels_name = |part: &str| match part.rsplit_once('/') {
+        Some((dir, file)) => format!("{}/_rels/{}.rels", dir, file),
+        None => format!("_rels/{}.rels", part),
+    };
+    let base_xml = base_part.as_deref().and_then(|part| part_text(&parts, part));
+    let base_rels = base_part.as_deref().and_then(|part| part_text(&parts, &rels_name(part)));
+
+    let mut next_header = parts.iter()
+        .filter_map(|(name, _)| name.strip_prefix("word/header")?.strip_suffix(".xml")?.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0) + 1;
+    // Relationship id of the header part drawn for each distinct watermark (or none)
+    let mut headers: Vec<(Option<*const Watermark>, String)> = Vec::new();
+    let mut section_refs: Vec<String> = Vec::new();
+    let mut shape = 0;
+    for watermark in assigned.iter().take(if sectioned { assigned.len() } else { 1 }).copied() {
+        let key = watermark.map(|w| w as *const Watermark);
+        if let Some((_, id)) = headers.iter().find(|(k, _)| *k == key) {
+            section_refs.push(id.clone());
+            continue;
+        }
+        let (part, id) = match (watermark, &base_part, &base_id) {
+            // Sections without a watermark keep the default header as it is
+            (None, Some(_), Some(id)) => {
+                headers.push((key, id.clone()));
+                section_refs.push(id.clone());
+                continue;
+            }
+            (Some(_), Some(part), Some(id)) if !sectioned => (part.clone(), id.clone()),
+            _ => {
+                let part = format!("word/header{}.xml", next_header);
+                let id = format!("rIdWatermarkHeader{}", next_header);
+                next_header += 1;
+                doc_rels = add_relationship(&doc_rels, &id, "header", &part["word/".len()..]);
+                types = types.replacen("</Types>", &format!(r#"<Override PartName="/{}" ContentType="{}"/></Types>"#, part, HEADER_TYPE), 1);
+                (part, id)
+            }
+        };
+        let mut xml = base_xml.clone().unwrap_or_else(|| EMPTY_HEADER.to_string());
+        let mut rels = base_rels.clone().unwrap_or_else(|| EMPTY_RELS.to_string());
+        if let Some(watermark) = watermark {
+            shape += 1;
+            let run = match &watermark.content {
+                WatermarkContent::Text { text, font, color, opacity, diagonal } => {
+                    text_watermark_run(text, font.as_deref(), color.as_deref(), *opacity, *diagonal, shape, text_width)
+                }
+                WatermarkContent::Image { data, width, washout } => {
+                    let (ext, px_width, px_height) = watermark_image_info(data)?;
+                    let cx = match width {
+                        Some(inches) => *inches as f64 * 914_400.0,
+                        None => (px_width * 9525.0).min(text_width as f64 * 635.0),
+                    };
+                    let cy = cx * px_height / px_width;
+                    let media = format!("media/watermark{}.{}", shape, ext);
+                    set_part(&mut parts, &format!("word/{}", media), data.clone());
+                    let mut svg_rel = None;
+                    if ext == "svg" {
+                        let fallback = format!("media/watermark{}.png", shape);
+                        set_part(&mut parts, &format!("word/{}", fallback), blank_png()?);
+                        rels = add_relationship(&rels, "rIdWatermarkSvg", "image", &media);
+                        rels = add_relationship(&rels, "rIdWatermarkImage", "image", &fallback);
+                        svg_rel = Some("rIdWatermarkSvg");
+                    } else {
+                        rels = add_relationship(&rels, "rIdWatermarkImage", "image", &media);
+                    }
+                    for (ext, mime) in [("png", "image/png"), (ext, if ext == "svg" { "image/svg+xml" } else { "image/jpeg" })] {
+                        if !types.contains(&format!(r#"Extension="{}""#, ext)) {
+                            types = types.replacen("<Default ", &format!(r#"<Default Extension="{}" ContentType="{}"/><Default "#, ext, mime), 1);
+                        }
+                    }
+                    image_watermark_run(shape, "rIdWatermarkImage", svg_rel, cx.round() as u64, cy.round() as u64, *washout)
+                }
+            };
+            xml = append_header_run(&with_watermark_namespaces(&xml), &run)?;
+        }
+        set_part(&mut parts, &part, xml.into_bytes());
+        if rels.contains("<Relationship ") {
+            set_part(&mut parts, &rels_name(&part), rels.into_bytes());
+        }
+        headers.push((key, id.clone()));
+        section_refs.push(id);
+    }
+
+    // Point every section at its header part
+    let sect_with = |id: &str| -> String {
+        let stripped = header_ref_re.replace_all(&body_sect, "");
+        let open_end = stripped.find('>').map_or(stripped.len(), |e| e + 1);
+        format!(r#"{}<w:headerReference w:type="default" r:id="{}"/>{}"#, &stripped[..open_end], id, &stripped[open_end..])
+    };
+    let last = section_refs.last().cloned().unwrap_or_default();
+    document.replace_range(sect_start..sect_end, &sect_with(&last));
+    if sectioned {
+        let p_re = regex::Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>")?;
+        let mut section = 0;
+        document = p_re.replace_all(&document, |caps: &regex::Captures| {
+            if !caps[0].contains(SECTION_BREAK_MARKER) {
+                return caps[0].to_string();
+            }
+            let id = section_refs.get(section).unwrap_or(&last);
+            section += 1;
+            format!("<w:p><w:pPr>{}</w:pPr></w:p>", sect_with(id))
+        }).into_owned();
+    }
+    set_part(&mut parts, "word/document.xml", document.into_bytes());
+    set_part(&mut parts, "word/_rels/document.xml.rels", doc_rels.into_bytes());
+    set_part(&mut parts, "[Content_Types].xml", types.into_bytes());
+    write_package_parts(docx_path, package, &parts)
+}
+
+/// tcPr children after `w:tcW` in the schema order
+const TC_W_FOLLOWERS: &[&str] = &[
+    "<w:gridSpan", "<w:hMerge", "<w:vMerge", "<w:tcBorders", "<w:shd", "<w:noWrap", "<w:tcMar",
+    "<w:textDirection", "<w:tcFitText", "<w:vAlign", "<w:hideMark",
+];
+
+/// Map a user-facing alignment name to the docx-rs alignment
+fn alignment_type(name: &str) -> Option<AlignmentType> {
+    match name.to_ascii_lowercase().as_str() {
+        "left" | "start" => Some(AlignmentType::Left),
+        "center" | "centre" => Some(AlignmentType::Center),
+        "right" | "end" => Some(AlignmentType::Right),
+        "justify" | "justified" | "both" => Some(AlignmentType::Both),
+        _ => None,
+    }
+}
+
+/// Read a package part as UTF-8, or None when the part does not exist
+fn read_part(docx_path: &Path, name: &str) -> Result<Option<String>> {
+    use std::io::Read as _;
+    let file = File::open(docx_path)
+        .with_context(|| format!("Failed to open {:?}", docx_path))?;
+    let mut archive = ZipArchive::new(file)?;
+    let result = match archive.by_name(name) {
+        Ok(mut part) => {
+            let mut xml = String::new();
+            part.read_to_string(&mut xml)?;
+            Some(xml)
+        }
+        Err(_) => None,
+    };
+    Ok(result)
+}
+
+/// Heading level from a style id such as "Heading2" (defaults to 1)
+/// An `analyze_structure` section: the content between a heading and the next one
+fn new_section(heading: Option<&str>, level: usize, element_index: Option<usize>) -> serde_json::Value {
+    serde_json::json!({
+        "heading": heading,
+        "level": level,
+        "element_index": element_index,
+        "paragraphs": 0,
+        "lists": 0,
+        "tables": 0,
+        "images": 0,
+    })
+}
+
+fn bump_section(sections: &mut [serde_json::Value], key: &str) {
+    if let Some(section) = sections.last_mut() {
+        section[key] = serde_json::json!(section[key].as_u64().unwrap_or(0) + 1);
+    }
+}
+
+/// Drop the section before the first heading when it holds nothing
+fn drop_empty_preamble(mut sections: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    let empty = ["paragraphs", "lists", "tables", "images"].iter().all(|k| sections[0][*k] == 0);
+    if empty {
+        sections.remove(0);
+    }
+    sections
+}
+
+/// Ops for a Markdown fragment: ATX headings, bullet and numbered list items (two spaces
+/// of indent per level) and paragraphs, whose consecutive lines are joined. A paragraph
+/// wrapped whole in `**` or `_` is bold or italic.
+fn markdown_fragment_ops(markdown: &str) -> Vec<DocxOp> {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    static ORDERED: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d+[.)]\s+").unwrap());
+    fn flush(lines: &mut Vec<&str>, ops: &mut Vec<DocxOp>) {
+        if lines.is_empty() { return; }
+        let text = lines.join(" ");
+        lines.clear();
+        let (text, style) = match (text.strip_prefix("**").and_then(|t| t.strip_suffix("**")), text.strip_prefix('_').and_then(|t| t.strip_suffix('_'))) {
+            (Some(inner), _) if !inner.is_empty() => (inner.to_string(), Some(DocxStyle { bold: Some(true), ..Default::default() })),
+            (_, Some(inner)) if !inner.is_empty() => (inner.to_string(), Some(DocxStyle { italic: Some(true), ..Default::default() })),
+            _ => (text, None),
+        };
+        ops.push(DocxOp::Paragraph { text, style });
+    }
+    let mut ops = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            flush(&mut lines, &mut ops);
+            continue;
+        }
+        let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            flush(&mut lines, &mut ops);
+            ops.push(DocxOp::Heading { text: trimmed[hashes..].trim().to_string(), style: format!("Heading{}", hashes) });
+            continue;
+        }
+        let level = (line.len() - line.trim_start().len()) / 2;
+        let item = match trimmed.get(..2) {
+            Some("- " | "* " | "+ ") => Some((&trimmed[2..], false)),
+            _ => ORDERED.find(trimmed).map(|m| (&trimmed[m.end()..], true)),
+        };
+        match item {
+            Some((text, ordered)) => {
+                flush(&mut lines, &mut ops);
+                ops.push(DocxOp::ListItem { text: text.trim().to_string(), level: level.min(8), ordered });
+            }
+            None => lines.push(trimmed),
+        }
+    }
+    flush(&mut lines, &mut ops);
+    ops
+}
+
+/// Numbering ids of the heading list (the list ops use 10/11 and 20/21)
+const HEADING_ABSTRACT_NUM_ID: usize = 30;
+const HEADING_NUM_ID: usize = 31;
+
+/// Heading numbering schemes: (numFmt, lvlText) for heading levels 1-6
+const HEADING_NUMBER_SCHEMES: &[(&str, [(&str, &str); 6])] = &[
+    ("decimal", [
+        ("decimal", "%1."), ("decimal", "%1.%2."), ("decimal", "%1.%2.%3."),
+        ("decimal", "%1.%2.%3.%4."), ("decimal", "%1.%2.%3.%4.%5."), ("decimal", "%1.%2.%3.%4.%5.%6."),
+    ]),
+    ("legal", [
+        ("decimal", "%1"), ("decimal", "%1.%2"), ("decimal", "%1.%2.%3"),
+        ("decimal", "%1.%2.%3.%4"), ("decimal", "%1.%2.%3.%4.%5"), ("decimal", "%1.%2.%3.%4.%5.%6"),
+    ]),
+    ("outline", [
+        ("upperRoman", "%1."), ("upperLetter", "%2."), ("decimal", "%3."),
+        ("lowerLetter", "%4)"), ("lowerRoman", "%5)"), ("decimal", "(%6)"),
+    ]),
+];
+
+/// Numbers typed into heading text: "1." for top-level headings, "1.1" below
+const TYPED_HEADING_NUMBERS: [(&str, &str); 6] = [
+    ("decimal", "%1."), ("decimal", "%1.%2"), ("decimal", "%1.%2.%3"),
+    ("decimal", "%1.%2.%3.%4"), ("decimal", "%1.%2.%3.%4.%5"), ("decimal", "%1.%2.%3.%4.%5.%6"),
+];
+
+fn heading_scheme(name: &str) -> Option<&'static [(&'static str, &'static str); 6]> {
+    HEADING_NUMBER_SCHEMES.iter().find(|(n, _)| *n == name).map(|(_, levels)| levels)
+}
+
+/// The heading numbering in effect (the last one set), if any
+fn active_heading_scheme(ops: &[DocxOp]) -> Option<&'static [(&'static str, &'static str); 6]> {
+    ops.iter().rev().find_map(|op| match op {
+        DocxOp::HeadingNumbering { scheme } => Some(heading_scheme(scheme)),
+        _ => None,
+    }).flatten()
+}
+
+/// One counter in a list number format
+fn format_list_number(n: usize, format: &str) -> String {
+    fn letters(mut n: usize, base: u8) -> String {
+        let mut out = Vec::new();
+        while n > 0 {
+            n -= 1;
+            out.push((base + (n % 26) as u8) as char);
+            n /= 26;
+        }
+        out.iter().rev().collect()
+    }
+    fn roman(mut n: usize) -> String {
+        const NUMERALS: &[(usize, &str)] = &[
+            (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+            (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+        ];
+        let mut out = String::new();
+        for (value, numeral) in NUMERALS {
+            while n >= *value {
+                out.push_str(numeral);
+                n -= value;
+            }
+        }
+        out
+    }
+    match format {
+        "upperRoman" => roman(n),
+        "lowerRoman" => roman(n).to_lowercase(),
+        "upperLetter" => letters(n, b'A'),
+        "lowerLetter" => letters(n, b'a'),
+        _ => n.to_string(),
+    }
+}
+
+/// The number each heading op gets under `levels`, as Word would render it (None for
+/// other ops). Skipped levels count as 1.
+fn heading_numbers(ops: &[DocxOp], levels: &[(&str, &str); 6]) -> Vec<Option<String>> {
+    let mut counters = [0usize; 6];
+    ops.iter().map(|op| match op {
+        DocxOp::Heading { style, .. } => {
+            let level = heading_level(style).clamp(1, 6);
+            counters[level - 1] += 1;
+            counters[level..].iter_mut().for_each(|c| *c = 0);
+            let mut number = levels[level - 1].1.to_string();
+            for (k, (format, _)) in levels.iter().enumerate().take(level) {
+                number = number.replace(&format!("%{}", k + 1), &format_list_number(counters[k].max(1), format));
+            }
+            Some(number)
+        }
+        _ => None,
+    }).collect()
+}
+
+/// Heading text without a typed leading number ("2.", "3.1 ")
+fn strip_typed_number(text: &str) -> Option<&str> {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    static TYPED_NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\d+(?:\.\d+)*\.?\s+").unwrap());
+    TYPED_NUMBER.find(text).map(|m| &text[m.end()..])
+}
+
+/// Split a heading into the number prefix typed before it and the rest. Besides the
+/// "1.2 " form this takes "2)", "IV.", "b.", "Section 3:" and "3 -" prefixes.
+fn split_heading_prefix(text: &str) -> (&str, &str) {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    static PREFIX: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+        r"^\s*(?:(?i:section|chapter|part)\s+)?",
+        r"(?:\d{1,3}(?:\.\d{1,3})*[.):]?|[IVXLC]+[.):]|[A-Za-z][.)])",
+        r"(?:\s*[-\x{2013}\x{2014}:]\s*|\s+)",
+    )).unwrap());
+    match PREFIX.find(text) {
+        Some(m) if m.end() < text.len() => (&text[..m.end()], &text[m.end()..]),
+        _ => ("", text),
+    }
+}
+
+fn strip_heading_numbers(ops: &mut [DocxOp]) {
+    for op in ops.iter_mut() {
+        if let DocxOp::Heading { text, .. } = op {
+            if let Some(bare) = strip_typed_number(text) {
+                *text = bare.to_string();
+            }
+        }
     }
+}
 
-    pub fn save_document(&self, doc_id: &str, output_path: &Path) -> Result<()> {
-        let metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
-        
-        fs::copy(&metadata.path, output_path)
-            .with_context(|| format!("Failed to save document to {:?}", output_path))?;
-        
-        info!("Saved document {} to {:?}", doc_id, output_path);
-        Ok(())
+/// Replace typed heading numbers with one outline sequence ("1.", "1.1", "2.") over all
+/// headings, numbering the ones that had none too
+fn renumber_heading_text(ops: &mut [DocxOp]) {
+    let numbers = heading_numbers(ops, &TYPED_HEADING_NUMBERS);
+    for (op, number) in ops.iter_mut().zip(numbers) {
+        if let (DocxOp::Heading { text, .. }, Some(number)) = (op, number) {
+            let bare = strip_typed_number(text).unwrap_or(text.as_str()).to_string();
+            *text = format!("{} {}", number, bare);
+        }
     }
+}
 
-    pub fn close_document(&mut self, doc_id: &str) -> Result<()> {
-        let metadata = self.documents.remove(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
-        
-        if metadata.path.exists() {
-            fs::remove_file(&metadata.path)?;
+/// Content ops of a .docx or .md file, without the document settings of a .docx
+fn fragment_ops(path: &Path) -> Result<Vec<DocxOp>> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    Ok(match ext.as_str() {
+        "md" => markdown_fragment_ops(&fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?),
+        "docx" => {
+            let read = crate::docx_reader::read_docx(path)?;
+            read.ops.into_iter().take(read.fidelity.elements).collect()
         }
-        self.in_memory_ops.remove(doc_id);
-        
-        info!("Closed document {}", doc_id);
-        Ok(())
+        _ => anyhow::bail!("Expected a .docx or .md file: {:?}", path),
+    })
+}
+
+/// Wrap ops `start..=end` in keep-together markers; returns the number of content
+/// elements in the group
+fn insert_keep_group(ops: &mut Vec<DocxOp>, start: usize, end: usize) -> Result<usize> {
+    if start > end || end >= ops.len() {
+        anyhow::bail!("Invalid element range {}..={} (document has {} elements)", start, end, ops.len());
     }
+    let opened = ops[..start].iter().filter(|op| matches!(op, DocxOp::KeepTogetherStart)).count();
+    let closed = ops[..start].iter().filter(|op| matches!(op, DocxOp::KeepTogetherEnd)).count();
+    if opened > closed || ops[start..=end].iter().any(|op| matches!(op, DocxOp::KeepTogetherStart | DocxOp::KeepTogetherEnd)) {
+        anyhow::bail!("Elements {}..={} overlap an existing keep-together group", start, end);
+    }
+    let count = ops[start..=end].iter().filter(|op| !is_document_setting(op)).count();
+    if count == 0 {
+        anyhow::bail!("Elements {}..={} hold no content to keep together", start, end);
+    }
+    ops.insert(end + 1, DocxOp::KeepTogetherEnd);
+    ops.insert(start, DocxOp::KeepTogetherStart);
+    Ok(count)
+}
 
-    pub fn list_documents(&self) -> Vec<DocxMetadata> {
-        self.documents.values().cloned().collect()
+/// Op index where content placed `after` (or before) an anchor goes. After a heading
+/// means directly below the heading line, past any bookmark attached to it.
+fn anchor_position(ops: &[DocxOp], anchor: &InsertAnchor, after: bool) -> Result<usize> {
+    let index = match anchor {
+        InsertAnchor::Heading(heading) => section_bounds(ops, Some(heading), None, true)?.0,
+        InsertAnchor::Bookmark(name) => ops.iter()
+            .position(|op| matches!(op, DocxOp::BookmarkAfterHeading { name: n, .. } if n == name))
+            .ok_or_else(|| anyhow::anyhow!("Bookmark not found: {}", name))?,
+        InsertAnchor::ElementId(i) => {
+            if *i >= ops.len() {
+                anyhow::bail!("Element {} is past the end of the document ({} elements)", i, ops.len());
+            }
+            *i
+        }
+        InsertAnchor::TableIndex(t) => ops.iter().enumerate()
+            .filter(|(_, op)| matches!(op, DocxOp::Table { .. }))
+            .nth(*t)
+            .map(|(i, _)| i)
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", t))?,
+    };
+    let mut at = if after { index + 1 } else { index };
+    if after && matches!(anchor, InsertAnchor::Heading(_)) {
+        while matches!(ops.get(at), Some(DocxOp::BookmarkAfterHeading { .. })) {
+            at += 1;
+        }
     }
+    Ok(at)
+}
 
-    pub fn temp_dir_path(&self) -> PathBuf {
-        self.temp_dir.clone()
+/// The ops for elements given to `insert_at` or `set_section_content`, checked
+fn new_element_ops(elements: Vec<NewElement>) -> Result<Vec<DocxOp>> {
+    let mut new_ops = Vec::with_capacity(elements.len());
+    for element in elements {
+        new_ops.push(match element {
+            NewElement::Paragraph { text, style } => DocxOp::Paragraph { text, style },
+            NewElement::Heading { text, level } => {
+                if !(1..=6).contains(&level) {
+                    anyhow::bail!("Heading level must be 1-6 (got {})", level);
+                }
+                DocxOp::Heading { text, style: format!("Heading{}", level) }
+            }
+            NewElement::List { items, ordered } => DocxOp::List { items, ordered },
+            NewElement::Table(data) => {
+                data.validate()?;
+                DocxOp::Table { data }
+            }
+            NewElement::PageBreak => DocxOp::PageBreak,
+        });
     }
+    Ok(new_ops)
+}
 
-    pub fn get_storage_info(&self) -> Result<serde_json::Value> {
-        use std::time::UNIX_EPOCH;
-        let mut total_bytes: u64 = 0;
-        let mut file_count: u64 = 0;
-        let mut oldest: Option<u64> = None;
-        let mut newest: Option<u64> = None;
-        if self.temp_dir.exists() {
-            for entry in walkdir::WalkDir::new(&self.temp_dir).into_iter().filter_map(|e| e.ok()) {
-                if entry.file_type().is_file() {
-                    file_count += 1;
-                    if let Ok(meta) = entry.metadata() {
-                        total_bytes = total_bytes.saturating_add(meta.len());
-                        if let Ok(modified) = meta.modified() {
-                            if let Ok(secs) = modified.duration_since(UNIX_EPOCH) {
-                                let ts = secs.as_secs();
-                                oldest = Some(oldest.map_or(ts, |o| o.min(ts)));
-                                newest = Some(newest.map_or(ts, |n| n.max(ts)));
-                            }
-                        }
+/// Ops that configure the whole document or mark a range rather than holding content
+/// of their own; element-range edits leave them where they are
+fn is_document_setting(op: &DocxOp) -> bool {
+    matches!(op,
+        DocxOp::Header(_) | DocxOp::Footer(_) | DocxOp::BookmarkAfterHeading { .. }
+            | DocxOp::TagStart(_) | DocxOp::TagEnd(_) | DocxOp::EmbedFont { .. }
+            | DocxOp::HeadingNumbering { .. } | DocxOp::PageSetup(_) | DocxOp::HeaderLayout { .. }
+            | DocxOp::DefaultFont { .. } | DocxOp::DefaultProofing(_) | DocxOp::ProofingStart(_)
+            | DocxOp::ProofingEnd | DocxOp::Watermark(_) | DocxOp::KeepTogetherStart | DocxOp::KeepTogetherEnd)
+}
+
+fn heading_level(style: &str) -> usize {
+    style.chars().last().and_then(|c| c.to_digit(10)).map(|d| d as usize).unwrap_or(1)
+}
+
+/// Replace `pattern` in the text of `ops` within `options.scope`, and in the extra
+/// targets of `options.include`, returning every replacement made
+fn replace_in_ops(
+    ops: &mut [DocxOp],
+    metadata: &mut DocxMetadata,
+    pattern: &str,
+    replacement: &str,
+    options: &ReplaceOptions,
+) -> Result<Vec<Replacement>> {
+    use regex::RegexBuilder;
+
+    // Elements [start, end) a section or range scope may touch
+    let bounds = match &options.scope {
+        ReplaceScope::Heading(heading) => Some(section_bounds(ops, Some(heading.as_str()), None, true)?),
+        ReplaceScope::ElementRange { start, end } => {
+            if start > end || *end >= ops.len() {
+                anyhow::bail!("Invalid element range {}..={} (document has {} elements)", start, end, ops.len());
+            }
+            Some((*start, end + 1))
+        }
+        _ => None,
+    };
+
+    // Build regex
+    let pattern = if options.use_regex { pattern.to_string() } else { regex::escape(pattern) };
+    let pattern = if options.whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+    let re = RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .with_context(|| "Invalid regex pattern")?;
+
+    let mut replacements = Vec::new();
+
+    let expand = |caps: &regex::Captures| -> String {
+        let found = caps.get(0).unwrap();
+        let mut new = String::new();
+        if options.use_regex {
+            caps.expand(replacement, &mut new);
+        } else {
+            new.push_str(replacement);
+        }
+        if options.preserve_case {
+            new = crate::text_transform::match_case(found.as_str(), &new);
+        }
+        new
+    };
+    let mut replace_text = |text: &str, at: Replacement| -> (String, usize) {
+        let mut count = 0usize;
+        let result = re.replace_all(text, |caps: &regex::Captures| {
+            let found = caps.get(0).unwrap();
+            let new = expand(caps);
+            replacements.push(Replacement {
+                matched: found.as_str().to_string(),
+                before: match_context(text, found.start(), found.end(), found.as_str()),
+                after: match_context(text, found.start(), found.end(), &new),
+                replacement: new.clone(),
+                ..at.clone()
+            });
+            count += 1;
+            new
+        });
+        (result.into_owned(), count)
+    };
+
+    for (i, op) in ops.iter_mut().enumerate() {
+        let in_scope = match &options.scope {
+            ReplaceScope::All => true,
+            ReplaceScope::Tables => matches!(op, DocxOp::Table { .. }),
+            ReplaceScope::Headers => matches!(op, DocxOp::Header(_) | DocxOp::Footer(_)),
+            // Headers and footers belong to the whole document, not to a section
+            ReplaceScope::Heading(_) | ReplaceScope::ElementRange { .. } => {
+                bounds.is_some_and(|(start, end)| (start..end).contains(&i))
+                    && !matches!(op, DocxOp::Header(_) | DocxOp::Footer(_))
+            }
+        };
+        if !in_scope {
+            continue;
+        }
+        let at = |kind: &str, item, cell| Replacement { element_index: Some(i), kind: kind.to_string(), item, cell, ..Default::default() };
+        match op {
+            DocxOp::Paragraph { text, .. } => {
+                let (new_text, cnt) = replace_text(text, at("paragraph", None, None));
+                if cnt > 0 { *text = new_text; }
+            }
+            DocxOp::Heading { text, .. } => {
+                let (new_text, cnt) = replace_text(text, at("heading", None, None));
+                if cnt > 0 { *text = new_text; }
+            }
+            DocxOp::List { items, .. } => {
+                for (n, item) in items.iter_mut().enumerate() {
+                    let (new_text, cnt) = replace_text(item, at("list_item", Some(n), None));
+                    if cnt > 0 { *item = new_text; }
+                }
+            }
+            DocxOp::ListItem { text, .. } => {
+                let (new_text, cnt) = replace_text(text, at("list_item", None, None));
+                if cnt > 0 { *text = new_text; }
+            }
+            DocxOp::Table { data } => {
+                for (r, row) in data.rows.iter_mut().enumerate() {
+                    for (c, cell) in row.iter_mut().enumerate() {
+                        let (new_text, cnt) = replace_text(cell, at("table_cell", None, Some([r, c])));
+                        if cnt > 0 { *cell = new_text; }
                     }
                 }
             }
-        }
-        Ok(serde_json::json!({
-            "success": true,
-            "storage": {
-                "base_dir": self.temp_dir,
-                "file_count": file_count,
-                "total_bytes": total_bytes,
-                "oldest_modified": oldest,
-                "newest_modified": newest,
+            DocxOp::Header(text) => {
+                let (new_text, cnt) = replace_text(text, at("header", None, None));
+                if cnt > 0 { *text = new_text; }
             }
-        }))
+            DocxOp::Footer(text) => {
+                let (new_text, cnt) = replace_text(text, at("footer", None, None));
+                if cnt > 0 { *text = new_text; }
+            }
+            DocxOp::Image { alt_text: Some(alt_text), .. } if options.include.contains(&ReplaceTarget::AltText) => {
+                let (new_text, cnt) = replace_text(alt_text, at("image_alt_text", None, None));
+                if cnt > 0 { *alt_text = new_text; }
+            }
+            DocxOp::Hyperlink { text, url } => {
+                let (new_text, cnt) = replace_text(text, at("hyperlink", None, None));
+                if cnt > 0 { *text = new_text; }
+                if options.include.contains(&ReplaceTarget::HyperlinkUrls) {
+                    let (new_url, cnt) = replace_text(url, at("hyperlink_url", None, None));
+                    if cnt > 0 { *url = new_url; }
+                }
+            }
+            DocxOp::BookmarkAfterHeading { name, .. } if options.include.contains(&ReplaceTarget::BookmarkNames) => {
+                let renamed = re.replace_all(name, |caps: &regex::Captures| expand(caps));
+                if renamed.as_ref() != name.as_str() && !is_valid_bookmark_name(&renamed) {
+                    warn!("Not renaming bookmark {} to the invalid name {:?}", name, renamed);
+                    continue;
+                }
+                let (new_text, cnt) = replace_text(name, at("bookmark_name", None, None));
+                if cnt > 0 { *name = new_text; }
+            }
+            DocxOp::Image { .. } => {}
+            DocxOp::PageBreak => {}
+            DocxOp::SectionBreak { .. } => {}
+            DocxOp::Toc { .. } => {}
+            DocxOp::BookmarkAfterHeading { .. } => {}
+            DocxOp::Highlight { .. } | DocxOp::InvisibleText { .. } | DocxOp::EmbedFont { .. } | DocxOp::HeadingNumbering { .. } | DocxOp::PageSetup(_)
+            | DocxOp::HeaderLayout { .. } | DocxOp::DefaultFont { .. } => {}
+            DocxOp::TagStart(_) | DocxOp::TagEnd(_) => {}
+            DocxOp::DefaultProofing(_) | DocxOp::ProofingStart(_) | DocxOp::ProofingEnd => {}
+            DocxOp::KeepTogetherStart | DocxOp::KeepTogetherEnd => {}
+            DocxOp::Watermark(_) => {}
+        }
     }
+    // Properties describe the whole document, so they are not limited by the scope
+    if options.include.contains(&ReplaceTarget::Properties) {
+        for (property, value) in [("title", &mut metadata.title), ("subject", &mut metadata.subject), ("author", &mut metadata.author)] {
+            let Some(value) = value else { continue };
+            let at = Replacement { kind: "property".into(), property: Some(property.into()), ..Default::default() };
+            let (new_text, cnt) = replace_text(value, at);
+            if cnt > 0 { *value = new_text; }
+        }
+    }
+    Ok(replacements)
 }
 
-#[derive(Debug, Clone)]
-enum DocxOp {
-    Paragraph { text: String, style: Option<DocxStyle> },
-    Heading { text: String, style: String },
-    Table { data: TableData },
-    List { items: Vec<String>, ordered: bool },
-    ListItem { text: String, level: usize, ordered: bool },
-    PageBreak,
-    Header(String),
-    Footer(String),
-    Image { data: Vec<u8>, width: u32, height: u32, alt_text: Option<String> },
-    Hyperlink { text: String, url: String },
-    SectionBreak { page_size: Option<String>, orientation: Option<String>, margins: Option<MarginsSpec> },
-    Toc { from_level: usize, to_level: usize, right_align_dots: bool },
-    BookmarkAfterHeading { heading_text: String, name: String },
+/// Whether Word accepts a bookmark name: a letter, then letters, digits or underscores,
+/// 40 characters at most
+fn is_valid_bookmark_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphabetic)
+        && name.chars().count() <= 40
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MarginsSpec {
-    pub top: Option<f32>,
-    pub bottom: Option<f32>,
-    pub left: Option<f32>,
-    pub right: Option<f32>,
+/// Up to 30 characters either side of `text[start..end]`, with `middle` in its place
+fn match_context(text: &str, start: usize, end: usize, middle: &str) -> String {
+    const CONTEXT: usize = 30;
+    let from = text[..start].char_indices().rev().nth(CONTEXT - 1).map(|(i, _)| i).unwrap_or(0);
+    let to = text[end..].char_indices().nth(CONTEXT).map(|(i, _)| end + i).unwrap_or(text.len());
+    format!(
+        "{}{}{}{}{}",
+        if from > 0 { "…" } else { "" },
+        &text[from..start],
+        middle,
+        &text[end..to],
+        if to < text.len() { "…" } else { "" },
+    )
+}
+
+/// Locate the op range of a section: the heading index and the index one past its last element
+fn section_bounds(
+    ops: &[DocxOp],
+    heading: Option<&str>,
+    element_index: Option<usize>,
+    include_subsections: bool,
+) -> Result<(usize, usize)> {
+    let start = match (element_index, heading) {
+        (Some(i), _) => {
+            if !matches!(ops.get(i), Some(DocxOp::Heading { .. })) {
+                anyhow::bail!("Element {} is not a heading", i);
+            }
+            i
+        }
+        (None, Some(h)) => {
+            let exact = ops.iter().position(|op| matches!(op, DocxOp::Heading { text, .. } if text == h));
+            exact.or_else(|| ops.iter().position(|op| matches!(op, DocxOp::Heading { text, .. } if text.eq_ignore_ascii_case(h.trim()))))
+                .ok_or_else(|| anyhow::anyhow!("Heading not found: {}", h))?
+        }
+        (None, None) => anyhow::bail!("Provide a heading or element_index"),
+    };
+    let level = match &ops[start] {
+        DocxOp::Heading { style, .. } => heading_level(style),
+        _ => 1,
+    };
+    let end = ops.iter().enumerate().skip(start + 1)
+        .find(|(_, op)| match op {
+            DocxOp::Heading { style, .. } => !include_subsections || heading_level(style) <= level,
+            _ => false,
+        })
+        .map(|(i, _)| i)
+        .unwrap_or(ops.len());
+    Ok((start, end))
+}
+
+/// Parse an A1-style cell reference ("C7", "$C$7") into zero-based (row, col)
+fn parse_a1_cell(cell: &str) -> Result<(u32, u32)> {
+    let cleaned: String = cell.trim().chars().filter(|c| *c != '$').collect();
+    let split = cleaned.find(|c: char| c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!("Invalid cell reference: {}", cell))?;
+    let (letters, digits) = cleaned.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        anyhow::bail!("Invalid cell reference: {}", cell);
+    }
+    // Columns end at XFD (16384), as in Excel
+    let col = letters.to_ascii_uppercase().bytes()
+        .try_fold(0u32, |acc, b| acc.checked_mul(26)?.checked_add((b - b'A' + 1) as u32))
+        .filter(|col| *col <= 16_384)
+        .ok_or_else(|| anyhow::anyhow!("Invalid cell reference: {}", cell))? - 1;
+    let row: u32 = digits.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid cell reference: {}", cell))?;
+    if row == 0 {
+        anyhow::bail!("Invalid cell reference: {}", cell);
+    }
+    Ok((row - 1, col))
+}
+
+/// Parse an A1-style range ("A1:D10", or a single cell "B3") into zero-based corners
+fn parse_a1_range(range: &str) -> Result<((u32, u32), (u32, u32))> {
+    let mut parts = range.split(':');
+    let start = parse_a1_cell(parts.next().unwrap_or(""))?;
+    let end = match parts.next() {
+        Some(e) => parse_a1_cell(e)?,
+        None => start,
+    };
+    if parts.next().is_some() {
+        anyhow::bail!("Invalid range: {}", range);
+    }
+    Ok(((start.0.min(end.0), start.1.min(end.1)), (start.0.max(end.0), start.1.max(end.1))))
 }
 
 impl DocxHandler {
     fn ensure_modifiable(&self, doc_id: &str) -> Result<()> {
         if !self.in_memory_ops.contains_key(doc_id) {
-            anyhow::bail!("Modifications are supported only for documents created by this server (doc_id: {})", doc_id);
+            return Err(missing_ops(&self.documents, doc_id).into());
+        }
+        Ok(())
+    }
+
+    /// Render the document's ops to its package on disk and mark it unsaved, then spill
+    /// the ops when they exceed the in-memory limit
+    fn write_docx(&mut self, doc_id: &str) -> Result<()> {
+        self.pack_docx(doc_id)?;
+        if let Some(metadata) = self.documents.get_mut(doc_id) {
+            metadata.activity.dirty = true;
         }
+        self.in_memory_ops.spill_if_large(doc_id)?;
         Ok(())
     }
 
-    fn write_docx(&self, doc_id: &str) -> Result<()> {
+    fn pack_docx(&self, doc_id: &str) -> Result<()> {
         let metadata = self.documents.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", doc_id))?;
-        let ops = self.in_memory_ops.get(doc_id)
-            .ok_or_else(|| anyhow::anyhow!("No in-memory ops for document: {}", doc_id))?;
+            .ok_or_else(|| DocxError::NotFound(doc_id.to_string()))?;
+        let ops = self.in_memory_ops.get(doc_id)?
+            .ok_or_else(|| missing_ops(&self.documents, doc_id))?;
+        let ops: &[DocxOp] = &ops;
 
         let mut docx = Docx::new();
         let mut header_text: Option<String> = None;
         let mut footer_text: Option<String> = None;
+        if let Some((family, size)) = ops.iter().rev().find_map(|op| match op {
+            DocxOp::DefaultFont { family, size } => Some((family, size)),
+            _ => None,
+        }) {
+            if let Some(family) = family {
+                docx = docx.default_fonts(RunFonts::new().ascii(family).hi_ansi(family).east_asia(family).cs(family));
+            }
+            if let Some(size) = size {
+                docx = docx.default_size(*size);
+            }
+        }
+        // Border/shading properties per boxed paragraph, written after packing
+        let mut boxes: Vec<String> = Vec::new();
+        // Section breaks become real sections when a watermark belongs to one of them
+        let real_sections = has_section_watermarks(ops);
+        // One multilevel list shared by every heading, when heading numbering is on
+        let heading_numbering = active_heading_scheme(ops);
+        if let Some(levels) = heading_numbering {
+            let mut abstract_num = docx_rs::AbstractNumbering::new(HEADING_ABSTRACT_NUM_ID);
+            for (ilvl, (format, text)) in levels.iter().enumerate() {
+                abstract_num = abstract_num.add_level(docx_rs::Level::new(
+                    ilvl,
+                    docx_rs::Start::new(1),
+                    docx_rs::NumberFormat::new(*format),
+                    docx_rs::LevelText::new(*text),
+                    docx_rs::LevelJc::new("left"),
+                ));
+            }
+            docx = docx
+                .add_abstract_numbering(abstract_num)
+                .add_numbering(docx_rs::Numbering::new(HEADING_NUM_ID, HEADING_ABSTRACT_NUM_ID));
+        }
 
         for op in ops {
             match op {
                 DocxOp::Paragraph { text, style } => {
-                    let mut run = Run::new().add_text(text);
+                    // A leading direction mark is turned into w:bidi by the RTL pass below
+                    let text = match style.as_ref().and_then(|s| s.direction.as_deref()) {
+                        Some("rtl") => format!("{}{}", crate::bidi::RLM, text),
+                        Some("ltr") if crate::bidi::contains_rtl(text) => format!("{}{}", crate::bidi::LRM, text),
+                        _ => text.clone(),
+                    };
+                    // Line feeds (wrapped code, multi-line text) become line breaks
+                    let mut run = Run::new();
+                    for (n, line) in text.split('\n').enumerate() {
+                        if n > 0 { run = run.add_break(BreakType::TextWrapping); }
+                        run = run.add_text(line);
+                    }
+                    let mut para = Paragraph::new();
                     if let Some(st) = style {
                         if let Some(size) = st.font_size { run = run.size(size); }
                         if st.bold == Some(true) { run = run.bold(); }
                         if st.italic == Some(true) { run = run.italic(); }
-                        if st.underline == Some(true) { run = run.underline("single"); }
-                        if let Some(color) = &st.color { run = run.color(color.clone()); }
+                        match st.underline_style.as_deref().and_then(|u| canonical_name(UNDERLINE_STYLES, u)) {
+                            Some(pattern) => run = run.underline(pattern),
+                            None if st.underline == Some(true) => run = run.underline("single"),
+                            None => {}
+                        }
+                        if let Some(color) = &st.color { run = run.color(color.trim_start_matches('#')); }
+                        if let Some(name) = st.highlight.as_deref().and_then(highlight_name) { run = run.highlight(name); }
+                        if st.strike == Some(true) { run = run.strike(); }
+                        if st.double_strike == Some(true) { run = run.dstrike(); }
+                        if let Some(align) = st.vert_align.as_deref().and_then(|a| vert_align_type(a).ok()) { run.run_property = run.run_property.vert_align(align); }
+                        if st.caps == Some(true) { run.run_property = run.run_property.caps(); }
+                        if st.hidden == Some(true) { run = run.vanish(); }
+                        // docx-rs has no small caps; a marker run style is swapped for w:smallCaps below
+                        if st.small_caps == Some(true) { run = run.style(SMALL_CAPS_MARKER); }
+                        if let Some(points) = st.character_spacing { run = run.character_spacing((points * 20.0).round() as i32); }
+                        if let Some(font) = &st.font_family {
+                            run = run.fonts(RunFonts::new().ascii(font).hi_ansi(font).east_asia(font).cs(font));
+                        }
+                        if let Some(align) = st.alignment.as_deref().and_then(alignment_type) { para = para.align(align); }
+                        if let Some(spacing) = st.line_spacing {
+                            para = para.line_spacing(LineSpacing::new().line((spacing * 240.0).round() as i32));
+                        }
+                        if st.has_box() {
+                            para = para.style(&format!("{}{}", PARA_BOX_MARKER, boxes.len()));
+                            boxes.push(st.box_xml());
+                        }
                     }
-                    let para = Paragraph::new().add_run(run);
-                    docx = docx.add_paragraph(para);
+                    docx = docx.add_paragraph(para.add_run(run));
                 }
                 DocxOp::Heading { text, style } => {
-                    let para = Paragraph::new().add_run(Run::new().add_text(text)).style(style);
+                    let mut para = Paragraph::new().add_run(Run::new().add_text(text)).style(style);
+                    if heading_numbering.is_some() {
+                        let level = heading_level(style).clamp(1, 6);
+                        para = para.numbering(NumberingId::new(HEADING_NUM_ID), IndentLevel::new(level - 1));
+                    }
                     docx = docx.add_paragraph(para);
                 }
                 DocxOp::Table { data } => {
-                    let col_count = data.rows.get(0).map(|r| r.len()).unwrap_or(0);
+                    if let Some(caption) = data.caption_text() {
+                        docx = docx.add_paragraph(Paragraph::new().style(CAPTION_STYLE_ID).add_run(Run::new().add_text(caption)));
+                    }
+                    let col_count = data.rows.first().map(|r| r.len()).unwrap_or(0);
                     // Note: docx-rs Table::new takes rows, not grid. We'll add rows then (optionally) rely on defaults.
                     let mut table = Table::new(vec![]);
 
@@ -1184,7 +7168,7 @@ impl DocxHandler {
                 DocxOp::Footer(text) => { footer_text = Some(text.clone()); }
                 DocxOp::Image { data, width, height, alt_text: _ } => {
                     let run = Run::new();
-                    let pic = Pic::new_with_dimensions(data.clone(), *width, *height);
+                    let pic = Pic::new_with_dimensions(data.bytes()?.into_owned(), *width, *height);
                     let para = Paragraph::new().add_run(run.add_image(pic));
                     docx = docx.add_paragraph(para);
                 }
@@ -1194,6 +7178,10 @@ impl DocxHandler {
                     let para = Paragraph::new().add_hyperlink(link);
                     docx = docx.add_paragraph(para);
                 }
+                DocxOp::SectionBreak { .. } if real_sections => {
+                    // Marker paragraph, replaced by the section's properties with the watermarks
+                    docx = docx.add_paragraph(Paragraph::new().add_run(Run::new().add_text(SECTION_BREAK_MARKER)));
+                }
                 DocxOp::SectionBreak { .. } => {
                     // Best-effort: denote a section break with a page break
                     let para = Paragraph::new().add_run(Run::new().add_break(BreakType::Page));
@@ -1211,21 +7199,130 @@ impl DocxHandler {
                     let para = Paragraph::new().add_run(Run::new().add_text(&text));
                     docx = docx.add_paragraph(para);
                 }
+                DocxOp::Highlight { .. } | DocxOp::InvisibleText { .. } | DocxOp::EmbedFont { .. } => {
+                    // Applied to the packed XML below
+                }
+                DocxOp::HeadingNumbering { .. } => {
+                    // Set up before the loop and attached to each heading
+                }
+                DocxOp::PageSetup(_) => {
+                    // Applied to the packed XML below
+                }
+                DocxOp::HeaderLayout { .. } => {
+                    // Looked up with the header/footer text below
+                }
+                DocxOp::DefaultFont { .. } => {
+                    // Set on the document before the loop
+                }
+                DocxOp::DefaultProofing(_) => {
+                    // Applied to the packed styles below
+                }
+                DocxOp::ProofingStart(proofing) => {
+                    // Marker paragraph; the runs up to the matching end marker get the settings below
+                    let text = format!("{} {} {}", PROOFING_START_MARKER, proofing.language.as_deref().unwrap_or("-"), u8::from(proofing.no_proof));
+                    docx = docx.add_paragraph(Paragraph::new().add_run(Run::new().add_text(text)));
+                }
+                DocxOp::ProofingEnd => {
+                    docx = docx.add_paragraph(Paragraph::new().add_run(Run::new().add_text(PROOFING_END_MARKER)));
+                }
+                DocxOp::KeepTogetherStart | DocxOp::KeepTogetherEnd => {
+                    // Marker paragraph; the paragraphs in between get keepNext/keepLines below
+                    let marker = if matches!(op, DocxOp::KeepTogetherStart) { KEEP_START_MARKER } else { KEEP_END_MARKER };
+                    docx = docx.add_paragraph(Paragraph::new().add_run(Run::new().add_text(marker)));
+                }
+                DocxOp::Watermark(_) => {
+                    // Drawn in the headers below
+                }
+                DocxOp::TagStart(tag) | DocxOp::TagEnd(tag) => {
+                    // Marker paragraph, replaced by a bookmark below
+                    let marker = if matches!(op, DocxOp::TagStart(_)) { TAG_START_MARKER } else { TAG_END_MARKER };
+                    let para = Paragraph::new().add_run(Run::new().add_text(format!("{} {}", marker, tag)));
+                    docx = docx.add_paragraph(para);
+                }
             }
         }
 
+        let text_width = text_area_width(ops);
+        let layout_of = |footer: bool| ops.iter().rev().find_map(|op| match op {
+            DocxOp::HeaderLayout { footer: f, layout } if *f == footer => Some(layout),
+            _ => None,
+        });
         if let Some(h) = header_text {
-            let header = Header::new().add_paragraph(Paragraph::new().add_run(Run::new().add_text(h)));
+            let mut header = Header::new();
+            if let Some(table) = layout_of(false).and_then(|l| l.table.as_ref()) {
+                header = header.add_table(header_footer_table(table));
+            }
+            let header = header_footer_paragraphs(&h, layout_of(false), text_width).into_iter()
+                .fold(header, |header, para| header.add_paragraph(para));
             docx = docx.header(header);
         }
         if let Some(f) = footer_text {
-            let footer = Footer::new().add_paragraph(Paragraph::new().add_run(Run::new().add_text(f)));
+            let mut footer = Footer::new();
+            if let Some(table) = layout_of(true).and_then(|l| l.table.as_ref()) {
+                footer = footer.add_table(header_footer_table(table));
+            }
+            let footer = header_footer_paragraphs(&f, layout_of(true), text_width).into_iter()
+                .fold(footer, |footer, para| footer.add_paragraph(para));
             docx = docx.footer(footer);
         }
 
         let file = File::create(&metadata.path)?;
         docx.build().pack(file)?;
 
+        let marks: Vec<(String, String)> = ops.iter().filter_map(|op| match op {
+            DocxOp::Highlight { pattern, color } => Some((pattern.clone(), highlight_mark(color))),
+            DocxOp::InvisibleText { pattern } => Some((pattern.clone(), INVISIBLE_MARK.to_string())),
+            _ => None,
+        }).collect();
+        if !marks.is_empty() {
+            self.apply_highlight_xml(&metadata.path, &marks)?;
+        }
+        let fonts = ops.iter().filter_map(|op| match op {
+            DocxOp::EmbedFont { family, slot, data } => Some(data.bytes().map(|data| (family.as_str(), slot.as_str(), data))),
+            _ => None,
+        }).collect::<Result<Vec<_>>>()?;
+        if !fonts.is_empty() {
+            let fonts: Vec<(&str, &str, &[u8])> = fonts.iter().map(|(family, slot, data)| (*family, *slot, &**data)).collect();
+            write_embedded_fonts(&metadata.path, self.package_options, &fonts)?;
+        }
+        if ops.iter().any(|op| matches!(op, DocxOp::TagStart(_))) {
+            write_tag_bookmarks(&metadata.path, self.package_options)?;
+        }
+        let (header_table, footer_table) = (layout_of(false).and_then(|l| l.table.as_ref()), layout_of(true).and_then(|l| l.table.as_ref()));
+        if header_table.is_some() || footer_table.is_some() {
+            write_header_footer_tables(&metadata.path, self.package_options, header_table, footer_table, text_width)?;
+        }
+        if ops.iter().any(|op| matches!(op, DocxOp::Table { data } if data.has_row_or_cell_layout())) {
+            write_table_layout(&metadata.path, self.package_options, ops)?;
+        }
+        let default_proofing = ops.iter().rev().find_map(|op| match op { DocxOp::DefaultProofing(p) => Some(p), _ => None });
+        if default_proofing.is_some() || ops.iter().any(|op| matches!(op, DocxOp::ProofingStart(_))) {
+            write_proofing(&metadata.path, self.package_options, default_proofing)?;
+        }
+        if ops.iter().any(|op| matches!(op, DocxOp::Paragraph { style: Some(st), .. } if st.small_caps == Some(true))) {
+            write_small_caps(&metadata.path, self.package_options)?;
+        }
+        if ops.iter().any(|op| matches!(op, DocxOp::KeepTogetherStart)) {
+            write_keep_together(&metadata.path, self.package_options)?;
+        }
+        if !boxes.is_empty() {
+            write_paragraph_boxes(&metadata.path, self.package_options, &boxes)?;
+        }
+        if ops.iter().any(has_field_token) {
+            write_field_codes(&metadata.path, self.package_options, metadata)?;
+        }
+        // A section break's page setup (hi-fidelity-sections) still wins for the final section
+        if let Some(setup) = ops.iter().rev().find_map(|op| match op { DocxOp::PageSetup(setup) => Some(setup), _ => None }) {
+            write_page_setup(&metadata.path, self.package_options, setup)?;
+        }
+        if ops.iter().any(op_has_rtl) {
+            rewrite_xml_parts(&metadata.path, self.package_options, |name, xml| {
+                let is_story = name == "word/document.xml"
+                    || ((name.starts_with("word/header") || name.starts_with("word/footer")) && name.ends_with(".xml"));
+                is_story.then(|| crate::bidi::mark_rtl_paragraphs(xml))
+            })?;
+        }
+
         // Optionally post-process to inject high-fidelity XML
         #[cfg(feature = "hi-fidelity-tables")]
         {
@@ -1251,7 +7348,347 @@ impl DocxHandler {
         {
             self.apply_bookmarks_xml_properties(&metadata.path)?;
         }
-        Ok(())
+        // Last, so the header copies and section properties include every pass above
+        if ops.iter().any(|op| matches!(op, DocxOp::Watermark(_))) {
+            write_watermarks(&metadata.path, self.package_options, ops, text_width)?;
+        }
+        finish_package(&metadata.path, self.package_options)
+    }
+}
+
+/// SHA-256 of a file as lowercase hex
+pub fn file_sha256(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read as _;
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Check a file against an expected SHA-256 (hex, case-insensitive, optionally prefixed
+/// with `sha256:`). Returns the actual hash, or an error naming both on a mismatch.
+pub fn verify_file_hash(path: &Path, expected: &str) -> Result<String> {
+    let expected = expected.trim();
+    let expected = expected.strip_prefix("sha256:").unwrap_or(expected).to_ascii_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("expected_hash must be a 64-digit hex SHA-256 (got '{}')", expected);
+    }
+    let actual = file_sha256(path)?;
+    if actual != expected {
+        anyhow::bail!("Hash mismatch for {:?}: expected {}, found {}", path, expected, actual);
+    }
+    Ok(actual)
+}
+
+/// How packages are zipped. Entries are deflated at level 6 by default; `deterministic`
+/// gives every entry the same timestamp (the ZIP epoch, 1980-01-01) so identical content
+/// packs to identical bytes, for caching and diffing. Entry order already follows the
+/// order parts are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageOptions {
+    /// 0 stores entries uncompressed; 1 (fastest) to 9 (smallest) deflates them
+    pub compression_level: u32,
+    pub deterministic: bool,
+}
+
+impl Default for PackageOptions {
+    fn default() -> Self {
+        Self { compression_level: 6, deterministic: false }
+    }
+}
+
+fn zip_file_options(package: PackageOptions) -> FileOptions {
+    let options = match package.compression_level {
+        0 => FileOptions::default().compression_method(zip::CompressionMethod::Stored),
+        level => FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .compression_level(Some(level as i32)),
+    };
+    if package.deterministic {
+        options.last_modified_time(zip::DateTime::default())
+    } else {
+        options
+    }
+}
+
+/// In deterministic mode, re-zip a package docx-rs just wrote so its entries get the
+/// fixed timestamps too
+fn finish_package(docx_path: &Path, package: PackageOptions) -> Result<()> {
+    if package.deterministic {
+        rewrite_xml_parts(docx_path, package, |_, _| None)?;
+    }
+    Ok(())
+}
+
+/// Rewrite XML parts of a DOCX package in place. `edit` receives the part name and its
+/// contents and returns replacement XML for parts that should change.
+fn rewrite_xml_parts<F>(docx_path: &Path, package: PackageOptions, mut edit: F) -> Result<()>
+where
+    F: FnMut(&str, &str) -> Option<String>,
+{
+    use std::io::{Read as _, Write as _};
+    let src_file = std::fs::File::open(docx_path)?;
+    let mut archive = ZipArchive::new(src_file)?;
+    let temp_path = docx_path.with_extension("docx.tmp");
+    let dst_file = std::fs::File::create(&temp_path)?;
+    let mut writer = ZipWriter::new(dst_file);
+    let options = zip_file_options(package);
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        if file.is_dir() {
+            writer.add_directory(name, options)?;
+            continue;
+        }
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        writer.start_file(name.clone(), options)?;
+        let replaced = if name.ends_with(".xml") || name.ends_with(".rels") {
+            std::str::from_utf8(&buf).ok().and_then(|xml| edit(&name, xml))
+        } else {
+            None
+        };
+        match replaced {
+            Some(xml) => writer.write_all(xml.as_bytes())?,
+            None => writer.write_all(&buf)?,
+        }
+    }
+    writer.finish()?;
+    std::fs::rename(&temp_path, docx_path)?;
+    Ok(())
+}
+
+/// Obfuscate a font for embedding (ECMA-376 Part 1, 17.8.1): XOR the first 32 bytes with
+/// the GUID key, read as bytes in reverse order of its hex digits
+fn obfuscate_font(data: &[u8], guid: &str) -> Vec<u8> {
+    let digits: Vec<u8> = guid.chars().filter(|c| c.is_ascii_hexdigit())
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).unwrap_or(0))
+        .collect();
+    let key: Vec<u8> = digits.into_iter().rev().collect();
+    let mut out = data.to_vec();
+    for (i, b) in out.iter_mut().take(32).enumerate() {
+        *b ^= key[i % key.len()];
+    }
+    out
+}
+
+/// Add embedded fonts to a packed document: word/fonts/fontN.odttf parts, their
+/// relationships from fontTable.xml, `w:font` entries with the obfuscation keys and the
+/// odttf content type. The key is derived from the font bytes so output is stable.
+fn write_embedded_fonts(docx_path: &Path, package: PackageOptions, fonts: &[(&str, &str, &[u8])]) -> Result<()> {
+    use sha2::{Digest, Sha256};
+    const W_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+    const R_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+    const FONT_TABLE_REL: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/fontTable";
+
+    let mut parts = read_package_parts(docx_path)?;
+
+    // Group by family so each w:font lists all of its embedded styles
+    let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+    let mut rels = String::new();
+    for (i, (family, slot, data)) in fonts.iter().enumerate() {
+        let hash: String = Sha256::digest(data)[..16].iter().map(|b| format!("{:02X}", b)).collect();
+        let guid = format!("{{{}-{}-{}-{}-{}}}", &hash[..8], &hash[8..12], &hash[12..16], &hash[16..20], &hash[20..32]);
+        let rel_id = format!("rIdFont{}", i + 1);
+        set_part(&mut parts, &format!("word/fonts/font{}.odttf", i + 1), obfuscate_font(data, &guid));
+        rels.push_str(&format!(
+            r#"<Relationship Id="{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/font" Target="fonts/font{}.odttf"/>"#,
+            rel_id, i + 1
+        ));
+        let embed = format!(r#"<w:{} r:id="{}" w:fontKey="{}"/>"#, slot, rel_id, guid);
+        match entries.iter_mut().find(|(f, _)| f == family) {
+            Some((_, embeds)) => embeds.push(embed),
+            None => entries.push((family.to_string(), vec![embed])),
+        }
+    }
+    set_part(&mut parts, "word/_rels/fontTable.xml.rels", format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}</Relationships>"#,
+        rels
+    ).into_bytes());
+
+    // fontTable.xml: replace entries for the embedded families, keep the rest
+    let mut table = part_text(&parts, "word/fontTable.xml").unwrap_or_else(|| format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><w:fonts xmlns:w="{}"></w:fonts>"#, W_NS
+    ));
+    for (family, _) in &entries {
+        let re = regex::Regex::new(&format!(r#"(?s)<w:font w:name="{}"(?:/>|>.*?</w:font>)"#, regex::escape(&xml_escape(family))))?;
+        table = re.replace_all(&table, "").into_owned();
+    }
+    if !table.contains("xmlns:r=") {
+        table = table.replacen("<w:fonts ", &format!(r#"<w:fonts xmlns:r="{}" "#, R_NS), 1);
+    }
+    let added: String = entries.iter().map(|(family, embeds)| format!(
+        r#"<w:font w:name="{}"><w:charset w:val="00"/><w:family w:val="auto"/><w:pitch w:val="variable"/>{}</w:font>"#,
+        xml_escape(family), embeds.join("")
+    )).collect();
+    table = table.replacen("</w:fonts>", &format!("{}</w:fonts>", added), 1);
+    set_part(&mut parts, "word/fontTable.xml", table.into_bytes());
+
+    // Make sure the main document points at fontTable.xml
+    if let Some(doc_rels) = part_text(&parts, "word/_rels/document.xml.rels") {
+        if !doc_rels.contains(FONT_TABLE_REL) {
+            let rel = format!(r#"<Relationship Id="rIdFontTable" Type="{}" Target="fontTable.xml"/>"#, FONT_TABLE_REL);
+            set_part(&mut parts, "word/_rels/document.xml.rels", doc_rels.replacen("</Relationships>", &format!("{}</Relationships>", rel), 1).into_bytes());
+        }
+    }
+    if let Some(types) = part_text(&parts, "[Content_Types].xml") {
+        let mut types = types;
+        if !types.contains(r#"Extension="odttf""#) {
+            types = types.replacen("<Default ", r#"<Default Extension="odttf" ContentType="application/vnd.openxmlformats-officedocument.obfuscatedFont"/><Default "#, 1);
+        }
+        if !types.contains("/word/fontTable.xml") {
+            types = types.replacen("</Types>", r#"<Override PartName="/word/fontTable.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.fontTable+xml"/></Types>"#, 1);
+        }
+        set_part(&mut parts, "[Content_Types].xml", types.into_bytes());
+    }
+    // Ask Word to keep the fonts embedded when the document is saved again
+    if let Some(settings) = part_text(&parts, "word/settings.xml") {
+        if !settings.contains("w:embedTrueTypeFonts") {
+            let settings = regex::Regex::new(r"<w:settings[^>]*>")?
+                .replace(&settings, "$0<w:embedTrueTypeFonts/>")
+                .into_owned();
+            set_part(&mut parts, "word/settings.xml", settings.into_bytes());
+        }
+    }
+    write_package_parts(docx_path, package, &parts)
+}
+
+/// Every part of a DOCX package, in archive order, for passes that add parts
+fn read_package_parts(docx_path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    use std::io::Read as _;
+    let mut parts = Vec::new();
+    let mut archive = ZipArchive::new(File::open(docx_path)?)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        parts.push((file.name().to_string(), buf));
+    }
+    Ok(parts)
+}
+
+fn part_text(parts: &[(String, Vec<u8>)], name: &str) -> Option<String> {
+    parts.iter().find(|(n, _)| n == name).map(|(_, b)| String::from_utf8_lossy(b).into_owned())
+}
+
+fn set_part(parts: &mut Vec<(String, Vec<u8>)>, name: &str, data: Vec<u8>) {
+    match parts.iter_mut().find(|(n, _)| n == name) {
+        Some(part) => part.1 = data,
+        None => parts.push((name.to_string(), data)),
+    }
+}
+
+/// Replace the package with `parts`
+fn write_package_parts(docx_path: &Path, package: PackageOptions, parts: &[(String, Vec<u8>)]) -> Result<()> {
+    use std::io::Write as _;
+    let temp_path = docx_path.with_extension("docx.tmp");
+    let mut writer = ZipWriter::new(File::create(&temp_path)?);
+    let options = zip_file_options(package);
+    for (name, data) in parts {
+        writer.start_file(name.clone(), options)?;
+        writer.write_all(data)?;
+    }
+    writer.finish()?;
+    std::fs::rename(&temp_path, docx_path)?;
+    Ok(())
+}
+
+pub(crate) fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const HIGHLIGHT_NAMES: &[&str] = &[
+    "yellow", "green", "cyan", "magenta", "blue", "red", "darkBlue", "darkCyan",
+    "darkGreen", "darkMagenta", "darkRed", "darkYellow", "darkGray", "lightGray", "black",
+];
+
+/// Run properties that highlight text: a Word highlight name, or else a hex fill
+fn highlight_mark(color: &str) -> String {
+    match HIGHLIGHT_NAMES.iter().find(|n| n.eq_ignore_ascii_case(color)) {
+        Some(name) => format!(r#"<w:highlight w:val="{}"/>"#, name),
+        None => format!(r#"<w:shd w:val="clear" w:color="auto" w:fill="{}"/>"#, color.trim_start_matches('#')),
+    }
+}
+
+/// White 1pt text: still on the page for e-signature services to find, but not to readers
+const INVISIBLE_MARK: &str = r#"<w:color w:val="FFFFFF"/><w:sz w:val="2"/><w:szCs w:val="2"/>"#;
+
+impl DocxHandler {
+    /// Split simple text runs around regex matches and add the run properties paired
+    /// with each pattern to the matched pieces
+    fn apply_highlight_xml(&self, docx_path: &Path, marks: &[(String, String)]) -> Result<()> {
+        let mut compiled = Vec::new();
+        for (pattern, mark) in marks {
+            let re = regex::Regex::new(pattern).with_context(|| "Invalid regex pattern")?;
+            compiled.push((re, mark.clone()));
+        }
+        let run_re = regex::Regex::new(r"(?s)<w:r(?:\s[^>]*)?>(.*?)</w:r>")?;
+        let rpr_re = regex::Regex::new(r"(?s)^\s*(<w:rPr\s*/>|<w:rPr>(.*?)</w:rPr>)")?;
+        let t_re = regex::Regex::new(r"(?s)^\s*<w:t(?:\s[^>]*)?>(.*?)</w:t>\s*$")?;
+
+        rewrite_xml_parts(docx_path, self.package_options, |name, xml| {
+            let is_story = name == "word/document.xml"
+                || ((name.starts_with("word/header") || name.starts_with("word/footer")) && name.ends_with(".xml"));
+            if !is_story { return None; }
+            let mut changed = false;
+            let out = run_re.replace_all(xml, |caps: &regex::Captures| {
+                let whole = caps.get(0).unwrap().as_str();
+                let inner = caps.get(1).unwrap().as_str();
+                let (rpr_inner, rest) = match rpr_re.captures(inner) {
+                    Some(c) => (c.get(2).map(|m| m.as_str()).unwrap_or(""), &inner[c.get(0).unwrap().end()..]),
+                    None => ("", inner),
+                };
+                let Some(t) = t_re.captures(rest) else { return whole.to_string() };
+                let text = xml_unescape(t.get(1).unwrap().as_str());
+
+                // Collect highlighted spans; earlier rules win on overlap
+                let mut spans: Vec<(usize, usize, &str)> = Vec::new();
+                for (re, mark) in &compiled {
+                    for m in re.find_iter(&text) {
+                        if m.start() == m.end() { continue; }
+                        if spans.iter().all(|(s, e, _)| m.end() <= *s || m.start() >= *e) {
+                            spans.push((m.start(), m.end(), mark.as_str()));
+                        }
+                    }
+                }
+                if spans.is_empty() { return whole.to_string(); }
+                spans.sort_by_key(|(s, _, _)| *s);
+                changed = true;
+
+                let make_run = |piece: &str, mark: Option<&str>| {
+                    let props = match mark {
+                        Some(m) => format!("<w:rPr>{}{}</w:rPr>", rpr_inner, m),
+                        None if rpr_inner.is_empty() => String::new(),
+                        None => format!("<w:rPr>{}</w:rPr>", rpr_inner),
+                    };
+                    format!(r#"<w:r>{}<w:t xml:space="preserve">{}</w:t></w:r>"#, props, xml_escape(piece))
+                };
+                let mut pieces = String::new();
+                let mut pos = 0usize;
+                for (start, end, mark) in spans {
+                    if start > pos { pieces.push_str(&make_run(&text[pos..start], None)); }
+                    pieces.push_str(&make_run(&text[start..end], Some(mark)));
+                    pos = end;
+                }
+                if pos < text.len() { pieces.push_str(&make_run(&text[pos..], None)); }
+                pieces
+            });
+            if changed { Some(out.into_owned()) } else { None }
+        })
     }
 }
 
@@ -1319,7 +7756,7 @@ impl DocxHandler {
             let temp_path = docx_path.with_extension("docx.tmp");
             let dst_file = std::fs::File::create(&temp_path)?;
             let mut writer = ZipWriter::new(dst_file);
-            let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+            let options = zip_file_options(self.package_options);
 
             for i in 0..archive.len() {
                 let mut file = archive.by_index(i)?;
@@ -1503,7 +7940,7 @@ impl DocxHandler {
         let temp_path = docx_path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
@@ -1556,7 +7993,7 @@ impl DocxHandler {
         let temp_path = docx_path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
@@ -1613,7 +8050,7 @@ impl DocxHandler {
         let temp_path = docx_path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
@@ -1685,7 +8122,7 @@ impl DocxHandler {
         let temp_path = docx_path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
@@ -1744,10 +8181,7 @@ impl DocxHandler {
         if last_spec.is_none() { return Ok(()); }
         let (page_size, orientation, margins) = last_spec.unwrap();
 
-        let (mut w, mut h) = match page_size.as_deref() {
-            Some("Letter") => (12240i32, 15840i32), // 8.5x11 in
-            _ => (11906i32, 16838i32), // default A4 210x297mm
-        };
+        let (mut w, mut h) = page_size.as_deref().and_then(|s| page_size_twips(s).ok()).unwrap_or(A4_TWIPS);
         if orientation.as_deref() == Some("landscape") {
             std::mem::swap(&mut w, &mut h);
         }
@@ -1792,7 +8226,7 @@ impl DocxHandler {
         let temp_path = docx_path.with_extension("docx.tmp");
         let dst_file = std::fs::File::create(&temp_path)?;
         let mut writer = ZipWriter::new(dst_file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let options = zip_file_options(self.package_options);
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
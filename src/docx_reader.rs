@@ -0,0 +1,899 @@
+//! Pure-Rust reader that turns a .docx package back into the handler's op model.
+//!
+//! `document.xml` supplies the body (headings by style or outline level, list paragraphs,
+//! tables with merged cells, images, hyperlinks, page and section breaks, and Word fields
+//! as `{FIELD:...}` tokens), `styles.xml` the heading styles and default font,
+//! `numbering.xml` whether lists are numbered or bulleted, and the default header and
+//! footer their text, alignment, logo and watermark. The final section gives the page
+//! setup.
+//!
+//! Anything the op model cannot hold is counted in `Fidelity` rather than silently
+//! lost, so callers can tell a faithful read from a lossy one. It is used by
+//! `open_document` in editable mode and by `insert_clause` for .docx fragments.
+//!
+//! `read_formatted` reads the body at run level instead, keeping bold, italic,
+//! underline, strikethrough and link targets per span for `extract_formatted_text`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read as _;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::docx_handler::{CellPadding, CellStyle, DocxOp, DocxStyle, HeaderFooterCell, HeaderFooterLayout, HeaderFooterTable, ImageData, MarginsSpec, PageSetup, TableData, TableMerge, Watermark, WatermarkContent};
+
+/// What a read kept and what it had to drop
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fidelity {
+    /// Body elements read into ops
+    pub elements: usize,
+    /// Features the op model cannot hold, with how often they occur
+    pub dropped: BTreeMap<String, usize>,
+    /// Paragraphs whose runs mix formatting; they keep the first run's formatting
+    pub flattened_paragraphs: usize,
+}
+
+impl Fidelity {
+    pub fn is_lossless(&self) -> bool {
+        self.dropped.is_empty() && self.flattened_paragraphs == 0
+    }
+
+    fn record(&mut self, feature: &str, count: usize) {
+        if count > 0 {
+            *self.dropped.entry(feature.to_string()).or_default() += count;
+        }
+    }
+}
+
+/// Ops read from a package, body first, then header/footer, page setup and default font
+#[derive(Debug, Clone)]
+pub(crate) struct ReadDocument {
+    pub(crate) ops: Vec<DocxOp>,
+    pub(crate) fidelity: Fidelity,
+}
+
+/// Text with one set of formatting, as returned by `extract_formatted_text`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Span {
+    pub text: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub underline: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub strike: bool,
+    /// Hyperlink target; `#name` for links to a bookmark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// A body paragraph or table with its text as formatted spans
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormattedBlock {
+    /// paragraph, heading, list_item or table
+    pub kind: String,
+    /// Heading level (1-6) or list nesting level (0-based)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<usize>,
+    /// Numbered rather than bulleted, for list items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ordered: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<Span>,
+    /// Table cells as spans, paragraphs within a cell separated by a line break
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<Vec<Vec<Span>>>,
+}
+
+/// Body features the op model has no place for, by element local name
+const UNSUPPORTED: &[(&str, &str)] = &[
+    ("commentReference", "comments"),
+    ("footnoteReference", "footnotes"),
+    ("endnoteReference", "endnotes"),
+    ("ins", "tracked_insertions"),
+    ("del", "tracked_deletions"),
+    ("txbxContent", "text_boxes"),
+    ("oMath", "equations"),
+    ("chart", "charts"),
+    ("smartTag", "smart_tags"),
+];
+
+struct Package {
+    archive: ZipArchive<std::fs::File>,
+}
+
+impl Package {
+    fn bytes(&mut self, name: &str) -> Option<Vec<u8>> {
+        let mut file = self.archive.by_name(name).ok()?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).ok()?;
+        Some(buf)
+    }
+
+    fn text(&mut self, name: &str) -> Option<String> {
+        self.bytes(name).and_then(|b| String::from_utf8(b).ok())
+    }
+
+    /// Relationship targets of a part, resolved to package paths
+    fn relationships(&mut self, part: &str) -> Result<HashMap<String, String>> {
+        let (dir, file) = part.rsplit_once('/').unwrap_or(("", part));
+        let Some(xml) = self.text(&format!("{}/_rels/{}.rels", dir, file)) else { return Ok(HashMap::new()) };
+        Ok(relationship_targets(&xml)?.into_iter()
+            .map(|(id, target)| {
+                let path = match target.strip_prefix('/') {
+                    Some(absolute) => absolute.to_string(),
+                    None if target.contains(':') => target,
+                    None => format!("{}/{}", dir, target),
+                };
+                (id, path)
+            })
+            .collect())
+    }
+}
+
+fn w(node: roxmltree::Node, attr: &str) -> Option<String> {
+    node.attributes().find(|a| a.name() == attr).map(|a| a.value().to_string())
+}
+
+fn child<'a, 'i>(node: roxmltree::Node<'a, 'i>, name: &str) -> Option<roxmltree::Node<'a, 'i>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn named<'a, 'i>(node: roxmltree::Node<'a, 'i>, name: &'static str) -> impl Iterator<Item = roxmltree::Node<'a, 'i>> {
+    node.descendants().filter(move |n| n.tag_name().name() == name)
+}
+
+/// Read a .docx file into ops
+pub(crate) fn read_docx(path: &Path) -> Result<ReadDocument> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut package = Package { archive: ZipArchive::new(file).with_context(|| format!("{:?} is not a .docx package", path))? };
+    let document_xml = package.text("word/document.xml")
+        .ok_or_else(|| anyhow::anyhow!("No document.xml found in {:?}", path))?;
+    let styles_xml = package.text("word/styles.xml");
+    let heading_styles = match &styles_xml {
+        Some(xml) => style_outline_levels(xml)?,
+        None => HashMap::new(),
+    };
+    let list_formats = match package.text("word/numbering.xml") {
+        Some(xml) => list_formats(&xml)?,
+        None => HashMap::new(),
+    };
+    let rels = package.relationships("word/document.xml")?;
+
+    let doc = roxmltree::Document::parse(&document_xml)?;
+    let body = named(doc.root(), "body").next()
+        .ok_or_else(|| anyhow::anyhow!("No body found in {:?}", path))?;
+    let mut fidelity = Fidelity::default();
+    for &(element, feature) in UNSUPPORTED {
+        fidelity.record(feature, named(body, element).count());
+    }
+    fidelity.record("bookmarks", named(body, "bookmarkStart").filter(|n| w(*n, "name").as_deref() != Some("_GoBack")).count());
+    fidelity.record("section_breaks", named(body, "sectPr").filter(|s| s.parent().map(|p| p.tag_name().name()) == Some("pPr")).count());
+    fidelity.record("nested_tables", named(body, "tbl").filter(|t| t.ancestors().skip(1).any(|a| a.tag_name().name() == "tbl")).count());
+
+    let mut ops = Vec::new();
+    for block in blocks(body) {
+        match block.tag_name().name() {
+            "tbl" => ops.extend(read_table(block)),
+            _ => read_paragraph(block, &heading_styles, &list_formats, &rels, &mut package, &mut fidelity, &mut ops),
+        }
+    }
+    fidelity.elements = ops.len();
+    ops.extend(invisible_text(body));
+
+    // Default header and footer of the final section
+    let final_sect = child(body, "sectPr");
+    for (reference, footer) in [("headerReference", false), ("footerReference", true)] {
+        let target = final_sect
+            .and_then(|s| s.children().filter(|n| n.tag_name().name() == reference).find(|n| w(*n, "type").as_deref() != Some("first") && w(*n, "type").as_deref() != Some("even")))
+            .and_then(|n| w(n, "id"))
+            .and_then(|id| rels.get(&id).cloned());
+        if let Some(part) = target {
+            ops.extend(read_header_footer(&mut package, &part, footer, &mut fidelity)?);
+        }
+    }
+    // A watermark read from the final section's header belongs to that section alone
+    let sections = ops.iter().filter(|op| matches!(op, DocxOp::SectionBreak { .. })).count();
+    if sections > 0 {
+        for op in ops.iter_mut() {
+            if let DocxOp::Watermark(watermark) = op {
+                watermark.section = Some(sections);
+            }
+        }
+    }
+    if let Some(sect) = final_sect {
+        let mirror = package.text("word/settings.xml").is_some_and(|xml| xml.contains("<w:mirrorMargins"));
+        ops.push(DocxOp::PageSetup(page_setup(sect, mirror)));
+    }
+    if let Some(font) = styles_xml.as_deref().map(default_font).transpose()?.flatten() {
+        ops.push(font);
+    }
+    Ok(ReadDocument { ops, fidelity })
+}
+
+/// Body paragraphs and tables with run-level formatting, skipping empty paragraphs
+pub(crate) fn read_formatted(path: &Path) -> Result<Vec<FormattedBlock>> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut package = Package { archive: ZipArchive::new(file).with_context(|| format!("{:?} is not a .docx package", path))? };
+    let document_xml = package.text("word/document.xml")
+        .ok_or_else(|| anyhow::anyhow!("No document.xml found in {:?}", path))?;
+    let heading_styles = match package.text("word/styles.xml") {
+        Some(xml) => style_outline_levels(&xml)?,
+        None => HashMap::new(),
+    };
+    let list_formats = match package.text("word/numbering.xml") {
+        Some(xml) => list_formats(&xml)?,
+        None => HashMap::new(),
+    };
+    let rels = package.relationships("word/document.xml")?;
+    let doc = roxmltree::Document::parse(&document_xml)?;
+    let body = named(doc.root(), "body").next()
+        .ok_or_else(|| anyhow::anyhow!("No body found in {:?}", path))?;
+
+    let mut out = Vec::new();
+    for block in blocks(body) {
+        if block.tag_name().name() == "tbl" {
+            let rows: Vec<Vec<Vec<Span>>> = block.children().filter(|n| n.tag_name().name() == "tr")
+                .map(|tr| tr.children().filter(|n| n.tag_name().name() == "tc")
+                    .map(|tc| {
+                        let mut cell: Vec<Span> = Vec::new();
+                        for p in tc.children().filter(|n| n.tag_name().name() == "p") {
+                            if !cell.is_empty() {
+                                cell.push(Span { text: "\n".to_string(), ..Default::default() });
+                            }
+                            cell.extend(paragraph_spans(p, Some(&rels)));
+                        }
+                        cell
+                    })
+                    .collect())
+                .collect();
+            if !rows.is_empty() {
+                out.push(FormattedBlock { kind: "table".to_string(), level: None, ordered: None, spans: Vec::new(), rows });
+            }
+            continue;
+        }
+        let spans = paragraph_spans(block, Some(&rels));
+        if spans.iter().all(|s| s.text.trim().is_empty()) {
+            continue;
+        }
+        let ppr = child(block, "pPr");
+        let (kind, level, ordered) = if let Some(level) = heading_level(ppr, &heading_styles) {
+            ("heading", Some(level), None)
+        } else if let Some((level, ordered)) = list_level(ppr, &list_formats) {
+            ("list_item", Some(level), Some(ordered))
+        } else {
+            ("paragraph", None, None)
+        };
+        out.push(FormattedBlock { kind: kind.to_string(), level, ordered, spans, rows: Vec::new() });
+    }
+    Ok(out)
+}
+
+/// Markdown for formatted blocks: `#` headings, `**bold**`, `*italic*`, `~~strike~~`,
+/// `<u>underline</u>`, `[links](url)`, nested lists and pipe tables
+pub fn render_markdown(blocks: &[FormattedBlock]) -> String {
+    let mut out = String::new();
+    let mut previous: Option<&str> = None;
+    for block in blocks {
+        if previous.is_some() {
+            // Consecutive list items form one list
+            out.push_str(if previous == Some("list_item") && block.kind == "list_item" { "\n" } else { "\n\n" });
+        }
+        match block.kind.as_str() {
+            "heading" => {
+                out.push_str(&"#".repeat(block.level.unwrap_or(1)));
+                out.push(' ');
+                out.push_str(&markdown_inline(&block.spans, " "));
+            }
+            "list_item" => {
+                out.push_str(&"    ".repeat(block.level.unwrap_or(0)));
+                out.push_str(if block.ordered == Some(true) { "1. " } else { "- " });
+                out.push_str(&markdown_inline(&block.spans, "  \n"));
+            }
+            "table" => {
+                let width = block.rows.iter().map(Vec::len).max().unwrap_or(0);
+                for (r, row) in block.rows.iter().enumerate() {
+                    if r > 0 {
+                        out.push('\n');
+                    }
+                    let cells: Vec<String> = (0..width)
+                        .map(|c| row.get(c).map(|spans| markdown_inline(spans, "<br>").replace('|', "\\|")).unwrap_or_default())
+                        .collect();
+                    out.push_str(&format!("| {} |", cells.join(" | ")));
+                    if r == 0 {
+                        out.push_str(&format!("\n|{}", "---|".repeat(width)));
+                    }
+                }
+            }
+            _ => out.push_str(&markdown_inline(&block.spans, "  \n")),
+        }
+        previous = Some(&block.kind);
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '~' | '<') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Inline markdown for spans. Markers hug the text, so surrounding spaces stay outside
+/// them, and consecutive spans with the same target share one link.
+fn markdown_inline(spans: &[Span], line_break: &str) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < spans.len() {
+        let link = &spans[i].link;
+        let end = spans[i..].iter().position(|s| &s.link != link).map(|p| i + p).unwrap_or(spans.len());
+        let mut inner = String::new();
+        for span in &spans[i..end] {
+            let body = span.text.trim();
+            if body.is_empty() {
+                inner.push_str(&span.text);
+                continue;
+            }
+            let leading = &span.text[..span.text.len() - span.text.trim_start().len()];
+            let trailing = &span.text[span.text.trim_end().len()..];
+            let (mut open, mut close) = (String::new(), String::new());
+            for (on, start, stop) in [
+                (span.underline, "<u>", "</u>"),
+                (span.strike, "~~", "~~"),
+                (span.italic, "*", "*"),
+                (span.bold, "**", "**"),
+            ] {
+                if on {
+                    open.push_str(start);
+                    close.insert_str(0, stop);
+                }
+            }
+            inner.push_str(leading);
+            inner.push_str(&open);
+            inner.push_str(&escape_markdown(body));
+            inner.push_str(&close);
+            inner.push_str(trailing);
+        }
+        if let Some(url) = link {
+            inner = format!("[{}]({})", inner, url.replace(' ', "%20").replace(')', "%29"));
+        }
+        out.push_str(&inner);
+        i = end;
+    }
+    out.trim().replace('\n', line_break)
+}
+
+/// Body-level paragraphs and tables in order, looking inside content controls
+fn blocks<'a, 'i>(node: roxmltree::Node<'a, 'i>) -> Vec<roxmltree::Node<'a, 'i>> {
+    let mut out = Vec::new();
+    for n in node.children() {
+        match n.tag_name().name() {
+            "p" | "tbl" => out.push(n),
+            "sdt" => if let Some(content) = child(n, "sdtContent") { out.extend(blocks(content)) },
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Paragraph text with tabs, line breaks and fields as `{FIELD:...}` tokens. Field
+/// results, deleted text and text boxes are left out.
+fn paragraph_text(p: roxmltree::Node) -> String {
+    paragraph_spans(p, None).into_iter().map(|span| span.text).collect()
+}
+
+/// Paragraph text split wherever the formatting or link target changes. Hyperlink
+/// targets are only recorded when `rels` is given.
+fn paragraph_spans(p: roxmltree::Node, rels: Option<&HashMap<String, String>>) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut instr: Option<String> = None;
+    let mut in_result = false;
+    for n in p.descendants() {
+        let skipped = n.ancestors().skip(1).take_while(|a| *a != p)
+            .any(|a| matches!(a.tag_name().name(), "fldSimple" | "pPr" | "rPr" | "txbxContent" | "del"));
+        if skipped { continue; }
+        let text = match n.tag_name().name() {
+            "fldSimple" => w(n, "instr").map(|code| format!("{{FIELD:{}}}", code.trim())),
+            "fldChar" => match w(n, "fldCharType").as_deref() {
+                Some("begin") => { instr = Some(String::new()); in_result = false; None }
+                Some("separate") => { in_result = true; None }
+                Some("end") => {
+                    in_result = false;
+                    instr.take().map(|code| format!("{{FIELD:{}}}", code.trim()))
+                }
+                _ => None,
+            },
+            "instrText" => {
+                if let Some(code) = instr.as_mut() { code.push_str(n.text().unwrap_or("")) }
+                None
+            }
+            _ if instr.is_some() || in_result => None,
+            "t" => n.text().map(str::to_string),
+            "tab" => Some("\t".to_string()),
+            "br" if w(n, "type").as_deref() != Some("page") => Some("\n".to_string()),
+            _ => None,
+        };
+        let Some(text) = text.filter(|t| !t.is_empty()) else { continue };
+        let format = span_format(n, p, rels);
+        match spans.last_mut() {
+            Some(last) if Span { text: String::new(), ..last.clone() } == format => last.text.push_str(&text),
+            _ => spans.push(Span { text, ..format }),
+        }
+    }
+    spans
+}
+
+/// Whether an on/off property such as `w:b` is set; absent is None
+fn toggle(n: Option<roxmltree::Node>) -> Option<bool> {
+    n.map(|n| !matches!(w(n, "val").as_deref(), Some("0" | "false" | "none")))
+}
+
+/// Formatting and link target of the run holding `n`, with empty text. The character
+/// styles Strong and Emphasis count as bold and italic.
+fn span_format(n: roxmltree::Node, p: roxmltree::Node, rels: Option<&HashMap<String, String>>) -> Span {
+    let mut span = Span::default();
+    let within = || n.ancestors().take_while(move |a| *a != p);
+    if let Some(rpr) = within().find(|a| a.tag_name().name() == "r").and_then(|r| child(r, "rPr")) {
+        let style = child(rpr, "rStyle").and_then(|s| w(s, "val")).unwrap_or_default();
+        span.bold = toggle(child(rpr, "b")).unwrap_or(style == "Strong");
+        span.italic = toggle(child(rpr, "i")).unwrap_or(style == "Emphasis");
+        span.underline = toggle(child(rpr, "u")).unwrap_or(false);
+        span.strike = toggle(child(rpr, "strike")).or(toggle(child(rpr, "dstrike"))).unwrap_or(false);
+    }
+    if let (Some(rels), Some(link)) = (rels, within().find(|a| a.tag_name().name() == "hyperlink")) {
+        span.link = w(link, "id").and_then(|id| rels.get(&id).cloned())
+            .or_else(|| w(link, "anchor").map(|anchor| format!("#{}", anchor)));
+    }
+    span
+}
+
+/// Formatting of a run as comparable (element, value) pairs
+fn run_format(r: roxmltree::Node) -> Vec<(String, Option<String>)> {
+    child(r, "rPr")
+        .map(|rpr| rpr.children().filter(|n| n.is_element())
+            .map(|n| (n.tag_name().name().to_string(), w(n, "val").or_else(|| w(n, "ascii"))))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Direct formatting of the first run that has text, plus the paragraph alignment
+fn run_style(p: roxmltree::Node) -> Option<DocxStyle> {
+    let rpr = p.descendants()
+        .filter(|n| n.tag_name().name() == "r")
+        .find(|r| r.descendants().any(|t| t.tag_name().name() == "t"))
+        .and_then(|r| child(r, "rPr"));
+    let alignment = child(p, "pPr").and_then(|ppr| child(ppr, "jc")).and_then(|jc| w(jc, "val")).map(|v| match v.as_str() {
+        "both" | "distribute" => "justify".to_string(),
+        "start" => "left".to_string(),
+        "end" => "right".to_string(),
+        _ => v,
+    });
+    let style = DocxStyle {
+        bold: rpr.and_then(|r| toggle(child(r, "b"))).filter(|b| *b),
+        italic: rpr.and_then(|r| toggle(child(r, "i"))).filter(|i| *i),
+        underline: rpr.and_then(|r| toggle(child(r, "u"))).filter(|u| *u),
+        font_size: rpr.and_then(|r| child(r, "sz")).and_then(|n| w(n, "val")).and_then(|v| v.parse().ok()),
+        font_family: rpr.and_then(|r| child(r, "rFonts")).and_then(|n| w(n, "ascii")),
+        color: rpr.and_then(|r| child(r, "color")).and_then(|n| w(n, "val")).filter(|c| c != "auto"),
+        alignment,
+        ..Default::default()
+    };
+    let plain = style.bold.is_none() && style.italic.is_none() && style.underline.is_none()
+        && style.font_size.is_none() && style.font_family.is_none() && style.color.is_none() && style.alignment.is_none();
+    (!plain).then_some(style)
+}
+
+/// The first image in `node` with its data, size in pixels and alt text
+fn read_image(node: roxmltree::Node, rels: &HashMap<String, String>, package: &mut Package) -> Option<ImageData> {
+    let drawing = named(node, "drawing").find(|d| !is_watermark(*d))?;
+    let embed = named(drawing, "blip").next().and_then(|b| w(b, "embed"))?;
+    let data = package.bytes(rels.get(&embed)?)?;
+    let extent = named(drawing, "extent").next();
+    let px = |attr: &str| extent.and_then(|e| w(e, attr)).and_then(|v| v.parse::<u64>().ok()).map(|emu| (emu / 9525) as u32);
+    Some(ImageData {
+        data,
+        width: px("cx"),
+        height: px("cy"),
+        alt_text: named(drawing, "docPr").next().and_then(|n| w(n, "descr")).filter(|d| !d.is_empty()),
+    })
+}
+
+fn read_paragraph(
+    p: roxmltree::Node,
+    heading_styles: &HashMap<String, usize>,
+    list_formats: &HashMap<String, HashMap<String, String>>,
+    rels: &HashMap<String, String>,
+    package: &mut Package,
+    fidelity: &mut Fidelity,
+    ops: &mut Vec<DocxOp>,
+) {
+    let ppr = child(p, "pPr");
+    let text = paragraph_text(p);
+    let drawings = named(p, "drawing").count();
+    if drawings > 0 {
+        match read_image(p, rels, package) {
+            Some(image) => ops.push(DocxOp::Image {
+                data: image.data.into(),
+                width: image.width.unwrap_or(100),
+                height: image.height.unwrap_or(100),
+                alt_text: image.alt_text,
+            }),
+            None => fidelity.record("unreadable_images", 1),
+        }
+        fidelity.record("extra_images", drawings - 1);
+    }
+    let page_break = named(p, "br").any(|n| w(n, "type").as_deref() == Some("page"));
+    let section = ppr.and_then(|ppr| child(ppr, "sectPr"));
+    let blank = text.trim().is_empty();
+
+    if !blank {
+        if page_break {
+            fidelity.record("page_breaks_in_text", 1);
+        }
+        let links: Vec<_> = named(p, "hyperlink").collect();
+        // A paragraph that is nothing but one external link
+        let link_url = match links.as_slice() {
+            [link] if paragraph_text(*link).trim() == text.trim() => w(*link, "id").and_then(|id| rels.get(&id).cloned()),
+            _ => None,
+        };
+        if link_url.is_none() && !links.is_empty() {
+            fidelity.record("inline_hyperlinks", links.len());
+        }
+        if let Some(url) = link_url {
+            ops.push(DocxOp::Hyperlink { text: text.trim().to_string(), url });
+        } else if let Some(level) = heading_level(ppr, heading_styles) {
+            ops.push(DocxOp::Heading { text: text.trim().to_string(), style: format!("Heading{}", level) });
+        } else if let Some((level, ordered)) = list_level(ppr, list_formats) {
+            ops.push(DocxOp::ListItem { text: text.trim().to_string(), level, ordered });
+        } else {
+            let formats: Vec<_> = named(p, "r")
+                .filter(|r| named(*r, "t").next().is_some() && !is_invisible_run(*r))
+                .map(run_format)
+                .collect();
+            if formats.windows(2).any(|pair| pair[0] != pair[1]) {
+                fidelity.flattened_paragraphs += 1;
+            }
+            ops.push(DocxOp::Paragraph { text, style: run_style(p) });
+        }
+    }
+    if let Some(sect) = section {
+        let setup = page_setup(sect, false);
+        ops.push(DocxOp::SectionBreak {
+            page_size: Some(page_size_name(setup.width, setup.height)),
+            orientation: Some(if setup.landscape { "landscape" } else { "portrait" }.to_string()),
+            margins: Some(setup.margins),
+        });
+    } else if page_break && blank && drawings == 0 {
+        ops.push(DocxOp::PageBreak);
+    }
+}
+
+/// Heading level (1-6) from the paragraph's outline level or style; style ids missing from
+/// styles.xml fall back to their `Heading<n>` spelling
+fn heading_level(ppr: Option<roxmltree::Node>, heading_styles: &HashMap<String, usize>) -> Option<usize> {
+    ppr.and_then(|p| child(p, "outlineLvl")).and_then(|n| w(n, "val"))
+        .and_then(|v| v.parse::<usize>().ok()).filter(|l| *l < 9).map(|l| l + 1)
+        .or_else(|| {
+            let style = ppr.and_then(|p| child(p, "pStyle")).and_then(|n| w(n, "val"))?;
+            heading_styles.get(&style).copied().or_else(|| {
+                let lower = style.to_ascii_lowercase();
+                lower.strip_prefix("heading")?.trim().parse::<usize>().ok().filter(|l| (1..=9).contains(l))
+            })
+        })
+        .map(|level| level.clamp(1, 6))
+}
+
+/// List level (0-based) and whether the list is numbered, for list paragraphs
+fn list_level(ppr: Option<roxmltree::Node>, list_formats: &HashMap<String, HashMap<String, String>>) -> Option<(usize, bool)> {
+    let num = ppr.and_then(|p| child(p, "numPr"))?;
+    let ilvl = child(num, "ilvl").and_then(|n| w(n, "val")).unwrap_or_else(|| "0".to_string());
+    let format = child(num, "numId").and_then(|n| w(n, "val"))
+        .and_then(|id| list_formats.get(&id)).and_then(|levels| levels.get(&ilvl));
+    Some((ilvl.parse::<usize>().unwrap_or(0).min(8), format.map(|f| f != "bullet").unwrap_or(false)))
+}
+
+/// A table with its grid widths and merged cells. Spanned grid positions hold empty
+/// text, as the table ops expect.
+fn read_table(tbl: roxmltree::Node) -> Option<DocxOp> {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut merges: Vec<TableMerge> = Vec::new();
+    let mut cell_styles: Vec<CellStyle> = Vec::new();
+    let (mut header_row, mut cant_split) = (false, true);
+    for (r, tr) in tbl.children().filter(|n| n.tag_name().name() == "tr").enumerate() {
+        let trpr = child(tr, "trPr");
+        header_row |= r == 0 && toggle(trpr.and_then(|p| child(p, "tblHeader"))) == Some(true);
+        cant_split &= toggle(trpr.and_then(|p| child(p, "cantSplit"))) == Some(true);
+        let mut row = Vec::new();
+        for tc in tr.children().filter(|n| n.tag_name().name() == "tc") {
+            let col = row.len();
+            let tcpr = child(tc, "tcPr");
+            let span = tcpr.and_then(|p| child(p, "gridSpan")).and_then(|n| w(n, "val"))
+                .and_then(|v| v.parse::<usize>().ok()).unwrap_or(1).max(1);
+            let vmerge = tcpr.and_then(|p| child(p, "vMerge")).map(|n| w(n, "val").unwrap_or_default());
+            if let Some(style) = tcpr.and_then(|p| read_cell_style(p, r, col)) {
+                cell_styles.push(style);
+            }
+            let text = tc.children().filter(|n| n.tag_name().name() == "p").map(paragraph_text).collect::<Vec<_>>().join("\n");
+            match vmerge.as_deref() {
+                Some("restart") => merges.push(TableMerge { row: r, col, row_span: 1, col_span: span }),
+                Some(_) => {
+                    // Continuation of the merge that starts above in this column
+                    if let Some(m) = merges.iter_mut().rev().find(|m| m.col == col && m.row + m.row_span == r) {
+                        m.row_span += 1;
+                    }
+                }
+                None if span > 1 => merges.push(TableMerge { row: r, col, row_span: 1, col_span: span }),
+                None => {}
+            }
+            row.push(if matches!(vmerge.as_deref(), Some(v) if v != "restart") { String::new() } else { text });
+            row.extend(std::iter::repeat_n(String::new(), span - 1));
+        }
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return None;
+    }
+    let col_widths: Vec<u32> = child(tbl, "tblGrid")
+        .map(|grid| grid.children().filter(|n| n.tag_name().name() == "gridCol")
+            .filter_map(|c| w(c, "w")?.parse::<u32>().ok())
+            .map(|twips| twips / 15)
+            .collect())
+        .unwrap_or_default();
+    let tblpr = child(tbl, "tblPr");
+    let border_style = named(tbl, "tblBorders").next()
+        .and_then(|b| b.children().find(|n| n.is_element()))
+        .and_then(|n| w(n, "val"))
+        .or_else(|| Some("single".to_string()));
+    Some(DocxOp::Table { data: TableData {
+        rows,
+        headers: None,
+        border_style,
+        col_widths: (!col_widths.is_empty()).then_some(col_widths),
+        merges: (!merges.is_empty()).then_some(merges),
+        cell_shading: None,
+        cell_styles: (!cell_styles.is_empty()).then_some(cell_styles),
+        repeat_header_row: header_row.then_some(true),
+        cant_split_rows: cant_split.then_some(true),
+        table_layout: tblpr.and_then(|p| child(p, "tblLayout")).and_then(|n| w(n, "type")),
+        width_percent: tblpr.and_then(|p| child(p, "tblW"))
+            .filter(|n| w(*n, "type").as_deref() == Some("pct"))
+            .and_then(|n| w(n, "w"))
+            .and_then(|v| v.trim_end_matches('%').parse::<f32>().ok().map(|n| if v.ends_with('%') { n } else { n / 50.0 })),
+        ..Default::default()
+    } })
+}
+
+/// Vertical alignment, padding and text direction of one cell, if it sets any
+fn read_cell_style(tcpr: roxmltree::Node, row: usize, col: usize) -> Option<CellStyle> {
+    let vertical_align = child(tcpr, "vAlign").and_then(|n| w(n, "val"))
+        .filter(|v| matches!(v.as_str(), "top" | "center" | "bottom"));
+    let text_direction = child(tcpr, "textDirection").and_then(|n| w(n, "val"))
+        .filter(|v| matches!(v.as_str(), "btLr" | "tbRl"));
+    let padding = child(tcpr, "tcMar").map(|mar| {
+        let side = |names: &[&str]| names.iter()
+            .find_map(|name| mar.children().find(|n| n.tag_name().name() == *name))
+            .and_then(|n| w(n, "w")?.parse::<f32>().ok())
+            .map(|twips| twips / 20.0);
+        CellPadding { top: side(&["top"]), bottom: side(&["bottom"]), left: side(&["left", "start"]), right: side(&["right", "end"]) }
+    }).filter(|p| *p != CellPadding::default());
+    if vertical_align.is_none() && text_direction.is_none() && padding.is_none() {
+        return None;
+    }
+    Some(CellStyle { row: Some(row), col: Some(col), vertical_align, padding, text_direction })
+}
+
+/// Header or footer text (one line per paragraph), alignment and logo, plus a leading
+/// one-row table such as logo | title | date
+fn read_header_footer(package: &mut Package, part: &str, footer: bool, fidelity: &mut Fidelity) -> Result<Vec<DocxOp>> {
+    let Some(xml) = package.text(part) else { return Ok(Vec::new()) };
+    let doc = roxmltree::Document::parse(&xml)?;
+    let rels = package.relationships(part)?;
+    let tables: Vec<_> = named(doc.root(), "tbl").collect();
+    let table = match tables.as_slice() {
+        [tbl] if named(*tbl, "tr").count() == 1 && named(*tbl, "tbl").count() == 0 => Some(read_header_footer_table(*tbl, &rels, package)),
+        _ => None,
+    };
+    if table.is_none() {
+        fidelity.record(if footer { "footer_tables" } else { "header_tables" }, tables.len());
+    }
+    let paragraphs: Vec<_> = named(doc.root(), "p")
+        .filter(|p| !p.ancestors().any(|a| a.tag_name().name() == "tbl"))
+        .collect();
+    let text = paragraphs.iter().map(|p| paragraph_text(*p)).collect::<Vec<_>>().join("\n");
+    let logo = paragraphs.iter().find_map(|p| read_image(*p, &rels, package));
+    let alignment = paragraphs.first()
+        .and_then(|p| child(*p, "pPr")).and_then(|ppr| child(ppr, "jc")).and_then(|jc| w(jc, "val"))
+        .filter(|v| matches!(v.as_str(), "center" | "right" | "end" | "both"))
+        .map(|v| if v == "end" { "right".to_string() } else { v });
+    let mut ops = vec![if footer { DocxOp::Footer(text) } else { DocxOp::Header(text) }];
+    if logo.is_some() || alignment.is_some() || table.is_some() {
+        ops.push(DocxOp::HeaderLayout { footer, layout: HeaderFooterLayout { alignment, logo, logo_position: None, table } });
+    }
+    if let Some(content) = read_watermark(doc.root(), &rels, package) {
+        ops.push(DocxOp::Watermark(Watermark { content, section: None }));
+    }
+    ops.extend(invisible_text(doc.root()));
+    Ok(ops)
+}
+
+/// Runs in white 1pt text, the way e-signature anchor tags are hidden
+fn is_invisible_run(r: roxmltree::Node) -> bool {
+    let Some(rpr) = child(r, "rPr") else { return false };
+    child(rpr, "color").and_then(|c| w(c, "val")).is_some_and(|v| v.eq_ignore_ascii_case("FFFFFF"))
+        && child(rpr, "sz").and_then(|s| w(s, "val")).as_deref() == Some("2")
+}
+
+/// One `InvisibleText` op for the texts of the invisible runs under `node`
+fn invisible_text(node: roxmltree::Node) -> Option<DocxOp> {
+    let mut texts: Vec<String> = Vec::new();
+    for r in named(node, "r").filter(|r| is_invisible_run(*r)) {
+        let text: String = named(r, "t").filter_map(|t| t.text()).collect();
+        if !text.trim().is_empty() && !texts.contains(&text) {
+            texts.push(text);
+        }
+    }
+    (!texts.is_empty()).then(|| DocxOp::InvisibleText {
+        pattern: texts.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|"),
+    })
+}
+
+/// Pictures that `add_watermark` anchors behind the text
+fn is_watermark(drawing: roxmltree::Node) -> bool {
+    named(drawing, "docPr").next().and_then(|n| w(n, "name")).is_some_and(|name| name.starts_with("Watermark"))
+}
+
+/// A WordArt text watermark, or a picture one as written by `add_watermark`
+fn read_watermark(root: roxmltree::Node, rels: &HashMap<String, String>, package: &mut Package) -> Option<WatermarkContent> {
+    if let Some(path) = named(root, "textpath").find(|t| w(*t, "string").is_some()) {
+        let shape = path.parent()?;
+        let style = w(shape, "style").unwrap_or_default();
+        let font = w(path, "style").and_then(|s| s.split(';')
+            .find_map(|decl| decl.trim().strip_prefix("font-family:").map(|f| f.trim().trim_matches('"').to_string())));
+        return Some(WatermarkContent::Text {
+            text: w(path, "string")?,
+            font,
+            color: w(shape, "fillcolor")
+                .map(|c| c.trim_start_matches('#').to_string())
+                .filter(|c| c.len() == 6 && c.chars().all(|ch| ch.is_ascii_hexdigit())),
+            opacity: child(shape, "fill").and_then(|f| w(f, "opacity")).and_then(|o| o.parse().ok()),
+            diagonal: style.split(';').any(|decl| decl.trim().strip_prefix("rotation:").is_some_and(|r| r.trim() != "0")),
+        });
+    }
+    let drawing = named(root, "drawing").find(|d| is_watermark(*d))?;
+    let blip = named(drawing, "blip").next()?;
+    let embed = named(blip, "svgBlip").next().and_then(|s| w(s, "embed")).or_else(|| w(blip, "embed"))?;
+    let cx = named(drawing, "extent").next().and_then(|e| w(e, "cx")).and_then(|v| v.parse::<u64>().ok());
+    Some(WatermarkContent::Image {
+        data: package.bytes(rels.get(&embed)?)?,
+        width: cx.map(|emu| emu as f32 / 914_400.0),
+        washout: child(blip, "lum").is_some(),
+    })
+}
+
+/// Cells of a one-row header/footer table, with the grid as relative widths
+fn read_header_footer_table(tbl: roxmltree::Node, rels: &HashMap<String, String>, package: &mut Package) -> HeaderFooterTable {
+    let cells = named(tbl, "tc").map(|tc| {
+        let paragraphs: Vec<_> = tc.children().filter(|n| n.tag_name().name() == "p").collect();
+        HeaderFooterCell {
+            text: paragraphs.iter().map(|p| paragraph_text(*p)).collect::<Vec<_>>().join("\n"),
+            alignment: paragraphs.first()
+                .and_then(|p| child(*p, "pPr")).and_then(|ppr| child(ppr, "jc")).and_then(|jc| w(jc, "val"))
+                .map(|v| match v.as_str() { "end" => "right".to_string(), "start" => "left".to_string(), "both" => "justify".to_string(), _ => v }),
+            image: read_image(tc, rels, package),
+        }
+    }).collect::<Vec<_>>();
+    let col_widths: Vec<u32> = child(tbl, "tblGrid")
+        .map(|grid| grid.children().filter(|n| n.tag_name().name() == "gridCol")
+            .filter_map(|c| w(c, "w")?.parse::<u32>().ok())
+            .collect())
+        .unwrap_or_default();
+    let borders = named(tbl, "tblBorders").next()
+        .is_some_and(|b| b.children().any(|n| n.is_element() && !matches!(w(n, "val").as_deref(), Some("nil" | "none"))));
+    HeaderFooterTable {
+        col_widths: (col_widths.len() == cells.len() && col_widths.iter().all(|w| *w > 0)).then_some(col_widths),
+        cells,
+        borders,
+    }
+}
+
+/// Page setup from a `w:sectPr`; margins and gutter in inches
+fn page_setup(sect: roxmltree::Node, mirror: bool) -> PageSetup {
+    let mut setup = PageSetup { mirror, ..Default::default() };
+    if let Some(size) = child(sect, "pgSz") {
+        let twips = |attr: &str| w(size, attr).and_then(|v| v.parse::<u32>().ok());
+        let (width, height) = (twips("w").unwrap_or(setup.width), twips("h").unwrap_or(setup.height));
+        setup.landscape = w(size, "orient").as_deref() == Some("landscape") || width > height;
+        (setup.width, setup.height) = if setup.landscape { (height, width) } else { (width, height) };
+    }
+    if let Some(margins) = child(sect, "pgMar") {
+        let inches = |attr: &str| w(margins, attr).and_then(|v| v.parse::<i64>().ok()).map(|t| t.max(0) as f32 / 1440.0);
+        setup.margins = MarginsSpec { top: inches("top"), bottom: inches("bottom"), left: inches("left"), right: inches("right") };
+        setup.gutter = inches("gutter").filter(|g| *g > 0.0);
+    }
+    setup
+}
+
+/// Preset name for portrait twips, or the size in millimetres
+fn page_size_name(width: u32, height: u32) -> String {
+    crate::docx_handler::PAGE_SIZE_PRESETS.iter()
+        .find(|(_, size)| *size == (width, height))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("{:.2}x{:.2}mm", width as f64 * 25.4 / 1440.0, height as f64 * 25.4 / 1440.0))
+}
+
+/// Default font from the style sheet's run defaults
+fn default_font(styles_xml: &str) -> Result<Option<DocxOp>> {
+    let doc = roxmltree::Document::parse(styles_xml)?;
+    let Some(rpr) = named(doc.root(), "rPrDefault").next() else { return Ok(None) };
+    let family = named(rpr, "rFonts").next().and_then(|n| w(n, "ascii"));
+    let size = named(rpr, "sz").next().and_then(|n| w(n, "val")).and_then(|v| v.parse::<usize>().ok());
+    Ok((family.is_some() || size.is_some()).then_some(DocxOp::DefaultFont { family, size }))
+}
+
+/// numId -> ilvl -> numFmt, to tell numbered lists from bullets
+fn list_formats(numbering_xml: &str) -> Result<HashMap<String, HashMap<String, String>>> {
+    let doc = roxmltree::Document::parse(numbering_xml)?;
+    let abstract_levels: HashMap<String, HashMap<String, String>> = named(doc.root(), "abstractNum")
+        .filter_map(|a| Some((w(a, "abstractNumId")?, a.children()
+            .filter(|l| l.tag_name().name() == "lvl")
+            .filter_map(|l| Some((w(l, "ilvl")?, w(child(l, "numFmt")?, "val")?)))
+            .collect())))
+        .collect();
+    Ok(named(doc.root(), "num")
+        .filter_map(|num| {
+            let abstract_id = child(num, "abstractNumId").and_then(|n| w(n, "val"))?;
+            Some((w(num, "numId")?, abstract_levels.get(&abstract_id)?.clone()))
+        })
+        .collect())
+}
+
+/// Heading level per paragraph style id, from the style name ("heading 2", "Title") or
+/// an outline level in the style's paragraph properties
+pub(crate) fn style_outline_levels(styles_xml: &str) -> Result<HashMap<String, usize>> {
+    let doc = roxmltree::Document::parse(styles_xml)?;
+    let attr = |n: roxmltree::Node, name: &str| n.attributes().find(|a| a.name() == name).map(|a| a.value().to_string());
+    let mut levels = HashMap::new();
+    for style in doc.descendants().filter(|n| n.tag_name().name() == "style") {
+        let Some(id) = attr(style, "styleId") else { continue };
+        let name = style.children().find(|n| n.tag_name().name() == "name")
+            .and_then(|n| attr(n, "val"))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let outline = style.descendants().find(|n| n.tag_name().name() == "outlineLvl")
+            .and_then(|n| attr(n, "val")).and_then(|v| v.parse::<usize>().ok())
+            .filter(|&l| l < 9);
+        let level = match name.strip_prefix("heading").map(str::trim) {
+            Some(n) => n.parse::<usize>().ok(),
+            None if name == "title" => Some(1),
+            None => outline.map(|l| l + 1),
+        };
+        if let Some(level) = level {
+            levels.insert(id, level);
+        }
+    }
+    // Packages without a styles part still use the built-in ids
+    for n in 1..=9 {
+        levels.entry(format!("Heading{}", n)).or_insert(n);
+    }
+    levels.entry("Title".to_string()).or_insert(1);
+    Ok(levels)
+}
+
+/// Relationship id to target, from a .rels part
+pub(crate) fn relationship_targets(rels_xml: &str) -> Result<HashMap<String, String>> {
+    let doc = roxmltree::Document::parse(rels_xml)?;
+    Ok(doc.descendants()
+        .filter(|n| n.tag_name().name() == "Relationship")
+        .filter_map(|n| Some((n.attribute("Id")?.to_string(), n.attribute("Target")?.to_string())))
+        .collect())
+}
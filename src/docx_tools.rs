@@ -1,25 +1,83 @@
-use mcp_core::types::{Tool, CallToolResponse, ToolResponseContent, TextContent};
+use mcp_core::types::{Tool, CallToolResponse};
 // Adapt to latest MCP: we'll integrate via mcp-server Router separately
+use once_cell::sync::Lazy;
+use base64::Engine as _;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
-use tracing::{debug, info};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{debug, info, Instrument};
 
-use crate::docx_handler::{DocxHandler, DocxStyle, TableData};
+use crate::docx_handler::{DocumentFilter, DocxHandler, DocxStyle, HeaderFooterLayout, StyleMap, TableData};
 use crate::converter::DocumentConverter;
-use crate::response::{ToolOutcome, ErrorCode};
+use crate::pure_converter::{PdfOptions, PureRustConverter};
+use crate::response::{ToolOutcome, ErrorCode, ResponseMode};
+use crate::locks::LockTable;
+use crate::notify::Notifier;
+use crate::clauses::ClauseLibrary;
+use crate::letterhead::LetterheadProfiles;
+use crate::object_store::ObjectUri;
+use crate::metrics::{Gauges, Metrics};
 #[cfg(feature = "advanced-docx")]
 use crate::advanced_docx::AdvancedDocxHandler;
 use crate::security::{SecurityConfig, SecurityMiddleware};
 
+/// Tools that reach across clients or act on the whole server; scoped clients need
+/// `admin` to call them
+const OPERATOR_TOOLS: &[&str] = &["list_all_documents", "transfer_document", "reload_config", "run_benchmark"];
+
+/// Read-only tools a viewer still may not call: they would add documents to the
+/// session it mirrors, read files outside it or write files to an output path
+const VIEWER_BLOCKED: &[&str] = &[
+    "open_document", "verify_document",
+    "export_to_markdown", "export_to_html", "export_ops", "export_xliff", "generate_thumbnail", "create_preview",
+];
+
+/// Whether a call changes the document it names: any tool outside the read-only list
+/// unless it only previews (`dry_run`), and read-only tools in a writing mode
+/// (`lint_formatting` with `auto_fix`)
+fn is_write_call(name: &str, arguments: &Value) -> bool {
+    !SecurityConfig::is_readonly_call(name, arguments)
+        || (name == "lint_formatting" && arguments.get("auto_fix").and_then(|v| v.as_bool()).unwrap_or(false))
+}
+
+/// Who a provider acts for on a shared server (see `DocxToolsProvider::for_client`)
+#[derive(Debug, Clone)]
+pub struct ClientScope {
+    /// Session or client id; documents this client creates or opens belong to it
+    pub id: String,
+    /// Operators may use every client's documents and the operator tools
+    pub admin: bool,
+    /// Checks on top of the server's own, e.g. from the client's API token profile
+    pub restrictions: Option<Arc<SecurityMiddleware>>,
+}
+
 #[derive(Clone)]
 pub struct DocxToolsProvider {
     handler: Arc<RwLock<DocxHandler>>,
     converter: Arc<DocumentConverter>,
     #[cfg(feature = "advanced-docx")]
     advanced: Arc<AdvancedDocxHandler>,
-    security: Arc<SecurityMiddleware>,
-    security_config: SecurityConfig,
+    /// Behind a lock so `reload_config` can swap it while clones of the provider run
+    security: Arc<RwLock<SecurityMiddleware>>,
+    /// Re-reads the settings for `reload_config`; None when started without a config file
+    reload: Option<Arc<dyn Fn() -> anyhow::Result<crate::security::Args> + Send + Sync>>,
+    metrics: Arc<Metrics>,
+    response_mode: ResponseMode,
+    locks: Arc<Mutex<LockTable>>,
+    notifier: Arc<Notifier>,
+    clauses: Option<Arc<ClauseLibrary>>,
+    letterheads: Option<Arc<LetterheadProfiles>>,
+    /// Set for one client of a shared server; None acts for the local operator
+    client: Option<ClientScope>,
+    /// Viewer keys and the client whose documents each one shows
+    viewers: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Default for DocxToolsProvider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DocxToolsProvider {
@@ -33,8 +91,16 @@ impl DocxToolsProvider {
             converter: Arc::new(DocumentConverter::new()),
             #[cfg(feature = "advanced-docx")]
             advanced: Arc::new(AdvancedDocxHandler::new()),
-            security: Arc::new(SecurityMiddleware::new(security_config.clone())),
-            security_config,
+            security: Arc::new(RwLock::new(SecurityMiddleware::new(security_config))),
+            reload: None,
+            metrics: Arc::new(Metrics::new()),
+            response_mode: ResponseMode::default(),
+            locks: Arc::new(Mutex::new(LockTable::new())),
+            notifier: Arc::new(Notifier::default()),
+            clauses: None,
+            letterheads: None,
+            client: None,
+            viewers: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -50,25 +116,344 @@ impl DocxToolsProvider {
             converter: Arc::new(DocumentConverter::new()),
             #[cfg(feature = "advanced-docx")]
             advanced: Arc::new(AdvancedDocxHandler::new()),
-            security: Arc::new(SecurityMiddleware::new(security_config.clone())),
-            security_config,
+            security: Arc::new(RwLock::new(SecurityMiddleware::new(security_config))),
+            reload: None,
+            metrics: Arc::new(Metrics::new()),
+            response_mode: ResponseMode::default(),
+            locks: Arc::new(Mutex::new(LockTable::new())),
+            notifier: Arc::new(Notifier::default()),
+            clauses: None,
+            letterheads: None,
+            client: None,
+            viewers: Arc::new(Mutex::new(HashMap::new())),
         }
     }
+
+    /// Choose between structured responses (default) and the legacy text-only format
+    pub fn with_response_mode(mut self, mode: ResponseMode) -> Self {
+        self.response_mode = mode;
+        self
+    }
+
+    /// Send document events to webhooks; delivery is off when network access is disabled
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Arc::new(notifier.with_network(self.security_config().allow_network));
+        self
+    }
+
+    /// Serve clause fragments from this directory (see `clauses`)
+    pub fn with_clause_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.clauses = dir.map(|d| Arc::new(ClauseLibrary::new(d)));
+        self
+    }
+
+    /// Spill a document's ops to disk once they exceed this many bytes (see `spill`)
+    pub fn with_max_document_memory(self, max_bytes: Option<usize>) -> Self {
+        self.handler.write().unwrap().set_max_document_memory(max_bytes);
+        self
+    }
+
+    /// Zip packages with these settings (compression level, deterministic timestamps)
+    pub fn with_package_options(self, options: crate::docx_handler::PackageOptions) -> anyhow::Result<Self> {
+        self.handler.write().unwrap().set_package_options(options)?;
+        Ok(self)
+    }
+
+    /// Read letterhead profiles from this TOML file (see `letterhead`)
+    pub fn with_letterheads(mut self, path: Option<PathBuf>) -> Self {
+        self.letterheads = path.map(|p| Arc::new(LetterheadProfiles::new(p)));
+        self
+    }
+
+    /// Try LibreOffice/unoconv before the built-in renderer for PDF conversion
+    pub fn with_prefer_external_tools(mut self, prefer: bool) -> Self {
+        self.converter = Arc::new(DocumentConverter::new().with_external_preference(prefer));
+        self
+    }
+
+    /// Let `reload_config` (and SIGHUP) re-read the settings with `reload`, which parses
+    /// the command line, environment and config file again
+    pub fn with_config_reload(mut self, reload: impl Fn() -> anyhow::Result<crate::security::Args> + Send + Sync + 'static) -> Self {
+        self.reload = Some(Arc::new(reload));
+        self
+    }
+
+    /// Call counters shared by this provider and its clones
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// The `/metrics` page: the counters plus open documents and temp directory size
+    pub fn render_metrics(&self) -> String {
+        let handler = self.handler.read().unwrap();
+        let temp_dir_bytes = handler.get_storage_info()
+            .map(|info| info["storage"]["total_bytes"].as_u64().unwrap_or(0))
+            .unwrap_or(0);
+        self.metrics.render(Gauges { open_documents: handler.list_documents().len(), temp_dir_bytes })
+    }
+
+    /// The security settings in force
+    pub fn security_config(&self) -> SecurityConfig {
+        self.security.read().unwrap().config().clone()
+    }
+
+    /// Whether this provider may not change documents: the server runs read-only, or
+    /// the client's restrictions (a readonly token profile, a viewer key) say so
+    fn is_readonly(&self) -> bool {
+        self.security_config().readonly_mode
+            || self.client.as_ref()
+                .and_then(|scope| scope.restrictions.as_ref())
+                .is_some_and(|restrictions| restrictions.config().readonly_mode)
+    }
+
+    /// Re-read the settings and apply the security ones (profile, command lists,
+    /// allowed paths, limits) along with the per-document memory limit and package
+    /// options. Open documents stay open; the temp dir, fonts, webhooks and content
+    /// directories keep their startup values until a restart. Returns the settings
+    /// before and after.
+    pub fn reload_config(&self) -> anyhow::Result<Value> {
+        let reload = self.reload.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("The server was started without a config file (--config), so there is nothing to reload"))?;
+        let args = reload()?;
+        let package_options = crate::docx_handler::PackageOptions {
+            compression_level: args.compression_level,
+            deterministic: args.deterministic,
+        };
+        let max_document_memory = args.max_document_memory;
+        let config = SecurityConfig::from_args(args);
+        let before = self.security_config().get_summary();
+        let after = config.get_summary();
+        let mut handler = self.handler.write().unwrap();
+        handler.set_package_options(package_options)?;
+        handler.set_max_document_memory(max_document_memory);
+        drop(handler);
+        *self.security.write().unwrap() = SecurityMiddleware::new(config);
+        info!("Reloaded configuration: {}", after);
+        Ok(json!({"before": before, "after": after}))
+    }
+}
+
+fn no_clause_library() -> ToolOutcome {
+    ToolOutcome::Error {
+        code: ErrorCode::InvalidArgument,
+        error: "No clause library is configured".into(),
+        hint: Some("Start the server with --clause-dir (or DOCX_MCP_CLAUSE_DIR) pointing at a directory of .docx/.md fragments".into()),
+    }
+}
+
+fn no_letterheads() -> ToolOutcome {
+    ToolOutcome::Error {
+        code: ErrorCode::InvalidArgument,
+        error: "No letterhead profiles are configured".into(),
+        hint: Some("Start the server with --letterheads (or DOCX_MCP_LETTERHEADS) pointing at a TOML file of profiles".into()),
+    }
 }
 
+/// Input schemas by tool name, used to validate arguments before dispatch
+static TOOL_SCHEMAS: Lazy<HashMap<String, Value>> = Lazy::new(|| {
+    DocxToolsProvider::tool_definitions()
+        .into_iter()
+        .map(|tool| (tool.name, tool.input_schema))
+        .collect()
+});
+
 impl DocxToolsProvider {
     pub async fn list_tools(&self) -> Vec<Tool> {
-        let mut all_tools = vec![
+        let mut all_tools = Self::tool_definitions();
+
+        // Filter tools based on security configuration
+        let security = self.security.read().unwrap();
+        let restrictions = self.client.as_ref().and_then(|scope| scope.restrictions.as_ref());
+        all_tools.retain(|tool| {
+            security.config().is_command_allowed(&tool.name)
+                && restrictions.map(|r| r.config().is_command_allowed(&tool.name)).unwrap_or(true)
+        });
+        
+        info!("Exposing {} tools (security filtered)", all_tools.len());
+        all_tools
+    }
+
+    /// A view of this provider for one client of a shared server. Documents the client
+    /// creates or opens belong to it, and other clients' documents look like they do
+    /// not exist, unless the scope is `admin`. Clones share documents and settings.
+    pub fn for_client(&self, scope: ClientScope) -> Self {
+        let mut provider = self.clone();
+        provider.client = Some(scope);
+        provider
+    }
+
+    /// A read-only view of the client a viewer key was created for (see `create_viewer`),
+    /// or None for an unknown or revoked key. It sees that client's documents as they
+    /// are edited, but cannot change them, open new ones or touch files.
+    pub fn viewer(&self, viewer_key: &str) -> Option<Self> {
+        let client = self.viewers.lock().unwrap().get(viewer_key).cloned()?;
+        let restrictions = SecurityConfig {
+            readonly_mode: true,
+            sandbox_mode: true,
+            command_whitelist: None,
+            command_blacklist: Some(VIEWER_BLOCKED.iter().map(|t| t.to_string()).collect()),
+            // Limits stay with the server settings
+            max_document_size: usize::MAX,
+            max_open_documents: usize::MAX,
+            allow_external_tools: false,
+            allow_network: false,
+            allowed_paths: Vec::new(),
+        };
+        Some(self.for_client(ClientScope {
+            id: client,
+            admin: false,
+            restrictions: Some(Arc::new(SecurityMiddleware::new(restrictions))),
+        }))
+    }
+
+    /// Revoke every viewer key of a client, e.g. when its session ends. Returns how many.
+    pub fn revoke_viewers(&self, client: &str) -> usize {
+        let mut viewers = self.viewers.lock().unwrap();
+        let before = viewers.len();
+        viewers.retain(|_, owner| owner != client);
+        before - viewers.len()
+    }
+
+    /// Close every document a client owns, e.g. when its session ends. Returns how many.
+    pub fn close_client_documents(&self, client: &str) -> usize {
+        let mut handler = self.handler.write().unwrap();
+        handler.documents_owned_by(client).iter()
+            .filter(|id| handler.close_document(id).is_ok())
+            .count()
+    }
+
+    /// Whether this provider's client may use a document (true for unknown ids, so the
+    /// tool reports them as missing itself)
+    fn can_access(&self, handler: &DocxHandler, doc_id: &str) -> bool {
+        match (&self.client, handler.document_owner(doc_id)) {
+            (Some(scope), Some(owner)) => scope.admin || owner == Some(scope.id.as_str()),
+            _ => true,
+        }
+    }
+
+    /// For a scoped client: refuse operator tools and other clients' documents
+    fn check_client_access(&self, name: &str, arguments: &Value) -> Option<ToolOutcome> {
+        let scope = self.client.as_ref().filter(|scope| !scope.admin)?;
+        if OPERATOR_TOOLS.contains(&name) {
+            return Some(ToolOutcome::Error {
+                code: ErrorCode::SecurityBlocked,
+                error: format!("{} is only available to operators", name),
+                hint: None,
+            });
+        }
+        let handler = self.handler.read().unwrap();
+        referenced_documents(arguments).into_iter()
+            .find(|id| !self.can_access(&handler, id))
+            .map(|id| {
+                debug!("Client {} denied document {}", scope.id, id);
+                ToolOutcome::Error { code: ErrorCode::DocNotFound, error: format!("Document not found: {}", id), hint: None }
+            })
+    }
+
+    /// Give this provider's client the unowned documents a successful call returned
+    fn claim_documents(&self, response: &CallToolResponse) {
+        let Some(scope) = &self.client else { return };
+        let Some(body) = response_body(response) else { return };
+        let ids = body.get("document_id").into_iter()
+            .chain(body.get("document_ids").and_then(|v| v.as_array()).into_iter().flatten())
+            .filter_map(|v| v.as_str());
+        let mut handler = self.handler.write().unwrap();
+        for id in ids {
+            if handler.document_owner(id) == Some(None) {
+                let _ = handler.set_document_owner(id, Some(&scope.id));
+            }
+        }
+    }
+
+    /// `docx://{id}/summary` resources as (uri, name), one per document the server created
+    pub fn summary_resources(&self) -> Vec<(String, String)> {
+        let handler = self.handler.read().unwrap();
+        handler.list_documents().into_iter()
+            .filter(|m| self.can_access(&handler, &m.id))
+            .filter(|m| handler.document_summary(&m.id).is_ok())
+            .map(|m| (format!("docx://{}/summary", m.id), m.title.clone().unwrap_or_else(|| m.id.clone())))
+            .collect()
+    }
+
+    /// Contents of a `docx://{id}/summary` resource as JSON
+    pub fn read_summary_resource(&self, uri: &str) -> anyhow::Result<String> {
+        let doc_id = uri.strip_prefix("docx://")
+            .and_then(|rest| rest.strip_suffix("/summary"))
+            .ok_or_else(|| anyhow::anyhow!("Unknown resource: {}", uri))?;
+        let handler = self.handler.read().unwrap();
+        if !self.can_access(&handler, doc_id) {
+            anyhow::bail!("Document not found: {}", doc_id);
+        }
+        let summary = handler.document_summary(doc_id)?;
+        Ok(serde_json::to_string_pretty(&summary)?)
+    }
+
+    /// Tools with their output schemas and annotations, in the MCP `tools/list` shape
+    /// (`outputSchema` is not part of mcp-core's `Tool`, so it is added here)
+    pub async fn list_tools_with_schemas(&self) -> Vec<Value> {
+        self.list_tools().await.into_iter().map(|tool| {
+            let output_schema = crate::tool_hints::output_schema_for(&tool.name);
+            let mut value = serde_json::to_value(&tool).unwrap_or_else(|_| json!({"name": tool.name}));
+            value["outputSchema"] = output_schema;
+            value
+        }).collect()
+    }
+
+    /// Every tool this build provides, before security filtering
+    fn tool_definitions() -> Vec<Tool> {
+        let tools = vec![
             Tool {
                 name: "create_document".to_string(),
                 description: Some("Create a new empty DOCX document".to_string()),
                 input_schema: json!({
                     "type": "object",
-                    "properties": {},
+                    "properties": {
+                        "title": {
+                            "type": "string",
+                            "description": "Label shown by list_documents (e.g. \"Q4 Board Report\")"
+                        },
+                        "label": {
+                            "type": "string",
+                            "description": "Alias for title"
+                        }
+                    },
                     "required": []
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "generate_invoice".to_string(),
+                description: Some("Create a complete invoice document from line items: computes line amounts, tax and totals and formats currency for the locale".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "company_name": {"type": "string"},
+                        "invoice_number": {"type": "string"},
+                        "date": {"type": "string"},
+                        "due_date": {"type": "string"},
+                        "bill_to": {"type": "array", "items": {"type": "string"}, "description": "Customer name and address lines"},
+                        "line_items": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "description": {"type": "string"},
+                                    "quantity": {"type": "number", "default": 1},
+                                    "unit_price": {"type": "number"},
+                                    "tax_rate": {"type": "number", "description": "Percent; defaults to the invoice tax_rate"}
+                                },
+                                "required": ["description", "unit_price"]
+                            }
+                        },
+                        "currency": {"type": "string", "description": "ISO 4217 code", "default": "USD"},
+                        "locale": {"type": "string", "description": "e.g. en-US, en-GB, de-DE, fr-FR", "default": "en-US"},
+                        "tax_rate": {"type": "number", "description": "Default tax percent", "default": 0},
+                        "notes": {"type": "string", "description": "Payment terms or other closing text"}
+                    },
+                    "required": ["company_name", "line_items"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "open_document".to_string(),
                 description: Some("Open an existing DOCX document".to_string()),
@@ -77,7 +462,11 @@ impl DocxToolsProvider {
                     "properties": {
                         "path": {
                             "type": "string",
-                            "description": "Path to the DOCX file to open"
+                            "description": "Path to the DOCX file to open, or an s3://, gs:// or az:// object URI (requires network access)"
+                        },
+                        "editable": {
+                            "type": "boolean",
+                            "description": "Read the document into the editing model so the add/edit tools work on it. Content the model cannot hold is dropped from the working copy and listed in the returned fidelity report (default false)"
                         }
                     },
                     "required": ["path"]
@@ -111,7 +500,38 @@ impl DocxToolsProvider {
                                     "type": "string",
                                     "enum": ["left", "center", "right", "justify"]
                                 },
-                                "line_spacing": {"type": "number"}
+                                "line_spacing": {"type": "number"},
+                                "direction": {
+                                    "type": "string",
+                                    "enum": ["ltr", "rtl"],
+                                    "description": "Paragraph direction; defaults to the direction of the first letter"
+                                },
+                                "highlight": {
+                                    "type": "string",
+                                    "description": "Word highlight color: yellow, green, cyan, magenta, blue, red, darkBlue, darkCyan, darkGreen, darkMagenta, darkRed, darkYellow, darkGray, lightGray or black"
+                                },
+                                "strike": {"type": "boolean"},
+                                "double_strike": {"type": "boolean"},
+                                "vert_align": {"type": "string", "enum": ["superscript", "subscript", "baseline"]},
+                                "caps": {"type": "boolean"},
+                                "small_caps": {"type": "boolean"},
+                                "character_spacing": {"type": "number", "description": "Extra space between characters in points"},
+                                "underline_style": {
+                                    "type": "string",
+                                    "enum": ["single", "double", "thick", "dotted", "dottedHeavy", "dash", "dashedHeavy", "dashLong", "dotDash", "dotDotDash", "wave", "wavyHeavy", "wavyDouble", "words"]
+                                },
+                                "borders": {
+                                    "type": "object",
+                                    "description": "Paragraph borders; each side is {style, color, size (eighths of a point), space (points)}",
+                                    "properties": {
+                                        "top": {"type": "object"},
+                                        "bottom": {"type": "object"},
+                                        "left": {"type": "object"},
+                                        "right": {"type": "object"}
+                                    }
+                                },
+                                "shading": {"type": "string", "description": "Paragraph background as hex RGB"},
+                                "hidden": {"type": "boolean", "description": "Hidden text, not shown or printed by default"}
                             }
                         }
                     },
@@ -180,6 +600,54 @@ impl DocxToolsProvider {
                             "type": "string",
                             "description": "Cell shading color (hex RGB)"
                         },
+                        "table_layout": {
+                            "type": "string",
+                            "enum": ["fixed", "autofit"],
+                            "description": "fixed keeps col_widths as given in every viewer; autofit sizes columns to their content"
+                        },
+                        "width_percent": {
+                            "type": "number",
+                            "description": "Table width as a percentage (1-100) of the text width"
+                        },
+                        "caption": {
+                            "type": "string",
+                            "description": "Caption above the table, numbered as \"Table N: ...\" and listed by add_table_of_figures"
+                        },
+                        "repeat_header_row": {
+                            "type": "boolean",
+                            "description": "Repeat the first row at the top of each page the table runs onto"
+                        },
+                        "cant_split_rows": {
+                            "type": "boolean",
+                            "description": "Keep each row on one page instead of breaking it across pages"
+                        },
+                        "cell_styles": {
+                            "type": "array",
+                            "description": "Cell layout. Leave out row or col to cover every row or column; later entries win.",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "row": {"type": "integer"},
+                                    "col": {"type": "integer"},
+                                    "vertical_align": {"type": "string", "enum": ["top", "center", "bottom"]},
+                                    "padding": {
+                                        "type": "object",
+                                        "description": "Padding in points",
+                                        "properties": {
+                                            "top": {"type": "number"},
+                                            "bottom": {"type": "number"},
+                                            "left": {"type": "number"},
+                                            "right": {"type": "number"}
+                                        }
+                                    },
+                                    "text_direction": {
+                                        "type": "string",
+                                        "enum": ["horizontal", "rotate_up", "rotate_down"],
+                                        "description": "rotate_up reads bottom to top, for narrow header columns"
+                                    }
+                                }
+                            }
+                        },
                         "merges": {
                             "type": "array",
                             "description": "Cell merge specs",
@@ -199,6 +667,38 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "add_table_from_xlsx".to_string(),
+                description: Some("Import a sheet range from an XLSX workbook as a table".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Path to the .xlsx workbook"
+                        },
+                        "sheet": {
+                            "type": "string",
+                            "description": "Sheet name (defaults to the first sheet)"
+                        },
+                        "range": {
+                            "type": "string",
+                            "description": "Cell range in A1 notation, e.g. \"A1:D12\" (defaults to the used range)"
+                        },
+                        "header_row": {
+                            "type": "boolean",
+                            "description": "Treat the first row of the range as the table header",
+                            "default": true
+                        }
+                    },
+                    "required": ["document_id", "path"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "add_section_break".to_string(),
                 description: Some("Insert a section break with optional page setup".to_string()),
@@ -206,7 +706,7 @@ impl DocxToolsProvider {
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "page_size": {"type": "string", "description": "A4, Letter, ..."},
+                        "page_size": {"type": "string", "description": "A3, A4, A5, B5, Letter, Legal, Tabloid, or custom dimensions such as 210x297mm or 8.5x11in"},
                         "orientation": {"type": "string", "enum": ["portrait", "landscape"]},
                         "margins": {
                             "type": "object",
@@ -222,6 +722,66 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "add_watermark".to_string(),
+                description: Some("Put a text or picture watermark behind the text, in every section or in one section only (e.g. DRAFT on the appendices). A section's own watermark replaces the document-wide one there".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "text": {"type": "string", "description": "Watermark text, such as DRAFT or CONFIDENTIAL"},
+                        "image_base64": {"type": "string", "description": "PNG, JPEG or SVG picture to use instead of text"},
+                        "section": {"type": "integer", "minimum": 0, "description": "Section to mark, counting from 0; each section break starts the next one. Omit for the whole document"},
+                        "font": {"type": "string", "description": "Font of a text watermark (default Calibri)"},
+                        "color": {"type": "string", "description": "Hex RGB color of a text watermark (default silver)"},
+                        "opacity": {"type": "number", "minimum": 0, "maximum": 1, "description": "Opacity of a text watermark (default 0.5)"},
+                        "layout": {"type": "string", "enum": ["diagonal", "horizontal"], "description": "Text direction (default diagonal)"},
+                        "width": {"type": "number", "description": "Picture width in inches (default its own size, at most the text width)"},
+                        "washout": {"type": "boolean", "description": "Fade the picture so the text stays readable (default true)"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "remove_watermark".to_string(),
+                description: Some("Remove the watermark of one section, or the document-wide one when section is omitted".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "section": {"type": "integer", "minimum": 0}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "set_page_setup".to_string(),
+                description: Some("Set the page size, orientation, margins, gutter and mirror margins of the document body, without inserting a section break. Omitted arguments keep their current value".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "size": {"type": "string", "description": "A3, A4, A5, B5, Letter, Legal, Tabloid, or custom dimensions such as 210x297mm or 8.5x11in"},
+                        "orientation": {"type": "string", "enum": ["portrait", "landscape"]},
+                        "margins": {
+                            "type": "object",
+                            "description": "Margins in inches",
+                            "properties": {
+                                "top": {"type": "number"},
+                                "bottom": {"type": "number"},
+                                "left": {"type": "number"},
+                                "right": {"type": "number"}
+                            }
+                        },
+                        "gutter": {"type": "number", "description": "Binding gutter in inches"},
+                        "mirror": {"type": "boolean", "description": "Mirror margins for facing pages (left/right become inside/outside)"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "add_list".to_string(),
                 description: Some("Add a bulleted or numbered list to the document".to_string()),
@@ -278,277 +838,263 @@ impl DocxToolsProvider {
                 annotations: None,
             },
             Tool {
-                name: "insert_toc".to_string(),
-                description: Some("Insert a Table of Contents placeholder (hi-fidelity can inject TOC field)".to_string()),
+                name: "group_keep_together".to_string(),
+                description: Some("Keep a run of elements (e.g. a heading, its first paragraph and a table) on one page with keep-with-next and keep-lines-together, so headings are never orphaned".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "from_level": {"type": "integer", "default": 1},
-                        "to_level": {"type": "integer", "default": 3},
-                        "right_align_dots": {"type": "boolean", "default": true}
+                        "start_element": {"type": "integer", "minimum": 0, "description": "First element_index of the group (see get_outline)"},
+                        "end_element": {"type": "integer", "minimum": 0, "description": "Last element_index of the group (inclusive)"}
                     },
-                    "required": ["document_id"]
+                    "required": ["document_id", "start_element", "end_element"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "insert_bookmark_after_heading".to_string(),
-                description: Some("Insert a bookmark immediately after the first matching heading".to_string()),
+                name: "fix_pagination".to_string(),
+                description: Some("Find orphaned headings, single-line widows and awkwardly split tables with the PDF layout engine and fix them with keep-with-next, keep-together or page-break-before".to_string()),
                 input_schema: json!({
                     "type": "object",
-                    "properties": {
-                        "document_id": {"type": "string"},
-                        "heading_text": {"type": "string"},
-                        "name": {"type": "string"}
-                    },
-                    "required": ["document_id", "heading_text", "name"]
+                    "properties": {"document_id": {"type": "string"}},
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "set_header".to_string(),
-                description: Some("Set the document header".to_string()),
+                name: "add_horizontal_rule".to_string(),
+                description: Some("Add a horizontal rule (an empty paragraph with a bottom border)".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
+                        "document_id": {"type": "string"},
+                        "style": {
                             "type": "string",
-                            "description": "ID of the document"
+                            "enum": ["single", "double", "dotted", "dashed", "thick", "wave", "dotDash", "dotDotDash", "triple"],
+                            "default": "single"
                         },
-                        "text": {
-                            "type": "string",
-                            "description": "Header text"
-                        }
+                        "color": {"type": "string", "description": "Hex RGB like \"808080\""},
+                        "size": {"type": "integer", "description": "Line width in eighths of a point", "default": 6}
                     },
-                    "required": ["document_id", "text"]
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "set_footer".to_string(),
-                description: Some("Set the document footer".to_string()),
+                name: "add_signature_block".to_string(),
+                description: Some("Add signature blocks for the parties to a contract: caption, signature line, name, title and date, with optional initials lines and e-signature anchor tags (DocuSign-style \\s1\\ by default) for e-sign workflows".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
-                            "type": "string",
-                            "description": "ID of the document"
+                        "document_id": {"type": "string"},
+                        "parties": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": {"type": "string", "description": "Person who signs"},
+                                    "company": {"type": "string", "description": "Organisation they sign for"},
+                                    "title": {"type": "string"},
+                                    "label": {"type": "string", "description": "Caption instead of \"Signed for and on behalf of <company>\""}
+                                },
+                                "required": ["name"]
+                            }
                         },
-                        "text": {
-                            "type": "string",
-                            "description": "Footer text"
-                        }
+                        "date": {"type": "string", "enum": ["line", "field", "none"], "description": "Blank date line (default), a DATE field, or no date"},
+                        "initials": {"type": "boolean", "description": "Add an initials line to each block"},
+                        "initial_each_page": {"type": "boolean", "description": "Add initials slots for every party to the footer"},
+                        "anchors": {"type": "boolean", "description": "Tag the signature, date and initials lines with invisible e-signature anchors"},
+                        "anchor_format": {"type": "string", "description": "Anchor text with {kind} (s, d or i) and {n} (party number from 1); default \\{kind}{n}\\"}
                     },
-                    "required": ["document_id", "text"]
+                    "required": ["document_id", "parties"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "set_page_numbering".to_string(),
-                description: Some("Set a simple page numbering text in header or footer".to_string()),
+                name: "insert_toc".to_string(),
+                description: Some("Insert a Table of Contents placeholder (hi-fidelity can inject TOC field)".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "location": {"type": "string", "enum": ["header", "footer"], "default": "footer"},
-                        "template": {"type": "string", "description": "e.g., 'Page {PAGE} of {PAGES}'"}
+                        "from_level": {"type": "integer", "default": 1},
+                        "to_level": {"type": "integer", "default": 3},
+                        "right_align_dots": {"type": "boolean", "default": true}
                     },
                     "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "embed_page_number_fields".to_string(),
-                description: Some("Replace placeholder 'Page {PAGE} of {PAGES}' with Word field codes (best-effort)".to_string()),
+                name: "enable_heading_numbering".to_string(),
+                description: Some("Number Heading 1-6 automatically with multilevel list numbering (decimal: 1. / 1.1. / 1.1.1., legal: 1 / 1.1 / 1.1.1, outline: I. / A. / 1.), or remove it with none. Numbers typed into heading text are removed".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {"type": "string"}
+                        "document_id": {"type": "string"},
+                        "scheme": {"type": "string", "enum": ["decimal", "legal", "outline", "none"], "description": "Numbering scheme (default decimal)"}
                     },
                     "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "add_image".to_string(),
-                description: Some("Insert an image into the document".to_string()),
+                name: "renumber_headings".to_string(),
+                description: Some("Renumbering pass after moving or deleting sections: returns each heading with its number, and rewrites numbers typed into heading text as one sequence".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {"type": "string"},
-                        "data_base64": {"type": "string", "description": "Base64-encoded image data (PNG/JPEG)"},
-                        "width": {"type": "integer", "description": "Width in pixels"},
-                        "height": {"type": "integer", "description": "Height in pixels"},
-                        "alt_text": {"type": "string"}
+                        "document_id": {"type": "string"}
                     },
-                    "required": ["document_id", "data_base64"]
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "add_hyperlink".to_string(),
-                description: Some("Insert a hyperlink into the document".to_string()),
+                name: "normalize_headings".to_string(),
+                description: Some("Fix inconsistent heading capitalization and typed number prefixes (\"2)\", \"IV.\", \"Section 3:\") across the document".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "text": {"type": "string"},
-                        "url": {"type": "string"}
+                        "style": {"type": "string", "enum": ["title_case", "sentence_case", "keep"], "description": "Capitalization for heading text (default keep)"},
+                        "renumber": {"type": "boolean", "description": "Replace typed number prefixes with one 1. / 1.1 sequence (default true)"}
                     },
-                    "required": ["document_id", "text", "url"]
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "find_and_replace".to_string(),
-                description: Some("Find and replace text in the document".to_string()),
+                name: "move_section".to_string(),
+                description: Some("Move a heading and all of its content (up to the next heading of the same level) before or after another heading's section in one step".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
-                            "type": "string",
-                            "description": "ID of the document"
-                        },
-                        "find_text": {
-                            "type": "string",
-                            "description": "Text to find"
-                        },
-                        "replace_text": {
-                            "type": "string",
-                            "description": "Text to replace with"
-                        }
+                        "document_id": {"type": "string"},
+                        "heading": {"type": "string", "description": "Text of the heading to move"},
+                        "position": {"type": "string", "enum": ["before", "after"]},
+                        "target": {"type": "string", "description": "Text of the heading to move next to; with after, the section goes after the target's subsections"}
                     },
-                    "required": ["document_id", "find_text", "replace_text"]
+                    "required": ["document_id", "heading", "position", "target"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "find_and_replace_advanced".to_string(),
-                description: Some("Find/replace with regex, case, whole-word, preserving runs".to_string()),
+                name: "delete_section".to_string(),
+                description: Some("Delete a heading and all of its content, including subsections".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "pattern": {"type": "string"},
-                        "replacement": {"type": "string"},
-                        "case_sensitive": {"type": "boolean", "default": false},
-                        "whole_word": {"type": "boolean", "default": false},
-                        "use_regex": {"type": "boolean", "default": false}
+                        "heading": {"type": "string", "description": "Heading text"},
+                        "element_index": {"type": "integer", "description": "Heading element_index from get_outline (takes precedence over heading)"}
                     },
-                    "required": ["document_id", "pattern", "replacement"]
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "apply_paragraph_format".to_string(),
-                description: Some("Apply paragraph formatting to paragraphs matching a simple selector".to_string()),
+                name: "set_section_content".to_string(),
+                description: Some("Replace the content directly under a heading (such as a create_outline placeholder) with new paragraphs, lists and tables. The heading, its subsections and the sections after it are kept".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "contains": {"type": "string", "description": "Substring to match in paragraph text"},
-                        "format": {
-                            "type": "object",
-                            "properties": {
-                                "font_family": {"type": "string"},
-                                "font_size": {"type": "integer"},
-                                "bold": {"type": "boolean"},
-                                "italic": {"type": "boolean"},
-                                "underline": {"type": "boolean"},
-                                "color": {"type": "string"},
-                                "alignment": {"type": "string"},
-                                "line_spacing": {"type": "number"}
+                        "heading": {"type": "string", "description": "Heading text"},
+                        "element_index": {"type": "integer", "description": "Heading element_index from get_outline (takes precedence over heading)"},
+                        "blocks": {
+                            "type": "array",
+                            "description": "New content, as the elements of insert_at; an empty list clears the section. Headings must be deeper than the section's own",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "type": {"type": "string", "enum": ["paragraph", "heading", "list", "table", "page_break"]},
+                                    "text": {"type": "string", "description": "paragraph and heading"},
+                                    "style": {"type": "object", "description": "paragraph style as in add_paragraph"},
+                                    "level": {"type": "integer", "minimum": 1, "maximum": 6, "description": "heading"},
+                                    "items": {"type": "array", "items": {"type": "string"}, "description": "list"},
+                                    "ordered": {"type": "boolean", "description": "list"},
+                                    "rows": {"type": "array", "items": {"type": "array", "items": {"type": "string"}}, "description": "table"},
+                                    "headers": {"type": "array", "items": {"type": "string"}, "description": "table"}
+                                },
+                                "required": ["type"]
                             }
                         }
                     },
-                    "required": ["document_id", "format"]
+                    "required": ["document_id", "blocks"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "extract_text".to_string(),
-                description: Some("Extract all text content from the document".to_string()),
+                name: "delete_range".to_string(),
+                description: Some("Delete the elements from start_element to end_element inclusive (element indices from get_outline or extract_text)".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
-                            "type": "string",
-                            "description": "ID of the document"
-                        }
+                        "document_id": {"type": "string"},
+                        "start_element": {"type": "integer", "minimum": 0},
+                        "end_element": {"type": "integer", "minimum": 0}
                     },
-                    "required": ["document_id"]
-                }),
-                annotations: None,
-            },
-            Tool {
-                name: "get_tables".to_string(),
-                description: Some("List tables with dimensions, merges, and cell content".to_string()),
-                input_schema: json!({
-                    "type": "object",
-                    "properties": {"document_id": {"type": "string"}},
-                    "required": ["document_id"]
-                }),
-                annotations: None,
-            },
-            Tool {
-                name: "list_images".to_string(),
-                description: Some("List images with width/height and alt text".to_string()),
-                input_schema: json!({
-                    "type": "object",
-                    "properties": {"document_id": {"type": "string"}},
-                    "required": ["document_id"]
-                }),
-                annotations: None,
-            },
-            Tool {
-                name: "list_hyperlinks".to_string(),
-                description: Some("List hyperlinks in the document".to_string()),
-                input_schema: json!({
-                    "type": "object",
-                    "properties": {"document_id": {"type": "string"}},
-                    "required": ["document_id"]
+                    "required": ["document_id", "start_element", "end_element"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "get_fields_summary".to_string(),
-                description: Some("Summarize Word fields (PAGE, NUMPAGES, TOC) in document and headers/footers".to_string()),
+                name: "add_table_of_figures".to_string(),
+                description: Some("Add a table of figures or list of tables: a TOC field over the Figure/Table caption sequence that Word fills in with page numbers, or a static list of the captions found now".to_string()),
                 input_schema: json!({
                     "type": "object",
-                    "properties": {"document_id": {"type": "string"}},
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "kind": {"type": "string", "enum": ["figures", "tables"], "description": "Default figures"},
+                        "static": {"type": "boolean", "description": "Write the captions as plain paragraphs instead of a field (default false)"}
+                    },
                     "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "strip_personal_info".to_string(),
-                description: Some("Remove personal info from metadata and core.xml (best-effort)".to_string()),
+                name: "insert_bookmark_after_heading".to_string(),
+                description: Some("Insert a bookmark immediately after the first matching heading".to_string()),
                 input_schema: json!({
                     "type": "object",
-                    "properties": {"document_id": {"type": "string"}},
-                    "required": ["document_id"]
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "heading_text": {"type": "string"},
+                        "name": {"type": "string"}
+                    },
+                    "required": ["document_id", "heading_text", "name"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "get_metadata".to_string(),
-                description: Some("Get document metadata".to_string()),
+                name: "set_header".to_string(),
+                description: Some("Set the document header".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {
                             "type": "string",
                             "description": "ID of the document"
-                        }
+                        },
+                        "text": {
+                            "type": "string",
+                            "description": "Header text. Tabs split it into left, center and right zones, e.g. \"Acme Ltd\\tConfidential\\tPage {PAGE}\""
+                        },
+                        "alignment": {"type": "string", "enum": ["left", "center", "right", "justify"], "description": "Paragraph alignment when the text has no tabs"},
+                        "logo_base64": {"type": "string", "description": "Image (e.g. a company logo) shown in the header"},
+                        "table": header_footer_table_schema(),
+                        "logo_width": {"type": "integer", "description": "Logo width in pixels (default 120)"},
+                        "logo_height": {"type": "integer", "description": "Logo height in pixels (default 40)"},
+                        "logo_position": {"type": "string", "enum": ["left", "center", "right"], "description": "Zone the logo goes in (default left)"}
                     },
-                    "required": ["document_id"]
+                    "required": ["document_id", "text"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "save_document".to_string(),
-                description: Some("Save the document to a specific path".to_string()),
+                name: "set_footer".to_string(),
+                description: Some("Set the document footer".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -556,179 +1102,1036 @@ impl DocxToolsProvider {
                             "type": "string",
                             "description": "ID of the document"
                         },
-                        "output_path": {
+                        "text": {
                             "type": "string",
-                            "description": "Path where to save the document"
-                        }
+                            "description": "Footer text. Tabs split it into left, center and right zones, e.g. \"Acme Ltd\\tConfidential\\tPage {PAGE}\""
+                        },
+                        "alignment": {"type": "string", "enum": ["left", "center", "right", "justify"], "description": "Paragraph alignment when the text has no tabs"},
+                        "logo_base64": {"type": "string", "description": "Image (e.g. a company logo) shown in the footer"},
+                        "table": header_footer_table_schema(),
+                        "logo_width": {"type": "integer", "description": "Logo width in pixels (default 120)"},
+                        "logo_height": {"type": "integer", "description": "Logo height in pixels (default 40)"},
+                        "logo_position": {"type": "string", "enum": ["left", "center", "right"], "description": "Zone the logo goes in (default left)"}
                     },
-                    "required": ["document_id", "output_path"]
+                    "required": ["document_id", "text"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "close_document".to_string(),
-                description: Some("Close the document and free resources".to_string()),
+                name: "set_page_numbering".to_string(),
+                description: Some("Set a simple page numbering text in header or footer".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
-                            "type": "string",
-                            "description": "ID of the document"
-                        }
+                        "document_id": {"type": "string"},
+                        "location": {"type": "string", "enum": ["header", "footer"], "default": "footer"},
+                        "template": {"type": "string", "description": "e.g., 'Page {PAGE} of {PAGES}'"}
                     },
                     "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "list_documents".to_string(),
-                description: Some("List all open documents".to_string()),
+                name: "embed_page_number_fields".to_string(),
+                description: Some("Replace placeholder 'Page {PAGE} of {PAGES}' with Word field codes (best-effort)".to_string()),
                 input_schema: json!({
                     "type": "object",
-                    "properties": {},
-                    "required": []
+                    "properties": {
+                        "document_id": {"type": "string"}
+                    },
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "convert_to_pdf".to_string(),
-                description: Some("Convert a DOCX document to PDF".to_string()),
+                name: "insert_field".to_string(),
+                description: Some("Insert a Word field (date, time, file name, author, title, STYLEREF, page numbers) that updates when the document opens in Word".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
+                        "document_id": {"type": "string"},
+                        "field": {
                             "type": "string",
-                            "description": "ID of the document to convert"
+                            "enum": ["DATE", "TIME", "FILENAME", "AUTHOR", "TITLE", "STYLEREF", "PAGE", "NUMPAGES"]
                         },
-                        "output_path": {
+                        "argument": {
                             "type": "string",
-                            "description": "Path where to save the PDF"
+                            "description": "Date/time picture for DATE/TIME (e.g. \"d MMMM yyyy\"); style name for STYLEREF (e.g. \"Heading 1\")"
                         },
-                        "prefer_external": {
-                            "type": "boolean",
-                            "description": "Prefer external hi-fidelity converter when available",
-                            "default": false
-                        }
+                        "location": {"type": "string", "enum": ["body", "header", "footer"], "default": "body"},
+                        "text_before": {"type": "string", "description": "Literal text placed before the field, e.g. \"Printed \""}
                     },
-                    "required": ["document_id", "output_path"]
+                    "required": ["document_id", "field"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "export_pdf_with_field_refresh".to_string(),
-                description: Some("Embed page fields then export to PDF (hi-fidelity when available)".to_string()),
+                name: "add_image".to_string(),
+                description: Some("Insert an image into the document".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "output_path": {"type": "string"},
-                        "prefer_external": {"type": "boolean", "default": true}
+                        "data_base64": {"type": "string", "description": "Base64-encoded image data (PNG/JPEG)"},
+                        "width": {"type": "integer", "description": "Width in pixels"},
+                        "height": {"type": "integer", "description": "Height in pixels"},
+                        "alt_text": {"type": "string"}
                     },
-                    "required": ["document_id", "output_path"]
+                    "required": ["document_id", "data_base64"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "convert_to_images".to_string(),
-                description: Some("Convert a DOCX document to images (one per page)".to_string()),
+                name: "add_hyperlink".to_string(),
+                description: Some("Insert a hyperlink into the document".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "text": {"type": "string"},
+                        "url": {"type": "string"}
+                    },
+                    "required": ["document_id", "text", "url"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "find_and_replace".to_string(),
+                description: Some("Find and replace text in the document".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {
                             "type": "string",
-                            "description": "ID of the document to convert"
+                            "description": "ID of the document"
                         },
-                        "output_dir": {
+                        "find_text": {
                             "type": "string",
-                            "description": "Directory where to save the images"
+                            "description": "Text to find"
                         },
-                        "format": {
+                        "replace_text": {
                             "type": "string",
-                            "description": "Image format",
-                            "enum": ["png", "jpg", "jpeg"],
-                            "default": "png"
-                        },
-                        "dpi": {
-                            "type": "integer",
-                            "description": "Resolution in DPI",
-                            "default": 150,
-                            "minimum": 72,
-                            "maximum": 600
+                            "description": "Text to replace with"
                         }
                     },
-                    "required": ["document_id", "output_dir"]
+                    "required": ["document_id", "find_text", "replace_text"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "convert_to_images_with_preference".to_string(),
-                description: Some("Convert DOCX to images, preferring external hi-fidelity path".to_string()),
+                name: "transform_text".to_string(),
+                description: Some("Bulk text transform over paragraphs, headings, table cells, lists or code paragraphs: title/sentence/upper/lower case, trim trailing whitespace, or hard-wrap long lines".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "document_id": {"type": "string"},
-                        "output_dir": {"type": "string"},
-                        "format": {"type": "string", "enum": ["png", "jpg", "jpeg"], "default": "png"},
-                        "dpi": {"type": "integer", "default": 150},
-                        "prefer_external": {"type": "boolean", "default": true}
+                        "scope": {
+                            "type": "string",
+                            "enum": ["paragraphs", "headings", "tables", "lists", "code", "all"],
+                            "description": "code = paragraphs set in a monospace font"
+                        },
+                        "operation": {
+                            "type": "string",
+                            "enum": ["title_case", "sentence_case", "uppercase", "lowercase", "trim_trailing_whitespace", "max_line_length"]
+                        },
+                        "max_length": {"type": "integer", "minimum": 8, "description": "Line length for max_line_length"}
                     },
-                    "required": ["document_id", "output_dir"]
+                    "required": ["document_id", "scope", "operation"]
                 }),
                 annotations: None,
             },
-            // Advanced tools are gated and added only when feature is enabled
-            
-            #[cfg(feature = "advanced-docx")]
             Tool {
-                name: "merge_documents".to_string(),
-                description: Some("Merge multiple DOCX documents into one".to_string()),
+                name: "find_and_replace_advanced".to_string(),
+                description: Some("Find/replace with regex, case, whole-word, preserving runs".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_ids": {
+                        "document_id": {"type": "string"},
+                        "pattern": {"type": "string"},
+                        "replacement": {"type": "string", "description": "With use_regex, $1 or ${name} inserts a captured group and $$ a dollar sign"},
+                        "case_sensitive": {"type": "boolean", "default": false},
+                        "whole_word": {"type": "boolean", "default": false},
+                        "use_regex": {"type": "boolean", "default": false},
+                        "preserve_case": {"type": "boolean", "default": false, "description": "Match the case of each replaced occurrence: Acme → Globex, ACME → GLOBEX, acme → globex"},
+                        "dry_run": {"type": "boolean", "default": false, "description": "List the replacements that would be made, each with the text around it before and after, without changing the document"},
+                        "include": {
                             "type": "array",
-                            "description": "IDs of documents to merge",
-                            "items": {"type": "string"}
+                            "items": {"type": "string", "enum": ["hyperlink_urls", "alt_text", "bookmark_names", "properties"]},
+                            "description": "Also replace in hyperlink URLs, image alt text, bookmark names or the title, subject and author properties (properties ignore scope)"
                         },
-                        "output_path": {
-                            "type": "string",
-                            "description": "Path where to save the merged document"
+                        "scope": {
+                            "description": "Limit replacements to part of the document: \"all\" (default), \"tables\", \"headers\" (headers and footers), {\"heading\": \"Terms\"} (that section and its subsections) or {\"element_range\": {\"start\": 3, \"end\": 9}} (element indices as in get_outline, inclusive)",
+                            "oneOf": [
+                                {"type": "string", "enum": ["all", "tables", "headers"]},
+                                {"type": "object", "properties": {"heading": {"type": "string"}}, "required": ["heading"]},
+                                {
+                                    "type": "object",
+                                    "properties": {"element_range": {
+                                        "type": "object",
+                                        "properties": {"start": {"type": "integer", "minimum": 0}, "end": {"type": "integer", "minimum": 0}},
+                                        "required": ["start", "end"]
+                                    }},
+                                    "required": ["element_range"]
+                                }
+                            ]
                         }
                     },
-                    "required": ["document_ids", "output_path"]
+                    "required": ["document_id", "pattern", "replacement"]
                 }),
                 annotations: None,
             },
-            #[cfg(feature = "advanced-docx")]
             Tool {
-                name: "split_document".to_string(),
-                description: Some("Split a document at page breaks".to_string()),
+                name: "apply_comment_suggestions".to_string(),
+                description: Some("Read review comments of the form \"change X to Y\", \"replace X with Y\" or \"X -> Y\" in an opened document and either list them as tasks with the text they point at, or make each one a tracked change (w:del/w:ins) on the commented text. Other comments are returned as tasks too".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
+                        "document_id": {"type": "string"},
+                        "mode": {
                             "type": "string",
-                            "description": "ID of the document to split"
+                            "enum": ["task_list", "track_changes"],
+                            "default": "task_list"
                         },
-                        "output_dir": {
-                            "type": "string",
-                            "description": "Directory where to save the split documents"
-                        }
+                        "author": {"type": "string", "description": "Author of the tracked changes", "default": "docx-mcp"}
                     },
-                    "required": ["document_id", "output_dir"]
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "get_document_structure".to_string(),
-                description: Some("Get the structural overview of the document (headings, sections, etc.)".to_string()),
+                name: "render_template".to_string(),
+                description: Some("Fill Handlebars-style tags ({{var}}, {{#each}}, {{#if}}) in paragraphs, lists, table cells, headers and footers from a JSON payload; table rows wrapped in {{#each}} are repeated per item".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
-                            "type": "string",
-                            "description": "ID of the document"
-                        }
+                        "document_id": {"type": "string"},
+                        "data": {"type": "object", "description": "Values for the template, e.g. {\"customer\": {\"name\": \"Acme\"}, \"items\": [...]}"},
+                        "locale": {"type": "string", "description": "Locale for format helpers such as {{currency total}} and {{date due \"long\"}} (e.g. de-DE)", "default": "en-US"},
+                        "currency": {"type": "string", "description": "Default ISO 4217 code for {{currency}}", "default": "USD"}
+                    },
+                    "required": ["document_id", "data"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "tag_content".to_string(),
+                description: Some("Tag a range of elements (e.g. \"internal\", \"customer\") so one master document can produce audience-specific versions with strip_tagged_content or keep_only_tagged".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "tag": {"type": "string", "description": "Letters, digits, '_' or '-'"},
+                        "start_element": {"type": "integer", "description": "First element_index in the region (see get_outline / search_text)"},
+                        "end_element": {"type": "integer", "description": "Last element_index in the region (inclusive)"}
+                    },
+                    "required": ["document_id", "tag", "start_element", "end_element"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_tagged_content".to_string(),
+                description: Some("List tagged regions with their element ranges".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "strip_tagged_content".to_string(),
+                description: Some("Remove every region tagged with one of the given tags (e.g. strip \"internal\" for the customer copy)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "tags": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["document_id", "tags"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "keep_only_tagged".to_string(),
+                description: Some("Remove every tagged region whose tag is not in the list; untagged content is kept".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "tags": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["document_id", "tags"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "highlight_matches".to_string(),
+                description: Some("Highlight every match of a pattern without changing the text (for review passes)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "pattern": {"type": "string"},
+                        "color": {"type": "string", "description": "Highlight name (yellow, green, cyan, magenta, ...) or hex fill like FFF2CC", "default": "yellow"},
+                        "case_sensitive": {"type": "boolean", "default": false},
+                        "whole_word": {"type": "boolean", "default": false},
+                        "use_regex": {"type": "boolean", "default": false}
+                    },
+                    "required": ["document_id", "pattern"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "apply_paragraph_format".to_string(),
+                description: Some("Apply paragraph formatting to paragraphs matching a simple selector".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "contains": {"type": "string", "description": "Substring to match in paragraph text"},
+                        "format": {
+                            "type": "object",
+                            "properties": {
+                                "font_family": {"type": "string"},
+                                "font_size": {"type": "integer"},
+                                "bold": {"type": "boolean"},
+                                "italic": {"type": "boolean"},
+                                "underline": {"type": "boolean"},
+                                "color": {"type": "string"},
+                                "alignment": {"type": "string"},
+                                "line_spacing": {"type": "number"},
+                                "direction": {"type": "string", "enum": ["ltr", "rtl"]},
+                                "highlight": {"type": "string"},
+                                "strike": {"type": "boolean"},
+                                "double_strike": {"type": "boolean"},
+                                "vert_align": {"type": "string", "enum": ["superscript", "subscript", "baseline"]},
+                                "caps": {"type": "boolean"},
+                                "small_caps": {"type": "boolean"},
+                                "character_spacing": {"type": "number"},
+                                "underline_style": {"type": "string"},
+                                "borders": {"type": "object"},
+                                "shading": {"type": "string"},
+                                "hidden": {"type": "boolean"}
+                            }
+                        }
+                    },
+                    "required": ["document_id", "format"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "extract_text".to_string(),
+                description: Some("Extract all text content from the document, optionally with headers, footers, notes, comments and hidden text".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "include_headers": {
+                            "type": "boolean",
+                            "description": "Add header and footer text before and after the body (default false)"
+                        },
+                        "include_footnotes": {
+                            "type": "boolean",
+                            "description": "Mark note references as [n] and list footnotes and endnotes after the body (default false)"
+                        },
+                        "include_comments": {
+                            "type": "boolean",
+                            "description": "List comments with their author and anchored text after the body (default false)"
+                        },
+                        "include_hidden": {
+                            "type": "boolean",
+                            "description": "Keep text formatted as hidden (default false)"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "extract_formatted_text".to_string(),
+                description: Some("Extract text keeping headings, list structure, tables, bold, italic, underline, strikethrough and links, as markdown or annotated JSON".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "format": {
+                            "type": "string",
+                            "enum": ["markdown", "annotated_json"],
+                            "description": "markdown text (default) or blocks of spans with their formatting flags and link targets"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "get_tables".to_string(),
+                description: Some("List tables with dimensions, merges, and cell content".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {"document_id": {"type": "string"}},
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_images".to_string(),
+                description: Some("List images with width/height and alt text".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {"document_id": {"type": "string"}},
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_hyperlinks".to_string(),
+                description: Some("List hyperlinks in the document".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {"document_id": {"type": "string"}},
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "get_fields_summary".to_string(),
+                description: Some("Summarize Word fields (PAGE, NUMPAGES, TOC) in document and headers/footers".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {"document_id": {"type": "string"}},
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "strip_personal_info".to_string(),
+                description: Some("Remove personal info from metadata and core.xml (best-effort)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {"document_id": {"type": "string"}},
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "get_metadata".to_string(),
+                description: Some("Get document metadata, including the SHA-256 of the working copy and session activity: ops applied, the last five ops, bytes written by saves and whether there are unsaved changes".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "save_document".to_string(),
+                description: Some("Save the document to a specific path. The response carries the SHA-256 and size of the saved file".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Path where to save the document, or an s3://, gs:// or az:// object URI (requires network access)"
+                        }
+                    },
+                    "required": ["document_id", "output_path"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "verify_document".to_string(),
+                description: Some("Check that a file still has the SHA-256 reported by save_document or get_metadata; fails on a mismatch".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {"type": "string", "description": "File to check"},
+                        "expected_hash": {"type": "string", "description": "Hex SHA-256, optionally prefixed with sha256:"}
+                    },
+                    "required": ["path", "expected_hash"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "close_document".to_string(),
+                description: Some("Close the document and free resources".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "rename_document".to_string(),
+                description: Some("Set or change the title a document is listed under; an empty title clears it".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "title": {
+                            "type": "string",
+                            "description": "New title"
+                        }
+                    },
+                    "required": ["document_id", "title"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "clone_document".to_string(),
+                description: Some("Duplicate a document (content and edit history) under a new ID, leaving the original untouched".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document to copy"
+                        },
+                        "title": {
+                            "type": "string",
+                            "description": "Title of the copy (default: original title with \" (copy)\")"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "export_ops".to_string(),
+                description: Some("Export the document's ops journal, the JSON recipe it was built from, for storing alongside its sources and rebuilding later with replay_ops".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "output_path": {"type": "string", "description": "Write the journal here instead of returning it"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "replay_ops".to_string(),
+                description: Some("Rebuild a document from an ops journal produced by export_ops, as a new document".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "ops_json": {"type": "string", "description": "The journal JSON"},
+                        "path": {"type": "string", "description": "Read the journal from this file instead"},
+                        "title": {"type": "string", "description": "Title of the new document (default: the title in the journal)"}
+                    }
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "snapshot_document".to_string(),
+                description: Some("Keep a copy of the document as it is now, as the baseline for summarize_changes. Snapshots live until the document is closed; past 20 per document, a new one drops the oldest".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "label": {"type": "string", "description": "Name to refer to the snapshot by, e.g. \"sent-to-legal\""}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "summarize_changes".to_string(),
+                description: Some("Human-readable change log since a snapshot: sections added and removed, word deltas per section and tables modified, as structured fields plus a bulleted `text` ready to paste into an email or PR description".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "since_snapshot": {"type": "string", "description": "snapshot_id or label from snapshot_document (the latest with that label)"}
+                    },
+                    "required": ["document_id", "since_snapshot"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "copy_elements".to_string(),
+                description: Some("Copy a range of elements (paragraphs, headings, tables, lists, images) from one open document into another, with their styles, numbering and image data".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "source_document_id": {"type": "string", "description": "Document to copy from"},
+                        "start_element": {"type": "integer", "description": "First element_index to copy (see get_outline / get_document_structure)"},
+                        "end_element": {"type": "integer", "description": "Last element_index to copy (inclusive, default: start_element)"},
+                        "document_id": {"type": "string", "description": "Document to copy into"},
+                        "position": {"type": "integer", "description": "element_index in the target to insert before (default: append)"},
+                        "style_map": style_map_schema()
+                    },
+                    "required": ["source_document_id", "start_element", "document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "assemble_document".to_string(),
+                description: Some("Assemble several open documents into a new deliverable: parts in order with page breaks, an optional generated table of contents, and optionally one continuous heading numbering".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "parts": {"type": "array", "items": {"type": "string"}, "description": "Document IDs in assembly order"},
+                        "title": {"type": "string", "description": "Title of the assembled document"},
+                        "toc": {"type": "boolean", "description": "Open with a table of contents (default true)"},
+                        "continuous_numbering": {"type": "boolean", "description": "Number headings in one sequence across parts, replacing typed numbers (default false)"},
+                        "style_map": style_map_schema()
+                    },
+                    "required": ["parts"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_clauses".to_string(),
+                description: Some("List the clauses in the clause library (.docx and .md fragments in the --clause-dir directory)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "insert_clause".to_string(),
+                description: Some("Insert a clause from the clause library into a document, keeping its headings, lists, tables and paragraph formatting".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "name": {"type": "string", "description": "Clause name (file name without extension, see list_clauses)"},
+                        "position": {"type": "integer", "description": "element_index to insert before (default: append)"}
+                    },
+                    "required": ["document_id", "name"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_letterheads".to_string(),
+                description: Some("List the letterhead profiles in the --letterheads file".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "apply_letterhead".to_string(),
+                description: Some("Stamp a letterhead profile onto a document: logo and address block in the header, footer text, default font, page size and margins".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "profile": {"type": "string", "description": "Profile name (see list_letterheads)"}
+                    },
+                    "required": ["document_id", "profile"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "rebrand".to_string(),
+                description: Some("Rebrand a document in one call: rename fonts, swap brand colors, replace the logo, rename company or product terms (whole words, keeping case, also in link URLs, alt text, bookmarks and properties) and set new header and footer text".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "mapping": {
+                            "type": "object",
+                            "description": "Brand profile; every part is optional",
+                            "properties": {
+                                "fonts": {"type": "object", "additionalProperties": {"type": "string"}, "description": "Old font family → new, e.g. {\"Calibri\": \"Inter\"}"},
+                                "default_font": {"type": "string", "description": "New document default font"},
+                                "colors": {"type": "object", "additionalProperties": {"type": "string"}, "description": "Old hex RGB → new, e.g. {\"1F4E79\": \"0B6E4F\"}: text, shading, borders, table shading and watermark colors"},
+                                "header": {"type": "string", "description": "New header text; the header layout and logo are kept"},
+                                "footer": {"type": "string", "description": "New footer text"},
+                                "logo_base64": {"type": "string", "description": "New logo (PNG or JPEG) for header/footer logos and body images whose alt text mentions \"logo\""},
+                                "terms": {"type": "object", "additionalProperties": {"type": "string"}, "description": "Old name → new, e.g. {\"Acme\": \"Globex\"}"}
+                            },
+                            "additionalProperties": false
+                        }
+                    },
+                    "required": ["document_id", "mapping"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "acquire_lock".to_string(),
+                description: Some("Lock a document for exclusive writing. While the lease is live, write tools on it fail with DOCUMENT_LOCKED unless they pass the returned token as lock_token. Call again with the token to renew".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "lease_seconds": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "maximum": 3600,
+                            "description": "Lease length (default: 60)"
+                        },
+                        "owner": {
+                            "type": "string",
+                            "description": "Holder name shown to other clients"
+                        },
+                        "lock_token": {
+                            "type": "string",
+                            "description": "Current token, to renew a held lock"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "release_lock".to_string(),
+                description: Some("Release a document lock".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "lock_token": {
+                            "type": "string",
+                            "description": "Token returned by acquire_lock"
+                        }
+                    },
+                    "required": ["document_id", "lock_token"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "get_lock_status".to_string(),
+                description: Some("Show who holds a document's lock and until when (the token is not revealed)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_documents".to_string(),
+                description: Some("List open documents with summaries (word count, headings, size, unsaved changes), oldest first. Supports paging and filtering by title or creation time".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "offset": {"type": "integer", "minimum": 0, "description": "Number of documents to skip"},
+                        "limit": {"type": "integer", "minimum": 1, "description": "Maximum documents to return (default: all)"},
+                        "title_contains": {"type": "string", "description": "Case-insensitive title substring"},
+                        "created_after": {"type": "string", "description": "RFC 3339 timestamp; only documents created at or after it"},
+                        "created_before": {"type": "string", "description": "RFC 3339 timestamp; only documents created before it"},
+                        "workspace_id": {"type": "string", "description": "Only documents in this workspace"}
+                    },
+                    "required": []
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_all_documents".to_string(),
+                description: Some("Operator tool: list the documents of every client of a shared (HTTP) server, with the owning client of each. Takes the same paging and filters as list_documents".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "client_id": {"type": "string", "description": "Only documents of this client (session id)"},
+                        "offset": {"type": "integer", "minimum": 0, "description": "Number of documents to skip"},
+                        "limit": {"type": "integer", "minimum": 1, "description": "Maximum documents to return (default: all)"},
+                        "title_contains": {"type": "string", "description": "Case-insensitive title substring"},
+                        "created_after": {"type": "string", "description": "RFC 3339 timestamp; only documents created at or after it"},
+                        "created_before": {"type": "string", "description": "RFC 3339 timestamp; only documents created before it"},
+                        "workspace_id": {"type": "string", "description": "Only documents in this workspace"}
+                    },
+                    "required": []
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "transfer_document".to_string(),
+                description: Some("Operator tool: hand a document to another client of a shared (HTTP) server, or omit client_id to give it back to the local operator".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "client_id": {"type": "string", "description": "Session id of the new owner"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "create_viewer".to_string(),
+                description: Some("Create a read-only viewer key for this HTTP session. A reviewer that posts MCP requests to /viewer with `Authorization: Bearer <viewer_key>` can read this session's documents, including later edits, but cannot change them".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "revoke_viewer".to_string(),
+                description: Some("Revoke a viewer key of this session, or all of them when viewer_key is omitted".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "viewer_key": {"type": "string"}
+                    },
+                    "required": []
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "create_workspace".to_string(),
+                description: Some("Create a workspace grouping related documents. Relative output paths of its documents (save, convert, export) are written to the workspace output directory".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "minLength": 1,
+                            "description": "Workspace name"
+                        },
+                        "output_dir": {
+                            "type": "string",
+                            "description": "Output directory (default: a per-workspace directory under the server temp dir)"
+                        }
+                    },
+                    "required": ["name"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "assign_document_to_workspace".to_string(),
+                description: Some("Add a document to a workspace; omit workspace_id to remove it from its workspace".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "workspace_id": {
+                            "type": "string",
+                            "description": "ID of the workspace"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_workspaces".to_string(),
+                description: Some("List workspaces with their output directories".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_workspace_documents".to_string(),
+                description: Some("List the documents in a workspace, with the same summaries and paging as list_documents".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "workspace_id": {"type": "string", "description": "ID of the workspace"},
+                        "offset": {"type": "integer", "minimum": 0, "description": "Number of documents to skip"},
+                        "limit": {"type": "integer", "minimum": 1, "description": "Maximum documents to return (default: all)"},
+                        "title_contains": {"type": "string", "description": "Case-insensitive title substring"},
+                        "created_after": {"type": "string", "description": "RFC 3339 timestamp; only documents created at or after it"},
+                        "created_before": {"type": "string", "description": "RFC 3339 timestamp; only documents created before it"}
+                    },
+                    "required": ["workspace_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "convert_to_pdf".to_string(),
+                description: Some("Convert a DOCX document to PDF".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document to convert"
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Path where to save the PDF"
+                        },
+                        "prefer_external": {
+                            "type": "boolean",
+                            "description": "Prefer external hi-fidelity converter when available",
+                            "default": false
+                        },
+                        "subset_fonts": {
+                            "type": "boolean",
+                            "description": "Embed only the glyphs used from fallback fonts (much smaller PDFs for CJK text)",
+                            "default": true
+                        },
+                        "hyphenate": {
+                            "type": "boolean",
+                            "description": "Hyphenate words at line ends when hyphenation patterns for the language are installed",
+                            "default": true
+                        },
+                        "language": {
+                            "type": "string",
+                            "description": "Hyphenation language (e.g. en_US, de-DE); detected from the text by default"
+                        }
+                    },
+                    "required": ["document_id", "output_path"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "export_pdf_with_field_refresh".to_string(),
+                description: Some("Embed page fields then export to PDF (hi-fidelity when available)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "output_path": {"type": "string"},
+                        "prefer_external": {"type": "boolean", "default": true},
+                        "subset_fonts": {"type": "boolean", "default": true},
+                        "hyphenate": {"type": "boolean", "default": true},
+                        "language": {"type": "string"}
+                    },
+                    "required": ["document_id", "output_path"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "convert_to_images".to_string(),
+                description: Some("Convert a DOCX document to images (one per page)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document to convert"
+                        },
+                        "output_dir": {
+                            "type": "string",
+                            "description": "Directory where to save the images"
+                        },
+                        "format": {
+                            "type": "string",
+                            "description": "Image format",
+                            "enum": ["png", "jpg", "jpeg"],
+                            "default": "png"
+                        },
+                        "dpi": {
+                            "type": "integer",
+                            "description": "Resolution in DPI",
+                            "default": 150,
+                            "minimum": 72,
+                            "maximum": 600
+                        }
+                    },
+                    "required": ["document_id", "output_dir"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "convert_to_images_with_preference".to_string(),
+                description: Some("Convert DOCX to images, preferring external hi-fidelity path".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "output_dir": {"type": "string"},
+                        "format": {"type": "string", "enum": ["png", "jpg", "jpeg"], "default": "png"},
+                        "dpi": {"type": "integer", "default": 150},
+                        "prefer_external": {"type": "boolean", "default": true}
+                    },
+                    "required": ["document_id", "output_dir"]
+                }),
+                annotations: None,
+            },
+            // Advanced tools are gated and added only when feature is enabled
+            
+            #[cfg(feature = "advanced-docx")]
+            Tool {
+                name: "merge_documents".to_string(),
+                description: Some("Merge multiple DOCX documents into one".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_ids": {
+                            "type": "array",
+                            "description": "IDs of documents to merge",
+                            "items": {"type": "string"}
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Path where to save the merged document"
+                        }
+                    },
+                    "required": ["document_ids", "output_path"]
+                }),
+                annotations: None,
+            },
+            #[cfg(feature = "advanced-docx")]
+            Tool {
+                name: "split_document".to_string(),
+                description: Some("Split a document at page breaks".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document to split"
+                        },
+                        "output_dir": {
+                            "type": "string",
+                            "description": "Directory where to save the split documents"
+                        }
+                    },
+                    "required": ["document_id", "output_dir"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "get_document_structure".to_string(),
+                description: Some("Get the structural overview of the document: headings with levels, per-section paragraph/list/table/image counts, and lists, tables, images and links, each with its element_index. Works for opened documents too (read from the XML)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        }
                     },
                     "required": ["document_id"]
                 }),
@@ -736,7 +2139,7 @@ impl DocxToolsProvider {
             },
             Tool {
                 name: "get_outline".to_string(),
-                description: Some("Return heading outline with range_ids".to_string()),
+                description: Some("Return the heading outline (flat list and nested tree) with element ids and range_ids".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {"document_id": {"type": "string"}},
@@ -744,6 +2147,21 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "get_section_text".to_string(),
+                description: Some("Return only the content under a heading, so large documents can be processed section by section".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "heading": {"type": "string", "description": "Heading text"},
+                        "element_index": {"type": "integer", "description": "Heading element_index from get_outline (takes precedence over heading)"},
+                        "include_subsections": {"type": "boolean", "default": true}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "get_ranges".to_string(),
                 description: Some("Resolve a selector to range_ids (heading:'Text', paragraph[i], table[t].cell[r,c])".to_string()),
@@ -774,6 +2192,64 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "populate_table".to_string(),
+                description: Some("Replace a template row with one row per data record, keeping the table's widths, borders, shading and the row's merges".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "table_index": {"type": "integer"},
+                        "rows": {
+                            "type": "array",
+                            "items": {"type": ["object", "array"]},
+                            "description": "Records: objects fill {{field}} placeholders in the template row (or the column whose header names the field); arrays fill cells in order"
+                        },
+                        "template_row": {"type": "integer", "description": "Row to repeat (default: last row)"},
+                        "locale": {"type": "string", "description": "Locale for formatted values (e.g. de-DE)", "default": "en-US"},
+                        "currency": {"type": "string", "description": "Default ISO 4217 code for currency formats", "default": "USD"},
+                        "formats": {
+                            "type": "object",
+                            "additionalProperties": {"type": "string"},
+                            "description": "Field or column header to format: number[:decimals], integer, currency[:CODE], percent[:decimals], date[:short|long|iso]"
+                        }
+                    },
+                    "required": ["document_id", "table_index", "rows"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "table_to_list".to_string(),
+                description: Some("Replace a table with a list of one item per row, the row's cells joined by a separator (a two-column table becomes \"Term: definition\" items)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "table_index": {"type": "integer"},
+                        "ordered": {"type": "boolean", "description": "Numbered instead of bulleted (default false)"},
+                        "separator": {"type": "string", "description": "Text between the cells of a row (default \": \")"},
+                        "skip_header": {"type": "boolean", "description": "Leave out the first row (default: when the table has headers)"}
+                    },
+                    "required": ["document_id", "table_index"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "list_to_table".to_string(),
+                description: Some("Replace a list with a table, splitting each item into cells at a separator (\"Term: definition\" items become a two-column table)".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "element_index": {"type": "integer", "description": "Element index of the list, or of the first of a run of list items"},
+                        "separator": {"type": "string", "description": "Where to split items into cells (default \": \")"},
+                        "columns": {"type": "integer", "description": "Split into at most this many cells; the last keeps any further separators"},
+                        "headers": {"type": "array", "items": {"type": "string"}, "description": "Header row to add above the items"}
+                    },
+                    "required": ["document_id", "element_index"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "get_document_properties".to_string(),
                 description: Some("Get document properties (title, subject, author, timestamps)".to_string()),
@@ -813,6 +2289,92 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "insert_at".to_string(),
+                description: Some("Insert one or more elements before or after an anchor: a heading, a bookmark, an element index or a table".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "anchor": {
+                            "type": "object",
+                            "description": "Exactly one of heading (text), bookmark (name), element_id (element_index from get_outline) or table_index",
+                            "properties": {
+                                "heading": {"type": "string"},
+                                "bookmark": {"type": "string"},
+                                "element_id": {"type": "integer", "minimum": 0},
+                                "table_index": {"type": "integer", "minimum": 0}
+                            },
+                            "minProperties": 1,
+                            "maxProperties": 1
+                        },
+                        "position": {"type": "string", "enum": ["before", "after"], "description": "After a heading means directly below the heading line"},
+                        "elements": {
+                            "type": "array",
+                            "minItems": 1,
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "type": {"type": "string", "enum": ["paragraph", "heading", "list", "table", "page_break"]},
+                                    "text": {"type": "string", "description": "paragraph and heading"},
+                                    "style": {"type": "object", "description": "paragraph style as in add_paragraph"},
+                                    "level": {"type": "integer", "minimum": 1, "maximum": 6, "description": "heading"},
+                                    "items": {"type": "array", "items": {"type": "string"}, "description": "list"},
+                                    "ordered": {"type": "boolean", "description": "list"},
+                                    "rows": {"type": "array", "items": {"type": "array", "items": {"type": "string"}}, "description": "table"},
+                                    "headers": {"type": "array", "items": {"type": "string"}, "description": "table"}
+                                },
+                                "required": ["type"]
+                            }
+                        }
+                    },
+                    "required": ["document_id", "anchor", "position", "elements"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "create_outline".to_string(),
+                description: Some("Draft a document's structure in one call: headings nested as in the outline tree, a placeholder paragraph under each and optionally a table of contents. Fill the sections in with set_section_content".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "outline_tree": {
+                            "type": "array",
+                            "minItems": 1,
+                            "description": "Top-level headings (Heading 1); children are one level deeper, down to Heading 6. The tree from get_outline is accepted too",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "title": {"type": "string"},
+                                    "placeholder": {"type": "string", "description": "Paragraph under the heading (default \"[title]\", empty for none)"},
+                                    "children": {"type": "array", "items": {"type": "object"}, "description": "Subheadings of the same shape"}
+                                },
+                                "required": ["title"]
+                            }
+                        },
+                        "toc": {"type": "boolean", "default": false, "description": "Start with a table of contents (levels 1-3) and a page break"}
+                    },
+                    "required": ["document_id", "outline_tree"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "insert_document".to_string(),
+                description: Some("Insert the whole content of another document (a session document or a .docx/.md file) at an anchor, such as a standard appendix or boilerplate section".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string", "description": "Target document"},
+                        "source": {"type": "string", "description": "Document id from this session, or path of a .docx or .md file"},
+                        "anchor": {"type": "object", "description": "As in insert_at; the end of the document when omitted"},
+                        "position": {"type": "string", "enum": ["before", "after"], "default": "after"},
+                        "style_map": style_map_schema()
+                    },
+                    "required": ["document_id", "source"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "sanitize_external_links".to_string(),
                 description: Some("Remove external hyperlinks (http/https)".to_string()),
@@ -833,7 +2395,8 @@ impl DocxToolsProvider {
                         "pattern": {"type": "string"},
                         "use_regex": {"type": "boolean", "default": false},
                         "whole_word": {"type": "boolean", "default": false},
-                        "case_sensitive": {"type": "boolean", "default": false}
+                        "case_sensitive": {"type": "boolean", "default": false},
+                        "dry_run": {"type": "boolean", "default": false, "description": "List the matches that would be redacted, each with the text around it, without changing the document"}
                     },
                     "required": ["document_id", "pattern"]
                 }),
@@ -841,7 +2404,7 @@ impl DocxToolsProvider {
             },
             Tool {
                 name: "analyze_formatting".to_string(),
-                description: Some("Analyze the formatting used throughout the document".to_string()),
+                description: Some("Analyze the formatting used throughout the document: styles, fonts, sizes, colors, images, numbering and inconsistencies".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -854,9 +2417,22 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "lint_formatting".to_string(),
+                description: Some("Flag style inconsistencies (mixed body fonts, heading level jumps, list indent jumps, bold lines used as headings, repeated blank paragraphs); auto_fix normalizes them".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "auto_fix": {"type": "boolean", "default": false}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "get_word_count".to_string(),
-                description: Some("Get detailed word count statistics for the document".to_string()),
+                description: Some("Get detailed word count statistics: body, tables, headers/footers and footnotes counted separately, per-section counts, and the page count from the PDF layout".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -869,9 +2445,21 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "get_pagination".to_string(),
+                description: Some("Get the page count from the PDF layout and which element starts each page, so content can be located as 'on page N'".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "search_text".to_string(),
-                description: Some("Search for text patterns in the document".to_string()),
+                description: Some("Search for text or regex patterns; each match references the element (paragraph, heading, table cell, header/footer) that contains it".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -881,7 +2469,7 @@ impl DocxToolsProvider {
                         },
                         "search_term": {
                             "type": "string",
-                            "description": "Text to search for"
+                            "description": "Text (or regex when use_regex is set) to search for"
                         },
                         "case_sensitive": {
                             "type": "boolean",
@@ -892,34 +2480,196 @@ impl DocxToolsProvider {
                             "type": "boolean", 
                             "description": "Whether to match whole words only",
                             "default": false
+                        },
+                        "use_regex": {
+                            "type": "boolean",
+                            "description": "Treat search_term as a regular expression (capture groups are returned per match)",
+                            "default": false
+                        },
+                        "group_by_element": {
+                            "type": "boolean",
+                            "description": "Also return matches grouped per element",
+                            "default": false
+                        }
+                    },
+                    "required": ["document_id", "search_term"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "extract_patterned_items".to_string(),
+                description: Some("Extract patterned items such as requirement ids (FR-001, SEC-001) as records with their capture groups, element text and section headings".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "pattern": {
+                            "type": "string",
+                            "description": "Regex; named groups become record fields, e.g. \"(?P<id>(?P<type>FR|NFR|SEC)-\\d{3}):\\s*(?P<statement>.+)\""
+                        },
+                        "case_sensitive": {"type": "boolean", "description": "Match case (default true)"}
+                    },
+                    "required": ["document_id", "pattern"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "detect_languages".to_string(),
+                description: Some("Identify the language of each paragraph/cell and report per-language proportions".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        }
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "set_proofing_language".to_string(),
+                description: Some("Set the language Word spell-checks text in (w:lang) and/or exclude text from proofing (w:noProof), for the whole document, a range of elements or tagged regions".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "language": {"type": "string", "description": "Language tag such as en-GB, de-DE, ja-JP or ar-SA"},
+                        "no_proof": {"type": "boolean", "description": "Skip spelling and grammar checks (code, part numbers, names); default false"},
+                        "scope": {
+                            "type": "string",
+                            "enum": ["document", "elements", "tag"],
+                            "description": "document (default) sets the style defaults; elements uses start_element and end_element; tag marks every region tagged with tag"
+                        },
+                        "start_element": {"type": "integer", "description": "First element_index for the elements scope"},
+                        "end_element": {"type": "integer", "description": "Last element_index (inclusive); defaults to start_element"},
+                        "tag": {"type": "string", "description": "Tag name for the tag scope (see tag_content)"}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "analyze_content".to_string(),
+                description: Some("Structured overview: top keywords, entities (dates, money, organizations, ...) and a per-section summary skeleton".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "max_keywords": {"type": "integer", "default": 15}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            #[cfg(feature = "spellcheck")]
+            Tool {
+                name: "check_spelling".to_string(),
+                description: Some("Check spelling against a downloaded dictionary; returns misspellings with element references and suggestions".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "language": {"type": "string", "description": "Dictionary id: en_US, en_GB, de_DE, fr_FR, es_ES", "default": "en_US"},
+                        "ignore_words": {"type": "array", "items": {"type": "string"}, "description": "Extra words to accept (names, product terms)"},
+                        "max_suggestions": {"type": "integer", "default": 5}
+                    },
+                    "required": ["document_id"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "apply_correction".to_string(),
+                description: Some("Replace a single word inside one element, using the location reported by check_spelling or search_text".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "element_index": {"type": "integer"},
+                        "word": {"type": "string"},
+                        "replacement": {"type": "string"},
+                        "start": {"type": "integer", "description": "Character offset of the word within the element"},
+                        "item": {"type": "integer", "description": "List item position for list elements"},
+                        "range_id": {"type": "object", "description": "TableCell range_id for table cells"}
+                    },
+                    "required": ["document_id", "element_index", "word", "replacement"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "export_to_markdown".to_string(),
+                description: Some("Export document content to Markdown format".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Path where to save the Markdown file"
+                        }
+                    },
+                    "required": ["document_id", "output_path"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "export_to_html".to_string(),
+                description: Some("Export document content to HTML format".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {
+                            "type": "string",
+                            "description": "ID of the document"
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Path where to save the HTML file"
+                        },
+                        "self_contained": {
+                            "type": "boolean",
+                            "description": "Render the document's formatting, tables and images into one portable file: images inlined as data URIs, embedded CSS and page-break markers matching the PDF layout (default false)"
                         }
                     },
-                    "required": ["document_id", "search_term"]
+                    "required": ["document_id", "output_path"]
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "export_xliff".to_string(),
+                description: Some("Export the document's text as XLIFF 1.2, one translation unit per paragraph, heading, list item and table cell".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "source_language": {"type": "string", "description": "BCP-47 tag of the document language", "default": "en"},
+                        "target_language": {"type": "string", "description": "BCP-47 tag of the translation"},
+                        "output_path": {"type": "string", "description": "Write the XLIFF here instead of returning it"}
+                    },
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "export_to_markdown".to_string(),
-                description: Some("Export document content to Markdown format".to_string()),
+                name: "import_xliff".to_string(),
+                description: Some("Apply translated targets from an XLIFF file produced by export_xliff; element formatting is kept and units whose source text changed since the export are skipped".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "document_id": {
-                            "type": "string",
-                            "description": "ID of the document"
-                        },
-                        "output_path": {
-                            "type": "string",
-                            "description": "Path where to save the Markdown file"
-                        }
+                        "document_id": {"type": "string"},
+                        "path": {"type": "string", "description": "Path of the translated XLIFF file"}
                     },
-                    "required": ["document_id", "output_path"]
+                    "required": ["document_id", "path"]
                 }),
                 annotations: None,
             },
             Tool {
-                name: "export_to_html".to_string(),
-                description: Some("Export document content to HTML format".to_string()),
+                name: "generate_thumbnail".to_string(),
+                description: Some("Render a small PNG preview of the document's first page, returned as image content and optionally saved to disk".to_string()),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -927,12 +2677,18 @@ impl DocxToolsProvider {
                             "type": "string",
                             "description": "ID of the document"
                         },
+                        "width": {
+                            "type": "integer",
+                            "minimum": 16,
+                            "maximum": 1024,
+                            "description": "Thumbnail width in pixels; height follows the A4 page ratio (default 200)"
+                        },
                         "output_path": {
                             "type": "string",
-                            "description": "Path where to save the HTML file"
+                            "description": "Optional path to also write the PNG to"
                         }
                     },
-                    "required": ["document_id", "output_path"]
+                    "required": ["document_id"]
                 }),
                 annotations: None,
             },
@@ -946,6 +2702,34 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "reload_config".to_string(),
+                description: Some("Re-read the config file (--config) and apply its security profile, command lists, allowed paths and limits. Open documents stay open; the temp directory, fonts, webhooks and content directories change only on restart. The server also reloads on SIGHUP.".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "server_info".to_string(),
+                description: Some("Health check: server version, compiled features, active security profile and temp directory status".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "ping".to_string(),
+                description: Some("Liveness check; returns the same payload as server_info".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                annotations: None,
+            },
             Tool {
                 name: "get_storage_info".to_string(),
                 description: Some("Get information about temporary storage usage".to_string()),
@@ -956,48 +2740,189 @@ impl DocxToolsProvider {
                 }),
                 annotations: None,
             },
+            Tool {
+                name: "list_available_fonts".to_string(),
+                description: Some("List fonts for PDF output: the standard PDF fonts the built-in renderer uses, and installed TrueType/OpenType fonts that can be embedded".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                annotations: None,
+            },
+            Tool {
+                name: "embed_font".to_string(),
+                description: Some("Embed a TrueType/OpenType font in the document (word/fonts/*.odttf plus a fontTable.xml entry) so it renders on machines without the font installed".to_string()),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "document_id": {"type": "string"},
+                        "ttf_path": {"type": "string", "description": "Path to the .ttf/.otf file"},
+                        "font_name": {"type": "string", "description": "Family name to register; defaults to the name stored in the font"}
+                    },
+                    "required": ["document_id", "ttf_path"]
+                }),
+                annotations: None,
+            },
         ];
-        
-        // Filter tools based on security configuration
-        all_tools.retain(|tool| {
-            self.security_config.is_command_allowed(&tool.name)
-        });
-        
-        info!("Exposing {} tools (security filtered)", all_tools.len());
-        all_tools
+        tools.into_iter().map(|mut tool| {
+            tool.annotations = Some(crate::tool_hints::annotations_for(&tool.name));
+            tool
+        }).collect()
+    }
+
+    /// Rewrite a relative `output_path` of a workspace document into the workspace's
+    /// output directory
+    fn resolve_workspace_output(&self, mut arguments: Value) -> Value {
+        let (Some(doc_id), Some(output_path)) = (
+            arguments.get("document_id").and_then(|v| v.as_str()),
+            arguments.get("output_path").and_then(|v| v.as_str()),
+        ) else {
+            return arguments;
+        };
+        let resolved = self.handler.read().unwrap().resolve_output_path(doc_id, std::path::Path::new(output_path));
+        arguments["output_path"] = Value::String(resolved.to_string_lossy().into_owned());
+        arguments
     }
 
+    /// Run a tool. Each call gets a correlation id: it is a field of the `tool_call`
+    /// span the call's logs are recorded under, and `call_id` in the response.
     pub async fn call_tool(&self, name: &str, arguments: Value) -> CallToolResponse {
+        let call_id = uuid::Uuid::new_v4().simple().to_string();
+        let span = tracing::info_span!("tool_call", call_id = %call_id, tool = name);
+        let started = std::time::Instant::now();
+        let mut response = self.dispatch_tool(name, arguments).instrument(span.clone()).await;
+        // Legacy text responses leave is_error unset; their JSON carries "success"
+        let success = match response.is_error {
+            Some(is_error) => !is_error,
+            None => match response.content.first() {
+                Some(mcp_core::types::ToolResponseContent::Text(t)) => serde_json::from_str::<Value>(&t.text)
+                    .map(|v| v["success"] == true)
+                    .unwrap_or(false),
+                _ => false,
+            },
+        };
+        let elapsed = started.elapsed();
+        span.in_scope(|| info!(success, elapsed_ms = elapsed.as_millis() as u64, "tool call finished"));
+        self.metrics.record_call(name, success, elapsed);
+        if success {
+            self.claim_documents(&response);
+        }
+        with_call_id(&mut response, &call_id);
+        response
+    }
+
+    async fn dispatch_tool(&self, name: &str, arguments: Value) -> CallToolResponse {
         debug!("Calling tool: {} with arguments: {:?}", name, arguments);
         
         // Security check
-        if let Err(security_error) = self.security.check_command(name, &arguments) {
-            let err_json = json!({
-                "success": false,
-                "error": format!("Security check failed: {}", security_error),
+        let checked = self.security.read().unwrap().check_command(name, &arguments).and_then(|()| {
+            match self.client.as_ref().and_then(|scope| scope.restrictions.as_ref()) {
+                Some(restrictions) => restrictions.check_command(name, &arguments),
+                None => Ok(()),
+            }
+        });
+        if let Err(security_error) = checked {
+            self.metrics.record_rejection(&security_error);
+            return self.respond(ToolOutcome::Error {
+                code: ErrorCode::for_security(&security_error),
+                error: format!("Security check failed: {}", security_error),
+                hint: None,
             });
-            return CallToolResponse {
-                content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: err_json.to_string(), annotations: None })],
-                is_error: Some(true),
-                meta: None,
-            };
         }
-        
+        if let Some(refused) = self.check_client_access(name, &arguments) {
+            return self.respond(refused);
+        }
+
+        if let Some(schema) = TOOL_SCHEMAS.get(name) {
+            if let Err(problem) = crate::schema::validate(schema, &arguments) {
+                return self.respond(ToolOutcome::Error {
+                    code: ErrorCode::InvalidArgument,
+                    error: problem,
+                    hint: Some(format!("Check the input schema of '{}' in tools/list", name)),
+                });
+            }
+        }
+        let arguments = self.resolve_workspace_output(arguments);
+
+        // Writes to a locked document must carry the holder's token
+        let is_lock_tool = matches!(name, "acquire_lock" | "release_lock" | "get_lock_status");
+        if let Some(doc_id) = arguments.get("document_id").and_then(|v| v.as_str()) {
+            if !is_lock_tool && is_write_call(name, &arguments) {
+                let token = arguments.get("lock_token").and_then(|v| v.as_str());
+                if let Err(e) = self.locks.lock().unwrap().check(doc_id, token) {
+                    return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::DocumentLocked,
+                        error: e.to_string(),
+                        hint: Some("Pass the holder's lock_token, or wait for the lease to expire".into()),
+                    });
+                }
+            }
+        }
+
+        // Element fingerprints before a write, to record and report which elements it changed
+        let before = match arguments.get("document_id").and_then(|v| v.as_str()) {
+            Some(doc_id) if is_write_call(name, &arguments) => {
+                self.handler.read().unwrap().element_fingerprints(doc_id)
+            }
+            _ => None,
+        };
+
         let outcome = match name {
             "create_document" => {
+                let title = arguments.get("title").or_else(|| arguments.get("label")).and_then(|v| v.as_str());
                 let mut handler = self.handler.write().unwrap();
-                match handler.create_document() {
+                let created = handler.create_document().and_then(|doc_id| {
+                    if let Some(title) = title {
+                        handler.rename_document(&doc_id, title)?;
+                    }
+                    Ok(doc_id)
+                });
+                match created {
                     Ok(doc_id) => ToolOutcome::Created { document_id: doc_id, message: Some("Document created successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
+                }
+            },
+            "generate_invoice" => {
+                match serde_json::from_value::<crate::invoice::Invoice>(arguments.clone()) {
+                    Ok(invoice) => {
+                        let mut handler = self.handler.write().unwrap();
+                        match handler.generate_invoice(&invoice) {
+                            Ok((doc_id, totals)) => {
+                                let total = invoice.locale().map(|l| l.format_currency(totals.total, &invoice.currency)).unwrap_or_default();
+                                ToolOutcome::Created { document_id: doc_id, message: Some(format!("Invoice created, total {}", total)) }
+                            }
+                            Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                        }
+                    }
+                    Err(e) => ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("Invalid invoice: {}", e), hint: None },
                 }
             },
             
             "open_document" => {
                 let path = arguments["path"].as_str().unwrap_or("");
                 let mut handler = self.handler.write().unwrap();
-                match handler.open_document(&PathBuf::from(path)) {
+                let opened = match ObjectUri::parse(path) {
+                    Some(uri) => uri.and_then(|uri| handler.open_remote_document(&uri, self.security_config().max_document_size)),
+                    None => handler.open_document(&PathBuf::from(path)),
+                };
+                let editable = arguments.get("editable").and_then(|v| v.as_bool()).unwrap_or(false);
+                match opened {
+                    Ok(doc_id) if editable => match handler.make_editable(&doc_id) {
+                        Ok(fidelity) => ToolOutcome::Created {
+                            document_id: doc_id,
+                            message: Some(if fidelity.is_lossless() {
+                                format!("Document opened from {} for editing", path)
+                            } else {
+                                format!("Document opened from {} for editing; dropped: {}", path, serde_json::to_string(&fidelity.dropped).unwrap_or_default())
+                            }),
+                        },
+                        Err(e) => {
+                            let _ = handler.close_document(&doc_id);
+                            ToolOutcome::from_error(e, ErrorCode::InvalidArgument)
+                        }
+                    },
                     Ok(doc_id) => ToolOutcome::Created { document_id: doc_id, message: Some(format!("Document opened from {}", path)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
@@ -1012,7 +2937,7 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.add_paragraph(doc_id, text, style) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Paragraph added successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
@@ -1024,7 +2949,7 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.add_heading(doc_id, text, level) {
                     Ok(_) => ToolOutcome::Ok { message: Some(format!("Heading level {} added successfully", level)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
@@ -1042,7 +2967,7 @@ impl DocxToolsProvider {
                             })
                             .collect()
                     })
-                    .unwrap_or_else(Vec::new);
+                    .unwrap_or_default();
                 
                 let headers = arguments.get("headers")
                     .and_then(|h| h.as_array())
@@ -1068,6 +2993,16 @@ impl DocxToolsProvider {
                     }).collect()
                 });
 
+                let cell_styles = match arguments.get("cell_styles").cloned().map(serde_json::from_value::<Vec<crate::docx_handler::CellStyle>>) {
+                    Some(Err(e)) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid cell_styles: {}", e),
+                        hint: None,
+                    }),
+                    Some(Ok(styles)) => Some(styles),
+                    None => None,
+                };
+
                 let table_data = TableData {
                     rows,
                     headers,
@@ -1075,84 +3010,406 @@ impl DocxToolsProvider {
                     col_widths: arguments.get("col_widths").and_then(|v| v.as_array()).map(|arr| arr.iter().filter_map(|x| x.as_u64().map(|n| n as u32)).collect()),
                     merges,
                     cell_shading: arguments.get("cell_shading").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    cell_styles,
+                    repeat_header_row: arguments.get("repeat_header_row").and_then(|v| v.as_bool()),
+                    cant_split_rows: arguments.get("cant_split_rows").and_then(|v| v.as_bool()),
+                    table_layout: arguments.get("table_layout").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    width_percent: arguments.get("width_percent").and_then(|v| v.as_f64()).map(|n| n as f32),
+                    caption: arguments.get("caption").and_then(|v| v.as_str()).map(|s| s.to_string()),
                 };
                 
                 let mut handler = self.handler.write().unwrap();
                 match handler.add_table(doc_id, table_data) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Table added successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "add_table_from_xlsx" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let path = arguments["path"].as_str().unwrap_or("");
+                let sheet = arguments.get("sheet").and_then(|v| v.as_str());
+                let range = arguments.get("range").and_then(|v| v.as_str());
+                let header_row = arguments.get("header_row").and_then(|v| v.as_bool()).unwrap_or(true);
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_table_from_xlsx(doc_id, &PathBuf::from(path), sheet, range, header_row) {
+                    Ok(rows) => ToolOutcome::Ok { message: Some(format!("Table with {} rows imported from {}", rows, path)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
 
             "add_section_break" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                let page_size = arguments.get("page_size").and_then(|v| v.as_str());
-                let orientation = arguments.get("orientation").and_then(|v| v.as_str());
-                let margins = arguments.get("margins").and_then(|m| m.as_object()).map(|m| crate::docx_handler::MarginsSpec {
-                    top: m.get("top").and_then(|v| v.as_f64()).map(|v| v as f32),
-                    bottom: m.get("bottom").and_then(|v| v.as_f64()).map(|v| v as f32),
-                    left: m.get("left").and_then(|v| v.as_f64()).map(|v| v as f32),
-                    right: m.get("right").and_then(|v| v.as_f64()).map(|v| v as f32),
-                });
-
+                let page_size = arguments.get("page_size").and_then(|v| v.as_str());
+                let orientation = arguments.get("orientation").and_then(|v| v.as_str());
+                let margins = arguments.get("margins").and_then(|m| m.as_object()).map(|m| crate::docx_handler::MarginsSpec {
+                    top: m.get("top").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    bottom: m.get("bottom").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    left: m.get("left").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    right: m.get("right").and_then(|v| v.as_f64()).map(|v| v as f32),
+                });
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_section_break(doc_id, page_size, orientation, margins) {
+                    Ok(_) => ToolOutcome::Ok { message: Some("Section break added".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "add_watermark" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let section = arguments.get("section").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let content = match (arguments.get("text").and_then(|v| v.as_str()), arguments.get("image_base64").and_then(|v| v.as_str())) {
+                    (Some(text), None) => crate::docx_handler::WatermarkContent::Text {
+                        text: text.to_string(),
+                        font: arguments.get("font").and_then(|v| v.as_str()).map(String::from),
+                        color: arguments.get("color").and_then(|v| v.as_str()).map(String::from),
+                        opacity: arguments.get("opacity").and_then(|v| v.as_f64()).map(|v| v as f32),
+                        diagonal: match arguments.get("layout").and_then(|v| v.as_str()).unwrap_or("diagonal") {
+                            "diagonal" => true,
+                            "horizontal" => false,
+                            other => return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("Unknown layout '{}' (use diagonal or horizontal)", other), hint: None }),
+                        },
+                    },
+                    (None, Some(data)) => match base64::engine::general_purpose::STANDARD.decode(data) {
+                        Ok(data) => crate::docx_handler::WatermarkContent::Image {
+                            data,
+                            width: arguments.get("width").and_then(|v| v.as_f64()).map(|v| v as f32),
+                            washout: arguments.get("washout").and_then(|v| v.as_bool()).unwrap_or(true),
+                        },
+                        Err(e) => return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("invalid image_base64: {}", e), hint: None }),
+                    },
+                    _ => return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: "Give either text or image_base64".into(), hint: None }),
+                };
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_watermark(doc_id, crate::docx_handler::Watermark { content, section }) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(match section {
+                        Some(section) => format!("Watermark added to section {}", section),
+                        None => "Watermark added".into(),
+                    }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "remove_watermark" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let section = arguments.get("section").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let mut handler = self.handler.write().unwrap();
+                match handler.remove_watermark(doc_id, section) {
+                    Ok(true) => ToolOutcome::Ok { message: Some("Watermark removed".into()) },
+                    Ok(false) => ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: "No watermark to remove".into(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "set_page_setup" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let size = arguments.get("size").and_then(|v| v.as_str());
+                let orientation = arguments.get("orientation").and_then(|v| v.as_str());
+                let margins = arguments.get("margins").and_then(|m| m.as_object()).map(|m| crate::docx_handler::MarginsSpec {
+                    top: m.get("top").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    bottom: m.get("bottom").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    left: m.get("left").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    right: m.get("right").and_then(|v| v.as_f64()).map(|v| v as f32),
+                });
+                let gutter = arguments.get("gutter").and_then(|v| v.as_f64()).map(|v| v as f32);
+                let mirror = arguments.get("mirror").and_then(|v| v.as_bool());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.set_page_setup(doc_id, size, orientation, margins, gutter, mirror) {
+                    Ok(_) => ToolOutcome::Ok { message: Some("Page setup updated".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            
+            "add_list" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let items = arguments["items"].as_array()
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| v.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let ordered = arguments.get("ordered")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_list(doc_id, items, ordered) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(format!("{} list added successfully", if ordered { "Ordered" } else { "Unordered" })) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "add_list_item" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let text = arguments["text"].as_str().unwrap_or("");
+                let level = arguments.get("level").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                let ordered = arguments.get("ordered").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_list_item(doc_id, text, level, ordered) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(format!("List item (level {}) added", level)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            
+            "add_page_break" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_page_break(doc_id) {
+                    Ok(_) => ToolOutcome::Ok { message: Some("Page break added successfully".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "group_keep_together" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let (Some(start), Some(end)) = (
+                    arguments.get("start_element").and_then(|v| v.as_u64()),
+                    arguments.get("end_element").and_then(|v| v.as_u64()),
+                ) else {
+                    return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: "start_element and end_element are required".into(),
+                        hint: Some("Find element indexes with get_outline or search_text".into()),
+                    });
+                };
+                let mut handler = self.handler.write().unwrap();
+                match handler.group_keep_together(doc_id, start as usize, end as usize) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Keeping {} elements together", count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "fix_pagination" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let mut handler = self.handler.write().unwrap();
+                match handler.fix_pagination(doc_id) {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "add_horizontal_rule" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let style = arguments.get("style").and_then(|v| v.as_str());
+                let color = arguments.get("color").and_then(|v| v.as_str());
+                let size = arguments.get("size").and_then(|v| v.as_u64()).map(|v| v as u32);
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_horizontal_rule(doc_id, style, color, size) {
+                    Ok(_) => ToolOutcome::Ok { message: Some("Horizontal rule added".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "add_signature_block" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let parties = match serde_json::from_value::<Vec<crate::docx_handler::SignatureParty>>(arguments.get("parties").cloned().unwrap_or(Value::Null)) {
+                    Ok(parties) => parties,
+                    Err(e) => return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("Invalid parties: {}", e), hint: None }),
+                };
+                let options = crate::docx_handler::SignatureBlockOptions {
+                    date: arguments.get("date").and_then(|v| v.as_str()).map(String::from),
+                    initials: arguments.get("initials").and_then(|v| v.as_bool()).unwrap_or(false),
+                    initial_each_page: arguments.get("initial_each_page").and_then(|v| v.as_bool()).unwrap_or(false),
+                    anchors: arguments.get("anchors").and_then(|v| v.as_bool()).unwrap_or(false),
+                    anchor_format: arguments.get("anchor_format").and_then(|v| v.as_str()).map(String::from),
+                };
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.add_signature_block(doc_id, &parties, &options) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Signature block added for {} part{} ({} paragraphs)", parties.len(), if parties.len() == 1 { "y" } else { "ies" }, count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "insert_toc" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let from_level = arguments.get("from_level").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+                let to_level = arguments.get("to_level").and_then(|v| v.as_u64()).unwrap_or(3) as usize;
+                let right_align_dots = arguments.get("right_align_dots").and_then(|v| v.as_bool()).unwrap_or(true);
+                let mut handler = self.handler.write().unwrap();
+                match handler.insert_toc(doc_id, from_level, to_level, right_align_dots) {
+                    Ok(_) => ToolOutcome::Ok { message: Some("TOC placeholder inserted".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "enable_heading_numbering" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let scheme = arguments.get("scheme").and_then(|v| v.as_str()).unwrap_or("decimal");
+                let mut handler = self.handler.write().unwrap();
+                match handler.enable_heading_numbering(doc_id, scheme) {
+                    Ok(headings) => ToolOutcome::Ok { message: Some(format!("Heading numbering set to {} ({} headings)", scheme, headings)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "renumber_headings" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let mut handler = self.handler.write().unwrap();
+                match handler.renumber_headings(doc_id) {
+                    Ok(headings) => ToolOutcome::Metadata { metadata: json!({"headings": headings}) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "normalize_headings" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let case = match arguments.get("style").and_then(|v| v.as_str()).unwrap_or("keep") {
+                    "keep" => None,
+                    style => match crate::text_transform::Transform::parse(style, None) {
+                        Ok(t @ (crate::text_transform::Transform::TitleCase | crate::text_transform::Transform::SentenceCase)) => Some(t),
+                        _ => return self.respond(ToolOutcome::Error {
+                            code: ErrorCode::InvalidArgument,
+                            error: format!("Unknown style '{}'", style),
+                            hint: Some("Use title_case, sentence_case or keep".into()),
+                        }),
+                    },
+                };
+                let renumber = arguments.get("renumber").and_then(|v| v.as_bool()).unwrap_or(true);
+                let mut handler = self.handler.write().unwrap();
+                match handler.normalize_headings(doc_id, case, renumber) {
+                    Ok(changed) => ToolOutcome::Metadata { metadata: json!({"changed": changed.len(), "headings": changed}) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "move_section" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let heading = arguments["heading"].as_str().unwrap_or("");
+                let position = arguments["position"].as_str().unwrap_or("");
+                let target = arguments["target"].as_str().unwrap_or("");
+                let mut handler = self.handler.write().unwrap();
+                match handler.move_section(doc_id, heading, position, target) {
+                    Ok(moved) => ToolOutcome::Ok { message: Some(format!("Moved '{}' ({} elements) {} '{}'", heading, moved, position, target)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "delete_section" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let heading = arguments.get("heading").and_then(|v| v.as_str());
+                let element_index = arguments.get("element_index").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let mut handler = self.handler.write().unwrap();
+                match handler.delete_section(doc_id, heading, element_index) {
+                    Ok(removed) => ToolOutcome::Ok { message: Some(format!("Deleted section ({} elements)", removed)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "set_section_content" => {
+                use crate::docx_handler::NewElement;
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let heading = arguments.get("heading").and_then(|v| v.as_str());
+                let element_index = arguments.get("element_index").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let blocks: Vec<NewElement> = match serde_json::from_value(arguments["blocks"].clone()) {
+                    Ok(blocks) => blocks,
+                    Err(e) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid blocks: {}", e),
+                        hint: Some("Each block needs a type: paragraph, heading, list, table or page_break".into()),
+                    }),
+                };
+                let added = blocks.len();
+                let mut handler = self.handler.write().unwrap();
+                match handler.set_section_content(doc_id, heading, element_index, blocks) {
+                    Ok(removed) => ToolOutcome::Ok { message: Some(format!("Set section content ({} elements removed, {} added)", removed, added)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "delete_range" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let (Some(start), Some(end)) = (
+                    arguments.get("start_element").and_then(|v| v.as_u64()),
+                    arguments.get("end_element").and_then(|v| v.as_u64()),
+                ) else {
+                    return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: "start_element and end_element are required".into(),
+                        hint: None,
+                    });
+                };
                 let mut handler = self.handler.write().unwrap();
-                match handler.add_section_break(doc_id, page_size, orientation, margins) {
-                    Ok(_) => ToolOutcome::Ok { message: Some("Section break added".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                match handler.delete_range(doc_id, start as usize, end as usize) {
+                    Ok(removed) => ToolOutcome::Ok { message: Some(format!("Deleted {} elements", removed)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
-            
-            "add_list" => {
+            "insert_at" => {
+                use crate::docx_handler::{InsertAnchor, NewElement};
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                let items = arguments["items"].as_array()
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|v| v.as_str().map(String::from))
-                            .collect()
-                    })
-                    .unwrap_or_else(Vec::new);
-                let ordered = arguments.get("ordered")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                
+                let position = arguments["position"].as_str().unwrap_or("after");
+                let anchor: InsertAnchor = match serde_json::from_value(arguments["anchor"].clone()) {
+                    Ok(anchor) => anchor,
+                    Err(e) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid anchor: {}", e),
+                        hint: Some("Use one of {\"heading\": ...}, {\"bookmark\": ...}, {\"element_id\": n} or {\"table_index\": n}".into()),
+                    }),
+                };
+                let elements: Vec<NewElement> = match serde_json::from_value(arguments["elements"].clone()) {
+                    Ok(elements) => elements,
+                    Err(e) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid elements: {}", e),
+                        hint: Some("Each element needs a type: paragraph, heading, list, table or page_break".into()),
+                    }),
+                };
+                let count = elements.len();
                 let mut handler = self.handler.write().unwrap();
-                match handler.add_list(doc_id, items, ordered) {
-                    Ok(_) => ToolOutcome::Ok { message: Some(format!("{} list added successfully", if ordered { "Ordered" } else { "Unordered" })) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                match handler.insert_at(doc_id, &anchor, position, elements) {
+                    Ok(index) => ToolOutcome::Metadata { metadata: json!({"inserted": count, "element_index": index}) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
-
-            "add_list_item" => {
+            "create_outline" => {
+                use crate::docx_handler::OutlineNode;
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                let text = arguments["text"].as_str().unwrap_or("");
-                let level = arguments.get("level").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
-                let ordered = arguments.get("ordered").and_then(|v| v.as_bool()).unwrap_or(false);
-
+                let toc = arguments.get("toc").and_then(|v| v.as_bool()).unwrap_or(false);
+                let outline: Vec<OutlineNode> = match serde_json::from_value(arguments["outline_tree"].clone()) {
+                    Ok(outline) => outline,
+                    Err(e) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid outline_tree: {}", e),
+                        hint: Some("Use a list of {\"title\": ..., \"children\": [...]} headings".into()),
+                    }),
+                };
                 let mut handler = self.handler.write().unwrap();
-                match handler.add_list_item(doc_id, text, level, ordered) {
-                    Ok(_) => ToolOutcome::Ok { message: Some(format!("List item (level {}) added", level)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                match handler.create_outline(doc_id, &outline, toc) {
+                    Ok(headings) => ToolOutcome::Ok { message: Some(format!("Created an outline of {} headings", headings)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
-            
-            "add_page_break" => {
+            "insert_document" => {
+                use crate::docx_handler::InsertAnchor;
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                
+                let source = arguments["source"].as_str().unwrap_or("");
+                let position = arguments.get("position").and_then(|v| v.as_str()).unwrap_or("after");
+                let anchor: Option<InsertAnchor> = match arguments.get("anchor").filter(|a| !a.is_null()) {
+                    Some(anchor) => match serde_json::from_value(anchor.clone()) {
+                        Ok(anchor) => Some(anchor),
+                        Err(e) => return self.respond(ToolOutcome::Error {
+                            code: ErrorCode::InvalidArgument,
+                            error: format!("Invalid anchor: {}", e),
+                            hint: Some("Use one of {\"heading\": ...}, {\"bookmark\": ...}, {\"element_id\": n} or {\"table_index\": n}".into()),
+                        }),
+                    },
+                    None => None,
+                };
+                let style_map = match style_map_arg(&arguments) {
+                    Ok(map) => map,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::InvalidArgument)),
+                };
                 let mut handler = self.handler.write().unwrap();
-                match handler.add_page_break(doc_id) {
-                    Ok(_) => ToolOutcome::Ok { message: Some("Page break added successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                match handler.insert_document(doc_id, source, anchor.as_ref(), position, style_map.as_ref()) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Inserted {} elements from {}", count, source)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
-            "insert_toc" => {
+            "add_table_of_figures" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                let from_level = arguments.get("from_level").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
-                let to_level = arguments.get("to_level").and_then(|v| v.as_u64()).unwrap_or(3) as usize;
-                let right_align_dots = arguments.get("right_align_dots").and_then(|v| v.as_bool()).unwrap_or(true);
+                let kind = arguments.get("kind").and_then(|v| v.as_str()).unwrap_or("figures");
+                let static_list = arguments.get("static").and_then(|v| v.as_bool()).unwrap_or(false);
                 let mut handler = self.handler.write().unwrap();
-                match handler.insert_toc(doc_id, from_level, to_level, right_align_dots) {
-                    Ok(_) => ToolOutcome::Ok { message: Some("TOC placeholder inserted".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                match handler.add_table_of_figures(doc_id, kind, static_list) {
+                    Ok(0) => ToolOutcome::Ok { message: Some(format!("Added a table of {} field (Word fills it in on open)", kind)) },
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Added a list of {} entries", count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             "insert_bookmark_after_heading" => {
@@ -1162,30 +3419,38 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.insert_bookmark_after_heading(doc_id, heading_text, name) {
                     Ok(true) => ToolOutcome::Ok { message: Some("Bookmark inserted".into()) },
-                    Ok(false) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: "Heading not found".into(), hint: None },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Ok(false) => ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: "Heading not found".into(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
             "set_header" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
                 let text = arguments["text"].as_str().unwrap_or("");
+                let layout = match header_footer_layout(&arguments) {
+                    Ok(layout) => layout,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::InvalidArgument)),
+                };
                 
                 let mut handler = self.handler.write().unwrap();
-                match handler.set_header(doc_id, text) {
+                match handler.set_header_footer(doc_id, "header", text, layout) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Header set successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
             "set_footer" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
                 let text = arguments["text"].as_str().unwrap_or("");
+                let layout = match header_footer_layout(&arguments) {
+                    Ok(layout) => layout,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::InvalidArgument)),
+                };
                 
                 let mut handler = self.handler.write().unwrap();
-                match handler.set_footer(doc_id, text) {
+                match handler.set_header_footer(doc_id, "footer", text, layout) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Footer set successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             "set_page_numbering" => {
@@ -1195,7 +3460,7 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.set_page_numbering(doc_id, location, template) {
                     Ok(_) => ToolOutcome::Ok { message: Some(format!("Page numbering set in {}", location)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             "embed_page_number_fields" => {
@@ -1203,7 +3468,20 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 match handler.embed_page_number_fields(doc_id) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Embedded PAGE/NUMPAGES fields (best-effort)".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
+                }
+            },
+            "insert_field" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let field = arguments["field"].as_str().unwrap_or("");
+                let argument = arguments.get("argument").and_then(|v| v.as_str());
+                let location = arguments.get("location").and_then(|v| v.as_str()).unwrap_or("body");
+                let text_before = arguments.get("text_before").and_then(|v| v.as_str());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.insert_field(doc_id, field, argument, location, text_before) {
+                    Ok(instr) => ToolOutcome::Ok { message: Some(format!("Inserted field {} in {}", instr, location)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
 
@@ -1214,16 +3492,16 @@ impl DocxToolsProvider {
                 let height = arguments.get("height").and_then(|v| v.as_u64()).map(|v| v as u32);
                 let alt_text = arguments.get("alt_text").and_then(|v| v.as_str()).map(|s| s.to_string());
 
-                let image_data = match base64::decode(data_b64) {
+                let image_data = match base64::engine::general_purpose::STANDARD.decode(data_b64) {
                     Ok(bytes) => bytes,
-                    Err(e) => return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: format!("{{\"success\":false,\"error\":\"invalid base64: {}\"}}", e), annotations: None })], is_error: Some(true), meta: None },
+                    Err(e) => return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("invalid base64: {}", e), hint: None }),
                 };
 
                 let mut handler = self.handler.write().unwrap();
                 let image = crate::docx_handler::ImageData { data: image_data, width, height, alt_text };
                 match handler.add_image(doc_id, image) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Image added".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
 
@@ -1234,7 +3512,7 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.add_hyperlink(doc_id, text, url) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Hyperlink added".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
@@ -1246,22 +3524,177 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.find_and_replace(doc_id, find_text, replace_text) {
                     Ok(count) => ToolOutcome::Ok { message: Some(format!("Replaced {} occurrences", count)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "transform_text" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let scope = arguments["scope"].as_str().unwrap_or("");
+                let operation = arguments["operation"].as_str().unwrap_or("");
+                let max_length = arguments.get("max_length").and_then(|v| v.as_u64()).map(|v| v as usize);
+
+                match crate::text_transform::Transform::parse(operation, max_length) {
+                    Ok(transform) => {
+                        let mut handler = self.handler.write().unwrap();
+                        match handler.transform_text(doc_id, scope, transform) {
+                            Ok(count) => ToolOutcome::Ok { message: Some(format!("Transformed {} element(s)", count)) },
+                            Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                        }
+                    }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
 
             "find_and_replace_advanced" => {
+                use crate::docx_handler::{ReplaceOptions, ReplaceScope, ReplaceTarget};
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
                 let pattern = arguments["pattern"].as_str().unwrap_or("");
                 let replacement = arguments["replacement"].as_str().unwrap_or("");
                 let case_sensitive = arguments.get("case_sensitive").and_then(|v| v.as_bool()).unwrap_or(false);
                 let whole_word = arguments.get("whole_word").and_then(|v| v.as_bool()).unwrap_or(false);
                 let use_regex = arguments.get("use_regex").and_then(|v| v.as_bool()).unwrap_or(false);
+                let preserve_case = arguments.get("preserve_case").and_then(|v| v.as_bool()).unwrap_or(false);
+                let scope: ReplaceScope = match arguments.get("scope").filter(|s| !s.is_null()) {
+                    Some(scope) => match serde_json::from_value(scope.clone()) {
+                        Ok(scope) => scope,
+                        Err(e) => return self.respond(ToolOutcome::Error {
+                            code: ErrorCode::InvalidArgument,
+                            error: format!("Invalid scope: {}", e),
+                            hint: Some("Use \"all\", \"tables\", \"headers\", {\"heading\": ...} or {\"element_range\": {\"start\": n, \"end\": m}}".into()),
+                        }),
+                    },
+                    None => ReplaceScope::All,
+                };
+                let include: Vec<ReplaceTarget> = match arguments.get("include").filter(|i| !i.is_null()) {
+                    Some(include) => match serde_json::from_value(include.clone()) {
+                        Ok(include) => include,
+                        Err(e) => return self.respond(ToolOutcome::Error {
+                            code: ErrorCode::InvalidArgument,
+                            error: format!("Invalid include: {}", e),
+                            hint: Some("Use a list of \"hyperlink_urls\", \"alt_text\", \"bookmark_names\" and \"properties\"".into()),
+                        }),
+                    },
+                    None => Vec::new(),
+                };
+                let options = ReplaceOptions { case_sensitive, whole_word, use_regex, preserve_case, scope, include };
+
+                if arguments.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    let handler = self.handler.read().unwrap();
+                    match handler.preview_replacements(doc_id, pattern, replacement, &options) {
+                        Ok(replacements) => ToolOutcome::Metadata { metadata: dry_run_report(&replacements) },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    }
+                } else {
+                    let mut handler = self.handler.write().unwrap();
+                    match handler.find_and_replace_with(doc_id, pattern, replacement, &options) {
+                        Ok(count) => ToolOutcome::Ok { message: Some(format!("Replaced {} occurrences", count)) },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    }
+                }
+            },
+            "redact_text" => {
+                use crate::docx_handler::{ReplaceOptions, REDACTION_MARK};
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let pattern = arguments["pattern"].as_str().unwrap_or("");
+                let use_regex = arguments.get("use_regex").and_then(|v| v.as_bool()).unwrap_or(false);
+                let whole_word = arguments.get("whole_word").and_then(|v| v.as_bool()).unwrap_or(false);
+                let case_sensitive = arguments.get("case_sensitive").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                if arguments.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    let options = ReplaceOptions { case_sensitive, whole_word, use_regex, ..Default::default() };
+                    let handler = self.handler.read().unwrap();
+                    match handler.preview_replacements(doc_id, pattern, REDACTION_MARK, &options) {
+                        Ok(replacements) => ToolOutcome::Metadata { metadata: dry_run_report(&replacements) },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    }
+                } else {
+                    let mut handler = self.handler.write().unwrap();
+                    match handler.redact_text(doc_id, pattern, use_regex, whole_word, case_sensitive) {
+                        Ok(count) => ToolOutcome::Ok { message: Some(format!("Redacted {} occurrences", count)) },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    }
+                }
+            },
+            "apply_comment_suggestions" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let author = arguments.get("author").and_then(|v| v.as_str()).unwrap_or("docx-mcp");
+                let apply = match arguments.get("mode").and_then(|v| v.as_str()).unwrap_or("task_list") {
+                    "task_list" => false,
+                    "track_changes" => true,
+                    other => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Unknown mode '{}'", other),
+                        hint: Some("Use task_list or track_changes".into()),
+                    }),
+                };
 
                 let mut handler = self.handler.write().unwrap();
-                match handler.find_and_replace_advanced(doc_id, pattern, replacement, case_sensitive, whole_word, use_regex) {
-                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Replaced {} occurrences", count)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                match handler.apply_comment_suggestions(doc_id, apply, author) {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "render_template" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let data = arguments.get("data").cloned().unwrap_or_else(|| json!({}));
+
+                let mut handler = self.handler.write().unwrap();
+                let rendered = crate::locale::Formatting::from_args(&arguments)
+                    .and_then(|formatting| handler.render_template(doc_id, &data, &formatting));
+                match rendered {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "tag_content" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let tag = arguments["tag"].as_str().unwrap_or("");
+                let start = arguments["start_element"].as_u64().unwrap_or(0) as usize;
+                let end = arguments["end_element"].as_u64().map(|v| v as usize).unwrap_or(start);
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.tag_content(doc_id, tag, start, end) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(format!("Tagged elements {}-{} as {}", start, end, tag)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "list_tagged_content" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let handler = self.handler.read().unwrap();
+                match handler.list_tagged_content(doc_id) {
+                    Ok(regions) => ToolOutcome::Metadata { metadata: json!({"regions": regions}) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "strip_tagged_content" | "keep_only_tagged" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let tags: Vec<String> = arguments["tags"].as_array()
+                    .map(|a| a.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                    .unwrap_or_default();
+
+                let mut handler = self.handler.write().unwrap();
+                let result = if name == "strip_tagged_content" {
+                    handler.strip_tagged_content(doc_id, &tags)
+                } else {
+                    handler.keep_only_tagged(doc_id, &tags)
+                };
+                match result {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Removed {} tagged element(s)", count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "highlight_matches" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let pattern = arguments["pattern"].as_str().unwrap_or("");
+                let color = arguments.get("color").and_then(|v| v.as_str()).unwrap_or("yellow");
+                let case_sensitive = arguments.get("case_sensitive").and_then(|v| v.as_bool()).unwrap_or(false);
+                let whole_word = arguments.get("whole_word").and_then(|v| v.as_bool()).unwrap_or(false);
+                let use_regex = arguments.get("use_regex").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.highlight_matches(doc_id, pattern, color, case_sensitive, whole_word, use_regex) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Highlighted {} matches", count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             "apply_paragraph_format" => {
@@ -1277,21 +3710,58 @@ impl DocxToolsProvider {
                     color: fmt.get("color").and_then(|v| v.as_str()).map(|s| s.to_string()),
                     alignment: fmt.get("alignment").and_then(|v| v.as_str()).map(|s| s.to_string()),
                     line_spacing: fmt.get("line_spacing").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    direction: fmt.get("direction").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    highlight: fmt.get("highlight").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    strike: fmt.get("strike").and_then(|v| v.as_bool()),
+                    double_strike: fmt.get("double_strike").and_then(|v| v.as_bool()),
+                    vert_align: fmt.get("vert_align").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    caps: fmt.get("caps").and_then(|v| v.as_bool()),
+                    small_caps: fmt.get("small_caps").and_then(|v| v.as_bool()),
+                    character_spacing: fmt.get("character_spacing").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    underline_style: fmt.get("underline_style").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    borders: fmt.get("borders").and_then(|v| serde_json::from_value(v.clone()).ok()),
+                    shading: fmt.get("shading").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    hidden: fmt.get("hidden").and_then(|v| v.as_bool()),
                 };
                 let mut handler = self.handler.write().unwrap();
                 match handler.apply_paragraph_format(doc_id, contains, style) {
                     Ok(count) => ToolOutcome::Ok { message: Some(format!("Updated {} paragraph(s)", count)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
             "extract_text" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                
+                let flag = |name: &str| arguments.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+                let scope = crate::pure_converter::TextScope {
+                    headers: flag("include_headers"),
+                    footnotes: flag("include_footnotes"),
+                    comments: flag("include_comments"),
+                    hidden: flag("include_hidden"),
+                };
+
                 let handler = self.handler.read().unwrap();
-                match handler.extract_text(doc_id) {
+                match handler.extract_text_with_scope(doc_id, &scope) {
                     Ok(text) => ToolOutcome::Text { text },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "extract_formatted_text" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let format = arguments.get("format").and_then(|v| v.as_str()).unwrap_or("markdown");
+                if !matches!(format, "markdown" | "annotated_json") {
+                    return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Unknown format '{}'", format),
+                        hint: Some("Use markdown or annotated_json".to_string()),
+                    });
+                }
+
+                let handler = self.handler.read().unwrap();
+                match handler.extract_formatted_text(doc_id) {
+                    Ok(blocks) if format == "markdown" => ToolOutcome::Text { text: crate::docx_reader::render_markdown(&blocks) },
+                    Ok(blocks) => ToolOutcome::Metadata { metadata: json!({ "blocks": blocks }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
                 }
             },
             "get_tables" => {
@@ -1299,7 +3769,7 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 match handler.get_tables_json(doc_id) {
                     Ok(json) => ToolOutcome::Metadata { metadata: json },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
                 }
             },
             "list_images" => {
@@ -1307,69 +3777,407 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 match handler.list_images(doc_id) {
                     Ok(json) => ToolOutcome::Metadata { metadata: json },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "list_hyperlinks" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let handler = self.handler.read().unwrap();
+                match handler.list_hyperlinks(doc_id) {
+                    Ok(json) => ToolOutcome::Metadata { metadata: json },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "get_fields_summary" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let handler = self.handler.read().unwrap();
+                match handler.get_fields_summary(doc_id) {
+                    Ok(json) => ToolOutcome::Metadata { metadata: json },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "strip_personal_info" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let mut handler = self.handler.write().unwrap();
+                match handler.strip_personal_info(doc_id) {
+                    Ok(_) => ToolOutcome::Ok { message: Some("Personal info stripped".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
+                }
+            },
+            
+            "get_metadata" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                
+                let handler = self.handler.read().unwrap();
+                match handler.get_metadata(doc_id) {
+                    Ok(metadata) => ToolOutcome::Metadata { metadata: serde_json::to_value(metadata).unwrap() },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            
+            "save_document" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let output_path = arguments["output_path"].as_str().unwrap_or("");
+                
+                let mut handler = self.handler.write().unwrap();
+                let saved = match ObjectUri::parse(output_path) {
+                    Some(uri) => uri.and_then(|uri| handler.save_document_remote(doc_id, &uri)),
+                    None => handler.save_document(doc_id, &PathBuf::from(output_path)),
+                };
+                let hashed = saved.and_then(|_| {
+                    // Remote saves upload the working copy as is, so that is what gets hashed
+                    let path = match ObjectUri::parse(output_path) {
+                        Some(_) => handler.documents.get(doc_id).map(|m| m.path.clone()).unwrap_or_default(),
+                        None => PathBuf::from(output_path),
+                    };
+                    Ok((crate::docx_handler::file_sha256(&path)?, std::fs::metadata(&path)?.len()))
+                });
+                match hashed {
+                    Ok((sha256, size_bytes)) => ToolOutcome::Saved {
+                        message: Some(format!("Document saved to {}", output_path)),
+                        sha256,
+                        size_bytes,
+                    },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "verify_document" => {
+                let path = PathBuf::from(arguments["path"].as_str().unwrap_or(""));
+                let expected = arguments["expected_hash"].as_str().unwrap_or("");
+                match crate::docx_handler::verify_file_hash(&path, expected) {
+                    Ok(sha256) => ToolOutcome::Metadata { metadata: json!({
+                        "path": path,
+                        "sha256": sha256,
+                        "verified": true,
+                    }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            
+            "close_document" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                
+                let mut handler = self.handler.write().unwrap();
+                match handler.close_document(doc_id) {
+                    Ok(_) => {
+                        self.locks.lock().unwrap().remove(doc_id);
+                        ToolOutcome::Ok { message: Some("Document closed successfully".into()) }
+                    }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+
+            "rename_document" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let title = arguments["title"].as_str().unwrap_or("");
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.rename_document(doc_id, title) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(format!("Document renamed to {:?}", title.trim())) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+
+            "clone_document" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let title = arguments.get("title").and_then(|v| v.as_str());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.clone_document(doc_id, title) {
+                    Ok(new_id) => ToolOutcome::Created { document_id: new_id, message: Some(format!("Cloned document {}", doc_id)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "export_ops" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let output_path = arguments.get("output_path").and_then(|v| v.as_str());
+
+                let handler = self.handler.read().unwrap();
+                match handler.export_ops(doc_id) {
+                    Ok(journal) => match output_path {
+                        Some(path) => match std::fs::write(path, journal) {
+                            Ok(_) => ToolOutcome::Ok { message: Some(format!("Ops journal exported to {}", path)) },
+                            Err(e) => ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to save file: {}", e), hint: None },
+                        },
+                        None => ToolOutcome::Text { text: journal },
+                    },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "replay_ops" => {
+                let title = arguments.get("title").and_then(|v| v.as_str());
+                let journal = match (arguments.get("ops_json").and_then(|v| v.as_str()), arguments.get("path").and_then(|v| v.as_str())) {
+                    (Some(json), _) => json.to_string(),
+                    (None, Some(path)) => match std::fs::read_to_string(path) {
+                        Ok(json) => json,
+                        Err(e) => return self.respond(ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to read {}: {}", path, e), hint: None }),
+                    },
+                    (None, None) => return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: "Provide ops_json or path".into(), hint: None }),
+                };
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.replay_ops(&journal, title) {
+                    Ok(new_id) => ToolOutcome::Created { document_id: new_id, message: Some("Replayed ops journal".into()) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "snapshot_document" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let label = arguments.get("label").and_then(|v| v.as_str());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.snapshot_document(doc_id, label) {
+                    Ok(snapshot) => ToolOutcome::Metadata { metadata: snapshot },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "summarize_changes" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let since = arguments["since_snapshot"].as_str().unwrap_or("");
+
+                let handler = self.handler.read().unwrap();
+                match handler.summarize_changes(doc_id, since) {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "copy_elements" => {
+                let source_id = arguments["source_document_id"].as_str().unwrap_or("");
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let start = arguments["start_element"].as_u64().unwrap_or(0) as usize;
+                let end = arguments["end_element"].as_u64().map(|v| v as usize).unwrap_or(start);
+                let position = arguments.get("position").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let style_map = match style_map_arg(&arguments) {
+                    Ok(map) => map,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::InvalidArgument)),
+                };
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.copy_elements(source_id, start, end, doc_id, position, style_map.as_ref()) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Copied {} elements from {}", count, source_id)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "assemble_document" => {
+                let parts: Vec<String> = arguments.get("parts").and_then(|v| v.as_array())
+                    .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                    .unwrap_or_default();
+                let title = arguments.get("title").and_then(|v| v.as_str());
+                let toc = arguments.get("toc").and_then(|v| v.as_bool()).unwrap_or(true);
+                let continuous_numbering = arguments.get("continuous_numbering").and_then(|v| v.as_bool()).unwrap_or(false);
+                let style_map = match style_map_arg(&arguments) {
+                    Ok(map) => map,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::InvalidArgument)),
+                };
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.assemble_document(&parts, title, toc, continuous_numbering, style_map.as_ref()) {
+                    Ok(doc_id) => ToolOutcome::Created { document_id: doc_id, message: Some(format!("Assembled {} parts", parts.len())) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "list_clauses" => match self.clauses.as_ref().map(|library| library.list()) {
+                Some(Ok(clauses)) => ToolOutcome::Metadata { metadata: json!({"clauses": clauses}) },
+                Some(Err(e)) => ToolOutcome::from_error(e, ErrorCode::IoError),
+                None => no_clause_library(),
+            },
+            "insert_clause" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let name = arguments["name"].as_str().unwrap_or("");
+                let position = arguments.get("position").and_then(|v| v.as_u64()).map(|v| v as usize);
+                match self.clauses.as_ref().map(|library| library.resolve(name)) {
+                    Some(Ok(path)) => {
+                        let mut handler = self.handler.write().unwrap();
+                        match handler.insert_fragment(doc_id, &path, position) {
+                            Ok(count) => ToolOutcome::Ok { message: Some(format!("Inserted clause {} ({} elements)", name, count)) },
+                            Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                        }
+                    }
+                    Some(Err(e)) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    None => no_clause_library(),
+                }
+            },
+            "list_letterheads" => match self.letterheads.as_ref().map(|profiles| profiles.load()) {
+                Some(Ok(profiles)) => ToolOutcome::Metadata { metadata: json!({"letterheads": profiles}) },
+                Some(Err(e)) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                None => no_letterheads(),
+            },
+            "apply_letterhead" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let profile = arguments["profile"].as_str().unwrap_or("");
+                match self.letterheads.as_ref().map(|profiles| profiles.resolve(profile)) {
+                    Some(Ok(letterhead)) => {
+                        let mut handler = self.handler.write().unwrap();
+                        match handler.apply_letterhead(doc_id, &letterhead) {
+                            Ok(()) => ToolOutcome::Ok { message: Some(format!("Applied letterhead {}", profile)) },
+                            Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                        }
+                    }
+                    Some(Err(e)) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    None => no_letterheads(),
+                }
+            },
+            "rebrand" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let mapping: crate::rebrand::BrandMapping = match serde_json::from_value(arguments["mapping"].clone()) {
+                    Ok(mapping) => mapping,
+                    Err(e) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid mapping: {}", e),
+                        hint: Some("Use an object with any of fonts, default_font, colors, header, footer, logo_base64 and terms".into()),
+                    }),
+                };
+                let mut handler = self.handler.write().unwrap();
+                match handler.rebrand(doc_id, &mapping) {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "acquire_lock" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let lease = arguments.get("lease_seconds").and_then(|v| v.as_i64()).unwrap_or(60);
+                let owner = arguments.get("owner").and_then(|v| v.as_str());
+                let token = arguments.get("lock_token").and_then(|v| v.as_str());
+
+                if let Err(e) = self.handler.read().unwrap().get_metadata(doc_id) {
+                    return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound));
+                }
+                match self.locks.lock().unwrap().acquire(doc_id, owner, chrono::Duration::seconds(lease), token) {
+                    Ok(lock) => ToolOutcome::Metadata { metadata: serde_json::to_value(lock).unwrap() },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocumentLocked),
                 }
             },
-            "list_hyperlinks" => {
+
+            "release_lock" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                let handler = self.handler.read().unwrap();
-                match handler.list_hyperlinks(doc_id) {
-                    Ok(json) => ToolOutcome::Metadata { metadata: json },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                let token = arguments["lock_token"].as_str().unwrap_or("");
+                match self.locks.lock().unwrap().release(doc_id, token) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(format!("Lock on {} released", doc_id)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocumentLocked),
                 }
             },
-            "get_fields_summary" => {
+
+            "get_lock_status" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let status = match self.locks.lock().unwrap().status(doc_id) {
+                    Some(lock) => json!({"locked": true, "owner": lock.owner, "expires_at": lock.expires_at}),
+                    None => json!({"locked": false}),
+                };
+                ToolOutcome::Metadata { metadata: status }
+            },
+            
+            "list_documents" | "list_workspace_documents" | "list_all_documents" => {
+                fn timestamp(arguments: &Value, key: &str) -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+                    match arguments.get(key).and_then(|v| v.as_str()) {
+                        Some(s) => chrono::DateTime::parse_from_rfc3339(s)
+                            .map(|t| Some(t.with_timezone(&chrono::Utc)))
+                            .map_err(|e| format!("{} must be an RFC 3339 timestamp: {}", key, e)),
+                        None => Ok(None),
+                    }
+                }
+                let filter = match (timestamp(&arguments, "created_after"), timestamp(&arguments, "created_before")) {
+                    (Ok(created_after), Ok(created_before)) => DocumentFilter {
+                        title_contains: arguments.get("title_contains").and_then(|v| v.as_str()).map(String::from),
+                        created_after,
+                        created_before,
+                        workspace: arguments.get("workspace_id").and_then(|v| v.as_str()).map(String::from),
+                        // A scoped client lists its own documents; operators can list anyone's
+                        owner: match (name, &self.client) {
+                            ("list_all_documents", _) => arguments.get("client_id").and_then(|v| v.as_str()).map(String::from),
+                            (_, Some(scope)) => Some(scope.id.clone()),
+                            (_, None) => None,
+                        },
+                    },
+                    (Err(e), _) | (_, Err(e)) => {
+                        return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: e, hint: None });
+                    }
+                };
+                let offset = arguments.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                let limit = arguments.get("limit").and_then(|v| v.as_u64()).map(|l| l as usize);
+
                 let handler = self.handler.read().unwrap();
-                match handler.get_fields_summary(doc_id) {
-                    Ok(json) => ToolOutcome::Metadata { metadata: json },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                if let Some(ws) = &filter.workspace {
+                    if let Err(e) = handler.get_workspace(ws) {
+                        return self.respond(ToolOutcome::from_error(e, ErrorCode::InvalidArgument));
+                    }
+                }
+                match handler.document_summaries(&filter, offset, limit) {
+                    Ok((total, documents)) => {
+                        let next = offset + documents.len();
+                        ToolOutcome::Documents {
+                            documents: Value::Array(documents),
+                            total: Some(total),
+                            next_offset: (next < total).then_some(next),
+                        }
+                    }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
                 }
             },
-            "strip_personal_info" => {
-                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+
+            "create_workspace" => {
+                let name = arguments["name"].as_str().unwrap_or("");
+                let output_dir = arguments.get("output_dir").and_then(|v| v.as_str()).map(PathBuf::from);
+
                 let mut handler = self.handler.write().unwrap();
-                match handler.strip_personal_info(doc_id) {
-                    Ok(_) => ToolOutcome::Ok { message: Some("Personal info stripped".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: None },
+                match handler.create_workspace(name, output_dir.as_deref()).and_then(|id| handler.get_workspace(&id)) {
+                    Ok(ws) => ToolOutcome::Metadata { metadata: serde_json::to_value(ws).unwrap() },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::IoError),
                 }
             },
-            
-            "get_metadata" => {
+
+            "assign_document_to_workspace" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                
-                let handler = self.handler.read().unwrap();
-                match handler.get_metadata(doc_id) {
-                    Ok(metadata) => ToolOutcome::Metadata { metadata: serde_json::to_value(metadata).unwrap() },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                let workspace_id = arguments.get("workspace_id").and_then(|v| v.as_str());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.assign_document_to_workspace(doc_id, workspace_id) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(match workspace_id {
+                        Some(ws) => format!("Document {} assigned to workspace {}", doc_id, ws),
+                        None => format!("Document {} removed from its workspace", doc_id),
+                    }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
-            
-            "save_document" => {
+            "transfer_document" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                let output_path = arguments["output_path"].as_str().unwrap_or("");
-                
-                let handler = self.handler.read().unwrap();
-                match handler.save_document(doc_id, &PathBuf::from(output_path)) {
-                    Ok(_) => ToolOutcome::Ok { message: Some(format!("Document saved to {}", output_path)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                let client_id = arguments.get("client_id").and_then(|v| v.as_str());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.set_document_owner(doc_id, client_id) {
+                    Ok(()) => ToolOutcome::Ok { message: Some(match client_id {
+                        Some(client) => format!("Document {} now belongs to client {}", doc_id, client),
+                        None => format!("Document {} now belongs to the local operator", doc_id),
+                    }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
                 }
             },
-            
-            "close_document" => {
-                let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                
-                let mut handler = self.handler.write().unwrap();
-                match handler.close_document(doc_id) {
-                    Ok(_) => ToolOutcome::Ok { message: Some("Document closed successfully".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+            "create_viewer" | "revoke_viewer" => {
+                let Some(scope) = &self.client else {
+                    return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: "Viewers mirror an HTTP session; this connection has none".into(),
+                        hint: Some("Run the server with --http".into()),
+                    });
+                };
+                let mut viewers = self.viewers.lock().unwrap();
+                if name == "create_viewer" {
+                    let viewer_key = format!("view-{}", uuid::Uuid::new_v4().simple());
+                    viewers.insert(viewer_key.clone(), scope.id.clone());
+                    info!("Client {} created a viewer", scope.id);
+                    ToolOutcome::Metadata { metadata: json!({"viewer_key": viewer_key, "endpoint": "/viewer"}) }
+                } else {
+                    let key = arguments.get("viewer_key").and_then(|v| v.as_str());
+                    let before = viewers.len();
+                    viewers.retain(|k, owner| owner != &scope.id || key.is_some_and(|key| key != k.as_str()));
+                    ToolOutcome::Ok { message: Some(format!("Revoked {} viewer keys", before - viewers.len())) }
                 }
             },
-            
-            "list_documents" => {
+
+            "list_workspaces" => {
                 let handler = self.handler.read().unwrap();
-                let documents = handler.list_documents();
-                ToolOutcome::Documents { documents: serde_json::to_value(documents).unwrap() }
+                ToolOutcome::Metadata { metadata: json!({"workspaces": handler.list_workspaces()}) }
             },
             
             "convert_to_pdf" => {
@@ -1380,12 +4188,13 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 let metadata = match handler.get_metadata(doc_id) {
                     Ok(m) => m,
-                    Err(e) => return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: e.to_string(), annotations: None })], is_error: Some(true), meta: None },
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound)),
                 };
                 
-                match if prefer_external { self.converter.docx_to_pdf_with_preference(&metadata.path, &PathBuf::from(output_path), true) } else { self.converter.docx_to_pdf(&metadata.path, &PathBuf::from(output_path)) } {
+                let prefer_external = prefer_external || self.converter.prefers_external_tools();
+                match self.converter.docx_to_pdf_with_options(&metadata.path, &PathBuf::from(output_path), prefer_external, &pdf_options(&arguments)) {
                     Ok(_) => ToolOutcome::Ok { message: Some(format!("Document converted to PDF at {}", output_path)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
                 }
             },
             
@@ -1398,25 +4207,22 @@ impl DocxToolsProvider {
                 {
                     let handler = self.handler.read().unwrap();
                     if let Err(e) = handler.embed_page_number_fields(doc_id) {
-                        return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: serde_json::json!({"success": false, "error": e.to_string()}).to_string(), annotations: None })], is_error: Some(true), meta: None };
+                        return self.respond(ToolOutcome::from_error(e, ErrorCode::InternalError));
                     }
                 }
 
                 let handler = self.handler.read().unwrap();
                 let metadata = match handler.get_metadata(doc_id) {
                     Ok(m) => m,
-                    Err(e) => return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: serde_json::json!({"success": false, "error": e.to_string()}).to_string(), annotations: None })], is_error: Some(true), meta: None },
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound)),
                 };
 
-                let result = if prefer_external {
-                    self.converter.docx_to_pdf_with_preference(&metadata.path, &PathBuf::from(output_path), true)
-                } else {
-                    self.converter.docx_to_pdf(&metadata.path, &PathBuf::from(output_path))
-                };
+                let prefer_external = prefer_external || self.converter.prefers_external_tools();
+                let result = self.converter.docx_to_pdf_with_options(&metadata.path, &PathBuf::from(output_path), prefer_external, &pdf_options(&arguments));
 
                 match result {
                     Ok(_) => ToolOutcome::Ok { message: Some(format!("PDF exported with field refresh at {}", output_path)) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: Some("Install LibreOffice or unoconv for hi-fidelity refresh".to_string()) },
+                    Err(e) => ToolOutcome::Error { code: ErrorCode::classify(&e, ErrorCode::InternalError), error: e.to_string(), hint: Some("Install LibreOffice or unoconv for hi-fidelity refresh".to_string()) },
                 }
             },
 
@@ -1433,7 +4239,7 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 let metadata = match handler.get_metadata(doc_id) {
                     Ok(m) => m,
-                    Err(e) => return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: e.to_string(), annotations: None })], is_error: Some(true), meta: None },
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound)),
                 };
                 
                 let image_format = match format {
@@ -1449,7 +4255,7 @@ impl DocxToolsProvider {
                     dpi
                 ) {
                     Ok(images) => ToolOutcome::Images { images: images.iter().map(|p| p.to_string_lossy().to_string()).collect(), message: Some(format!("Document converted to {} images", images.len())) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
                 }
             },
 
@@ -1463,7 +4269,7 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 let metadata = match handler.get_metadata(doc_id) {
                     Ok(m) => m,
-                    Err(e) => return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "text".into(), text: e.to_string(), annotations: None })], is_error: Some(true), meta: None },
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound)),
                 };
 
                 let image_format = match format {
@@ -1480,7 +4286,7 @@ impl DocxToolsProvider {
                     prefer_external,
                 ) {
                     Ok(images) => ToolOutcome::Images { images: images.iter().map(|p| p.to_string_lossy().to_string()).collect(), message: Some(format!("Document converted to {} images", images.len())) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: Some("Install LibreOffice/ImageMagick for hi-fidelity path".to_string()) },
+                    Err(e) => ToolOutcome::Error { code: ErrorCode::classify(&e, ErrorCode::InternalError), error: e.to_string(), hint: Some("Install LibreOffice/ImageMagick for hi-fidelity path".to_string()) },
                 }
             },
             
@@ -1489,7 +4295,7 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 match handler.analyze_structure(doc_id) {
                     Ok(summary) => ToolOutcome::Metadata { metadata: summary },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound)
                 }
             },
             "get_outline" => {
@@ -1497,7 +4303,18 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 match handler.get_outline(doc_id) {
                     Ok(outline) => ToolOutcome::Metadata { metadata: outline },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "get_section_text" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let heading = arguments.get("heading").and_then(|v| v.as_str());
+                let element_index = arguments.get("element_index").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let include_subsections = arguments.get("include_subsections").and_then(|v| v.as_bool()).unwrap_or(true);
+                let handler = self.handler.read().unwrap();
+                match handler.get_section_text(doc_id, heading, element_index, include_subsections) {
+                    Ok(section) => ToolOutcome::Metadata { metadata: section },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             "get_ranges" => {
@@ -1506,7 +4323,7 @@ impl DocxToolsProvider {
                 let handler = self.handler.read().unwrap();
                 match handler.get_ranges(doc_id, selector) {
                     Ok(ranges) => ToolOutcome::Metadata { metadata: serde_json::json!({"ranges": ranges}) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
                 }
             },
             "replace_range_text" => {
@@ -1516,13 +4333,13 @@ impl DocxToolsProvider {
                 let range: crate::docx_handler::RangeId = match serde_json::from_value(range_id) {
                     Ok(v) => v,
                     Err(e) => {
-                        return CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "application/json".into(), text: serde_json::json!({"success": false, "code": ErrorCode::ValidationError, "error": format!("invalid range_id: {}", e)}).to_string(), annotations: None })], is_error: Some(true), meta: None };
+                        return self.respond(ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("invalid range_id: {}", e), hint: None });
                     }
                 };
                 let mut handler = self.handler.write().unwrap();
                 match handler.replace_range_text(doc_id, &range, text) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Range text replaced".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             "set_table_cell_text" => {
@@ -1534,31 +4351,76 @@ impl DocxToolsProvider {
                 let mut handler = self.handler.write().unwrap();
                 match handler.set_table_cell_text(doc_id, ti, r, c, text) {
                     Ok(_) => ToolOutcome::Ok { message: Some("Table cell updated".into()) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::ValidationError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "populate_table" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let ti = arguments["table_index"].as_u64().unwrap_or(0) as usize;
+                let template_row = arguments.get("template_row").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let records = arguments["rows"].as_array().cloned().unwrap_or_default();
+                let mut handler = self.handler.write().unwrap();
+                let populated = crate::locale::Formatting::from_args(&arguments)
+                    .and_then(|formatting| handler.populate_table(doc_id, ti, template_row, &records, &formatting));
+                match populated {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Added {} row(s)", count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "table_to_list" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let ti = arguments["table_index"].as_u64().unwrap_or(0) as usize;
+                let ordered = arguments.get("ordered").and_then(|v| v.as_bool()).unwrap_or(false);
+                let separator = arguments.get("separator").and_then(|v| v.as_str()).unwrap_or(": ");
+                let skip_header = arguments.get("skip_header").and_then(|v| v.as_bool());
+                let mut handler = self.handler.write().unwrap();
+                match handler.table_to_list(doc_id, ti, ordered, separator, skip_header) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Converted table {} to a list of {} item(s)", ti, count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "list_to_table" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let element_index = arguments["element_index"].as_u64().unwrap_or(0) as usize;
+                let separator = arguments.get("separator").and_then(|v| v.as_str()).unwrap_or(": ");
+                let columns = arguments.get("columns").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let headers = arguments.get("headers").and_then(|v| v.as_array())
+                    .map(|h| h.iter().filter_map(|v| v.as_str().map(String::from)).collect());
+                let mut handler = self.handler.write().unwrap();
+                match handler.list_to_table(doc_id, element_index, separator, columns, headers) {
+                    Ok(count) => ToolOutcome::Ok { message: Some(format!("Converted the list to a table of {} row(s)", count)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
             "analyze_formatting" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
-                
-                // For now, return basic analysis - in full implementation would parse DOCX XML
-                ToolOutcome::Metadata { metadata: serde_json::json!({
-                    "styles_used": ["Normal", "Heading1", "Heading2"],
-                    "fonts_detected": ["Calibri", "Arial"],
-                    "has_tables": true,
-                    "has_images": false,
-                    "has_hyperlinks": false,
-                    "page_count": 1,
-                    "section_count": 1
-                }) }
+                let handler = self.handler.read().unwrap();
+                match handler.analyze_formatting(doc_id) {
+                    Ok(analysis) => ToolOutcome::Metadata { metadata: analysis },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "lint_formatting" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let auto_fix = arguments.get("auto_fix").and_then(|v| v.as_bool()).unwrap_or(false);
+                if auto_fix && self.is_readonly() {
+                    ToolOutcome::Error { code: ErrorCode::SecurityBlocked, error: "auto_fix modifies the document and is not allowed by the current security settings".into(), hint: Some("Run without auto_fix".into()) }
+                } else {
+                    let mut handler = self.handler.write().unwrap();
+                    match handler.lint_formatting(doc_id, auto_fix) {
+                        Ok(report) => ToolOutcome::Metadata { metadata: report },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    }
+                }
             },
             
             "get_word_count" => {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
                 
                 let handler = self.handler.read().unwrap();
-                match handler.extract_text(doc_id) {
-                    Ok(text) => {
+                match handler.extract_text(doc_id).and_then(|text| Ok((handler.word_count_report(doc_id)?, text))) {
+                    Ok((report, text)) => {
                         let words: Vec<&str> = text.split_whitespace().collect();
                         let characters = text.chars().count();
                         let characters_no_spaces = text.chars().filter(|c| !c.is_whitespace()).count();
@@ -1571,11 +4433,24 @@ impl DocxToolsProvider {
                             "characters_no_spaces": characters_no_spaces,
                             "paragraphs": paragraphs,
                             "sentences": sentences,
-                            "pages": ((words.len() as f32 / 250.0).ceil() as usize).max(1),
-                            "reading_time_minutes": (words.len() as f32 / 200.0).ceil() as usize
+                            "pages": report["pages"],
+                            "lines": report["lines"],
+                            "reading_time_minutes": (words.len() as f32 / 200.0).ceil() as usize,
+                            "main_text_words": report["main_text_words"],
+                            "words_including_notes": report["words_including_notes"],
+                            "breakdown": report["breakdown"],
+                            "sections": report["sections"]
                         }) }
                     }
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound)
+                }
+            },
+            "get_pagination" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let handler = self.handler.read().unwrap();
+                match handler.get_pagination(doc_id) {
+                    Ok(pagination) => ToolOutcome::Metadata { metadata: pagination },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
                 }
             },
             
@@ -1583,40 +4458,152 @@ impl DocxToolsProvider {
                 let doc_id = arguments["document_id"].as_str().unwrap_or("");
                 let search_term = arguments["search_term"].as_str().unwrap_or("");
                 let case_sensitive = arguments.get("case_sensitive").and_then(|v| v.as_bool()).unwrap_or(false);
-                let _whole_word = arguments.get("whole_word").and_then(|v| v.as_bool()).unwrap_or(false);
+                let whole_word = arguments.get("whole_word").and_then(|v| v.as_bool()).unwrap_or(false);
+                let use_regex = arguments.get("use_regex").and_then(|v| v.as_bool()).unwrap_or(false);
+                let group_by_element = arguments.get("group_by_element").and_then(|v| v.as_bool()).unwrap_or(false);
                 
                 let handler = self.handler.read().unwrap();
-                match handler.extract_text(doc_id) {
-                    Ok(text) => {
-                        let search_text = if case_sensitive { text.clone() } else { text.to_lowercase() };
-                        let search_for = if case_sensitive { search_term.to_string() } else { search_term.to_lowercase() };
-                        
-                        let mut matches = Vec::new();
-                        let mut position = 0;
-                        
-                        while let Some(found_pos) = search_text[position..].find(&search_for) {
-                            let absolute_pos = position + found_pos;
-                            
-                            // Extract context around the match
-                            let context_start = absolute_pos.saturating_sub(50);
-                            let context_end = (absolute_pos + search_for.len() + 50).min(text.len());
-                            let context = &text[context_start..context_end];
-                            
-                            matches.push(json!({
-                                "position": absolute_pos,
-                                "context": context,
-                                "line": text[..absolute_pos].matches('\n').count() + 1
-                            }));
-                            
-                            position = absolute_pos + search_for.len();
+                if !handler.documents.contains_key(doc_id) {
+                    ToolOutcome::Error { code: ErrorCode::DocNotFound, error: format!("Document not found: {}", doc_id), hint: None }
+                } else {
+                    match handler.search_elements(doc_id, search_term, case_sensitive, whole_word, use_regex) {
+                        Ok(matches) => {
+                            let mut result = serde_json::json!({
+                                "total_matches": matches.len(),
+                                "matches": matches,
+                            });
+                            if group_by_element {
+                                let mut groups: Vec<serde_json::Value> = Vec::new();
+                                for m in result["matches"].as_array().unwrap() {
+                                    let key = (&m["element_index"], &m["kind"], &m["range_id"], &m["item"]);
+                                    match groups.last_mut() {
+                                        Some(g) if (&g["element_index"], &g["kind"], &g["range_id"], &g["item"]) == key => {
+                                            g["count"] = json!(g["count"].as_u64().unwrap_or(0) + 1);
+                                            g["matches"].as_array_mut().unwrap().push(m["text"].clone());
+                                        }
+                                        _ => groups.push(json!({
+                                            "element_index": m["element_index"],
+                                            "kind": m["kind"],
+                                            "range_id": m["range_id"],
+                                            "item": m["item"],
+                                            "count": 1,
+                                            "matches": [m["text"]],
+                                        })),
+                                    }
+                                }
+                                result["groups"] = json!(groups);
+                            }
+                            ToolOutcome::Metadata { metadata: result }
+                        }
+                        Err(e) => ToolOutcome::Error { code: ErrorCode::classify(&e, ErrorCode::InvalidArgument), error: e.to_string(), hint: Some("Check the regex syntax or disable use_regex".into()) }
+                    }
+                }
+            },
+            "extract_patterned_items" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let pattern = arguments["pattern"].as_str().unwrap_or("");
+                let case_sensitive = arguments.get("case_sensitive").and_then(|v| v.as_bool()).unwrap_or(true);
+                let handler = self.handler.read().unwrap();
+                if !handler.documents.contains_key(doc_id) {
+                    ToolOutcome::Error { code: ErrorCode::DocNotFound, error: format!("Document not found: {}", doc_id), hint: None }
+                } else {
+                    match handler.extract_patterned_items(doc_id, pattern, case_sensitive) {
+                        Ok(items) => ToolOutcome::Metadata { metadata: json!({"count": items.len(), "items": items}) },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                    }
+                }
+            },
+            "detect_languages" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let handler = self.handler.read().unwrap();
+                match handler.detect_languages(doc_id) {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "set_proofing_language" => {
+                use crate::docx_handler::{Proofing, ProofingScope};
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let scope = match arguments.get("scope").and_then(|v| v.as_str()).unwrap_or("document") {
+                    "document" => ProofingScope::Document,
+                    "elements" => {
+                        let Some(start) = arguments.get("start_element").and_then(|v| v.as_u64()) else {
+                            return self.respond(ToolOutcome::Error {
+                                code: ErrorCode::InvalidArgument,
+                                error: "The elements scope needs start_element".to_string(),
+                                hint: Some("Find element indexes with get_outline or search_text".to_string()),
+                            });
+                        };
+                        let end = arguments.get("end_element").and_then(|v| v.as_u64()).unwrap_or(start);
+                        ProofingScope::Elements { start: start as usize, end: end as usize }
+                    }
+                    "tag" => ProofingScope::Tag(arguments.get("tag").and_then(|v| v.as_str()).unwrap_or("").to_string()),
+                    other => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Unknown scope '{}'", other),
+                        hint: Some("Use document, elements or tag".to_string()),
+                    }),
+                };
+                let proofing = Proofing {
+                    language: arguments.get("language").and_then(|v| v.as_str()).map(str::trim).filter(|l| !l.is_empty()).map(String::from),
+                    no_proof: arguments.get("no_proof").and_then(|v| v.as_bool()).unwrap_or(false),
+                };
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.set_proofing_language(doc_id, scope, proofing) {
+                    Ok(regions) => ToolOutcome::Ok { message: Some(format!("Set proofing on {} region(s)", regions)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+            "analyze_content" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let max_keywords = arguments.get("max_keywords").and_then(|v| v.as_u64()).unwrap_or(15) as usize;
+                let handler = self.handler.read().unwrap();
+                match handler.analyze_content(doc_id, max_keywords) {
+                    Ok(report) => ToolOutcome::Metadata { metadata: report },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            #[cfg(feature = "spellcheck")]
+            "check_spelling" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let language = arguments.get("language").and_then(|v| v.as_str()).unwrap_or("en_US");
+                let max_suggestions = arguments.get("max_suggestions").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+                match crate::spelling::SpellChecker::load(language) {
+                    Ok(mut checker) => {
+                        if let Some(words) = arguments.get("ignore_words").and_then(|v| v.as_array()) {
+                            checker.add_words(words.iter().filter_map(|w| w.as_str()));
+                        }
+                        let handler = self.handler.read().unwrap();
+                        match handler.check_spelling(doc_id, &checker, max_suggestions) {
+                            Ok(misspellings) => ToolOutcome::Metadata { metadata: json!({
+                                "language": language,
+                                "total_matches": misspellings.len(),
+                                "matches": misspellings,
+                            }) },
+                            Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
                         }
-                        
-                        ToolOutcome::Metadata { metadata: serde_json::json!({
-                            "matches": matches,
-                            "total_matches": matches.len()
-                        }) }
                     }
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None }
+                    Err(e) => ToolOutcome::Error { code: ErrorCode::classify(&e, ErrorCode::InvalidArgument), error: e.to_string(), hint: Some("Run `docx-mcp dictionaries download`".into()) },
+                }
+            },
+            "apply_correction" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let element_index = arguments["element_index"].as_u64().unwrap_or(0) as usize;
+                let word = arguments["word"].as_str().unwrap_or("");
+                let replacement = arguments["replacement"].as_str().unwrap_or("");
+                let start = arguments.get("start").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let item = arguments.get("item").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let cell = arguments.get("range_id").cloned()
+                    .and_then(|v| serde_json::from_value::<crate::docx_handler::RangeId>(v).ok())
+                    .and_then(|r| match r {
+                        crate::docx_handler::RangeId::TableCell { row, col, .. } => Some((row, col)),
+                        _ => None,
+                    });
+                let mut handler = self.handler.write().unwrap();
+                match handler.apply_correction(doc_id, element_index, item, cell, word, replacement, start) {
+                    Ok(_) => ToolOutcome::Ok { message: Some(format!("Replaced '{}' with '{}'", word, replacement)) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
@@ -1652,10 +4639,10 @@ impl DocxToolsProvider {
                         // Save to file
                         match std::fs::write(output_path, markdown) {
                             Ok(_) => ToolOutcome::Ok { message: Some(format!("Document exported to Markdown at {}", output_path)) },
-                            Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: format!("Failed to save file: {}", e), hint: None }
+                            Err(e) => ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to save file: {}", e), hint: None }
                         }
                     }
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound)
                 }
             },
 
@@ -1664,55 +4651,226 @@ impl DocxToolsProvider {
                 let output_path = arguments["output_path"].as_str().unwrap_or("");
                 
                 let handler = self.handler.read().unwrap();
-                match handler.extract_text(doc_id) {
-                    Ok(text) => {
-                        // Simple conversion to HTML - preserve headings heuristically
-                        let mut html = String::from("<html><head><meta charset=\"utf-8\"></head><body>\n");
-                        for line in text.lines() {
-                            let trimmed = line.trim();
-                            if trimmed.is_empty() { continue; }
-                            if trimmed.len() < 100 && trimmed.chars().any(|c| c.is_uppercase()) {
-                                if trimmed.chars().all(|c| c.is_uppercase() || c.is_whitespace()) {
-                                    html.push_str(&format!("<h1>{}</h1>\n", html_escape::encode_text(trimmed)));
+                if arguments.get("self_contained").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    let metadata = match handler.get_metadata(doc_id) {
+                        Ok(m) => m,
+                        Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound)),
+                    };
+                    let title = metadata.title.clone().unwrap_or_else(|| doc_id.to_string());
+                    match crate::html_export::self_contained_html(&metadata.path, &title) {
+                        Ok(html) => match std::fs::write(output_path, html) {
+                            Ok(_) => ToolOutcome::Ok { message: Some(format!("Document exported to self-contained HTML at {}", output_path)) },
+                            Err(e) => ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to save file: {}", e), hint: None },
+                        },
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
+                    }
+                } else {
+                    match handler.extract_text(doc_id) {
+                        Ok(text) => {
+                            // Simple conversion to HTML - preserve headings heuristically
+                            let mut html = String::from("<html><head><meta charset=\"utf-8\"></head><body>\n");
+                            for line in text.lines() {
+                                let trimmed = line.trim();
+                                if trimmed.is_empty() { continue; }
+                                if trimmed.len() < 100 && trimmed.chars().any(|c| c.is_uppercase()) {
+                                    if trimmed.chars().all(|c| c.is_uppercase() || c.is_whitespace()) {
+                                        html.push_str(&format!("<h1>{}</h1>\n", html_escape::encode_text(trimmed)));
+                                    } else {
+                                        html.push_str(&format!("<h2>{}</h2>\n", html_escape::encode_text(trimmed)));
+                                    }
+                                } else if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
+                                    // naive list handling: wrap each as <li>
+                                    html.push_str(&format!("<ul><li>{}</li></ul>\n", html_escape::encode_text(&trimmed[2..])));
                                 } else {
-                                    html.push_str(&format!("<h2>{}</h2>\n", html_escape::encode_text(trimmed)));
+                                    html.push_str(&format!("<p>{}</p>\n", html_escape::encode_text(trimmed)));
                                 }
-                            } else if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
-                                // naive list handling: wrap each as <li>
-                                html.push_str(&format!("<ul><li>{}</li></ul>\n", html_escape::encode_text(&trimmed[2..])));
-                            } else {
-                                html.push_str(&format!("<p>{}</p>\n", html_escape::encode_text(trimmed)));
+                            }
+                            html.push_str("</body></html>\n");
+                            match std::fs::write(output_path, html) {
+                                Ok(_) => ToolOutcome::Ok { message: Some(format!("Document exported to HTML at {}", output_path)) },
+                                Err(e) => ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to save file: {}", e), hint: None }
                             }
                         }
-                        html.push_str("</body></html>\n");
-                        match std::fs::write(output_path, html) {
-                            Ok(_) => ToolOutcome::Ok { message: Some(format!("Document exported to HTML at {}", output_path)) },
-                            Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: format!("Failed to save file: {}", e), hint: None }
+                        Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound)
+                    }
+                }
+            },
+            "export_xliff" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let source_language = arguments.get("source_language").and_then(|v| v.as_str()).unwrap_or("en");
+                let target_language = arguments.get("target_language").and_then(|v| v.as_str());
+                let output_path = arguments.get("output_path").and_then(|v| v.as_str());
+
+                let handler = self.handler.read().unwrap();
+                match handler.export_xliff(doc_id, source_language, target_language) {
+                    Ok(xliff) => match output_path {
+                        Some(path) => match std::fs::write(path, xliff) {
+                            Ok(_) => ToolOutcome::Ok { message: Some(format!("XLIFF exported to {}", path)) },
+                            Err(e) => ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to save file: {}", e), hint: None },
+                        },
+                        None => ToolOutcome::Text { text: xliff },
+                    },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::DocNotFound),
+                }
+            },
+            "import_xliff" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let path = arguments["path"].as_str().unwrap_or("");
+
+                match std::fs::read_to_string(path) {
+                    Ok(xml) => {
+                        let mut handler = self.handler.write().unwrap();
+                        match handler.import_xliff(doc_id, &xml) {
+                            Ok(report) => ToolOutcome::Metadata { metadata: report },
+                            Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                         }
                     }
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::DocNotFound, error: e.to_string(), hint: None }
+                    Err(e) => ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to read {}: {}", path, e), hint: None },
+                }
+            },
+
+            "generate_thumbnail" => {
+                use base64::Engine as _;
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let width = arguments.get("width").and_then(|v| v.as_u64()).unwrap_or(200).clamp(16, 1024) as u32;
+                let output_path = arguments.get("output_path").and_then(|v| v.as_str());
+
+                let handler = self.handler.read().unwrap();
+                let metadata = match handler.get_metadata(doc_id) {
+                    Ok(m) => m,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::DocNotFound)),
+                };
+                let thumbnail = match PureRustConverter::new().render_page_preview(&metadata.path, 0, width) {
+                    Ok(img) => img,
+                    Err(e) => return self.respond(ToolOutcome::from_error(e, ErrorCode::InternalError)),
+                };
+                let mut png = Vec::new();
+                if let Err(e) = thumbnail.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png) {
+                    return self.respond(ToolOutcome::from_error(e, ErrorCode::InternalError));
+                }
+                if let Some(path) = output_path {
+                    if let Err(e) = std::fs::write(path, &png) {
+                        return self.respond(ToolOutcome::Error { code: ErrorCode::IoError, error: format!("Failed to save file: {}", e), hint: None });
+                    }
+                }
+                ToolOutcome::Image {
+                    data: base64::engine::general_purpose::STANDARD.encode(&png),
+                    mime_type: "image/png".into(),
+                    metadata: json!({
+                        "document_id": doc_id,
+                        "width": thumbnail.width(),
+                        "height": thumbnail.height(),
+                        "mime_type": "image/png",
+                        "output_path": output_path,
+                    }),
                 }
             },
             
             "get_security_info" => {
+                let security_config = self.security_config();
                 ToolOutcome::Security { security: serde_json::json!({
-                    "readonly_mode": self.security_config.readonly_mode,
-                    "sandbox_mode": self.security_config.sandbox_mode,
-                    "allow_external_tools": self.security_config.allow_external_tools,
-                    "allow_network": self.security_config.allow_network,
-                    "max_document_size": self.security_config.max_document_size,
-                    "max_open_documents": self.security_config.max_open_documents,
-                    "summary": self.security_config.get_summary(),
+                    "readonly_mode": security_config.readonly_mode,
+                    "sandbox_mode": security_config.sandbox_mode,
+                    "allow_external_tools": security_config.allow_external_tools,
+                    "allow_network": security_config.allow_network,
+                    "max_document_size": security_config.max_document_size,
+                    "max_open_documents": security_config.max_open_documents,
+                    "summary": security_config.get_summary(),
                     "readonly_commands": crate::security::SecurityConfig::get_readonly_commands().len(),
                     "write_commands": crate::security::SecurityConfig::get_write_commands().len()
                 }) }
             },
+            "reload_config" => {
+                match self.reload_config() {
+                    Ok(security) => ToolOutcome::Security { security },
+                    Err(e) => ToolOutcome::Error { code: ErrorCode::InvalidArgument, error: format!("{:#}", e), hint: None },
+                }
+            },
+            "server_info" | "ping" => {
+                let handler = self.handler.read().unwrap();
+                let temp_dir = handler.temp_dir_health();
+                let healthy = temp_dir["exists"] == true && temp_dir["writable"] == true;
+                let security_config = self.security_config();
+                ToolOutcome::Metadata { metadata: serde_json::json!({
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "status": if healthy { "ok" } else { "degraded" },
+                    "features": crate::security::enabled_features(),
+                    "security": {
+                        "summary": security_config.get_summary(),
+                        "readonly_mode": security_config.readonly_mode,
+                        "sandbox_mode": security_config.sandbox_mode,
+                        "allow_external_tools": security_config.allow_external_tools,
+                        "allow_network": security_config.allow_network,
+                    },
+                    "temp_dir": temp_dir,
+                }) }
+            },
             
             "get_storage_info" => {
                 let handler = self.handler.read().unwrap();
                 match handler.get_storage_info() {
                     Ok(info) => ToolOutcome::Storage { storage: info.get("storage").cloned().unwrap_or(serde_json::json!({})) },
-                    Err(e) => ToolOutcome::Error { code: ErrorCode::InternalError, error: e.to_string(), hint: None },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InternalError),
+                }
+            },
+
+            // Not listed in tools/list: a diagnostic for measuring the write path on this machine
+            "run_benchmark" => {
+                let scenario = match serde_json::from_value::<crate::bench::Scenario>(arguments.clone()) {
+                    Ok(scenario) => scenario,
+                    Err(e) => return self.respond(ToolOutcome::Error {
+                        code: ErrorCode::InvalidArgument,
+                        error: format!("Invalid scenario: {}", e),
+                        hint: Some("Use paragraphs, tables, table_rows, table_cols, images, image_size and iterations".into()),
+                    }),
+                };
+                let scenarios = if scenario.is_empty() {
+                    crate::bench::standard_suite(scenario.iterations)
+                } else {
+                    vec![scenario]
+                };
+                let package = self.handler.read().unwrap().package_options();
+                match crate::bench::run_all(&scenarios, package) {
+                    Ok(reports) => ToolOutcome::Metadata { metadata: json!({ "reports": reports }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
+                }
+            },
+
+            "list_available_fonts" => {
+                match crate::fonts_cli::list_fonts() {
+                    Ok(fonts) => {
+                        let installed: Vec<Value> = fonts.into_iter().map(|f| json!({
+                            "file": f.file,
+                            "family": f.family,
+                            "subfamily": f.subfamily,
+                            "size_bytes": f.size_bytes,
+                            "embeddable": f.valid && !f.restricted_license,
+                        })).collect();
+                        ToolOutcome::Metadata { metadata: json!({
+                            "builtin": crate::fonts_cli::PDF_BUILTIN_FONTS,
+                            "installed": installed,
+                            "font_dir": crate::fonts_cli::fonts_dir(),
+                            "fallbacks": crate::font_fallback::FallbackResolver::discover().fallbacks(),
+                        }) }
+                    }
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::IoError),
+                }
+            },
+
+            "embed_font" => {
+                let doc_id = arguments["document_id"].as_str().unwrap_or("");
+                let ttf_path = arguments["ttf_path"].as_str().unwrap_or("");
+                let font_name = arguments.get("font_name").and_then(|v| v.as_str());
+
+                let mut handler = self.handler.write().unwrap();
+                match handler.embed_font(doc_id, &PathBuf::from(ttf_path), font_name) {
+                    Ok((family, slot)) => ToolOutcome::Metadata { metadata: json!({
+                        "document_id": doc_id,
+                        "font_name": family,
+                        "style": slot,
+                    }) },
+                    Err(e) => ToolOutcome::from_error(e, ErrorCode::InvalidArgument),
                 }
             },
             
@@ -1720,49 +4878,218 @@ impl DocxToolsProvider {
                 ToolOutcome::Error { code: ErrorCode::UnknownTool, error: format!("Unknown or unsupported tool: {}", name), hint: None }
             }
         };
-        // Backward-compatible JSON shaping with success boolean at top-level
-        let legacy = match outcome {
-            ToolOutcome::Ok { message } => {
-                let mut obj = serde_json::json!({"success": true});
-                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
-                obj
+        if outcome.success() {
+            if let Some(before) = before {
+                self.track_change(name, &arguments, &before);
             }
-            ToolOutcome::Created { document_id, message } => {
-                let mut obj = serde_json::json!({"success": true, "document_id": document_id});
-                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
-                obj
+            self.emit_events(name, &arguments);
+        }
+        self.respond(outcome)
+    }
+
+    /// Compare element fingerprints from before a write with the current ones; when the
+    /// content changed, count the op in the document's activity and emit document_modified
+    fn track_change(&self, name: &str, arguments: &Value, before: &[u64]) {
+        let Some(doc_id) = arguments.get("document_id").and_then(|v| v.as_str()) else { return };
+        let mut handler = self.handler.write().unwrap();
+        let Some(after) = handler.element_fingerprints(doc_id) else { return };
+        let Some((element_index, removed, added)) = crate::notify::changed_span(before, &after) else { return };
+        handler.record_op(doc_id, name, element_index);
+        drop(handler);
+        self.notifier.emit("document_modified", json!({
+            "document_id": doc_id,
+            "tool": name,
+            "element_index": element_index,
+            "elements_removed": removed,
+            "elements_added": added,
+            "element_count": after.len(),
+            "resource": format!("docx://{}/summary", doc_id),
+        }));
+    }
+
+    /// Notify about completed saves and conversions, and about storage crossing the threshold
+    fn emit_events(&self, name: &str, arguments: &Value) {
+        let field = |key: &str| arguments.get(key).cloned().unwrap_or(Value::Null);
+        match name {
+            "save_document" => {
+                self.notifier.emit("document_saved", json!({"document_id": field("document_id"), "output_path": field("output_path")}));
             }
-            ToolOutcome::Text { text } => serde_json::json!({"success": true, "text": text}),
-            ToolOutcome::Metadata { metadata } => {
-                // Heuristic: if this looks like search results (matches/total_matches), flatten.
-                let is_search_shape = metadata.get("matches").is_some() || metadata.get("total_matches").is_some();
-                if is_search_shape {
-                    let mut obj = serde_json::json!({"success": true});
-                    if let Some(map) = metadata.as_object() {
-                        for (k, v) in map { obj[&k[..]] = v.clone(); }
-                    }
-                    obj
-                } else {
-                    serde_json::json!({"success": true, "metadata": metadata})
-                }
+            "convert_to_pdf" | "export_pdf_with_field_refresh" | "export_to_markdown" | "export_to_html" => {
+                self.notifier.emit("conversion_completed", json!({"document_id": field("document_id"), "tool": name, "output_path": field("output_path")}));
             }
-            ToolOutcome::Documents { documents } => serde_json::json!({"success": true, "documents": documents}),
-            ToolOutcome::Images { images, message } => {
-                let mut obj = serde_json::json!({"success": true, "images": images});
-                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
-                obj
+            "convert_to_images" | "convert_to_images_with_preference" => {
+                self.notifier.emit("conversion_completed", json!({"document_id": field("document_id"), "tool": name, "output_dir": field("output_dir")}));
             }
-            ToolOutcome::Security { security } => serde_json::json!({"success": true, "security": security}),
-            ToolOutcome::Storage { storage } => serde_json::json!({"success": true, "storage": storage}),
-            ToolOutcome::Statistics { statistics } => serde_json::json!({"success": true, "statistics": statistics}),
-            ToolOutcome::Structure { structure } => serde_json::json!({"success": true, "structure": structure}),
-            ToolOutcome::Error { code, error, hint } => {
-                let mut obj = serde_json::json!({"success": false, "error": error});
-                obj["code"] = serde_json::json!(code);
-                if let Some(h) = hint { obj["hint"] = serde_json::Value::String(h); }
-                obj
+            _ => {}
+        }
+        if self.notifier.storage_threshold().is_some() && is_write_call(name, arguments) {
+            if let Ok(info) = self.handler.read().unwrap().get_storage_info() {
+                if let Some(used) = info["storage"]["total_bytes"].as_u64() {
+                    self.notifier.check_storage(used);
+                }
             }
-        };
-        CallToolResponse { content: vec![ToolResponseContent::Text(TextContent { content_type: "application/json".into(), text: legacy.to_string(), annotations: None })], is_error: None, meta: None }
+        }
+    }
+
+    /// Wrap an outcome in the configured response format
+    fn respond(&self, outcome: ToolOutcome) -> CallToolResponse {
+        outcome.into_response(self.response_mode)
+    }
+}
+
+/// Document ids a tool call refers to: `document_id`, `source_document_id`, the
+/// `document_ids` and `parts` lists, and `source` (which may also be a path)
+fn referenced_documents(arguments: &Value) -> Vec<&str> {
+    let single = ["document_id", "source_document_id", "source"].into_iter()
+        .filter_map(|key| arguments.get(key).and_then(|v| v.as_str()));
+    let lists = ["document_ids", "parts"].into_iter()
+        .filter_map(|key| arguments.get(key).and_then(|v| v.as_array()))
+        .flatten()
+        .filter_map(|v| v.as_str());
+    single.chain(lists).collect()
+}
+
+/// The JSON object a tool returned, from structuredContent or the text block
+fn response_body(response: &CallToolResponse) -> Option<std::borrow::Cow<'_, Value>> {
+    use std::borrow::Cow;
+    if let Some(body) = response.meta.as_ref().and_then(|m| m.get("structuredContent")) {
+        return Some(Cow::Borrowed(body));
     }
-}
\ No newline at end of file
+    match response.content.first() {
+        Some(mcp_core::types::ToolResponseContent::Text(t)) => serde_json::from_str(&t.text).ok().map(Cow::Owned),
+        _ => None,
+    }
+}
+
+/// Add `call_id` to the JSON body of a response (the text block and structuredContent)
+fn with_call_id(response: &mut CallToolResponse, call_id: &str) {
+    if let Some(mcp_core::types::ToolResponseContent::Text(t)) = response.content.first_mut() {
+        // Bodies are JSON objects with at least `success`; splice the field in rather
+        // than re-serializing large results
+        if t.text.starts_with('{') && t.text.len() > 2 {
+            t.text.insert_str(1, &format!("\"call_id\":\"{}\",", call_id));
+        }
+    }
+    if let Some(Value::Object(body)) = response.meta.as_mut().and_then(|m| m.get_mut("structuredContent")) {
+        body.insert("call_id".to_string(), json!(call_id));
+    }
+}
+
+/// PDF rendering options shared by the PDF export tools
+fn pdf_options(arguments: &Value) -> PdfOptions {
+    let defaults = PdfOptions::default();
+    PdfOptions {
+        subset_fonts: arguments.get("subset_fonts").and_then(|v| v.as_bool()).unwrap_or(defaults.subset_fonts),
+        hyphenate: arguments.get("hyphenate").and_then(|v| v.as_bool()).unwrap_or(defaults.hyphenate),
+        language: arguments.get("language").and_then(|v| v.as_str()).map(String::from).or(defaults.language),
+    }
+}
+
+/// Result of a find/replace or redaction tool called with `dry_run`
+fn dry_run_report(replacements: &[crate::docx_handler::Replacement]) -> Value {
+    json!({"dry_run": true, "total": replacements.len(), "replacements": replacements})
+}
+
+/// The `style_map` argument of tools that bring content in from other documents
+fn style_map_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "Restyle the incoming content to match the target",
+        "properties": {
+            "styles": {
+                "type": "object",
+                "additionalProperties": {"type": "string"},
+                "description": "Source heading style to target style, e.g. {\"Heading 1\": \"Heading 2\"}; map to Normal for body text"
+            },
+            "fonts": {
+                "type": "object",
+                "additionalProperties": {"type": "string"},
+                "description": "Source font to target font, e.g. {\"Times New Roman\": \"Arial\"}; \"*\" maps every other font"
+            }
+        }
+    })
+}
+
+/// The optional `style_map` argument
+fn style_map_arg(arguments: &Value) -> anyhow::Result<Option<StyleMap>> {
+    match arguments.get("style_map").filter(|v| !v.is_null()) {
+        Some(map) => {
+            let map: StyleMap = serde_json::from_value(map.clone()).map_err(|e| anyhow::anyhow!("invalid style_map: {}", e))?;
+            map.validate()?;
+            Ok(Some(map).filter(|m| !m.is_empty()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The `table` argument of set_header and set_footer
+fn header_footer_table_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "One-row table above the text, e.g. logo | title | date. Spans the text width without borders.",
+        "properties": {
+            "cells": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "text": {"type": "string", "description": "Cell text; newlines start new paragraphs"},
+                        "alignment": {"type": "string", "enum": ["left", "center", "right", "justify"]},
+                        "image_base64": {"type": "string", "description": "Image shown before the text"},
+                        "image_width": {"type": "integer", "description": "Image width in pixels (default 120)"},
+                        "image_height": {"type": "integer", "description": "Image height in pixels (default 40)"}
+                    }
+                }
+            },
+            "col_widths": {"type": "array", "items": {"type": "integer"}, "description": "Relative column widths, e.g. [1, 2, 1] (default equal)"},
+            "borders": {"type": "boolean", "description": "Draw cell borders (default false)"}
+        },
+        "required": ["cells"]
+    })
+}
+
+/// Header/footer alignment, logo and table arguments shared by `set_header` and `set_footer`
+fn header_footer_layout(arguments: &Value) -> anyhow::Result<HeaderFooterLayout> {
+    let logo = match arguments.get("logo_base64").and_then(|v| v.as_str()) {
+        Some(data) => Some(crate::docx_handler::ImageData {
+            data: base64::engine::general_purpose::STANDARD.decode(data).map_err(|e| anyhow::anyhow!("invalid logo_base64: {}", e))?,
+            width: arguments.get("logo_width").and_then(|v| v.as_u64()).map(|v| v as u32),
+            height: arguments.get("logo_height").and_then(|v| v.as_u64()).map(|v| v as u32),
+            alt_text: None,
+        }),
+        None => None,
+    };
+    let table = match arguments.get("table") {
+        Some(table) => {
+            let cells = table.get("cells").and_then(|v| v.as_array()).map(|cells| cells.iter().map(|cell| {
+                let image = match cell.get("image_base64").and_then(|v| v.as_str()) {
+                    Some(data) => Some(crate::docx_handler::ImageData {
+                        data: base64::engine::general_purpose::STANDARD.decode(data).map_err(|e| anyhow::anyhow!("invalid image_base64: {}", e))?,
+                        width: cell.get("image_width").and_then(|v| v.as_u64()).map(|v| v as u32),
+                        height: cell.get("image_height").and_then(|v| v.as_u64()).map(|v| v as u32),
+                        alt_text: None,
+                    }),
+                    None => None,
+                };
+                Ok(crate::docx_handler::HeaderFooterCell {
+                    text: cell.get("text").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                    alignment: cell.get("alignment").and_then(|v| v.as_str()).map(String::from),
+                    image,
+                })
+            }).collect::<anyhow::Result<Vec<_>>>()).transpose()?.unwrap_or_default();
+            Some(crate::docx_handler::HeaderFooterTable {
+                cells,
+                col_widths: table.get("col_widths").and_then(|v| v.as_array())
+                    .map(|widths| widths.iter().map(|w| w.as_u64().unwrap_or(0) as u32).collect()),
+                borders: table.get("borders").and_then(|v| v.as_bool()).unwrap_or(false),
+            })
+        }
+        None => None,
+    };
+    Ok(HeaderFooterLayout {
+        alignment: arguments.get("alignment").and_then(|v| v.as_str()).map(String::from),
+        logo,
+        logo_position: arguments.get("logo_position").and_then(|v| v.as_str()).map(String::from),
+        table,
+    })
+}
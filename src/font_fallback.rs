@@ -0,0 +1,205 @@
+//! Font fallback for PDF output. Text the built-in Helvetica can encode (WinAnsi) uses it;
+//! other characters are grouped into runs by script and rendered with a per-script font:
+//! a registered custom font first, otherwise a Noto font found in the font directory
+//! (`fonts_cli::fonts_dir`). Characters with no font available stay in Helvetica.
+//!
+//! Glyphs are drawn in logical order without shaping, so joined scripts (Arabic,
+//! Devanagari) show isolated forms.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Han,
+    Kana,
+    Hangul,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+}
+
+impl Script {
+    pub const ALL: [Script; 10] = [
+        Script::Latin, Script::Greek, Script::Cyrillic, Script::Han, Script::Kana,
+        Script::Hangul, Script::Arabic, Script::Hebrew, Script::Devanagari, Script::Thai,
+    ];
+
+    pub fn of(c: char) -> Option<Script> {
+        Some(match c as u32 {
+            0x0041..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+            0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x0400..=0x052F => Script::Cyrillic,
+            0x0590..=0x05FF | 0xFB1D..=0xFB4F => Script::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+            0x0900..=0x097F => Script::Devanagari,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+            0x2E80..=0x2FDF | 0x3000..=0x303F | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0xFF00..=0xFF65 => Script::Han,
+            _ => return None,
+        })
+    }
+
+    /// Font files tried in the font directory, most specific first
+    fn candidates(self) -> &'static [&'static str] {
+        match self {
+            Script::Latin | Script::Greek | Script::Cyrillic => &["NotoSans-Regular.ttf", "LiberationSans-Regular.ttf"],
+            Script::Han => &["NotoSansCJKsc-Regular.otf", "NotoSansSC-Regular.otf", "NotoSansSC-Regular.ttf", "NotoSansCJKjp-Regular.otf", "NotoSansJP-Regular.otf", "NotoSansJP-Regular.ttf", "NotoSansTC-Regular.otf"],
+            Script::Kana => &["NotoSansCJKjp-Regular.otf", "NotoSansJP-Regular.otf", "NotoSansJP-Regular.ttf", "NotoSansCJKsc-Regular.otf"],
+            Script::Hangul => &["NotoSansCJKkr-Regular.otf", "NotoSansKR-Regular.otf", "NotoSansKR-Regular.ttf", "NotoSansCJKsc-Regular.otf"],
+            Script::Arabic => &["NotoSansArabic-Regular.ttf", "NotoNaskhArabic-Regular.ttf"],
+            Script::Hebrew => &["NotoSansHebrew-Regular.ttf"],
+            Script::Devanagari => &["NotoSansDevanagari-Regular.ttf"],
+            Script::Thai => &["NotoSansThai-Regular.ttf"],
+        }
+    }
+}
+
+impl std::str::FromStr for Script {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.trim().to_ascii_lowercase().as_str() {
+            "latin" => Script::Latin,
+            "greek" => Script::Greek,
+            "cyrillic" => Script::Cyrillic,
+            "han" | "chinese" | "cjk" => Script::Han,
+            "kana" | "japanese" => Script::Kana,
+            "hangul" | "korean" => Script::Hangul,
+            "arabic" => Script::Arabic,
+            "hebrew" => Script::Hebrew,
+            "devanagari" | "hindi" => Script::Devanagari,
+            "thai" => Script::Thai,
+            other => anyhow::bail!("unknown script '{}'", other),
+        })
+    }
+}
+
+/// Layout width in columns: CJK and fullwidth characters take two
+pub fn column_width(c: char) -> usize {
+    match Script::of(c) {
+        Some(Script::Han | Script::Kana | Script::Hangul) => 2,
+        _ => 1,
+    }
+}
+
+/// Whether a line may break before/after this character without a space
+pub fn breaks_anywhere(c: char) -> bool {
+    matches!(Script::of(c), Some(Script::Han | Script::Kana | Script::Hangul | Script::Thai))
+}
+
+/// Characters Helvetica can draw: printable ASCII and the Latin-1/WinAnsi extras
+fn builtin_can_encode(c: char) -> bool {
+    matches!(c as u32, 0x20..=0x7E | 0xA0..=0xFF)
+        || "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ".contains(c)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FontChoice {
+    Builtin,
+    External(PathBuf),
+}
+
+static CUSTOM_FALLBACKS: Lazy<RwLock<HashMap<Script, PathBuf>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Use `path` for `script`, ahead of the fonts discovered in the font directory
+pub fn register_fallback(script: Script, path: &Path) -> Result<()> {
+    let info = crate::fonts_cli::font_info(path)?;
+    if !info.valid {
+        anyhow::bail!("{} is not a TrueType/OpenType font", path.display());
+    }
+    CUSTOM_FALLBACKS.write().unwrap().insert(script, path.to_path_buf());
+    Ok(())
+}
+
+/// Register `script=path` specs (from `--fallback-font`)
+pub fn register_from_specs(specs: &[String]) -> Result<()> {
+    for spec in specs {
+        let (script, path) = spec.split_once('=')
+            .with_context(|| format!("fallback font '{}' must be SCRIPT=PATH", spec))?;
+        register_fallback(script.parse()?, Path::new(path.trim()))
+            .with_context(|| format!("register fallback font '{}'", spec))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FallbackResolver {
+    fonts: HashMap<Script, PathBuf>,
+}
+
+impl FallbackResolver {
+    /// Custom registrations plus whatever candidate fonts exist in the font directory
+    pub fn discover() -> Self {
+        let dir = crate::fonts_cli::fonts_dir();
+        let installed: HashMap<String, PathBuf> = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str().map(|n| (n.to_ascii_lowercase(), e.path())))
+            .collect();
+        let custom = CUSTOM_FALLBACKS.read().unwrap();
+        let mut fonts = HashMap::new();
+        for script in Script::ALL {
+            let found = custom.get(&script).cloned().or_else(|| {
+                script.candidates().iter()
+                    .filter_map(|name| installed.get(&name.to_ascii_lowercase()))
+                    // Skip placeholder files left by the build script
+                    .find(|p| crate::fonts_cli::font_info(p).map(|i| i.valid).unwrap_or(false))
+                    .cloned()
+            });
+            if let Some(path) = found {
+                fonts.insert(script, path);
+            }
+        }
+        Self { fonts }
+    }
+
+    pub fn font_for(&self, script: Script) -> Option<&Path> {
+        self.fonts.get(&script).map(|p| p.as_path())
+    }
+
+    /// Script to font file, for reporting
+    pub fn fallbacks(&self) -> HashMap<Script, PathBuf> {
+        self.fonts.clone()
+    }
+
+    fn choice_for(&self, c: char) -> Option<FontChoice> {
+        if builtin_can_encode(c) {
+            return Some(FontChoice::Builtin);
+        }
+        match Script::of(c).and_then(|s| self.font_for(s)) {
+            Some(path) => Some(FontChoice::External(path.to_path_buf())),
+            None if Script::of(c).is_some() => Some(FontChoice::Builtin),
+            // Spaces, digits and punctuation outside WinAnsi join the surrounding run
+            None => None,
+        }
+    }
+
+    /// Split text into runs that share a font. Neutral characters (spaces, punctuation)
+    /// stay with the run they follow so runs do not flip around every space.
+    pub fn split_runs(&self, text: &str) -> Vec<(FontChoice, String)> {
+        let mut runs: Vec<(FontChoice, String)> = Vec::new();
+        for c in text.chars() {
+            let neutral = c.is_whitespace() || c.is_ascii_punctuation() || (Script::of(c).is_none() && !c.is_alphanumeric());
+            let choice = match (neutral, runs.last()) {
+                (true, Some((current, _))) => current.clone(),
+                _ => self.choice_for(c).unwrap_or(FontChoice::Builtin),
+            };
+            match runs.last_mut() {
+                Some((current, run)) if *current == choice => run.push(c),
+                _ => runs.push((choice, c.to_string())),
+            }
+        }
+        runs
+    }
+}
@@ -1,13 +1,26 @@
+//! Font management: downloading the pinned open-source set (checksummed, with a download
+//! cache), listing installed fonts and adding user fonts. Fonts live in `assets/fonts`,
+//! or `DOCX_MCP_FONT_DIR` when set.
+
 use anyhow::{Context, Result};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const FONTS_DIR: &str = "assets/fonts";
+const DICTIONARIES_DIR: &str = "assets/dictionaries";
+
+/// The 14 standard PDF fonts; the built-in renderer writes text with these and needs no files
+pub const PDF_BUILTIN_FONTS: &[&str] = &[
+    "Helvetica", "Helvetica-Bold", "Helvetica-Oblique", "Helvetica-BoldOblique",
+    "Times-Roman", "Times-Bold", "Times-Italic", "Times-BoldItalic",
+    "Courier", "Courier-Bold", "Courier-Oblique", "Courier-BoldOblique",
+    "Symbol", "ZapfDingbats",
+];
 
 // Pin sources and expected checksums
-const LIBERATION_VERSION: &str = "2.1.5";
 const LIBERATION_TAR_URL: &str = "https://github.com/liberationfonts/liberation-fonts/files/7261482/liberation-fonts-ttf-2.1.5.tar.gz";
 const NOTO_BASE_URL: &str = "https://github.com/googlefonts/noto-fonts/raw/main/hinted/ttf/NotoSans";
 
@@ -20,18 +33,52 @@ const FONT_FILES: &[(&str, Option<&str>)] = &[
     ("NotoSans-Bold.ttf",          Some("c976e4b1b99edc88775377fcc21692ca4bfa46b6d6ca6522bfda505b28ff9d6a")),
 ];
 
+pub fn fonts_dir() -> PathBuf {
+    std::env::var_os("DOCX_MCP_FONT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(FONTS_DIR))
+}
+
+/// Spelling dictionaries and hyphenation patterns (see `spelling`, `hyphenation`)
+pub fn dictionaries_dir() -> PathBuf {
+    std::env::var_os("DOCX_MCP_DICT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DICTIONARIES_DIR))
+}
+
+/// Where downloaded archives are kept so repeated downloads work offline
+pub fn download_cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("DOCX_MCP_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("docx-mcp")
+}
+
 pub fn download_fonts_blocking() -> Result<()> {
-    fs::create_dir_all(FONTS_DIR).context("create fonts dir")?;
+    let dir = fonts_dir();
+    fs::create_dir_all(&dir).context("create fonts dir")?;
+
+    // Skip the downloads entirely when every pinned font is already present and verified
+    if verify_fonts_blocking().is_ok() {
+        return Ok(());
+    }
 
     // Download Liberation tarball
     let tar_bytes = download_bytes(LIBERATION_TAR_URL)?;
-    extract_liberation_from_tar(&tar_bytes, Path::new(FONTS_DIR))?;
+    extract_liberation_from_tar(&tar_bytes, &dir)?;
 
     // Download Noto fonts
     for name in ["NotoSans-Regular.ttf", "NotoSans-Bold.ttf"] {
+        let out = dir.join(name);
+        if out.exists() && verify_single(&out, expected_for(name)).is_ok() {
+            continue;
+        }
         let url = format!("{}/{}", NOTO_BASE_URL, name);
         let bytes = download_bytes(&url)?;
-        let out = Path::new(FONTS_DIR).join(name);
         fs::write(&out, bytes).context("write noto font")?;
         // verify immediate
         verify_single(&out, expected_for(name))?;
@@ -41,8 +88,9 @@ pub fn download_fonts_blocking() -> Result<()> {
 }
 
 pub fn verify_fonts_blocking() -> Result<()> {
+    let dir = fonts_dir();
     for (name, expected_opt) in FONT_FILES {
-        let path = Path::new(FONTS_DIR).join(name);
+        let path = dir.join(name);
         if !path.exists() {
             anyhow::bail!("missing font: {}", name);
         }
@@ -56,8 +104,25 @@ pub fn verify_fonts_blocking() -> Result<()> {
     Ok(())
 }
 
+/// Fetch a URL through the download cache (keyed by the URL's file name)
 fn download_bytes(url: &str) -> Result<Vec<u8>> {
-    let res = ureq::get(url).call().context("request failed")?;
+    let cache_dir = download_cache_dir().join("downloads");
+    let cached = cache_dir.join(url.rsplit('/').next().unwrap_or("download"));
+    if let Ok(bytes) = fs::read(&cached) {
+        if !bytes.is_empty() {
+            return Ok(bytes);
+        }
+    }
+    let buf = fetch_bytes(url)?;
+    if fs::create_dir_all(&cache_dir).is_ok() {
+        let _ = fs::write(&cached, &buf);
+    }
+    Ok(buf)
+}
+
+/// GET a URL into memory, bypassing the download cache
+pub(crate) fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
+    let res = ureq::get(url).call().with_context(|| format!("request failed: {}", url))?;
     let mut buf = Vec::new();
     res.into_reader().read_to_end(&mut buf).context("read body")?;
     Ok(buf)
@@ -86,8 +151,12 @@ fn extract_liberation_from_tar(tar_gz: &[u8], out_dir: &Path) -> Result<()> {
                 let dest = out_dir.join(filename);
                 let context_msg = format!("unpack {}", filename);
                 entry.unpack(&dest).context(context_msg)?;
-                // verify immediate
-                verify_single(&dest, expected_for(filename))?;
+                // verify immediate; a corrupt cached archive is dropped so the next run refetches
+                if let Err(e) = verify_single(&dest, expected_for(filename)) {
+                    let cached = download_cache_dir().join("downloads").join(LIBERATION_TAR_URL.rsplit('/').next().unwrap_or(""));
+                    let _ = fs::remove_file(cached);
+                    return Err(e);
+                }
             }
             _ => {}
         }
@@ -126,3 +195,123 @@ fn sha256_file(path: &Path) -> Result<String> {
     }
     Ok(format!("{:x}", hasher.finalize()))
 }
+
+/// An installed font file and the names from its `name` table
+#[derive(Debug, Clone, Serialize)]
+pub struct FontInfo {
+    pub file: String,
+    pub path: PathBuf,
+    pub family: Option<String>,
+    pub subfamily: Option<String>,
+    pub size_bytes: u64,
+    /// Parsed as a TrueType/OpenType font (placeholders and corrupt files are not)
+    pub valid: bool,
+    /// The OS/2 `fsType` marks the font "restricted license": it must not be embedded
+    pub restricted_license: bool,
+}
+
+/// Fonts (`.ttf`/`.otf`) in the font directory, sorted by file name
+pub fn list_fonts() -> Result<Vec<FontInfo>> {
+    let dir = fonts_dir();
+    let mut fonts = Vec::new();
+    if !dir.is_dir() {
+        return Ok(fonts);
+    }
+    for entry in fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))? {
+        let path = entry?.path();
+        let is_font = path.extension().and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("ttf") || e.eq_ignore_ascii_case("otf"))
+            .unwrap_or(false);
+        if is_font {
+            fonts.push(font_info(&path)?);
+        }
+    }
+    fonts.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(fonts)
+}
+
+pub fn font_info(path: &Path) -> Result<FontInfo> {
+    let bytes = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    let names = parse_font_names(&bytes);
+    Ok(FontInfo {
+        file: path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string(),
+        path: path.to_path_buf(),
+        family: names.as_ref().and_then(|(f, _)| f.clone()),
+        subfamily: names.as_ref().and_then(|(_, s)| s.clone()),
+        size_bytes: bytes.len() as u64,
+        valid: names.is_some(),
+        restricted_license: names.is_some() && parse_fs_type(&bytes).map(|t| t & 0x000F == 0x0002).unwrap_or(false),
+    })
+}
+
+/// Copy a TrueType/OpenType font into the font directory, returning its details
+pub fn add_font(src: &Path) -> Result<FontInfo> {
+    let info = font_info(src)?;
+    if !info.valid {
+        anyhow::bail!("{} is not a TrueType/OpenType font", src.display());
+    }
+    let dir = fonts_dir();
+    fs::create_dir_all(&dir).context("create fonts dir")?;
+    let dest = dir.join(&info.file);
+    fs::copy(src, &dest).with_context(|| format!("copy {} to {}", src.display(), dest.display()))?;
+    font_info(&dest)
+}
+
+/// Offset of an sfnt table by tag
+fn table_offset(data: &[u8], tag: &[u8; 4]) -> Option<usize> {
+    let num_tables = data.get(4..6).map(|b| u16::from_be_bytes([b[0], b[1]]))? as usize;
+    (0..num_tables)
+        .map(|i| 12 + i * 16)
+        .find(|&rec| data.get(rec..rec + 4) == Some(&tag[..]))
+        .and_then(|rec| data.get(rec + 8..rec + 12))
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}
+
+/// Embedding permissions (`fsType`) from the OS/2 table
+fn parse_fs_type(data: &[u8]) -> Option<u16> {
+    let os2 = table_offset(data, b"OS/2")?;
+    data.get(os2 + 8..os2 + 10).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+/// Family and subfamily names from an sfnt font; `None` when the data is not a font
+fn parse_font_names(data: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    let u16_at = |o: usize| data.get(o..o + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+
+    let version = data.get(0..4)?;
+    if version != [0, 1, 0, 0] && version != b"OTTO" && version != b"true" {
+        return None;
+    }
+    let name_offset = table_offset(data, b"name")?;
+
+    let count = u16_at(name_offset + 2)? as usize;
+    let strings = name_offset + u16_at(name_offset + 4)? as usize;
+    // (rank, text): Windows US English is what Word matches font names against
+    let mut family: Option<(u8, String)> = None;
+    let mut subfamily: Option<(u8, String)> = None;
+    for i in 0..count {
+        let rec = name_offset + 6 + i * 12;
+        let (platform, language, name_id) = (u16_at(rec)?, u16_at(rec + 4)?, u16_at(rec + 6)?);
+        let (length, offset) = (u16_at(rec + 8)? as usize, u16_at(rec + 10)? as usize);
+        let Some(raw) = data.get(strings + offset..strings + offset + length) else { continue };
+        let text = match platform {
+            // Unicode and Windows names are UTF-16BE; Macintosh names are (mostly) ASCII
+            0 | 3 => String::from_utf16_lossy(&raw.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect::<Vec<_>>()),
+            1 => raw.iter().map(|&b| b as char).collect(),
+            _ => continue,
+        };
+        let slot = match name_id {
+            1 => &mut family,
+            2 => &mut subfamily,
+            _ => continue,
+        };
+        let rank = match (platform, language) {
+            (3, 0x0409) => 2,
+            (3, _) => 1,
+            _ => 0,
+        };
+        if slot.as_ref().map(|(r, _)| rank > *r).unwrap_or(true) {
+            *slot = Some((rank, text));
+        }
+    }
+    Some((family.map(|(_, t)| t), subfamily.map(|(_, t)| t)))
+}
@@ -0,0 +1,430 @@
+//! Self-contained HTML export: one `.html` file with images inlined as data URIs and
+//! styles in an embedded stylesheet, so it can be mailed or opened without the package.
+//!
+//! Page breaks are marked where the PDF layout engine (`PureRustConverter::layout_text`)
+//! starts a new page, as `<div class="page-break" data-page="N">` elements.
+
+use anyhow::Result;
+use base64::Engine as _;
+use roxmltree::{Document, Node, NodeId};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::pure_converter::PureRustConverter;
+
+const STYLESHEET: &str = r#"body { font-family: Helvetica, Arial, sans-serif; font-size: 11pt; line-height: 1.4; max-width: 170mm; margin: 20mm auto; color: #222; }
+h1, h2, h3, h4, h5, h6 { margin: 1em 0 0.4em; }
+p { margin: 0 0 0.6em; }
+table { border-collapse: collapse; margin: 0 0 0.8em; }
+td, th { border: 1px solid #999; padding: 2pt 5pt; vertical-align: top; }
+td p, th p { margin: 0; }
+img { max-width: 100%; height: auto; }
+.page-break { border-top: 1px dashed #bbb; margin: 1.5em 0; text-align: right; font-size: 8pt; color: #999; }
+.page-break::after { content: "Page " attr(data-page); }
+@media print { .page-break { border: none; margin: 0; break-before: page; } .page-break::after { content: none; } }
+"#;
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn attr<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes().find(|a| a.name() == name).map(|a| a.value())
+}
+
+fn escape(text: &str) -> String {
+    html_escape::encode_text(text).into_owned()
+}
+
+struct Package {
+    archive: ZipArchive<File>,
+    /// Relationship id to target, from word/_rels/document.xml.rels
+    rels: HashMap<String, String>,
+    /// (numId, ilvl) to whether the list level is bulleted
+    bullets: HashMap<(String, String), bool>,
+}
+
+impl Package {
+    fn part(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        match self.archive.by_name(name) {
+            Ok(mut file) => {
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)?;
+                Ok(Some(buf))
+            }
+            Err(zip::result::ZipError::FileNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn xml(&mut self, name: &str) -> Result<Option<String>> {
+        Ok(self.part(name)?.map(|b| String::from_utf8_lossy(&b).into_owned()))
+    }
+
+    /// Image part referenced by `rid` as a data URI
+    fn data_uri(&mut self, rid: &str) -> Result<Option<String>> {
+        let Some(target) = self.rels.get(rid).cloned() else { return Ok(None) };
+        let name = format!("word/{}", target.trim_start_matches("/word/").trim_start_matches('/'));
+        let Some(bytes) = self.part(&name)? else { return Ok(None) };
+        let mime = match name.rsplit('.').next().unwrap_or("").to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "bmp" => "image/bmp",
+            "svg" => "image/svg+xml",
+            "webp" => "image/webp",
+            _ => "image/png",
+        };
+        Ok(Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes))))
+    }
+}
+
+/// Page (1-based) of every body paragraph that produces layout lines
+fn paragraph_pages(doc: &Document) -> HashMap<NodeId, usize> {
+    let mut owners = Vec::new();
+    let mut lines = Vec::new();
+    for p in doc.descendants().filter(|n| n.tag_name().name() == "p") {
+        let mut text = String::new();
+        for node in p.descendants() {
+            match node.tag_name().name() {
+                "t" => text.push_str(node.text().unwrap_or("")),
+                "tab" => text.push('\t'),
+                "br" => text.push('\n'),
+                _ => {}
+            }
+        }
+        for line in text.split('\n') {
+            owners.push(p.id());
+            lines.push(line.to_string());
+        }
+    }
+    let mut pages = HashMap::new();
+    for line in PureRustConverter::new().layout_text(&lines.join("\n")) {
+        pages.entry(owners[line.source_line]).or_insert(line.page + 1);
+    }
+    pages
+}
+
+struct Renderer<'p> {
+    package: &'p mut Package,
+    pages: HashMap<NodeId, usize>,
+    current_page: usize,
+    out: String,
+    /// Open list: (bulleted, level)
+    list: Option<(bool, usize)>,
+}
+
+impl Renderer<'_> {
+    fn page_marker(&mut self, block: Node) {
+        let page = std::iter::once(block)
+            .chain(block.descendants())
+            .find_map(|n| self.pages.get(&n.id()).copied());
+        if let Some(page) = page {
+            while self.current_page < page {
+                self.close_list();
+                self.current_page += 1;
+                self.out.push_str(&format!("<div class=\"page-break\" data-page=\"{}\"></div>\n", self.current_page));
+            }
+        }
+    }
+
+    fn close_list(&mut self) {
+        if let Some((bulleted, _)) = self.list.take() {
+            self.out.push_str(if bulleted { "</ul>\n" } else { "</ol>\n" });
+        }
+    }
+
+    fn blocks(&mut self, container: Node, top_level: bool) -> Result<()> {
+        for block in container.children() {
+            match block.tag_name().name() {
+                "p" => {
+                    if top_level {
+                        self.page_marker(block);
+                    }
+                    self.paragraph(block)?;
+                }
+                "tbl" => {
+                    if top_level {
+                        self.page_marker(block);
+                    }
+                    self.close_list();
+                    self.table(block)?;
+                }
+                "sdt" => if let Some(content) = child(block, "sdtContent") {
+                    self.blocks(content, top_level)?;
+                },
+                _ => {}
+            }
+        }
+        if !top_level {
+            self.close_list();
+        }
+        Ok(())
+    }
+
+    fn paragraph(&mut self, p: Node) -> Result<()> {
+        let ppr = child(p, "pPr");
+        let style = ppr.and_then(|n| child(n, "pStyle")).and_then(|n| attr(n, "val")).unwrap_or("");
+        let numbering = ppr.and_then(|n| child(n, "numPr")).map(|num| {
+            let id = num.children().find(|n| n.tag_name().name() == "numId").and_then(|n| attr(n, "val")).unwrap_or("0");
+            let level = num.children().find(|n| n.tag_name().name() == "ilvl").and_then(|n| attr(n, "val")).unwrap_or("0");
+            (id.to_string(), level.to_string())
+        });
+        let tag = match style.to_ascii_lowercase().as_str() {
+            "title" => "h1".to_string(),
+            s if s.starts_with("heading") => {
+                let level = style.chars().last().and_then(|c| c.to_digit(10)).unwrap_or(1);
+                format!("h{}", level.clamp(1, 6))
+            }
+            _ if numbering.is_some() => "li".to_string(),
+            _ => "p".to_string(),
+        };
+        match (&numbering, tag.as_str()) {
+            (Some(key), "li") => {
+                let bulleted = self.package.bullets.get(key).copied().unwrap_or(true);
+                let level = key.1.parse().unwrap_or(0);
+                if self.list != Some((bulleted, level)) {
+                    self.close_list();
+                    self.out.push_str(if bulleted { "<ul>\n" } else { "<ol>\n" });
+                    self.list = Some((bulleted, level));
+                }
+            }
+            _ => self.close_list(),
+        }
+        let align = ppr.and_then(|n| child(n, "jc")).and_then(|n| attr(n, "val")).and_then(|v| match v {
+            "center" => Some("center"),
+            "right" | "end" => Some("right"),
+            "both" | "distribute" => Some("justify"),
+            _ => None,
+        });
+        let rtl = ppr.and_then(|n| child(n, "bidi"))
+            .map(|n| !matches!(attr(n, "val"), Some("0") | Some("false")))
+            .unwrap_or(false);
+        let dir = if rtl { " dir=\"rtl\"" } else { "" };
+        let mut css: Vec<String> = align.map(|a| format!("text-align:{}", a)).into_iter().collect();
+        if let Some(borders) = ppr.and_then(|n| child(n, "pBdr")) {
+            for edge in borders.children().filter(|n| n.is_element()) {
+                let side = match edge.tag_name().name() {
+                    "top" => "top",
+                    "bottom" => "bottom",
+                    "left" | "start" => "left",
+                    "right" | "end" => "right",
+                    _ => continue,
+                };
+                let kind = match attr(edge, "val").unwrap_or("single") {
+                    "none" | "nil" => continue,
+                    "double" | "triple" => "double",
+                    "dotted" => "dotted",
+                    "dashed" | "dotDash" | "dotDotDash" => "dashed",
+                    _ => "solid",
+                };
+                // Border widths are in eighths of a point
+                let width = attr(edge, "sz").and_then(|v| v.parse::<f32>().ok()).unwrap_or(4.0) / 8.0;
+                let color = attr(edge, "color").filter(|c| *c != "auto").map(|c| format!("#{}", c)).unwrap_or_else(|| "currentColor".into());
+                css.push(format!("border-{}:{}pt {} {}", side, width, kind, color));
+            }
+        }
+        if let Some(fill) = ppr.and_then(|n| child(n, "shd")).and_then(|n| attr(n, "fill")).filter(|f| *f != "auto") {
+            css.push(format!("background-color:#{}", fill));
+        }
+        if css.is_empty() {
+            self.out.push_str(&format!("<{}{}>", tag, dir));
+        } else {
+            self.out.push_str(&format!("<{}{} style=\"{}\">", tag, dir, css.join(";")));
+        }
+        self.inline(p)?;
+        self.out.push_str(&format!("</{}>\n", tag));
+        Ok(())
+    }
+
+    /// Runs, hyperlinks and images of a paragraph
+    fn inline(&mut self, container: Node) -> Result<()> {
+        for node in container.children() {
+            match node.tag_name().name() {
+                "r" => self.run(node)?,
+                "hyperlink" => {
+                    let href = attr(node, "id").and_then(|rid| self.package.rels.get(rid).cloned())
+                        .or_else(|| attr(node, "anchor").map(|a| format!("#{}", a)));
+                    match href {
+                        Some(href) => {
+                            self.out.push_str(&format!("<a href=\"{}\">", html_escape::encode_double_quoted_attribute(&href)));
+                            self.inline(node)?;
+                            self.out.push_str("</a>");
+                        }
+                        None => self.inline(node)?,
+                    }
+                }
+                "ins" | "smartTag" | "fldSimple" => self.inline(node)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, r: Node) -> Result<()> {
+        let rpr = child(r, "rPr");
+        let on = |name: &str| {
+            rpr.and_then(|n| child(n, name))
+                .map(|n| !matches!(attr(n, "val"), Some("0") | Some("false") | Some("none")))
+                .unwrap_or(false)
+        };
+        let mut open = Vec::new();
+        let mut styles = Vec::new();
+        if let Some(color) = rpr.and_then(|n| child(n, "color")).and_then(|n| attr(n, "val")).filter(|c| *c != "auto") {
+            styles.push(format!("color:#{}", color));
+        }
+        if let Some(size) = rpr.and_then(|n| child(n, "sz")).and_then(|n| attr(n, "val")).and_then(|v| v.parse::<f32>().ok()) {
+            styles.push(format!("font-size:{}pt", size / 2.0));
+        }
+        if let Some(highlight) = rpr.and_then(|n| child(n, "highlight")).and_then(|n| attr(n, "val")).filter(|c| *c != "none") {
+            let css = if highlight == "darkYellow" { "#808000".to_string() } else { highlight.to_ascii_lowercase() };
+            styles.push(format!("background-color:{}", css));
+        }
+        if on("caps") {
+            styles.push("text-transform:uppercase".to_string());
+        }
+        if on("smallCaps") {
+            styles.push("font-variant:small-caps".to_string());
+        }
+        // Character spacing is in twentieths of a point
+        if let Some(spacing) = rpr.and_then(|n| child(n, "spacing")).and_then(|n| attr(n, "val")).and_then(|v| v.parse::<f32>().ok()) {
+            styles.push(format!("letter-spacing:{}pt", spacing / 20.0));
+        }
+        if on("rtl") {
+            self.out.push_str("<span dir=\"rtl\">");
+            open.push("span");
+        }
+        if !styles.is_empty() {
+            self.out.push_str(&format!("<span style=\"{}\">", styles.join(";")));
+            open.push("span");
+        }
+        let vert = rpr.and_then(|n| child(n, "vertAlign")).and_then(|n| attr(n, "val"));
+        for (tag, enabled) in [
+            ("strong", on("b")),
+            ("em", on("i")),
+            ("u", on("u")),
+            ("s", on("strike") || on("dstrike")),
+            ("sup", vert == Some("superscript")),
+            ("sub", vert == Some("subscript")),
+        ] {
+            if enabled {
+                self.out.push_str(&format!("<{}>", tag));
+                open.push(tag);
+            }
+        }
+        for node in r.descendants() {
+            match node.tag_name().name() {
+                "t" => self.out.push_str(&escape(node.text().unwrap_or(""))),
+                "tab" => self.out.push('\u{2003}'),
+                "br" => self.out.push_str("<br>"),
+                "inline" | "anchor" => self.image(node)?,
+                _ => {}
+            }
+        }
+        for tag in open.iter().rev() {
+            self.out.push_str(&format!("</{}>", tag));
+        }
+        Ok(())
+    }
+
+    fn image(&mut self, drawing: Node) -> Result<()> {
+        let Some(rid) = drawing.descendants().find(|n| n.tag_name().name() == "blip").and_then(|b| attr(b, "embed")) else {
+            return Ok(());
+        };
+        let Some(uri) = self.package.data_uri(rid)? else { return Ok(()) };
+        let alt = child(drawing, "docPr").and_then(|d| attr(d, "descr")).unwrap_or("");
+        // Extents are in EMU; 9525 EMU per CSS pixel
+        let size = child(drawing, "extent")
+            .and_then(|e| Some((attr(e, "cx")?.parse::<u64>().ok()? / 9525, attr(e, "cy")?.parse::<u64>().ok()? / 9525)))
+            .map(|(w, h)| format!(" width=\"{}\" height=\"{}\"", w, h))
+            .unwrap_or_default();
+        self.out.push_str(&format!(
+            "<img src=\"{}\" alt=\"{}\"{}>",
+            uri,
+            html_escape::encode_double_quoted_attribute(alt),
+            size
+        ));
+        Ok(())
+    }
+
+    fn table(&mut self, tbl: Node) -> Result<()> {
+        self.out.push_str("<table>\n");
+        for tr in tbl.children().filter(|n| n.tag_name().name() == "tr") {
+            let header = child(tr, "trPr").and_then(|n| child(n, "tblHeader")).is_some();
+            let cell_tag = if header { "th" } else { "td" };
+            self.out.push_str("<tr>");
+            for tc in tr.children().filter(|n| n.tag_name().name() == "tc") {
+                let tcpr = child(tc, "tcPr");
+                if tcpr.and_then(|n| child(n, "vMerge")).map(|m| attr(m, "val") != Some("restart")).unwrap_or(false) {
+                    continue;
+                }
+                match tcpr.and_then(|n| child(n, "gridSpan")).and_then(|n| attr(n, "val")) {
+                    Some(span) => self.out.push_str(&format!("<{} colspan=\"{}\">", cell_tag, span)),
+                    None => self.out.push_str(&format!("<{}>", cell_tag)),
+                }
+                self.blocks(tc, false)?;
+                // Block separators stay outside the row markup
+                let end = self.out.trim_end_matches('\n').len();
+                self.out.truncate(end);
+                self.out.push_str(&format!("</{}>", cell_tag));
+            }
+            self.out.push_str("</tr>\n");
+        }
+        self.out.push_str("</table>\n");
+        Ok(())
+    }
+}
+
+/// Render a DOCX file as a single self-contained HTML page
+pub fn self_contained_html(docx_path: &Path, title: &str) -> Result<String> {
+    let mut package = Package {
+        archive: ZipArchive::new(File::open(docx_path)?)?,
+        rels: HashMap::new(),
+        bullets: HashMap::new(),
+    };
+    let document_xml = package.xml("word/document.xml")?
+        .ok_or_else(|| anyhow::anyhow!("No document.xml found in DOCX file"))?;
+    if let Some(rels_xml) = package.xml("word/_rels/document.xml.rels")? {
+        let rels = Document::parse(&rels_xml)?;
+        package.rels = rels.descendants()
+            .filter_map(|r| Some((r.attribute("Id")?.to_string(), r.attribute("Target")?.to_string())))
+            .collect();
+    }
+    if let Some(numbering_xml) = package.xml("word/numbering.xml")? {
+        let numbering = Document::parse(&numbering_xml)?;
+        let abstract_levels: HashMap<(&str, &str), bool> = numbering.descendants()
+            .filter(|n| n.tag_name().name() == "abstractNum")
+            .flat_map(|a| {
+                let id = attr(a, "abstractNumId").unwrap_or("");
+                a.children().filter(|n| n.tag_name().name() == "lvl").map(move |lvl| {
+                    let bullet = child(lvl, "numFmt").and_then(|f| attr(f, "val")).map(|f| f == "bullet").unwrap_or(true);
+                    ((id, attr(lvl, "ilvl").unwrap_or("0")), bullet)
+                })
+            })
+            .collect();
+        for num in numbering.descendants().filter(|n| n.tag_name().name() == "num") {
+            let (Some(num_id), Some(abstract_id)) = (attr(num, "numId"), child(num, "abstractNumId").and_then(|n| attr(n, "val"))) else { continue };
+            for ((id, level), bullet) in &abstract_levels {
+                if *id == abstract_id {
+                    package.bullets.insert((num_id.to_string(), level.to_string()), *bullet);
+                }
+            }
+        }
+    }
+
+    let doc = Document::parse(&document_xml)?;
+    let body = doc.descendants().find(|n| n.tag_name().name() == "body")
+        .ok_or_else(|| anyhow::anyhow!("document.xml has no body"))?;
+    let mut renderer = Renderer { pages: paragraph_pages(&doc), package: &mut package, current_page: 1, out: String::new(), list: None };
+    renderer.blocks(body, true)?;
+    renderer.close_list();
+
+    Ok(format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>\n<style>\n{}</style>\n</head><body>\n{}</body></html>\n",
+        escape(title),
+        STYLESHEET,
+        renderer.out
+    ))
+}
@@ -0,0 +1,393 @@
+//! HTTP transport (`--http <ADDR>`) for shared deployments. MCP JSON-RPC messages are
+//! POSTed to `/mcp` and answered in the response body (a batch gets an array back;
+//! notifications get `202 Accepted`), and `GET /metrics` serves Prometheus metrics
+//! (see `metrics`) to operators, or to anyone with `HttpOptions::metrics_public`.
+//!
+//! `initialize` starts a session: the reply carries an `Mcp-Session-Id` header that
+//! later requests must send back, and `DELETE /mcp` with it ends the session and closes
+//! its documents. Each session sees only the documents it created or opened. A session
+//! left idle past `HttpOptions::session_idle_timeout` is ended the same way, and a
+//! token may hold at most `HttpOptions::max_sessions_per_token` sessions at once.
+//!
+//! With an admin key or API tokens configured, every `/mcp` request needs
+//! `Authorization: Bearer <token>`; others get `401`. A token's profile and command
+//! lists apply on top of the server settings, a session stays bound to the token that
+//! started it, and the admin key (or a token with `admin = true`) acts as an operator
+//! that sees every session's documents.
+//!
+//! A session can call `create_viewer` to get a viewer key for a reviewer: MCP requests
+//! POSTed to `/viewer` with `Authorization: Bearer <viewer_key>` see that session's
+//! documents read-only, edits included, until the key is revoked or the session ends.
+
+use anyhow::{Context, Result};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::ApiToken;
+use crate::docx_tools::{ClientScope, DocxToolsProvider};
+use crate::security::SecurityMiddleware;
+
+/// MCP protocol revision this transport speaks
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Settings of the HTTP transport
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Bearer token that lets a request act as an operator across sessions
+    pub admin_key: Option<String>,
+    /// Bearer tokens clients authenticate with (see `ApiToken`)
+    pub tokens: Vec<ApiToken>,
+    /// End sessions that send no request for this long; None keeps them until deleted
+    pub session_idle_timeout: Option<Duration>,
+    /// Most sessions one token may have open at once; sessions started without a
+    /// token count together. None for no limit.
+    pub max_sessions_per_token: Option<usize>,
+    /// Serve `/metrics` without a bearer token even when keys or tokens are configured
+    pub metrics_public: bool,
+}
+
+/// An open session
+struct Session {
+    /// Name of the token that started it
+    token: Option<String>,
+    last_seen: Instant,
+}
+
+/// Who a request authenticated as
+struct Caller {
+    /// Token name; None for the admin key or when authentication is off
+    token: Option<String>,
+    admin: bool,
+    restrictions: Option<Arc<SecurityMiddleware>>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    provider: DocxToolsProvider,
+    /// Open sessions by id
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    options: Arc<HttpOptions>,
+    /// Checks of each token, by position in `options.tokens`
+    restrictions: Arc<Vec<Arc<SecurityMiddleware>>>,
+}
+
+impl AppState {
+    /// Authenticate a request. With no admin key or tokens configured every caller is
+    /// let in as an unrestricted, non-operator client.
+    fn caller(&self, headers: &HeaderMap) -> Option<Caller> {
+        let given = bearer_token(headers);
+        if given.zip(self.options.admin_key.as_deref()).is_some_and(|(token, key)| same_secret(token, key)) {
+            return Some(Caller { token: None, admin: true, restrictions: None });
+        }
+        if let Some(given) = given {
+            if let Some(i) = self.options.tokens.iter().position(|t| same_secret(given, &t.token)) {
+                let token = &self.options.tokens[i];
+                return Some(Caller {
+                    token: Some(token.name.clone()),
+                    admin: token.admin,
+                    restrictions: Some(self.restrictions[i].clone()),
+                });
+            }
+        }
+        self.is_open().then_some(Caller { token: None, admin: false, restrictions: None })
+    }
+
+    /// Whether authentication is off: no admin key and no tokens configured
+    fn is_open(&self) -> bool {
+        self.options.admin_key.is_none() && self.options.tokens.is_empty()
+    }
+
+    /// Close the documents and revoke the viewers of a session already taken out of
+    /// `sessions`
+    fn close_session(&self, id: &str, reason: &str) {
+        let closed = self.provider.close_client_documents(id);
+        self.provider.revoke_viewers(id);
+        info!("Ended HTTP session {} ({}; {} documents closed)", id, reason, closed);
+    }
+
+    /// End every session idle for longer than the configured timeout
+    fn expire_idle_sessions(&self) {
+        let Some(timeout) = self.options.session_idle_timeout else { return };
+        let expired: Vec<String> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let ids: Vec<String> = sessions.iter()
+                .filter(|(_, session)| session.last_seen.elapsed() > timeout)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.into_iter().filter(|id| sessions.remove(id).is_some()).collect()
+        };
+        for id in expired {
+            self.close_session(&id, "idle");
+        }
+    }
+}
+
+/// The `/mcp` and `/metrics` routes over `provider`
+pub fn router(provider: DocxToolsProvider, options: HttpOptions) -> axum::Router {
+    routes(app_state(provider, options))
+}
+
+fn app_state(provider: DocxToolsProvider, options: HttpOptions) -> AppState {
+    let restrictions = options.tokens.iter()
+        .map(|token| Arc::new(SecurityMiddleware::new(token.restrictions())))
+        .collect();
+    AppState {
+        provider,
+        sessions: Arc::default(),
+        options: Arc::new(options),
+        restrictions: Arc::new(restrictions),
+    }
+}
+
+fn routes(state: AppState) -> axum::Router {
+    axum::Router::new()
+        .route("/mcp", post(mcp).delete(end_session))
+        .route("/viewer", post(viewer))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// Listen on `addr` (e.g. `127.0.0.1:8080`) until the process exits
+pub async fn serve(provider: DocxToolsProvider, addr: &str, options: HttpOptions) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    if options.admin_key.is_none() && options.tokens.is_empty() {
+        warn!("No admin key or API tokens configured: any client that can reach {} can call every tool", addr);
+    }
+    info!("Serving MCP over HTTP on http://{}/mcp (metrics on /metrics{})", listener.local_addr()?, if options.metrics_public { ", public" } else { "" });
+    let state = app_state(provider, options);
+    // Requests expire idle sessions too; this catches them when no requests come in
+    if let Some(timeout) = state.options.session_idle_timeout {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(timeout.min(Duration::from_secs(60)));
+            loop {
+                ticks.tick().await;
+                state.expire_idle_sessions();
+            }
+        });
+    }
+    axum::serve(listener, routes(state)).await?;
+    Ok(())
+}
+
+async fn mcp(State(state): State<AppState>, headers: HeaderMap, body: String) -> Response {
+    let Some(caller) = state.caller(&headers) else {
+        return unauthorized();
+    };
+    let message: Value = match serde_json::from_str(&body) {
+        Ok(message) => message,
+        Err(e) => return Json(error_reply(Value::Null, PARSE_ERROR, format!("Parse error: {}", e))).into_response(),
+    };
+    state.expire_idle_sessions();
+    let initializing = match &message {
+        Value::Array(batch) => batch.iter().any(|m| m["method"] == "initialize"),
+        message => message["method"] == "initialize",
+    };
+    let session = match headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(id) => match state.sessions.lock().unwrap().get_mut(id) {
+            // The admin key may act in any session; tokens only in their own
+            Some(session) if caller.admin || session.token == caller.token => {
+                session.last_seen = Instant::now();
+                id.to_string()
+            }
+            Some(_) => return session_error(StatusCode::FORBIDDEN, "This session was started with a different token"),
+            None => return session_error(StatusCode::NOT_FOUND, "Unknown or ended session; send initialize to start a new one"),
+        },
+        None if initializing => {
+            let mut sessions = state.sessions.lock().unwrap();
+            if let Some(max) = state.options.max_sessions_per_token {
+                if sessions.values().filter(|s| s.token == caller.token).count() >= max {
+                    return session_error(StatusCode::TOO_MANY_REQUESTS, "This token has too many open sessions; end one with DELETE /mcp first");
+                }
+            }
+            let id = uuid::Uuid::new_v4().simple().to_string();
+            info!("Started HTTP session {} (token: {})", id, caller.token.as_deref().unwrap_or("none"));
+            sessions.insert(id.clone(), Session { token: caller.token.clone(), last_seen: Instant::now() });
+            id
+        }
+        None => return session_error(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header; send initialize first"),
+    };
+    let provider = state.provider.for_client(ClientScope {
+        id: session.clone(),
+        admin: caller.admin,
+        restrictions: caller.restrictions,
+    });
+
+    let mut response = match handle_body(&provider, message).await {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&session) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+/// MCP requests of a reviewer holding a viewer key; no session is needed
+async fn viewer(State(state): State<AppState>, headers: HeaderMap, body: String) -> Response {
+    let Some(provider) = bearer_token(&headers).and_then(|key| state.provider.viewer(key)) else {
+        return unauthorized();
+    };
+    match handle_text(&provider, &body).await {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// End the session named by the `Mcp-Session-Id` header and close its documents
+async fn end_session(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(caller) = state.caller(&headers) else {
+        return unauthorized();
+    };
+    let Some(id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    state.expire_idle_sessions();
+    {
+        let mut sessions = state.sessions.lock().unwrap();
+        match sessions.get(id) {
+            Some(session) if caller.admin || session.token == caller.token => sessions.remove(id),
+            Some(_) => return StatusCode::FORBIDDEN.into_response(),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        };
+    }
+    state.close_session(id, "deleted");
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Prometheus metrics, for the admin key and admin tokens unless they are public or
+/// authentication is off
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !state.options.metrics_public && !state.is_open() {
+        match state.caller(&headers) {
+            Some(caller) if caller.admin => {}
+            Some(_) => return StatusCode::FORBIDDEN.into_response(),
+            None => return unauthorized(),
+        }
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], state.provider.render_metrics()).into_response()
+}
+
+fn session_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(error_reply(Value::Null, INVALID_REQUEST, message.to_string()))).into_response()
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(error_reply(Value::Null, INVALID_REQUEST, "Missing or unknown bearer token".to_string())),
+    ).into_response()
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ")
+}
+
+/// Compare secrets through their SHA-256 digests, so the time taken shows neither where
+/// they differ nor how long the expected one is
+fn same_secret(given: &str, expected: &str) -> bool {
+    use sha2::{Digest, Sha256};
+    let (given, expected) = (Sha256::digest(given.as_bytes()), Sha256::digest(expected.as_bytes()));
+    given.iter().zip(expected.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Answer a raw JSON-RPC message or batch, or report that it does not parse; None when
+/// nothing needs a reply
+pub async fn handle_text(provider: &DocxToolsProvider, text: &str) -> Option<Value> {
+    match serde_json::from_str(text) {
+        Ok(message) => handle_body(provider, message).await,
+        Err(e) => Some(error_reply(Value::Null, PARSE_ERROR, format!("Parse error: {}", e))),
+    }
+}
+
+/// Answer a message or a batch of them; None when nothing needs a reply
+async fn handle_body(provider: &DocxToolsProvider, message: Value) -> Option<Value> {
+    match message {
+        Value::Array(batch) => {
+            let mut replies = Vec::with_capacity(batch.len());
+            for message in batch {
+                replies.extend(handle_message(provider, message).await);
+            }
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        message => handle_message(provider, message).await,
+    }
+}
+
+/// Answer one JSON-RPC message; notifications (no `id`) get no reply
+pub async fn handle_message(provider: &DocxToolsProvider, message: Value) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(|m| m.as_str()) else {
+        return Some(error_reply(id.unwrap_or(Value::Null), INVALID_REQUEST, "Invalid request: missing method".into()));
+    };
+    let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {"tools": {}, "resources": {}},
+            "serverInfo": {"name": "docx-mcp-server", "version": env!("CARGO_PKG_VERSION")},
+            "instructions": "DOCX tools for reading and exporting",
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({"tools": provider.list_tools_with_schemas().await})),
+        "tools/call" => match params.get("name").and_then(|n| n.as_str()) {
+            Some(name) => {
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                Ok(call_result(provider.call_tool(name, arguments).await))
+            }
+            None => Err((INVALID_PARAMS, "tools/call needs params.name".to_string())),
+        },
+        "resources/list" => Ok(json!({"resources": provider.summary_resources().into_iter()
+            .map(|(uri, name)| json!({"uri": uri, "name": format!("{} (summary)", name), "mimeType": "application/json"}))
+            .collect::<Vec<_>>()})),
+        "resources/read" => match params.get("uri").and_then(|u| u.as_str()) {
+            Some(uri) => provider.read_summary_resource(uri)
+                .map(|text| json!({"contents": [{"uri": uri, "mimeType": "application/json", "text": text}]}))
+                .map_err(|e| (INVALID_PARAMS, e.to_string())),
+            None => Err((INVALID_PARAMS, "resources/read needs params.uri".to_string())),
+        },
+        _ if method.starts_with("notifications/") => return None,
+        other => Err((METHOD_NOT_FOUND, format!("Method not found: {}", other))),
+    };
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => error_reply(id, code, message),
+    })
+}
+
+/// A tool response in the MCP `tools/call` result shape
+fn call_result(response: mcp_core::types::CallToolResponse) -> Value {
+    use mcp_core::types::ToolResponseContent;
+    let content: Vec<Value> = response.content.iter().filter_map(|block| match block {
+        ToolResponseContent::Text(t) => Some(json!({"type": "text", "text": t.text})),
+        ToolResponseContent::Image(i) => Some(json!({"type": "image", "data": i.data, "mimeType": i.mime_type})),
+        _ => None,
+    }).collect();
+    let mut result = json!({"content": content, "isError": response.is_error.unwrap_or(false)});
+    if let Some(structured) = response.meta.as_ref().and_then(|m| m.get("structuredContent")) {
+        result["structuredContent"] = structured.clone();
+    }
+    result
+}
+
+fn error_reply(id: Value, code: i64, message: String) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
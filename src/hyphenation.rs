@@ -0,0 +1,181 @@
+//! Liang (TeX-style) hyphenation for the PDF renderer.
+//!
+//! Patterns are the `hyph_*.dic` files used by LibreOffice (libhyphen format). They are
+//! not bundled; fetch them with `docx-mcp hyphenation download` (stored under
+//! `assets/dictionaries`, or `DOCX_MCP_DICT_DIR` when set). Without a pattern file for
+//! the text's language, lines are wrapped at spaces only.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::fonts_cli::{dictionaries_dir, fetch_bytes};
+
+const PATTERNS_BASE_URL: &str = "https://raw.githubusercontent.com/LibreOffice/dictionaries/master";
+
+/// Supported pattern files: (our language id, path in the LibreOffice dictionaries repo)
+pub const HYPHENATION_DICTIONARIES: &[(&str, &str)] = &[
+    ("en_US", "en/hyph_en_US.dic"),
+    ("en_GB", "en/hyph_en_GB.dic"),
+    ("de_DE", "de/hyph_de_DE.dic"),
+    ("fr_FR", "fr_FR/hyph_fr.dic"),
+    ("es_ES", "es/hyph_es.dic"),
+];
+
+#[derive(Debug, Clone)]
+pub struct Hyphenator {
+    language: String,
+    /// Pattern letters to inter-letter values (one more value than letters)
+    patterns: HashMap<String, Vec<u8>>,
+    longest_pattern: usize,
+    left_min: usize,
+    right_min: usize,
+}
+
+impl Hyphenator {
+    /// Load the patterns for `language` (e.g. "en_US") from the dictionaries directory
+    pub fn load(language: &str) -> Result<Self> {
+        let path = dictionaries_dir().join(format!("hyph_{}.dic", language));
+        if !path.exists() {
+            anyhow::bail!(
+                "Hyphenation patterns for {} not found at {}. Run `docx-mcp hyphenation download --lang {}`",
+                language, path.display(), language
+            );
+        }
+        let bytes = fs::read(&path).with_context(|| format!("read patterns {}", path.display()))?;
+        Ok(Self::from_patterns(language, &decode(&bytes)))
+    }
+
+    /// Build a hyphenator from libhyphen `.dic` contents: an optional charset line,
+    /// LEFTHYPHENMIN/RIGHTHYPHENMIN settings and one pattern per line (`hy3ph`)
+    pub fn from_patterns(language: &str, content: &str) -> Self {
+        let mut hyphenator = Self {
+            language: language.to_string(),
+            patterns: HashMap::new(),
+            longest_pattern: 0,
+            left_min: 2,
+            right_min: 3,
+        };
+        for line in content.lines().map(str::trim) {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next().and_then(|v| v.parse::<usize>().ok())) {
+                (Some("LEFTHYPHENMIN"), Some(n)) => hyphenator.left_min = n.max(1),
+                (Some("RIGHTHYPHENMIN"), Some(n)) => hyphenator.right_min = n.max(1),
+                _ => {}
+            }
+            // Skip the charset line, settings, comments and non-standard (`/`) patterns
+            let is_pattern = !line.is_empty()
+                && !line.starts_with('%')
+                && !line.contains('/')
+                && !line.contains(' ')
+                && line.chars().any(|c| c.is_ascii_digit())
+                && !line.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-');
+            if !is_pattern {
+                continue;
+            }
+            let mut letters = String::new();
+            let mut values = vec![0u8];
+            for c in line.chars() {
+                match c.to_digit(10) {
+                    Some(d) => *values.last_mut().unwrap() = d as u8,
+                    None => {
+                        letters.extend(c.to_lowercase());
+                        values.push(0);
+                    }
+                }
+            }
+            hyphenator.longest_pattern = hyphenator.longest_pattern.max(letters.chars().count());
+            hyphenator.patterns.insert(letters, values);
+        }
+        hyphenator
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Character offsets inside `word` where it may be broken with a hyphen
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let letters: Vec<char> = word.chars().flat_map(|c| c.to_lowercase()).collect();
+        let n = letters.len();
+        if n < self.left_min + self.right_min || letters.len() != word.chars().count() {
+            return Vec::new();
+        }
+        let padded: Vec<char> = std::iter::once('.').chain(letters).chain(std::iter::once('.')).collect();
+        let mut points = vec![0u8; padded.len() + 1];
+        for start in 0..padded.len() {
+            let mut key = String::new();
+            for (len, c) in padded[start..].iter().take(self.longest_pattern).enumerate() {
+                key.push(*c);
+                if let Some(values) = self.patterns.get(&key) {
+                    for (k, v) in values.iter().enumerate().take(len + 2) {
+                        let slot = &mut points[start + k];
+                        *slot = (*slot).max(*v);
+                    }
+                }
+            }
+        }
+        // points[i + 1] sits before word character i (index 0 is the leading '.')
+        (self.left_min..=n - self.right_min)
+            .filter(|&i| points[i + 1] % 2 == 1)
+            .collect()
+    }
+}
+
+/// Pattern files declare their charset on the first line; older ones are Latin-1
+fn decode(bytes: &[u8]) -> String {
+    let first_line = bytes.split(|b| *b == b'\n').next().unwrap_or_default();
+    if String::from_utf8_lossy(first_line).to_ascii_uppercase().contains("8859-1") {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+/// Pattern language for a BCP-47 tag or language id ("en-US", "en_US", "de")
+pub fn language_for_tag(tag: &str) -> Option<&'static str> {
+    let normalized = tag.replace('-', "_");
+    HYPHENATION_DICTIONARIES.iter()
+        .map(|(lang, _)| *lang)
+        .find(|lang| lang.eq_ignore_ascii_case(&normalized))
+        .or_else(|| {
+            let primary = normalized.split('_').next().unwrap_or_default().to_ascii_lowercase();
+            HYPHENATION_DICTIONARIES.iter().map(|(lang, _)| *lang).find(|lang| lang.starts_with(&format!("{}_", primary)))
+        })
+}
+
+/// Download pattern files for the given languages (all supported when empty)
+pub fn download_patterns_blocking(languages: &[String]) -> Result<()> {
+    let dir = dictionaries_dir();
+    fs::create_dir_all(&dir).context("create dictionaries dir")?;
+    for (lang, upstream) in HYPHENATION_DICTIONARIES {
+        if !languages.is_empty() && !languages.iter().any(|l| l == lang) {
+            continue;
+        }
+        let url = format!("{}/{}", PATTERNS_BASE_URL, upstream);
+        let bytes = fetch_bytes(&url)?;
+        let out = dir.join(format!("hyph_{}.dic", lang));
+        fs::write(&out, bytes).with_context(|| format!("write {}", out.display()))?;
+    }
+    verify_patterns_blocking(languages)
+}
+
+/// Check that the requested pattern files are present and contain patterns
+pub fn verify_patterns_blocking(languages: &[String]) -> Result<()> {
+    for (lang, _) in HYPHENATION_DICTIONARIES {
+        if !languages.is_empty() && !languages.iter().any(|l| l == lang) {
+            continue;
+        }
+        let path = dictionaries_dir().join(format!("hyph_{}.dic", lang));
+        if !path.exists() {
+            if languages.is_empty() {
+                continue;
+            }
+            anyhow::bail!("missing hyphenation patterns: {}", path.display());
+        }
+        if Hyphenator::load(lang)?.patterns.is_empty() {
+            anyhow::bail!("pattern file {} contains no patterns", path.display());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Invoice data and totals for `generate_invoice`.
+//!
+//! Amounts are rounded to the currency's minor unit per line, so the printed line
+//! amounts always add up to the printed totals.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::{self, Locale};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineItem {
+    pub description: String,
+    #[serde(default = "one")]
+    pub quantity: f64,
+    pub unit_price: f64,
+    /// Tax percentage for this line (e.g. 20 for 20%); falls back to the invoice rate
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invoice {
+    pub company_name: String,
+    #[serde(default)]
+    pub invoice_number: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub due_date: Option<String>,
+    /// Customer name and address, one line per entry
+    #[serde(default)]
+    pub bill_to: Vec<String>,
+    pub line_items: Vec<LineItem>,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// Default tax percentage for lines without their own `tax_rate`
+    #[serde(default)]
+    pub tax_rate: f64,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_locale() -> String {
+    "en-US".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+    pub tax_rate: f64,
+    pub net: f64,
+    pub tax: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceTotals {
+    pub lines: Vec<InvoiceLine>,
+    pub subtotal: f64,
+    pub tax: f64,
+    pub total: f64,
+}
+
+fn round_to(value: f64, decimals: usize) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+impl Invoice {
+    pub fn locale(&self) -> Result<Locale> {
+        locale::lookup(&self.locale)
+    }
+
+    /// Line amounts and totals, rounded to the currency's minor unit
+    pub fn totals(&self) -> Result<InvoiceTotals> {
+        if self.line_items.is_empty() {
+            anyhow::bail!("An invoice needs at least one line item");
+        }
+        let (_, decimals) = locale::currency(&self.currency);
+        let mut lines = Vec::with_capacity(self.line_items.len());
+        for (i, item) in self.line_items.iter().enumerate() {
+            let tax_rate = item.tax_rate.unwrap_or(self.tax_rate);
+            if !item.quantity.is_finite() || !item.unit_price.is_finite() || !tax_rate.is_finite() || tax_rate < 0.0 {
+                anyhow::bail!("Line item {} has an invalid quantity, price or tax rate", i);
+            }
+            let net = round_to(item.quantity * item.unit_price, decimals);
+            lines.push(InvoiceLine {
+                description: item.description.clone(),
+                quantity: item.quantity,
+                unit_price: item.unit_price,
+                tax_rate,
+                net,
+                tax: round_to(net * tax_rate / 100.0, decimals),
+            });
+        }
+        let subtotal = round_to(lines.iter().map(|l| l.net).sum(), decimals);
+        let tax = round_to(lines.iter().map(|l| l.tax).sum(), decimals);
+        Ok(InvoiceTotals { lines, subtotal, tax, total: round_to(subtotal + tax, decimals) })
+    }
+}
@@ -0,0 +1,70 @@
+//! Language identification helpers (pure Rust, offline) used for per-paragraph
+//! detection and for choosing proofing language tags (`w:lang`).
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code as reported by the detector (e.g. "eng", "cmn")
+    pub code: String,
+    /// English language name
+    pub name: String,
+    /// BCP-47 tag suitable for `w:lang` (e.g. "en-US"), when known
+    pub tag: Option<String>,
+    pub script: String,
+    pub confidence: f64,
+    pub reliable: bool,
+}
+
+/// Detect the dominant language of a piece of text. Returns None for text that has
+/// no letters (numbers, punctuation) or that the detector cannot classify.
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    if !text.chars().any(|c| c.is_alphabetic()) {
+        return None;
+    }
+    let info = whatlang::detect(text)?;
+    let lang = info.lang();
+    Some(DetectedLanguage {
+        code: lang.code().to_string(),
+        name: lang.eng_name().to_string(),
+        tag: bcp47_tag(lang.code()).map(|t| t.to_string()),
+        script: format!("{:?}", info.script()),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+    })
+}
+
+/// Map an ISO 639-3 code to the default BCP-47 tag Word uses for proofing
+pub fn bcp47_tag(code: &str) -> Option<&'static str> {
+    let tag = match code {
+        "eng" => "en-US",
+        "spa" => "es-ES",
+        "fra" => "fr-FR",
+        "deu" => "de-DE",
+        "ita" => "it-IT",
+        "por" => "pt-BR",
+        "nld" => "nl-NL",
+        "rus" => "ru-RU",
+        "ukr" => "uk-UA",
+        "pol" => "pl-PL",
+        "ces" => "cs-CZ",
+        "swe" => "sv-SE",
+        "dan" => "da-DK",
+        "nob" => "nb-NO",
+        "fin" => "fi-FI",
+        "tur" => "tr-TR",
+        "ell" => "el-GR",
+        "heb" => "he-IL",
+        "ara" => "ar-SA",
+        "pes" => "fa-IR",
+        "hin" => "hi-IN",
+        "jpn" => "ja-JP",
+        "cmn" => "zh-CN",
+        "kor" => "ko-KR",
+        "tha" => "th-TH",
+        "vie" => "vi-VN",
+        "ind" => "id-ID",
+        _ => return None,
+    };
+    Some(tag)
+}
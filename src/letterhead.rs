@@ -0,0 +1,93 @@
+//! Letterhead profiles for `apply_letterhead`, read from the TOML file given with
+//! `--letterheads`. Each table is one profile:
+//!
+//! ```toml
+//! [acme]
+//! logo = "logos/acme.png"        # relative to the profiles file
+//! logo_width = 160               # pixels
+//! address = ["Acme Ltd", "1 Queen's Road", "Hong Kong"]
+//! footer = "Acme Ltd is registered in Hong Kong, No. 1234567"
+//! font = "Arial"
+//! font_size = 10                 # points
+//! page_size = "A4"
+//! margins = { top = 1.2, bottom = 1.0, left = 1.0, right = 1.0 }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::docx_handler::MarginsSpec;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LetterheadProfile {
+    /// Image file for the header, relative to the profiles file
+    pub logo: Option<PathBuf>,
+    pub logo_width: Option<u32>,
+    pub logo_height: Option<u32>,
+    /// left (default), center or right
+    pub logo_position: Option<String>,
+    /// Lines of the address block, right-aligned in the header
+    #[serde(default)]
+    pub address: Vec<String>,
+    /// Footer text such as the registered office and company number
+    pub footer: Option<String>,
+    /// Footer alignment (default center)
+    pub footer_alignment: Option<String>,
+    /// Default font of the document
+    pub font: Option<String>,
+    /// Default font size in points
+    pub font_size: Option<f32>,
+    pub page_size: Option<String>,
+    /// Margins in inches
+    pub margins: Option<MarginsSpec>,
+}
+
+/// A profile with its logo read from disk, ready to apply
+#[derive(Debug, Clone)]
+pub struct Letterhead {
+    pub name: String,
+    pub profile: LetterheadProfile,
+    pub logo: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LetterheadProfiles {
+    path: PathBuf,
+}
+
+impl LetterheadProfiles {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All profiles by name. The file is read on every call, so edits show up without
+    /// a restart.
+    pub fn load(&self) -> Result<BTreeMap<String, LetterheadProfile>> {
+        let text = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read letterhead profiles {:?}", self.path))?;
+        toml::from_str(&text).with_context(|| format!("Invalid letterhead profiles in {:?}", self.path))
+    }
+
+    /// The named profile with its logo loaded
+    pub fn resolve(&self, name: &str) -> Result<Letterhead> {
+        let mut profiles = self.load()?;
+        let profile = profiles.remove(name.trim()).ok_or_else(|| anyhow::anyhow!(
+            "No letterhead profile named '{}' in {:?} (see list_letterheads)", name, self.path
+        ))?;
+        let logo = match &profile.logo {
+            Some(logo) => {
+                let logo_path = self.path.parent().map(|dir| dir.join(logo)).unwrap_or_else(|| logo.clone());
+                Some(std::fs::read(&logo_path).with_context(|| format!("Failed to read logo {:?}", logo_path))?)
+            }
+            None => None,
+        };
+        Ok(Letterhead { name: name.trim().to_string(), profile, logo })
+    }
+}
@@ -1,13 +1,53 @@
 pub mod security;
+pub mod config;
 pub mod fonts_cli;
+pub mod font_fallback;
+pub mod client_setup;
+pub mod self_test;
+pub mod bench;
 pub mod response;
+pub mod schema;
+pub mod tool_hints;
+pub mod locks;
+pub mod notify;
+pub mod object_store;
+pub mod metrics;
+pub mod http;
+pub mod stdio;
 
 // Expose primary modules for tests and external use
 pub mod docx_tools;
 pub mod docx_handler;
+pub mod docx_reader;
 pub mod pure_converter;
 pub mod converter;
+pub mod language;
+pub mod analysis;
+pub mod html_export;
+pub mod templating;
+pub mod locale;
+pub mod invoice;
+pub mod bidi;
+pub mod text_transform;
+pub mod review;
+pub mod changes;
+pub mod xliff;
+pub mod clauses;
+pub mod letterhead;
+pub mod rebrand;
+pub mod spill;
+pub mod script;
+pub mod oneshot;
+pub mod repl;
+#[cfg(feature = "spellcheck")]
+pub mod spelling;
+#[cfg(feature = "hyphenation")]
+pub mod hyphenation;
 #[cfg(feature = "advanced-docx")]
 pub mod advanced_docx;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(feature = "embedded-fonts")]
+pub mod fonts;
 
 pub use security::{Args, SecurityConfig, SecurityMiddleware, SecurityError};
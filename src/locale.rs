@@ -0,0 +1,319 @@
+//! Locale conventions for numbers, currency amounts and dates ("$1,234.56" vs
+//! "1.234,56 €", "03/01/2024" vs "01.03.2024").
+//!
+//! Locales are BCP-47 tags ("de-DE", "de_DE" or just "de"). The table covers common
+//! business locales; unknown tags are an error rather than a silent en-US fallback.
+//! `Formatting` bundles a locale with a default currency and per-field format specs
+//! for the templating and table tools.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub tag: &'static str,
+    decimal: char,
+    group: &'static str,
+    /// Currency symbol before the amount ("$1.00") rather than after ("1,00 €")
+    currency_first: bool,
+    /// Space between symbol and amount
+    currency_space: bool,
+    /// Indian grouping: the first group has three digits, the rest two ("12,34,567")
+    indian_grouping: bool,
+}
+
+const fn locale(tag: &'static str, decimal: char, group: &'static str, currency_first: bool, currency_space: bool) -> Locale {
+    Locale { tag, decimal, group, currency_first, currency_space, indian_grouping: false }
+}
+
+pub const LOCALES: &[Locale] = &[
+    locale("en-US", '.', ",", true, false),
+    locale("en-GB", '.', ",", true, false),
+    locale("en-AU", '.', ",", true, false),
+    locale("en-CA", '.', ",", true, false),
+    locale("en-HK", '.', ",", true, false),
+    Locale { indian_grouping: true, ..locale("en-IN", '.', ",", true, false) },
+    locale("de-DE", ',', ".", false, true),
+    locale("de-AT", ',', "\u{a0}", true, true),
+    locale("de-CH", '.', "'", true, true),
+    locale("fr-FR", ',', "\u{202f}", false, true),
+    locale("fr-CA", ',', "\u{a0}", false, true),
+    locale("es-ES", ',', ".", false, true),
+    locale("es-MX", '.', ",", true, false),
+    locale("it-IT", ',', ".", false, true),
+    locale("nl-NL", ',', ".", true, true),
+    locale("pt-BR", ',', ".", true, true),
+    locale("pt-PT", ',', "\u{a0}", false, true),
+    locale("sv-SE", ',', "\u{a0}", false, true),
+    locale("pl-PL", ',', "\u{a0}", false, true),
+    locale("ja-JP", '.', ",", true, false),
+    locale("zh-CN", '.', ",", true, false),
+    locale("zh-HK", '.', ",", true, false),
+    locale("ko-KR", '.', ",", true, false),
+];
+
+/// Look up a locale by tag; a bare language ("de") picks its first listed region
+pub fn lookup(tag: &str) -> Result<Locale> {
+    let normalized = tag.trim().replace('_', "-");
+    LOCALES.iter()
+        .find(|l| l.tag.eq_ignore_ascii_case(&normalized))
+        .or_else(|| {
+            let primary = normalized.split('-').next().unwrap_or_default().to_ascii_lowercase();
+            LOCALES.iter().find(|l| l.tag.split('-').next() == Some(primary.as_str()))
+        })
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!(
+            "Unsupported locale '{}' (supported: {})",
+            tag, LOCALES.iter().map(|l| l.tag).collect::<Vec<_>>().join(", ")
+        ))
+}
+
+/// Symbol and minor-unit digits for an ISO 4217 code; unknown codes print the code itself
+pub fn currency(code: &str) -> (String, usize) {
+    let code = code.trim().to_ascii_uppercase();
+    let (symbol, decimals) = match code.as_str() {
+        "USD" => ("$", 2),
+        "EUR" => ("€", 2),
+        "GBP" => ("£", 2),
+        "JPY" => ("¥", 0),
+        "CNY" => ("CN¥", 2),
+        "HKD" => ("HK$", 2),
+        "AUD" => ("A$", 2),
+        "CAD" => ("CA$", 2),
+        "CHF" => ("CHF", 2),
+        "INR" => ("₹", 2),
+        "KRW" => ("₩", 0),
+        "BRL" => ("R$", 2),
+        "MXN" => ("MX$", 2),
+        "SEK" => ("kr", 2),
+        "PLN" => ("zł", 2),
+        _ => return (code, 2),
+    };
+    (symbol.to_string(), decimals)
+}
+
+impl Locale {
+    /// Format with grouping and exactly `decimals` fraction digits
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let fixed = format!("{:.*}", decimals, value.abs());
+        let (int_part, frac_part) = match fixed.split_once('.') {
+            Some((i, f)) => (i, Some(f)),
+            None => (fixed.as_str(), None),
+        };
+        let mut out = String::new();
+        if value < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        out.push_str(&self.group_digits(int_part));
+        if let Some(frac) = frac_part {
+            out.push(self.decimal);
+            out.push_str(frac);
+        }
+        out
+    }
+
+    /// Format an amount in `currency_code` (ISO 4217) with the locale's symbol placement
+    pub fn format_currency(&self, value: f64, currency_code: &str) -> String {
+        let (symbol, decimals) = currency(currency_code);
+        let number = self.format_number(value, decimals);
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest.to_string()),
+            None => ("", number),
+        };
+        let space = if self.currency_space { "\u{a0}" } else { "" };
+        if self.currency_first {
+            format!("{}{}{}{}", sign, symbol, space, digits)
+        } else {
+            format!("{}{}{}{}", sign, digits, space, symbol)
+        }
+    }
+
+    fn group_digits(&self, digits: &str) -> String {
+        let chars: Vec<char> = digits.chars().collect();
+        let mut groups: Vec<String> = Vec::new();
+        let mut end = chars.len();
+        let mut size = 3;
+        while end > 0 {
+            let start = end.saturating_sub(size);
+            groups.push(chars[start..end].iter().collect());
+            end = start;
+            if self.indian_grouping {
+                size = 2;
+            }
+        }
+        groups.reverse();
+        groups.join(self.group)
+    }
+}
+
+const MONTHS_EN: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+const MONTHS_DE: [&str; 12] = ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"];
+const MONTHS_FR: [&str; 12] = ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"];
+const MONTHS_ES: [&str; 12] = ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"];
+const MONTHS_IT: [&str; 12] = ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"];
+const MONTHS_NL: [&str; 12] = ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"];
+const MONTHS_PT: [&str; 12] = ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"];
+const MONTHS_SV: [&str; 12] = ["januari", "februari", "mars", "april", "maj", "juni", "juli", "augusti", "september", "oktober", "november", "december"];
+// Polish dates use the genitive month
+const MONTHS_PL: [&str; 12] = ["stycznia", "lutego", "marca", "kwietnia", "maja", "czerwca", "lipca", "sierpnia", "września", "października", "listopada", "grudnia"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// Numeric, e.g. 03/01/2024 (en-US) or 01.03.2024 (de-DE)
+    Short,
+    /// Month spelled out, e.g. March 1, 2024 or 1. März 2024
+    Long,
+    /// 2024-03-01 regardless of locale
+    Iso,
+}
+
+impl std::str::FromStr for DateStyle {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.trim().to_ascii_lowercase().as_str() {
+            "" | "short" => DateStyle::Short,
+            "long" => DateStyle::Long,
+            "iso" => DateStyle::Iso,
+            other => anyhow::bail!("unknown date style '{}' (use short, long or iso)", other),
+        })
+    }
+}
+
+/// Parse "2024-03-01", or the date part of an RFC 3339 timestamp
+pub fn parse_date(text: &str) -> Result<NaiveDate> {
+    let text = text.trim();
+    let date = text.get(..10).unwrap_or(text);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("'{}' is not a YYYY-MM-DD date", text))
+}
+
+impl Locale {
+    fn language(&self) -> &'static str {
+        self.tag.split('-').next().unwrap_or(self.tag)
+    }
+
+    /// Format a percentage (19 -> "19%", or "19 %" where the locale spaces it)
+    pub fn format_percent(&self, value: f64, decimals: usize) -> String {
+        let space = if matches!(self.language(), "de" | "fr" | "es" | "sv" | "pl") { "\u{a0}" } else { "" };
+        format!("{}{}%", self.format_number(value, decimals), space)
+    }
+
+    pub fn format_date(&self, date: NaiveDate, style: DateStyle) -> String {
+        let (d, m, y) = (date.day(), date.month() as usize, date.year());
+        match style {
+            DateStyle::Iso => date.format("%Y-%m-%d").to_string(),
+            DateStyle::Short => {
+                let pattern = match self.tag {
+                    "en-US" => "%m/%d/%Y",
+                    "en-CA" | "sv-SE" => "%Y-%m-%d",
+                    "nl-NL" => "%d-%m-%Y",
+                    _ => match self.language() {
+                        "de" | "pl" => "%d.%m.%Y",
+                        "ja" | "zh" | "ko" => "%Y/%m/%d",
+                        _ => "%d/%m/%Y",
+                    },
+                };
+                date.format(pattern).to_string()
+            }
+            DateStyle::Long => match self.language() {
+                "en" if self.tag == "en-US" => format!("{} {}, {}", MONTHS_EN[m - 1], d, y),
+                "en" => format!("{} {} {}", d, MONTHS_EN[m - 1], y),
+                "de" => format!("{}. {} {}", d, MONTHS_DE[m - 1], y),
+                "fr" => format!("{} {} {}", d, MONTHS_FR[m - 1], y),
+                "es" => format!("{} de {} de {}", d, MONTHS_ES[m - 1], y),
+                "it" => format!("{} {} {}", d, MONTHS_IT[m - 1], y),
+                "nl" => format!("{} {} {}", d, MONTHS_NL[m - 1], y),
+                "pt" => format!("{} de {} de {}", d, MONTHS_PT[m - 1], y),
+                "sv" => format!("{} {} {}", d, MONTHS_SV[m - 1], y),
+                "pl" => format!("{} {} {}", d, MONTHS_PL[m - 1], y),
+                "ko" => format!("{}년 {}월 {}일", y, m, d),
+                _ => format!("{}年{}月{}日", y, m, d),
+            },
+        }
+    }
+}
+
+/// Locale, default currency and per-field format specs used when rendering values.
+///
+/// A spec is `kind[:argument]`: `number[:decimals]` (default 2), `integer`,
+/// `currency[:CODE]`, `percent[:decimals]` (default 0) or `date[:short|long|iso]`.
+#[derive(Debug, Clone)]
+pub struct Formatting {
+    pub locale: Locale,
+    pub currency: String,
+    /// Field or column header name to format spec
+    pub fields: HashMap<String, String>,
+}
+
+impl Default for Formatting {
+    fn default() -> Self {
+        Self { locale: LOCALES[0], currency: "USD".to_string(), fields: HashMap::new() }
+    }
+}
+
+impl Formatting {
+    /// Build from tool arguments: `locale`, `currency` and a `formats` object
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let mut formatting = Self::default();
+        if let Some(tag) = args.get("locale").and_then(|v| v.as_str()) {
+            formatting.locale = lookup(tag)?;
+        }
+        if let Some(code) = args.get("currency").and_then(|v| v.as_str()) {
+            formatting.currency = code.trim().to_ascii_uppercase();
+        }
+        if let Some(formats) = args.get("formats").and_then(|v| v.as_object()) {
+            for (field, spec) in formats {
+                let spec = spec.as_str().with_context(|| format!("format for '{}' must be a string", field))?;
+                // Reject bad specs up front rather than per value
+                formatting.format(&Value::from(0), spec)?;
+                formatting.fields.insert(field.clone(), spec.to_string());
+            }
+        }
+        Ok(formatting)
+    }
+
+    /// Format spec configured for a field, matched like column headers (case-insensitive)
+    pub fn spec_for(&self, field: &str) -> Option<&str> {
+        self.fields.get(field)
+            .or_else(|| self.fields.iter().find(|(k, _)| k.eq_ignore_ascii_case(field.trim())).map(|(_, v)| v))
+            .map(|s| s.as_str())
+    }
+
+    /// Format a value with a spec; numbers may be given as JSON numbers or numeric strings
+    pub fn format(&self, value: &Value, spec: &str) -> Result<String> {
+        let (kind, arg) = match spec.split_once(':') {
+            Some((k, a)) => (k.trim(), Some(a.trim())),
+            None => (spec.trim(), None),
+        };
+        let number = || -> Result<f64> {
+            match value {
+                Value::Number(n) => n.as_f64().context("number out of range"),
+                Value::String(s) => s.trim().parse::<f64>().with_context(|| format!("'{}' is not a number", s)),
+                other => anyhow::bail!("{} is not a number", other),
+            }
+        };
+        let decimals = |default: usize| -> Result<usize> {
+            arg.map(|a| a.parse::<usize>().with_context(|| format!("'{}' is not a digit count", a)))
+                .transpose()
+                .map(|d| d.unwrap_or(default).min(10))
+        };
+        Ok(match kind.to_ascii_lowercase().as_str() {
+            "number" => self.locale.format_number(number()?, decimals(2)?),
+            "integer" => self.locale.format_number(number()?, 0),
+            "currency" => self.locale.format_currency(number()?, arg.unwrap_or(&self.currency)),
+            "percent" => self.locale.format_percent(number()?, decimals(0)?),
+            "date" => {
+                let style: DateStyle = arg.unwrap_or("short").parse()?;
+                match value {
+                    Value::String(s) => self.locale.format_date(parse_date(s)?, style),
+                    // Validation call from `from_args`
+                    Value::Number(_) => self.locale.format_date(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(), style),
+                    other => anyhow::bail!("{} is not a date", other),
+                }
+            }
+            other => anyhow::bail!("unknown format '{}' (use number, integer, currency, percent or date)", other),
+        })
+    }
+}
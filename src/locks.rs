@@ -0,0 +1,96 @@
+//! Per-document leases for servers shared by several clients (HTTP transport). A client
+//! that acquires a lock gets a token; while the lease is live, write tools on that
+//! document are rejected unless they pass the token as `lock_token`.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentLock {
+    pub document_id: String,
+    pub token: String,
+    /// Free-form holder name reported to other clients
+    pub owner: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("Document {document_id} is locked by {} until {expires_at}", owner.as_deref().unwrap_or("another client"))]
+    Held { document_id: String, owner: Option<String>, expires_at: DateTime<Utc> },
+    #[error("Document {0} is not locked")]
+    NotLocked(String),
+}
+
+#[derive(Debug, Default)]
+pub struct LockTable {
+    locks: HashMap<String, DocumentLock>,
+}
+
+impl LockTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The live lock on a document, dropping it first if its lease ran out
+    pub fn status(&mut self, doc_id: &str) -> Option<DocumentLock> {
+        if self.locks.get(doc_id).map(|l| l.expires_at <= Utc::now()).unwrap_or(false) {
+            self.locks.remove(doc_id);
+        }
+        self.locks.get(doc_id).cloned()
+    }
+
+    /// Take the lock, or renew it when `token` matches the current holder
+    pub fn acquire(&mut self, doc_id: &str, owner: Option<&str>, lease: Duration, token: Option<&str>) -> Result<DocumentLock, LockError> {
+        let expires_at = Utc::now() + lease;
+        if let Some(current) = self.status(doc_id) {
+            if token != Some(current.token.as_str()) {
+                return Err(held(current));
+            }
+            let lock = self.locks.get_mut(doc_id).expect("live lock");
+            lock.expires_at = expires_at;
+            if owner.is_some() {
+                lock.owner = owner.map(String::from);
+            }
+            return Ok(lock.clone());
+        }
+        let lock = DocumentLock {
+            document_id: doc_id.to_string(),
+            token: Uuid::new_v4().to_string(),
+            owner: owner.map(String::from),
+            expires_at,
+        };
+        self.locks.insert(doc_id.to_string(), lock.clone());
+        Ok(lock)
+    }
+
+    pub fn release(&mut self, doc_id: &str, token: &str) -> Result<(), LockError> {
+        match self.status(doc_id) {
+            None => Err(LockError::NotLocked(doc_id.to_string())),
+            Some(current) if current.token != token => Err(held(current)),
+            Some(_) => {
+                self.locks.remove(doc_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether a write with `token` may touch the document: unlocked, or token matches
+    pub fn check(&mut self, doc_id: &str, token: Option<&str>) -> Result<(), LockError> {
+        match self.status(doc_id) {
+            Some(current) if token != Some(current.token.as_str()) => Err(held(current)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Forget a document's lock (the document was closed)
+    pub fn remove(&mut self, doc_id: &str) {
+        self.locks.remove(doc_id);
+    }
+}
+
+fn held(lock: DocumentLock) -> LockError {
+    LockError::Held { document_id: lock.document_id, owner: lock.owner, expires_at: lock.expires_at }
+}
@@ -1,37 +1,73 @@
 use anyhow::Result;
-#[cfg(feature = "runtime-server")]
-use mcp_server::Server;
 use tracing::info;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
-use clap::Parser;
 
+use docx_mcp::{client_setup, security};
 #[cfg(feature = "runtime-server")]
-mod docx_tools;
-#[cfg(feature = "runtime-server")]
-mod docx_handler;
+use docx_mcp::{docx_handler, font_fallback, http, notify, response};
 #[cfg(feature = "runtime-server")]
-mod converter;
-#[cfg(feature = "runtime-server")]
-mod pure_converter;
-#[cfg(all(feature = "runtime-server", feature = "advanced-docx"))]
-mod advanced_docx;
-mod security;
+use docx_mcp::docx_tools::DocxToolsProvider;
 
-#[cfg(feature = "embedded-fonts")]
-mod fonts;
-
-#[cfg(feature = "runtime-server")]
-use docx_tools::DocxToolsProvider;
+/// A tool provider for subcommands that call tools without a client (`run`, `repl`,
+/// `convert`, `extract`), set up from the same flags as the server.
+fn local_provider() -> Result<docx_mcp::docx_tools::DocxToolsProvider> {
+    let args = docx_mcp::Args::load()?;
+    docx_mcp::font_fallback::register_from_specs(&args.fallback_fonts)?;
+    let package_options = docx_mcp::docx_handler::PackageOptions {
+        compression_level: args.compression_level,
+        deterministic: args.deterministic,
+    };
+    let clause_dir = args.clause_dir.clone();
+    let letterheads = args.letterheads.clone();
+    let max_document_memory = args.max_document_memory;
+    let prefer_external_tools = args.prefer_external_tools;
+    let temp_dir = args.temp_dir.clone();
+    let security_config = docx_mcp::SecurityConfig::from_args(args);
+    let provider = match temp_dir {
+        Some(dir) => docx_mcp::docx_tools::DocxToolsProvider::with_base_dir_and_security(dir, security_config),
+        None => docx_mcp::docx_tools::DocxToolsProvider::new_with_security(security_config),
+    };
+    provider
+        .with_prefer_external_tools(prefer_external_tools)
+        .with_clause_dir(clause_dir)
+        .with_letterheads(letterheads)
+        .with_max_document_memory(max_document_memory)
+        .with_package_options(package_options)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
+    let registry = tracing_subscriber::registry()
+        // stdout carries the JSON-RPC stream; logs must go to stderr
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(EnvFilter::from_default_env());
+    #[cfg(feature = "otel")]
+    let _telemetry = {
+        let (layer, guard) = docx_mcp::telemetry::layer()?.unzip();
+        registry.with(layer).init();
+        guard
+    };
+    #[cfg(not(feature = "otel"))]
+    registry.init();
 
     // Parse command line arguments (which also includes environment variables)
-    let args = security::Args::parse();
+    let args = security::Args::load()?;
+
+    if args.self_test {
+        match docx_mcp::self_test::run_self_test() {
+            Ok(steps) => {
+                for step in steps {
+                    println!("[ ok ] {}", step);
+                }
+                println!("Self-test passed");
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Self-test failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
     // Handle top-level subcommands that should run and exit
     if let Some(cmd) = &args.command {
@@ -48,6 +84,143 @@ async fn main() -> Result<()> {
                         info!("Fonts verified successfully");
                         return Ok(());
                     }
+                    security::FontsAction::List => {
+                        println!("Font directory: {}", docx_mcp::fonts_cli::fonts_dir().display());
+                        for font in docx_mcp::fonts_cli::list_fonts()? {
+                            let name = match (&font.family, &font.subfamily) {
+                                (Some(f), Some(s)) => format!("{} {}", f, s),
+                                (Some(f), None) => f.clone(),
+                                _ => "(not a valid font)".to_string(),
+                            };
+                            println!("{:<32} {:<32} {:>9} bytes", font.file, name, font.size_bytes);
+                        }
+                        return Ok(());
+                    }
+                    security::FontsAction::Add { path } => {
+                        let font = docx_mcp::fonts_cli::add_font(path)?;
+                        println!("Installed {} ({}) to {}", font.file, font.family.unwrap_or_default(), font.path.display());
+                        return Ok(());
+                    }
+                }
+            }
+            security::CliCommand::Install { client, name, config, env, dry_run, server_args } => {
+                let path = match config {
+                    Some(path) => path.clone(),
+                    None => client_setup::default_config_path(*client)?,
+                };
+                let exe = std::env::current_exe()?.canonicalize()?;
+                let entry = client_setup::server_entry(*client, &exe, server_args, client_setup::parse_env_pairs(env)?);
+                let result = client_setup::install(&path, name, entry, *dry_run)?;
+                if *dry_run {
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                } else {
+                    println!("Registered '{}' for {} in {}", name, client.label(), path.display());
+                    println!("Restart the client to pick up the change.");
+                }
+                return Ok(());
+            }
+            security::CliCommand::Doctor { command, name, timeout } => {
+                let exe = match command {
+                    Some(path) => path.clone(),
+                    None => std::env::current_exe()?,
+                };
+                let mut checks = client_setup::run_handshake(&exe, &[], std::time::Duration::from_secs(*timeout));
+                checks.extend(client_setup::inspect_client_configs(name));
+                for check in &checks {
+                    println!("[{}] {}: {}", if check.ok { " ok " } else { "FAIL" }, check.name, check.detail);
+                }
+                if checks.iter().any(|c| !c.ok) {
+                    anyhow::bail!("doctor found problems");
+                }
+                return Ok(());
+            }
+            security::CliCommand::Run { script, continue_on_error } => {
+                let text = if script.as_os_str() == "-" {
+                    std::io::read_to_string(std::io::stdin())?
+                } else {
+                    std::fs::read_to_string(script)
+                        .map_err(|e| anyhow::anyhow!("Failed to read script {}: {}", script.display(), e))?
+                };
+                let steps = docx_mcp::script::parse(&text)?;
+                let provider = local_provider()?;
+                let results = docx_mcp::script::run(&provider, &steps, *continue_on_error, |result| {
+                    println!("{}", serde_json::to_string(result).unwrap_or_default());
+                }).await;
+                let failed = results.iter().filter(|r| !r.success).count();
+                if failed > 0 {
+                    anyhow::bail!("{} of {} steps failed", failed, steps.len());
+                }
+                return Ok(());
+            }
+            security::CliCommand::Repl => {
+                let provider = local_provider()?;
+                docx_mcp::repl::run(&provider).await?;
+                return Ok(());
+            }
+            security::CliCommand::Convert { input, output, to, dpi } => {
+                let provider = local_provider()?;
+                let summary = docx_mcp::oneshot::convert(&provider, input, output, to.as_deref(), *dpi).await?;
+                eprintln!("{}", summary);
+                return Ok(());
+            }
+            security::CliCommand::Extract { what, input, output } => {
+                let provider = local_provider()?;
+                if let Some(content) = docx_mcp::oneshot::extract(&provider, input, what, output.as_deref()).await? {
+                    println!("{}", content);
+                }
+                return Ok(());
+            }
+            security::CliCommand::Bench { paragraphs, tables, table_rows, table_cols, images, image_size, iterations } => {
+                let scenario = docx_mcp::bench::Scenario {
+                    paragraphs: *paragraphs,
+                    tables: *tables,
+                    table_rows: *table_rows,
+                    table_cols: *table_cols,
+                    images: *images,
+                    image_size: *image_size,
+                    iterations: *iterations,
+                };
+                let scenarios = if scenario.is_empty() {
+                    docx_mcp::bench::standard_suite(scenario.iterations)
+                } else {
+                    vec![scenario]
+                };
+                let package = docx_mcp::docx_handler::PackageOptions {
+                    compression_level: args.compression_level,
+                    deterministic: args.deterministic,
+                };
+                let reports = docx_mcp::bench::run_all(&scenarios, package)?;
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+                return Ok(());
+            }
+            #[cfg(feature = "spellcheck")]
+            security::CliCommand::Dictionaries { action } => {
+                match action {
+                    security::DictionariesAction::Download { lang } => {
+                        docx_mcp::spelling::download_dictionaries_blocking(lang)?;
+                        info!("Dictionaries downloaded successfully");
+                        return Ok(());
+                    }
+                    security::DictionariesAction::Verify { lang } => {
+                        docx_mcp::spelling::verify_dictionaries_blocking(lang)?;
+                        info!("Dictionaries verified successfully");
+                        return Ok(());
+                    }
+                }
+            }
+            #[cfg(feature = "hyphenation")]
+            security::CliCommand::Hyphenation { action } => {
+                match action {
+                    security::DictionariesAction::Download { lang } => {
+                        docx_mcp::hyphenation::download_patterns_blocking(lang)?;
+                        info!("Hyphenation patterns downloaded successfully");
+                        return Ok(());
+                    }
+                    security::DictionariesAction::Verify { lang } => {
+                        docx_mcp::hyphenation::verify_patterns_blocking(lang)?;
+                        info!("Hyphenation patterns verified successfully");
+                        return Ok(());
+                    }
                 }
             }
         }
@@ -55,64 +228,79 @@ async fn main() -> Result<()> {
 
     #[cfg(feature = "runtime-server")]
     {
-        use mcp_server::{Router, Server};
-        use mcp_server::router::RouterService;
-        use mcp_server::router::CapabilitiesBuilder;
-        use mcp_spec::{prompt::Prompt, resource::Resource};
-        use mcp_spec::protocol::ServerCapabilities;
-        use mcp_spec::content::Content;
-        use mcp_spec::tool::Tool as SpecTool;
-        use serde_json::Value as JsonValue;
-        use std::pin::Pin;
-        use std::future::Future;
         use tokio::io::{stdin, stdout};
 
+        let response_mode = if args.legacy_text_responses {
+            response::ResponseMode::LegacyText
+        } else {
+            response::ResponseMode::Structured
+        };
+        let notifier = notify::Notifier::new(
+            args.webhook_urls.clone(),
+            args.webhook_events.clone(),
+            args.storage_threshold_mb.map(|mb| mb * 1024 * 1024),
+        );
+        font_fallback::register_from_specs(&args.fallback_fonts)?;
+        let package_options = docx_handler::PackageOptions {
+            compression_level: args.compression_level,
+            deterministic: args.deterministic,
+        };
+        let clause_dir = args.clause_dir.clone();
+        let letterheads = args.letterheads.clone();
+        let max_document_memory = args.max_document_memory;
+        let prefer_external_tools = args.prefer_external_tools;
+        let temp_dir = args.temp_dir.clone();
+        let has_config = args.config.is_some();
+        let http_addr = args.http.clone();
+        let http_options = http::HttpOptions {
+            admin_key: args.admin_key.clone(),
+            tokens: args.tokens.clone(),
+            session_idle_timeout: (args.session_idle_timeout > 0).then(|| std::time::Duration::from_secs(args.session_idle_timeout)),
+            max_sessions_per_token: (args.max_sessions_per_token > 0).then_some(args.max_sessions_per_token),
+            metrics_public: args.metrics_public,
+        };
         let security_config = security::SecurityConfig::from_args(args);
         info!("Starting DOCX MCP Server - Security: {}", security_config.get_summary());
 
-        #[derive(Clone)]
-        struct DocxRouter(docx_tools::DocxToolsProvider);
-
-        impl Router for DocxRouter {
-            fn name(&self) -> String { "docx-mcp-server".to_string() }
-            fn instructions(&self) -> String { "DOCX tools for reading and exporting".to_string() }
-            fn capabilities(&self) -> ServerCapabilities {
-                CapabilitiesBuilder::new().with_tools(true).build()
-            }
-            fn list_tools(&self) -> Vec<SpecTool> {
-                // DocxToolsProvider::list_tools is async; block briefly with tokio runtime handle
-                let rt = tokio::runtime::Handle::current();
-                let tools = rt.block_on(self.0.list_tools());
-                tools.into_iter().map(|t| SpecTool{ name: t.name, description: t.description.unwrap_or_default(), input_schema: t.input_schema }).collect()
-            }
-            fn call_tool(&self, tool_name: &str, arguments: JsonValue) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, mcp_spec::handler::ToolError>> + Send + 'static>> {
-                let provider = self.0.clone();
-                let name = tool_name.to_string();
-                Box::pin(async move {
-                    let resp = provider.call_tool(&name, arguments).await;
-                    // Convert our CallToolResponse (text JSON) to Content::text
-                    let text = match resp.content.get(0) {
-                        Some(mcp_core::types::ToolResponseContent::Text(t)) => t.text.clone(),
-                        _ => serde_json::to_string(&resp).unwrap_or_else(|_| "{}".to_string()),
+        let provider = match temp_dir {
+            Some(dir) => DocxToolsProvider::with_base_dir_and_security(dir, security_config),
+            None => DocxToolsProvider::new_with_security(security_config),
+        };
+        let mut provider = provider
+            .with_prefer_external_tools(prefer_external_tools)
+            .with_response_mode(response_mode)
+            .with_notifier(notifier)
+            .with_clause_dir(clause_dir)
+            .with_letterheads(letterheads)
+            .with_max_document_memory(max_document_memory)
+            .with_package_options(package_options)?;
+        if has_config {
+            // Same argv and environment as at startup; only the file's contents change
+            provider = provider.with_config_reload(|| security::Args::load_from(std::env::args_os()));
+            #[cfg(unix)]
+            {
+                let provider = provider.clone();
+                tokio::spawn(async move {
+                    use tokio::signal::unix::{signal, SignalKind};
+                    let mut hangup = match signal(SignalKind::hangup()) {
+                        Ok(hangup) => hangup,
+                        Err(e) => {
+                            tracing::error!("Cannot listen for SIGHUP: {}", e);
+                            return;
+                        }
                     };
-                    Ok(vec![Content::text(text)])
-                })
-            }
-            fn list_resources(&self) -> Vec<Resource> { vec![] }
-            fn read_resource(&self, _uri: &str) -> Pin<Box<dyn Future<Output = Result<String, mcp_spec::handler::ResourceError>> + Send + 'static>> {
-                Box::pin(async { Ok(String::new()) })
-            }
-            fn list_prompts(&self) -> Vec<Prompt> { vec![] }
-            fn get_prompt(&self, _prompt_name: &str) -> Pin<Box<dyn Future<Output = Result<String, mcp_spec::handler::PromptError>> + Send + 'static>> {
-                Box::pin(async { Ok(String::new()) })
+                    while hangup.recv().await.is_some() {
+                        if let Err(e) = provider.reload_config() {
+                            tracing::error!("Config reload on SIGHUP failed, keeping the previous settings: {:#}", e);
+                        }
+                    }
+                });
             }
         }
-
-        let router = DocxRouter(DocxToolsProvider::new_with_security(security_config));
-        let service = RouterService(router);
-        let server = Server::new(service);
-        let transport = mcp_server::ByteTransport::new(stdin(), stdout());
-        server.run(transport).await?;
+        if let Some(addr) = http_addr {
+            return http::serve(provider, &addr, http_options).await;
+        }
+        docx_mcp::stdio::serve(&provider, stdin(), stdout()).await?;
     }
 
     #[cfg(not(feature = "runtime-server"))]
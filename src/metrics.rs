@@ -0,0 +1,148 @@
+//! Counters for the `/metrics` endpoint of the HTTP transport, rendered in the
+//! Prometheus text exposition format. A provider and its clones share one `Metrics`;
+//! gauges that describe current state (open documents, temp directory size) are
+//! measured at scrape time rather than tracked here.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::security::SecurityError;
+
+/// Tools whose durations go into `docx_mcp_conversion_duration_seconds`
+pub const CONVERSION_TOOLS: &[&str] = &[
+    "convert_to_pdf",
+    "export_pdf_with_field_refresh",
+    "convert_to_images",
+    "convert_to_images_with_preference",
+    "export_to_markdown",
+    "export_to_html",
+];
+
+/// Upper bounds (seconds) of the conversion duration histogram buckets
+const BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket (not cumulative; summed when rendered)
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(i) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[i] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    /// Calls by (tool, "success" | "error")
+    tool_calls: BTreeMap<(String, &'static str), u64>,
+    conversions: BTreeMap<String, Histogram>,
+    /// Rejections by reason (see `rejection_reason`)
+    security_rejections: BTreeMap<&'static str, u64>,
+}
+
+/// State measured when `/metrics` is scraped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gauges {
+    pub open_documents: usize,
+    pub temp_dir_bytes: u64,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a finished tool call; conversion tools also record their duration
+    pub fn record_call(&self, tool: &str, success: bool, elapsed: Duration) {
+        let mut counters = self.counters.lock().unwrap();
+        let result = if success { "success" } else { "error" };
+        *counters.tool_calls.entry((tool.to_string(), result)).or_default() += 1;
+        if CONVERSION_TOOLS.contains(&tool) {
+            counters.conversions.entry(tool.to_string()).or_default().observe(elapsed.as_secs_f64());
+        }
+    }
+
+    /// Count a call the security middleware refused
+    pub fn record_rejection(&self, error: &SecurityError) {
+        *self.counters.lock().unwrap().security_rejections.entry(rejection_reason(error)).or_default() += 1;
+    }
+
+    /// Calls of `tool` with `result` ("success" or "error") so far
+    pub fn tool_calls(&self, tool: &str, result: &str) -> u64 {
+        self.counters.lock().unwrap().tool_calls.iter()
+            .filter(|((t, r), _)| t == tool && *r == result)
+            .map(|(_, n)| *n)
+            .sum()
+    }
+
+    /// Everything in the Prometheus text format
+    pub fn render(&self, gauges: Gauges) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP docx_mcp_tool_calls_total Tool calls by tool and result.\n");
+        out.push_str("# TYPE docx_mcp_tool_calls_total counter\n");
+        for ((tool, result), n) in &counters.tool_calls {
+            let _ = writeln!(out, "docx_mcp_tool_calls_total{{tool=\"{}\",result=\"{}\"}} {}", escape(tool), result, n);
+        }
+
+        out.push_str("# HELP docx_mcp_conversion_duration_seconds Time spent in conversion and export tools.\n");
+        out.push_str("# TYPE docx_mcp_conversion_duration_seconds histogram\n");
+        for (tool, histogram) in &counters.conversions {
+            let tool = escape(tool);
+            let mut cumulative = 0;
+            for (bound, n) in BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += n;
+                let _ = writeln!(out, "docx_mcp_conversion_duration_seconds_bucket{{tool=\"{}\",le=\"{}\"}} {}", tool, bound, cumulative);
+            }
+            let _ = writeln!(out, "docx_mcp_conversion_duration_seconds_bucket{{tool=\"{}\",le=\"+Inf\"}} {}", tool, histogram.count);
+            let _ = writeln!(out, "docx_mcp_conversion_duration_seconds_sum{{tool=\"{}\"}} {}", tool, histogram.sum);
+            let _ = writeln!(out, "docx_mcp_conversion_duration_seconds_count{{tool=\"{}\"}} {}", tool, histogram.count);
+        }
+
+        out.push_str("# HELP docx_mcp_security_rejections_total Tool calls refused by the security settings, by reason.\n");
+        out.push_str("# TYPE docx_mcp_security_rejections_total counter\n");
+        for (reason, n) in &counters.security_rejections {
+            let _ = writeln!(out, "docx_mcp_security_rejections_total{{reason=\"{}\"}} {}", reason, n);
+        }
+
+        out.push_str("# HELP docx_mcp_open_documents Documents currently open.\n");
+        out.push_str("# TYPE docx_mcp_open_documents gauge\n");
+        let _ = writeln!(out, "docx_mcp_open_documents {}", gauges.open_documents);
+        out.push_str("# HELP docx_mcp_temp_dir_bytes Bytes of working copies and spill files in the temp directory.\n");
+        out.push_str("# TYPE docx_mcp_temp_dir_bytes gauge\n");
+        let _ = writeln!(out, "docx_mcp_temp_dir_bytes {}", gauges.temp_dir_bytes);
+        out
+    }
+}
+
+fn rejection_reason(error: &SecurityError) -> &'static str {
+    match error {
+        SecurityError::CommandNotAllowed(_) => "command_not_allowed",
+        SecurityError::PathNotAllowed(_) => "path_not_allowed",
+        SecurityError::FileTooLarge { .. } => "file_too_large",
+        SecurityError::TooManyDocuments => "too_many_documents",
+        SecurityError::ExternalToolsDisabled => "external_tools_disabled",
+        SecurityError::NetworkDisabled => "network_disabled",
+    }
+}
+
+/// Escape a label value (backslash, quote, newline)
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,134 @@
+//! Outbound notifications on document events (document_saved, document_modified,
+//! conversion_completed, storage_threshold_exceeded) so orchestrators can react without
+//! polling.
+//!
+//! Every event is logged as a JSON line under the `docx_mcp::events` target; when webhook
+//! URLs are configured and network access is allowed, it is also POSTed to each URL by
+//! one delivery thread. Events that arrive while its queue is full are dropped with a
+//! warning rather than piling up behind a slow endpoint. The stdio server has no channel
+//! for server-initiated MCP notifications, so the log line is the in-band alternative.
+
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub const EVENTS: &[&str] = &["document_saved", "document_modified", "conversion_completed", "storage_threshold_exceeded"];
+
+/// Events waiting for webhook delivery before new ones are dropped
+const QUEUE_CAPACITY: usize = 256;
+
+#[derive(Debug, Default)]
+pub struct Notifier {
+    webhook_urls: Vec<String>,
+    /// Events to send; all when `None`
+    events: Option<HashSet<String>>,
+    storage_threshold_bytes: Option<u64>,
+    allow_network: bool,
+    /// Set while storage is above the threshold so the event fires once per crossing
+    over_threshold: AtomicBool,
+    /// Queue of the delivery thread, started with the first webhook event
+    queue: OnceLock<SyncSender<String>>,
+}
+
+impl Notifier {
+    pub fn new(webhook_urls: Vec<String>, events: Option<Vec<String>>, storage_threshold_bytes: Option<u64>) -> Self {
+        for unknown in events.iter().flatten().filter(|e| !EVENTS.contains(&e.as_str())) {
+            warn!("Unknown webhook event '{}' (known: {})", unknown, EVENTS.join(", "));
+        }
+        Self {
+            webhook_urls,
+            events: events.map(|e| e.into_iter().collect()),
+            storage_threshold_bytes,
+            allow_network: true,
+            over_threshold: AtomicBool::new(false),
+            queue: OnceLock::new(),
+        }
+    }
+
+    /// Disable webhook delivery (events are still logged)
+    pub fn with_network(mut self, allow: bool) -> Self {
+        self.allow_network = allow;
+        self
+    }
+
+    pub fn storage_threshold(&self) -> Option<u64> {
+        self.storage_threshold_bytes
+    }
+
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.as_ref().map(|e| e.contains(event)).unwrap_or(true)
+    }
+
+    /// Log and deliver an event; returns the payload that was sent, if the event is enabled
+    pub fn emit(&self, event: &str, data: Value) -> Option<Value> {
+        if !self.wants(event) {
+            return None;
+        }
+        let payload = json!({
+            "event": event,
+            "timestamp": chrono::Utc::now(),
+            "data": data,
+        });
+        info!(target: "docx_mcp::events", "{}", payload);
+        if self.allow_network && !self.webhook_urls.is_empty() {
+            match self.queue().try_send(payload.to_string()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => warn!("Webhook queue is full, dropping {} event", event),
+                Err(TrySendError::Disconnected(_)) => warn!("Webhook delivery thread has stopped, dropping {} event", event),
+            }
+        }
+        Some(payload)
+    }
+
+    /// The delivery thread's queue. The thread posts each event to every URL in turn and
+    /// exits when the notifier is dropped.
+    fn queue(&self) -> &SyncSender<String> {
+        self.queue.get_or_init(|| {
+            let (sender, receiver) = mpsc::sync_channel::<String>(QUEUE_CAPACITY);
+            let urls = self.webhook_urls.clone();
+            std::thread::spawn(move || {
+                for body in receiver {
+                    for url in &urls {
+                        let sent = ureq::post(url)
+                            .timeout(Duration::from_secs(10))
+                            .set("Content-Type", "application/json")
+                            .send_string(&body);
+                        if let Err(e) = sent {
+                            warn!("Webhook delivery to {} failed: {}", url, e);
+                        }
+                    }
+                }
+            });
+            sender
+        })
+    }
+
+    /// Emit storage_threshold_exceeded when usage first goes over the threshold
+    pub fn check_storage(&self, used_bytes: u64) -> Option<Value> {
+        let threshold = self.storage_threshold_bytes?;
+        let over = used_bytes > threshold;
+        let was_over = self.over_threshold.swap(over, Ordering::SeqCst);
+        if over && !was_over {
+            self.emit("storage_threshold_exceeded", json!({"used_bytes": used_bytes, "threshold_bytes": threshold}))
+        } else {
+            None
+        }
+    }
+}
+
+/// Where two element lists differ: (first differing index, elements removed, elements
+/// added), after trimming the common prefix and suffix. None when they are identical.
+/// An in-place edit of one element is one removed and one added.
+pub fn changed_span<T: PartialEq>(before: &[T], after: &[T]) -> Option<(usize, usize, usize)> {
+    let prefix = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+    if prefix == before.len() && prefix == after.len() {
+        return None;
+    }
+    let max_suffix = before.len().min(after.len()) - prefix;
+    let suffix = before.iter().rev().zip(after.iter().rev()).take(max_suffix).take_while(|(a, b)| a == b).count();
+    Some((prefix, before.len() - prefix - suffix, after.len() - prefix - suffix))
+}
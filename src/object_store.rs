@@ -0,0 +1,254 @@
+//! Object storage for `open_document`/`save_document`: `s3://bucket/key`,
+//! `gs://bucket/object` and `az://account/container/blob` URIs, so containerized
+//! deployments can deliver results without a shared filesystem.
+//!
+//! Credentials come from the environment:
+//! - S3: `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, optional `AWS_SESSION_TOKEN`,
+//!   `AWS_REGION` (or `AWS_DEFAULT_REGION`, default us-east-1) and `AWS_ENDPOINT_URL` for
+//!   S3-compatible stores (path-style addressing). Requests are signed with SigV4.
+//! - GCS: `GOOGLE_OAUTH_ACCESS_TOKEN` (bearer token, e.g. from `gcloud auth print-access-token`)
+//! - Azure Blob: `AZURE_STORAGE_SAS_TOKEN` (container or account SAS)
+//!
+//! Callers gate all of this on `SecurityConfig::allow_network`.
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectUri {
+    S3 { bucket: String, key: String },
+    Gcs { bucket: String, object: String },
+    Azure { account: String, container: String, blob: String },
+}
+
+impl ObjectUri {
+    /// Parse an object storage URI; `None` for anything else (plain paths)
+    pub fn parse(uri: &str) -> Option<Result<Self>> {
+        let (scheme, rest) = uri.split_once("://")?;
+        let parts: Vec<&str> = rest.splitn(3, '/').collect();
+        let parsed = match scheme {
+            "s3" | "gs" => match parts.as_slice() {
+                [bucket, first, more @ ..] if !bucket.is_empty() && !first.is_empty() => {
+                    if let Err(e) = check_name(bucket, "bucket", "abcdefghijklmnopqrstuvwxyz0123456789.-", 63) {
+                        return Some(Err(e));
+                    }
+                    let key = std::iter::once(*first).chain(more.iter().copied()).collect::<Vec<_>>().join("/");
+                    Ok(if scheme == "s3" {
+                        ObjectUri::S3 { bucket: bucket.to_string(), key }
+                    } else {
+                        ObjectUri::Gcs { bucket: bucket.to_string(), object: key }
+                    })
+                }
+                _ => Err(anyhow::anyhow!("{} must look like {}://bucket/key", uri, scheme)),
+            },
+            "az" => match parts.as_slice() {
+                [account, container, blob] if !account.is_empty() && !container.is_empty() && !blob.is_empty() => {
+                    let checked = check_name(account, "storage account", "abcdefghijklmnopqrstuvwxyz0123456789", 24)
+                        .and_then(|_| check_name(container, "container", "abcdefghijklmnopqrstuvwxyz0123456789-", 63));
+                    if let Err(e) = checked {
+                        return Some(Err(e));
+                    }
+                    Ok(ObjectUri::Azure { account: account.to_string(), container: container.to_string(), blob: blob.to_string() })
+                }
+                _ => Err(anyhow::anyhow!("{} must look like az://account/container/blob", uri)),
+            },
+            _ => return None,
+        };
+        Some(parsed)
+    }
+}
+
+/// Bucket, account and container names end up in request hosts and paths unescaped, so
+/// only the characters the providers allow get through (no `?`, `#`, `@` or `:`)
+fn check_name(name: &str, kind: &str, allowed: &str, max_len: usize) -> Result<()> {
+    if name.len() < 3 || name.len() > max_len || !name.chars().all(|c| allowed.contains(c)) {
+        bail!("Invalid {} name {:?}: use 3-{} of the characters {}", kind, name, max_len, allowed);
+    }
+    Ok(())
+}
+
+/// Whether a path argument refers to object storage rather than the local filesystem
+pub fn is_object_uri(s: &str) -> bool {
+    ["s3://", "gs://", "az://"].iter().any(|p| s.starts_with(p))
+}
+
+/// Download an object to a local file, refusing objects larger than `max_bytes`
+pub fn download(uri: &ObjectUri, dest: &Path, max_bytes: usize) -> Result<()> {
+    let response = match uri {
+        ObjectUri::S3 { bucket, key } => s3_request("GET", bucket, key, &[])?,
+        ObjectUri::Gcs { bucket, object } => ureq::get(&format!(
+            "https://storage.googleapis.com/storage/v1/b/{}/o/{}?alt=media",
+            bucket,
+            encode(object, false)
+        ))
+        .timeout(TIMEOUT)
+        .set("Authorization", &format!("Bearer {}", env_var("GOOGLE_OAUTH_ACCESS_TOKEN")?))
+        .call(),
+        ObjectUri::Azure { .. } => ureq::get(&azure_url(uri)?).timeout(TIMEOUT).call(),
+    }
+    .map_err(|e| request_error(e, uri))?;
+
+    let mut body = Vec::new();
+    response.into_reader().take(max_bytes as u64 + 1).read_to_end(&mut body)?;
+    if body.len() > max_bytes {
+        bail!("{:?} is larger than the {} byte document limit", uri, max_bytes);
+    }
+    std::fs::write(dest, body).with_context(|| format!("Failed to write download to {:?}", dest))
+}
+
+/// Upload a local file as an object, replacing any existing one
+pub fn upload(src: &Path, uri: &ObjectUri) -> Result<()> {
+    const DOCX_TYPE: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+    let body = std::fs::read(src).with_context(|| format!("Failed to read {:?}", src))?;
+    match uri {
+        ObjectUri::S3 { bucket, key } => s3_request("PUT", bucket, key, &body)?,
+        ObjectUri::Gcs { bucket, object } => ureq::post(&format!(
+            "https://storage.googleapis.com/upload/storage/v1/b/{}/o?uploadType=media&name={}",
+            bucket,
+            encode(object, false)
+        ))
+        .timeout(TIMEOUT)
+        .set("Authorization", &format!("Bearer {}", env_var("GOOGLE_OAUTH_ACCESS_TOKEN")?))
+        .set("Content-Type", DOCX_TYPE)
+        .send_bytes(&body),
+        ObjectUri::Azure { .. } => ureq::put(&azure_url(uri)?)
+            .timeout(TIMEOUT)
+            .set("x-ms-blob-type", "BlockBlob")
+            .set("Content-Type", DOCX_TYPE)
+            .send_bytes(&body),
+    }
+    .map_err(|e| request_error(e, uri))?;
+    Ok(())
+}
+
+fn env_var(name: &str) -> Result<String> {
+    std::env::var(name).with_context(|| format!("{} is not set", name))
+}
+
+fn request_error(err: ureq::Error, uri: &ObjectUri) -> anyhow::Error {
+    match err {
+        ureq::Error::Status(code, response) => {
+            let detail = response.into_string().unwrap_or_default();
+            anyhow::anyhow!("{:?}: HTTP {} {}", uri, code, detail.chars().take(300).collect::<String>())
+        }
+        other => anyhow::anyhow!("{:?}: {}", uri, other),
+    }
+}
+
+fn azure_url(uri: &ObjectUri) -> Result<String> {
+    let ObjectUri::Azure { account, container, blob } = uri else { unreachable!() };
+    let sas = env_var("AZURE_STORAGE_SAS_TOKEN")?;
+    Ok(format!(
+        "https://{}.blob.core.windows.net/{}/{}?{}",
+        account,
+        container,
+        encode(blob, true),
+        sas.trim_start_matches('?')
+    ))
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters (and `/` if asked)
+fn encode(s: &str, keep_slash: bool) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b'/' if keep_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+pub struct S3Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub region: String,
+}
+
+/// SigV4 `Authorization` header for an S3 request. `headers` are the (lowercase) headers
+/// to sign, including `host`, `x-amz-date` and `x-amz-content-sha256`.
+pub fn s3_authorization(method: &str, path: &str, headers: &[(&str, &str)], payload_sha256: &str, creds: &S3Credentials, amz_date: &str) -> String {
+    let mut sorted: Vec<(&str, &str)> = headers.to_vec();
+    sorted.sort_by(|a, b| a.0.cmp(b.0));
+    let canonical_headers: String = sorted.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+    let signed_headers = sorted.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+    let canonical_request = format!("{}\n{}\n\n{}\n{}\n{}", method, path, canonical_headers, signed_headers, payload_sha256);
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, creds.region);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex(&Sha256::digest(canonical_request.as_bytes())));
+    let k_date = hmac_sha256(format!("AWS4{}", creds.secret_key).as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, creds.region.as_bytes());
+    let k_service = hmac_sha256(&k_region, b"s3");
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex(&hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        creds.access_key, scope, signed_headers, signature
+    )
+}
+
+/// Sign and send an S3 request. Credential problems fail here; the HTTP outcome is
+/// returned for the caller to map.
+fn s3_request(method: &str, bucket: &str, key: &str, body: &[u8]) -> Result<std::result::Result<ureq::Response, ureq::Error>> {
+    let creds = S3Credentials {
+        access_key: env_var("AWS_ACCESS_KEY_ID")?,
+        secret_key: env_var("AWS_SECRET_ACCESS_KEY")?,
+        region: std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string()),
+    };
+    let (base, host, path) = match std::env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => {
+            let endpoint = endpoint.trim_end_matches('/').to_string();
+            let host = endpoint.split_once("://").map(|(_, h)| h).unwrap_or(&endpoint).to_string();
+            (endpoint, host, format!("/{}/{}", encode(bucket, false), encode(key, true)))
+        }
+        Err(_) => {
+            let host = format!("{}.s3.{}.amazonaws.com", bucket, creds.region);
+            (format!("https://{}", host), host, format!("/{}", encode(key, true)))
+        }
+    };
+    let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let payload_hash = hex(&Sha256::digest(body));
+    let token = std::env::var("AWS_SESSION_TOKEN").ok();
+    let mut headers = vec![("host", host.as_str()), ("x-amz-content-sha256", payload_hash.as_str()), ("x-amz-date", amz_date.as_str())];
+    if let Some(t) = &token {
+        headers.push(("x-amz-security-token", t.as_str()));
+    }
+    let authorization = s3_authorization(method, &path, &headers, &payload_hash, &creds, &amz_date);
+
+    let mut request = ureq::request(method, &format!("{}{}", base, path))
+        .timeout(TIMEOUT)
+        .set("Authorization", &authorization);
+    for (k, v) in headers.iter().filter(|(k, _)| *k != "host") {
+        request = request.set(k, v);
+    }
+    Ok(if method == "PUT" { request.send_bytes(body) } else { request.call() })
+}
@@ -0,0 +1,147 @@
+//! One-shot `convert` and `extract` subcommands: open a file, run the same tools the
+//! server exposes, write the result and exit. No server or client is involved, so the
+//! binary can sit in a shell pipeline; security flags still apply as they do for `run`.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::docx_tools::DocxToolsProvider;
+
+/// Formats `convert` accepts for `--to`
+pub const CONVERT_FORMATS: &[&str] = &["pdf", "md", "html", "png", "jpg"];
+/// What `extract` can pull out of a document
+pub const EXTRACT_KINDS: &[&str] = &["text", "tables", "images"];
+
+/// `convert --to` value for an output path, from its extension
+fn format_for(output: &Path) -> Option<&'static str> {
+    match output.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "pdf" => Some("pdf"),
+        "md" | "markdown" => Some("md"),
+        "html" | "htm" => Some("html"),
+        "png" => Some("png"),
+        "jpg" | "jpeg" => Some("jpg"),
+        _ => None,
+    }
+}
+
+/// Call a tool, turning a failed response into an error
+async fn call(provider: &DocxToolsProvider, tool: &str, arguments: Value) -> Result<Value> {
+    let result = crate::script::call(provider, tool, arguments).await;
+    if result.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+        Ok(result)
+    } else {
+        let error = result.get("error").and_then(|v| v.as_str()).unwrap_or("unknown error");
+        anyhow::bail!("{} failed: {}", tool, error)
+    }
+}
+
+/// Open `input` and return its document id
+async fn open(provider: &DocxToolsProvider, input: &Path, editable: bool) -> Result<String> {
+    let opened = call(provider, "open_document", json!({"path": input, "editable": editable})).await?;
+    Ok(opened["document_id"].as_str().context("open_document returned no document_id")?.to_string())
+}
+
+async fn close(provider: &DocxToolsProvider, doc_id: &str) {
+    let _ = call(provider, "close_document", json!({"document_id": doc_id})).await;
+}
+
+/// Convert `input` to `output`. The format comes from `to` or else from the output
+/// extension; `png` and `jpg` write one image per page into `output` as a directory.
+/// Returns a one-line summary.
+pub async fn convert(provider: &DocxToolsProvider, input: &Path, output: &Path, to: Option<&str>, dpi: u32) -> Result<String> {
+    let format = match to {
+        Some(to) => CONVERT_FORMATS.iter().copied().find(|f| *f == to.to_ascii_lowercase())
+            .ok_or_else(|| anyhow::anyhow!("Unknown format '{}' (use {})", to, CONVERT_FORMATS.join(", ")))?,
+        None => format_for(output).ok_or_else(|| anyhow::anyhow!(
+            "Cannot tell the format from {:?}; pass --to ({})", output, CONVERT_FORMATS.join(", ")
+        ))?,
+    };
+    let doc_id = open(provider, input, false).await?;
+    let result = convert_open(provider, &doc_id, output, format, dpi).await;
+    close(provider, &doc_id).await;
+    result
+}
+
+async fn convert_open(provider: &DocxToolsProvider, doc_id: &str, output: &Path, format: &str, dpi: u32) -> Result<String> {
+    match format {
+        "pdf" => {
+            call(provider, "convert_to_pdf", json!({"document_id": doc_id, "output_path": output})).await?;
+        }
+        "md" => {
+            let result = call(provider, "extract_formatted_text", json!({"document_id": doc_id, "format": "markdown"})).await?;
+            std::fs::write(output, result["text"].as_str().unwrap_or_default())
+                .with_context(|| format!("Failed to write {:?}", output))?;
+        }
+        "html" => {
+            call(provider, "export_to_html", json!({"document_id": doc_id, "output_path": output, "self_contained": true})).await?;
+        }
+        image_format => {
+            std::fs::create_dir_all(output).with_context(|| format!("Failed to create {:?}", output))?;
+            let result = call(provider, "convert_to_images", json!({
+                "document_id": doc_id,
+                "output_dir": output,
+                "format": image_format,
+                "dpi": dpi,
+            })).await?;
+            let count = result["images"].as_array().map(|a| a.len()).unwrap_or(0);
+            return Ok(format!("Wrote {} page images to {}", count, output.display()));
+        }
+    }
+    Ok(format!("Wrote {}", output.display()))
+}
+
+/// Extract text, tables (as JSON) or embedded images from `input`. Text and tables go
+/// to `output` when given and are returned otherwise; images need `output` as the
+/// directory to write them to, and their paths are returned one per line.
+pub async fn extract(provider: &DocxToolsProvider, input: &Path, what: &str, output: Option<&Path>) -> Result<Option<String>> {
+    let (tool, editable) = match what {
+        "text" => ("extract_text", false),
+        // Tables are read from the op model, so the document is opened editable
+        "tables" => ("get_tables", true),
+        "images" => {
+            let dir = output.context("extract images needs --output <directory>")?;
+            let written = extract_media(input, dir)?;
+            return Ok(Some(written.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join("\n")));
+        }
+        other => anyhow::bail!("Unknown extract kind '{}' (use {})", other, EXTRACT_KINDS.join(", ")),
+    };
+    let doc_id = open(provider, input, editable).await?;
+    let result = call(provider, tool, json!({"document_id": doc_id})).await;
+    close(provider, &doc_id).await;
+    let result = result?;
+    let content = match what {
+        "text" => result["text"].as_str().unwrap_or_default().to_string(),
+        _ => serde_json::to_string_pretty(&result["metadata"]["tables"])?,
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, content).with_context(|| format!("Failed to write {:?}", path))?;
+            Ok(None)
+        }
+        None => Ok(Some(content)),
+    }
+}
+
+/// Copy the package's `word/media` parts into `dir` under their own file names
+fn extract_media(input: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let file = std::fs::File::open(input).with_context(|| format!("Failed to open {:?}", input))?;
+    let mut archive = zip::ZipArchive::new(file).with_context(|| format!("{:?} is not a DOCX package", input))?;
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    let mut written = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(name) = entry.name().strip_prefix("word/media/").map(str::to_string) else { continue };
+        // Nested or traversing names never come from Word; skip rather than write outside dir
+        if name.is_empty() || name.contains('/') || name.contains("..") {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        let path = dir.join(&name);
+        std::fs::write(&path, bytes).with_context(|| format!("Failed to write {:?}", path))?;
+        written.push(path);
+    }
+    Ok(written)
+}
@@ -1,17 +1,447 @@
 use anyhow::{Context, Result};
 use ::image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
 use printpdf::*;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufWriter, Read};
 use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
-use tracing::{info};
+use tracing::{info, warn};
 use roxmltree;
 use zip::ZipArchive;
 use ::lopdf::{dictionary, Object};
 
 pub struct PureRustConverter;
 
+/// Rendering options for PDF output
+#[derive(Debug, Clone)]
+pub struct PdfOptions {
+    /// Embed only the glyphs the document uses from external (fallback) fonts. Full CJK
+    /// fonts are tens of megabytes; subsets are usually a few hundred kilobytes.
+    pub subset_fonts: bool,
+    /// Hyphenate words at line ends (needs the `hyphenation` feature and downloaded patterns)
+    pub hyphenate: bool,
+    /// Hyphenation language such as "en_US" or "de-DE"; detected from the text when unset
+    pub language: Option<String>,
+}
+
+/// What `extract_text_with_scope` reads beyond the visible body text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextScope {
+    /// Header and footer text, before and after the body
+    pub headers: bool,
+    /// Footnote and endnote texts after the body, with `[n]` markers at the references
+    pub footnotes: bool,
+    /// Comments with their author and the text they are anchored to
+    pub comments: bool,
+    /// Runs formatted as hidden (`w:vanish`)
+    pub hidden: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self { subset_fonts: true, hyphenate: true, language: None }
+    }
+}
+
+/// Lines that fit between the 280mm top and 20mm bottom margins at 5mm spacing
+pub const LAYOUT_LINES_PER_PAGE: usize = 53;
+const LAYOUT_FONT_SIZE: f32 = 11.0;
+/// Text width between the 20mm side margins of an A4 page, in points
+const TEXT_WIDTH_PT: f32 = 170.0 * 72.0 / 25.4;
+/// The same width in glyph units (1/1000 em) at the layout font size
+const LAYOUT_LINE_UNITS: u32 = (TEXT_WIDTH_PT * 1000.0 / LAYOUT_FONT_SIZE) as u32;
+/// Justified lines may widen each space by at most this many spaces; looser lines are
+/// left ragged instead of showing rivers of white space
+const MAX_WORD_STRETCH: f32 = 2.0;
+
+/// Helvetica advance widths (1/1000 em) for ASCII 0x20..=0x7E, from the standard AFM
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+fn helvetica_width(c: char) -> f32 {
+    match c as u32 {
+        cp @ 0x20..=0x7E => HELVETICA_WIDTHS[(cp - 0x20) as usize] as f32,
+        _ => 556.0,
+    }
+}
+
+/// Advance width used for line breaking, in 1/1000 em: Helvetica metrics, one em for
+/// CJK characters (drawn with a fallback font)
+fn advance_width(c: char) -> u32 {
+    if crate::font_fallback::column_width(c) == 2 {
+        1000
+    } else {
+        helvetica_width(c) as u32
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    text.chars().map(advance_width).sum()
+}
+
+/// Extra space per word gap (points) that stretches a Helvetica line to the text width;
+/// `None` when the line has no gaps, is already full, or would need gaps wider than
+/// `MAX_WORD_STRETCH` allows
+fn justified_word_spacing(text: &str, font_size: f32) -> Option<f32> {
+    let gaps = text.matches(' ').count();
+    if gaps == 0 {
+        return None;
+    }
+    let natural = text.chars().map(helvetica_width).sum::<f32>() * font_size / 1000.0;
+    let extra = (TEXT_WIDTH_PT - natural) / gaps as f32;
+    let space = helvetica_width(' ') * font_size / 1000.0;
+    (extra > 0.0 && extra <= space * MAX_WORD_STRETCH).then_some(extra)
+}
+
+/// Hyphenation points of a word, as character offsets
+type HyphenBreaks<'a> = dyn Fn(&str) -> Vec<usize> + 'a;
+
+/// Where to hyphenate `token` so the part before the break plus a hyphen fits in `room`
+/// glyph units: a byte offset into `token`. Only all-letter words (with surrounding
+/// punctuation) are hyphenated.
+fn hyphen_split(token: &str, room: u32, breaks: &HyphenBreaks<'_>) -> Option<usize> {
+    let chars: Vec<(usize, char)> = token.char_indices().collect();
+    let start = chars.iter().position(|(_, c)| c.is_alphabetic())?;
+    let end = chars.iter().rposition(|(_, c)| c.is_alphabetic())? + 1;
+    if chars[start..end].iter().any(|(_, c)| !c.is_alphabetic()) {
+        return None;
+    }
+    let core: String = chars[start..end].iter().map(|(_, c)| *c).collect();
+    breaks(&core).into_iter().rev()
+        .map(|i| start + i)
+        .find(|&i| chars[..i].iter().map(|(_, c)| advance_width(*c)).sum::<u32>() + advance_width('-') <= room)
+        .map(|i| chars[i].0)
+}
+
+/// One rendered line of the text layout
+#[derive(Debug, Clone)]
+pub struct LayoutLine {
+    pub page: usize,
+    /// Line position on the page, counted from the top margin
+    pub slot: usize,
+    /// Index of the source text line this line was wrapped from
+    pub source_line: usize,
+    pub text: String,
+}
+
+/// Whether the paragraph containing `node` is justified (`w:jc` both or distribute)
+fn paragraph_is_justified(node: roxmltree::Node) -> bool {
+    node.ancestors()
+        .find(|n| n.tag_name().name() == "p")
+        .and_then(|p| p.children().find(|n| n.tag_name().name() == "pPr"))
+        .and_then(|ppr| ppr.children().find(|n| n.tag_name().name() == "jc"))
+        .and_then(|jc| jc.attributes().find(|a| a.name() == "val").map(|a| a.value().to_string()))
+        .map(|val| val == "both" || val == "distribute")
+        .unwrap_or(false)
+}
+
+/// Whether the paragraph containing `node` is right-to-left (`w:bidi`)
+fn paragraph_is_rtl(node: roxmltree::Node) -> bool {
+    node.ancestors()
+        .find(|n| n.tag_name().name() == "p")
+        .and_then(|p| p.children().find(|n| n.tag_name().name() == "pPr"))
+        .and_then(|ppr| ppr.children().find(|n| n.tag_name().name() == "bidi"))
+        .map(|bidi| !matches!(bidi.attributes().find(|a| a.name() == "val").map(|a| a.value()), Some("0") | Some("false")))
+        .unwrap_or(false)
+}
+
+/// Source lines (of the extracted text) that need special handling when rendered
+#[derive(Debug, Clone, Default)]
+pub struct LineFlags {
+    /// Lines from justified paragraphs
+    pub justified: HashSet<usize>,
+    /// Lines from right-to-left paragraphs
+    pub rtl: HashSet<usize>,
+}
+
+/// Wrap units of a source line with whether a space precedes them. Words are units;
+/// within a word, each CJK/Thai character is its own unit so lines can break between them.
+fn layout_tokens(line: &str) -> Vec<(String, bool)> {
+    use crate::font_fallback::breaks_anywhere;
+    let mut tokens = Vec::new();
+    for word in line.split_whitespace() {
+        let mut spaced = true;
+        let mut current = String::new();
+        for c in word.chars() {
+            if breaks_anywhere(c) {
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), spaced));
+                    spaced = false;
+                }
+                tokens.push((c.to_string(), spaced));
+                spaced = false;
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            tokens.push((current, spaced));
+        }
+    }
+    tokens
+}
+
+/// Header or footer text drawn in the page margin. `{PAGE}` and `{PAGES}` are replaced
+/// with the page number and page count when the page is rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Band {
+    pub text: String,
+    /// "left", "center" or "right"
+    pub alignment: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BandPair {
+    pub header: Option<Band>,
+    pub footer: Option<Band>,
+}
+
+/// Headers and footers of the document's (last) section, following Word's rules for
+/// first-page (`w:titlePg`) and even-page (`w:evenAndOddHeaders`) variants
+#[derive(Debug, Clone, Default)]
+pub struct PageBands {
+    pub default: BandPair,
+    pub first: BandPair,
+    pub even: BandPair,
+    pub title_page: bool,
+    pub even_and_odd: bool,
+}
+
+impl PageBands {
+    /// Bands for a 1-based page number
+    pub fn for_page(&self, page: usize) -> &BandPair {
+        if page == 1 && self.title_page {
+            &self.first
+        } else if page.is_multiple_of(2) && self.even_and_odd {
+            &self.even
+        } else {
+            &self.default
+        }
+    }
+}
+
+fn read_part(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<String>> {
+    match archive.by_name(name) {
+        Ok(mut file) => {
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf)?;
+            Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+        }
+        Err(zip::result::ZipError::FileNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Placeholder for a page-number field instruction (PAGE, NUMPAGES, SECTIONPAGES)
+fn page_field_placeholder(instr: &str) -> Option<&'static str> {
+    match instr.split_whitespace().next()?.to_ascii_uppercase().as_str() {
+        "PAGE" => Some("{PAGE}"),
+        "NUMPAGES" | "SECTIONPAGES" => Some("{PAGES}"),
+        _ => None,
+    }
+}
+
+/// Text of a header/footer part with page-number fields (simple or complex) turned into
+/// placeholders; paragraphs are joined on one line. `None` when the part has no text.
+fn band_from_part(xml: &str) -> Result<Option<Band>> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let mut paragraphs = Vec::new();
+    let mut alignment = None;
+    for p in doc.descendants().filter(|n| n.tag_name().name() == "p") {
+        let mut text = String::new();
+        // Open complex fields: (instruction, past the separator)
+        let mut fields: Vec<(String, bool)> = Vec::new();
+        for node in p.descendants() {
+            match node.tag_name().name() {
+                "fldChar" => match node.attributes().find(|a| a.name() == "fldCharType").map(|a| a.value()) {
+                    Some("begin") => fields.push((String::new(), false)),
+                    Some("separate") => if let Some(field) = fields.last_mut() { field.1 = true; },
+                    Some("end") => if let Some((instr, _)) = fields.pop() {
+                        text.push_str(page_field_placeholder(&instr).unwrap_or(""));
+                    },
+                    _ => {}
+                },
+                "instrText" => if let Some(field) = fields.last_mut() {
+                    field.0.push_str(node.text().unwrap_or(""));
+                },
+                "fldSimple" => {
+                    let instr = node.attributes().find(|a| a.name() == "instr").map(|a| a.value()).unwrap_or("");
+                    text.push_str(page_field_placeholder(instr).unwrap_or(""));
+                }
+                "t" => {
+                    let in_page_simple = node.ancestors().any(|a| {
+                        a.tag_name().name() == "fldSimple"
+                            && a.attributes().any(|at| at.name() == "instr" && page_field_placeholder(at.value()).is_some())
+                    });
+                    // Cached results of page fields are stale; other field results are kept
+                    let in_page_result = fields.last().map(|(instr, _)| page_field_placeholder(instr).is_some()).unwrap_or(false);
+                    let in_instruction = fields.last().map(|(_, separated)| !separated).unwrap_or(false);
+                    if !in_page_simple && !in_page_result && !in_instruction {
+                        text.push_str(node.text().unwrap_or(""));
+                    }
+                }
+                "tab" | "br" => text.push_str("  "),
+                _ => {}
+            }
+        }
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        if alignment.is_none() {
+            alignment = p.children().find(|n| n.tag_name().name() == "pPr")
+                .and_then(|ppr| ppr.children().find(|n| n.tag_name().name() == "jc"))
+                .and_then(|jc| jc.attributes().find(|a| a.name() == "val").map(|a| a.value().to_string()));
+        }
+        paragraphs.push(text);
+    }
+    if paragraphs.is_empty() {
+        return Ok(None);
+    }
+    let alignment = match alignment.as_deref() {
+        Some("center") => "center",
+        Some("right") | Some("end") => "right",
+        _ => "left",
+    };
+    Ok(Some(Band { text: paragraphs.join("  "), alignment: alignment.to_string() }))
+}
+
+/// Whether the run holding `node` is formatted as hidden
+fn run_is_hidden(node: roxmltree::Node) -> bool {
+    node.ancestors()
+        .find(|a| a.tag_name().name() == "r")
+        .and_then(|r| r.children().find(|n| n.tag_name().name() == "rPr"))
+        .and_then(|rpr| rpr.children().find(|n| n.tag_name().name() == "vanish"))
+        .is_some_and(|v| !matches!(v.attributes().find(|a| a.name() == "val").map(|a| a.value()), Some("0" | "false")))
+}
+
+/// Comments as `[Author] on "anchored text": comment` lines, in the order they appear
+/// in comments.xml
+fn comment_texts(archive: &mut ZipArchive<File>) -> Result<Vec<String>> {
+    let Some(comments_xml) = read_part(archive, "word/comments.xml")? else { return Ok(Vec::new()) };
+    let document_xml = read_part(archive, "word/document.xml")?.unwrap_or_default();
+    let attr = |n: roxmltree::Node, name: &str| n.attributes().find(|a| a.name() == name).map(|a| a.value().to_string());
+
+    // Text between each commentRangeStart and its commentRangeEnd
+    let mut anchors: HashMap<String, String> = HashMap::new();
+    if let Ok(doc) = roxmltree::Document::parse(&document_xml) {
+        let mut open: Vec<String> = Vec::new();
+        for node in doc.descendants() {
+            match node.tag_name().name() {
+                "commentRangeStart" => if let Some(id) = attr(node, "id") { open.push(id) },
+                "commentRangeEnd" => if let Some(id) = attr(node, "id") { open.retain(|o| *o != id) },
+                "t" => for id in &open {
+                    anchors.entry(id.clone()).or_default().push_str(node.text().unwrap_or(""));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    let doc = roxmltree::Document::parse(&comments_xml)?;
+    Ok(doc.descendants().filter(|n| n.tag_name().name() == "comment").map(|comment| {
+        let body = comment.descendants()
+            .filter(|n| n.tag_name().name() == "p")
+            .map(|p| p.descendants().filter(|n| n.tag_name().name() == "t").filter_map(|t| t.text()).collect::<String>())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let author = attr(comment, "author").unwrap_or_else(|| "Unknown".to_string());
+        match attr(comment, "id").and_then(|id| anchors.get(&id)).map(|a| a.trim()).filter(|a| !a.is_empty()) {
+            Some(anchor) => format!("[{}] on \"{}\": {}", author, anchor, body),
+            None => format!("[{}]: {}", author, body),
+        }
+    }).collect())
+}
+
+/// Footnote or endnote texts by id, skipping Word's separator pseudo-notes
+fn note_texts(xml: &str, kind: &str) -> Result<HashMap<String, String>> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let mut notes = HashMap::new();
+    for note in doc.descendants().filter(|n| n.tag_name().name() == kind) {
+        if note.attributes().any(|a| a.name() == "type" && a.value() != "normal") {
+            continue;
+        }
+        let Some(id) = note.attributes().find(|a| a.name() == "id").map(|a| a.value().to_string()) else { continue };
+        let paragraphs: Vec<String> = note.descendants()
+            .filter(|n| n.tag_name().name() == "p")
+            .map(|p| p.descendants().filter(|n| n.tag_name().name() == "t").filter_map(|t| t.text()).collect::<String>())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        notes.insert(id, paragraphs.join(" "));
+    }
+    Ok(notes)
+}
+
+/// Fonts used while rendering: Helvetica plus external fallback fonts, loaded once per
+/// document. A font that fails to load falls back to Helvetica.
+struct RunFonts {
+    builtin: IndirectFontRef,
+    external: HashMap<PathBuf, IndirectFontRef>,
+    subset: bool,
+}
+
+impl RunFonts {
+    fn get(&mut self, doc: &PdfDocumentReference, choice: crate::font_fallback::FontChoice) -> IndirectFontRef {
+        use crate::font_fallback::FontChoice;
+        let path = match choice {
+            FontChoice::Builtin => return self.builtin.clone(),
+            FontChoice::External(path) => path,
+        };
+        if let Some(f) = self.external.get(&path) {
+            return f.clone();
+        }
+        let loaded = File::open(&path).map_err(anyhow::Error::from)
+            .and_then(|f| doc.add_external_font_with_subsetting(f, self.subset).map_err(anyhow::Error::from));
+        match loaded {
+            Ok(f) => {
+                self.external.insert(path, f.clone());
+                f
+            }
+            Err(e) => {
+                warn!("Fallback font {:?} unusable, using Helvetica: {}", path, e);
+                self.builtin.clone()
+            }
+        }
+    }
+}
+
+/// Draw font runs as one text object starting at (x, y), switching fonts between runs
+/// so each run starts where the previous one ended
+fn write_runs(
+    doc: &PdfDocumentReference,
+    layer: &PdfLayerReference,
+    runs: Vec<(crate::font_fallback::FontChoice, String)>,
+    fonts: &mut RunFonts,
+    font_size: f32,
+    x: Mm,
+    y: Mm,
+) {
+    layer.begin_text_section();
+    layer.set_text_cursor(x, y);
+    for (choice, run) in runs {
+        let run_font = fonts.get(doc, choice);
+        layer.set_font(&run_font, font_size);
+        layer.write_text(run.as_str(), &run_font);
+    }
+    layer.end_text_section();
+}
+
+impl Default for PureRustConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PureRustConverter {
     pub fn new() -> Self {
         Self
@@ -19,44 +449,114 @@ impl PureRustConverter {
 
     /// Extract text from DOCX using pure Rust XML parsing
     pub fn extract_text_from_docx(&self, docx_path: &Path) -> Result<String> {
+        Ok(self.extract_text_and_line_flags(docx_path, false, false)?.0)
+    }
+
+    /// Body text plus whatever `scope` asks for. Headers and footers come first and last
+    /// as `Header: ...` / `Footer: ...` lines (labelled for first-page and even-page
+    /// variants), then notes and comments follow the body.
+    pub fn extract_text_with_scope(&self, docx_path: &Path, scope: &TextScope) -> Result<String> {
+        let (body, _) = self.extract_text_and_line_flags(docx_path, scope.footnotes, !scope.hidden)?;
+        let mut archive = ZipArchive::new(File::open(docx_path)?)?;
+        let mut text = String::new();
+        let mut footers = Vec::new();
+        if scope.headers {
+            for (label, band) in self.header_footer_texts(&mut archive)? {
+                if label.starts_with("Header") {
+                    text.push_str(&format!("{}: {}\n", label, band));
+                } else {
+                    footers.push(format!("{}: {}", label, band));
+                }
+            }
+            if !text.is_empty() {
+                text.push('\n');
+            }
+        }
+        text.push_str(&body);
+        if !footers.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&footers.join("\n"));
+        }
+        if scope.comments {
+            let comments = comment_texts(&mut archive)?;
+            if !comments.is_empty() {
+                text.push_str("\n\nComments:\n");
+                text.push_str(&comments.join("\n"));
+            }
+        }
+        Ok(text.trim().to_string())
+    }
+
+    /// Text of each header and footer referenced by any section, in document order and
+    /// once per part, labelled by kind and variant
+    fn header_footer_texts(&self, archive: &mut ZipArchive<File>) -> Result<Vec<(String, String)>> {
+        let Some(document_xml) = read_part(archive, "word/document.xml")? else { return Ok(Vec::new()) };
+        let rels = crate::docx_reader::relationship_targets(&read_part(archive, "word/_rels/document.xml.rels")?.unwrap_or_else(|| "<Relationships/>".to_string()))?;
+        let doc = roxmltree::Document::parse(&document_xml)?;
+        let mut seen = HashSet::new();
+        let mut out = Vec::new();
+        for reference in doc.descendants().filter(|n| matches!(n.tag_name().name(), "headerReference" | "footerReference")) {
+            let Some(target) = reference.attributes().find(|a| a.name() == "id").and_then(|a| rels.get(a.value())) else { continue };
+            let part = format!("word/{}", target.trim_start_matches("/word/").trim_start_matches('/'));
+            if !seen.insert(part.clone()) {
+                continue;
+            }
+            let Some(band) = read_part(archive, &part)?.map(|xml| band_from_part(&xml)).transpose()?.flatten() else { continue };
+            let kind = if reference.tag_name().name() == "headerReference" { "Header" } else { "Footer" };
+            let label = match reference.attributes().find(|a| a.name() == "type").map(|a| a.value()) {
+                Some("first") => format!("{} (first page)", kind),
+                Some("even") => format!("{} (even pages)", kind),
+                _ => kind.to_string(),
+            };
+            out.push((label, band.text));
+        }
+        Ok(out)
+    }
+
+    /// Extracted text plus the indices of its lines that come from justified or
+    /// right-to-left paragraphs. With `with_notes`, footnote/endnote references become
+    /// `[n]` markers and the note texts follow the body under a separator line.
+    /// With `skip_hidden`, runs formatted as hidden are left out.
+    fn extract_text_and_line_flags(&self, docx_path: &Path, with_notes: bool, skip_hidden: bool) -> Result<(String, LineFlags)> {
         let file = File::open(docx_path)?;
         let mut archive = ZipArchive::new(file)?;
         
         // Find the main document XML
-        let mut document_xml = String::new();
-        
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)?;
-            let name = file.name().to_string();
-            
-            if name == "word/document.xml" {
-                let mut buf = Vec::new();
-                file.read_to_end(&mut buf)?;
-                document_xml = String::from_utf8_lossy(&buf).to_string();
-                break;
-            }
-        }
+        let document_xml = read_part(&mut archive, "word/document.xml")?.unwrap_or_default();
         
         if document_xml.is_empty() {
             anyhow::bail!("No document.xml found in DOCX file");
         }
+        let (footnotes, endnotes) = if with_notes {
+            let notes = |archive: &mut ZipArchive<File>, part: &str, kind: &str| -> Result<HashMap<String, String>> {
+                read_part(archive, part)?.map(|xml| note_texts(&xml, kind)).transpose().map(Option::unwrap_or_default)
+            };
+            (notes(&mut archive, "word/footnotes.xml", "footnote")?, notes(&mut archive, "word/endnotes.xml", "endnote")?)
+        } else {
+            (HashMap::new(), HashMap::new())
+        };
         
         // Parse XML and extract text with basic whitespace semantics
         let doc = roxmltree::Document::parse(&document_xml)?;
         let mut text = String::new();
         let mut last_char: Option<char> = None;
+        let mut line = 0usize;
+        let mut justified = HashSet::new();
+        let mut rtl = HashSet::new();
+        // Referenced notes in order of first reference
+        let mut notes: Vec<&String> = Vec::new();
 
         for node in doc.descendants() {
             let name = node.tag_name().name();
             match name {
                 // Paragraph boundary
-                "p" => {
-                    if !text.ends_with('\n') {
-                        text.push('\n');
-                        last_char = Some('\n');
-                    }
+                "p" if !text.ends_with('\n') => {
+                    text.push('\n');
+                    last_char = Some('\n');
+                    line += 1;
                 }
                 // Text run
+                "t" if skip_hidden && run_is_hidden(node) => {}
                 "t" => {
                     if let Some(node_text) = node.text() {
                         // Preserve spaces if xml:space="preserve"
@@ -69,8 +569,16 @@ impl PureRustConverter {
                         if !content.is_empty() {
                             // Insert a space if needed between words
                             if let Some(c) = last_char { if !c.is_whitespace() && !content.starts_with([' ', '\n', '\t']) { text.push(' '); } }
+                            let added_lines = content.matches('\n').count();
+                            if paragraph_is_justified(node) {
+                                justified.extend(line..=line + added_lines);
+                            }
+                            if paragraph_is_rtl(node) {
+                                rtl.extend(line..=line + added_lines);
+                            }
+                            line += added_lines;
                             text.push_str(&content);
-                            last_char = content.chars().rev().next();
+                            last_char = content.chars().next_back();
                         }
                     }
                 }
@@ -78,32 +586,135 @@ impl PureRustConverter {
                 "br" => {
                     text.push('\n');
                     last_char = Some('\n');
+                    line += 1;
                 }
                 // Tab
                 "tab" => {
                     text.push('\t');
                     last_char = Some('\t');
                 }
+                "footnoteReference" | "endnoteReference" if with_notes => {
+                    let texts = if name == "footnoteReference" { &footnotes } else { &endnotes };
+                    let note = node.attributes().find(|a| a.name() == "id").and_then(|a| texts.get(a.value()));
+                    if let Some(note) = note {
+                        let number = match notes.iter().position(|n| std::ptr::eq(*n, note)) {
+                            Some(i) => i + 1,
+                            None => {
+                                notes.push(note);
+                                notes.len()
+                            }
+                        };
+                        text.push_str(&format!("[{}]", number));
+                        last_char = Some(']');
+                    }
+                }
                 _ => {}
             }
         }
+        if !notes.is_empty() {
+            text.push_str("\n\n__________\n");
+            for (i, note) in notes.iter().enumerate() {
+                text.push_str(&format!("[{}] {}\n", i + 1, note));
+            }
+        }
 
-        Ok(text.trim().to_string())
+        // Line numbers are relative to the trimmed text
+        let trimmed = text.trim();
+        let shift = text[..text.len() - text.trim_start().len()].matches('\n').count();
+        let relative = |lines: HashSet<usize>| -> HashSet<usize> { lines.into_iter().filter(|l| *l >= shift).map(|l| l - shift).collect() };
+        Ok((trimmed.to_string(), LineFlags { justified: relative(justified), rtl: relative(rtl) }))
+    }
+
+    /// Extract body paragraphs (in document order, including those inside table cells).
+    /// Empty paragraphs are kept so indices line up with `w:p` elements.
+    pub fn extract_paragraphs_from_docx(&self, docx_path: &Path) -> Result<Vec<String>> {
+        let file = File::open(docx_path)?;
+        let mut archive = ZipArchive::new(file)?;
+        let mut document_xml = String::new();
+        archive.by_name("word/document.xml")
+            .map_err(|_| anyhow::anyhow!("No document.xml found in DOCX file"))?
+            .read_to_string(&mut document_xml)?;
+
+        let doc = roxmltree::Document::parse(&document_xml)?;
+        let mut paragraphs = Vec::new();
+        for p in doc.descendants().filter(|n| n.tag_name().name() == "p") {
+            let mut text = String::new();
+            for node in p.descendants() {
+                match node.tag_name().name() {
+                    "t" => if let Some(t) = node.text() { text.push_str(t); },
+                    "tab" => text.push('\t'),
+                    "br" => text.push('\n'),
+                    _ => {}
+                }
+            }
+            paragraphs.push(text);
+        }
+        Ok(paragraphs)
     }
 
     /// Convert DOCX to PDF using pure Rust (no external dependencies)
     pub fn docx_to_pdf_pure(&self, docx_path: &Path, pdf_path: &Path) -> Result<()> {
+        self.docx_to_pdf_pure_with_options(docx_path, pdf_path, &PdfOptions::default())
+    }
+
+    pub fn docx_to_pdf_pure_with_options(&self, docx_path: &Path, pdf_path: &Path, options: &PdfOptions) -> Result<()> {
         // Extract text from DOCX
-        let text = self.extract_text_from_docx(docx_path)
+        let (text, flags) = self.extract_text_and_line_flags(docx_path, true, false)
             .with_context(|| format!("Failed to extract text from {:?}", docx_path))?;
+        let bands = self.read_page_bands(docx_path)
+            .with_context(|| format!("Failed to read headers/footers from {:?}", docx_path))?;
         
         // Create PDF with extracted text
-        self.create_pdf_from_text(&text, pdf_path)?;
+        self.render_text_pdf(&text, &flags, &bands, pdf_path, options)?;
         
         info!("Successfully converted DOCX to PDF using pure Rust");
         Ok(())
     }
 
+    /// Headers and footers referenced by the body's final `w:sectPr`
+    pub fn read_page_bands(&self, docx_path: &Path) -> Result<PageBands> {
+        let mut archive = ZipArchive::new(File::open(docx_path)?)?;
+        let mut bands = PageBands::default();
+        let Some(document_xml) = read_part(&mut archive, "word/document.xml")? else { return Ok(bands) };
+        let doc = roxmltree::Document::parse(&document_xml)?;
+        let Some(sect_pr) = doc.descendants()
+            .find(|n| n.tag_name().name() == "body")
+            .and_then(|body| body.children().rfind(|n| n.tag_name().name() == "sectPr"))
+        else {
+            return Ok(bands);
+        };
+        bands.title_page = sect_pr.children().any(|n| n.tag_name().name() == "titlePg");
+        bands.even_and_odd = read_part(&mut archive, "word/settings.xml")?
+            .map(|xml| xml.contains("evenAndOddHeaders"))
+            .unwrap_or(false);
+
+        let rels_xml = read_part(&mut archive, "word/_rels/document.xml.rels")?.unwrap_or_default();
+        let rels = if rels_xml.is_empty() { None } else { Some(roxmltree::Document::parse(&rels_xml)?) };
+        for reference in sect_pr.children().filter(|n| matches!(n.tag_name().name(), "headerReference" | "footerReference")) {
+            let rid = reference.attributes().find(|a| a.name() == "id").map(|a| a.value());
+            let target = rels.as_ref().zip(rid).and_then(|(rels, rid)| {
+                rels.descendants()
+                    .find(|r| r.attribute("Id") == Some(rid))
+                    .and_then(|r| r.attribute("Target"))
+            });
+            let Some(target) = target else { continue };
+            let part = format!("word/{}", target.trim_start_matches("/word/").trim_start_matches('/'));
+            let Some(xml) = read_part(&mut archive, &part)? else { continue };
+            let pair = match reference.attributes().find(|a| a.name() == "type").map(|a| a.value()) {
+                Some("first") => &mut bands.first,
+                Some("even") => &mut bands.even,
+                _ => &mut bands.default,
+            };
+            let band = band_from_part(&xml)?;
+            if reference.tag_name().name() == "headerReference" {
+                pair.header = band;
+            } else {
+                pair.footer = band;
+            }
+        }
+        Ok(bands)
+    }
+
     // Backward-compat wrapper names expected by tests
     pub fn convert_docx_to_pdf(&self, docx_path: &Path, pdf_path: &Path) -> Result<()> {
         self.docx_to_pdf_pure(docx_path, pdf_path)
@@ -121,75 +732,165 @@ impl PureRustConverter {
         self.docx_to_images_pure(docx_path, output_dir, fmt)
     }
 
-    /// Create a PDF from text content
+    /// Lay out plain text the way `create_pdf_from_text` renders it: A4 pages, 11pt
+    /// Helvetica, 5mm lines, greedy wrapping to the 170mm text width using Helvetica
+    /// metrics (CJK characters are one em wide and may break anywhere). Blank source lines
+    /// take no space. Page and slot numbers are zero-based.
+    pub fn layout_text(&self, text: &str) -> Vec<LayoutLine> {
+        self.layout_text_with(text, None)
+    }
+
+    /// `layout_text`, hyphenating a word that would start a new line when `breaks`
+    /// (hyphenation points of a word, as character offsets) allows part of it to fit
+    pub fn layout_text_with(&self, text: &str, breaks: Option<&HyphenBreaks<'_>>) -> Vec<LayoutLine> {
+        let mut out = Vec::new();
+        let mut page = 0usize;
+        let mut slot = 0usize;
+
+        for (source_line, line) in text.lines().enumerate() {
+            let mut current_line = String::new();
+            let mut width = 0u32;
+            for (mut token, spaced) in layout_tokens(line) {
+                let mut token_width = text_width(&token);
+                let gap = if spaced && !current_line.is_empty() { advance_width(' ') } else { 0 };
+                if width + gap + token_width > LAYOUT_LINE_UNITS && !current_line.is_empty() {
+                    let room = LAYOUT_LINE_UNITS.saturating_sub(width + gap);
+                    if let Some(split) = breaks.filter(|_| spaced).and_then(|b| hyphen_split(&token, room, b)) {
+                        current_line.push(' ');
+                        current_line.push_str(&token[..split]);
+                        current_line.push('-');
+                        token = token[split..].to_string();
+                        token_width = text_width(&token);
+                    }
+                    out.push(LayoutLine { page, slot, source_line, text: std::mem::take(&mut current_line) });
+                    width = 0;
+                    slot += 1;
+                    if slot == LAYOUT_LINES_PER_PAGE {
+                        page += 1;
+                        slot = 0;
+                    }
+                }
+                if spaced && !current_line.is_empty() {
+                    current_line.push(' ');
+                    width += gap;
+                }
+                current_line.push_str(&token);
+                width += token_width;
+            }
+            if !current_line.is_empty() {
+                out.push(LayoutLine { page, slot, source_line, text: current_line });
+                slot += 1;
+                if slot == LAYOUT_LINES_PER_PAGE {
+                    page += 1;
+                    slot = 0;
+                }
+            }
+        }
+        out
+    }
+
+    /// Number of pages `create_pdf_from_text` produces for `text` (at least 1)
+    pub fn page_count_for_text(&self, text: &str) -> usize {
+        self.layout_text(text).last().map(|l| l.page + 1).unwrap_or(1)
+    }
+
+    /// Create a PDF from text content. Characters Helvetica cannot draw are rendered with
+    /// per-script fallback fonts (see `font_fallback`).
     pub fn create_pdf_from_text(&self, text: &str, pdf_path: &Path) -> Result<()> {
+        self.create_pdf_from_text_with_options(text, pdf_path, &PdfOptions::default())
+    }
+
+    pub fn create_pdf_from_text_with_options(&self, text: &str, pdf_path: &Path, options: &PdfOptions) -> Result<()> {
+        self.render_text_pdf(text, &LineFlags::default(), &PageBands::default(), pdf_path, options)
+    }
+
+    /// Render laid-out text; justified lines (source line indices in `flags`) are
+    /// stretched to the full text width except for the last line of each. Right-to-left
+    /// lines (flagged, or starting with an RTL letter) are reordered for display and
+    /// right-aligned. Header and footer bands are drawn in the top and bottom margins
+    /// of every page.
+    fn render_text_pdf(&self, text: &str, flags: &LineFlags, bands: &PageBands, pdf_path: &Path, options: &PdfOptions) -> Result<()> {
+        use crate::bidi::{base_direction, contains_rtl, visual_order, Direction};
+        use crate::font_fallback::{FallbackResolver, FontChoice};
         let (doc, page1, layer1) = PdfDocument::new("Document", Mm(210.0), Mm(297.0), "Layer 1");
-        let current_layer = doc.get_page(page1).get_layer(layer1);
         
         // Use embedded font or built-in font
         let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let resolver = FallbackResolver::discover();
+        let mut fonts = RunFonts { builtin: font.clone(), external: HashMap::new(), subset: options.subset_fonts };
         
         // Configure text layout
-        let font_size = 11.0;
+        let font_size = LAYOUT_FONT_SIZE;
         let line_height = Mm(5.0);
         let margin_left = Mm(20.0);
         let margin_top = Mm(280.0);
-        let margin_bottom = Mm(20.0);
-        let page_width = Mm(210.0);
-        let page_height = Mm(297.0);
-        let text_width = page_width - (margin_left * 2.0);
-        
-        let lines: Vec<&str> = text.lines().collect();
-        let mut current_page = page1;
-        let mut current_layer = layer1;
-        let mut y_position = margin_top;
-        
-        for line in lines {
-            // Check if we need a new page
-            if y_position < margin_bottom {
-                let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Page layer");
-                current_page = new_page;
-                current_layer = new_layer;
-                y_position = margin_top;
+        
+        let mut pages = vec![(page1, layer1)];
+        
+        let breaks = self.hyphenation_breaks(text, options);
+        let layout = self.layout_text_with(text, breaks.as_deref());
+        for (i, line) in layout.iter().enumerate() {
+            while pages.len() <= line.page {
+                pages.push(doc.add_page(Mm(210.0), Mm(297.0), "Page layer"));
             }
-            
-            // Word wrap if line is too long
-            let words: Vec<&str> = line.split_whitespace().collect();
-            let mut current_line = String::new();
-            let max_chars_per_line = 80; // Approximate
-            
-            for word in words {
-                if current_line.len() + word.len() + 1 > max_chars_per_line {
-                    // Write current line
-                    if !current_line.is_empty() {
-                        doc.get_page(current_page)
-                            .get_layer(current_layer)
-                            .use_text(&current_line, font_size, margin_left, y_position, &font);
-                        y_position -= line_height;
-                        current_line.clear();
-                        
-                        // Check for new page
-                        if y_position < margin_bottom {
-                            let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Page layer");
-                            current_page = new_page;
-                            current_layer = new_layer;
-                            y_position = margin_top;
-                        }
+            let (current_page, current_layer) = pages[line.page];
+            let y_position = margin_top - line_height * line.slot as f32;
+            let layer = doc.get_page(current_page).get_layer(current_layer);
+            let rtl = flags.rtl.contains(&line.source_line) || base_direction(&line.text) == Some(Direction::Rtl);
+            if rtl || contains_rtl(&line.text) {
+                let visual = visual_order(&line.text, if rtl { Direction::Rtl } else { Direction::Ltr });
+                let x = if rtl {
+                    let width = Mm(text_width(&visual) as f32 * font_size / 1000.0 * 25.4 / 72.0);
+                    Mm(190.0) - width
+                } else {
+                    margin_left
+                };
+                write_runs(&doc, &layer, resolver.split_runs(&visual), &mut fonts, font_size, x, y_position);
+                continue;
+            }
+            let runs = resolver.split_runs(&line.text);
+            if runs.iter().all(|(choice, _)| *choice == FontChoice::Builtin) {
+                let continues = layout.get(i + 1).map(|next| next.source_line == line.source_line).unwrap_or(false);
+                let spacing = (continues && flags.justified.contains(&line.source_line))
+                    .then(|| justified_word_spacing(&line.text, font_size))
+                    .flatten();
+                match spacing {
+                    Some(spacing) => {
+                        // Word spacing (Tw) only stretches the single-byte spaces of the builtin font
+                        layer.begin_text_section();
+                        layer.set_font(&font, font_size);
+                        layer.set_text_cursor(margin_left, y_position);
+                        layer.set_word_spacing(spacing);
+                        layer.write_text(line.text.clone(), &font);
+                        layer.set_word_spacing(0.0);
+                        layer.end_text_section();
                     }
+                    None => layer.use_text(&line.text, font_size, margin_left, y_position, &font),
                 }
-                
-                if !current_line.is_empty() {
-                    current_line.push(' ');
-                }
-                current_line.push_str(word);
+                continue;
             }
-            
-            // Write remaining text in line
-            if !current_line.is_empty() {
-                doc.get_page(current_page)
-                    .get_layer(current_layer)
-                    .use_text(&current_line, font_size, margin_left, y_position, &font);
-                y_position -= line_height;
+            write_runs(&doc, &layer, runs, &mut fonts, font_size, margin_left, y_position);
+        }
+
+        // Bands need the page count, so they are drawn once the body is laid out
+        let band_size = 9.0;
+        let page_count = pages.len();
+        for (index, (page, layer)) in pages.iter().enumerate() {
+            let layer = doc.get_page(*page).get_layer(*layer);
+            let pair = bands.for_page(index + 1);
+            for (band, y) in [(&pair.header, Mm(287.0)), (&pair.footer, Mm(10.0))] {
+                let Some(band) = band else { continue };
+                let text = band.text
+                    .replace("{PAGE}", &(index + 1).to_string())
+                    .replace("{PAGES}", &page_count.to_string());
+                let width = Mm(text_width(&text) as f32 * band_size / 1000.0 * 25.4 / 72.0);
+                let x = match band.alignment.as_str() {
+                    "center" => Mm((210.0 - width.0) / 2.0),
+                    "right" => Mm(190.0) - width,
+                    _ => margin_left,
+                };
+                let runs = resolver.split_runs(&text);
+                write_runs(&doc, &layer, runs, &mut fonts, band_size, x, y);
             }
         }
         
@@ -198,6 +899,35 @@ impl PureRustConverter {
         Ok(())
     }
 
+    /// Hyphenation points for the text's language, when enabled and patterns are installed
+    #[cfg(feature = "hyphenation")]
+    fn hyphenation_breaks(&self, text: &str, options: &PdfOptions) -> Option<Box<HyphenBreaks<'static>>> {
+        use crate::hyphenation::{language_for_tag, Hyphenator};
+        if !options.hyphenate {
+            return None;
+        }
+        let tag = match &options.language {
+            Some(language) => language.clone(),
+            None => {
+                let sample_end = text.char_indices().nth(2000).map(|(i, _)| i).unwrap_or(text.len());
+                crate::language::detect_language(&text[..sample_end])?.tag?
+            }
+        };
+        let language = language_for_tag(&tag)?;
+        match Hyphenator::load(language) {
+            Ok(hyphenator) => Some(Box::new(move |word: &str| hyphenator.hyphenate(word))),
+            Err(e) => {
+                tracing::debug!("Hyphenation disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "hyphenation"))]
+    fn hyphenation_breaks(&self, _text: &str, _options: &PdfOptions) -> Option<Box<HyphenBreaks<'static>>> {
+        None
+    }
+
     /// Convert PDF to images using pure Rust
     pub fn pdf_to_images_pure(
         &self,
@@ -276,6 +1006,57 @@ impl PureRustConverter {
         self.pdf_to_images_pure(&temp_pdf, output_dir, format)
     }
 
+    /// Preview image of a page (zero-based) `width` pixels wide on an A4 canvas. Each
+    /// laid-out line is drawn as a gray bar as wide as its text, the way thumbnails
+    /// "greek" text too small to read; headers and footers are lighter. Images and
+    /// drawings are not shown.
+    pub fn render_page_preview(&self, docx_path: &Path, page: usize, width: u32) -> Result<DynamicImage> {
+        let (text, _) = self.extract_text_and_line_flags(docx_path, true, false)?;
+        let bands = self.read_page_bands(docx_path)?;
+        let layout = self.layout_text(&text);
+        let page_count = layout.last().map(|l| l.page + 1).unwrap_or(1);
+        if page >= page_count {
+            anyhow::bail!("page {} out of range (document has {} pages)", page + 1, page_count);
+        }
+
+        let width = width.max(16);
+        let height = (width as f32 * 297.0 / 210.0).round() as u32;
+        let px_per_mm = width as f32 / 210.0;
+        let mut img = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+        // Bar covering the x-height of a line whose baseline is `baseline` mm from the top
+        let mut bar = |x: f32, baseline: f32, text: &str, size: f32, shade: u8| {
+            let length = text_width(text) as f32 * size / 1000.0 * 25.4 / 72.0;
+            let x_height = size * 0.52 * 25.4 / 72.0;
+            let (x0, x1) = ((x * px_per_mm) as u32, ((x + length) * px_per_mm).ceil() as u32);
+            let y1 = (baseline * px_per_mm).round() as u32;
+            let y0 = ((baseline - x_height) * px_per_mm).round().min(y1 as f32 - 1.0).max(0.0) as u32;
+            for y in y0..y1.min(height) {
+                for x in x0..x1.min(width) {
+                    img.put_pixel(x, y, Rgba([shade, shade, shade, 255]));
+                }
+            }
+        };
+
+        for line in layout.iter().filter(|l| l.page == page) {
+            bar(20.0, 17.0 + 5.0 * line.slot as f32, &line.text, LAYOUT_FONT_SIZE, 110);
+        }
+        let pair = bands.for_page(page + 1);
+        for (band, baseline) in [(&pair.header, 10.0), (&pair.footer, 287.0)] {
+            let Some(band) = band else { continue };
+            let text = band.text
+                .replace("{PAGE}", &(page + 1).to_string())
+                .replace("{PAGES}", &page_count.to_string());
+            let length = text_width(&text) as f32 * 9.0 / 1000.0 * 25.4 / 72.0;
+            let x = match band.alignment.as_str() {
+                "center" => (210.0 - length) / 2.0,
+                "right" => 190.0 - length,
+                _ => 20.0,
+            };
+            bar(x, baseline, &text, 9.0, 170);
+        }
+        Ok(DynamicImage::ImageRgba8(img))
+    }
+
     /// Create a thumbnail from an image
     pub fn create_thumbnail(
         &self,
@@ -420,10 +1201,8 @@ impl PureRustConverter {
                             plain_text.push_str(&"#".repeat(level as usize));
                             plain_text.push(' ');
                         }
-                        Tag::Paragraph => {
-                            if !plain_text.is_empty() {
-                                plain_text.push_str("\n\n");
-                            }
+                        Tag::Paragraph if !plain_text.is_empty() => {
+                            plain_text.push_str("\n\n");
                         }
                         Tag::List(_) => {
                             list_depth += 1;
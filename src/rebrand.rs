@@ -0,0 +1,152 @@
+//! Whole-document rebranding for `rebrand`: one mapping swaps fonts, brand colors,
+//! header/footer text, the logo and company or product names.
+//!
+//! Fonts and colors are the ones the document sets itself: the default font, paragraph
+//! fonts and colors, paragraph shading and borders, table shading and text watermarks.
+//! The logo is every image in a header or footer layout plus each body image whose alt
+//! text mentions "logo"; a new logo keeps the width and height set for the old one.
+
+use anyhow::Result;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::docx_handler::{DocxOp, DocxStyle, WatermarkContent};
+
+/// A brand profile, written in JSON as
+/// `{"fonts": {"Calibri": "Inter"}, "colors": {"1F4E79": "0B6E4F"}, "terms": {"Acme": "Globex"}}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrandMapping {
+    /// Old font family → new, matched case-insensitively
+    #[serde(default)]
+    pub fonts: BTreeMap<String, String>,
+    /// New default font, for documents that rely on the built-in one
+    pub default_font: Option<String>,
+    /// Old hex RGB → new; a leading '#' is ignored
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+    /// New header text; the layout (logo, alignment) is kept
+    pub header: Option<String>,
+    /// New footer text; the layout is kept
+    pub footer: Option<String>,
+    /// Replacement logo, base64 PNG or JPEG
+    pub logo_base64: Option<String>,
+    /// Old name → new, matched as whole words in any case and written in the case of
+    /// each occurrence, in the text, link URLs, alt text, bookmarks and properties
+    #[serde(default)]
+    pub terms: BTreeMap<String, String>,
+}
+
+impl BrandMapping {
+    /// Colors as (old, new) pairs of bare upper-case hex, checked
+    pub fn color_pairs(&self) -> Result<Vec<(String, String)>> {
+        self.colors.iter().map(|(old, new)| Ok((hex_color(old)?, hex_color(new)?))).collect()
+    }
+
+    /// The decoded logo, if the mapping has one
+    pub fn logo(&self) -> Result<Option<Vec<u8>>> {
+        self.logo_base64.as_deref()
+            .map(|data| base64::engine::general_purpose::STANDARD.decode(data).map_err(|e| anyhow::anyhow!("invalid logo_base64: {}", e)))
+            .transpose()
+    }
+
+    /// Terms longest first, so "Acme Cloud" is renamed before "Acme"
+    pub fn ordered_terms(&self) -> Vec<(&str, &str)> {
+        let mut terms: Vec<(&str, &str)> = self.terms.iter()
+            .filter(|(old, _)| !old.trim().is_empty())
+            .map(|(old, new)| (old.as_str(), new.as_str()))
+            .collect();
+        terms.sort_by_key(|(old, _)| std::cmp::Reverse(old.chars().count()));
+        terms
+    }
+}
+
+fn hex_color(color: &str) -> Result<String> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid color '{}': use 6-digit hex RGB such as 1F4E79", color);
+    }
+    Ok(hex.to_ascii_uppercase())
+}
+
+/// Rename the fonts of `ops` per `fonts`; returns how many font settings changed
+pub(crate) fn swap_fonts(ops: &mut [DocxOp], fonts: &BTreeMap<String, String>) -> usize {
+    let mut changed = 0;
+    let mut swap = |family: &mut String| {
+        if let Some((_, new)) = fonts.iter().find(|(old, _)| old.trim().eq_ignore_ascii_case(family.trim())) {
+            if *family != *new {
+                *family = new.clone();
+                changed += 1;
+            }
+        }
+    };
+    for op in ops.iter_mut() {
+        match op {
+            DocxOp::DefaultFont { family: Some(family), .. } => swap(family),
+            DocxOp::Paragraph { style: Some(DocxStyle { font_family: Some(family), .. }), .. } => swap(family),
+            DocxOp::Watermark(watermark) => {
+                if let WatermarkContent::Text { font: Some(family), .. } = &mut watermark.content {
+                    swap(family);
+                }
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+/// Recolor `ops` per `colors` (pairs from `BrandMapping::color_pairs`); returns how many
+/// color settings changed
+pub(crate) fn swap_colors(ops: &mut [DocxOp], colors: &[(String, String)]) -> usize {
+    let mut changed = 0;
+    let mut swap = |color: &mut String| {
+        let current = color.trim().trim_start_matches('#').to_ascii_uppercase();
+        if let Some((_, new)) = colors.iter().find(|(old, _)| *old == current) {
+            *color = new.clone();
+            changed += 1;
+        }
+    };
+    for op in ops.iter_mut() {
+        match op {
+            DocxOp::Paragraph { style: Some(style), .. } => {
+                style.color.iter_mut().chain(style.shading.iter_mut()).for_each(&mut swap);
+                if let Some(borders) = &mut style.borders {
+                    [&mut borders.top, &mut borders.bottom, &mut borders.left, &mut borders.right].into_iter()
+                        .filter_map(|side| side.as_mut()?.color.as_mut())
+                        .for_each(&mut swap);
+                }
+            }
+            DocxOp::Table { data } => data.cell_shading.iter_mut().for_each(&mut swap),
+            DocxOp::Watermark(watermark) => {
+                if let WatermarkContent::Text { color: Some(color), .. } = &mut watermark.content {
+                    swap(color);
+                }
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+/// Put `logo` in place of the document's logos; returns how many were replaced
+pub(crate) fn swap_logos(ops: &mut [DocxOp], logo: &[u8]) -> usize {
+    let mut replaced = 0;
+    for op in ops.iter_mut() {
+        match op {
+            DocxOp::HeaderLayout { layout, .. } => {
+                let cells = layout.table.iter_mut().flat_map(|table| table.cells.iter_mut()).filter_map(|cell| cell.image.as_mut());
+                for image in layout.logo.iter_mut().chain(cells) {
+                    image.data = logo.to_vec();
+                    replaced += 1;
+                }
+            }
+            DocxOp::Image { data, alt_text: Some(alt_text), .. } if alt_text.to_lowercase().contains("logo") => {
+                *data = logo.to_vec().into();
+                replaced += 1;
+            }
+            _ => {}
+        }
+    }
+    replaced
+}
@@ -0,0 +1,156 @@
+//! Interactive `docx-mcp repl` for trying tools by hand, e.g. to reproduce what an MCP
+//! client reported. Each line is a tool name followed by its JSON arguments:
+//!
+//! ```text
+//! docx> create_document
+//! docx> add_heading {"document_id": "${_.document_id}", "text": "Notes", "level": 1}
+//! ```
+//!
+//! Tab completes tool names. The last result is kept as `_`, so `${_.field}` works as in
+//! `run` scripts (see `script`); `help <tool>` prints a tool's description and schema.
+
+use anyhow::Result;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::docx_tools::DocxToolsProvider;
+
+/// One line of input, parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Empty,
+    Quit,
+    /// `help`, or `help <tool>`
+    Help(Option<String>),
+    /// `tools`: list the available tool names
+    Tools,
+    Call { tool: String, arguments: Value },
+}
+
+/// Parse a line: a command word or tool name, then optional JSON arguments
+pub fn parse_line(line: &str) -> Result<Command> {
+    let line = line.trim();
+    let (word, rest) = match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    };
+    Ok(match word {
+        "" => Command::Empty,
+        "quit" | "exit" => Command::Quit,
+        "help" | "?" => Command::Help((!rest.is_empty()).then(|| rest.to_string())),
+        "tools" => Command::Tools,
+        tool => {
+            let arguments = if rest.is_empty() {
+                serde_json::json!({})
+            } else {
+                serde_json::from_str(rest).map_err(|e| anyhow::anyhow!("Arguments must be a JSON object: {}", e))?
+            };
+            if !arguments.is_object() {
+                anyhow::bail!("Arguments must be a JSON object, e.g. {{\"document_id\": \"...\"}}");
+            }
+            Command::Call { tool: tool.to_string(), arguments }
+        }
+    })
+}
+
+/// Tool names (and commands) starting with `prefix`, sorted
+pub fn complete(names: &[String], prefix: &str) -> Vec<String> {
+    let mut matches: Vec<String> = names.iter()
+        .map(String::as_str)
+        .chain(["help", "tools", "quit"])
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Completes the first word of the line against the tool names
+struct ToolCompleter {
+    names: Vec<String>,
+}
+
+impl Completer for ToolCompleter {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        if before.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        Ok((0, complete(&self.names, before)))
+    }
+}
+
+impl Hinter for ToolCompleter {
+    type Hint = String;
+}
+impl Highlighter for ToolCompleter {}
+impl Validator for ToolCompleter {}
+impl rustyline::Helper for ToolCompleter {}
+
+/// Read lines until `quit` or end of input, printing each response as pretty JSON
+pub async fn run(provider: &DocxToolsProvider) -> Result<()> {
+    let tools = provider.list_tools().await;
+    let names: Vec<String> = tools.iter().map(|t| t.name.clone()).collect();
+    let mut editor: rustyline::Editor<ToolCompleter, rustyline::history::DefaultHistory> = rustyline::Editor::new()?;
+    editor.set_helper(Some(ToolCompleter { names: names.clone() }));
+    let mut results: HashMap<String, Value> = HashMap::new();
+
+    println!("docx-mcp repl: {} tools. Type a tool name and JSON arguments, `help` or `quit`.", names.len());
+    loop {
+        let line = match editor.readline("docx> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        let command = match parse_line(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                continue;
+            }
+        };
+        match command {
+            Command::Empty => {}
+            Command::Quit => break,
+            Command::Tools => println!("{}", names.join("\n")),
+            Command::Help(None) => {
+                println!("<tool> [json]   call a tool, e.g. extract_text {{\"document_id\": \"${{_.document_id}}\"}}");
+                println!("tools           list tool names (Tab completes them)");
+                println!("help <tool>     describe a tool and its arguments");
+                println!("quit            leave");
+            }
+            Command::Help(Some(name)) => match tools.iter().find(|t| t.name == name) {
+                Some(tool) => {
+                    println!("{}", tool.description.as_deref().unwrap_or(""));
+                    println!("{}", serde_json::to_string_pretty(&tool.input_schema)?);
+                }
+                None => eprintln!("error: no tool named '{}'", name),
+            },
+            Command::Call { tool, arguments } => {
+                let arguments = match crate::script::substitute(&arguments, &results) {
+                    Ok(arguments) => arguments,
+                    Err(e) => {
+                        eprintln!("error: {:#}", e);
+                        continue;
+                    }
+                };
+                let result = crate::script::call(provider, &tool, arguments).await;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+                results.insert("_".to_string(), result);
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,18 +1,23 @@
+use mcp_core::types::{CallToolResponse, ImageContent, TextContent, ToolResponseContent};
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ToolOutcome {
     Ok { message: Option<String> },
+    /// A file was written; its hash lets later steps check it is unchanged
+    Saved { message: Option<String>, sha256: String, size_bytes: u64 },
     Created { document_id: String, message: Option<String> },
     Text { text: String },
     Metadata { metadata: serde_json::Value },
-    Documents { documents: serde_json::Value },
+    Documents { documents: serde_json::Value, total: Option<usize>, next_offset: Option<usize> },
     Images { images: Vec<String>, message: Option<String> },
     Security { security: serde_json::Value },
     Storage { storage: serde_json::Value },
     Statistics { statistics: serde_json::Value },
     Structure { structure: serde_json::Value },
+    /// Base64 image returned as an image content block, with details in the JSON text
+    Image { data: String, mime_type: String, metadata: serde_json::Value },
     Error { code: ErrorCode, error: String, hint: Option<String> },
 }
 
@@ -20,14 +25,62 @@ pub enum ToolOutcome {
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ErrorCode {
     DocNotFound,
-    ValidationError,
-    SecurityDenied,
+    SecurityBlocked,
+    InvalidArgument,
+    UnsupportedOnOpenedDoc,
+    IoError,
     LimitExceeded,
+    DocumentLocked,
     UnknownTool,
     InternalError,
 }
 
+impl ErrorCode {
+    /// Code for an error, looking through its cause chain for handler, security and
+    /// I/O errors; `fallback` is used when nothing more specific is found
+    pub fn classify(err: &anyhow::Error, fallback: ErrorCode) -> ErrorCode {
+        use crate::docx_handler::DocxError;
+        use crate::locks::LockError;
+        use crate::security::SecurityError;
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<DocxError>() {
+                return match e {
+                    DocxError::NotFound(_) => ErrorCode::DocNotFound,
+                    DocxError::UnsupportedOnOpenedDoc(_) => ErrorCode::UnsupportedOnOpenedDoc,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<SecurityError>() {
+                return ErrorCode::for_security(e);
+            }
+            if let Some(e) = cause.downcast_ref::<LockError>() {
+                return match e {
+                    LockError::Held { .. } => ErrorCode::DocumentLocked,
+                    LockError::NotLocked(_) => ErrorCode::InvalidArgument,
+                };
+            }
+            if cause.is::<std::io::Error>() || cause.is::<zip::result::ZipError>() {
+                return ErrorCode::IoError;
+            }
+        }
+        fallback
+    }
+
+    pub fn for_security(err: &crate::security::SecurityError) -> ErrorCode {
+        use crate::security::SecurityError;
+        match err {
+            SecurityError::FileTooLarge { .. } | SecurityError::TooManyDocuments => ErrorCode::LimitExceeded,
+            _ => ErrorCode::SecurityBlocked,
+        }
+    }
+}
+
 impl ToolOutcome {
+    /// Error outcome with a code derived from the error (see `ErrorCode::classify`)
+    pub fn from_error<E: Into<anyhow::Error>>(err: E, fallback: ErrorCode) -> Self {
+        let err = err.into();
+        ToolOutcome::Error { code: ErrorCode::classify(&err, fallback), error: err.to_string(), hint: None }
+    }
+
     pub fn success(&self) -> bool {
         !matches!(self, ToolOutcome::Error { .. })
     }
@@ -39,4 +92,103 @@ impl ToolOutcome {
             "error": format!("serialization failed: {}", e),
         }))
     }
+
+    /// Backward-compatible JSON shaping with the `success` boolean at top level
+    pub fn into_legacy_json(self) -> serde_json::Value {
+        match self {
+            ToolOutcome::Ok { message } => {
+                let mut obj = serde_json::json!({"success": true});
+                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
+                obj
+            }
+            ToolOutcome::Saved { message, sha256, size_bytes } => {
+                let mut obj = serde_json::json!({"success": true, "sha256": sha256, "size_bytes": size_bytes});
+                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
+                obj
+            }
+            ToolOutcome::Created { document_id, message } => {
+                let mut obj = serde_json::json!({"success": true, "document_id": document_id});
+                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
+                obj
+            }
+            ToolOutcome::Text { text } => serde_json::json!({"success": true, "text": text}),
+            ToolOutcome::Metadata { metadata } => {
+                // Heuristic: if this looks like search results (matches/total_matches), flatten.
+                let is_search_shape = metadata.get("matches").is_some() || metadata.get("total_matches").is_some();
+                if is_search_shape {
+                    let mut obj = serde_json::json!({"success": true});
+                    if let Some(map) = metadata.as_object() {
+                        for (k, v) in map { obj[&k[..]] = v.clone(); }
+                    }
+                    obj
+                } else {
+                    serde_json::json!({"success": true, "metadata": metadata})
+                }
+            }
+            ToolOutcome::Documents { documents, total, next_offset } => {
+                let mut obj = serde_json::json!({"success": true, "documents": documents});
+                if let Some(t) = total { obj["total"] = serde_json::json!(t); }
+                if let Some(n) = next_offset { obj["next_offset"] = serde_json::json!(n); }
+                obj
+            }
+            ToolOutcome::Images { images, message } => {
+                let mut obj = serde_json::json!({"success": true, "images": images});
+                if let Some(m) = message { obj["message"] = serde_json::Value::String(m); }
+                obj
+            }
+            ToolOutcome::Security { security } => serde_json::json!({"success": true, "security": security}),
+            ToolOutcome::Storage { storage } => serde_json::json!({"success": true, "storage": storage}),
+            ToolOutcome::Statistics { statistics } => serde_json::json!({"success": true, "statistics": statistics}),
+            ToolOutcome::Structure { structure } => serde_json::json!({"success": true, "structure": structure}),
+            ToolOutcome::Image { metadata, .. } => serde_json::json!({"success": true, "metadata": metadata}),
+            ToolOutcome::Error { code, error, hint } => {
+                let mut obj = serde_json::json!({"success": false, "error": error});
+                obj["code"] = serde_json::json!(code);
+                if let Some(h) = hint { obj["hint"] = serde_json::Value::String(h); }
+                obj
+            }
+        }
+    }
+
+    /// Build the MCP response. In structured mode the JSON object is also attached as
+    /// `structuredContent` (under `_meta`, which is the only extension slot mcp-core's
+    /// response type has) and failures set `is_error`; the text block is the same JSON
+    /// in both modes so text-only clients keep working.
+    pub fn into_response(self, mode: ResponseMode) -> CallToolResponse {
+        let is_error = !self.success();
+        let image = match &self {
+            ToolOutcome::Image { data, mime_type, .. } => Some(ToolResponseContent::Image(ImageContent {
+                content_type: "image".into(),
+                data: data.clone(),
+                mime_type: mime_type.clone(),
+                annotations: None,
+            })),
+            _ => None,
+        };
+        let body = self.into_legacy_json();
+        let text = ToolResponseContent::Text(TextContent {
+            content_type: "application/json".into(),
+            text: body.to_string(),
+            annotations: None,
+        });
+        let content = std::iter::once(text).chain(image).collect();
+        match mode {
+            ResponseMode::Structured => CallToolResponse {
+                content,
+                is_error: Some(is_error),
+                meta: Some(serde_json::json!({ "structuredContent": body })),
+            },
+            ResponseMode::LegacyText => CallToolResponse { content, is_error: None, meta: None },
+        }
+    }
+}
+
+/// How tool results are returned to the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ResponseMode {
+    /// JSON text block plus structured content, with `is_error` set on failures
+    #[default]
+    Structured,
+    /// JSON text block only and `is_error` left unset (pre-structured behavior)
+    LegacyText,
 }
@@ -0,0 +1,161 @@
+//! Comment-driven revisions for `apply_comment_suggestions`: review comments that ask for
+//! a wording change ("change X to Y", "replace X with Y", "X -> Y") become tracked
+//! changes on the text they are anchored to, or a task list for an agent to work through.
+//!
+//! Edits are made on the package XML, so they only apply to opened documents that still
+//! have their comments (the op model has no place for either). A change is made when the
+//! old wording sits inside one run of the commented range; wording split across runs
+//! (e.g. partly bold) is left for a person, with the reason in the task list.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::docx_handler::{xml_escape, xml_unescape};
+
+static CHANGE_TO: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)^\s*(?:please\s+)?(?:change|replace)\s+["“'‘]?(.+?)["”'’]?\s+(?:to|with|by)\s+["“'‘]?(.+?)["”'’]?\s*[.!]?\s*$"#).unwrap()
+});
+static ARROW: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)^\s*["“'‘]?(.+?)["”'’]?\s*(?:->|=>|→)\s*["“'‘]?(.+?)["”'’]?\s*$"#).unwrap()
+});
+static RUN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<w:r(?:\s[^>]*)?>.*?</w:r>").unwrap());
+static TEXT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<w:t(?:\s[^>]*)?>(.*?)</w:t>").unwrap());
+static RUN_PROPERTIES: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<w:rPr>.*?</w:rPr>").unwrap());
+static REVISION_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r#"w:id="(\d+)""#).unwrap());
+
+/// The (old, new) wording a comment asks for, if it is a change request
+pub fn parse_suggestion(comment: &str) -> Option<(String, String)> {
+    let caps = CHANGE_TO.captures(comment).or_else(|| ARROW.captures(comment))?;
+    let (find, replace) = (caps[1].trim(), caps[2].trim());
+    (!find.is_empty() && find != replace).then(|| (find.to_string(), replace.to_string()))
+}
+
+/// A review comment from `word/comments.xml`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comment {
+    pub id: String,
+    pub author: Option<String>,
+    /// Paragraphs of the comment joined with newlines
+    pub text: String,
+}
+
+pub fn read_comments(xml: &str) -> Result<Vec<Comment>> {
+    let doc = roxmltree::Document::parse(xml).context("Invalid comments part: not well-formed XML")?;
+    let attr = |node: roxmltree::Node, name: &str| {
+        node.attributes().find(|a| a.name() == name).map(|a| a.value().to_string())
+    };
+    Ok(doc.descendants()
+        .filter(|n| n.tag_name().name() == "comment")
+        .filter_map(|comment| {
+            let paragraphs: Vec<String> = comment.children()
+                .filter(|n| n.tag_name().name() == "p")
+                .map(|p| p.descendants().filter(|n| n.tag_name().name() == "t").filter_map(|t| t.text()).collect())
+                .collect();
+            Some(Comment { id: attr(comment, "id")?, author: attr(comment, "author"), text: paragraphs.join("\n") })
+        })
+        .collect())
+}
+
+/// Byte range of `document.xml` a comment is anchored to: between its range markers, or
+/// else the paragraph holding its reference
+fn anchor_range(xml: &str, comment_id: &str) -> Option<(usize, usize)> {
+    let marker = |tag: &str| {
+        Regex::new(&format!(r#"<w:{}\b[^>]*\bw:id="{}"[^>]*/>"#, tag, regex::escape(comment_id))).ok()?.find(xml)
+    };
+    if let (Some(start), Some(end)) = (marker("commentRangeStart"), marker("commentRangeEnd")) {
+        if start.end() <= end.start() {
+            return Some((start.end(), end.start()));
+        }
+    }
+    let reference = marker("commentReference")?;
+    let start = xml[..reference.start()].rfind("<w:p>").into_iter()
+        .chain(xml[..reference.start()].rfind("<w:p "))
+        .max()?;
+    let end = reference.end() + xml[reference.end()..].find("</w:p>")?;
+    Some((start, end))
+}
+
+/// Plain text of the commented range
+pub fn anchor_text(xml: &str, comment_id: &str) -> Option<String> {
+    let (start, end) = anchor_range(xml, comment_id)?;
+    Some(TEXT.captures_iter(&xml[start..end]).map(|c| xml_unescape(&c[1])).collect())
+}
+
+/// Author and date stamped on a tracked change
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub author: String,
+    /// ISO 8601; left off when None (e.g. for reproducible output)
+    pub date: Option<String>,
+}
+
+/// What became of one suggestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Made as a tracked change
+    Applied,
+    /// Reported only (task list mode)
+    Pending,
+    /// A tracked change already replaces the old wording
+    AlreadyApplied,
+    /// The old wording is not in the commented text
+    NotFound,
+    /// The old wording crosses run boundaries (mixed formatting)
+    SpansRuns,
+    /// The comment's anchor is missing from the document
+    NoAnchor,
+}
+
+/// Replace the first `find` inside the comment's range with a tracked deletion and
+/// insertion. Returns the new document XML, or why it was not changed.
+pub fn track_replacement(xml: &str, comment_id: &str, find: &str, replace: &str, revision: &Revision) -> std::result::Result<String, Status> {
+    let (start, end) = anchor_range(xml, comment_id).ok_or(Status::NoAnchor)?;
+    let region = &xml[start..end];
+    let deleted: String = Regex::new(r"(?s)<w:delText(?:\s[^>]*)?>(.*?)</w:delText>").unwrap()
+        .captures_iter(region).map(|c| xml_unescape(&c[1])).collect();
+    if deleted.contains(find) {
+        return Err(Status::AlreadyApplied);
+    }
+    let mut next_id = REVISION_ID.captures_iter(xml).filter_map(|c| c[1].parse::<u64>().ok()).max().unwrap_or(0) + 1;
+    let mut stamp = |kind: &str| {
+        let id = next_id;
+        next_id += 1;
+        let date = revision.date.as_deref().map(|d| format!(r#" w:date="{}""#, d)).unwrap_or_default();
+        format!(r#"<w:{} w:id="{}" w:author="{}"{}>"#, kind, id, xml_escape(&revision.author), date)
+    };
+
+    for run in RUN.find_iter(region) {
+        let Some(text) = TEXT.captures(run.as_str()) else { continue };
+        let content = xml_unescape(&text[1]);
+        let Some(at) = content.find(find) else { continue };
+        // The run's parts around its text, so tabs or breaks next to it are kept
+        let (run_xml, whole) = (run.as_str(), text.get(0).unwrap());
+        let open = &run_xml[..run_xml.find('>').unwrap() + 1];
+        let properties = RUN_PROPERTIES.find(&run_xml[open.len()..])
+            .filter(|m| m.start() == 0)
+            .map(|m| m.as_str())
+            .unwrap_or("");
+        let before_text = &run_xml[open.len() + properties.len()..whole.start()];
+        let after_text = &run_xml[whole.end()..run_xml.len() - "</w:r>".len()];
+        let (before, after) = (&content[..at], &content[at + find.len()..]);
+
+        let mut out = String::new();
+        if !before.is_empty() || !before_text.trim().is_empty() {
+            out.push_str(&format!(r#"{}{}{}<w:t xml:space="preserve">{}</w:t></w:r>"#, open, properties, before_text, xml_escape(before)));
+        }
+        out.push_str(&format!(r#"{}<w:r>{}<w:delText xml:space="preserve">{}</w:delText></w:r></w:del>"#, stamp("del"), properties, xml_escape(find)));
+        if !replace.is_empty() {
+            out.push_str(&format!(r#"{}<w:r>{}<w:t xml:space="preserve">{}</w:t></w:r></w:ins>"#, stamp("ins"), properties, xml_escape(replace)));
+        }
+        if !after.is_empty() || !after_text.trim().is_empty() {
+            out.push_str(&format!(r#"<w:r>{}<w:t xml:space="preserve">{}</w:t>{}</w:r>"#, properties, xml_escape(after), after_text));
+        }
+        let (run_start, run_end) = (start + run.start(), start + run.end());
+        return Ok(format!("{}{}{}", &xml[..run_start], out, &xml[run_end..]));
+    }
+    let text: String = TEXT.captures_iter(region).map(|c| xml_unescape(&c[1])).collect();
+    Err(if text.contains(find) { Status::SpansRuns } else { Status::NotFound })
+}
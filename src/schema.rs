@@ -0,0 +1,118 @@
+//! Minimal JSON-schema checks for tool arguments, run before dispatch so missing or
+//! mistyped fields produce precise errors instead of silently defaulting.
+//!
+//! Supports the subset the tool definitions use: `type` (string or list), `required`,
+//! `properties`, `additionalProperties: false`, `enum`, `items`, `minimum`/`maximum`,
+//! `minLength` and `minItems`.
+
+use serde_json::Value;
+
+/// Validate `value` against `schema`, returning the first problem found
+pub fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    validate_at(schema, value, "")
+}
+
+fn field_name(path: &str) -> String {
+    if path.is_empty() { "arguments".to_string() } else { format!("field '{}'", path) }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            return Err(format!("{} must be {}, got {}", field_name(path), allowed.join(" or "), type_name(value)));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            let list: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+            return Err(format!("{} must be one of [{}], got {}", field_name(path), list.join(", "), value));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if map.get(key).map(|v| v.is_null()).unwrap_or(true) {
+                        return Err(format!("missing required field '{}'", join(path, key)));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for (key, child) in map {
+                match properties.and_then(|p| p.get(key)) {
+                    // Explicit nulls are treated as "not provided"
+                    Some(child_schema) if !child.is_null() => validate_at(child_schema, child, &join(path, key))?,
+                    Some(_) => {}
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        return Err(format!("unknown field '{}'", join(path, key)));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+                if (items.len() as u64) < min {
+                    return Err(format!("{} must have at least {} items", field_name(path), min));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{}[{}]", path, i))?;
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or(0.0);
+            if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+                if n < min {
+                    return Err(format!("{} must be >= {}", field_name(path), min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+                if n > max {
+                    return Err(format!("{} must be <= {}", field_name(path), max));
+                }
+            }
+        }
+        Value::String(s) => {
+            if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+                if (s.chars().count() as u64) < min {
+                    return Err(format!("{} must be at least {} characters", field_name(path), min));
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
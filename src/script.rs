@@ -0,0 +1,164 @@
+//! Batch mode for `docx-mcp run`: execute a JSON script of tool calls against a local
+//! provider, without an MCP client. Useful for generating documents in CI and for
+//! replaying the calls from a bug report.
+//!
+//! ```json
+//! {
+//!   "steps": [
+//!     {"id": "doc", "tool": "create_document"},
+//!     {"tool": "add_heading", "arguments": {"document_id": "${doc.document_id}", "text": "Report", "level": 1}},
+//!     {"tool": "save_document", "arguments": {"document_id": "${doc.document_id}", "output_path": "report.docx"}}
+//!   ]
+//! }
+//! ```
+//!
+//! A bare array of steps works too. `${id.field}` in a string argument is replaced by a
+//! field of an earlier step's result (dotted paths such as `${meta.metadata.sha256}`
+//! reach into objects); a string that is nothing but a reference takes the field's JSON
+//! value, so numbers and arrays keep their type.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::docx_tools::DocxToolsProvider;
+
+static REFERENCE: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r"\$\{([A-Za-z0-9_-]+)\.([A-Za-z0-9_.]+)\}").unwrap());
+
+fn empty_object() -> Value {
+    json!({})
+}
+
+/// One tool call of a script
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Name later steps use to reference this step's result
+    #[serde(default)]
+    pub id: Option<String>,
+    pub tool: String,
+    #[serde(default = "empty_object")]
+    pub arguments: Value,
+    /// Carry on with the next step when this one fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptFile {
+    Steps(Vec<Step>),
+    Script { steps: Vec<Step> },
+}
+
+/// Read a script: `{"steps": [...]}` or a bare array of steps
+pub fn parse(text: &str) -> Result<Vec<Step>> {
+    let script: ScriptFile = serde_json::from_str(text)
+        .context("Invalid script: expected {\"steps\": [...]} or an array of {\"tool\", \"arguments\"} steps")?;
+    let steps = match script {
+        ScriptFile::Steps(steps) | ScriptFile::Script { steps } => steps,
+    };
+    let mut seen = std::collections::HashSet::new();
+    for step in &steps {
+        if let Some(id) = &step.id {
+            if !seen.insert(id.as_str()) {
+                anyhow::bail!("Invalid script: step id '{}' is used twice", id);
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// What one step returned
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    /// 1-based position in the script
+    pub step: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub tool: String,
+    pub success: bool,
+    /// The tool's JSON response
+    pub result: Value,
+}
+
+/// Run the steps in order, passing each result to `on_result` as it arrives. Stops
+/// after the first failed step unless it has `continue_on_error` (or `keep_going` is
+/// set); check `success` on the returned results.
+pub async fn run(
+    provider: &DocxToolsProvider,
+    steps: &[Step],
+    keep_going: bool,
+    mut on_result: impl FnMut(&StepResult),
+) -> Vec<StepResult> {
+    let mut results: Vec<StepResult> = Vec::with_capacity(steps.len());
+    let mut by_id: HashMap<String, Value> = HashMap::new();
+    for (i, step) in steps.iter().enumerate() {
+        let result = match substitute(&step.arguments, &by_id) {
+            Ok(arguments) => call(provider, &step.tool, arguments).await,
+            Err(e) => json!({"success": false, "error": format!("{:#}", e)}),
+        };
+        let success = result.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
+        if let Some(id) = &step.id {
+            by_id.insert(id.clone(), result.clone());
+        }
+        let outcome = StepResult { step: i + 1, id: step.id.clone(), tool: step.tool.clone(), success, result };
+        on_result(&outcome);
+        results.push(outcome);
+        if !success && !step.continue_on_error && !keep_going {
+            break;
+        }
+    }
+    results
+}
+
+/// Call a tool and read back the JSON body of its response
+pub(crate) async fn call(provider: &DocxToolsProvider, tool: &str, arguments: Value) -> Value {
+    let response = provider.call_tool(tool, arguments).await;
+    match response.content.first() {
+        Some(mcp_core::types::ToolResponseContent::Text(t)) => serde_json::from_str(&t.text)
+            .unwrap_or_else(|_| json!({"success": false, "error": t.text.clone()})),
+        _ => json!({"success": false, "error": "non-text response"}),
+    }
+}
+
+/// Replace `${id.field}` references in every string of `value`
+pub(crate) fn substitute(value: &Value, results: &HashMap<String, Value>) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => {
+            if let Some(caps) = REFERENCE.captures(text).filter(|c| c[0].len() == text.len()) {
+                lookup(&caps[1], &caps[2], results)?.clone()
+            } else {
+                let mut out = String::with_capacity(text.len());
+                let mut last = 0;
+                for caps in REFERENCE.captures_iter(text) {
+                    let whole = caps.get(0).unwrap();
+                    out.push_str(&text[last..whole.start()]);
+                    match lookup(&caps[1], &caps[2], results)? {
+                        Value::String(s) => out.push_str(s),
+                        other => out.push_str(&other.to_string()),
+                    }
+                    last = whole.end();
+                }
+                out.push_str(&text[last..]);
+                Value::String(out)
+            }
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, results)).collect::<Result<_>>()?),
+        Value::Object(map) => Value::Object(
+            map.iter().map(|(k, v)| Ok((k.clone(), substitute(v, results)?))).collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+fn lookup<'a>(id: &str, path: &str, results: &'a HashMap<String, Value>) -> Result<&'a Value> {
+    let result = results.get(id)
+        .ok_or_else(|| anyhow::anyhow!("${{{}.{}}}: no earlier step has id '{}'", id, path, id))?;
+    path.split('.')
+        .try_fold(result, |value, key| value.get(key))
+        .ok_or_else(|| anyhow::anyhow!("${{{}.{}}}: step '{}' returned no field '{}'", id, path, id, path))
+}
@@ -26,10 +26,18 @@ pub struct Args {
     #[arg(long, env = "DOCX_MCP_SANDBOX")]
     pub sandbox: bool,
 
+    /// Comma-separated directories that file paths in tool arguments must stay inside
+    #[arg(long = "allowed-path", env = "DOCX_MCP_ALLOWED_PATHS", value_delimiter = ',')]
+    pub allowed_paths: Vec<std::path::PathBuf>,
+
     /// Disable external tools (LibreOffice, etc.)
     #[arg(long, env = "DOCX_MCP_NO_EXTERNAL_TOOLS")]
     pub no_external_tools: bool,
 
+    /// Try LibreOffice/unoconv before the built-in renderer for PDF conversion
+    #[arg(long, env = "DOCX_MCP_PREFER_EXTERNAL_TOOLS")]
+    pub prefer_external_tools: bool,
+
     /// Disable network operations
     #[arg(long, env = "DOCX_MCP_NO_NETWORK")]
     pub no_network: bool,
@@ -42,11 +50,136 @@ pub struct Args {
     #[arg(long, env = "DOCX_MCP_MAX_DOCS")]
     pub max_docs: Option<usize>,
 
+    /// Directory for working copies and spill files (default: the system temp directory)
+    #[arg(long, env = "DOCX_MCP_TEMP")]
+    pub temp_dir: Option<std::path::PathBuf>,
+
+    /// Deflate level for written packages: 0 stores entries uncompressed, 1-9 compress
+    #[arg(long, env = "DOCX_MCP_COMPRESSION_LEVEL", default_value_t = 6)]
+    pub compression_level: u32,
+
+    /// Give every package entry a fixed timestamp so identical content produces
+    /// byte-identical files
+    #[arg(long, env = "DOCX_MCP_DETERMINISTIC")]
+    pub deterministic: bool,
+
+    /// Bytes of edit history kept in memory per document; past it, image and font data
+    /// and then the rest are moved to disk after each edit and read back when needed
+    #[arg(long, env = "DOCX_MCP_MAX_DOC_MEMORY")]
+    pub max_document_memory: Option<usize>,
+
+    /// Return tool results as a JSON text block only, without structured content or is_error
+    #[arg(long, env = "DOCX_MCP_LEGACY_RESPONSES")]
+    pub legacy_text_responses: bool,
+
+    /// Comma-separated webhook URLs that receive document events as JSON POSTs
+    #[arg(long = "webhook-url", env = "DOCX_MCP_WEBHOOK_URLS", value_delimiter = ',')]
+    pub webhook_urls: Vec<String>,
+
+    /// Comma-separated events to send (document_saved, conversion_completed, storage_threshold_exceeded); all by default
+    #[arg(long, env = "DOCX_MCP_WEBHOOK_EVENTS", value_delimiter = ',')]
+    pub webhook_events: Option<Vec<String>>,
+
+    /// Emit storage_threshold_exceeded when temp storage grows past this many megabytes
+    #[arg(long, env = "DOCX_MCP_STORAGE_THRESHOLD_MB")]
+    pub storage_threshold_mb: Option<u64>,
+
+    /// Comma-separated SCRIPT=PATH fonts used for that script in PDF output, ahead of
+    /// the Noto fonts in the font directory (e.g. han=/fonts/SourceHanSans.otf)
+    #[arg(long = "fallback-font", env = "DOCX_MCP_FALLBACK_FONTS", value_delimiter = ',')]
+    pub fallback_fonts: Vec<String>,
+
+    /// Directory of .docx and .md clause fragments for list_clauses / insert_clause
+    #[arg(long, env = "DOCX_MCP_CLAUSE_DIR")]
+    pub clause_dir: Option<std::path::PathBuf>,
+
+    /// TOML file of letterhead profiles for list_letterheads / apply_letterhead
+    #[arg(long, env = "DOCX_MCP_LETTERHEADS")]
+    pub letterheads: Option<std::path::PathBuf>,
+
+    /// Serve MCP over HTTP on this address (e.g. 127.0.0.1:8080) instead of stdio,
+    /// with Prometheus metrics on /metrics
+    #[arg(long, env = "DOCX_MCP_HTTP")]
+    pub http: Option<String>,
+
+    /// Bearer token that lets HTTP requests act as an operator: see every session's
+    /// documents and use list_all_documents / transfer_document
+    #[arg(long, env = "DOCX_MCP_ADMIN_KEY", hide_env_values = true)]
+    pub admin_key: Option<String>,
+
+    /// End HTTP sessions idle for this many seconds, closing their documents (0 keeps
+    /// them until DELETE /mcp)
+    #[arg(long, env = "DOCX_MCP_SESSION_IDLE_TIMEOUT", default_value_t = 1800)]
+    pub session_idle_timeout: u64,
+
+    /// Most HTTP sessions one token may have open at once (0 for no limit)
+    #[arg(long, env = "DOCX_MCP_MAX_SESSIONS_PER_TOKEN", default_value_t = 16)]
+    pub max_sessions_per_token: usize,
+
+    /// Serve /metrics without a bearer token; by default it needs the admin key (or an
+    /// admin token) once authentication is configured
+    #[arg(long, env = "DOCX_MCP_METRICS_PUBLIC")]
+    pub metrics_public: bool,
+
+    /// Bearer tokens for HTTP clients; only settable in the config file (`[[server.tokens]]`)
+    #[arg(skip)]
+    pub tokens: Vec<crate::config::ApiToken>,
+
+    /// TOML file with defaults for these settings; flags and environment variables
+    /// take precedence over it
+    #[arg(long, env = "DOCX_MCP_CONFIG")]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Create, edit, convert and delete a scratch document, then exit (nonzero on failure)
+    #[arg(long)]
+    pub self_test: bool,
+
     /// Optional top-level subcommand (e.g., fonts download)
     #[command(subcommand)]
     pub command: Option<CliCommand>,
 }
 
+impl Args {
+    /// Parse the command line and environment, then fill what neither gave from the
+    /// `--config` file (see `config`). Exits with usage on bad arguments, like `parse`.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::from_matches(<Self as clap::CommandFactory>::command().get_matches())
+    }
+
+    /// `load` for an explicit argument list
+    pub fn load_from<I, T>(argv: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        Self::from_matches(<Self as clap::CommandFactory>::command().try_get_matches_from(argv)?)
+    }
+
+    fn from_matches(matches: clap::ArgMatches) -> anyhow::Result<Self> {
+        let mut args = <Self as clap::FromArgMatches>::from_arg_matches(&matches)?;
+        if let Some(path) = args.config.clone() {
+            crate::config::ConfigFile::load(&path)?.apply(&mut args, &matches);
+            info!("Loaded config file {:?}", path);
+        }
+        Ok(args)
+    }
+}
+
+/// Cargo features this binary was compiled with (reported by server_info)
+pub fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "runtime-server") { features.push("runtime-server"); }
+    if cfg!(feature = "embedded-fonts") { features.push("embedded-fonts"); }
+    if cfg!(feature = "pure-rust-pdf") { features.push("pure-rust-pdf"); }
+    if cfg!(feature = "external-tools") { features.push("external-tools"); }
+    if cfg!(feature = "advanced-docx") { features.push("advanced-docx"); }
+    if cfg!(feature = "spellcheck") { features.push("spellcheck"); }
+    if cfg!(feature = "hyphenation") { features.push("hyphenation"); }
+    if cfg!(feature = "otel") { features.push("otel"); }
+    if cfg!(feature = "hi-fidelity") { features.push("hi-fidelity"); }
+    features
+}
+
 /// Security configuration for the MCP server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
@@ -73,6 +206,10 @@ pub struct SecurityConfig {
     
     /// Sandbox mode - restricts file operations to temp directory only
     pub sandbox_mode: bool,
+
+    /// Directories file paths in tool arguments must stay inside; empty allows any path
+    #[serde(default)]
+    pub allowed_paths: Vec<std::path::PathBuf>,
 }
 
 /// Top-level CLI subcommands
@@ -83,15 +220,143 @@ pub enum CliCommand {
         #[command(subcommand)]
         action: FontsAction,
     },
+    /// Register this server in an MCP client's config
+    Install {
+        /// Client to configure
+        #[arg(long, value_enum)]
+        client: crate::client_setup::ClientKind,
+        /// Server name under mcpServers
+        #[arg(long, default_value = "docx-mcp")]
+        name: String,
+        /// Config file to edit instead of the client's default location
+        #[arg(long)]
+        config: Option<std::path::PathBuf>,
+        /// Environment variables for the server (KEY=VALUE, repeatable)
+        #[arg(long = "env")]
+        env: Vec<String>,
+        /// Print the resulting config without writing it
+        #[arg(long)]
+        dry_run: bool,
+        /// Extra server arguments, after `--` (e.g. -- --readonly)
+        #[arg(last = true)]
+        server_args: Vec<String>,
+    },
+    /// Run the stdio handshake locally and report why a client may fail to connect
+    Doctor {
+        /// Server binary to test (defaults to this executable)
+        #[arg(long)]
+        command: Option<std::path::PathBuf>,
+        /// Server name to look up in client configs
+        #[arg(long, default_value = "docx-mcp")]
+        name: String,
+        /// Seconds to wait for handshake responses
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Run a JSON script of tool calls without an MCP client, printing one JSON result
+    /// per line; exits nonzero when a step fails. Server flags go before `run`.
+    Run {
+        /// Script file, or - for stdin
+        script: std::path::PathBuf,
+        /// Run the remaining steps after a failure instead of stopping
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    /// Call tools interactively, with tab completion over tool names
+    Repl,
+    /// Convert a DOCX file to PDF, Markdown, HTML or page images and exit
+    Convert {
+        /// DOCX file to convert
+        input: std::path::PathBuf,
+        /// Output file, or directory for png/jpg page images
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+        /// pdf, md, html, png or jpg (default: from the output extension)
+        #[arg(long, value_parser = ["pdf", "md", "html", "png", "jpg"])]
+        to: Option<String>,
+        /// Resolution of page images
+        #[arg(long, default_value_t = 150)]
+        dpi: u32,
+    },
+    /// Print or save a DOCX file's text, tables (as JSON) or embedded images and exit
+    Extract {
+        /// What to extract
+        #[arg(value_parser = ["text", "tables", "images"])]
+        what: String,
+        /// DOCX file to read
+        input: std::path::PathBuf,
+        /// File to write text or tables to (default: stdout); directory for images
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Time document builds on this machine and print a JSON report (runs a standard
+    /// suite when no counts are given)
+    #[command(hide = true)]
+    Bench {
+        /// Body paragraphs
+        #[arg(long, default_value_t = 0)]
+        paragraphs: usize,
+        /// Tables of table_rows x table_cols cells
+        #[arg(long, default_value_t = 0)]
+        tables: usize,
+        #[arg(long, default_value_t = 10)]
+        table_rows: usize,
+        #[arg(long, default_value_t = 4)]
+        table_cols: usize,
+        /// Square PNG images of image_size pixels
+        #[arg(long, default_value_t = 0)]
+        images: usize,
+        #[arg(long, default_value_t = 256)]
+        image_size: u32,
+        /// Times to repeat each scenario
+        #[arg(long, default_value_t = 3)]
+        iterations: usize,
+    },
+    /// Spell-check dictionary utilities
+    #[cfg(feature = "spellcheck")]
+    Dictionaries {
+        #[command(subcommand)]
+        action: DictionariesAction,
+    },
+    /// Hyphenation pattern utilities (same download/verify actions as dictionaries)
+    #[cfg(feature = "hyphenation")]
+    Hyphenation {
+        #[command(subcommand)]
+        action: DictionariesAction,
+    },
 }
 
 /// Font-related actions
 #[derive(Subcommand, Debug, Clone, Serialize, Deserialize)]
 pub enum FontsAction {
-    /// Download open-source fonts into assets/fonts
+    /// Download open-source fonts into assets/fonts (or DOCX_MCP_FONT_DIR)
     Download,
     /// Verify checksums of fonts in assets/fonts
     Verify,
+    /// List installed fonts with their family names
+    List,
+    /// Install a TrueType/OpenType font file
+    Add {
+        /// Path to the .ttf/.otf file
+        path: std::path::PathBuf,
+    },
+}
+
+/// Dictionary-related actions (spell-check dictionaries and hyphenation patterns)
+#[cfg(any(feature = "spellcheck", feature = "hyphenation"))]
+#[derive(Subcommand, Debug, Clone, Serialize, Deserialize)]
+pub enum DictionariesAction {
+    /// Download dictionaries into assets/dictionaries
+    Download {
+        /// Languages to fetch (e.g. en_US,de_DE); all supported when omitted
+        #[arg(long, value_delimiter = ',')]
+        lang: Vec<String>,
+    },
+    /// Verify downloaded dictionaries load correctly
+    Verify {
+        #[arg(long, value_delimiter = ',')]
+        lang: Vec<String>,
+    },
 }
 
 impl Default for SecurityConfig {
@@ -105,6 +370,7 @@ impl Default for SecurityConfig {
             allow_external_tools: true,
             allow_network: true,
             sandbox_mode: false,
+            allowed_paths: Vec::new(),
         }
     }
 }
@@ -153,6 +419,11 @@ impl SecurityConfig {
             config.max_document_size = size;
             info!("Max document size set to {} bytes", size);
         }
+
+        if !args.allowed_paths.is_empty() {
+            info!("File paths restricted to {} directories", args.allowed_paths.len());
+            config.allowed_paths = args.allowed_paths;
+        }
         
         if let Some(max) = args.max_docs {
             config.max_open_documents = max;
@@ -233,10 +504,24 @@ impl SecurityConfig {
     
     /// Check if a command is allowed based on security configuration
     pub fn is_command_allowed(&self, command: &str) -> bool {
-        // First check if it's a readonly command
-        let readonly_commands = Self::get_readonly_commands();
-        let is_readonly_command = readonly_commands.contains(command);
-        
+        self.allows(command, Self::get_readonly_commands().contains(command))
+    }
+
+    /// Like `is_command_allowed`, but for one call: a preview (`dry_run`) of a writing
+    /// tool passes readonly mode the way a read-only command does
+    pub fn is_call_allowed(&self, command: &str, arguments: &serde_json::Value) -> bool {
+        self.allows(command, Self::is_readonly_call(command, arguments))
+    }
+
+    /// Whether a call leaves documents unchanged: a read-only command, or
+    /// `find_and_replace_advanced`/`redact_text` with `dry_run`
+    pub fn is_readonly_call(command: &str, arguments: &serde_json::Value) -> bool {
+        Self::get_readonly_commands().contains(command)
+            || (matches!(command, "find_and_replace_advanced" | "redact_text")
+                && arguments.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false))
+    }
+
+    fn allows(&self, command: &str, is_readonly_command: bool) -> bool {
         // In readonly mode, only allow readonly commands
         if self.readonly_mode && !is_readonly_command {
             debug!("Command '{}' blocked: readonly mode", command);
@@ -278,17 +563,31 @@ impl SecurityConfig {
         // Document viewing commands
         commands.insert("open_document");
         commands.insert("extract_text");
+        commands.insert("extract_formatted_text");
         commands.insert("get_metadata");
+        commands.insert("verify_document");
         commands.insert("list_documents");
+        commands.insert("list_all_documents");
+        commands.insert("list_available_fonts");
+        commands.insert("get_lock_status");
+        commands.insert("list_workspaces");
+        commands.insert("list_workspace_documents");
         commands.insert("get_document_info");
         commands.insert("read_paragraph");
         commands.insert("read_table");
         commands.insert("read_section");
+        commands.insert("get_outline");
+        commands.insert("list_tagged_content");
+        commands.insert("list_clauses");
+        commands.insert("list_letterheads");
+        commands.insert("get_section_text");
         commands.insert("search_text");
+        commands.insert("extract_patterned_items");
         commands.insert("get_document_structure");
         commands.insert("get_styles");
         commands.insert("get_headers_footers");
         commands.insert("get_page_count");
+        commands.insert("get_pagination");
         commands.insert("get_word_count");
         commands.insert("get_table_of_contents");
         commands.insert("list_bookmarks");
@@ -300,17 +599,25 @@ impl SecurityConfig {
         
         // Analysis commands
         commands.insert("analyze_formatting");
+        commands.insert("lint_formatting");
         commands.insert("check_spelling");
-        commands.insert("check_grammar");
         commands.insert("get_statistics");
+        commands.insert("detect_languages");
+        commands.insert("analyze_content");
         commands.insert("compare_documents");
         
         // Export commands (readonly as they don't modify the original)
         commands.insert("export_to_json");
         commands.insert("export_to_markdown");
         commands.insert("export_to_html");
+        commands.insert("export_xliff");
+        commands.insert("export_ops");
+        commands.insert("summarize_changes");
         commands.insert("create_preview");
+        commands.insert("generate_thumbnail");
         commands.insert("get_security_info");
+        commands.insert("server_info");
+        commands.insert("ping");
         commands.insert("get_storage_info");
         
         commands
@@ -324,32 +631,84 @@ impl SecurityConfig {
         commands.insert("create_document");
         commands.insert("save_document");
         commands.insert("close_document");
+        commands.insert("rename_document");
+        commands.insert("clone_document");
+        commands.insert("replay_ops");
+        commands.insert("snapshot_document");
+        commands.insert("transfer_document");
+        commands.insert("create_viewer");
+        commands.insert("revoke_viewer");
+        commands.insert("reload_config");
+        commands.insert("copy_elements");
+        commands.insert("assemble_document");
+        commands.insert("insert_clause");
+        commands.insert("apply_letterhead");
+        commands.insert("rebrand");
+        commands.insert("generate_invoice");
+        commands.insert("embed_font");
+        commands.insert("acquire_lock");
+        commands.insert("release_lock");
+        commands.insert("create_workspace");
+        commands.insert("assign_document_to_workspace");
         
         // Content addition
         commands.insert("add_paragraph");
         commands.insert("add_heading");
+        commands.insert("create_outline");
         commands.insert("add_table");
+        commands.insert("add_table_from_xlsx");
+        commands.insert("populate_table");
+        commands.insert("table_to_list");
+        commands.insert("list_to_table");
         commands.insert("add_list");
         commands.insert("add_page_break");
+        commands.insert("group_keep_together");
+        commands.insert("fix_pagination");
+        commands.insert("add_horizontal_rule");
+        commands.insert("add_signature_block");
         commands.insert("add_section_break");
+        commands.insert("set_page_setup");
         commands.insert("add_image");
         commands.insert("add_chart");
         commands.insert("add_shape");
         commands.insert("add_hyperlink");
         commands.insert("add_bookmark");
+        commands.insert("enable_heading_numbering");
+        commands.insert("renumber_headings");
+        commands.insert("normalize_headings");
+        commands.insert("move_section");
+        commands.insert("delete_section");
+        commands.insert("set_section_content");
+        commands.insert("delete_range");
+        commands.insert("insert_at");
+        commands.insert("insert_document");
+        commands.insert("add_table_of_figures");
         commands.insert("add_footnote");
         commands.insert("add_endnote");
         commands.insert("add_comment");
         commands.insert("add_watermark");
+        commands.insert("remove_watermark");
         
         // Content modification
         commands.insert("edit_paragraph");
         commands.insert("delete_paragraph");
         commands.insert("find_and_replace");
+        commands.insert("redact_text");
+        commands.insert("apply_comment_suggestions");
+        commands.insert("transform_text");
+        commands.insert("import_xliff");
+        commands.insert("render_template");
+        commands.insert("tag_content");
+        commands.insert("set_proofing_language");
+        commands.insert("strip_tagged_content");
+        commands.insert("keep_only_tagged");
+        commands.insert("apply_correction");
+        commands.insert("highlight_matches");
         commands.insert("update_table");
         commands.insert("update_style");
         commands.insert("set_header");
         commands.insert("set_footer");
+        commands.insert("insert_field");
         commands.insert("set_margins");
         commands.insert("set_page_size");
         commands.insert("apply_template");
@@ -367,14 +726,20 @@ impl SecurityConfig {
         commands.insert("accept_changes");
         commands.insert("reject_changes");
         
+        // Diagnostics that write scratch files
+        commands.insert("run_benchmark");
+
         commands
     }
     
-    /// Check if a file path is allowed based on sandbox configuration
+    /// Check if a file path is allowed based on sandbox configuration and allowed paths
     pub fn is_path_allowed(&self, path: &std::path::Path) -> bool {
-        if !self.sandbox_mode {
+        if !self.allowed_paths.is_empty() && self.is_under_allowed_path(path) {
             return true;
         }
+        if !self.sandbox_mode {
+            return self.allowed_paths.is_empty();
+        }
         
         // In sandbox mode, only allow operations in temp directory
         let temp_dir = std::env::temp_dir();
@@ -415,6 +780,18 @@ impl SecurityConfig {
         false
     }
     
+    /// Whether `path`, once resolved, lies inside one of `allowed_paths`. The path need not
+    /// exist; its nearest existing ancestor is canonicalized so symlinks and `..` cannot
+    /// step outside.
+    fn is_under_allowed_path(&self, path: &std::path::Path) -> bool {
+        let Some(resolved) = resolve_through_existing_ancestor(path) else {
+            return false;
+        };
+        self.allowed_paths.iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|dir| resolved.starts_with(dir))
+    }
+    
     /// Get a summary of current security settings
     pub fn get_summary(&self) -> String {
         let mut summary: Vec<String> = Vec::new();
@@ -442,6 +819,10 @@ impl SecurityConfig {
         if !self.allow_network {
             summary.push("🌐 No network access".to_string());
         }
+
+        if !self.allowed_paths.is_empty() {
+            summary.push(format!("📁 Paths: {} allowed directories", self.allowed_paths.len()));
+        }
         
         if summary.is_empty() {
             "Standard mode (all features enabled)".to_string()
@@ -451,7 +832,25 @@ impl SecurityConfig {
     }
 }
 
+/// `path` made absolute by canonicalizing its nearest existing ancestor and appending the
+/// rest; None when a component past that ancestor is `..`
+fn resolve_through_existing_ancestor(path: &std::path::Path) -> Option<std::path::PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Some(rest.iter().rev().fold(canonical, |dir, name| dir.join(name)));
+        }
+        rest.push(existing.file_name()?.to_os_string());
+        existing = existing.parent()?;
+        if existing.as_os_str().is_empty() {
+            existing = std::path::Path::new(".");
+        }
+    }
+}
+
 /// Security middleware to check commands before execution
+#[derive(Debug)]
 pub struct SecurityMiddleware {
     config: SecurityConfig,
 }
@@ -460,21 +859,38 @@ impl SecurityMiddleware {
     pub fn new(config: SecurityConfig) -> Self {
         Self { config }
     }
+
+    pub fn config(&self) -> &SecurityConfig {
+        &self.config
+    }
     
     /// Check if a command should be allowed to execute
     pub fn check_command(&self, command: &str, arguments: &serde_json::Value) -> Result<(), SecurityError> {
         // Check if command is allowed
-        if !self.config.is_command_allowed(command) {
+        if !self.config.is_call_allowed(command, arguments) {
             return Err(SecurityError::CommandNotAllowed(command.to_string()));
         }
         
+        // Object storage URIs (s3://, gs://, az://) need network access
+        let uses_object_store = ["path", "output_path"].iter()
+            .filter_map(|k| arguments.get(*k).and_then(|v| v.as_str()))
+            .any(crate::object_store::is_object_uri);
+        if uses_object_store && !self.config.allow_network {
+            return Err(SecurityError::NetworkDisabled);
+        }
+
         // Check file paths in arguments if in sandbox mode
         if self.config.sandbox_mode {
             self.check_paths_in_arguments(arguments)?;
         }
+
+        // With allowed paths, arguments that name files must point inside them
+        if !self.config.allowed_paths.is_empty() {
+            self.check_path_arguments(arguments)?;
+        }
         
         // Check document size limits for open/create operations
-        if command == "open_document" {
+        if command == "open_document" || command == "add_table_from_xlsx" {
             if let Some(path) = arguments.get("path").and_then(|v| v.as_str()) {
                 self.check_file_size(path)?;
             }
@@ -486,12 +902,10 @@ impl SecurityMiddleware {
     fn check_paths_in_arguments(&self, arguments: &serde_json::Value) -> Result<(), SecurityError> {
         // Recursively check all string values that look like paths
         match arguments {
-            serde_json::Value::String(s) => {
-                if s.contains('/') || s.contains('\\') {
-                    let path = std::path::Path::new(s);
-                    if !self.config.is_path_allowed(path) {
-                        return Err(SecurityError::PathNotAllowed(s.to_string()));
-                    }
+            serde_json::Value::String(s) if (s.contains('/') || s.contains('\\')) && !crate::object_store::is_object_uri(s) => {
+                let path = std::path::Path::new(s);
+                if !self.config.is_path_allowed(path) {
+                    return Err(SecurityError::PathNotAllowed(s.to_string()));
                 }
             }
             serde_json::Value::Object(map) => {
@@ -509,6 +923,51 @@ impl SecurityMiddleware {
         Ok(())
     }
     
+    /// Check the values of path-valued arguments (`path`, `*_path`, `*_dir`, `*_paths`,
+    /// and `source` when it is not a document id) against the allowed paths. Unlike the
+    /// sandbox check, text that merely contains a slash is left alone.
+    fn check_path_arguments(&self, arguments: &serde_json::Value) -> Result<(), SecurityError> {
+        match arguments {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    let names_file = key == "path" || key == "paths"
+                        || key.ends_with("_path") || key.ends_with("_paths") || key.ends_with("_dir")
+                        || (key == "source" && value.as_str().is_some_and(|s| s.contains(['/', '\\', '.'])));
+                    if names_file {
+                        self.check_path_values(value)?;
+                    } else {
+                        self.check_path_arguments(value)?;
+                    }
+                }
+            }
+            serde_json::Value::Array(arr) => {
+                for value in arr {
+                    self.check_path_arguments(value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_path_values(&self, value: &serde_json::Value) -> Result<(), SecurityError> {
+        match value {
+            serde_json::Value::String(s)
+                if !s.is_empty() && !crate::object_store::is_object_uri(s)
+                    && !self.config.is_path_allowed(std::path::Path::new(s)) =>
+            {
+                return Err(SecurityError::PathNotAllowed(s.to_string()));
+            }
+            serde_json::Value::Array(arr) => {
+                for value in arr {
+                    self.check_path_values(value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     fn check_file_size(&self, path: &str) -> Result<(), SecurityError> {
         let file_path = std::path::Path::new(path);
         if let Ok(metadata) = std::fs::metadata(file_path) {
@@ -528,7 +987,7 @@ pub enum SecurityError {
     #[error("Command not allowed: {0}")]
     CommandNotAllowed(String),
     
-    #[error("Path not allowed in sandbox mode: {0}")]
+    #[error("Path not allowed: {0}")]
     PathNotAllowed(String),
     
     #[error("File too large: {size} bytes (max: {max_size} bytes)")]
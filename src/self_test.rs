@@ -0,0 +1,43 @@
+//! `--self-test`: exercise the document pipeline end to end in a scratch directory so
+//! integration problems (temp dir permissions, missing fonts, broken conversion) show up
+//! before a client connects.
+
+use crate::docx_handler::DocxHandler;
+use crate::pure_converter::PureRustConverter;
+use anyhow::{Context, Result};
+
+/// Run every step, returning a line per completed step. Stops at the first failure.
+pub fn run_self_test() -> Result<Vec<String>> {
+    let mut steps = Vec::new();
+    let scratch = tempfile::TempDir::new().context("create scratch directory")?;
+    let mut handler = DocxHandler::new_with_base_dir(scratch.path()).context("initialize handler")?;
+    steps.push(format!("temp dir {}", handler.temp_dir_path().display()));
+
+    let doc_id = handler.create_document().context("create document")?;
+    steps.push(format!("created document {}", doc_id));
+
+    handler.add_heading(&doc_id, "Self-test", 1).context("add heading")?;
+    handler.add_paragraph(&doc_id, "The quick brown fox jumps over the lazy dog.", None).context("add paragraph")?;
+    handler.add_list(&doc_id, vec!["alpha".to_string(), "beta".to_string()], false).context("add list")?;
+    let text = handler.extract_text(&doc_id).context("extract text")?;
+    if !text.contains("quick brown fox") || !text.contains("beta") {
+        anyhow::bail!("edited content missing from extracted text: {:?}", text);
+    }
+    steps.push("edited and re-read content".to_string());
+
+    let pdf_path = scratch.path().join("self-test.pdf");
+    let docx_path = handler.get_metadata(&doc_id)?.path;
+    PureRustConverter::new().convert_docx_to_pdf(&docx_path, &pdf_path).context("convert to PDF")?;
+    let pdf = std::fs::read(&pdf_path).context("read PDF output")?;
+    if !pdf.starts_with(b"%PDF") {
+        anyhow::bail!("conversion produced an invalid PDF ({} bytes)", pdf.len());
+    }
+    steps.push(format!("converted to PDF ({} bytes)", pdf.len()));
+
+    handler.close_document(&doc_id).context("close document")?;
+    if docx_path.exists() {
+        anyhow::bail!("scratch document was not deleted: {}", docx_path.display());
+    }
+    steps.push("closed and deleted document".to_string());
+    Ok(steps)
+}
@@ -0,0 +1,282 @@
+//! Dictionary-based spell checking with hunspell `.dic` word lists.
+//!
+//! Dictionaries are not bundled; fetch them with `docx-mcp dictionaries download`
+//! (stored under `assets/dictionaries`, or `DOCX_MCP_DICT_DIR` when set). Affix rules
+//! are not expanded; common English inflections are handled by suffix stripping.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+
+use crate::fonts_cli::dictionaries_dir;
+
+const DICTIONARIES_BASE_URL: &str = "https://raw.githubusercontent.com/wooorm/dictionaries/main/dictionaries";
+
+/// Supported dictionaries: (our language id, upstream directory)
+pub const DICTIONARIES: &[(&str, &str)] = &[
+    ("en_US", "en"),
+    ("en_GB", "en-GB"),
+    ("de_DE", "de"),
+    ("fr_FR", "fr"),
+    ("es_ES", "es"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Misspelling {
+    pub word: String,
+    pub element_index: usize,
+    pub kind: String,
+    pub range_id: Option<crate::docx_handler::RangeId>,
+    pub item: Option<usize>,
+    /// Character offsets of the word within the element text
+    pub start: usize,
+    pub end: usize,
+    pub suggestions: Vec<String>,
+}
+
+pub struct SpellChecker {
+    language: String,
+    words: HashSet<String>,
+}
+
+impl SpellChecker {
+    /// Load the dictionary for `language` (e.g. "en_US") from the dictionaries directory.
+    /// Only the ids in `DICTIONARIES` are accepted, so the id cannot name another file.
+    pub fn load(language: &str) -> Result<Self> {
+        if !DICTIONARIES.iter().any(|(id, _)| *id == language) {
+            let known: Vec<&str> = DICTIONARIES.iter().map(|(id, _)| *id).collect();
+            anyhow::bail!("Unknown dictionary '{}'; available: {}", language, known.join(", "));
+        }
+        let path = dictionaries_dir().join(format!("{}.dic", language));
+        if !path.exists() {
+            anyhow::bail!(
+                "Dictionary for {} not found at {}. Run `docx-mcp dictionaries download --lang {}`",
+                language, path.display(), language
+            );
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("read dictionary {}", path.display()))?;
+        Ok(Self::from_dic(language, &content))
+    }
+
+    /// Build a checker from hunspell `.dic` contents (first line is the word count)
+    pub fn from_dic(language: &str, content: &str) -> Self {
+        let words = content
+            .lines()
+            .skip_while(|l| l.trim().chars().all(|c| c.is_ascii_digit()))
+            .filter_map(|l| l.split('/').next())
+            .map(|w| w.trim().to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect();
+        Self { language: language.to_string(), words }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Add words that should be accepted (names, product terms)
+    pub fn add_words<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, words: I) {
+        for w in words {
+            self.words.insert(w.as_ref().to_lowercase());
+        }
+    }
+
+    pub fn is_correct(&self, word: &str) -> bool {
+        let lower = word.to_lowercase();
+        if self.words.contains(&lower) {
+            return true;
+        }
+        let lower = lower.trim_end_matches("'s").trim_end_matches("\u{2019}s");
+        if self.words.contains(lower) {
+            return true;
+        }
+        for (suffix, replacements) in [
+            ("ies", &["y"][..]),
+            ("es", &[""][..]),
+            ("s", &[""][..]),
+            ("ed", &["", "e"][..]),
+            ("ing", &["", "e"][..]),
+            ("ly", &[""][..]),
+            ("er", &["", "e"][..]),
+            ("est", &["", "e"][..]),
+        ] {
+            if let Some(stem) = lower.strip_suffix(suffix) {
+                if stem.len() < 2 {
+                    continue;
+                }
+                if replacements.iter().any(|r| self.words.contains(&format!("{}{}", stem, r))) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Up to `limit` dictionary words within edit distance 2, closest first
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let len = lower.chars().count();
+        let mut scored: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .filter(|w| w.chars().count().abs_diff(len) <= 2)
+            .filter_map(|w| {
+                let d = edit_distance(&lower, w);
+                (d <= 2).then_some((d, w))
+            })
+            .collect();
+        scored.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+        let capitalized = word.chars().next().map(|c| c.is_uppercase()).unwrap_or(false);
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, w)| if capitalized { crate::text_transform::capitalize_first(w) } else { w.clone() })
+            .collect()
+    }
+
+    /// Words in `text` that are not in the dictionary, as (word, char start, char end).
+    /// Skips tokens with digits, URLs/emails and short all-caps acronyms.
+    pub fn check_text(&self, text: &str) -> Vec<(String, usize, usize)> {
+        let mut out = Vec::new();
+        for (start, end, word) in tokenize(text) {
+            if word.chars().any(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            if word.chars().count() > 1 && word.chars().count() <= 5 && word.chars().all(|c| !c.is_lowercase()) {
+                continue;
+            }
+            if !self.is_correct(&word) {
+                out.push((word, start, end));
+            }
+        }
+        out
+    }
+}
+
+/// Split text into word tokens with character offsets. URLs and emails are skipped whole.
+fn tokenize(text: &str) -> Vec<(usize, usize, String)> {
+    let mut tokens = Vec::new();
+    for (offset, chunk) in split_whitespace_indices(text) {
+        if chunk.contains("://") || chunk.contains('@') || chunk.starts_with("www.") {
+            continue;
+        }
+        let mut current = String::new();
+        let mut start = 0usize;
+        for (i, c) in chunk.chars().enumerate() {
+            let is_word_char = c.is_alphanumeric() || ((c == '\'' || c == '\u{2019}') && !current.is_empty());
+            if is_word_char {
+                if current.is_empty() {
+                    start = offset + i;
+                }
+                current.push(c);
+            } else if !current.is_empty() {
+                push_token(&mut tokens, start, std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            push_token(&mut tokens, start, current);
+        }
+    }
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<(usize, usize, String)>, start: usize, word: String) {
+    let word = word.trim_end_matches(['\'', '\u{2019}']).to_string();
+    if !word.is_empty() {
+        let end = start + word.chars().count();
+        tokens.push((start, end, word));
+    }
+}
+
+/// Whitespace-separated chunks with their starting character offset
+fn split_whitespace_indices(text: &str) -> Vec<(usize, String)> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut start = 0usize;
+    for (i, c) in text.chars().enumerate() {
+        if c.is_whitespace() {
+            if !current.is_empty() {
+                chunks.push((start, std::mem::take(&mut current)));
+            }
+        } else {
+            if current.is_empty() {
+                start = i;
+            }
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push((start, current));
+    }
+    chunks
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Download `.dic`/`.aff` files for the given languages (all supported when empty)
+pub fn download_dictionaries_blocking(languages: &[String]) -> Result<()> {
+    let dir = dictionaries_dir();
+    fs::create_dir_all(&dir).context("create dictionaries dir")?;
+    for (lang, upstream) in DICTIONARIES {
+        if !languages.is_empty() && !languages.iter().any(|l| l == lang) {
+            continue;
+        }
+        for ext in ["dic", "aff"] {
+            let url = format!("{}/{}/index.{}", DICTIONARIES_BASE_URL, upstream, ext);
+            // Every language's files are named index.*, so the font download cache would mix them up
+            let bytes = crate::fonts_cli::fetch_bytes(&url)?;
+            let out = dir.join(format!("{}.{}", lang, ext));
+            fs::write(&out, bytes).with_context(|| format!("write {}", out.display()))?;
+        }
+    }
+    verify_dictionaries_blocking(languages)
+}
+
+/// Check that the requested dictionaries are present and parse to a non-empty word list
+pub fn verify_dictionaries_blocking(languages: &[String]) -> Result<()> {
+    for (lang, _) in DICTIONARIES {
+        if !languages.is_empty() && !languages.iter().any(|l| l == lang) {
+            continue;
+        }
+        let path = dictionaries_dir().join(format!("{}.dic", lang));
+        if !path.exists() {
+            if languages.is_empty() {
+                continue;
+            }
+            anyhow::bail!("missing dictionary: {}", path.display());
+        }
+        let checker = SpellChecker::load(lang)?;
+        if checker.words.is_empty() {
+            anyhow::bail!("dictionary {} contains no words", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Whether a dictionary file exists for `language`
+pub fn has_dictionary(language: &str) -> bool {
+    dictionaries_dir().join(format!("{}.dic", language)).exists()
+}
@@ -0,0 +1,534 @@
+//! Op lists of the documents a handler created, with automatic spill to disk.
+//!
+//! With a limit set (`--max-document-memory`), a document whose ops grow past it first
+//! has its image and font bytes moved to its spill file, largest first. Each payload is
+//! written once and read back by offset only when something needs its bytes, such as
+//! building the package, so later edits neither reload nor rewrite it.
+//!
+//! When the ops are still over the limit without their payloads, the op list moves to
+//! the spill file too, one record per op, and only an index of offsets, digests and
+//! element fingerprints stays in memory. Appending an op adds one record; other edits
+//! load the list back and, when it spills again, write only the ops whose content is
+//! not in the file yet. The file only grows: records and payloads the document no longer
+//! uses stay in it until the document is closed.
+//! Without a limit every op list stays in memory.
+
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::docx_handler::{op_fingerprint, DocxOp};
+
+pub(crate) struct OpStore {
+    live: HashMap<String, Vec<DocxOp>>,
+    /// Documents whose op list is on disk only
+    spilled: HashMap<String, SpilledOps>,
+    /// Records of a spilled list that was loaded back for an edit, by content digest, so
+    /// spilling it again writes only the ops that changed
+    reusable: HashMap<String, HashMap<u64, Record>>,
+    /// Copies of op lists by snapshot id. With a limit set they are records in the
+    /// document's spill file, sharing the ones already there.
+    snapshots: HashMap<String, StoredOps>,
+    /// Spill file by document id, created the first time something of it moves out
+    files: HashMap<String, Arc<SpillFile>>,
+    dir: PathBuf,
+    max_bytes: Option<usize>,
+}
+
+/// An op list on disk. Its payloads are kept here, in op order, rather than in the records.
+#[derive(Clone)]
+struct SpilledOps {
+    file: Arc<SpillFile>,
+    records: Vec<Record>,
+    payloads: Vec<Payload>,
+}
+
+enum StoredOps {
+    Memory(Vec<DocxOp>),
+    Spilled(SpilledOps),
+}
+
+/// One op in the spill file: where its JSON is, and its element fingerprint
+#[derive(Clone, Copy)]
+struct Record {
+    offset: u64,
+    len: usize,
+    digest: u64,
+    fingerprint: u64,
+}
+
+impl OpStore {
+    /// Spill files go to `dir`, which is created on first use
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            live: HashMap::new(),
+            spilled: HashMap::new(),
+            reusable: HashMap::new(),
+            snapshots: HashMap::new(),
+            files: HashMap::new(),
+            dir,
+            max_bytes: None,
+        }
+    }
+
+    pub(crate) fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    pub(crate) fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+    }
+
+    pub(crate) fn contains_key(&self, doc_id: &str) -> bool {
+        self.live.contains_key(doc_id) || self.spilled.contains_key(doc_id)
+    }
+
+    /// Whether any of a document's ops or payloads are on disk
+    pub(crate) fn is_spilled(&self, doc_id: &str) -> bool {
+        self.spilled.contains_key(doc_id)
+            || self.live.get(doc_id).is_some_and(|ops| ops.iter().filter_map(payload).any(Payload::is_spilled))
+    }
+
+    /// Number of documents with ops or payloads on disk
+    pub(crate) fn spilled_count(&self) -> usize {
+        self.spilled.len() + self.live.keys().filter(|id| self.is_spilled(id)).count()
+    }
+
+    /// The ops of a document, borrowed when in memory and read from the spill file
+    /// otherwise; payloads on disk stay there. An unreadable spill file is an error.
+    pub(crate) fn get(&self, doc_id: &str) -> Result<Option<Cow<'_, [DocxOp]>>> {
+        if let Some(ops) = self.live.get(doc_id) {
+            return Ok(Some(Cow::Borrowed(ops)));
+        }
+        let Some(spilled) = self.spilled.get(doc_id) else {
+            return Ok(None);
+        };
+        let ops = read_ops(spilled).with_context(|| format!("Failed to load the ops of document {}", doc_id))?;
+        Ok(Some(Cow::Owned(ops)))
+    }
+
+    /// The element fingerprints of a document's ops, from the index when they are on disk
+    pub(crate) fn fingerprints(&self, doc_id: &str) -> Option<Vec<u64>> {
+        if let Some(ops) = self.live.get(doc_id) {
+            return Some(ops.iter().map(op_fingerprint).collect());
+        }
+        self.spilled.get(doc_id).map(|spilled| spilled.records.iter().map(|r| r.fingerprint).collect())
+    }
+
+    /// The ops of a document for editing, moving a spilled op list back into memory. An
+    /// unreadable spill file is an error; the records are kept, so a later call can retry.
+    pub(crate) fn get_mut(&mut self, doc_id: &str) -> Result<Option<&mut Vec<DocxOp>>> {
+        if let Some(spilled) = self.spilled.get(doc_id) {
+            let ops = read_ops(spilled).with_context(|| format!("Failed to load the ops of document {}", doc_id))?;
+            let spilled = self.spilled.remove(doc_id).expect("checked above");
+            self.reusable.insert(doc_id.to_string(), spilled.records.into_iter().map(|r| (r.digest, r)).collect());
+            self.live.insert(doc_id.to_string(), ops);
+        }
+        Ok(self.live.get_mut(doc_id))
+    }
+
+    /// Append an op. A spilled list grows by one record, and the op's payload goes
+    /// straight to disk, without loading or rewriting the rest. `None` for an unknown
+    /// document.
+    pub(crate) fn push(&mut self, doc_id: &str, mut op: DocxOp) -> Result<Option<()>> {
+        if let Some(ops) = self.live.get_mut(doc_id) {
+            ops.push(op);
+            return Ok(Some(()));
+        }
+        let Some(spilled) = self.spilled.get_mut(doc_id) else {
+            return Ok(None);
+        };
+        let fingerprint = op_fingerprint(&op);
+        let mut writer = spilled.file.appender()?;
+        let payload = match payload_mut(&mut op) {
+            Some(slot) => Some(match std::mem::replace(slot, Payload::Memory(Vec::new())) {
+                Payload::Memory(data) => writer.payload(&spilled.file, &data)?,
+                spilled => spilled,
+            }),
+            None => None,
+        };
+        let record = writer.record(&op, fingerprint)?;
+        writer.finish()?;
+        spilled.records.push(record);
+        spilled.payloads.extend(payload);
+        Ok(Some(()))
+    }
+
+    pub(crate) fn insert(&mut self, doc_id: String, ops: Vec<DocxOp>) {
+        self.spilled.remove(&doc_id);
+        self.reusable.remove(&doc_id);
+        self.live.insert(doc_id, ops);
+    }
+
+    /// Forget a document. Its spill file goes once no op copy (e.g. a snapshot) refers
+    /// to it anymore.
+    pub(crate) fn remove(&mut self, doc_id: &str) {
+        self.live.remove(doc_id);
+        self.spilled.remove(doc_id);
+        self.reusable.remove(doc_id);
+        self.files.remove(doc_id);
+    }
+
+    /// Keep a copy of a document's ops under `snapshot_id` and return how many there
+    /// are, `None` for an unknown document. With a limit set the copy is written to the
+    /// spill file, where it shares records and payloads with the document.
+    pub(crate) fn snapshot(&mut self, doc_id: &str, snapshot_id: &str) -> Result<Option<usize>> {
+        if let Some(spilled) = self.spilled.get(doc_id) {
+            let len = spilled.records.len();
+            self.snapshots.insert(snapshot_id.to_string(), StoredOps::Spilled(spilled.clone()));
+            return Ok(Some(len));
+        }
+        let Some(ops) = self.live.get(doc_id) else {
+            return Ok(None);
+        };
+        if self.max_bytes.is_none() {
+            self.snapshots.insert(snapshot_id.to_string(), StoredOps::Memory(ops.clone()));
+            return Ok(Some(ops.len()));
+        }
+        let file = spill_file(&mut self.files, &self.dir, doc_id)?;
+        let reusable = self.reusable.entry(doc_id.to_string()).or_default();
+        let records = write_records(&file, ops, reusable)?;
+        // Spilling the document later reuses these records
+        reusable.extend(records.iter().map(|r| (r.digest, *r)));
+        let mut writer = file.appender()?;
+        let mut payloads = Vec::new();
+        for payload in ops.iter().filter_map(payload) {
+            payloads.push(match payload {
+                Payload::Memory(data) => writer.payload(&file, data)?,
+                spilled => spilled.clone(),
+            });
+        }
+        writer.finish()?;
+        self.snapshots.insert(snapshot_id.to_string(), StoredOps::Spilled(SpilledOps { file, records, payloads }));
+        Ok(Some(ops.len()))
+    }
+
+    /// The ops kept by `snapshot`, read back from the spill file when they are there
+    pub(crate) fn snapshot_ops(&self, snapshot_id: &str) -> Result<Option<Cow<'_, [DocxOp]>>> {
+        match self.snapshots.get(snapshot_id) {
+            Some(StoredOps::Memory(ops)) => Ok(Some(Cow::Borrowed(ops))),
+            Some(StoredOps::Spilled(spilled)) => read_ops(spilled)
+                .with_context(|| format!("Failed to load snapshot {}", snapshot_id))
+                .map(|ops| Some(Cow::Owned(ops))),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn remove_snapshot(&mut self, snapshot_id: &str) {
+        self.snapshots.remove(snapshot_id);
+    }
+
+    /// Move a document's payloads, then if needed its op list, to disk when they exceed
+    /// the limit. Returns whether anything moved.
+    pub(crate) fn spill_if_large(&mut self, doc_id: &str) -> Result<bool> {
+        let (Some(max_bytes), Some(ops)) = (self.max_bytes, self.live.get_mut(doc_id)) else {
+            return Ok(false);
+        };
+        let mut bytes = approx_bytes(ops);
+        if bytes <= max_bytes {
+            return Ok(false);
+        }
+        let file = spill_file(&mut self.files, &self.dir, doc_id)?;
+
+        let mut payloads: Vec<&mut Payload> = ops.iter_mut().filter_map(payload_mut)
+            .filter(|p| p.resident_len() > 0)
+            .collect();
+        if !payloads.is_empty() {
+            payloads.sort_by_key(|p| std::cmp::Reverse(p.resident_len()));
+            let mut writer = file.appender()?;
+            let mut moved = 0;
+            for payload in payloads {
+                if bytes <= max_bytes {
+                    break;
+                }
+                let Payload::Memory(data) = &*payload else { continue };
+                let len = data.len();
+                *payload = writer.payload(&file, data)?;
+                bytes -= len;
+                moved += len;
+            }
+            writer.finish()?;
+            info!("Moved {} bytes of images and fonts of document {} to disk (limit {})", moved, doc_id, max_bytes);
+            if bytes <= max_bytes {
+                return Ok(true);
+            }
+        }
+
+        // Still over the limit: the op list goes to disk too, its payloads staying where
+        // they are. Ops already in the file from an earlier spill are not written again.
+        let reusable = self.reusable.remove(doc_id).unwrap_or_default();
+        let ops = self.live.get(doc_id).map(Vec::as_slice).unwrap_or_default();
+        let records = match write_records(&file, ops, &reusable) {
+            Ok(records) => records,
+            Err(e) => {
+                self.reusable.insert(doc_id.to_string(), reusable);
+                return Err(e);
+            }
+        };
+        let ops = self.live.remove(doc_id).unwrap_or_default();
+        let payloads = ops.into_iter().filter_map(|mut op| payload_mut(&mut op).map(|p| std::mem::replace(p, Payload::Memory(Vec::new())))).collect();
+        self.spilled.insert(doc_id.to_string(), SpilledOps { file, records, payloads });
+        info!("Spilled ops of document {} to disk (~{} bytes, limit {})", doc_id, bytes, max_bytes);
+        Ok(true)
+    }
+}
+
+fn spill_file(files: &mut HashMap<String, Arc<SpillFile>>, dir: &Path, doc_id: &str) -> Result<Arc<SpillFile>> {
+    if let Some(file) = files.get(doc_id) {
+        return Ok(file.clone());
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create spill directory {:?}", dir))?;
+    let file = Arc::new(SpillFile { path: dir.join(format!("{}.spill", doc_id)) });
+    files.insert(doc_id.to_string(), file.clone());
+    Ok(file)
+}
+
+/// Index `ops` in the spill file, appending the ones not among `reusable`
+fn write_records(file: &SpillFile, ops: &[DocxOp], reusable: &HashMap<u64, Record>) -> Result<Vec<Record>> {
+    let mut writer = file.appender()?;
+    let mut records = Vec::with_capacity(ops.len());
+    for op in ops {
+        let fingerprint = op_fingerprint(op);
+        let json = record_json(op, &file.path)?;
+        let digest = digest(&json);
+        let record = match reusable.get(&digest) {
+            Some(record) if record.len == json.len() => Record { fingerprint, ..*record },
+            _ => writer.write(&json, digest, fingerprint)?,
+        };
+        records.push(record);
+    }
+    writer.finish()?;
+    Ok(records)
+}
+
+/// An op as stored in a record: JSON without its payload
+fn record_json(op: &DocxOp, path: &Path) -> Result<Vec<u8>> {
+    let json = match payload(op) {
+        Some(_) => {
+            let mut bare = op.clone();
+            if let Some(slot) = payload_mut(&mut bare) {
+                *slot = Payload::Memory(Vec::new());
+            }
+            serde_json::to_vec(&bare)
+        }
+        None => serde_json::to_vec(op),
+    };
+    json.with_context(|| format!("Failed to write spill file {:?}", path))
+}
+
+/// Read a spilled op list back, payloads in place
+fn read_ops(spilled: &SpilledOps) -> Result<Vec<DocxOp>> {
+    let path = &spilled.file.path;
+    let mut reader = std::fs::File::open(path).with_context(|| format!("Failed to open spill file {:?}", path))?;
+    let mut ops = Vec::with_capacity(spilled.records.len());
+    for record in &spilled.records {
+        let json = read_checked(&mut reader, path, record.offset, record.len, record.digest)?;
+        ops.push(serde_json::from_slice(&json).with_context(|| format!("Invalid spill file {:?}: record at {}", path, record.offset))?);
+    }
+    attach_payloads(&mut ops, spilled.payloads.clone()).with_context(|| format!("Invalid spill file {:?}", path))?;
+    Ok(ops)
+}
+
+/// Read `len` bytes at `offset` and check them against the digest taken when they were written
+fn read_checked(reader: &mut std::fs::File, path: &Path, offset: u64, len: usize, expected: u64) -> Result<Vec<u8>> {
+    let mut data = vec![0; len];
+    reader.seek(SeekFrom::Start(offset))
+        .and_then(|_| reader.read_exact(&mut data))
+        .with_context(|| format!("Invalid spill file {:?}: data at {} is missing", path, offset))?;
+    anyhow::ensure!(digest(&data) == expected, "Invalid spill file {:?}: data at {} changed", path, offset);
+    Ok(data)
+}
+
+/// Put payloads taken out of `ops` (in op order) back in place
+fn attach_payloads(ops: &mut [DocxOp], payloads: Vec<Payload>) -> Result<()> {
+    let mut payloads = payloads.into_iter();
+    for slot in ops.iter_mut().filter_map(payload_mut) {
+        *slot = payloads.next().context("More payloads in the ops than were taken out")?;
+    }
+    anyhow::ensure!(payloads.next().is_none(), "Fewer payloads in the ops than were taken out");
+    Ok(())
+}
+
+fn payload(op: &DocxOp) -> Option<&Payload> {
+    match op {
+        DocxOp::Image { data, .. } | DocxOp::EmbedFont { data, .. } => Some(data),
+        _ => None,
+    }
+}
+
+fn payload_mut(op: &mut DocxOp) -> Option<&mut Payload> {
+    match op {
+        DocxOp::Image { data, .. } | DocxOp::EmbedFont { data, .. } => Some(data),
+        _ => None,
+    }
+}
+
+/// Rough heap size of an op list: text and binary payloads in memory plus a fixed cost
+/// per op. Good enough to compare against a limit; not an exact accounting.
+pub(crate) fn approx_bytes(ops: &[DocxOp]) -> usize {
+    const PER_OP: usize = 64;
+    ops.iter().map(|op| PER_OP + match op {
+        DocxOp::Paragraph { text, .. } | DocxOp::Heading { text, .. } | DocxOp::ListItem { text, .. } => text.len(),
+        DocxOp::Header(text) | DocxOp::Footer(text) => text.len(),
+        DocxOp::List { items, .. } => items.iter().map(|i| i.len() + 24).sum(),
+        DocxOp::Table { data } => data.rows.iter().flatten().map(|c| c.len() + 24).sum(),
+        DocxOp::Image { data, .. } | DocxOp::EmbedFont { data, .. } => data.resident_len(),
+        DocxOp::Hyperlink { text, url } => text.len() + url.len(),
+        DocxOp::HeaderLayout { layout, .. } => layout.logo.as_ref().map(|l| l.data.len()).unwrap_or(0),
+        _ => 0,
+    }).sum()
+}
+
+/// Image or font bytes of an op: in memory, or a range of the document's payload file
+/// once the ops outgrew the memory limit. Serializes as base64 either way.
+#[derive(Clone)]
+pub(crate) enum Payload {
+    Memory(Vec<u8>),
+    Spilled { file: Arc<SpillFile>, offset: u64, len: usize, digest: u64 },
+}
+
+impl Payload {
+    /// The bytes, read from the spill file when they are on disk. A file that was
+    /// truncated or changed since is an error.
+    pub(crate) fn bytes(&self) -> Result<Cow<'_, [u8]>> {
+        let (file, offset, len, expected) = match self {
+            Payload::Memory(data) => return Ok(Cow::Borrowed(data)),
+            Payload::Spilled { file, offset, len, digest } => (file, *offset, *len, *digest),
+        };
+        let path = &file.path;
+        let mut reader = std::fs::File::open(path).with_context(|| format!("Failed to open spill file {:?}", path))?;
+        let data = read_checked(&mut reader, path, offset, len, expected)?;
+        Ok(Cow::Owned(data))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Payload::Memory(data) => data.len(),
+            Payload::Spilled { len, .. } => *len,
+        }
+    }
+
+    fn resident_len(&self) -> usize {
+        match self {
+            Payload::Memory(data) => data.len(),
+            Payload::Spilled { .. } => 0,
+        }
+    }
+
+    fn is_spilled(&self) -> bool {
+        matches!(self, Payload::Spilled { .. })
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        Payload::Memory(data)
+    }
+}
+
+/// Hashes the content, the same whether it is in memory or on disk
+impl std::hash::Hash for Payload {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(match self {
+            Payload::Memory(data) => digest(data),
+            Payload::Spilled { digest, .. } => *digest,
+        });
+    }
+}
+
+impl std::fmt::Debug for Payload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Payload({} bytes{})", self.len(), if self.is_spilled() { ", on disk" } else { "" })
+    }
+}
+
+impl serde::Serialize for Payload {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = self.bytes().map_err(|e| serde::ser::Error::custom(format!("{:#}", e)))?;
+        base64_bytes::serialize(&data, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Payload {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        base64_bytes::deserialize(deserializer).map(Payload::Memory)
+    }
+}
+
+/// Append-only file of a document's payloads and op records, removed with the last
+/// payload or op list using it
+pub(crate) struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    fn appender(&self) -> Result<Appender<'_>> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)
+            .with_context(|| format!("Failed to open spill file {:?}", self.path))?;
+        let offset = file.metadata()?.len();
+        Ok(Appender { path: &self.path, writer: std::io::BufWriter::new(file), offset })
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Writes to the end of a spill file, keeping track of where each write landed
+struct Appender<'f> {
+    path: &'f Path,
+    writer: std::io::BufWriter<std::fs::File>,
+    offset: u64,
+}
+
+impl Appender<'_> {
+    fn write(&mut self, data: &[u8], digest: u64, fingerprint: u64) -> Result<Record> {
+        self.writer.write_all(data).with_context(|| format!("Failed to write spill file {:?}", self.path))?;
+        let record = Record { offset: self.offset, len: data.len(), digest, fingerprint };
+        self.offset += data.len() as u64;
+        Ok(record)
+    }
+
+    fn record(&mut self, op: &DocxOp, fingerprint: u64) -> Result<Record> {
+        let json = record_json(op, self.path)?;
+        self.write(&json, digest(&json), fingerprint)
+    }
+
+    fn payload(&mut self, file: &Arc<SpillFile>, data: &[u8]) -> Result<Payload> {
+        let Record { offset, len, digest, .. } = self.write(data, digest(data), 0)?;
+        Ok(Payload::Spilled { file: file.clone(), offset, len, digest })
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.writer.flush().with_context(|| format!("Failed to write spill file {:?}", self.path))
+    }
+}
+
+fn digest(data: &[u8]) -> u64 {
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Serde adapter storing byte payloads as base64 strings, several times smaller in
+/// JSON than an array of numbers
+pub(crate) mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD.decode(text).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,34 @@
+//! Stdio transport, the default: newline-delimited MCP JSON-RPC messages on stdin and
+//! one reply line on stdout for each message that needs one.
+//!
+//! Messages are answered by the same code as the HTTP transport (`http::handle_text`),
+//! so failed tool calls come back as plain JSON with `isError` set, and
+//! `structuredContent`, image blocks and the full tool definitions reach stdio clients
+//! as well.
+
+use anyhow::Result;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::docx_tools::DocxToolsProvider;
+use crate::http::handle_text;
+
+/// Answer messages from `input` on `output` until `input` ends
+pub async fn serve<R, W>(provider: &DocxToolsProvider, input: R, mut output: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(input).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(reply) = handle_text(provider, &line).await {
+            let mut text = serde_json::to_string(&reply)?;
+            text.push('\n');
+            output.write_all(text.as_bytes()).await?;
+            output.flush().await?;
+        }
+    }
+    Ok(())
+}
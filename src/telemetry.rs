@@ -0,0 +1,38 @@
+//! OpenTelemetry export (feature `otel`). When `OTEL_EXPORTER_OTLP_ENDPOINT` is set,
+//! tracing spans, including the `tool_call` span with its `call_id`, are sent to that
+//! collector over OTLP/HTTP alongside the usual stderr logs. The other standard
+//! `OTEL_EXPORTER_OTLP_*` variables (headers, timeout) are honoured by the exporter.
+
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::OpenTelemetryLayer;
+
+/// Flushes pending spans when dropped; keep it alive until the process exits
+pub struct Guard(SdkTracerProvider);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.shutdown() {
+            eprintln!("Failed to flush OpenTelemetry spans: {:?}", e);
+        }
+    }
+}
+
+/// The layer that exports spans, or None when no collector is configured
+pub fn layer<S>() -> Result<Option<(OpenTelemetryLayer<S, SdkTracer>, Guard)>>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return Ok(None);
+    }
+    let exporter = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name("docx-mcp").build())
+        .with_batch_exporter(exporter)
+        .build();
+    let tracer = provider.tracer("docx-mcp");
+    Ok(Some((tracing_opentelemetry::layer().with_tracer(tracer), Guard(provider))))
+}
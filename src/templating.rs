@@ -0,0 +1,288 @@
+//! Handlebars-style templates over document text, rendered against a JSON payload.
+//!
+//! Supported: `{{path.to.value}}` (with `this`, `@index` and `../` for the enclosing
+//! item), `{{#each list}}…{{else}}…{{/each}}`, `{{#if value}}…{{else}}…{{/if}}` and
+//! `{{#unless value}}…{{/unless}}`. Values that are not in the payload render as empty
+//! text and are collected in `Context::missing`.
+//!
+//! Format helpers render a value for the context's locale (see `locale::Formatting`):
+//! `{{currency total}}`, `{{currency total "EUR"}}`, `{{number qty 1}}`,
+//! `{{percent rate}}` and `{{date due "long"}}`.
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+use crate::locale::Formatting;
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([#/]?)\s*([^{}]*?)\s*\}\}").unwrap());
+
+/// A paragraph (or cell) whose whole text is one block tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockTag<'t> {
+    Open { kind: &'t str, path: &'t str },
+    Else,
+    Close(&'t str),
+}
+
+/// Classify text consisting of a single `{{#…}}`, `{{else}}` or `{{/…}}` tag
+pub fn block_tag(text: &str) -> Option<BlockTag<'_>> {
+    let text = text.trim();
+    let caps = TAG.captures(text)?;
+    if caps.get(0)?.as_str().len() != text.len() {
+        return None;
+    }
+    let body = caps.get(2)?.as_str();
+    match caps.get(1)?.as_str() {
+        "#" => {
+            let (kind, path) = body.split_once(char::is_whitespace)?;
+            matches!(kind, "each" | "if" | "unless").then(|| BlockTag::Open { kind, path: path.trim() })
+        }
+        "/" => Some(BlockTag::Close(body)),
+        _ if body == "else" => Some(BlockTag::Else),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var(String),
+    Block { kind: String, path: String, body: Vec<Node>, otherwise: Vec<Node> },
+}
+
+/// An open block while parsing: (kind, path, body, otherwise, in else branch)
+type OpenBlock = (String, String, Vec<Node>, Vec<Node>, bool);
+
+fn parse(source: &str) -> Result<Vec<Node>> {
+    let mut stack: Vec<OpenBlock> = Vec::new();
+    let mut root = Vec::new();
+    let mut last = 0;
+    fn target<'a>(stack: &'a mut [OpenBlock], root: &'a mut Vec<Node>) -> &'a mut Vec<Node> {
+        match stack.last_mut() {
+            Some((_, _, body, otherwise, in_else)) => if *in_else { otherwise } else { body },
+            None => root,
+        }
+    }
+    for caps in TAG.captures_iter(source) {
+        let whole = caps.get(0).unwrap();
+        if whole.start() > last {
+            target(&mut stack, &mut root).push(Node::Text(source[last..whole.start()].to_string()));
+        }
+        last = whole.end();
+        let body = caps[2].to_string();
+        match &caps[1] {
+            "#" => {
+                let Some((kind, path)) = body.split_once(char::is_whitespace) else {
+                    bail!("block tag '{}' needs a value, e.g. {{{{#each items}}}}", whole.as_str());
+                };
+                if !matches!(kind, "each" | "if" | "unless") {
+                    bail!("unsupported block '{}' (use each, if or unless)", kind);
+                }
+                stack.push((kind.to_string(), path.trim().to_string(), Vec::new(), Vec::new(), false));
+            }
+            "/" => {
+                let Some((kind, path, body_nodes, otherwise, _)) = stack.pop() else {
+                    bail!("'{}' has no matching opening tag", whole.as_str());
+                };
+                if kind != body {
+                    bail!("'{}' closes {{{{#{} {}}}}}", whole.as_str(), kind, path);
+                }
+                target(&mut stack, &mut root).push(Node::Block { kind, path, body: body_nodes, otherwise });
+            }
+            _ if body == "else" => match stack.last_mut() {
+                Some(open) => open.4 = true,
+                None => bail!("{{{{else}}}} outside a block"),
+            },
+            _ => target(&mut stack, &mut root).push(Node::Var(body)),
+        }
+    }
+    if let Some((kind, path, ..)) = stack.last() {
+        bail!("{{{{#{} {}}}}} is not closed", kind, path);
+    }
+    if last < source.len() {
+        root.push(Node::Text(source[last..].to_string()));
+    }
+    Ok(root)
+}
+
+/// Value text: strings as-is, lists joined with ", ", null as nothing
+pub(crate) fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+        Some(Value::Array(a)) => !a.is_empty(),
+        Some(Value::Object(o)) => !o.is_empty(),
+        Some(_) => true,
+    }
+}
+
+fn walk<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.').filter(|s| !s.is_empty()).try_fold(value, |v, key| match v {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => v.get(key),
+    })
+}
+
+struct Frame {
+    value: Value,
+    index: Option<usize>,
+}
+
+/// Render scope: the payload plus the items of enclosing `each` blocks
+pub struct Context {
+    frames: Vec<Frame>,
+    missing: BTreeSet<String>,
+    formatting: Formatting,
+}
+
+const HELPERS: &[&str] = &["number", "integer", "currency", "percent", "date"];
+
+impl Context {
+    pub fn new(data: &Value) -> Self {
+        Self {
+            frames: vec![Frame { value: data.clone(), index: None }],
+            missing: BTreeSet::new(),
+            formatting: Formatting::default(),
+        }
+    }
+
+    /// Locale and currency for format helpers
+    pub fn with_formatting(mut self, formatting: Formatting) -> Self {
+        self.formatting = formatting;
+        self
+    }
+
+    /// Enter an `each` item
+    pub fn push(&mut self, value: Value, index: usize) {
+        self.frames.push(Frame { value, index: Some(index) });
+    }
+
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    /// Paths referenced by the template but absent from the payload
+    pub fn missing(&self) -> Vec<String> {
+        self.missing.iter().cloned().collect()
+    }
+
+    /// Resolve a path: `this`, `@index`, `../x`; plain names are looked up in the
+    /// current item first, then in enclosing items and the payload
+    pub fn lookup(&mut self, path: &str) -> Option<Value> {
+        let mut rest = path.trim();
+        let mut depth = 0;
+        while let Some(r) = rest.strip_prefix("../") {
+            depth += 1;
+            rest = r;
+        }
+        let frames = &self.frames[..self.frames.len().saturating_sub(depth).max(1)];
+        let found = match rest {
+            "@index" => frames.last().and_then(|f| f.index).map(Value::from),
+            "this" | "." => frames.last().map(|f| f.value.clone()),
+            _ => match rest.strip_prefix("this.") {
+                Some(own) => frames.last().and_then(|f| walk(&f.value, own)).cloned(),
+                None => frames.iter().rev().find_map(|f| walk(&f.value, rest)).cloned(),
+            },
+        };
+        if found.is_none() {
+            self.missing.insert(rest.to_string());
+        }
+        found
+    }
+
+    pub fn is_truthy(&mut self, path: &str) -> bool {
+        let value = self.lookup(path);
+        truthy(value.as_ref())
+    }
+
+    /// Items an `each` block iterates: array elements or object values
+    pub fn items(&mut self, path: &str) -> Vec<Value> {
+        match self.lookup(path) {
+            Some(Value::Array(items)) => items,
+            Some(Value::Object(map)) => map.into_iter().map(|(_, v)| v).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// `{{helper value [argument]}}`; the argument is quoted text or a bare number
+    fn helper(&mut self, helper: &str, args: &str) -> Result<Option<String>> {
+        let (path, arg) = match args.trim().split_once(char::is_whitespace) {
+            Some((path, arg)) => (path, Some(arg.trim().trim_matches('"').trim_matches('\''))),
+            None => (args.trim(), None),
+        };
+        let Some(value) = self.lookup(path) else { return Ok(None) };
+        if value.is_null() {
+            return Ok(None);
+        }
+        let spec = match arg {
+            Some(arg) => format!("{}:{}", helper, arg),
+            None => helper.to_string(),
+        };
+        self.formatting.format(&value, &spec)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("{{{{{} {}}}}}: {}", helper, args.trim(), e))
+    }
+
+    fn render_nodes(&mut self, nodes: &[Node], out: &mut String) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var(path) => match path.split_once(char::is_whitespace) {
+                    Some((helper, args)) if HELPERS.contains(&helper) => {
+                        if let Some(text) = self.helper(helper, args)? {
+                            out.push_str(&text);
+                        }
+                    }
+                    _ => if let Some(value) = self.lookup(path) {
+                        out.push_str(&display(&value));
+                    },
+                },
+                Node::Block { kind, path, body, otherwise } => match kind.as_str() {
+                    "each" => {
+                        let items = self.items(path);
+                        if items.is_empty() {
+                            self.render_nodes(otherwise, out)?;
+                        }
+                        for (index, item) in items.into_iter().enumerate() {
+                            self.push(item, index);
+                            let rendered = self.render_nodes(body, out);
+                            self.pop();
+                            rendered?;
+                        }
+                    }
+                    _ => {
+                        let show = self.is_truthy(path) == (kind == "if");
+                        self.render_nodes(if show { body } else { otherwise }, out)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Render one piece of text; text without tags is returned unchanged
+    pub fn render(&mut self, text: &str) -> Result<String> {
+        if !text.contains("{{") {
+            return Ok(text.to_string());
+        }
+        let nodes = parse(text)?;
+        let mut out = String::new();
+        self.render_nodes(&nodes, &mut out)?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,242 @@
+//! Bulk text transforms for `transform_text`: case changes, whitespace cleanup and hard
+//! wrapping of code lines.
+//!
+//! Case changes keep acronyms ("API", "PDF") unless the surrounding text is all upper
+//! case, in which case there is nothing to tell them apart from shouting.
+//!
+//! `match_case` gives a find/replace replacement the case of the text it replaces.
+
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    TitleCase,
+    SentenceCase,
+    Upper,
+    Lower,
+    TrimTrailingWhitespace,
+    /// Hard-wrap lines longer than this many characters
+    MaxLineLength(usize),
+}
+
+impl Transform {
+    /// `operation` is title_case, sentence_case, uppercase, lowercase,
+    /// trim_trailing_whitespace or max_line_length (which needs `max_length`)
+    pub fn parse(operation: &str, max_length: Option<usize>) -> Result<Self> {
+        Ok(match operation.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "title_case" | "title" => Transform::TitleCase,
+            "sentence_case" | "sentence" => Transform::SentenceCase,
+            "uppercase" | "upper" => Transform::Upper,
+            "lowercase" | "lower" => Transform::Lower,
+            "trim_trailing_whitespace" | "trim" => Transform::TrimTrailingWhitespace,
+            "max_line_length" | "wrap" => match max_length {
+                Some(n) if n >= 8 => Transform::MaxLineLength(n),
+                Some(n) => anyhow::bail!("max_length must be at least 8 (got {})", n),
+                None => anyhow::bail!("max_line_length needs max_length"),
+            },
+            other => anyhow::bail!(
+                "unknown operation '{}' (use title_case, sentence_case, uppercase, lowercase, trim_trailing_whitespace or max_line_length)",
+                other
+            ),
+        })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Transform::TitleCase => outside_tokens(text, title_case),
+            Transform::SentenceCase => outside_tokens(text, sentence_case),
+            Transform::Upper => outside_tokens(text, str::to_uppercase),
+            Transform::Lower => outside_tokens(text, str::to_lowercase),
+            Transform::TrimTrailingWhitespace => text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n"),
+            Transform::MaxLineLength(max) => text.lines().map(|l| wrap_line(l, *max)).collect::<Vec<_>>().join("\n"),
+        }
+    }
+}
+
+/// Apply a case change to the text between `{{template}}` tags and `{FIELD:...}` tokens,
+/// leaving the tokens themselves intact
+fn outside_tokens(text: &str, change: impl Fn(&str) -> String) -> String {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    static TOKEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{[^{}]*\}\}|\{FIELD:[^{}]*\}").unwrap());
+    if !TOKEN.is_match(text) {
+        return change(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    for m in TOKEN.find_iter(text) {
+        out.push_str(&change(&text[pos..m.start()]));
+        out.push_str(m.as_str());
+        pos = m.end();
+    }
+    out.push_str(&change(&text[pos..]));
+    out
+}
+
+/// Short words left lower case inside titles
+const MINOR_WORDS: &[&str] = &[
+    "a", "an", "the", "and", "but", "or", "nor", "for", "so", "yet", "as", "at", "by", "in", "of", "on",
+    "to", "up", "via", "per", "vs",
+];
+
+fn is_acronym(word: &str) -> bool {
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    letters.len() > 1 && letters.iter().all(|c| c.is_uppercase())
+}
+
+fn shouting(text: &str) -> bool {
+    !text.chars().any(|c| c.is_lowercase()) && text.chars().any(|c| c.is_uppercase())
+}
+
+/// Upper-case the first letter and lower-case the rest
+pub(crate) fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+        None => String::new(),
+    }
+}
+
+/// Upper-case the first letter and keep the rest as written ("mcDonald" -> "McDonald")
+#[cfg(feature = "spellcheck")]
+pub(crate) fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Split into (word, separator) pieces so the original spacing and punctuation survive
+fn words(text: &str) -> Vec<(bool, &str)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut in_word = None;
+    for (i, c) in text.char_indices() {
+        let word_char = c.is_alphanumeric() || c == '\'' || c == '\u{2019}';
+        if in_word != Some(word_char) {
+            if i > start {
+                pieces.push((in_word.unwrap_or(false), &text[start..i]));
+            }
+            start = i;
+            in_word = Some(word_char);
+        }
+    }
+    if start < text.len() {
+        pieces.push((in_word.unwrap_or(false), &text[start..]));
+    }
+    pieces
+}
+
+pub fn title_case(text: &str) -> String {
+    let keep_acronyms = !shouting(text);
+    let pieces = words(text);
+    let word_positions: Vec<usize> = pieces.iter().enumerate().filter(|(_, (w, _))| *w).map(|(i, _)| i).collect();
+    let (first, last) = (word_positions.first().copied(), word_positions.last().copied());
+    let mut out = String::with_capacity(text.len());
+    let mut after_colon = false;
+    for (i, (is_word, piece)) in pieces.iter().enumerate() {
+        if !is_word {
+            if piece.contains(':') { after_colon = true; }
+            out.push_str(piece);
+            continue;
+        }
+        let lower = piece.to_lowercase();
+        let edge = Some(i) == first || Some(i) == last || after_colon;
+        if keep_acronyms && is_acronym(piece) {
+            out.push_str(piece);
+        } else if !edge && MINOR_WORDS.contains(&lower.as_str()) {
+            out.push_str(&lower);
+        } else {
+            out.push_str(&capitalize(piece));
+        }
+        after_colon = false;
+    }
+    out
+}
+
+/// Sentence case, judging "shouting" per sentence so an all-caps sentence is lowered
+/// while acronyms in ordinary sentences survive
+pub fn sentence_case(text: &str) -> String {
+    let pieces = words(text);
+    let mut out = String::with_capacity(text.len());
+    let mut start = 0;
+    while start < pieces.len() {
+        let end = pieces[start..].iter()
+            .position(|(is_word, piece)| !is_word && piece.contains(['.', '!', '?', '\n']))
+            .map(|p| start + p + 1)
+            .unwrap_or(pieces.len());
+        let sentence: String = pieces[start..end].iter().map(|(_, piece)| *piece).collect();
+        let keep_acronyms = !shouting(&sentence);
+        let mut first = true;
+        for (is_word, piece) in &pieces[start..end] {
+            if !is_word {
+                out.push_str(piece);
+                continue;
+            }
+            if keep_acronyms && is_acronym(piece) {
+                out.push_str(piece);
+            } else if first {
+                out.push_str(&capitalize(piece));
+            } else if piece.eq_ignore_ascii_case("i") {
+                out.push('I');
+            } else {
+                out.push_str(&piece.to_lowercase());
+            }
+            first = false;
+        }
+        start = end;
+    }
+    out
+}
+
+/// `replacement` in the case of `matched`: all upper ("ACME" → "GLOBEX"), all lower
+/// ("acme" → "globex") or capitalized words ("Acme" → "Globex"). Any other mix of case
+/// leaves the replacement as written.
+pub fn match_case(matched: &str, replacement: &str) -> String {
+    let matched_words: Vec<&str> = words(matched).into_iter()
+        .filter(|(is_word, piece)| *is_word && piece.chars().any(char::is_alphabetic))
+        .map(|(_, piece)| piece)
+        .collect();
+    if matched_words.is_empty() {
+        replacement.to_string()
+    } else if is_acronym(matched) {
+        replacement.to_uppercase()
+    } else if !matched.chars().any(char::is_uppercase) {
+        replacement.to_lowercase()
+    } else if matched_words.iter().all(|w| capitalize(w) == *w) {
+        words(replacement).into_iter()
+            .map(|(is_word, piece)| if is_word { capitalize(piece) } else { piece.to_string() })
+            .collect()
+    } else {
+        replacement.to_string()
+    }
+}
+
+/// Break a line at the last space that fits, or mid-token when none does. Continuation
+/// lines keep the original indentation.
+fn wrap_line(line: &str, max: usize) -> String {
+    if line.chars().count() <= max {
+        return line.to_string();
+    }
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+    let indent = if indent.chars().count() >= max / 2 { String::new() } else { indent };
+    let mut out: Vec<String> = Vec::new();
+    let mut rest: Vec<char> = line.chars().collect();
+    let mut prefix = String::new();
+    loop {
+        let budget = max - prefix.chars().count();
+        if rest.len() <= budget {
+            out.push(format!("{}{}", prefix, rest.iter().collect::<String>()));
+            break;
+        }
+        let cut = rest[..=budget].iter().rposition(|c| *c == ' ')
+            .filter(|&p| p > 0 && rest[..p].iter().any(|c| !c.is_whitespace()))
+            .unwrap_or(budget);
+        out.push(format!("{}{}", prefix, rest[..cut].iter().collect::<String>().trim_end()));
+        rest = rest[cut..].iter().copied().skip_while(|c| *c == ' ').collect();
+        if rest.is_empty() { break; }
+        prefix = indent.clone();
+    }
+    out.join("\n")
+}
@@ -0,0 +1,128 @@
+//! Tool annotations (read-only / destructive / idempotent hints) and output schemas,
+//! derived from the security command lists and the response shapes in `response`.
+
+use mcp_core::types::ToolAnnotations;
+use serde_json::{json, Value};
+
+use crate::security::SecurityConfig;
+
+/// Tools that overwrite or discard existing content
+const DESTRUCTIVE: &[&str] = &[
+    "close_document",
+    "find_and_replace",
+    "find_and_replace_advanced",
+    "redact_text",
+    "render_template",
+    "transform_text",
+    "import_xliff",
+    "strip_tagged_content",
+    "keep_only_tagged",
+    "replace_range_text",
+    "set_table_cell_text",
+    "apply_correction",
+    "strip_personal_info",
+    "lint_formatting",
+    "rebrand",
+    "set_header",
+    "set_footer",
+    "remove_watermark",
+    "delete_section",
+    "set_section_content",
+    "delete_range",
+    "save_document",
+];
+
+/// Write tools that leave the document unchanged when repeated with the same arguments
+const IDEMPOTENT_WRITES: &[&str] = &[
+    "set_header",
+    "set_footer",
+    "set_page_numbering",
+    "set_page_setup",
+    "add_watermark",
+    "embed_page_number_fields",
+    "enable_heading_numbering",
+    "renumber_headings",
+    "normalize_headings",
+    "move_section",
+    "set_section_content",
+    "set_table_cell_text",
+    "replace_range_text",
+    "apply_paragraph_format",
+    "transform_text",
+    "strip_personal_info",
+    "save_document",
+    "rename_document",
+    "assign_document_to_workspace",
+    "embed_font",
+    "reload_config",
+    "transfer_document",
+    "revoke_viewer",
+    "apply_comment_suggestions",
+];
+
+pub fn annotations_for(name: &str) -> ToolAnnotations {
+    let read_only = SecurityConfig::get_readonly_commands().contains(name) && !DESTRUCTIVE.contains(&name);
+    ToolAnnotations {
+        title: None,
+        read_only_hint: Some(read_only),
+        destructive_hint: Some(!read_only && DESTRUCTIVE.contains(&name)),
+        idempotent_hint: Some(read_only || IDEMPOTENT_WRITES.contains(&name)),
+        // Paths may be object storage URIs for these
+        open_world_hint: Some(matches!(name, "open_document" | "save_document")),
+    }
+}
+
+/// Schema of the JSON object a tool returns (the text block and structuredContent).
+/// Failures always use the error shape: `success: false`, `code`, `error`, optional `hint`.
+pub fn output_schema_for(name: &str) -> Value {
+    let payload = match name {
+        "create_document" | "open_document" | "clone_document" | "replay_ops" | "assemble_document" | "generate_invoice" => json!({"document_id": {"type": "string"}, "message": {"type": "string"}}),
+        "extract_text" => json!({"text": {"type": "string"}}),
+        "extract_formatted_text" => json!({"text": {"type": "string"}, "metadata": {"type": "object"}}),
+        "list_documents" | "list_workspace_documents" | "list_all_documents" => json!({
+            "documents": {"type": "array", "items": {"type": "object"}},
+            "total": {"type": "integer"},
+            "next_offset": {"type": "integer"}
+        }),
+        "convert_to_images" | "convert_to_images_with_preference" => json!({
+            "images": {"type": "array", "items": {"type": "string"}},
+            "message": {"type": "string"}
+        }),
+        "get_word_count" => json!({"statistics": {"type": "object"}}),
+        "get_security_info" | "reload_config" => json!({"security": {"type": "object"}}),
+        "get_storage_info" => json!({"storage": {"type": "object"}}),
+        "search_text" => json!({
+            "matches": {"type": "array", "items": {"type": "object"}},
+            "total_matches": {"type": "integer"}
+        }),
+        "convert_to_pdf" | "export_pdf_with_field_refresh" | "export_to_markdown" | "export_to_html" => {
+            json!({"message": {"type": "string"}})
+        }
+        "find_and_replace_advanced" | "redact_text" => json!({
+            "message": {"type": "string"},
+            "metadata": {"type": "object", "description": "With dry_run: total and the replacements that would be made"}
+        }),
+        "save_document" => json!({
+            "message": {"type": "string"},
+            "sha256": {"type": "string"},
+            "size_bytes": {"type": "integer"}
+        }),
+        n if SecurityConfig::get_readonly_commands().contains(n) || matches!(n, "lint_formatting" | "create_viewer" | "apply_comment_suggestions" | "snapshot_document" | "rebrand") => {
+            json!({"metadata": {"type": "object"}})
+        }
+        _ => json!({"message": {"type": "string"}}),
+    };
+    let mut properties = json!({
+        "success": {"type": "boolean"},
+        "call_id": {"type": "string", "description": "Correlation id of this call, also in the server logs"},
+        "code": {"type": "string", "description": "Error code when success is false"},
+        "error": {"type": "string"},
+        "hint": {"type": "string"}
+    });
+    if let (Some(props), Some(extra)) = (properties.as_object_mut(), payload.as_object()) {
+        for (k, v) in extra {
+            props.insert(k.clone(), v.clone());
+        }
+    }
+    json!({"type": "object", "properties": properties, "required": ["success"]})
+}
@@ -0,0 +1,73 @@
+//! XLIFF 1.2 export and import for translation workflows.
+//!
+//! Each text-bearing element becomes one `trans-unit`. Ids are stable references back
+//! into the element list: `e12` for element 12, `e12.i3` for item 3 of a list and
+//! `e12.r1c2` for a table cell. Import only touches text, so element styles carry over
+//! to the translated document unchanged.
+
+use anyhow::{Context, Result};
+
+use crate::docx_handler::{xml_escape, ElementText, RangeId};
+
+/// Trans-unit id for an element, as described in the module docs
+pub fn unit_id(element: &ElementText) -> String {
+    match (&element.range_id, element.item) {
+        (Some(RangeId::TableCell { row, col, .. }), _) => format!("e{}.r{}c{}", element.element_index, row, col),
+        (_, Some(item)) => format!("e{}.i{}", element.element_index, item),
+        _ => format!("e{}", element.element_index),
+    }
+}
+
+/// Render an XLIFF document with one unit per non-blank element
+pub fn export(original: &str, source_language: &str, target_language: Option<&str>, elements: &[ElementText]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n");
+    out.push_str(&format!(
+        "  <file original=\"{}\" source-language=\"{}\"{} datatype=\"plaintext\">\n    <body>\n",
+        xml_escape(original),
+        xml_escape(source_language),
+        target_language.map(|t| format!(" target-language=\"{}\"", xml_escape(t))).unwrap_or_default(),
+    ));
+    for element in elements.iter().filter(|e| !e.text.trim().is_empty()) {
+        out.push_str(&format!(
+            "      <trans-unit id=\"{}\" resname=\"{}\" xml:space=\"preserve\">\n        <source>{}</source>\n      </trans-unit>\n",
+            unit_id(element),
+            xml_escape(&element.kind),
+            xml_escape(&element.text),
+        ));
+    }
+    out.push_str("    </body>\n  </file>\n</xliff>\n");
+    out
+}
+
+/// A translated unit read back from an XLIFF file
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslatedUnit {
+    pub id: String,
+    pub source: String,
+    /// None when the unit has no target or an empty one
+    pub target: Option<String>,
+}
+
+/// Read the units of an XLIFF 1.2 file. Inline markup inside `source`/`target` is
+/// flattened to its text.
+pub fn parse(xml: &str) -> Result<Vec<TranslatedUnit>> {
+    let doc = roxmltree::Document::parse(xml).context("Invalid XLIFF: not well-formed XML")?;
+    if doc.root_element().tag_name().name() != "xliff" {
+        anyhow::bail!("Invalid XLIFF: root element is <{}>", doc.root_element().tag_name().name());
+    }
+    let text_of = |node: roxmltree::Node| -> String {
+        node.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect()
+    };
+    let mut units = Vec::new();
+    for unit in doc.descendants().filter(|n| n.tag_name().name() == "trans-unit") {
+        let id = unit.attribute("id").context("Invalid XLIFF: trans-unit without an id")?;
+        let child = |name: &str| unit.children().find(|n| n.tag_name().name() == name);
+        units.push(TranslatedUnit {
+            id: id.to_string(),
+            source: child("source").map(text_of).unwrap_or_default(),
+            target: child("target").map(text_of).filter(|t| !t.trim().is_empty()),
+        });
+    }
+    Ok(units)
+}
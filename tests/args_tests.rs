@@ -30,7 +30,7 @@ fn parses_flags_and_lists() {
         "10",
     ];
 
-    let args = Args::parse_from(&argv);
+    let args = Args::parse_from(argv);
     assert!(args.readonly);
     assert!(args.sandbox);
     assert!(args.no_external_tools);
@@ -98,3 +98,122 @@ fn parses_from_environment() {
     let bl = cfg.command_blacklist.unwrap();
     assert!(bl.contains("save_document"));
 }
+
+#[test]
+fn parses_install_subcommand_and_merges_config() {
+    use docx_mcp::client_setup::{self, ClientKind};
+    use docx_mcp::security::CliCommand;
+    reset_env();
+
+    let args = Args::parse_from([
+        "docx-mcp", "install", "--client", "claude-desktop", "--env", "DOCX_MCP_READONLY=true", "--", "--sandbox",
+    ]);
+    let Some(CliCommand::Install { client, name, env, server_args, .. }) = args.command else {
+        panic!("expected install subcommand");
+    };
+    assert_eq!(client, ClientKind::ClaudeDesktop);
+    assert_eq!(name, "docx-mcp");
+    assert_eq!(server_args, vec!["--sandbox".to_string()]);
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("claude_desktop_config.json");
+    std::fs::write(&path, r#"{"theme": "dark", "mcpServers": {"other": {"command": "/bin/other"}}}"#).unwrap();
+    let entry = client_setup::server_entry(client, std::path::Path::new("/opt/docx-mcp"), &server_args, client_setup::parse_env_pairs(&env).unwrap());
+    client_setup::install(&path, &name, entry, false).unwrap();
+
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written["theme"], "dark");
+    assert_eq!(written["mcpServers"]["other"]["command"], "/bin/other");
+    assert_eq!(written["mcpServers"]["docx-mcp"]["command"], "/opt/docx-mcp");
+    assert_eq!(written["mcpServers"]["docx-mcp"]["env"]["DOCX_MCP_READONLY"], "true");
+    assert!(dir.path().join("claude_desktop_config.json.bak").exists());
+}
+
+#[test]
+fn parses_fonts_subcommands() {
+    use docx_mcp::security::{CliCommand, FontsAction};
+    let args = Args::try_parse_from(["docx-mcp", "fonts", "add", "/tmp/MyFont.ttf"]).unwrap();
+    match args.command {
+        Some(CliCommand::Fonts { action: FontsAction::Add { path } }) => assert_eq!(path, std::path::PathBuf::from("/tmp/MyFont.ttf")),
+        other => panic!("unexpected command: {:?}", other),
+    }
+    let args = Args::try_parse_from(["docx-mcp", "fonts", "list"]).unwrap();
+    assert!(matches!(args.command, Some(CliCommand::Fonts { action: FontsAction::List })));
+}
+
+#[test]
+fn config_file_fills_settings_flags_do_not_give() {
+    reset_env();
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("docx-mcp.toml");
+    std::fs::write(&path, r#"
+[security]
+profile = "readonly"
+allowed_paths = ["docs"]
+external_tools = false
+
+[limits]
+max_open_documents = 7
+max_document_size = 2048
+
+[storage]
+compression_level = 9
+
+[content]
+clause_dir = "clauses"
+"#).unwrap();
+
+    let args = Args::load_from(["docx-mcp", "--config", path.to_str().unwrap(), "--max-docs", "3"]).unwrap();
+    assert!(args.readonly);
+    assert!(!args.sandbox);
+    assert!(args.no_external_tools);
+    assert_eq!(args.max_docs, Some(3), "the command line wins over the file");
+    assert_eq!(args.max_size, Some(2048));
+    assert_eq!(args.compression_level, 9);
+    assert_eq!(args.clause_dir, Some(dir.path().join("clauses")));
+    assert_eq!(args.allowed_paths, vec![dir.path().join("docs")]);
+
+    let cfg = SecurityConfig::from_args(args);
+    assert!(cfg.readonly_mode);
+    assert_eq!(cfg.max_open_documents, 3);
+    assert_eq!(cfg.allowed_paths.len(), 1);
+
+    // Without --config nothing changes
+    let args = Args::load_from(["docx-mcp"]).unwrap();
+    assert!(!args.readonly);
+    assert_eq!(args.compression_level, 6);
+
+    std::fs::write(&path, "[security]\nprofile = \"paranoid\"\n").unwrap();
+    assert!(Args::load_from(["docx-mcp", "--config", path.to_str().unwrap()]).is_err());
+    std::fs::write(&path, "[limits]\nmax_docs = 3\n").unwrap();
+    assert!(Args::load_from(["docx-mcp", "--config", path.to_str().unwrap()]).is_err());
+}
+
+#[test]
+fn config_file_api_tokens() {
+    reset_env();
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("docx-mcp.toml");
+    std::fs::write(&path, r#"
+[server]
+session_idle_timeout = 600
+metrics_public = true
+
+[[server.tokens]]
+name = "analyst"
+token = "analyst-token-0123456789"
+profile = "readonly"
+"#).unwrap();
+    let args = Args::load_from(["docx-mcp", "--config", path.to_str().unwrap()]).unwrap();
+    assert_eq!(args.tokens.len(), 1);
+    assert_eq!(args.session_idle_timeout, 600);
+    assert_eq!(args.max_sessions_per_token, 16);
+    assert!(args.metrics_public);
+    assert!(!format!("{:?}", args.tokens).contains("analyst-token"), "tokens must not show in debug output");
+    let restrictions = args.tokens[0].restrictions();
+    assert!(restrictions.readonly_mode);
+    assert!(!restrictions.is_command_allowed("add_paragraph"));
+
+    std::fs::write(&path, "[[server.tokens]]\nname = \"weak\"\ntoken = \"short\"\n").unwrap();
+    assert!(Args::load_from(["docx-mcp", "--config", path.to_str().unwrap()]).is_err());
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use docx_mcp::docx_handler::{DocxHandler, DocxStyle, TableData};
 use docx_mcp::pure_converter::PureRustConverter;
 use tempfile::TempDir;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::fs;
 use pretty_assertions::assert_eq;
 use rstest::*;
@@ -28,6 +28,7 @@ fn setup_test_handler_with_content() -> (DocxHandler, String, TempDir) {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, table_data).unwrap();
     
@@ -46,9 +47,8 @@ fn setup_test_handler_with_content() -> (DocxHandler, String, TempDir) {
 
 #[test]
 fn test_pure_converter_creation() {
-    let converter = PureRustConverter::new();
     // Just verify it can be created without panicking
-    assert!(true);
+    let _converter = PureRustConverter::new();
 }
 
 #[test]
@@ -73,7 +73,7 @@ fn test_extract_text_from_docx() -> Result<()> {
 
 #[test]
 fn test_extract_text_empty_document() -> Result<()> {
-    let temp_dir = TempDir::new().unwrap();
+    let _temp_dir = TempDir::new().unwrap();
     let mut handler = DocxHandler::new().unwrap();
     let doc_id = handler.create_document().unwrap();
     
@@ -130,6 +130,7 @@ fn test_convert_docx_to_pdf_with_complex_content() -> Result<()> {
         color: Some("#FF0000".to_string()),
         alignment: Some("center".to_string()),
         line_spacing: Some(1.5),
+        ..Default::default()
     };
     handler.add_paragraph(&doc_id, "Bold and underlined text", Some(style))?;
     
@@ -352,7 +353,7 @@ fn test_large_document_conversion() -> Result<()> {
 
 #[test]
 fn test_text_extraction_accuracy() -> Result<()> {
-    let temp_dir = TempDir::new().unwrap();
+    let _temp_dir = TempDir::new().unwrap();
     let mut handler = DocxHandler::new().unwrap();
     let doc_id = handler.create_document().unwrap();
     
@@ -386,11 +387,7 @@ fn test_text_extraction_accuracy() -> Result<()> {
     let extracted_words = extracted_text.split_whitespace().count();
     
     // Should be approximately equal (allowing for minor differences)
-    let word_diff = if extracted_words > expected_words {
-        extracted_words - expected_words
-    } else {
-        expected_words - extracted_words
-    };
+    let word_diff = extracted_words.abs_diff(expected_words);
     assert!(word_diff <= 5, "Word count difference too large: expected ~{}, got {}", expected_words, extracted_words);
     
     Ok(())
@@ -497,4 +494,231 @@ fn test_conversion_thread_safety() -> Result<()> {
     assert_eq!(pdf_count, 3);
     
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn test_font_listing_reads_family_names() {
+    use docx_mcp::fonts_cli::{add_font, font_info};
+    let info = font_info(Path::new("assets/fonts/LiberationSans-Bold.ttf")).unwrap();
+    assert!(info.valid);
+    assert_eq!(info.family.as_deref(), Some("Liberation Sans"));
+    assert_eq!(info.subfamily.as_deref(), Some("Bold"));
+
+    let temp_dir = TempDir::new().unwrap();
+    let bogus = temp_dir.path().join("bogus.ttf");
+    fs::write(&bogus, [0u8; 100]).unwrap();
+    assert!(!font_info(&bogus).unwrap().valid);
+    assert!(add_font(&bogus).is_err());
+}
+
+#[test]
+fn test_font_fallback_runs_and_cjk_wrapping() -> Result<()> {
+    use docx_mcp::font_fallback::{register_fallback, FallbackResolver, FontChoice, Script};
+    assert_eq!("japanese".parse::<Script>()?, Script::Kana);
+    assert!("klingon".parse::<Script>().is_err());
+    assert!(register_fallback(Script::Han, Path::new("README.md")).is_err());
+
+    let font = Path::new("assets/fonts/NotoSans-Regular.ttf");
+    register_fallback(Script::Han, font)?;
+    let runs = FallbackResolver::discover().split_runs("Total: 中文 ok");
+    assert_eq!(runs, vec![
+        (FontChoice::Builtin, "Total: ".to_string()),
+        (FontChoice::External(font.to_path_buf()), "中文 ".to_string()),
+        (FontChoice::Builtin, "ok".to_string()),
+    ]);
+
+    // 60 one-em ideographs with no spaces overflow the 170mm line and must wrap
+    let converter = PureRustConverter::new();
+    let lines = converter.layout_text(&"漢".repeat(60));
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].text.chars().count(), 43);
+
+    let temp_dir = TempDir::new()?;
+    let pdf_path = temp_dir.path().join("mixed.pdf");
+    converter.create_pdf_from_text("Mixed 中文 text", &pdf_path)?;
+    assert!(fs::metadata(&pdf_path)?.len() > 0);
+    Ok(())
+}
+
+#[test]
+fn test_fallback_fonts_are_subset_by_default() -> Result<()> {
+    use docx_mcp::pure_converter::PdfOptions;
+    // Cyrillic is outside Helvetica's range and drawn with the bundled Noto Sans
+    let text = "Отчёт за квартал";
+    let converter = PureRustConverter::new();
+    let temp_dir = TempDir::new()?;
+    let subset = temp_dir.path().join("subset.pdf");
+    let full = temp_dir.path().join("full.pdf");
+    converter.create_pdf_from_text_with_options(text, &subset, &PdfOptions::default())?;
+    converter.create_pdf_from_text_with_options(text, &full, &PdfOptions { subset_fonts: false, ..PdfOptions::default() })?;
+
+    let (subset_len, full_len) = (fs::metadata(&subset)?.len(), fs::metadata(&full)?.len());
+    assert!(subset_len * 4 < full_len, "subset {} vs full {}", subset_len, full_len);
+    Ok(())
+}
+
+#[test]
+fn test_justified_paragraphs_stretch_word_spacing() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let sentence = "The committee reviewed the quarterly figures and agreed on the revised budget. ";
+    let mut page_content = |alignment: &str| -> Result<String> {
+        let doc_id = handler.create_document()?;
+        let style = DocxStyle {
+            font_family: None,
+            font_size: None,
+            bold: None,
+            italic: None,
+            underline: None,
+            color: None,
+            alignment: Some(alignment.to_string()),
+            line_spacing: None,
+            ..Default::default()
+        };
+        handler.add_paragraph(&doc_id, &sentence.repeat(4), Some(style))?;
+        let pdf_path = temp_dir.path().join(format!("{}.pdf", alignment));
+        PureRustConverter::new().convert_docx_to_pdf(&handler.get_metadata(&doc_id)?.path, &pdf_path)?;
+        let pdf = lopdf::Document::load(&pdf_path)?;
+        let first_page = *pdf.get_pages().values().next().unwrap();
+        Ok(String::from_utf8_lossy(&pdf.get_page_content(first_page)?).into_owned())
+    };
+
+    let justified = page_content("justify")?;
+    // Every wrapped line but the last is stretched, then spacing is reset
+    assert_eq!(justified.matches(" Tw").count(), 2 * 3, "{}", justified);
+    assert!(!page_content("left")?.contains(" Tw"));
+    Ok(())
+}
+
+#[cfg(feature = "hyphenation")]
+#[test]
+fn test_hyphenation_patterns_break_long_words() {
+    use docx_mcp::hyphenation::{language_for_tag, Hyphenator};
+    // Liang's example patterns
+    let hyphenator = Hyphenator::from_patterns("en_US", "UTF-8\nLEFTHYPHENMIN 2\nRIGHTHYPHENMIN 3\nhy3ph\nhe2n\nhena4\nhen5at\n1na\nn2at\n1tio\n2io\no2n\n");
+    assert_eq!(hyphenator.hyphenate("hyphenation"), vec![2, 6]);
+    assert_eq!(hyphenator.hyphenate("Hyphenation"), vec![2, 6]);
+    assert!(hyphenator.hyphenate("hyp").is_empty());
+    assert_eq!(language_for_tag("en-US"), Some("en_US"));
+    assert_eq!(language_for_tag("de"), Some("de_DE"));
+    assert_eq!(language_for_tag("xx-YY"), None);
+
+    let converter = PureRustConverter::new();
+    let breaks = |w: &str| hyphenator.hyphenate(w);
+    // 81 x's leave room on the line for "hy-" but not "hyphen-"
+    let text = format!("{} hyphenation", "x".repeat(81));
+    let lines = converter.layout_text_with(&text, Some(&breaks));
+    assert_eq!(lines[0].text, format!("{} hy-", "x".repeat(81)));
+    assert_eq!(lines[1].text, "phenation");
+    assert_eq!(converter.layout_text(&text)[1].text, "hyphenation");
+}
+
+#[test]
+fn test_pdf_draws_headers_footers_page_numbers_and_footnotes() -> Result<()> {
+    use std::io::Write;
+    let temp_dir = TempDir::new()?;
+    let docx_path = temp_dir.path().join("bands.docx");
+    let w = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships""#;
+    let body = "<w:p><w:r><w:t>Filler paragraph</w:t></w:r></w:p>".repeat(60);
+    let parts = [
+        ("word/document.xml".to_string(), format!(
+            r#"<w:document {w}><w:body><w:p><w:r><w:t>Cited claim</w:t></w:r><w:r><w:footnoteReference w:id="2"/></w:r></w:p>{body}<w:sectPr><w:headerReference w:type="default" r:id="rIdH"/><w:footerReference w:type="default" r:id="rIdF"/></w:sectPr></w:body></w:document>"#
+        )),
+        ("word/_rels/document.xml.rels".to_string(), r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rIdH" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/><Relationship Id="rIdF" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer" Target="footer1.xml"/></Relationships>"#.to_string()),
+        ("word/header1.xml".to_string(), format!(r#"<w:hdr {w}><w:p><w:r><w:t>Confidential</w:t></w:r></w:p></w:hdr>"#)),
+        ("word/footer1.xml".to_string(), format!(
+            r#"<w:ftr {w}><w:p><w:pPr><w:jc w:val="center"/></w:pPr><w:r><w:t xml:space="preserve">Page </w:t></w:r><w:r><w:fldChar w:fldCharType="begin"/></w:r><w:r><w:instrText> PAGE </w:instrText></w:r><w:r><w:fldChar w:fldCharType="separate"/></w:r><w:r><w:t>9</w:t></w:r><w:r><w:fldChar w:fldCharType="end"/></w:r><w:r><w:t xml:space="preserve"> of </w:t></w:r><w:fldSimple w:instr=" NUMPAGES "><w:r><w:t>9</w:t></w:r></w:fldSimple></w:p></w:ftr>"#
+        )),
+        ("word/footnotes.xml".to_string(), format!(
+            r#"<w:footnotes {w}><w:footnote w:type="separator" w:id="-1"><w:p><w:r><w:separator/></w:r></w:p></w:footnote><w:footnote w:id="2"><w:p><w:r><w:footnoteRef/></w:r><w:r><w:t>Source: annual report.</w:t></w:r></w:p></w:footnote></w:footnotes>"#
+        )),
+    ];
+    let mut zip = zip::ZipWriter::new(fs::File::create(&docx_path)?);
+    for (name, xml) in &parts {
+        zip.start_file(name.as_str(), zip::write::FileOptions::default())?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    zip.finish()?;
+
+    let pdf_path = temp_dir.path().join("bands.pdf");
+    PureRustConverter::new().convert_docx_to_pdf(&docx_path, &pdf_path)?;
+    let pdf = lopdf::Document::load(&pdf_path)?;
+    let page_texts: Vec<String> = pdf.get_pages().values().map(|id| {
+        let content = lopdf::content::Content::decode(&pdf.get_page_content(*id).unwrap()).unwrap();
+        content.operations.iter()
+            .filter(|op| op.operator == "Tj")
+            .filter_map(|op| op.operands.first()?.as_str().ok().map(|b| String::from_utf8_lossy(b).into_owned()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }).collect();
+
+    assert_eq!(page_texts.len(), 2);
+    for (i, text) in page_texts.iter().enumerate() {
+        assert!(text.contains("Confidential"), "{}", text);
+        assert!(text.contains(&format!("Page {} of 2", i + 1)), "{}", text);
+        assert!(!text.contains('9'), "stale field results should be dropped: {}", text);
+    }
+    assert!(page_texts[0].contains("Cited claim[1]"));
+    assert!(page_texts[1].contains("[1] Source: annual report."));
+    // Plain text extraction is unchanged
+    assert!(!PureRustConverter::new().extract_text_from_docx(&docx_path)?.contains("[1]"));
+    Ok(())
+}
+
+#[test]
+fn test_extract_text_scope_adds_headers_notes_comments_and_hidden_text() -> Result<()> {
+    use docx_mcp::pure_converter::TextScope;
+    use std::io::Write;
+    let temp_dir = TempDir::new()?;
+    let docx_path = temp_dir.path().join("review.docx");
+    let w = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships""#;
+    let parts = [
+        ("word/document.xml".to_string(), format!(concat!(
+            r#"<w:document {w}><w:body>"#,
+            r#"<w:p><w:commentRangeStart w:id="0"/><w:r><w:t>Revenue grew</w:t></w:r><w:commentRangeEnd w:id="0"/><w:r><w:t xml:space="preserve"> strongly</w:t></w:r><w:r><w:footnoteReference w:id="2"/></w:r></w:p>"#,
+            r#"<w:p><w:r><w:t xml:space="preserve">Visible </w:t></w:r><w:r><w:rPr><w:vanish/></w:rPr><w:t>secret</w:t></w:r></w:p>"#,
+            r#"<w:sectPr><w:headerReference w:type="default" r:id="rIdH"/><w:footerReference w:type="default" r:id="rIdF"/><w:headerReference w:type="first" r:id="rIdH1"/></w:sectPr>"#,
+            r#"</w:body></w:document>"#), w = w)),
+        ("word/_rels/document.xml.rels".to_string(), r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rIdH" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/><Relationship Id="rIdH1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header2.xml"/><Relationship Id="rIdF" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer" Target="footer1.xml"/></Relationships>"#.to_string()),
+        ("word/header1.xml".to_string(), format!(r#"<w:hdr {w}><w:p><w:r><w:t>Acme Ltd</w:t></w:r></w:p></w:hdr>"#)),
+        ("word/header2.xml".to_string(), format!(r#"<w:hdr {w}><w:p><w:r><w:t>Cover</w:t></w:r></w:p></w:hdr>"#)),
+        ("word/footer1.xml".to_string(), format!(r#"<w:ftr {w}><w:p><w:r><w:t>Confidential</w:t></w:r></w:p></w:ftr>"#)),
+        ("word/footnotes.xml".to_string(), format!(r#"<w:footnotes {w}><w:footnote w:id="2"><w:p><w:r><w:t>Audited figures.</w:t></w:r></w:p></w:footnote></w:footnotes>"#)),
+        ("word/comments.xml".to_string(), format!(r#"<w:comments {w}><w:comment w:id="0" w:author="Jane Doe"><w:p><w:r><w:t>Source?</w:t></w:r></w:p></w:comment></w:comments>"#)),
+    ];
+    let mut zip = zip::ZipWriter::new(fs::File::create(&docx_path)?);
+    for (name, xml) in &parts {
+        zip.start_file(name.as_str(), zip::write::FileOptions::default())?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    zip.finish()?;
+
+    let converter = PureRustConverter::new();
+    let body = converter.extract_text_with_scope(&docx_path, &TextScope::default())?;
+    assert_eq!(body, "Revenue grew strongly\nVisible");
+
+    let everything = TextScope { headers: true, footnotes: true, comments: true, hidden: true };
+    let text = converter.extract_text_with_scope(&docx_path, &everything)?;
+    assert_eq!(text, concat!(
+        "Header: Acme Ltd\nHeader (first page): Cover\n\n",
+        "Revenue grew strongly[1]\nVisible secret\n\n__________\n[1] Audited figures.\n\n",
+        "Footer: Confidential\n\n",
+        "Comments:\n[Jane Doe] on \"Revenue grew\": Source?",
+    ));
+    // The plain extraction used elsewhere is unchanged
+    assert!(converter.extract_text_from_docx(&docx_path)?.contains("secret"));
+    Ok(())
+}
+
+#[test]
+fn test_page_preview_draws_text_lines() -> Result<()> {
+    let (handler, doc_id, _temp_dir) = setup_test_handler_with_content();
+    let path = handler.get_metadata(&doc_id)?.path;
+    let converter = PureRustConverter::new();
+    let preview = converter.render_page_preview(&path, 0, 210)?.to_rgba8();
+    assert_eq!(preview.dimensions(), (210, 297));
+    // Lines start at the 20mm margin; the margin itself stays white
+    assert!(preview.pixels().any(|p| p.0[0] < 200));
+    assert!((0..297).all(|y| preview.get_pixel(5, y).0 == [255, 255, 255, 255]));
+    assert!(converter.render_page_preview(&path, 5, 210).is_err());
+    Ok(())
+}
@@ -1,7 +1,6 @@
-use anyhow::Result;
-use docx_mcp::docx_handler::{DocxHandler, DocxStyle, TableData};
+use docx_mcp::docx_handler::{DocumentFilter, DocxHandler, DocxStyle, TableData, TableMerge};
+use docx_mcp::locale::Formatting;
 use tempfile::TempDir;
-use std::path::PathBuf;
 use pretty_assertions::assert_eq;
 use rstest::*;
 use chrono::Utc;
@@ -59,6 +58,7 @@ fn test_add_paragraph_with_style() {
         color: Some("#FF0000".to_string()),
         alignment: Some("center".to_string()),
         line_spacing: Some(1.5),
+        ..Default::default()
     };
     
     let result = handler.add_paragraph(&doc_id, "Styled paragraph", Some(style));
@@ -97,6 +97,7 @@ fn test_add_table() {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     
     let result = handler.add_table(&doc_id, table_data);
@@ -219,6 +220,61 @@ fn test_list_documents() {
     assert_eq!(docs.len(), initial_count + 3);
 }
 
+#[test]
+fn test_clone_document_branches_independently() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Shared intro", None).unwrap();
+    handler.rename_document(&doc_id, "Board Report").unwrap();
+
+    let copy = handler.clone_document(&doc_id, None).unwrap();
+    assert_ne!(copy, doc_id);
+    assert_eq!(handler.documents[&copy].title.as_deref(), Some("Board Report (copy)"));
+    handler.add_paragraph(&copy, "Exec summary only", None).unwrap();
+
+    assert!(handler.extract_text(&copy).unwrap().contains("Shared intro"));
+    assert!(handler.extract_text(&copy).unwrap().contains("Exec summary only"));
+    assert!(!handler.extract_text(&doc_id).unwrap().contains("Exec summary only"));
+    assert!(handler.clone_document("missing", None).is_err());
+}
+
+#[test]
+fn test_document_summaries_page_filter_and_dirty() {
+    let (mut handler, temp_dir) = setup_test_handler();
+    let first = handler.create_document().unwrap();
+    handler.add_heading(&first, "Intro", 1).unwrap();
+    handler.add_paragraph(&first, "three words here", None).unwrap();
+    let second = handler.create_document().unwrap();
+    let third = handler.create_document().unwrap();
+    handler.documents.get_mut(&second).unwrap().title = Some("Quarterly Report".to_string());
+
+    let (total, page) = handler.document_summaries(&DocumentFilter::default(), 0, Some(2)).unwrap();
+    assert_eq!(total, 3);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0]["id"], first.as_str());
+    assert_eq!(page[0]["headings"], 1);
+    assert_eq!(page[0]["word_count"], 4);
+    assert_eq!(page[0]["dirty"], true);
+    assert_eq!(page[1]["dirty"], false);
+    assert!(page[0]["size_bytes"].as_u64().unwrap() > 0);
+    let (_, rest) = handler.document_summaries(&DocumentFilter::default(), 2, Some(2)).unwrap();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0]["id"], third.as_str());
+
+    let by_title = DocumentFilter { title_contains: Some("quarterly".into()), ..Default::default() };
+    let (total, page) = handler.document_summaries(&by_title, 0, None).unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(page[0]["id"], second.as_str());
+
+    let created = handler.documents[&third].created_at;
+    let recent = DocumentFilter { created_after: Some(created), ..Default::default() };
+    assert_eq!(handler.document_summaries(&recent, 0, None).unwrap().0, 1);
+
+    handler.save_document(&first, &temp_dir.path().join("first.docx")).unwrap();
+    let (_, page) = handler.document_summaries(&DocumentFilter::default(), 0, Some(1)).unwrap();
+    assert_eq!(page[0]["dirty"], false);
+    assert!(page[0]["saved_at"].is_string());
+}
+
 #[test]
 fn test_document_not_found_error() {
     let (handler, _temp_dir) = setup_test_handler();
@@ -314,4 +370,1395 @@ fn test_special_characters_in_content() {
     let text = handler.extract_text(&doc_id).unwrap();
     assert!(text.contains("éñüñdéd"));
     assert!(text.contains("🚀📝✨"));
-}
\ No newline at end of file
+}
+fn write_minimal_xlsx(path: &std::path::Path) {
+    use std::io::Write;
+    let file = std::fs::File::create(path).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default();
+    let parts: [(&str, &str); 4] = [
+        ("[Content_Types].xml", r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#),
+        ("xl/workbook.xml", r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Q1" sheetId="1" r:id="rId1"/></sheets></workbook>"#),
+        ("xl/_rels/workbook.xml.rels", r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#),
+        ("xl/worksheets/sheet1.xml", r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData><row r="1"><c r="A1" t="inlineStr"><is><t>Region</t></is></c><c r="B1" t="inlineStr"><is><t>Revenue</t></is></c></row><row r="2"><c r="A2" t="inlineStr"><is><t>North</t></is></c><c r="B2"><v>1200</v></c></row><row r="3"><c r="A3" t="inlineStr"><is><t>South</t></is></c><c r="B3"><v>950</v></c></row></sheetData></worksheet>"#),
+    ];
+    for (name, body) in parts {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(body.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+#[test]
+fn test_add_table_from_xlsx() {
+    let (mut handler, doc_id, temp_dir) = handler_and_doc();
+    let xlsx = temp_dir.path().join("figures.xlsx");
+    write_minimal_xlsx(&xlsx);
+
+    let rows = handler.add_table_from_xlsx(&doc_id, &xlsx, Some("Q1"), Some("A1:B2"), true).unwrap();
+    assert_eq!(rows, 1);
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Revenue"));
+    assert!(text.contains("North"));
+    assert!(!text.contains("South"));
+
+    // A whole-sheet range only reads the cells that exist
+    let rows = handler.add_table_from_xlsx(&doc_id, &xlsx, Some("Q1"), Some("A1:XFD1048576"), true).unwrap();
+    assert_eq!(rows, 2);
+    assert!(handler.extract_text(&doc_id).unwrap().contains("South"));
+
+    assert!(handler.add_table_from_xlsx(&doc_id, &xlsx, Some("Missing"), None, true).is_err());
+}
+
+#[test]
+fn test_add_table_from_xlsx_rejects_columns_past_xfd() {
+    let (mut handler, doc_id, temp_dir) = handler_and_doc();
+    let xlsx = temp_dir.path().join("figures.xlsx");
+    write_minimal_xlsx(&xlsx);
+
+    // Long column names used to overflow the column number
+    for range in ["AAAAAAAA1", "A1:XFE1", "A1:ZZZZZZZZZZZZ9"] {
+        let err = handler.add_table_from_xlsx(&doc_id, &xlsx, Some("Q1"), Some(range), true).unwrap_err();
+        assert!(err.to_string().contains("Invalid cell reference"), "{}: {}", range, err);
+    }
+    assert_eq!(handler.add_table_from_xlsx(&doc_id, &xlsx, Some("Q1"), Some("A1:XFD2"), true).unwrap(), 1);
+}
+
+#[test]
+fn test_summarize_changes_since_snapshot() {
+    use docx_mcp::docx_handler::RangeId;
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Introduction", 1).unwrap();
+    handler.add_paragraph(&doc_id, "This proposal covers the pilot.", None).unwrap();
+    handler.add_heading(&doc_id, "Terms", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Pay in 30 days.", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["Item".into(), "Cost".into()], vec!["Pilot".into(), "100".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+    handler.add_heading(&doc_id, "Appendix", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Old notes.", None).unwrap();
+
+    let snapshot = handler.snapshot_document(&doc_id, Some("sent-to-legal")).unwrap();
+    let unchanged = handler.summarize_changes(&doc_id, snapshot["snapshot_id"].as_str().unwrap()).unwrap();
+    assert_eq!(unchanged["changed"], false);
+
+    handler.replace_range_text(&doc_id, &RangeId::Paragraph { index: 1 }, "Pay within 45 days of invoice.").unwrap();
+    handler.set_table_cell_text(&doc_id, 0, 1, 1, "120").unwrap();
+    handler.replace_range_text(&doc_id, &RangeId::Paragraph { index: 0 }, "This proposal covers the rollout.").unwrap();
+    handler.delete_section(&doc_id, Some("Appendix"), None).unwrap();
+    handler.add_heading(&doc_id, "Pricing", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Prices are fixed for a year.", None).unwrap();
+
+    let log = handler.summarize_changes(&doc_id, "sent-to-legal").unwrap();
+    assert_eq!(log["changed"], true);
+    assert_eq!(log["sections_added"][0]["heading"], "Pricing");
+    assert_eq!(log["sections_removed"][0]["heading"], "Appendix");
+    let changed = log["sections_changed"].as_array().unwrap();
+    assert_eq!(changed.len(), 2);
+    assert_eq!(changed[0]["heading"], "Introduction");
+    assert_eq!(changed[0]["word_delta"], 0);
+    assert_eq!(changed[0]["text_edited"], true);
+    assert_eq!(changed[1]["word_delta"], 2);
+    assert_eq!(changed[1]["tables_modified"][0]["table"], 1);
+
+    let text = log["text"].as_str().unwrap();
+    assert!(text.starts_with("Changes since snapshot \"sent-to-legal\""), "{}", text);
+    assert!(text.contains("- Added section \"Pricing\" (6 words)"), "{}", text);
+    assert!(text.contains("- Removed section \"Appendix\" (2 words)"), "{}", text);
+    assert!(text.contains("- \"Terms\": +2 words (8 → 10), table 1 modified (cells edited)"), "{}", text);
+
+    assert!(handler.summarize_changes(&doc_id, "no-such-snapshot").is_err());
+    handler.close_document(&doc_id).unwrap();
+    assert!(handler.summarize_changes(&doc_id, "sent-to-legal").is_err());
+}
+
+#[test]
+fn test_snapshots_spill_and_are_capped() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path()).unwrap();
+    let doc_id = handler.create_document().unwrap();
+    handler.set_max_document_memory(Some(256));
+    handler.add_heading(&doc_id, "Terms", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Pay in 30 days.", None).unwrap();
+    let spill_file = temp_dir.path().join("docx-mcp").join("spill").join(format!("{}.spill", doc_id));
+
+    // The copy goes to the spill file even while the document itself fits in memory
+    let first = handler.snapshot_document(&doc_id, Some("draft")).unwrap();
+    assert!(!handler.is_spilled(&doc_id));
+    assert!(spill_file.exists());
+    for i in 0..10 {
+        handler.add_paragraph(&doc_id, &format!("Clause {} with enough text to pass the limit.", i), None).unwrap();
+    }
+    assert!(handler.is_spilled(&doc_id));
+    let log = handler.summarize_changes(&doc_id, "draft").unwrap();
+    assert_eq!(log["sections_changed"][0]["heading"], "Terms");
+
+    let mut last = serde_json::Value::Null;
+    for _ in 0..20 {
+        last = handler.snapshot_document(&doc_id, None).unwrap();
+    }
+    assert_eq!(last["dropped_snapshot_id"], first["snapshot_id"]);
+    assert!(handler.summarize_changes(&doc_id, "draft").is_err());
+    assert_eq!(handler.summarize_changes(&doc_id, last["snapshot_id"].as_str().unwrap()).unwrap()["changed"], false);
+
+    handler.close_document(&doc_id).unwrap();
+    assert!(!spill_file.exists());
+}
+
+#[test]
+fn test_find_and_replace_scope() {
+    use docx_mcp::docx_handler::{ReplaceOptions, ReplaceScope};
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Introduction", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Acme supplies widgets.", None).unwrap();
+    handler.add_heading(&doc_id, "Terms", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Acme pays on delivery.", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["Party".into()], vec!["Acme".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+    handler.add_heading(&doc_id, "Appendix", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Contact Acme.", None).unwrap();
+    handler.set_header(&doc_id, "Acme Ltd").unwrap();
+
+    let mut replace = |scope: ReplaceScope| {
+        let options = ReplaceOptions { case_sensitive: true, scope, ..Default::default() };
+        handler.find_and_replace_with(&doc_id, "Acme", "Globex", &options)
+    };
+    assert_eq!(replace(ReplaceScope::Tables).unwrap(), 1);
+    assert_eq!(replace(ReplaceScope::Headers).unwrap(), 1);
+    assert_eq!(replace(ReplaceScope::Heading("Terms".into())).unwrap(), 1);
+    assert_eq!(replace(ReplaceScope::ElementRange { start: 0, end: 1 }).unwrap(), 1);
+    assert!(replace(ReplaceScope::ElementRange { start: 3, end: 99 }).is_err());
+    assert!(replace(ReplaceScope::Heading("Pricing".into())).is_err());
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Globex supplies widgets."), "{}", text);
+    assert!(text.contains("Globex pays on delivery."), "{}", text);
+    assert!(text.contains("Contact Acme."), "{}", text);
+    assert_eq!(handler.find_and_replace_advanced(&doc_id, "Acme", "Globex", true, false, false).unwrap(), 1);
+
+    // Whole-word bounds every alternative of a regex
+    handler.add_paragraph(&doc_id, "cat catalog dog doghouse", None).unwrap();
+    let options = ReplaceOptions { whole_word: true, use_regex: true, ..Default::default() };
+    assert_eq!(handler.find_and_replace_with(&doc_id, "cat|dog", "pet", &options).unwrap(), 2);
+    assert!(handler.extract_text(&doc_id).unwrap().contains("pet catalog pet doghouse"));
+}
+
+#[test]
+fn test_replace_with_captures_and_preserved_case() {
+    use docx_mcp::docx_handler::ReplaceOptions;
+    use docx_mcp::text_transform::match_case;
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "ACME SERVICES AGREEMENT", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Acme Corp (\"acme\") signed on 2024-03-15 for $100.", None).unwrap();
+
+    let options = ReplaceOptions { preserve_case: true, ..Default::default() };
+    assert_eq!(handler.find_and_replace_with(&doc_id, "acme", "globex", &options).unwrap(), 3);
+    let options = ReplaceOptions { use_regex: true, ..Default::default() };
+    assert_eq!(handler.find_and_replace_with(&doc_id, r"(\d{4})-(\d{2})-(\d{2})", "$3/$2/$1", &options).unwrap(), 1);
+    assert_eq!(handler.find_and_replace_with(&doc_id, r"\$(?P<amount>\d+)", "USD ${amount}", &options).unwrap(), 1);
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("GLOBEX SERVICES AGREEMENT"), "{}", text);
+    assert!(text.contains("Globex Corp (\"globex\") signed on 15/03/2024 for USD 100."), "{}", text);
+
+    assert_eq!(match_case("Acme Corp", "globex inc"), "Globex Inc");
+    assert_eq!(match_case("AcMe", "globex"), "globex");
+    assert_eq!(match_case("2024", "Globex"), "Globex");
+}
+
+#[test]
+fn test_replace_in_links_alt_text_bookmarks_and_properties() {
+    use docx_mcp::docx_handler::{ReplaceOptions, ReplaceTarget};
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Acme Overview", 1).unwrap();
+    handler.insert_bookmark_after_heading(&doc_id, "Acme Overview", "acme_overview").unwrap();
+    handler.add_hyperlink(&doc_id, "Visit us", "https://www.acme.com/products").unwrap();
+    let mut png = Vec::new();
+    ::image::DynamicImage::ImageRgba8(::image::RgbaImage::new(8, 8))
+        .write_to(&mut std::io::Cursor::new(&mut png), ::image::ImageFormat::Png).unwrap();
+    handler.add_image(&doc_id, docx_mcp::docx_handler::ImageData { data: png, width: Some(8), height: Some(8), alt_text: Some("Acme logo".into()) }).unwrap();
+    handler.set_document_properties(&doc_id, Some("Acme Overview".into()), None, Some("Acme Marketing".into())).unwrap();
+
+    // Only body text unless asked
+    let body_only = handler.preview_replacements(&doc_id, "acme", "globex", &ReplaceOptions::default()).unwrap();
+    assert_eq!(body_only.len(), 1);
+
+    let options = ReplaceOptions {
+        preserve_case: true,
+        include: vec![ReplaceTarget::HyperlinkUrls, ReplaceTarget::AltText, ReplaceTarget::BookmarkNames, ReplaceTarget::Properties],
+        ..Default::default()
+    };
+    let preview = handler.preview_replacements(&doc_id, "acme", "globex", &options).unwrap();
+    let kinds: Vec<&str> = preview.iter().map(|r| r.kind.as_str()).collect();
+    assert_eq!(kinds, ["heading", "bookmark_name", "hyperlink_url", "image_alt_text", "property", "property"]);
+    assert_eq!(preview[4].property.as_deref(), Some("title"));
+    assert_eq!(preview[4].element_index, None);
+
+    assert_eq!(handler.find_and_replace_with(&doc_id, "acme", "globex", &options).unwrap(), 6);
+    let ops = handler.export_ops(&doc_id).unwrap();
+    assert!(ops.contains("https://www.globex.com/products"), "{}", ops);
+    assert!(ops.contains("globex_overview"), "{}", ops);
+    assert!(ops.contains("Globex logo"), "{}", ops);
+    let properties = handler.get_document_properties_json(&doc_id).unwrap();
+    assert_eq!(properties["title"], "Globex Overview");
+    assert_eq!(properties["author"], "Globex Marketing");
+}
+
+#[test]
+fn test_bookmark_renames_keep_names_valid() {
+    use docx_mcp::docx_handler::{ReplaceOptions, ReplaceTarget};
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Overview", 1).unwrap();
+    handler.insert_bookmark_after_heading(&doc_id, "Overview", "acme_overview").unwrap();
+    let options = ReplaceOptions { include: vec![ReplaceTarget::BookmarkNames], ..Default::default() };
+
+    // Whitespace, a leading digit or more than 40 characters would break the bookmark
+    let long = "x".repeat(40);
+    for bad in ["Big Co", "9co", long.as_str()] {
+        assert!(handler.preview_replacements(&doc_id, "acme", bad, &options).unwrap().is_empty(), "{}", bad);
+        assert_eq!(handler.find_and_replace_with(&doc_id, "acme", bad, &options).unwrap(), 0);
+    }
+    assert!(handler.export_ops(&doc_id).unwrap().contains("acme_overview"));
+
+    assert_eq!(handler.find_and_replace_with(&doc_id, "acme", "globex", &options).unwrap(), 1);
+    assert!(handler.export_ops(&doc_id).unwrap().contains("globex_overview"));
+}
+
+#[test]
+fn test_outline_tree_and_section_text() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Introduction", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Intro body.", None).unwrap();
+    handler.add_heading(&doc_id, "Scope", 2).unwrap();
+    handler.add_paragraph(&doc_id, "Scope body.", None).unwrap();
+    handler.add_heading(&doc_id, "Results", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Results body.", None).unwrap();
+
+    let outline = handler.get_outline(&doc_id).unwrap();
+    let tree = outline["tree"].as_array().unwrap();
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree[0]["children"][0]["text"], "Scope");
+    assert_eq!(tree[1]["element_index"], 4);
+
+    let section = handler.get_section_text(&doc_id, Some("introduction"), None, true).unwrap();
+    let text = section["text"].as_str().unwrap();
+    assert!(text.contains("Intro body.") && text.contains("Scope body."));
+    assert!(!text.contains("Results body."));
+
+    let shallow = handler.get_section_text(&doc_id, None, Some(0), false).unwrap();
+    assert_eq!(shallow["text"], "Intro body.");
+    assert!(handler.get_section_text(&doc_id, Some("Missing"), None, true).is_err());
+}
+
+#[test]
+fn test_section_text_leaves_out_headers_and_footers() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Terms", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Terms body.", None).unwrap();
+    handler.set_header(&doc_id, "Confidential").unwrap();
+    handler.set_footer(&doc_id, "Page footer").unwrap();
+    handler.add_heading(&doc_id, "Annex", 1).unwrap();
+
+    let section = handler.get_section_text(&doc_id, Some("Terms"), None, true).unwrap();
+    assert_eq!(section["text"], "Terms body.");
+    assert_eq!(section["elements"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_create_outline_skeleton() {
+    use docx_mcp::docx_handler::OutlineNode;
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    let outline: Vec<OutlineNode> = serde_json::from_value(serde_json::json!([
+        {"title": "Introduction", "children": [{"title": "Scope", "placeholder": "Who this is for"}]},
+        {"title": "Results", "placeholder": ""}
+    ])).unwrap();
+    assert_eq!(handler.create_outline(&doc_id, &outline, true).unwrap(), 3);
+
+    let tree = handler.get_outline(&doc_id).unwrap()["tree"].clone();
+    assert_eq!(tree[0]["level"], 1);
+    assert_eq!(tree[0]["children"][0]["text"], "Scope");
+    assert_eq!(tree[0]["children"][0]["level"], 2);
+    let ops = handler.export_ops(&doc_id).unwrap();
+    assert!(ops.contains("Toc") && ops.contains("[Introduction]") && ops.contains("Who this is for"), "{}", ops);
+    assert!(!ops.contains("[Results]"), "{}", ops);
+
+    // The tree from get_outline drafts the same structure elsewhere
+    let copy = handler.create_document().unwrap();
+    let same: Vec<OutlineNode> = serde_json::from_value(tree).unwrap();
+    assert_eq!(handler.create_outline(&copy, &same, false).unwrap(), 3);
+
+    let too_deep = (0..7).fold(serde_json::json!([]), |children, n| serde_json::json!([{"title": format!("Level {}", 7 - n), "children": children}]));
+    let too_deep: Vec<OutlineNode> = serde_json::from_value(too_deep).unwrap();
+    assert!(handler.create_outline(&doc_id, &too_deep, false).is_err());
+    assert!(handler.create_outline(&doc_id, &[], false).is_err());
+}
+
+#[test]
+fn test_set_section_content_keeps_heading_and_subsections() {
+    use docx_mcp::docx_handler::{NewElement, OutlineNode};
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    let outline: Vec<OutlineNode> = serde_json::from_value(serde_json::json!([
+        {"title": "Introduction", "children": [{"title": "Scope"}]},
+        {"title": "Results"}
+    ])).unwrap();
+    handler.create_outline(&doc_id, &outline, false).unwrap();
+    handler.insert_bookmark_after_heading(&doc_id, "Introduction", "intro").unwrap();
+
+    let blocks: Vec<NewElement> = serde_json::from_value(serde_json::json!([
+        {"type": "paragraph", "text": "Why we did this."},
+        {"type": "list", "items": ["Faster", "Cheaper"]}
+    ])).unwrap();
+    assert_eq!(handler.set_section_content(&doc_id, Some("introduction"), None, blocks).unwrap(), 1);
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(!text.contains("[Introduction]"), "{}", text);
+    let order: Vec<usize> = ["Introduction", "Why we did this.", "Cheaper", "Scope", "[Scope]", "Results"].iter()
+        .map(|needle| text.find(needle).unwrap())
+        .collect();
+    assert!(order.windows(2).all(|w| w[0] < w[1]), "{}", text);
+    assert!(handler.export_ops(&doc_id).unwrap().contains(r#""intro""#));
+
+    // An empty list clears the section
+    assert_eq!(handler.set_section_content(&doc_id, Some("Results"), None, Vec::new()).unwrap(), 1);
+    assert!(!handler.extract_text(&doc_id).unwrap().contains("[Results]"));
+
+    let sibling = vec![NewElement::Heading { text: "Scope".into(), level: 2 }];
+    assert!(handler.set_section_content(&doc_id, Some("Scope"), None, sibling).is_err());
+    assert!(handler.set_section_content(&doc_id, Some("Missing"), None, Vec::new()).is_err());
+}
+
+#[test]
+fn test_move_section_with_subsections() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Introduction", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Intro body.", None).unwrap();
+    handler.add_heading(&doc_id, "Scope", 2).unwrap();
+    handler.add_paragraph(&doc_id, "Scope body.", None).unwrap();
+    handler.add_heading(&doc_id, "Results", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Results body.", None).unwrap();
+
+    let moved = handler.move_section(&doc_id, "Introduction", "after", "Results").unwrap();
+    assert_eq!(moved, 4);
+    let text = handler.extract_text(&doc_id).unwrap();
+    let order: Vec<usize> = ["Results body.", "Introduction", "Scope body."].iter()
+        .map(|needle| text.find(needle).unwrap())
+        .collect();
+    assert!(order.windows(2).all(|w| w[0] < w[1]), "{}", text);
+
+    assert!(handler.move_section(&doc_id, "Introduction", "before", "Scope").is_err());
+    handler.move_section(&doc_id, "scope", "before", "Results").unwrap();
+    let outline = handler.get_outline(&doc_id).unwrap();
+    assert_eq!(outline["tree"][0]["text"], "Scope");
+
+    assert!(handler.move_section(&doc_id, "Introduction", "after", "Missing").is_err());
+    assert!(handler.move_section(&doc_id, "Introduction", "inside", "Results").is_err());
+}
+
+#[test]
+fn test_delete_section_and_range() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.set_header(&doc_id, "Draft").unwrap();
+    handler.add_heading(&doc_id, "Introduction", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Intro body.", None).unwrap();
+    handler.add_heading(&doc_id, "Legacy", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Legacy body.", None).unwrap();
+    handler.add_heading(&doc_id, "Legacy detail", 2).unwrap();
+    handler.add_paragraph(&doc_id, "Legacy detail body.", None).unwrap();
+    handler.add_heading(&doc_id, "Results", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Results body.", None).unwrap();
+
+    assert_eq!(handler.delete_section(&doc_id, Some("legacy"), None).unwrap(), 4);
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(!text.contains("Legacy"));
+    assert!(text.contains("Intro body.") && text.contains("Results body."));
+
+    let outline = handler.get_outline(&doc_id).unwrap();
+    let intro = outline["outline"][0]["element_index"].as_u64().unwrap() as usize;
+    assert_eq!(handler.delete_range(&doc_id, intro, intro + 1).unwrap(), 2);
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(!text.contains("Intro"));
+    assert!(text.contains("Results body."));
+
+    assert!(handler.delete_range(&doc_id, 3, 1).is_err());
+    assert!(handler.delete_range(&doc_id, 0, 99).is_err());
+    assert!(handler.delete_section(&doc_id, Some("Missing"), None).is_err());
+}
+
+#[test]
+fn test_insert_at_anchors() {
+    use docx_mcp::docx_handler::{InsertAnchor, NewElement};
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Introduction", 1).unwrap();
+    handler.insert_bookmark_after_heading(&doc_id, "Introduction", "intro").unwrap();
+    handler.add_paragraph(&doc_id, "Intro body.", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["a".into(), "b".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    let elements: Vec<NewElement> = serde_json::from_value(serde_json::json!([
+        {"type": "heading", "text": "Summary", "level": 2},
+        {"type": "paragraph", "text": "Summary body."}
+    ])).unwrap();
+    let anchor: InsertAnchor = serde_json::from_value(serde_json::json!({"heading": "introduction"})).unwrap();
+    assert_eq!(handler.insert_at(&doc_id, &anchor, "after", elements).unwrap(), 2);
+
+    let note = vec![NewElement::Paragraph { text: "Table note.".into(), style: None }];
+    handler.insert_at(&doc_id, &InsertAnchor::TableIndex(0), "after", note).unwrap();
+    let lead = vec![NewElement::Paragraph { text: "Preface.".into(), style: None }];
+    assert_eq!(handler.insert_at(&doc_id, &InsertAnchor::Bookmark("intro".into()), "before", lead).unwrap(), 1);
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    let order: Vec<usize> = ["Preface.", "Summary", "Summary body.", "Intro body.", "Table note."].iter()
+        .map(|needle| text.find(needle).unwrap())
+        .collect();
+    assert!(order.windows(2).all(|w| w[0] < w[1]), "{}", text);
+
+    let page_break = || vec![NewElement::PageBreak];
+    assert!(handler.insert_at(&doc_id, &InsertAnchor::TableIndex(3), "before", page_break()).is_err());
+    assert!(handler.insert_at(&doc_id, &InsertAnchor::ElementId(99), "before", page_break()).is_err());
+    assert!(handler.insert_at(&doc_id, &InsertAnchor::ElementId(0), "inside", page_break()).is_err());
+    assert!(handler.insert_at(&doc_id, &InsertAnchor::ElementId(0), "after", vec![]).is_err());
+}
+
+#[test]
+fn test_fix_pagination_keeps_orphan_heading_with_next() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    let lines = docx_mcp::pure_converter::LAYOUT_LINES_PER_PAGE;
+    for i in 0..lines - 1 {
+        handler.add_paragraph(&doc_id, &format!("Line {}", i), None).unwrap();
+    }
+    handler.add_heading(&doc_id, "Stranded heading", 2).unwrap();
+    handler.add_paragraph(&doc_id, "First paragraph of the section.", None).unwrap();
+
+    let pagination = handler.get_pagination(&doc_id).unwrap();
+    assert_eq!(pagination["page_count"], 2);
+
+    let report = handler.fix_pagination(&doc_id).unwrap();
+    assert_eq!(report["fixed"], 1);
+    assert_eq!(report["fixes"][0]["problem"], "orphan_heading");
+    assert_eq!(report["fixes"][0]["fix"], "keep_with_next");
+    assert_eq!(report["fixes"][0]["element_index"], lines - 1);
+
+    let again = handler.fix_pagination(&doc_id).unwrap();
+    assert_eq!(again["fixed"], 0);
+}
+
+#[test]
+fn test_spilled_document_stays_editable() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.set_max_document_memory(Some(512));
+    handler.add_heading(&doc_id, "Findings", 1).unwrap();
+    assert!(!handler.is_spilled(&doc_id));
+
+    let mut png = Vec::new();
+    ::image::DynamicImage::ImageRgba8(::image::RgbaImage::new(40, 40))
+        .write_to(&mut std::io::Cursor::new(&mut png), ::image::ImageFormat::Png).unwrap();
+    handler.add_image(&doc_id, docx_mcp::docx_handler::ImageData { data: png, width: Some(40), height: Some(40), alt_text: Some("chart".into()) }).unwrap();
+    for i in 0..10 {
+        handler.add_paragraph(&doc_id, &format!("Finding number {} with enough text to pass the limit.", i), None).unwrap();
+    }
+    assert!(handler.is_spilled(&doc_id));
+
+    // Reads work from the spill file, edits bring the ops back and spill them again
+    assert_eq!(handler.get_outline(&doc_id).unwrap()["tree"][0]["text"], "Findings");
+    assert_eq!(handler.list_images(&doc_id).unwrap()["images"][0]["alt_text"], "chart");
+    handler.add_heading(&doc_id, "Appendix", 1).unwrap();
+    assert!(handler.is_spilled(&doc_id));
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Finding number 9") && text.contains("Appendix"), "{}", text);
+
+    let path = handler.get_metadata(&doc_id).unwrap().path;
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let media: Vec<String> = zip.file_names().filter(|n| n.starts_with("word/media/") && !n.ends_with('/')).map(String::from).collect();
+    assert_eq!(media.len(), 1);
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut zip.by_name(&media[0]).unwrap(), &mut bytes).unwrap();
+    assert_eq!(::image::load_from_memory(&bytes).unwrap().width(), 40);
+    assert!(handler.get_storage_info().unwrap()["storage"]["spilled_documents"].as_u64().unwrap() >= 1);
+
+    handler.close_document(&doc_id).unwrap();
+    assert!(!handler.is_spilled(&doc_id));
+}
+
+#[test]
+fn test_unreadable_spill_file_is_an_error_not_a_panic() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path()).unwrap();
+    let doc_id = handler.create_document().unwrap();
+    handler.set_max_document_memory(Some(256));
+    for i in 0..10 {
+        handler.add_paragraph(&doc_id, &format!("Paragraph {} with enough text to pass the limit.", i), None).unwrap();
+    }
+    assert!(handler.is_spilled(&doc_id));
+
+    let spill_file = temp_dir.path().join("docx-mcp").join("spill").join(format!("{}.spill", doc_id));
+    std::fs::write(&spill_file, "not json").unwrap();
+    let err = handler.add_paragraph(&doc_id, "More", None).unwrap_err();
+    assert!(format!("{:#}", err).contains("Invalid spill file"), "{:#}", err);
+    assert!(handler.set_header(&doc_id, "Header").is_err());
+    // Reads report the broken file rather than missing ops
+    let err = handler.get_outline(&doc_id).unwrap_err();
+    assert!(format!("{:#}", err).contains("Invalid spill file"), "{:#}", err);
+
+    // Other documents are unaffected
+    let other = handler.create_document().unwrap();
+    handler.add_paragraph(&other, "Still works", None).unwrap();
+}
+
+#[test]
+fn test_spilled_ops_are_written_once() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path()).unwrap();
+    let doc_id = handler.create_document().unwrap();
+    handler.set_max_document_memory(Some(256));
+    for i in 0..20 {
+        handler.add_paragraph(&doc_id, &format!("Paragraph {} with enough text to pass the limit.", i), None).unwrap();
+    }
+    assert!(handler.is_spilled(&doc_id));
+    let spill_file = temp_dir.path().join("docx-mcp").join("spill").join(format!("{}.spill", doc_id));
+    let size = std::fs::metadata(&spill_file).unwrap().len();
+
+    // Appending adds one record; an edit elsewhere rewrites only the op it changed
+    handler.add_paragraph(&doc_id, "Appended", None).unwrap();
+    let appended = std::fs::metadata(&spill_file).unwrap().len();
+    assert!(appended > size && appended - size < 200, "{} -> {}", size, appended);
+    assert_eq!(handler.find_and_replace_advanced(&doc_id, "Paragraph 3 ", "Clause 3 ", true, false, false).unwrap(), 1);
+    let edited = std::fs::metadata(&spill_file).unwrap().len();
+    assert!(edited > appended && edited - appended < 200, "{} -> {}", appended, edited);
+    assert!(handler.is_spilled(&doc_id));
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Clause 3 with") && text.contains("Paragraph 19") && text.ends_with("Appended"), "{}", text);
+    assert_eq!(handler.element_fingerprints(&doc_id).unwrap().len(), 21);
+}
+
+#[test]
+fn test_spilled_images_stay_on_disk_across_edits() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path()).unwrap();
+    let doc_id = handler.create_document().unwrap();
+    handler.set_max_document_memory(Some(4096));
+
+    // Noise so the PNG stays well over the limit
+    let mut seed = 7u32;
+    let noise = ::image::RgbaImage::from_fn(64, 64, |_, _| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        ::image::Rgba(seed.to_be_bytes())
+    });
+    let mut png = Vec::new();
+    ::image::DynamicImage::ImageRgba8(noise)
+        .write_to(&mut std::io::Cursor::new(&mut png), ::image::ImageFormat::Png).unwrap();
+    assert!(png.len() > 4096);
+    handler.add_image(&doc_id, docx_mcp::docx_handler::ImageData { data: png.clone(), width: Some(64), height: Some(64), alt_text: None }).unwrap();
+    assert!(handler.is_spilled(&doc_id));
+
+    // The image moved to the spill file once; text edits keep the op list in memory
+    // and leave the file alone
+    let spill_dir = temp_dir.path().join("docx-mcp").join("spill");
+    let payloads = spill_dir.join(format!("{}.spill", doc_id));
+    let size = std::fs::metadata(&payloads).unwrap().len();
+    assert_eq!(size, png.len() as u64);
+    for i in 0..5 {
+        handler.add_paragraph(&doc_id, &format!("Caption {}", i), None).unwrap();
+    }
+    assert_eq!(std::fs::metadata(&payloads).unwrap().len(), size);
+
+    let path = handler.get_metadata(&doc_id).unwrap().path;
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let media = zip.file_names().find(|n| n.starts_with("word/media/") && !n.ends_with('/')).unwrap().to_string();
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut zip.by_name(&media).unwrap(), &mut bytes).unwrap();
+    assert_eq!(::image::load_from_memory(&bytes).unwrap().width(), 64);
+
+    // A damaged payload file fails the next build instead of dropping the image
+    std::fs::write(&payloads, vec![0u8; png.len()]).unwrap();
+    let err = handler.add_paragraph(&doc_id, "More", None).unwrap_err();
+    assert!(format!("{:#}", err).contains("Invalid spill file"), "{:#}", err);
+
+    handler.close_document(&doc_id).unwrap();
+    assert!(!payloads.exists());
+}
+
+#[test]
+fn test_ops_journal_replays_document() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.rename_document(&doc_id, "Release notes").unwrap();
+    handler.add_heading(&doc_id, "Version 2.0", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Faster saves.", Some(DocxStyle { bold: Some(true), ..Default::default() })).unwrap();
+    handler.add_list(&doc_id, vec!["Hashes".into(), "Journals".into()], false).unwrap();
+
+    let journal = handler.export_ops(&doc_id).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&journal).unwrap();
+    assert_eq!(value["format"], "docx-mcp-ops");
+    assert_eq!(value["ops"].as_array().unwrap().len(), 3);
+
+    let replayed = handler.replay_ops(&journal, None).unwrap();
+    assert_ne!(replayed, doc_id);
+    assert_eq!(handler.get_metadata(&replayed).unwrap().title.as_deref(), Some("Release notes"));
+    assert_eq!(handler.extract_text(&replayed).unwrap(), handler.extract_text(&doc_id).unwrap());
+    assert_eq!(handler.export_ops(&replayed).unwrap(), journal);
+
+    // Replayed documents are ordinary editable documents
+    handler.add_paragraph(&replayed, "Appendix", None).unwrap();
+    assert!(handler.extract_text(&replayed).unwrap().contains("Appendix"));
+
+    let newer = journal.replace("\"version\": 1", "\"version\": 99");
+    assert!(handler.replay_ops(&newer, None).unwrap_err().to_string().contains("newer"));
+    assert!(handler.replay_ops("{\"ops\": []}", None).is_err());
+}
+
+#[test]
+fn test_detect_languages() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "The quarterly report describes how the team improved delivery times across every region.", None).unwrap();
+    handler.add_paragraph(&doc_id, "Быстрая коричневая лиса прыгает через ленивую собаку, а потом убегает в лес.", None).unwrap();
+    handler.add_paragraph(&doc_id, "2024-01-01", None).unwrap();
+
+    let report = handler.detect_languages(&doc_id).unwrap();
+    let codes: Vec<&str> = report["languages"].as_array().unwrap()
+        .iter().map(|l| l["code"].as_str().unwrap()).collect();
+    assert!(codes.contains(&"eng"));
+    assert!(codes.contains(&"rus"));
+    let total: f64 = report["languages"].as_array().unwrap()
+        .iter().map(|l| l["proportion"].as_f64().unwrap()).sum();
+    assert!((total - 1.0).abs() < 0.01);
+    // The date-only paragraph has no letters and is not reported
+    assert_eq!(report["elements"].as_array().unwrap().len(), 2);
+    assert_eq!(report["elements"][1]["language"]["tag"], "ru-RU");
+}
+
+#[test]
+fn test_apply_correction_targets_single_element() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Teh cat sat with teh dog.", None).unwrap();
+    handler.add_paragraph(&doc_id, "Teh end.", None).unwrap();
+
+    // Offset-based: the second "teh" in the first paragraph
+    handler.apply_correction(&doc_id, 0, None, None, "teh", "the", Some(17)).unwrap();
+    // First whole-word occurrence in the second paragraph
+    handler.apply_correction(&doc_id, 1, None, None, "Teh", "The", None).unwrap();
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Teh cat sat with the dog."));
+    assert!(text.contains("The end."));
+    assert!(handler.apply_correction(&doc_id, 0, None, None, "cat", "dog", Some(0)).is_err());
+    // An empty word would insert the replacement instead of correcting anything
+    assert!(handler.apply_correction(&doc_id, 1, None, None, "", "Oops ", Some(0)).is_err());
+    assert!(handler.extract_text(&doc_id).unwrap().contains("The end."));
+}
+
+#[cfg(feature = "spellcheck")]
+#[test]
+fn test_check_spelling_reports_locations_and_suggestions() {
+    use docx_mcp::spelling::SpellChecker;
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "The quick brwn fox jumps over NASA servers.", None).unwrap();
+
+    let mut checker = SpellChecker::from_dic("en_US", "8\nthe\nquick\nbrown\nfox\njump/S\nover\nserver/S\nbrow\n");
+    let misspellings = handler.check_spelling(&doc_id, &checker, 3).unwrap();
+    assert_eq!(misspellings.len(), 1);
+    assert_eq!(misspellings[0].word, "brwn");
+    assert_eq!((misspellings[0].start, misspellings[0].end), (10, 14));
+    assert!(misspellings[0].suggestions.contains(&"brown".to_string()));
+
+    checker.add_words(["brwn"]);
+    assert!(handler.check_spelling(&doc_id, &checker, 3).unwrap().is_empty());
+
+    // Language ids name a supported dictionary, never a path
+    let err = SpellChecker::load("../../../etc/passwd").err().unwrap();
+    assert!(err.to_string().contains("Unknown dictionary"), "{}", err);
+}
+
+#[test]
+fn test_analyze_content_overview() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Summary", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Acme Holdings signed the supply contract on 2024-03-15. The contract is worth $1,200,000 and covers 15% of annual supply.", None).unwrap();
+    handler.add_heading(&doc_id, "Risks", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Supply delays remain the main contract risk.", None).unwrap();
+
+    let report = handler.analyze_content(&doc_id, 5).unwrap();
+    assert_eq!(report["keywords"][0]["term"], "contract");
+    let entity = |kind: &str| report["entities"].as_array().unwrap().iter()
+        .find(|e| e["kind"] == kind).map(|e| e["text"].as_str().unwrap().to_string());
+    assert_eq!(entity("date").as_deref(), Some("2024-03-15"));
+    assert_eq!(entity("money").as_deref(), Some("$1,200,000"));
+    assert_eq!(entity("percentage").as_deref(), Some("15%"));
+    assert_eq!(entity("organization").as_deref(), Some("Acme Holdings"));
+
+    let sections = report["sections"].as_array().unwrap();
+    assert_eq!(sections.len(), 2);
+    assert_eq!(sections[0]["heading"], "Summary");
+    assert_eq!(sections[0]["lead"], "Acme Holdings signed the supply contract on 2024-03-15.");
+}
+
+#[test]
+fn test_analyze_formatting_reads_actual_styles() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    let font = |family: &str| DocxStyle {
+        font_family: Some(family.to_string()),
+        font_size: Some(22),
+        bold: None,
+        italic: None,
+        underline: None,
+        color: Some("#1F4E79".to_string()),
+        alignment: None,
+        line_spacing: None,
+        ..Default::default()
+    };
+    handler.add_heading(&doc_id, "Overview", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Body in Arial.", Some(font("Arial"))).unwrap();
+    handler.add_paragraph(&doc_id, "Body in Georgia.", Some(font("Georgia"))).unwrap();
+
+    let analysis = handler.analyze_formatting(&doc_id).unwrap();
+    assert_eq!(analysis["paragraph_styles"]["Heading1"], 1);
+    assert_eq!(analysis["fonts"]["Arial"], 1);
+    assert_eq!(analysis["fonts"]["Georgia"], 1);
+    assert_eq!(analysis["font_sizes"]["11"], 2);
+    assert_eq!(analysis["color_palette"]["1F4E79"], 2);
+    assert_eq!(analysis["has_tables"], false);
+    assert_eq!(analysis["inconsistencies"][0]["kind"], "mixed_body_fonts");
+}
+
+#[test]
+fn test_lint_formatting_reports_and_fixes() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    let style = |family: &str, bold: bool| DocxStyle {
+        font_family: Some(family.to_string()),
+        font_size: None,
+        bold: Some(bold),
+        italic: None,
+        underline: None,
+        color: None,
+        alignment: None,
+        line_spacing: None,
+        ..Default::default()
+    };
+    handler.add_heading(&doc_id, "Title", 1).unwrap();
+    handler.add_heading(&doc_id, "Deep", 3).unwrap();
+    handler.add_paragraph(&doc_id, "Body one.", Some(style("Arial", false))).unwrap();
+    handler.add_paragraph(&doc_id, "Body two.", Some(style("Arial", false))).unwrap();
+    handler.add_paragraph(&doc_id, "Odd one out.", Some(style("Comic Sans MS", false))).unwrap();
+    handler.add_paragraph(&doc_id, "", None).unwrap();
+    handler.add_paragraph(&doc_id, "", None).unwrap();
+    handler.add_paragraph(&doc_id, "Key Findings", Some(style("Arial", true))).unwrap();
+    handler.add_list_item(&doc_id, "first", 0, false).unwrap();
+    handler.add_list_item(&doc_id, "too deep", 2, false).unwrap();
+
+    let report = handler.lint_formatting(&doc_id, false).unwrap();
+    let rules: Vec<&str> = report["issues"].as_array().unwrap().iter()
+        .map(|i| i["rule"].as_str().unwrap()).collect();
+    for rule in ["heading_level_jump", "mixed_body_fonts", "repeated_blank_paragraphs", "bold_paragraph_as_heading", "list_indent_jump"] {
+        assert!(rules.contains(&rule), "missing {} in {:?}", rule, rules);
+    }
+    assert_eq!(report["fixed"], 0);
+
+    let fixed = handler.lint_formatting(&doc_id, true).unwrap();
+    assert_eq!(fixed["fixed"], fixed["issue_count"]);
+    let after = handler.lint_formatting(&doc_id, false).unwrap();
+    assert_eq!(after["issue_count"], 0, "{}", after);
+    let outline = handler.get_outline(&doc_id).unwrap();
+    assert!(outline["outline"].as_array().unwrap().iter().any(|h| h["text"] == "Key Findings"));
+}
+
+#[test]
+fn test_word_count_report_breakdown_and_layout_pages() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Preamble has four words", None).unwrap();
+    handler.add_heading(&doc_id, "First Section", 1).unwrap();
+    handler.add_paragraph(&doc_id, "One two three.", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["alpha beta".to_string(), "gamma".to_string()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+    handler.set_header(&doc_id, "Confidential draft").unwrap();
+
+    let report = handler.word_count_report(&doc_id).unwrap();
+    assert_eq!(report["breakdown"]["body"], 9);
+    assert_eq!(report["breakdown"]["tables"], 3);
+    assert_eq!(report["breakdown"]["headers_footers"], 2);
+    assert_eq!(report["main_text_words"], 12);
+    assert_eq!(report["sections"][0]["words"], 4);
+    assert_eq!(report["sections"][1]["heading"], "First Section");
+    assert_eq!(report["sections"][1]["words"], 8);
+    assert_eq!(report["pages"], 1);
+
+    // 60 single-line paragraphs overflow the 53-line A4 layout
+    for i in 0..60 {
+        handler.add_paragraph(&doc_id, &format!("Line {}", i), None).unwrap();
+    }
+    let report = handler.word_count_report(&doc_id).unwrap();
+    assert_eq!(report["pages"], 2);
+}
+
+#[test]
+fn test_get_pagination_reports_page_starts() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Report", 1).unwrap();
+    for i in 0..60 {
+        handler.add_paragraph(&doc_id, &format!("Paragraph {}", i), None).unwrap();
+    }
+
+    let pagination = handler.get_pagination(&doc_id).unwrap();
+    assert_eq!(pagination["page_count"], 2);
+    assert_eq!(pagination["pages"][0]["first_element"]["kind"], "heading");
+    // 53 lines per page: the heading plus paragraphs 0..=51 fill page one
+    assert_eq!(pagination["pages"][1]["first_element"]["text"], "Paragraph 52");
+    assert_eq!(pagination["pages"][1]["continues_previous"], false);
+    assert_eq!(pagination["elements"][60]["page"], 2);
+    assert_eq!(handler.get_metadata(&doc_id).unwrap().page_count, Some(2));
+    // The estimate is cached per file content, so an edit still shows up
+    assert_eq!(handler.get_metadata(&doc_id).unwrap().page_count, Some(2));
+    for i in 60..120 {
+        handler.add_paragraph(&doc_id, &format!("Paragraph {}", i), None).unwrap();
+    }
+    assert_eq!(handler.get_metadata(&doc_id).unwrap().page_count, Some(3));
+}
+
+#[test]
+fn test_render_template_fills_blocks_and_repeats_rows() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Invoice for {{customer.name}}", 1).unwrap();
+    handler.add_paragraph(&doc_id, "{{#if vip}}", None).unwrap();
+    handler.add_paragraph(&doc_id, "Priority support included.", None).unwrap();
+    handler.add_paragraph(&doc_id, "{{else}}", None).unwrap();
+    handler.add_paragraph(&doc_id, "Standard support.", None).unwrap();
+    handler.add_paragraph(&doc_id, "{{/if}}", None).unwrap();
+    handler.add_paragraph(&doc_id, "{{#each notes}}", None).unwrap();
+    handler.add_paragraph(&doc_id, "Note {{@index}}: {{this}}", None).unwrap();
+    handler.add_paragraph(&doc_id, "{{/each}}", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![
+            vec!["Item".into(), "Qty".into()],
+            vec!["{{#each items}}{{name}}".into(), "{{qty}}{{/each}}".into()],
+            vec!["Total".into(), "{{total}} ({{currency}})".into()],
+        ],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    let data = serde_json::json!({
+        "customer": {"name": "Acme Ltd"},
+        "vip": false,
+        "notes": ["Net 30", "Ship by air"],
+        "items": [{"name": "Bolts", "qty": 40}, {"name": "Nuts", "qty": 25}],
+        "total": 65
+    });
+    let report = handler.render_template(&doc_id, &data, &Formatting::default()).unwrap();
+    assert_eq!(report["missing"], serde_json::json!(["currency"]));
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Invoice for Acme Ltd"));
+    assert!(text.contains("Standard support.") && !text.contains("Priority"));
+    assert!(text.contains("Note 0: Net 30") && text.contains("Note 1: Ship by air"));
+    assert!(!text.contains("{{"));
+
+    let tables = handler.get_tables_json(&doc_id).unwrap();
+    assert_eq!(tables["tables"][0]["rows"], 4);
+    let rows = &tables["tables"][0]["cells"];
+    assert_eq!(rows[1], serde_json::json!(["Bolts", "40"]));
+    assert_eq!(rows[2], serde_json::json!(["Nuts", "25"]));
+    assert_eq!(rows[3], serde_json::json!(["Total", "65 ()"]));
+
+    // Unbalanced blocks are rejected without touching the document
+    handler.add_paragraph(&doc_id, "{{#each stray}}", None).unwrap();
+    assert!(handler.render_template(&doc_id, &data, &Formatting::default()).is_err());
+}
+
+#[test]
+fn test_strip_and_keep_tagged_content() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Everyone sees this.", None).unwrap();
+    handler.add_paragraph(&doc_id, "Margin analysis.", None).unwrap();
+    handler.add_paragraph(&doc_id, "Cost breakdown.", None).unwrap();
+    handler.add_paragraph(&doc_id, "Thank you for your order.", None).unwrap();
+    handler.tag_content(&doc_id, "internal", 1, 2).unwrap();
+    // The region markers are elements too, so the last paragraph is now element 5
+    handler.tag_content(&doc_id, "customer", 5, 5).unwrap();
+    assert!(handler.tag_content(&doc_id, "bad tag", 0, 0).is_err());
+    assert!(handler.tag_content(&doc_id, "x", 3, 99).is_err());
+
+    let regions = handler.list_tagged_content(&doc_id).unwrap();
+    assert_eq!(regions.len(), 2);
+    assert_eq!(regions[0]["tag"], "internal");
+    assert_eq!((regions[0]["start"].as_u64(), regions[0]["end"].as_u64()), (Some(2), Some(3)));
+
+    // Markers become bookmarks, not visible text
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Margin analysis.") && !text.contains("__TAG"));
+
+    let customer_id = handler.clone_document(&doc_id, None).unwrap();
+    assert_eq!(handler.strip_tagged_content(&customer_id, &["internal".to_string()]).unwrap(), 2);
+    let text = handler.extract_text(&customer_id).unwrap();
+    assert!(text.contains("Everyone sees this.") && text.contains("Thank you"));
+    assert!(!text.contains("Margin") && !text.contains("Cost"));
+
+    assert_eq!(handler.keep_only_tagged(&doc_id, &["internal".to_string()]).unwrap(), 1);
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Everyone sees this.") && text.contains("Cost breakdown."));
+    assert!(!text.contains("Thank you"));
+}
+
+#[test]
+fn test_populate_table_repeats_template_row() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![
+            vec!["Item".into(), "Unit Price".into(), "Note".into()],
+            vec!["{{item}}".into(), "".into(), "".into()],
+            vec!["Total".into(), "".into(), "".into()],
+        ],
+        headers: None,
+        border_style: Some("single".into()),
+        col_widths: Some(vec![200, 100, 100]),
+        merges: Some(vec![
+            TableMerge { row: 1, col: 1, row_span: 1, col_span: 2 },
+            TableMerge { row: 2, col: 1, row_span: 1, col_span: 2 },
+        ]),
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    let records = vec![
+        serde_json::json!({"item": "Bolts", "unit_price": 0.1}),
+        serde_json::json!({"item": "Nuts", "Unit Price": "0.05"}),
+        serde_json::json!(["Washers", "0.02", "bulk"]),
+    ];
+    assert_eq!(handler.populate_table(&doc_id, 0, Some(1), &records, &Formatting::default()).unwrap(), 3);
+
+    let tables = handler.get_tables_json(&doc_id).unwrap();
+    let table = &tables["tables"][0];
+    assert_eq!(table["rows"], 5);
+    assert_eq!(table["cells"][1], serde_json::json!(["Bolts", "0.1", ""]));
+    assert_eq!(table["cells"][2], serde_json::json!(["Nuts", "0.05", ""]));
+    assert_eq!(table["cells"][3], serde_json::json!(["Washers", "0.02", "bulk"]));
+    assert_eq!(table["cells"][4][0], "Total");
+    let merge_rows: Vec<u64> = table["merges"].as_array().unwrap().iter().map(|m| m["row"].as_u64().unwrap()).collect();
+    assert_eq!(merge_rows, vec![1, 2, 3, 4]);
+
+    assert!(handler.populate_table(&doc_id, 1, None, &records, &Formatting::default()).is_err());
+    assert!(handler.populate_table(&doc_id, 0, None, &[serde_json::json!("text")], &Formatting::default()).is_err());
+}
+
+#[test]
+fn test_table_to_list_and_back() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Glossary", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![
+            vec!["Term".into(), "Meaning".into()],
+            vec!["SLA".into(), "Service level agreement".into()],
+            vec!["RPO".into(), "Recovery point objective: data loss window".into()],
+        ],
+        headers: Some(vec!["Term".into(), "Meaning".into()]),
+        border_style: Some("single".into()),
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    assert_eq!(handler.table_to_list(&doc_id, 0, false, ": ", None).unwrap(), 2);
+    let items: Vec<String> = handler.text_elements(&doc_id).unwrap().into_iter()
+        .filter(|e| e.kind == "list_item").map(|e| e.text).collect();
+    assert_eq!(items, vec!["SLA: Service level agreement", "RPO: Recovery point objective: data loss window"]);
+    assert!(handler.table_to_list(&doc_id, 0, false, ": ", None).is_err());
+
+    assert!(handler.list_to_table(&doc_id, 0, ": ", None, None).is_err(), "element 0 is a paragraph");
+    let headers = Some(vec!["Term".to_string(), "Meaning".to_string()]);
+    assert_eq!(handler.list_to_table(&doc_id, 1, ": ", Some(2), headers).unwrap(), 3);
+    let tables = handler.get_tables_json(&doc_id).unwrap();
+    let table = &tables["tables"][0];
+    assert_eq!(table["cells"][0], serde_json::json!(["Term", "Meaning"]));
+    assert_eq!(table["cells"][2], serde_json::json!(["RPO", "Recovery point objective: data loss window"]));
+
+    handler.add_list_item(&doc_id, "a | b | c", 0, false).unwrap();
+    handler.add_list_item(&doc_id, "d | e", 0, false).unwrap();
+    assert_eq!(handler.list_to_table(&doc_id, 2, " | ", None, None).unwrap(), 2);
+    let tables = handler.get_tables_json(&doc_id).unwrap();
+    assert_eq!(tables["tables"][1]["cells"], serde_json::json!([["a", "b", "c"], ["d", "e", ""]]));
+}
+
+#[test]
+fn test_extract_patterned_items_with_section_context() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "3. Functional Requirements", 1).unwrap();
+    handler.add_heading(&doc_id, "3.1 Document Upload", 2).unwrap();
+    handler.add_list(&doc_id, vec![
+        "FR-001: System shall support uploads up to 100MB".into(),
+        "FR-002: System shall generate file metadata".into(),
+    ], false).unwrap();
+    handler.add_heading(&doc_id, "7. Security Requirements", 1).unwrap();
+    handler.add_paragraph(&doc_id, "SEC-001: All traffic must use TLS 1.3 (see also FR-001)", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["SEC-002".into(), "Passwords must meet complexity rules".into(), "Critical".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    let items = handler.extract_patterned_items(&doc_id, r"^(?P<id>(?P<type>FR|SEC)-(\d{3}))", true).unwrap();
+    let ids: Vec<&str> = items.iter().map(|i| i["fields"]["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["FR-001", "FR-002", "SEC-001", "SEC-002"]);
+    assert_eq!(items[0]["fields"]["type"], "FR");
+    assert_eq!(items[0]["fields"]["3"], "001");
+    assert_eq!(items[0]["text"], "FR-001: System shall support uploads up to 100MB");
+    assert_eq!(items[1]["section"], "3.1 Document Upload");
+    assert_eq!(items[1]["section_path"], serde_json::json!(["3. Functional Requirements", "3.1 Document Upload"]));
+    assert_eq!(items[2]["section_path"], serde_json::json!(["7. Security Requirements"]));
+    assert_eq!(items[3]["kind"], "table_cell");
+    assert_eq!(items[3]["row"], serde_json::json!(["SEC-002", "Passwords must meet complexity rules", "Critical"]));
+
+    let mentions = handler.extract_patterned_items(&doc_id, r"fr-\d{3}", false).unwrap();
+    assert_eq!(mentions.len(), 3);
+    assert!(handler.extract_patterned_items(&doc_id, "(unclosed", true).is_err());
+}
+
+#[test]
+fn test_generate_invoice_computes_totals_in_locale() {
+    let (mut handler, _temp_dir) = setup_test_handler();
+    let invoice: docx_mcp::invoice::Invoice = serde_json::from_value(serde_json::json!({
+        "company_name": "Muster GmbH",
+        "invoice_number": "2024-017",
+        "date": "01.03.2024",
+        "bill_to": ["Acme AG", "Hauptstraße 1, Berlin"],
+        "currency": "EUR",
+        "locale": "de-DE",
+        "tax_rate": 19,
+        "line_items": [
+            {"description": "Widget", "quantity": 3, "unit_price": 19.99},
+            {"description": "Consulting", "unit_price": 1200, "tax_rate": 0}
+        ]
+    })).unwrap();
+
+    let (doc_id, totals) = handler.generate_invoice(&invoice).unwrap();
+    assert_eq!((totals.subtotal, totals.tax, totals.total), (1259.97, 11.39, 1271.36));
+    assert_eq!(handler.get_metadata(&doc_id).unwrap().title.as_deref(), Some("Invoice 2024-017"));
+
+    let tables = handler.get_tables_json(&doc_id).unwrap();
+    let items = &tables["tables"][1]["cells"];
+    assert_eq!(items[1], serde_json::json!(["Widget", "3", "19,99\u{a0}€", "19%", "59,97\u{a0}€"]));
+    assert_eq!(items[5][0], "Total");
+    assert_eq!(items[5][4], "1.271,36\u{a0}€");
+
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("INVOICE") && text.contains("Hauptstraße 1, Berlin"));
+
+    let us = docx_mcp::locale::lookup("en_US").unwrap();
+    assert_eq!(us.format_currency(-1234.5, "USD"), "-$1,234.50");
+    assert_eq!(docx_mcp::locale::lookup("en-IN").unwrap().format_number(1234567.0, 0), "12,34,567");
+    assert_eq!(docx_mcp::locale::lookup("ja").unwrap().format_currency(5000.0, "JPY"), "¥5,000");
+    assert!(docx_mcp::locale::lookup("xx-YY").is_err());
+}
+
+#[test]
+fn test_locale_formatting_in_templates_and_tables() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Fällig am {{date due \"long\"}}: {{currency total}} ({{percent rate 1}})", None).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![
+            vec!["Item".into(), "Price".into(), "Shipped".into()],
+            vec!["".into(), "".into(), "".into()],
+        ],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    let formatting = Formatting::from_args(&serde_json::json!({
+        "locale": "de-DE",
+        "currency": "EUR",
+        "formats": {"price": "currency", "Shipped": "date"}
+    })).unwrap();
+    let data = serde_json::json!({"due": "2024-03-01", "total": 1234.5, "rate": 19});
+    handler.render_template(&doc_id, &data, &formatting).unwrap();
+    let text = handler.extract_text(&doc_id).unwrap();
+    assert!(text.contains("Fällig am 1. März 2024: 1.234,50\u{a0}€ (19,0\u{a0}%)"), "{}", text);
+
+    let records = vec![serde_json::json!({"item": "Bolts", "price": "0.5", "shipped": "2024-03-04T10:00:00Z"})];
+    handler.populate_table(&doc_id, 0, None, &records, &formatting).unwrap();
+    let tables = handler.get_tables_json(&doc_id).unwrap();
+    assert_eq!(tables["tables"][0]["cells"][1], serde_json::json!(["Bolts", "0,50\u{a0}€", "04.03.2024"]));
+
+    let bad = vec![serde_json::json!({"item": "Nuts", "price": "n/a"})];
+    assert!(handler.populate_table(&doc_id, 0, None, &bad, &formatting).is_err());
+    assert!(Formatting::from_args(&serde_json::json!({"formats": {"x": "money"}})).is_err());
+
+    let us = Formatting::default();
+    assert_eq!(us.format(&serde_json::json!("2024-03-01"), "date").unwrap(), "03/01/2024");
+    assert_eq!(us.format(&serde_json::json!("2024-03-01"), "date:long").unwrap(), "March 1, 2024");
+}
+
+#[test]
+fn test_transform_text_by_scope() {
+    use docx_mcp::text_transform::Transform;
+
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "the state of the API: a review of PDF tools", 1).unwrap();
+    handler.add_paragraph(&doc_id, "THIS IS LOUD. and this is not.  ", None).unwrap();
+    let code = DocxStyle { font_family: Some("Consolas".to_string()), ..Default::default() };
+    handler.add_paragraph(&doc_id, "    let total = items.iter().map(|item| item.price * item.quantity).sum::<f64>();", Some(code)).unwrap();
+
+    let title = Transform::parse("title_case", None).unwrap();
+    assert_eq!(handler.transform_text(&doc_id, "headings", title).unwrap(), 1);
+    let wrap = Transform::parse("max_line_length", Some(40)).unwrap();
+    assert_eq!(handler.transform_text(&doc_id, "code", wrap).unwrap(), 1);
+    let sentence = Transform::parse("sentence_case", None).unwrap();
+    handler.transform_text(&doc_id, "paragraphs", sentence).unwrap();
+    let trim = Transform::parse("trim_trailing_whitespace", None).unwrap();
+    handler.transform_text(&doc_id, "all", trim).unwrap();
+
+    let elements = handler.text_elements(&doc_id).unwrap();
+    assert_eq!(elements[0].text, "The State of the API: A Review of PDF Tools");
+    assert_eq!(elements[1].text, "This is loud. And this is not.");
+    let code_lines: Vec<&str> = elements[2].text.lines().collect();
+    assert!(code_lines.len() > 1 && code_lines.iter().all(|l| l.chars().count() <= 40), "{:?}", code_lines);
+    assert!(code_lines[1].starts_with("    "), "continuation keeps the indent");
+
+    assert!(Transform::parse("max_line_length", None).is_err());
+    assert!(handler.transform_text(&doc_id, "footnotes", title).is_err());
+    let upper = Transform::parse("uppercase", None).unwrap();
+    assert_eq!(upper.apply("hello {{name}} on {FIELD:DATE}"), "HELLO {{name}} ON {FIELD:DATE}");
+}
+
+#[test]
+fn test_xliff_round_trip() {
+    let (mut handler, doc_id, _temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Welcome", 1).unwrap();
+    let bold = DocxStyle { bold: Some(true), ..Default::default() };
+    handler.add_paragraph(&doc_id, "Fish & chips", Some(bold)).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["Name".into(), "Price".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+
+    let xliff = handler.export_xliff(&doc_id, "en", Some("fr")).unwrap();
+    assert!(xliff.contains(r#"target-language="fr""#));
+    assert!(xliff.contains(r#"<trans-unit id="e1" resname="paragraph" xml:space="preserve">"#), "{}", xliff);
+    assert!(xliff.contains("<source>Fish &amp; chips</source>"));
+    assert!(xliff.contains(r#"id="e2.r0c1""#));
+
+    let translated = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+  <file original="doc" source-language="en" target-language="fr" datatype="plaintext"><body>
+    <trans-unit id="e0"><source>Welcome</source><target>Bienvenue</target></trans-unit>
+    <trans-unit id="e1"><source>Fish &amp; chips</source><target>Poisson &amp; frites</target></trans-unit>
+    <trans-unit id="e2.r0c0"><source>Name</source><target></target></trans-unit>
+    <trans-unit id="e2.r0c1"><source>Cost</source><target>Prix</target></trans-unit>
+    <trans-unit id="e9"><source>Gone</source><target>Parti</target></trans-unit>
+  </body></file>
+</xliff>"#;
+    let report = handler.import_xliff(&doc_id, translated).unwrap();
+    assert_eq!(report["applied"], 2);
+    assert_eq!(report["untranslated"], 1);
+    assert_eq!(report["stale"], serde_json::json!(["e2.r0c1"]));
+    assert_eq!(report["unknown"], serde_json::json!(["e9"]));
+
+    let elements = handler.text_elements(&doc_id).unwrap();
+    assert_eq!(elements[0].text, "Bienvenue");
+    assert_eq!(elements[1].text, "Poisson & frites");
+    assert_eq!(elements[3].text, "Price");
+    assert!(handler.import_xliff(&doc_id, "<html/>").is_err());
+}
+
+#[test]
+fn test_structure_matches_between_ops_and_opened_xml() {
+    let (mut handler, doc_id, temp_dir) = handler_and_doc();
+    handler.add_paragraph(&doc_id, "Preface", None).unwrap();
+    handler.add_heading(&doc_id, "Scope", 1).unwrap();
+    handler.add_paragraph(&doc_id, "First", None).unwrap();
+    handler.add_paragraph(&doc_id, "Second", None).unwrap();
+    handler.add_heading(&doc_id, "Detail", 2).unwrap();
+    handler.add_list(&doc_id, vec!["One".to_string(), "Two".to_string()], false).unwrap();
+    handler.add_paragraph(&doc_id, "Closing", None).unwrap();
+
+    let built = handler.analyze_structure(&doc_id).unwrap();
+    let path = temp_dir.path().join("structure.docx");
+    handler.save_document(&doc_id, &path).unwrap();
+    let opened_id = handler.open_document(&path).unwrap();
+    let opened = handler.analyze_structure(&opened_id).unwrap();
+    assert_eq!(opened["has_ops"], false);
+
+    for structure in [&built, &opened] {
+        let outline: Vec<(String, u64)> = structure["outline"].as_array().unwrap().iter()
+            .map(|h| (h["text"].as_str().unwrap().to_string(), h["level"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(outline, vec![("Scope".to_string(), 1), ("Detail".to_string(), 2)]);
+        let sections = structure["sections"].as_array().unwrap();
+        let counts: Vec<(u64, u64)> = sections.iter()
+            .map(|s| (s["paragraphs"].as_u64().unwrap(), s["lists"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(counts, vec![(1, 0), (2, 0), (1, 1)]);
+        assert_eq!(sections[1]["element_index"], structure["outline"][0]["element_index"]);
+    }
+    assert_eq!(opened["lists"][0]["items"], serde_json::json!(["One", "Two"]));
+}
+
+#[test]
+fn test_copy_elements_between_documents() {
+    let (mut handler, source, _temp_dir) = handler_and_doc();
+    handler.add_heading(&source, "Q3 Report", 1).unwrap();
+    handler.add_paragraph(&source, "Budget", Some(DocxStyle { bold: Some(true), ..Default::default() })).unwrap();
+    handler.add_table(&source, TableData {
+        rows: vec![vec!["Item".to_string(), "Cost".to_string()], vec!["Rent".to_string(), "100".to_string()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+    handler.set_header(&source, "Q3 only").unwrap();
+
+    let target = handler.create_document().unwrap();
+    handler.add_paragraph(&target, "Intro", None).unwrap();
+    handler.add_paragraph(&target, "Outro", None).unwrap();
+    assert_eq!(handler.copy_elements(&source, 1, 3, &target, Some(1), None).unwrap(), 2);
+
+    let texts: Vec<String> = handler.text_elements(&target).unwrap().into_iter().map(|e| e.text).collect();
+    assert_eq!(texts[..2], ["Intro".to_string(), "Budget".to_string()]);
+    assert!(texts.contains(&"Rent".to_string()));
+    assert_eq!(texts.last().unwrap(), "Outro");
+    assert!(!handler.extract_text(&target).unwrap().contains("Q3 only"));
+    assert!(handler.copy_elements(&source, 2, 9, &target, None, None).is_err());
+}
+
+#[test]
+fn test_insert_document_at_anchor() {
+    use docx_mcp::docx_handler::InsertAnchor;
+    let (mut handler, source, temp_dir) = handler_and_doc();
+    handler.add_heading(&source, "Terms", 2).unwrap();
+    handler.add_paragraph(&source, "Payment is due in 30 days.", None).unwrap();
+    handler.set_header(&source, "Boilerplate header").unwrap();
+
+    let target = handler.create_document().unwrap();
+    handler.add_heading(&target, "Appendices", 1).unwrap();
+    handler.add_paragraph(&target, "Closing remarks.", None).unwrap();
+    let anchor = InsertAnchor::Heading("Appendices".into());
+    assert_eq!(handler.insert_document(&target, &source, Some(&anchor), "after", None).unwrap(), 2);
+
+    let outline = handler.get_outline(&target).unwrap();
+    assert_eq!(outline["outline"][1]["text"], "Terms");
+    assert_eq!(outline["outline"][1]["level"], 2);
+    let text = handler.extract_text(&target).unwrap();
+    assert!(text.find("Payment is due").unwrap() < text.find("Closing remarks.").unwrap());
+    assert!(!text.contains("Boilerplate header"));
+
+    let md = temp_dir.path().join("signoff.md");
+    std::fs::write(&md, "## Sign-off\n\nApproved by the board.\n").unwrap();
+    handler.insert_document(&target, md.to_str().unwrap(), None, "after", None).unwrap();
+    assert!(handler.extract_text(&target).unwrap().trim_end().ends_with("Approved by the board."));
+
+    assert!(handler.insert_document(&target, &target, None, "after", None).is_err());
+    assert!(handler.insert_document(&target, "no-such-doc", None, "after", None).is_err());
+}
+
+#[test]
+fn test_assemble_document_numbers_headings_across_parts() {
+    let (mut handler, first, _temp_dir) = handler_and_doc();
+    handler.add_heading(&first, "1. Introduction", 1).unwrap();
+    handler.add_heading(&first, "Scope", 2).unwrap();
+    handler.add_paragraph(&first, "Covers phase one.", None).unwrap();
+    handler.set_footer(&first, "Acme confidential").unwrap();
+    let second = handler.create_document().unwrap();
+    handler.add_heading(&second, "1. Costs", 1).unwrap();
+    handler.set_footer(&second, "Draft").unwrap();
+
+    let parts = vec![first.clone(), second.clone()];
+    let assembled = handler.assemble_document(&parts, Some("Proposal"), true, true, None).unwrap();
+    let outline = handler.get_outline(&assembled).unwrap();
+    let headings: Vec<&str> = outline["outline"].as_array().unwrap().iter().map(|h| h["text"].as_str().unwrap()).collect();
+    assert_eq!(headings, vec!["1. Introduction", "1.1 Scope", "2. Costs"]);
+    let footers: Vec<String> = handler.text_elements(&assembled).unwrap().into_iter()
+        .filter(|e| e.kind == "footer").map(|e| e.text).collect();
+    assert_eq!(footers, vec!["Acme confidential".to_string()]);
+    assert_eq!(handler.get_metadata(&assembled).unwrap().title.as_deref(), Some("Proposal"));
+
+    // Without continuous numbering the parts keep their own numbers
+    let separate = handler.assemble_document(&parts, None, false, false, None).unwrap();
+    let outline = handler.get_outline(&separate).unwrap();
+    assert_eq!(outline["outline"][2]["text"], "1. Costs");
+}
+
+#[test]
+fn test_make_editable_reads_opened_document_into_ops() {
+    let (mut handler, doc_id, temp_dir) = handler_and_doc();
+    handler.add_heading(&doc_id, "Scope", 1).unwrap();
+    handler.add_paragraph(&doc_id, "Budget", Some(DocxStyle { bold: Some(true), ..Default::default() })).unwrap();
+    handler.add_list(&doc_id, vec!["One".to_string(), "Two".to_string()], true).unwrap();
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["Item".to_string(), "Cost".to_string()], vec!["Rent".to_string(), "100".to_string()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    }).unwrap();
+    handler.add_hyperlink(&doc_id, "Docs", "https://example.com/docs").unwrap();
+    handler.set_header(&doc_id, "Acme").unwrap();
+    handler.set_page_setup(&doc_id, Some("Letter"), None, None, None, None).unwrap();
+    let built: Vec<(String, String)> = handler.text_elements(&doc_id).unwrap().into_iter()
+        .map(|e| (e.kind, e.text)).collect();
+
+    let path = temp_dir.path().join("source.docx");
+    handler.save_document(&doc_id, &path).unwrap();
+    let opened_id = handler.open_document(&path).unwrap();
+    assert!(handler.add_paragraph(&opened_id, "Not yet", None).is_err());
+
+    let fidelity = handler.make_editable(&opened_id).unwrap();
+    assert!(fidelity.dropped.is_empty(), "{:?}", fidelity);
+    assert!(handler.get_metadata(&opened_id).unwrap().fidelity.is_some());
+    let read: Vec<(String, String)> = handler.text_elements(&opened_id).unwrap().into_iter()
+        .map(|e| (e.kind, e.text)).collect();
+    assert_eq!(read, built);
+    let outline = handler.get_outline(&opened_id).unwrap();
+    assert_eq!(outline["outline"][0]["text"], "Scope");
+
+    // The opened document now takes edits like one built with create_document
+    handler.add_paragraph(&opened_id, "Added later", None).unwrap();
+    let resaved = temp_dir.path().join("resaved.docx");
+    handler.save_document(&opened_id, &resaved).unwrap();
+    let reopened = handler.open_document(&resaved).unwrap();
+    let text = handler.extract_text(&reopened).unwrap();
+    assert!(text.contains("Rent") && text.contains("Added later"));
+}
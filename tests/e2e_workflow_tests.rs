@@ -6,7 +6,6 @@ use serde_json::{json, Value};
 use tempfile::TempDir;
 use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
 use pretty_assertions::assert_eq;
 // tokio_test not needed in async tests here
 
@@ -17,7 +16,7 @@ enum ToolResult {
 
 async fn tool_result(provider: &DocxToolsProvider, name: &str, args: Value) -> ToolResult {
     let resp = provider.call_tool(name, args).await;
-    let val = match resp.content.get(0) {
+    let val = match resp.content.first() {
         Some(ToolResponseContent::Text(t)) => serde_json::from_str::<Value>(&t.text)
             .unwrap_or_else(|_| json!({"success": false, "error": t.text.clone()})),
         _ => json!({"success": false, "error": "non-text response"}),
@@ -447,18 +446,18 @@ async fn test_collaborative_workflow() -> Result<()> {
         })).await;
         
         // Add achievements
-        let achievements = match member {
-            &"Alice" => vec![
+        let achievements = match *member {
+            "Alice" => vec![
                 "Completed user research interviews",
                 "Created wireframes for new features",
                 "Updated design system documentation"
             ],
-            &"Bob" => vec![
+            "Bob" => vec![
                 "Implemented new API endpoints",
                 "Optimized database queries",
                 "Fixed critical security vulnerability"
             ],
-            &"Charlie" => vec![
+            "Charlie" => vec![
                 "Deployed version 2.1 to production",
                 "Set up monitoring dashboards",
                 "Conducted security audit"
@@ -479,18 +478,18 @@ async fn test_collaborative_workflow() -> Result<()> {
             "level": 2
         })).await;
         
-        let metrics = match member {
-            &"Alice" => vec![
+        let metrics = match *member {
+            "Alice" => vec![
                 vec!["Interviews Conducted", "8"],
                 vec!["Designs Created", "12"],
                 vec!["User Stories", "15"]
             ],
-            &"Bob" => vec![
+            "Bob" => vec![
                 vec!["Lines of Code", "2,450"],
                 vec!["Tests Written", "23"],
                 vec!["Bugs Fixed", "7"]
             ],
-            &"Charlie" => vec![
+            "Charlie" => vec![
                 vec!["Deployments", "3"],
                 vec!["Issues Resolved", "11"],
                 vec!["System Uptime", "99.9%"]
@@ -655,6 +654,7 @@ async fn test_security_restricted_workflow() -> Result<()> {
         max_open_documents: 5,
         allow_external_tools: false,
         allow_network: false,
+        allowed_paths: Vec::new(),
     };
     
     let provider = DocxToolsProvider::with_base_dir_and_security(temp_dir.path(), security_config);
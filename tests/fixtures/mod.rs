@@ -37,6 +37,7 @@ impl TestStyles {
             color: Some("#000000".to_string()),
             alignment: Some("left".to_string()),
             line_spacing: Some(1.15),
+            ..Default::default()
         }
     }
     
@@ -50,6 +51,7 @@ impl TestStyles {
             color: Some("#1f4e79".to_string()),
             alignment: Some("left".to_string()),
             line_spacing: Some(1.15),
+            ..Default::default()
         }
     }
     
@@ -63,6 +65,7 @@ impl TestStyles {
             color: Some("#c55a11".to_string()),
             alignment: Some("left".to_string()),
             line_spacing: Some(1.15),
+            ..Default::default()
         }
     }
     
@@ -76,6 +79,7 @@ impl TestStyles {
             color: Some("#000000".to_string()),
             alignment: Some("center".to_string()),
             line_spacing: Some(1.15),
+            ..Default::default()
         }
     }
 }
@@ -117,6 +117,7 @@ pub fn create_technical_report(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, response_time_data)?;
     
@@ -137,6 +138,7 @@ pub fn create_technical_report(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, reliability_data)?;
     
@@ -198,6 +200,7 @@ pub fn create_meeting_minutes(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, meeting_details)?;
     
@@ -247,6 +250,7 @@ pub fn create_meeting_minutes(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, budget_data)?;
     
@@ -278,6 +282,7 @@ pub fn create_meeting_minutes(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, action_items_data)?;
     
@@ -389,6 +394,7 @@ pub fn create_product_spec(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, nfr_data)?;
     
@@ -523,6 +529,7 @@ pub fn create_formatted_document(handler: &mut DocxHandler) -> Result<String> {
         col_widths: None,
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, formatted_table)?;
     
@@ -3,7 +3,7 @@ use docx_mcp::docx_handler::{DocxHandler, TableData, TableMerge};
 use tempfile::TempDir;
 use std::fs;
 use zip::ZipArchive;
-use docx_mcp::docx_handler::MarginsSpec;
+use docx_mcp::docx_handler::{HeaderFooterLayout, ImageData, MarginsSpec};
 
 fn open_zip_str(path: &std::path::Path, name: &str) -> Result<String> {
     let file = fs::File::open(path)?;
@@ -76,6 +76,7 @@ fn test_table_merge_best_effort_xml() -> Result<()> {
         col_widths: None,
         merges: Some(vec![TableMerge { row: 0, col: 0, row_span: 1, col_span: 2 }]),
         cell_shading: None,
+        ..Default::default()
     };
 
     handler.add_table(&doc_id, table)?;
@@ -115,6 +116,7 @@ fn test_table_vmerge_and_col_widths_injection() -> Result<()> {
         col_widths: Some(vec![2400, 3600]),
         merges: Some(vec![TableMerge { row: 0, col: 0, row_span: 2, col_span: 1 }]),
         cell_shading: None,
+        ..Default::default()
     };
 
     handler.add_table(&doc_id, table)?;
@@ -170,6 +172,7 @@ fn test_styles_and_lists_and_sections_hifi_xml() -> Result<()> {
         col_widths: Some(vec![3000, 3000]),
         merges: None,
         cell_shading: None,
+        ..Default::default()
     };
     handler.add_table(&doc_id, table)?;
 
@@ -235,3 +238,890 @@ fn test_insert_toc_and_bookmark_placeholders() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_highlight_matches_marks_runs() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Net revenue grew while revenue costs fell.", None)?;
+    handler.add_paragraph(&doc_id, "Nothing to see here.", None)?;
+
+    let count = handler.highlight_matches(&doc_id, "revenue", "yellow", false, true, false)?;
+    assert_eq!(count, 2);
+    // Highlights survive later edits
+    handler.add_paragraph(&doc_id, "Revenue outlook.", None)?;
+
+    let out_path = temp_dir.path().join("highlight.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert_eq!(doc_xml.matches(r#"<w:highlight w:val="yellow"/>"#).count(), 3, "{}", doc_xml);
+    assert!(handler.extract_text(&doc_id)?.contains("Net revenue grew while revenue costs fell."));
+
+    // Whole-word applies to every alternative of a regex, not just the outer ends
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "cost costs costly", None)?;
+    assert_eq!(handler.highlight_matches(&doc_id, "cost|costs", "green", false, true, true)?, 2);
+    Ok(())
+}
+
+#[test]
+fn test_embed_font_writes_obfuscated_part_and_font_table() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Branded", None)?;
+
+    let font_path = std::path::Path::new("assets/fonts/NotoSans-Bold.ttf");
+    let (family, slot) = handler.embed_font(&doc_id, font_path, None)?;
+    assert_eq!((family.as_str(), slot.as_str()), ("Noto Sans", "embedBold"));
+    // Re-embedding the same face replaces it rather than adding a second part
+    handler.embed_font(&doc_id, font_path, None)?;
+    assert!(handler.embed_font(&doc_id, std::path::Path::new("Cargo.toml"), None).is_err());
+
+    let out_path = temp_dir.path().join("fonts.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let table = open_zip_str(&out_path, "word/fontTable.xml")?;
+    assert_eq!(table.matches(r#"w:name="Noto Sans""#).count(), 1, "{}", table);
+    let key = regex::Regex::new(r#"<w:embedBold r:id="rIdFont1" w:fontKey="(\{[0-9A-F-]+\})"/>"#)?
+        .captures(&table).expect("embedBold entry")[1].to_string();
+    assert!(open_zip_str(&out_path, "word/_rels/fontTable.xml.rels")?.contains("fonts/font1.odttf"));
+    assert!(open_zip_str(&out_path, "[Content_Types].xml")?.contains(r#"Extension="odttf""#));
+
+    // Undo the obfuscation: XOR the first 32 bytes with the reversed GUID bytes
+    let mut zip = ZipArchive::new(fs::File::open(&out_path)?)?;
+    assert!(zip.by_name("word/fonts/font2.odttf").is_err());
+    let mut embedded = Vec::new();
+    use std::io::Read as _;
+    zip.by_name("word/fonts/font1.odttf")?.read_to_end(&mut embedded)?;
+    let hex: String = key.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    let guid: Vec<u8> = (0..16).map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap()).rev().collect();
+    for (i, b) in embedded.iter_mut().take(32).enumerate() {
+        *b ^= guid[i % 16];
+    }
+    assert_eq!(embedded, fs::read(font_path)?);
+    Ok(())
+}
+
+#[test]
+fn test_rtl_paragraphs_get_bidi_markup() -> Result<()> {
+    use docx_mcp::bidi::{visual_order, Direction};
+    use docx_mcp::docx_handler::DocxStyle;
+
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+
+    handler.add_paragraph(&doc_id, "שלום עולם", None)?;
+    handler.add_paragraph(&doc_id, "Plain English", None)?;
+    let rtl = DocxStyle { direction: Some("rtl".to_string()), ..Default::default() };
+    handler.add_paragraph(&doc_id, "Forced right to left", Some(rtl))?;
+    let bad = DocxStyle { direction: Some("sideways".to_string()), ..Default::default() };
+    assert!(handler.add_paragraph(&doc_id, "x", Some(bad)).is_err());
+
+    let out_path = temp_dir.path().join("rtl.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert_eq!(doc_xml.matches("<w:bidi/>").count(), 2, "{}", doc_xml);
+    assert!(doc_xml.contains("<w:rtl/>"));
+
+    let html = docx_mcp::html_export::self_contained_html(&out_path, "rtl")?;
+    assert!(html.contains("dir=\"rtl\""));
+
+    assert_eq!(visual_order("שלום 123", Direction::Rtl), "123 םולש");
+    assert_eq!(visual_order("see (שלום)", Direction::Ltr), "see (םולש)");
+    Ok(())
+}
+
+#[test]
+fn test_character_formatting_reaches_run_properties() -> Result<()> {
+    use docx_mcp::docx_handler::DocxStyle;
+
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+
+    handler.add_paragraph(&doc_id, "Marked", Some(DocxStyle {
+        highlight: Some("Yellow".to_string()),
+        strike: Some(true),
+        small_caps: Some(true),
+        character_spacing: Some(2.0),
+        ..Default::default()
+    }))?;
+    handler.add_paragraph(&doc_id, "E=mc2", Some(DocxStyle { vert_align: Some("superscript".to_string()), ..Default::default() }))?;
+    let bad = DocxStyle { highlight: Some("mauve".to_string()), ..Default::default() };
+    assert!(handler.add_paragraph(&doc_id, "x", Some(bad)).is_err());
+
+    let out_path = temp_dir.path().join("formatting.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains(r#"w:val="yellow""#), "{}", doc_xml);
+    assert!(doc_xml.contains("<w:strike"));
+    assert!(doc_xml.contains(r#"<w:vertAlign w:val="superscript""#));
+    assert!(doc_xml.contains(r#"<w:spacing w:val="40""#));
+    assert!(doc_xml.contains("<w:smallCaps/>") && !doc_xml.contains("__SMALL_CAPS__"));
+    assert!(doc_xml.find("<w:smallCaps/>") < doc_xml.find("<w:strike"), "smallCaps must precede strike");
+
+    let html = docx_mcp::html_export::self_contained_html(&out_path, "formatting")?;
+    assert!(html.contains("background-color:yellow") && html.contains("<s>") && html.contains("<sup>"));
+    Ok(())
+}
+
+#[test]
+fn test_paragraph_borders_shading_and_horizontal_rule() -> Result<()> {
+    use docx_mcp::docx_handler::{BorderSpec, DocxStyle, ParagraphBorders};
+
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+
+    let side = || Some(BorderSpec { style: "single".into(), color: Some("#1F4E79".into()), size: Some(8), space: Some(4) });
+    handler.add_paragraph(&doc_id, "Call-out", Some(DocxStyle {
+        borders: Some(ParagraphBorders { top: side(), bottom: side(), left: side(), right: side() }),
+        shading: Some("DEEAF6".into()),
+        alignment: Some("center".into()),
+        underline_style: Some("wave".into()),
+        ..Default::default()
+    }))?;
+    handler.add_horizontal_rule(&doc_id, Some("double"), None, None)?;
+    assert!(handler.add_horizontal_rule(&doc_id, Some("zigzag"), None, None).is_err());
+    let bad = DocxStyle { shading: Some("blue".into()), ..Default::default() };
+    assert!(handler.add_paragraph(&doc_id, "x", Some(bad)).is_err());
+
+    let out_path = temp_dir.path().join("boxes.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(!doc_xml.contains("__PARA_BOX__"), "{}", doc_xml);
+    assert_eq!(doc_xml.matches("<w:pBdr>").count(), 2);
+    assert!(doc_xml.contains(r#"<w:top w:val="single" w:sz="8" w:space="4" w:color="1F4E79"/>"#));
+    assert!(doc_xml.contains(r#"<w:bottom w:val="double""#));
+    assert!(doc_xml.contains(r#"w:fill="DEEAF6""#));
+    assert!(doc_xml.contains(r#"w:val="wave""#));
+    // pBdr/shd precede jc inside pPr
+    assert!(doc_xml.find("<w:pBdr>") < doc_xml.find("<w:jc"));
+
+    let html = docx_mcp::html_export::self_contained_html(&out_path, "boxes")?;
+    assert!(html.contains("background-color:#DEEAF6") && html.contains("border-bottom:"));
+    Ok(())
+}
+
+#[test]
+fn test_insert_field_and_hidden_text() -> Result<()> {
+    use docx_mcp::docx_handler::DocxStyle;
+
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+
+    handler.add_paragraph(&doc_id, "Reviewer note", Some(DocxStyle { hidden: Some(true), ..Default::default() }))?;
+    let instr = handler.insert_field(&doc_id, "date", Some("yyyy"), "body", Some("Year: "))?;
+    assert_eq!(instr, r#"DATE \@ "yyyy""#);
+    handler.set_footer(&doc_id, "Confidential - ")?;
+    handler.insert_field(&doc_id, "FILENAME", None, "footer", None)?;
+    handler.insert_field(&doc_id, "STYLEREF", Some("Heading 1"), "header", None)?;
+    assert!(handler.insert_field(&doc_id, "STYLEREF", None, "header", None).is_err());
+    assert!(handler.insert_field(&doc_id, "MERGEFIELD", None, "body", None).is_err());
+    assert!(handler.insert_field(&doc_id, "DATE", None, "margin", None).is_err());
+
+    let out_path = temp_dir.path().join("fields.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains("<w:vanish"), "{}", doc_xml);
+    assert!(!doc_xml.contains("{FIELD:"));
+    assert!(doc_xml.contains(r#"w:dirty="true""#));
+    assert!(doc_xml.contains(r#"> DATE \@ &quot;yyyy&quot; </w:instrText>"#));
+    let year = chrono::Local::now().format("%Y").to_string();
+    assert!(doc_xml.contains(&format!(">{}</w:t>", year)), "cached DATE result should be the current year");
+    assert!(doc_xml.contains("Year: "));
+
+    let footer_xml = open_zip_str(&out_path, "word/footer1.xml")?;
+    assert!(footer_xml.contains("Confidential - ") && footer_xml.contains("> FILENAME </w:instrText>"), "{}", footer_xml);
+    let header_xml = open_zip_str(&out_path, "word/header1.xml")?;
+    assert!(header_xml.contains("STYLEREF &quot;Heading 1&quot;"), "{}", header_xml);
+    Ok(())
+}
+
+#[test]
+fn test_heading_numbering_and_renumber() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_heading(&doc_id, "1. Scope", 1)?;
+    handler.add_heading(&doc_id, "Terms", 2)?;
+    handler.add_heading(&doc_id, "3. Fees", 1)?;
+
+    // Typed numbers are rewritten as one sequence while numbering is off
+    let headings = handler.renumber_headings(&doc_id)?;
+    assert_eq!(headings[2]["text"], "2. Fees");
+
+    assert_eq!(handler.enable_heading_numbering(&doc_id, "decimal")?, 3);
+    let outline = handler.get_outline(&doc_id)?;
+    let numbered: Vec<(String, String)> = outline["outline"].as_array().unwrap().iter()
+        .map(|h| (h["number"].as_str().unwrap().to_string(), h["text"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(numbered, vec![
+        ("1.".to_string(), "Scope".to_string()),
+        ("1.1.".to_string(), "Terms".to_string()),
+        ("2.".to_string(), "Fees".to_string()),
+    ]);
+
+    let out_path = temp_dir.path().join("numbered.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains(r#"<w:numId w:val="31""#), "{}", doc_xml);
+    let numbering_xml = open_zip_str(&out_path, "word/numbering.xml")?;
+    assert!(numbering_xml.contains(r#"w:val="%1.%2.""#), "{}", numbering_xml);
+
+    handler.enable_heading_numbering(&doc_id, "outline")?;
+    let headings = handler.renumber_headings(&doc_id)?;
+    assert_eq!(headings[1]["number"], "A.");
+    assert!(handler.enable_heading_numbering(&doc_id, "klingon").is_err());
+    handler.enable_heading_numbering(&doc_id, "none")?;
+    assert!(handler.get_outline(&doc_id)?["outline"][0].get("number").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_normalize_headings_case_and_prefixes() -> Result<()> {
+    use docx_mcp::text_transform::Transform;
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_heading(&doc_id, "1) introduction to the API", 1)?;
+    handler.add_heading(&doc_id, "Section 2: SCOPE OF WORK", 1)?;
+    handler.add_heading(&doc_id, "b. terms and definitions", 2)?;
+    handler.add_heading(&doc_id, "IV. fees", 1)?;
+    handler.add_heading(&doc_id, "2024 results", 2)?;
+
+    let changed = handler.normalize_headings(&doc_id, Some(Transform::TitleCase), true)?;
+    assert_eq!(changed.len(), 5);
+    let texts: Vec<String> = handler.get_outline(&doc_id)?["outline"].as_array().unwrap().iter()
+        .map(|h| h["text"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(texts, vec![
+        "1. Introduction to the API",
+        "2. Scope of Work",
+        "2.1 Terms and Definitions",
+        "3. Fees",
+        "3.1 2024 Results",
+    ]);
+    // A second pass has nothing left to fix
+    assert!(handler.normalize_headings(&doc_id, Some(Transform::TitleCase), true)?.is_empty());
+
+    let changed = handler.normalize_headings(&doc_id, Some(Transform::SentenceCase), false)?;
+    let after: Vec<&str> = changed.iter().map(|h| h["after"].as_str().unwrap()).collect();
+    assert_eq!(after, vec!["2. Scope of work", "2.1 Terms and definitions", "3.1 2024 results"]);
+
+    handler.enable_heading_numbering(&doc_id, "decimal")?;
+    handler.normalize_headings(&doc_id, None, true)?;
+    let outline = handler.get_outline(&doc_id)?;
+    assert_eq!(outline["outline"][0]["text"], "Introduction to the API");
+    Ok(())
+}
+
+#[test]
+fn test_table_of_figures_field_and_static_list() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.insert_toc(&doc_id, 1, 3, true)?;
+    handler.add_heading(&doc_id, "Results", 1)?;
+    handler.add_paragraph(&doc_id, "Table 1: Costs", None)?;
+    handler.add_paragraph(&doc_id, "Table 2: Risks", None)?;
+
+    assert_eq!(handler.add_table_of_figures(&doc_id, "figures", false)?, 0);
+    assert_eq!(handler.add_table_of_figures(&doc_id, "tables", true)?, 2);
+    assert!(handler.add_table_of_figures(&doc_id, "charts", true).is_err());
+
+    // Both lists follow the table of contents
+    let texts: Vec<String> = handler.text_elements(&doc_id)?.into_iter().map(|e| e.text).collect();
+    assert_eq!(texts[..3], ["List of Tables".to_string(), "Table 1: Costs".to_string(), "Table 2: Risks".to_string()]);
+    assert_eq!(texts[3], "List of Figures");
+
+    let out_path = temp_dir.path().join("figures.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains(r#"> TOC \h \z \c &quot;Figure&quot; </w:instrText>"#), "{}", doc_xml);
+    Ok(())
+}
+
+#[test]
+fn test_page_setup_without_section_break() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Body", None)?;
+    let margins = MarginsSpec { top: None, bottom: None, left: Some(1.25), right: Some(0.75) };
+    handler.set_page_setup(&doc_id, Some("Letter"), Some("landscape"), Some(margins), Some(0.5), Some(true))?;
+    // Later calls only change what they name
+    handler.set_page_setup(&doc_id, None, None, None, None, None)?;
+    assert!(handler.set_page_setup(&doc_id, Some("Foolscap"), None, None, None, None).is_err());
+
+    let out_path = temp_dir.path().join("page_setup.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains(r#"<w:pgSz w:w="15840" w:h="12240" w:orient="landscape"/>"#), "{}", doc_xml);
+    assert!(doc_xml.contains(r#"w:top="1440" w:right="1080" w:bottom="1440" w:left="1800""#), "{}", doc_xml);
+    assert!(doc_xml.contains(r#"w:gutter="720""#), "{}", doc_xml);
+    assert_eq!(doc_xml.matches("<w:pgSz").count(), 1);
+    let settings_xml = open_zip_str(&out_path, "word/settings.xml")?;
+    assert!(settings_xml.contains("<w:mirrorMargins/>"), "{}", settings_xml);
+
+    handler.set_page_setup(&doc_id, None, None, None, None, Some(false))?;
+    handler.save_document(&doc_id, &out_path)?;
+    assert!(!open_zip_str(&out_path, "word/settings.xml")?.contains("mirrorMargins"));
+    Ok(())
+}
+
+#[test]
+fn test_page_size_presets_and_custom_dimensions() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    let out_path = temp_dir.path().join("sizes.docx");
+    for (size, pg_sz) in [
+        ("a5", r#"<w:pgSz w:w="8391" w:h="11906"/>"#),
+        ("Tabloid", r#"<w:pgSz w:w="15840" w:h="24480"/>"#),
+        ("210x297mm", r#"<w:pgSz w:w="11906" w:h="16838"/>"#),
+        ("6 x 9 in", r#"<w:pgSz w:w="8640" w:h="12960"/>"#),
+    ] {
+        handler.set_page_setup(&doc_id, Some(size), None, None, None, None)?;
+        handler.save_document(&doc_id, &out_path)?;
+        let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+        assert!(doc_xml.contains(pg_sz), "{}: {}", size, doc_xml);
+    }
+    assert!(handler.set_page_setup(&doc_id, Some("30x40in"), None, None, None, None).is_err());
+    assert!(handler.set_page_setup(&doc_id, Some("210x297"), None, None, None, None).is_err());
+    assert!(handler.add_section_break(&doc_id, Some("Quarto"), None, None).is_err());
+    handler.add_section_break(&doc_id, Some("Legal"), None, None)?;
+    Ok(())
+}
+
+#[test]
+fn test_header_logo_and_tab_zones() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Body", None)?;
+    let logo: Vec<u8> = {
+        let img = ::image::RgbaImage::new(4, 2);
+        let mut buf = Vec::new();
+        ::image::DynamicImage::ImageRgba8(img).write_to(&mut std::io::Cursor::new(&mut buf), ::image::ImageFormat::Png)?;
+        buf
+    };
+    let layout = HeaderFooterLayout {
+        logo: Some(ImageData { data: logo, width: Some(120), height: Some(60), alt_text: None }),
+        logo_position: Some("right".into()),
+        ..Default::default()
+    };
+    handler.set_header_footer(&doc_id, "header", "Acme Ltd\tConfidential", layout)?;
+    handler.set_header_footer(&doc_id, "footer", "", HeaderFooterLayout { alignment: Some("center".into()), ..Default::default() })?;
+    // Page numbering replaces the footer text but keeps its alignment
+    handler.set_page_numbering(&doc_id, "footer", Some("Page {PAGE}"))?;
+    assert!(handler.set_header_footer(&doc_id, "header", "x", HeaderFooterLayout { logo_position: Some("top".into()), ..Default::default() }).is_err());
+
+    let out_path = temp_dir.path().join("letterhead.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let header_xml = open_zip_str(&out_path, "word/header1.xml")?;
+    // Default A4 with 1in margins: 9026 twips of text width
+    assert!(header_xml.contains(r#"w:pos="4513""#), "{}", header_xml);
+    assert!(header_xml.contains(r#"w:pos="9026""#), "{}", header_xml);
+    assert!(header_xml.contains("w:drawing"), "{}", header_xml);
+    let logo_at = header_xml.find("w:drawing").unwrap();
+    assert!(header_xml.find("Confidential").unwrap() < logo_at, "logo should sit in the right zone");
+    let footer_xml = open_zip_str(&out_path, "word/footer1.xml")?;
+    assert!(footer_xml.contains(r#"<w:jc w:val="center""#), "{}", footer_xml);
+    Ok(())
+}
+
+#[test]
+fn test_rebrand_swaps_fonts_colors_logos_and_names() -> Result<()> {
+    use docx_mcp::docx_handler::DocxStyle;
+    use docx_mcp::rebrand::BrandMapping;
+    use base64::Engine as _;
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    let png = |width: u32| -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        ::image::DynamicImage::ImageRgba8(::image::RgbaImage::new(width, 2))
+            .write_to(&mut std::io::Cursor::new(&mut buf), ::image::ImageFormat::Png)?;
+        Ok(buf)
+    };
+    handler.add_paragraph(&doc_id, "Acme makes widgets.", Some(DocxStyle {
+        font_family: Some("Calibri".to_string()),
+        color: Some("1F4E79".to_string()),
+        ..Default::default()
+    }))?;
+    handler.add_hyperlink(&doc_id, "Acme site", "https://acme.com")?;
+    handler.add_image(&doc_id, ImageData { data: png(4)?, width: Some(80), height: Some(40), alt_text: Some("Acme logo".to_string()) })?;
+    let layout = HeaderFooterLayout {
+        logo: Some(ImageData { data: png(4)?, width: Some(120), height: Some(60), alt_text: None }),
+        ..Default::default()
+    };
+    handler.set_header_footer(&doc_id, "header", "Acme Ltd\tConfidential", layout)?;
+
+    let mapping: BrandMapping = serde_json::from_value(serde_json::json!({
+        "fonts": {"calibri": "Inter"},
+        "colors": {"#1f4e79": "0B6E4F"},
+        "terms": {"Acme": "Globex"},
+        "logo_base64": base64::engine::general_purpose::STANDARD.encode(png(8)?),
+        "footer": "Globex Inc",
+    }))?;
+    let report = handler.rebrand(&doc_id, &mapping)?;
+    assert_eq!(report["fonts_changed"], 1);
+    assert_eq!(report["colors_changed"], 1);
+    assert_eq!(report["logos_replaced"], 2);
+    // Paragraph, link text and URL, alt text, header
+    assert_eq!(report["terms_replaced"], 5);
+    assert_eq!(report["footer_set"], true);
+
+    let bad: BrandMapping = serde_json::from_value(serde_json::json!({"colors": {"navy": "0B6E4F"}}))?;
+    assert!(handler.rebrand(&doc_id, &bad).is_err());
+    assert!(serde_json::from_value::<BrandMapping>(serde_json::json!({"colour": {}})).is_err());
+
+    let out_path = temp_dir.path().join("rebrand.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains(r#"w:ascii="Inter""#), "{}", doc_xml);
+    assert!(doc_xml.contains(r#"w:val="0B6E4F""#), "{}", doc_xml);
+    assert!(doc_xml.contains("Globex makes widgets.") && doc_xml.contains("Globex site"), "{}", doc_xml);
+    assert!(!doc_xml.contains("Acme"), "{}", doc_xml);
+    let rels = open_zip_str(&out_path, "word/_rels/document.xml.rels")?;
+    assert!(rels.contains("https://globex.com"), "{}", rels);
+    assert!(open_zip_str(&out_path, "word/header1.xml")?.contains("Globex Ltd"));
+    assert!(open_zip_str(&out_path, "word/footer1.xml")?.contains("Globex Inc"));
+    Ok(())
+}
+
+#[test]
+fn test_proofing_language_for_document_elements_and_tags() -> Result<()> {
+    use docx_mcp::docx_handler::{Proofing, ProofingScope};
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Colour scheme", None)?;
+    handler.add_paragraph(&doc_id, "Farbschema", None)?;
+    handler.add_paragraph(&doc_id, "let x = foo_bar();", None)?;
+
+    let english = Proofing { language: Some("en-GB".to_string()), no_proof: false };
+    assert_eq!(handler.set_proofing_language(&doc_id, ProofingScope::Document, english)?, 1);
+    let german = Proofing { language: Some("de-DE".to_string()), no_proof: false };
+    handler.set_proofing_language(&doc_id, ProofingScope::Elements { start: 1, end: 1 }, german)?;
+    // The markers shift the code paragraph to element 4
+    handler.tag_content(&doc_id, "code", 4, 4)?;
+    let code = Proofing { language: None, no_proof: true };
+    assert_eq!(handler.set_proofing_language(&doc_id, ProofingScope::Tag("code".to_string()), code)?, 1);
+    assert!(handler.set_proofing_language(&doc_id, ProofingScope::Tag("missing".to_string()), Proofing { language: None, no_proof: true }).is_err());
+    assert!(handler.set_proofing_language(&doc_id, ProofingScope::Document, Proofing { language: Some("not a tag".to_string()), no_proof: false }).is_err());
+
+    let out_path = temp_dir.path().join("proofing.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(!doc_xml.contains("__PROOFING"), "{}", doc_xml);
+    let run_of = |text: &str| -> String {
+        let at = doc_xml.find(text).unwrap();
+        let start = doc_xml[..at].rfind("<w:r>").unwrap();
+        doc_xml[start..at].to_string()
+    };
+    assert!(!run_of("Colour scheme").contains("<w:lang"));
+    assert!(run_of("Farbschema").contains(r#"<w:lang w:val="de-DE"/>"#), "{}", doc_xml);
+    assert!(run_of("let x").contains("<w:noProof/>"), "{}", doc_xml);
+    assert!(!run_of("let x").contains("<w:lang"));
+    let styles_xml = open_zip_str(&out_path, "word/styles.xml")?;
+    assert!(styles_xml.contains(r#"<w:lang w:val="en-GB"/>"#), "{}", styles_xml);
+    Ok(())
+}
+
+#[test]
+fn test_table_cell_alignment_padding_and_direction() -> Result<()> {
+    use docx_mcp::docx_handler::{CellPadding, CellStyle};
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    let cell_styles = vec![
+        CellStyle { vertical_align: Some("center".to_string()), ..Default::default() },
+        CellStyle { row: Some(0), text_direction: Some("rotate_up".to_string()), ..Default::default() },
+        CellStyle {
+            row: Some(1),
+            col: Some(1),
+            vertical_align: Some("bottom".to_string()),
+            padding: Some(CellPadding { top: Some(6.0), left: Some(4.5), ..Default::default() }),
+            ..Default::default()
+        },
+    ];
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["Q1".into(), "Q2".into()], vec!["10".into(), "20".into()]],
+        headers: None,
+        border_style: Some("single".into()),
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        cell_styles: Some(cell_styles),
+        ..Default::default()
+    })?;
+    let bad = TableData {
+        rows: vec![vec!["x".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        cell_styles: Some(vec![CellStyle { vertical_align: Some("sideways".to_string()), ..Default::default() }]),
+        ..Default::default()
+    };
+    assert!(handler.add_table(&doc_id, bad).is_err());
+
+    let out_path = temp_dir.path().join("cells.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    let cell_of = |text: &str| -> String {
+        let at = doc_xml.find(text).unwrap();
+        let start = doc_xml[..at].rfind("<w:tc>").unwrap();
+        doc_xml[start..at].to_string()
+    };
+    assert!(cell_of(">Q1<").contains(r#"<w:textDirection w:val="btLr"/>"#), "{}", doc_xml);
+    assert!(cell_of(">Q2<").contains(r#"<w:vAlign w:val="center"/>"#), "{}", doc_xml);
+    assert!(!cell_of(">10<").contains("<w:textDirection"), "{}", doc_xml);
+    let styled = cell_of(">20<");
+    assert!(styled.contains(r#"<w:vAlign w:val="bottom"/>"#), "{}", styled);
+    assert_eq!(styled.matches("<w:vAlign").count(), 1, "{}", styled);
+    assert!(styled.contains(r#"<w:tcMar><w:top w:w="120" w:type="dxa"/><w:left w:w="90" w:type="dxa"/></w:tcMar>"#), "{}", styled);
+    assert!(styled.find("<w:tcMar>").unwrap() < styled.find("<w:vAlign").unwrap());
+    Ok(())
+}
+
+#[test]
+fn test_table_repeats_header_row_and_keeps_rows_whole() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    let mut rows = vec![vec!["Item".to_string(), "Notes".to_string()]];
+    rows.extend((1..=40).map(|i| vec![format!("Row {}", i), "A long note\nover two lines".to_string()]));
+    handler.add_table(&doc_id, TableData {
+        rows,
+        headers: Some(vec!["Item".into(), "Notes".into()]),
+        border_style: Some("single".into()),
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        repeat_header_row: Some(true),
+        cant_split_rows: Some(true),
+        ..Default::default()
+    })?;
+
+    let out_path = temp_dir.path().join("long_table.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    let row_re = regex::Regex::new(r"(?s)<w:tr(?:\s[^>]*)?>.*?</w:tr>").unwrap();
+    let rows: Vec<&str> = row_re.find_iter(&doc_xml).map(|m| m.as_str()).collect();
+    assert_eq!(rows.len(), 41);
+    assert_eq!(rows[0].matches("<w:tblHeader/>").count(), 1, "{}", rows[0]);
+    assert!(rows[1..].iter().all(|r| !r.contains("tblHeader")));
+    assert!(rows.iter().all(|r| r.matches("<w:cantSplit/>").count() == 1));
+    assert!(rows[0].find("<w:trPr>").unwrap() < rows[0].find("<w:tc>").unwrap(), "{}", rows[0]);
+    Ok(())
+}
+
+#[test]
+fn test_table_caption_layout_and_percent_width() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    let table = |caption: &str, layout: Option<&str>, width_percent: Option<f32>| TableData {
+        rows: vec![vec!["Region".into(), "Sales".into()], vec!["North".into(), "120".into()]],
+        headers: None,
+        border_style: Some("single".into()),
+        col_widths: Some(vec![200, 100]),
+        merges: None,
+        cell_shading: None,
+        table_layout: layout.map(|l| l.to_string()),
+        width_percent,
+        caption: Some(caption.to_string()),
+        ..Default::default()
+    };
+    handler.add_table(&doc_id, table("Sales by region", Some("fixed"), Some(80.0)))?;
+    handler.add_paragraph(&doc_id, "Between the tables", None)?;
+    handler.add_table(&doc_id, table("Sales by month", None, None))?;
+    assert!(handler.add_table(&doc_id, table("Bad", Some("stretchy"), None)).is_err());
+    assert!(handler.add_table(&doc_id, table("Bad", None, Some(150.0))).is_err());
+    assert_eq!(handler.add_table_of_figures(&doc_id, "tables", true)?, 2);
+
+    let out_path = temp_dir.path().join("captions.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let doc_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(doc_xml.contains(r#"<w:pStyle w:val="Caption" />"#), "{}", doc_xml);
+    assert_eq!(doc_xml.matches(r"SEQ Table \* ARABIC").count(), 2, "{}", doc_xml);
+    let first = doc_xml.find("Sales by region").unwrap();
+    let second = doc_xml.find("Sales by month").unwrap();
+    let first_table = &doc_xml[first..doc_xml[first..].find("</w:tbl>").unwrap() + first];
+    assert!(first_table.contains(r#"<w:tblW w:w="4000" w:type="pct"/>"#), "{}", first_table);
+    assert!(first_table.contains(r#"<w:tblLayout w:type="fixed"/>"#), "{}", first_table);
+    assert!(first_table.contains(r#"<w:tblCaption w:val="Sales by region"/>"#), "{}", first_table);
+    // Placeholder numbers until Word updates the fields
+    assert!(doc_xml[..first].contains(r#"<w:t xml:space="preserve">1</w:t>"#), "{}", doc_xml);
+    assert!(doc_xml[first..second].contains(r#"<w:t xml:space="preserve">2</w:t>"#), "{}", doc_xml);
+    assert!(doc_xml.contains("Table 1: Sales by region"), "{}", doc_xml);
+    assert!(doc_xml.contains("Table 2: Sales by month"), "{}", doc_xml);
+    let styles_xml = open_zip_str(&out_path, "word/styles.xml")?;
+    assert!(styles_xml.contains(r#"w:styleId="Caption""#), "{}", styles_xml);
+    assert!(styles_xml.contains("<w:keepNext/>"), "{}", styles_xml);
+    Ok(())
+}
+
+#[test]
+fn test_header_table_logo_title_date() -> Result<()> {
+    use docx_mcp::docx_handler::{HeaderFooterCell, HeaderFooterTable};
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Body", None)?;
+    let logo: Vec<u8> = {
+        let img = ::image::RgbaImage::new(4, 2);
+        let mut buf = Vec::new();
+        ::image::DynamicImage::ImageRgba8(img).write_to(&mut std::io::Cursor::new(&mut buf), ::image::ImageFormat::Png)?;
+        buf
+    };
+    let cell = |text: &str, alignment: &str| HeaderFooterCell { text: text.into(), alignment: Some(alignment.into()), image: None };
+    let table = HeaderFooterTable {
+        cells: vec![
+            HeaderFooterCell { image: Some(ImageData { data: logo, width: Some(80), height: Some(40), alt_text: None }), ..Default::default() },
+            cell("Quarterly Report", "center"),
+            cell("October 2026", "right"),
+        ],
+        col_widths: Some(vec![1, 2, 1]),
+        borders: false,
+    };
+    let layout = HeaderFooterLayout { table: Some(table.clone()), ..Default::default() };
+    handler.set_header_footer(&doc_id, "header", "", layout)?;
+    let too_wide = HeaderFooterTable { col_widths: Some(vec![1, 2]), ..table };
+    assert!(handler.set_header_footer(&doc_id, "footer", "", HeaderFooterLayout { table: Some(too_wide), ..Default::default() }).is_err());
+
+    let out_path = temp_dir.path().join("header_table.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let header_xml = open_zip_str(&out_path, "word/header1.xml")?;
+    assert!(header_xml.contains(r#"<w:tblW w:w="5000" w:type="pct"/>"#), "{}", header_xml);
+    assert!(header_xml.contains(r#"<w:tblLayout w:type="fixed"/>"#), "{}", header_xml);
+    assert!(header_xml.contains(r#"<w:insideV w:val="nil"/>"#), "{}", header_xml);
+    // Default A4 with 1in margins: 9026 twips split 1:2:1
+    assert!(header_xml.contains(r#"<w:gridCol w:w="2256"/><w:gridCol w:w="4513"/><w:gridCol w:w="2256"/>"#), "{}", header_xml);
+    assert!(header_xml.contains(r#"<w:tcW w:w="4513" w:type="dxa"/>"#), "{}", header_xml);
+    let cells: Vec<&str> = header_xml.split("<w:tc>").skip(1).collect();
+    assert_eq!(cells.len(), 3);
+    assert!(cells[0].contains("w:drawing"), "{}", header_xml);
+    assert!(cells[1].contains(r#"<w:jc w:val="center" />"#) && cells[1].contains("Quarterly Report"));
+    assert!(cells[2].contains(r#"<w:jc w:val="right" />"#) && cells[2].contains("October 2026"));
+    // A header has to end with a paragraph
+    assert!(header_xml.rfind("</w:tbl>").unwrap() < header_xml.rfind("</w:p>").unwrap());
+    Ok(())
+}
+
+#[test]
+fn test_section_watermark_and_svg_picture() -> Result<()> {
+    use docx_mcp::docx_handler::{Watermark, WatermarkContent};
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+
+    // DRAFT on the appendix only, with the header text kept in both sections
+    let doc_id = handler.create_document()?;
+    handler.set_header(&doc_id, "Acme Ltd")?;
+    handler.add_paragraph(&doc_id, "Main text", None)?;
+    handler.add_section_break(&doc_id, None, None, None)?;
+    handler.add_paragraph(&doc_id, "Appendix A", None)?;
+    let draft = |section| Watermark {
+        content: WatermarkContent::Text { text: "DRAFT".into(), font: None, color: Some("FF0000".into()), opacity: None, diagonal: true },
+        section,
+    };
+    assert!(handler.add_watermark(&doc_id, draft(Some(2))).is_err());
+    handler.add_watermark(&doc_id, draft(Some(1)))?;
+    let out_path = temp_dir.path().join("appendix_draft.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let document_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(!document_xml.contains("__SECTION_BREAK__"), "{}", document_xml);
+    assert_eq!(document_xml.matches("<w:pPr><w:sectPr").count(), 1, "{}", document_xml);
+    assert_eq!(document_xml.matches(r#"<w:headerReference w:type="default""#).count(), 2, "{}", document_xml);
+    let rels = open_zip_str(&out_path, "word/_rels/document.xml.rels")?;
+    let target = |rels: &str, sect: &str| -> String {
+        let id = sect.split(r#"<w:headerReference w:type="default" r:id=""#).nth(1).unwrap().split('"').next().unwrap().to_string();
+        let rel = rels.split("<Relationship ").find(|r| r.contains(&format!(r#"Id="{}""#, id))).unwrap();
+        format!("word/{}", rel.split(r#"Target=""#).nth(1).unwrap().split('"').next().unwrap())
+    };
+    let (main, appendix) = document_xml.split_once("</w:sectPr>").unwrap();
+    let main_header = open_zip_str(&out_path, &target(&rels, main))?;
+    let appendix_header = open_zip_str(&out_path, &target(&rels, appendix))?;
+    assert!(main_header.contains("Acme Ltd") && !main_header.contains("textpath"), "{}", main_header);
+    assert!(appendix_header.contains("Acme Ltd"), "{}", appendix_header);
+    assert!(appendix_header.contains(r#"string="DRAFT""#) && appendix_header.contains("rotation:315"), "{}", appendix_header);
+    assert!(appendix_header.contains(r##"fillcolor="#FF0000""##), "{}", appendix_header);
+    assert!(handler.remove_watermark(&doc_id, Some(1))?);
+    assert!(!handler.remove_watermark(&doc_id, None)?);
+
+    // An SVG picture across the whole document, without a header of its own
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Body", None)?;
+    let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100"><text x="10" y="50">DRAFT</text></svg>"#;
+    handler.add_watermark(&doc_id, Watermark {
+        content: WatermarkContent::Image { data: svg.to_vec(), width: None, washout: true },
+        section: None,
+    })?;
+    assert!(handler.add_watermark(&doc_id, Watermark {
+        content: WatermarkContent::Image { data: b"not a picture".to_vec(), width: None, washout: true },
+        section: None,
+    }).is_err());
+    let out_path = temp_dir.path().join("svg_watermark.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let document_xml = open_zip_str(&out_path, "word/document.xml")?;
+    let rels = open_zip_str(&out_path, "word/_rels/document.xml.rels")?;
+    let header_xml = open_zip_str(&out_path, &target(&rels, &document_xml))?;
+    assert!(header_xml.contains(r#"behindDoc="1""#) && header_xml.contains("asvg:svgBlip"), "{}", header_xml);
+    // 200x100 px at 9525 EMU each
+    assert!(header_xml.contains(r#"<wp:extent cx="1905000" cy="952500"/>"#), "{}", header_xml);
+    assert!(header_xml.contains(r#"<a:lum bright="70000" contrast="-70000"/>"#), "{}", header_xml);
+    assert_eq!(open_zip_str(&out_path, "word/media/watermark1.svg")?.as_bytes(), &svg[..]);
+    assert!(open_zip_str(&out_path, "[Content_Types].xml")?.contains(r#"Extension="svg""#));
+    Ok(())
+}
+
+#[test]
+fn test_signature_block_with_esign_anchors() -> Result<()> {
+    use docx_mcp::docx_handler::{SignatureBlockOptions, SignatureParty};
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "The parties agree as set out above.", None)?;
+    let parties = vec![
+        SignatureParty { name: "Jane Chan".into(), company: Some("Acme Ltd".into()), title: Some("Director".into()), label: None },
+        SignatureParty { name: "Sam Lee".into(), ..Default::default() },
+    ];
+    let options = SignatureBlockOptions { initial_each_page: true, anchors: true, ..Default::default() };
+    assert!(handler.add_signature_block(&doc_id, &[], &options).is_err());
+    assert!(handler.add_signature_block(&doc_id, &parties, &SignatureBlockOptions { date: Some("today".into()), ..Default::default() }).is_err());
+    // Caption, gap, signature, name, title, date; then a spacer and the same minus the title
+    assert_eq!(handler.add_signature_block(&doc_id, &parties, &options)?, 12);
+
+    let out_path = temp_dir.path().join("signatures.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let document_xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(document_xml.contains("Signed for and on behalf of Acme Ltd") && document_xml.contains("Signed by Sam Lee"));
+    assert!(document_xml.contains(">Title: Director<") && document_xml.contains(">Name: Sam Lee<"));
+    let invisible = r#"<w:color w:val="FFFFFF"/><w:sz w:val="2"/><w:szCs w:val="2"/></w:rPr><w:t xml:space="preserve">"#;
+    for tag in [r"\s1\", r"\d1\", r"\s2\", r"\d2\"] {
+        assert!(document_xml.contains(&format!("{}{}</w:t>", invisible, tag)), "{} not hidden: {}", tag, document_xml);
+    }
+    // The line itself stays visible, in its own run after the anchor
+    assert!(document_xml.contains(&format!(r#"<w:r><w:t xml:space="preserve">{}</w:t></w:r>"#, "_".repeat(30))), "{}", document_xml);
+    let footer_xml = open_zip_str(&out_path, "word/footer1.xml")?;
+    assert!(footer_xml.contains("Initials: "), "{}", footer_xml);
+    assert!(footer_xml.contains(&format!(r"{}\i1\</w:t>", invisible)) && footer_xml.contains(&format!(r"{}\i2\</w:t>", invisible)), "{}", footer_xml);
+    Ok(())
+}
+
+#[test]
+fn test_insert_document_with_style_map() -> Result<()> {
+    use docx_mcp::docx_handler::{DocxStyle, StyleMap};
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let source = handler.create_document()?;
+    handler.add_heading(&source, "Appendix A", 1)?;
+    handler.add_heading(&source, "Rates", 2)?;
+    let serif = DocxStyle { font_family: Some("Times New Roman".into()), ..Default::default() };
+    handler.add_paragraph(&source, "Hourly rates apply.", Some(serif))?;
+
+    let target = handler.create_document()?;
+    handler.add_heading(&target, "Contract", 1)?;
+    let map: StyleMap = serde_json::from_value(serde_json::json!({
+        "styles": {"Heading 1": "Heading 2", "heading2": "Normal"},
+        "fonts": {"*": "Arial"}
+    }))?;
+    handler.insert_document(&target, &source, None, "after", Some(&map))?;
+
+    let outline = handler.get_outline(&target)?;
+    let headings: Vec<(String, u64)> = outline["outline"].as_array().unwrap().iter()
+        .map(|h| (h["text"].as_str().unwrap().to_string(), h["level"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(headings, vec![("Contract".to_string(), 1), ("Appendix A".to_string(), 2)]);
+
+    let out_path = temp_dir.path().join("style_map.docx");
+    handler.save_document(&target, &out_path)?;
+    let xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(xml.contains("Arial") && !xml.contains("Times New Roman"), "{}", xml);
+    assert!(xml.contains("Rates") && xml.matches("w:val=\"Heading2\"").count() == 1);
+
+    let bad: StyleMap = serde_json::from_value(serde_json::json!({"styles": {"Heading 1": "H1-Corporate"}}))?;
+    assert!(bad.validate().is_err());
+    assert!(handler.insert_document(&target, &source, None, "after", Some(&bad)).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_group_keep_together_sets_keep_next() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Before the group.", None)?;
+    handler.add_heading(&doc_id, "Quarterly figures", 2)?;
+    handler.add_paragraph(&doc_id, "The table below lists revenue.", None)?;
+    handler.add_table(&doc_id, TableData {
+        rows: vec![vec!["Q1".into(), "10".into()], vec!["Q2".into(), "12".into()]],
+        headers: None,
+        border_style: None,
+        col_widths: None,
+        merges: None,
+        cell_shading: None,
+        ..Default::default()
+    })?;
+    handler.add_paragraph(&doc_id, "After the group.", None)?;
+
+    assert_eq!(handler.group_keep_together(&doc_id, 1, 3)?, 3);
+    assert!(handler.group_keep_together(&doc_id, 3, 5).is_err());
+    assert!(handler.group_keep_together(&doc_id, 0, 42).is_err());
+
+    let out_path = temp_dir.path().join("keep.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let xml = open_zip_str(&out_path, "word/document.xml")?;
+    assert!(!xml.contains("__KEEP_TOGETHER"));
+    let p_re = regex::Regex::new(r"(?s)<w:p(?:\s[^>]*)?>.*?</w:p>")?;
+    let para = |text: &str| p_re.find_iter(&xml).map(|m| m.as_str()).find(|p| p.contains(text)).unwrap();
+    assert!(!para("Before the group.").contains("keepNext"));
+    assert!(para("Quarterly figures").contains("<w:keepNext/><w:keepLines/>"));
+    assert!(para("revenue").contains("<w:keepNext/>"));
+    assert!(para(">Q1<").contains("<w:keepNext/>"));
+    let last = para(">12<");
+    assert!(last.contains("<w:keepLines/>") && !last.contains("keepNext"));
+    assert!(!para("After the group.").contains("keep"));
+    Ok(())
+}
+
+#[test]
+fn test_deterministic_packages_are_byte_identical() -> Result<()> {
+    use docx_mcp::docx_handler::PackageOptions;
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    assert!(handler.set_package_options(PackageOptions { compression_level: 10, deterministic: true }).is_err());
+
+    let build = |name: &str| -> Result<Vec<u8>> {
+        let mut handler = DocxHandler::new_with_base_dir(temp_dir.path().join(name))?;
+        handler.set_package_options(PackageOptions { compression_level: 6, deterministic: true })?;
+        let doc_id = handler.create_document()?;
+        handler.add_heading(&doc_id, "Release notes", 1)?;
+        handler.add_paragraph(&doc_id, &"Every line of this paragraph repeats. ".repeat(50), None)?;
+        handler.set_footer(&doc_id, "Internal")?;
+        let out_path = temp_dir.path().join(format!("{}.docx", name));
+        handler.save_document(&doc_id, &out_path)?;
+        Ok(fs::read(&out_path)?)
+    };
+    let first = build("first")?;
+    assert_eq!(first, build("second")?);
+
+    // Every entry carries the fixed timestamp, so builds at different times match too
+    let mut zip = ZipArchive::new(std::io::Cursor::new(&first))?;
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        let t = entry.last_modified();
+        assert_eq!((t.year(), t.month(), t.day(), t.hour(), t.minute()), (1980, 1, 1, 0, 0), "{}", entry.name());
+    }
+    let document = zip.by_name("word/document.xml")?;
+    assert_eq!(document.compression(), zip::CompressionMethod::Deflated);
+    assert!(document.compressed_size() < document.size());
+    Ok(())
+}
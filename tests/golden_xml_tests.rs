@@ -2,7 +2,6 @@ use anyhow::Result;
 use docx_mcp::docx_handler::{DocxHandler, ImageData};
 use tempfile::TempDir;
 use std::fs;
-use std::path::PathBuf;
 use zip::ZipArchive;
 
 #[test]
@@ -70,3 +69,30 @@ fn test_golden_xml_links_images_numbering_header() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_golden_xml_paragraph_font_alignment_spacing() -> Result<()> {
+    use docx_mcp::docx_handler::DocxStyle;
+    let temp_dir = TempDir::new()?;
+    let mut handler = DocxHandler::new_with_base_dir(temp_dir.path())?;
+    let doc_id = handler.create_document()?;
+    handler.add_paragraph(&doc_id, "Styled paragraph.", Some(DocxStyle {
+        font_family: Some("Georgia".to_string()),
+        color: Some("#1F4E79".to_string()),
+        alignment: Some("center".to_string()),
+        line_spacing: Some(1.5),
+        ..Default::default()
+    }))?;
+
+    let out_path = temp_dir.path().join("styled.docx");
+    handler.save_document(&doc_id, &out_path)?;
+    let mut zip = ZipArchive::new(fs::File::open(&out_path)?)?;
+    let mut s = String::new();
+    use std::io::Read as _;
+    zip.by_name("word/document.xml")?.read_to_string(&mut s)?;
+    assert!(s.contains(r#"w:ascii="Georgia""#), "document.xml missing run font: {}", s);
+    assert!(s.contains(r#"<w:jc w:val="center""#), "document.xml missing alignment: {}", s);
+    assert!(s.contains(r#"w:line="360""#), "document.xml missing line spacing: {}", s);
+    assert!(s.contains(r#"<w:color w:val="1F4E79""#), "color should be written without '#': {}", s);
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use docx_mcp::docx_handler::{DocxHandler, DocxStyle, TableData};
+use docx_mcp::docx_handler::{DocxHandler, TableData};
 use docx_mcp::pure_converter::PureRustConverter;
 use docx_mcp::docx_tools::DocxToolsProvider;
 use docx_mcp::security::SecurityConfig;
@@ -12,7 +12,6 @@ use std::thread;
 use pretty_assertions::assert_eq;
 
 const PERFORMANCE_TIMEOUT: Duration = Duration::from_secs(30);
-const STRESS_TEST_ITERATIONS: usize = 100;
 
 #[test]
 fn test_large_document_performance() -> Result<()> {
@@ -52,6 +51,7 @@ fn test_large_document_performance() -> Result<()> {
                 col_widths: None,
                 merges: None,
                 cell_shading: None,
+                ..Default::default()
             };
             handler.add_table(&doc_id, table_data)?;
         }
@@ -135,6 +135,7 @@ fn test_concurrent_document_stress() -> Result<()> {
                     col_widths: None,
                     merges: None,
                     cell_shading: None,
+                    ..Default::default()
                 };
                 handler.add_table(&doc_id, table_data)?;
                 
@@ -223,6 +224,7 @@ fn test_memory_intensive_operations() -> Result<()> {
             col_widths: None,
             merges: None,
             cell_shading: None,
+            ..Default::default()
         };
         handler.add_table(&doc_id, table_data)?;
         
@@ -273,7 +275,7 @@ fn test_mcp_tool_performance() -> Result<()> {
     let create_resp: CallToolResponse = tokio_test::block_on(async {
         provider.call_tool("create_document", json!({})).await
     });
-    let create_result = match create_resp.content.get(0) {
+    let create_result = match create_resp.content.first() {
         Some(ToolResponseContent::Text(t)) => serde_json::from_str::<Value>(&t.text)
             .map_err(|e| e.to_string()),
         _ => Err("non-text response".to_string())
@@ -297,7 +299,7 @@ fn test_mcp_tool_performance() -> Result<()> {
         let result: CallToolResponse = tokio_test::block_on(async {
             provider.call_tool("add_paragraph", args).await
         });
-        if let Some(ToolResponseContent::Text(t)) = result.content.get(0) {
+        if let Some(ToolResponseContent::Text(t)) = result.content.first() {
             let v: Value = serde_json::from_str(&t.text).unwrap_or(json!({"success": false}));
             assert!(v.get("success").and_then(|b| b.as_bool()).unwrap_or(false), "Failed to add paragraph {}: {}", i, t.text);
         } else {
@@ -351,7 +353,7 @@ fn test_mcp_tool_performance() -> Result<()> {
     let extraction_time = start.elapsed();
     operation_times.push(("extract_text", extraction_time));
     
-    match extract_resp.content.get(0) {
+    match extract_resp.content.first() {
         Some(ToolResponseContent::Text(t)) => {
             let value: Value = serde_json::from_str(&t.text).unwrap();
             let text = value["text"].as_str().unwrap();
@@ -378,7 +380,7 @@ fn test_mcp_tool_performance() -> Result<()> {
     
     // Verify reasonable performance
     for (operation, duration) in &operation_times {
-        match operation.as_ref() {
+        match *operation {
             "create_document" => assert!(duration < &Duration::from_millis(500), "Document creation too slow"),
             "add_100_paragraphs" => assert!(duration < &Duration::from_secs(10), "Paragraph addition too slow"),
             "extract_text" => assert!(duration < &Duration::from_secs(5), "Text extraction too slow"),
@@ -518,7 +520,7 @@ fn test_error_handling_performance() -> Result<()> {
     for (operation, args) in error_operations {
         let start = Instant::now();
         
-        let result = tokio_test::block_on(async {
+        tokio_test::block_on(async {
             provider.call_tool(operation, args).await
         });
         